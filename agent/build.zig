@@ -64,6 +64,7 @@ pub fn build(b: *std.Build) !void {
         .{ .target = b.resolveTargetQuery(.{ .cpu_arch = .x86_64, .os_tag = .windows, .abi = .gnu }), .wine = true },
         .{ .target = b.resolveTargetQuery(.{ .cpu_arch = .x86_64, .os_tag = .windows, .abi = .gnu }), .ipc = .stderr },
         .{ .target = b.resolveTargetQuery(.{ .cpu_arch = .x86_64, .os_tag = .windows, .abi = .gnu }), .ipc = .winelib },
+        .{ .target = b.resolveTargetQuery(.{ .cpu_arch = .aarch64, .os_tag = .windows, .abi = .gnu }) },
     }) |cfg| {
         if (builtin.os.tag != .linux and cfg.host_lib) {
             // building host_lib is currently only supported on Linux
@@ -147,7 +148,8 @@ fn createLib(
         },
         switch (target.result.os.tag) {
             .macos => "",
-            // .windows => "gnu",
+            // Rust only has the LLVM flavour of MinGW for ARM64
+            .windows => if (target.result.cpu.arch == .aarch64) "gnullvm" else @tagName(target.result.abi),
             else => @tagName(target.result.abi),
         },
     });
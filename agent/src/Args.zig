@@ -16,7 +16,33 @@ args: std.process.ArgIterator,
 
 pub fn extract() !@This() {
     var args = try std.process.argsWithAllocator(alloc);
+    errdefer args.deinit();
 
+    const options = try parse(&args);
+
+    return .{
+        .log_to_file = options.log_to_file,
+        .logs_dir = options.logs_dir,
+        .c2s_tx = options.c2s_tx,
+        .instructions = options.instructions,
+        .dlfcn_host_path = options.dlfcn_host_path,
+        .agent_host_path = options.agent_host_path,
+
+        .args = args,
+    };
+}
+
+const Options = struct {
+    log_to_file: bool,
+    logs_dir: ?std.fs.Dir,
+    c2s_tx: ?[:0]const u8,
+    instructions: []Instruction,
+    dlfcn_host_path: ?[:0]const u8,
+    agent_host_path: ?[:0]const u8,
+};
+
+/// Parses the Manderrow arguments out of `args`, which must outlive the returned options.
+fn parse(args: anytype) !Options {
     var enabled = false;
     var instructions: std.ArrayListUnmanaged(Instruction) = .empty;
     errdefer instructions.deinit(alloc);
@@ -42,6 +68,8 @@ pub fn extract() !@This() {
                 @"--insn-prepend-arg",
                 @"--insn-append-arg",
                 @"--agent-path",
+                @"--preload-library",
+                @"--wrapper-arg",
                 @"--dlfcn-host-path",
                 @"--agent-host-path",
             }, arg) orelse return error.UnexpectedArgument;
@@ -100,7 +128,7 @@ pub fn extract() !@This() {
                 .@"--insn-append-arg" => try instructions.append(alloc, .{ .append_arg = .{
                     .arg = args.next() orelse return error.MissingOptionValue,
                 } }),
-                .@"--agent-path" => {
+                .@"--agent-path", .@"--preload-library", .@"--wrapper-arg" => {
                     // arg for wrapper. ignore.
                     if (!args.skip()) {
                         return error.MissingOptionValue;
@@ -140,8 +168,6 @@ pub fn extract() !@This() {
         .instructions = try instructions.toOwnedSlice(alloc),
         .dlfcn_host_path = dlfcn_host_path,
         .agent_host_path = agent_host_path,
-
-        .args = args,
     };
 }
 
@@ -157,3 +183,23 @@ pub const Instruction = union(enum) {
     prepend_arg: struct { arg: [:0]const u8 },
     append_arg: struct { arg: [:0]const u8 },
 };
+
+test "parse" {
+    // as emitted for a BepInEx launch that preloads a library through the wrapper
+    var args = try std.process.ArgIteratorGeneral(.{}).init(
+        alloc,
+        "game -batchmode {manderrow --agent-path /agent.so --preload-library /preload.so " ++
+            "--insn-load-library /doorstop.so --insn-set-var DOORSTOP_ENABLED=1 manderrow} " ++
+            "{manderrow --c2s-tx tx --log-to-file --enable manderrow}",
+    );
+    defer args.deinit();
+
+    const options = try parse(&args);
+    defer alloc.free(options.instructions);
+
+    try std.testing.expectEqualStrings("tx", options.c2s_tx.?);
+    try std.testing.expect(options.log_to_file);
+    try std.testing.expectEqual(2, options.instructions.len);
+    try std.testing.expectEqualStrings("/doorstop.so", options.instructions[0].load_library.path);
+    try std.testing.expectEqualStrings("DOORSTOP_ENABLED=1", options.instructions[1].set_var.kv);
+}
@@ -0,0 +1,90 @@
+//! Reports the exceptions that crash the game on Windows, with the frames of the thread that raised
+//! them rather than those of the handler.
+
+const builtin = @import("builtin");
+const std = @import("std");
+const windows = std.os.windows;
+
+const rs = @import("rs.zig");
+
+comptime {
+    if (builtin.os.tag != .windows) @compileError("Windows-only code cannot be accessed from " ++ @tagName(builtin.os.tag));
+}
+
+const EXCEPTION_CONTINUE_SEARCH: c_long = 0;
+const EXCEPTION_NONCONTINUABLE: windows.DWORD = 0x1;
+
+const GET_MODULE_HANDLE_EX_FLAG_UNCHANGED_REFCOUNT: windows.DWORD = 0x2;
+const GET_MODULE_HANDLE_EX_FLAG_FROM_ADDRESS: windows.DWORD = 0x4;
+
+extern "kernel32" fn GetModuleHandleExW(dwFlags: windows.DWORD, lpModuleName: ?*const anyopaque, phModule: *windows.HMODULE) callconv(.winapi) windows.BOOL;
+extern "kernel32" fn GetModuleFileNameW(hModule: windows.HMODULE, lpFilename: [*]u16, nSize: windows.DWORD) callconv(.winapi) windows.DWORD;
+
+/// Only the first crash is reported.
+var reported = std.atomic.Value(bool).init(false);
+
+// static, since there may be little stack left
+var addresses: [64]usize = undefined;
+var msg_buf: [1024]u8 = undefined;
+var frames_buf: [16 * 1024]u8 = undefined;
+var module_name_buf: [windows.PATH_MAX_WIDE]u16 = undefined;
+
+/// Must be called before the segfault handler is attached, so that the exception is reported
+/// before that handler aborts.
+pub fn install() void {
+    // called after the handlers registered as first, like those of the runtime that recover from
+    // exceptions
+    _ = windows.kernel32.AddVectoredExceptionHandler(0, &onException);
+}
+
+fn onException(info: *windows.EXCEPTION_POINTERS) callconv(.winapi) c_long {
+    const record = info.ExceptionRecord;
+    const fatal = switch (record.ExceptionCode) {
+        // the exceptions that the segfault handler treats as fatal
+        windows.EXCEPTION_ACCESS_VIOLATION,
+        windows.EXCEPTION_ILLEGAL_INSTRUCTION,
+        windows.EXCEPTION_STACK_OVERFLOW,
+        => true,
+        else => record.ExceptionFlags & EXCEPTION_NONCONTINUABLE != 0,
+    };
+    if (fatal and !reported.swap(true, .acq_rel)) {
+        report(record.ExceptionCode, @intFromPtr(record.ExceptionAddress), info.ContextRecord);
+    }
+    return EXCEPTION_CONTINUE_SEARCH;
+}
+
+fn report(code: windows.DWORD, address: usize, context: *const windows.CONTEXT) void {
+    const msg = std.fmt.bufPrint(&msg_buf, "Exception 0x{x:0>8} at {f}", .{ code, fmtAddress(address) }) catch "Exception";
+
+    var frames_writer = std.io.Writer.fixed(&frames_buf);
+    const n = std.debug.walkStackWindows(&addresses, context);
+    for (addresses[0..n]) |frame| {
+        frames_writer.print("{f}\n", .{fmtAddress(frame)}) catch break;
+    }
+
+    rs.sendException(code, msg, frames_writer.buffered());
+}
+
+fn fmtAddress(address: usize) FormatAddress {
+    return .{ .address = address };
+}
+
+/// Formats an address as `module+0xoffset`, which can be resolved without the process.
+const FormatAddress = struct {
+    address: usize,
+
+    pub fn format(self: FormatAddress, writer: *std.io.Writer) !void {
+        var module: windows.HMODULE = undefined;
+        if (GetModuleHandleExW(
+            GET_MODULE_HANDLE_EX_FLAG_FROM_ADDRESS | GET_MODULE_HANDLE_EX_FLAG_UNCHANGED_REFCOUNT,
+            @ptrFromInt(self.address),
+            &module,
+        ) == 0) {
+            return writer.print("0x{x}", .{self.address});
+        }
+        const len = GetModuleFileNameW(module, &module_name_buf, module_name_buf.len);
+        const path = module_name_buf[0..len];
+        const name = path[if (std.mem.lastIndexOfScalar(u16, path, '\\')) |i| i + 1 else 0..];
+        try writer.print("{f}+0x{x}", .{ std.unicode.fmtUtf16Le(name), self.address - @intFromPtr(module) });
+    }
+};
@@ -0,0 +1,142 @@
+//! Passes requests from the app on to the mod loader, which registers handlers for them through
+//! the functions exported here.
+//!
+//! The Rust side calls into this module from its receiver thread, which under Wine is a host
+//! thread that must not call into Windows. Requests are therefore only recorded there, and are
+//! passed on from a thread of the game's.
+
+const builtin = @import("builtin");
+const std = @import("std");
+
+const root = @import("root.zig");
+const logger = root.logger;
+const proto = @import("rs/proto.zig");
+const rs = @import("rs.zig");
+const util = @import("util.zig");
+
+/// Reloads the mod given as `Owner-Name`, whose changed files are given relative to its folder and
+/// separated by newlines. Neither string is nul-terminated.
+pub const ReloadHandler = fn (
+    mod_ptr: [*]const u8,
+    mod_len: usize,
+    files_ptr: [*]const u8,
+    files_len: usize,
+) callconv(.c) void;
+
+var reload_handler = std.atomic.Value(?*const ReloadHandler).init(null);
+
+/// Called by the mod loader if it can reload mods while the game is running. The handler is called
+/// from a thread of the agent's.
+export fn manderrow_set_reload_handler(handler: ?*const ReloadHandler) void {
+    reload_handler.store(handler, .release);
+}
+
+/// Makes the game exit, e.g. through the engine's own API.
+pub const ExitHandler = fn () callconv(.c) void;
+
+var exit_handler = std.atomic.Value(?*const ExitHandler).init(null);
+
+/// Called by the mod loader if it has a better way to make the game exit than closing its windows.
+/// The handler is called from a thread of the agent's.
+export fn manderrow_set_exit_handler(handler: ?*const ExitHandler) void {
+    exit_handler.store(handler, .release);
+}
+
+/// How often the recorded requests are checked for.
+const poll_interval = 50 * std.time.ns_per_ms;
+
+/// Holds the mod and then the files of the reload that hasn't been passed on yet. Longer lists of
+/// files are cut off at a line.
+var reload_buf: [64 * 1024]u8 = undefined;
+var reload_mod_len: usize = 0;
+var reload_files_len: usize = 0;
+/// Whether `reload_buf` holds a reload that hasn't been passed on yet.
+var reload_pending = std.atomic.Value(bool).init(false);
+
+fn onReload(
+    mod_ptr: [*]const u8,
+    mod_len: usize,
+    files_ptr: [*]const u8,
+    files_len: usize,
+) callconv(proto.calling_convention) void {
+    // wait for the last one to be taken, which happens within one poll_interval
+    while (reload_pending.load(.acquire)) std.atomic.spinLoopHint();
+
+    const mod = mod_ptr[0..@min(mod_len, reload_buf.len)];
+    @memcpy(reload_buf[0..mod.len], mod);
+    var files = files_ptr[0..@min(files_len, reload_buf.len - mod.len)];
+    if (files.len < files_len) {
+        files = files[0 .. std.mem.lastIndexOfScalar(u8, files, '\n') orelse 0];
+    }
+    @memcpy(reload_buf[mod.len..][0..files.len], files);
+    reload_mod_len = mod.len;
+    reload_files_len = files.len;
+    reload_pending.store(true, .release);
+}
+
+var exit_requested = std.atomic.Value(bool).init(false);
+
+fn onExit() callconv(proto.calling_convention) void {
+    exit_requested.store(true, .release);
+}
+
+/// Registers the handlers with the Rust side and starts passing requests on to the mod loader.
+pub fn start() !void {
+    rs.setReloadHandler(&onReload);
+    rs.setExitHandler(&onExit);
+
+    const thread = try std.Thread.spawn(.{}, run, .{});
+    defer thread.detach();
+    thread.setName("manderrow-handlers") catch {};
+}
+
+fn run() void {
+    while (true) {
+        std.Thread.sleep(poll_interval);
+
+        if (reload_pending.load(.acquire)) {
+            const buf = root.alloc.dupe(u8, reload_buf[0 .. reload_mod_len + reload_files_len]) catch @panic("Out of memory");
+            defer root.alloc.free(buf);
+            const mod = buf[0..reload_mod_len];
+            const files = buf[reload_mod_len..];
+            reload_pending.store(false, .release);
+
+            if (reload_handler.load(.acquire)) |handler| {
+                handler(mod.ptr, mod.len, files.ptr, files.len);
+            } else {
+                logger.info("Not reloading {s}, since the mod loader can't reload mods", .{mod});
+            }
+        }
+
+        if (exit_requested.swap(false, .acq_rel)) {
+            if (exit_handler.load(.acquire)) |handler| {
+                handler();
+            } else if (builtin.os.tag != .windows or !closeWindows()) {
+                logger.warn("Not asking the game to exit, since the mod loader can't make it exit", .{});
+            }
+        }
+    }
+}
+
+/// Asks the game's windows to close, like clicking their close buttons does, which makes most games
+/// exit. Returns whether the game has any.
+fn closeWindows() bool {
+    var closed = false;
+    _ = util.windows.user32.EnumWindows(&closeWindow, @bitCast(@intFromPtr(&closed)));
+    return closed;
+}
+
+extern "kernel32" fn GetCurrentProcessId() callconv(.winapi) std.os.windows.DWORD;
+
+fn closeWindow(hwnd: std.os.windows.HWND, lparam: std.os.windows.LPARAM) callconv(.winapi) std.os.windows.BOOL {
+    const user32 = util.windows.user32;
+    var pid: std.os.windows.DWORD = 0;
+    _ = user32.GetWindowThreadProcessId(hwnd, &pid);
+    if (pid == GetCurrentProcessId() and user32.IsWindowVisible(hwnd) != 0) {
+        if (user32.PostMessageW(hwnd, user32.WM_CLOSE, 0, 0) != 0) {
+            const closed: *bool = @ptrFromInt(@as(usize, @bitCast(lparam)));
+            closed.* = true;
+        }
+    }
+    return std.os.windows.TRUE;
+}
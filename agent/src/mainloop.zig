@@ -0,0 +1,152 @@
+//! Following the game's main loop, so that heartbeats stop when it hangs and frames are counted.
+//!
+//! On Windows, the loop is followed through the game's calls to `PeekMessage`, which games make
+//! once per frame until the message queue is empty. Only the imports of the game's own modules are
+//! hooked, and only in 64-bit processes. Elsewhere, it is up to the mod loader to call the function
+//! exported here. Either way, heartbeats are still sent without it, just not tied to the loop.
+
+const builtin = @import("builtin");
+const std = @import("std");
+const windows = std.os.windows;
+
+const rs = @import("rs.zig");
+
+/// Called by the mod loader once per frame from the game's main loop. Not needed on Windows.
+export fn manderrow_heartbeat() void {
+    rs.heartbeat();
+}
+
+/// Starts following the game's main loop, if the platform allows it.
+pub fn start() !void {
+    switch (builtin.os.tag) {
+        .windows => try startWindows(),
+        else => {},
+    }
+}
+
+fn startWindows() !void {
+    // import tables are only read in their PE32+ layout
+    if (@sizeOf(usize) != 8) return error.Unsupported32Bit;
+
+    const user32 = GetModuleHandleW(std.unicode.utf8ToUtf16LeStringLiteral("user32.dll")) orelse return error.MissingUser32;
+    inline for (hooks) |hook| {
+        hook.real = @ptrCast(GetProcAddress(user32, hook.name) orelse return error.MissingPeekMessage);
+    }
+
+    const thread = try std.Thread.spawn(.{}, run, .{});
+    defer thread.detach();
+    thread.setName("manderrow-mainloop") catch {};
+}
+
+/// How long modules are looked for after the agent starts, since the game may load the one with its
+/// main loop late.
+const hook_duration = 30;
+
+/// The modules that may run the game's main loop: the executable and, in Unity games, the engine's
+/// player. Hooking every module in the process would also count the message loops of overlays and
+/// other injected libraries.
+const game_modules = [_]?[*:0]const u16{ null, std.unicode.utf8ToUtf16LeStringLiteral("UnityPlayer.dll") };
+
+fn run() void {
+    var hooked = [_]bool{false} ** game_modules.len;
+    for (0..hook_duration) |_| {
+        var done = true;
+        for (game_modules, &hooked) |name, *is_hooked| {
+            if (is_hooked.*) continue;
+            if (GetModuleHandleW(name)) |module| {
+                hookModule(@ptrCast(module));
+                is_hooked.* = true;
+            } else {
+                done = false;
+            }
+        }
+        if (done) return;
+        std.Thread.sleep(std.time.ns_per_s);
+    }
+}
+
+const PM_REMOVE: windows.UINT = 0x1;
+const PAGE_READWRITE: windows.DWORD = 0x04;
+
+const PeekMessage = fn (
+    lpMsg: *anyopaque,
+    hWnd: ?windows.HWND,
+    wMsgFilterMin: windows.UINT,
+    wMsgFilterMax: windows.UINT,
+    wRemoveMsg: windows.UINT,
+) callconv(.winapi) windows.BOOL;
+
+extern "kernel32" fn GetModuleHandleW(lpModuleName: ?[*:0]const u16) callconv(.winapi) ?windows.HMODULE;
+extern "kernel32" fn GetProcAddress(hModule: windows.HMODULE, lpProcName: [*:0]const u8) callconv(.winapi) ?*const anyopaque;
+extern "kernel32" fn VirtualProtect(lpAddress: *anyopaque, dwSize: usize, flNewProtect: windows.DWORD, lpflOldProtect: *windows.DWORD) callconv(.winapi) windows.BOOL;
+extern "kernel32" fn GetCurrentThreadId() callconv(.winapi) windows.DWORD;
+
+fn Hook(comptime fn_name: [:0]const u8) type {
+    return struct {
+        const name = fn_name;
+        var real: ?*const PeekMessage = null;
+
+        fn hook(
+            lpMsg: *anyopaque,
+            hWnd: ?windows.HWND,
+            wMsgFilterMin: windows.UINT,
+            wMsgFilterMax: windows.UINT,
+            wRemoveMsg: windows.UINT,
+        ) callconv(.winapi) windows.BOOL {
+            const result = real.?(lpMsg, hWnd, wMsgFilterMin, wMsgFilterMax, wRemoveMsg);
+            if (result == 0 and wRemoveMsg & PM_REMOVE != 0) {
+                onQueueEmptied();
+            }
+            return result;
+        }
+    };
+}
+
+const hooks = .{ Hook("PeekMessageW"), Hook("PeekMessageA") };
+
+/// The thread that runs the main loop, which is taken to be the first that empties its message
+/// queue.
+var loop_thread = std.atomic.Value(windows.DWORD).init(0);
+
+fn onQueueEmptied() void {
+    const id = GetCurrentThreadId();
+    if (loop_thread.cmpxchgStrong(0, id, .monotonic, .monotonic)) |owner| {
+        if (owner != id) return;
+    }
+    rs.heartbeat();
+}
+
+/// Points the module's imports of `PeekMessage` at the hooks.
+fn hookModule(base: [*]u8) void {
+    if (!std.mem.eql(u8, base[0..2], "MZ")) return;
+    const nt = base + std.mem.readInt(u32, base[0x3c..][0..4], .little);
+    if (!std.mem.eql(u8, nt[0..4], "PE\x00\x00")) return;
+    const optional_header = nt + 24;
+    // only PE32+ images can be loaded into a 64-bit process, but don't trust a mangled header
+    if (std.mem.readInt(u16, optional_header[0..2], .little) != 0x20b) return;
+    const import_directory = optional_header + 112 + 8;
+    const imports_rva = std.mem.readInt(u32, import_directory[0..4], .little);
+    if (imports_rva == 0) return;
+
+    var descriptor = base + imports_rva;
+    while (true) : (descriptor += 20) {
+        const first_thunk = std.mem.readInt(u32, descriptor[16..20], .little);
+        if (first_thunk == 0) break;
+        const slots: [*]usize = @ptrCast(@alignCast(base + first_thunk));
+        var i: usize = 0;
+        while (slots[i] != 0) : (i += 1) {
+            inline for (hooks) |hook| {
+                if (slots[i] == @intFromPtr(hook.real.?)) {
+                    patch(&slots[i], @intFromPtr(&hook.hook));
+                }
+            }
+        }
+    }
+}
+
+fn patch(slot: *usize, value: usize) void {
+    var protection: windows.DWORD = undefined;
+    if (VirtualProtect(slot, @sizeOf(usize), PAGE_READWRITE, &protection) == 0) return;
+    @atomicStore(usize, slot, value, .release);
+    _ = VirtualProtect(slot, @sizeOf(usize), protection, &protection);
+}
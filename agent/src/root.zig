@@ -5,7 +5,10 @@ const dll_proxy = @import("dll_proxy");
 const build_options = @import("build_options");
 const Args = @import("Args.zig");
 const crash = @import("crash.zig");
+const exceptions = @import("exceptions.zig");
+const handlers = @import("handlers.zig");
 const ipc = @import("ipc.zig");
+const mainloop = @import("mainloop.zig");
 const paths = @import("paths.zig");
 const rs = @import("rs.zig");
 const stdio = @import("stdio.zig");
@@ -145,6 +148,10 @@ pub fn entrypoint(module: if (builtin.os.tag == .windows) std.os.windows.HMODULE
 
     logger.debug("Agent pre-started", .{});
 
+    if (builtin.os.tag == .windows and build_options.ipc_mode != .stderr) {
+        exceptions.install();
+    }
+
     std.debug.attachSegfaultHandler();
 
     logger.debug("Attached segfault handler", .{});
@@ -200,6 +207,9 @@ fn startAgent() void {
 
             startIpc(args.c2s_tx);
             logger.debug("Ran Rust-side init", .{});
+
+            handlers.start() catch |e| logger.warn("Failed to start handling requests: {}", .{e});
+            mainloop.start() catch |e| logger.warn("Failed to start following the main loop: {}", .{e});
         },
         .stderr => {},
     }
@@ -2,6 +2,7 @@ const std = @import("std");
 
 const build_options = @import("build_options");
 const ipc = @import("ipc.zig");
+const proto = @import("rs/proto.zig");
 const LogLevel = ipc.LogLevel;
 const StandardOutputChannel = ipc.StandardOutputChannel;
 
@@ -52,6 +53,11 @@ pub fn sendCrash(msg: []const u8) !void {
     impl.manderrow_agent_send_crash(msg.ptr, msg.len);
 }
 
+/// `frames` must be `module+0xoffset` lines, innermost first.
+pub fn sendException(code: u32, msg: []const u8, frames: []const u8) void {
+    impl.manderrow_agent_send_exception(code, msg.ptr, msg.len, frames.ptr, frames.len);
+}
+
 /// `line` may consist of arbitrary binary data.
 pub fn sendOutputLine(channel: StandardOutputChannel, line: []const u8) void {
     impl.manderrow_agent_send_output_line(channel, line.ptr, line.len);
@@ -74,3 +80,16 @@ pub fn sendLog(
 
     impl.manderrow_agent_send_log(level, scope.ptr, scope.len, msg.ptr, msg.len);
 }
+
+pub fn setReloadHandler(handler: ?*const proto.ReloadHandler) void {
+    impl.manderrow_agent_set_reload_handler(handler);
+}
+
+pub fn setExitHandler(handler: ?*const proto.ExitHandler) void {
+    impl.manderrow_agent_set_exit_handler(handler);
+}
+
+/// Must be called once per frame from the game's main loop.
+pub fn heartbeat() void {
+    impl.manderrow_agent_heartbeat();
+}
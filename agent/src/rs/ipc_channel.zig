@@ -28,3 +28,18 @@ pub extern fn manderrow_agent_send_log(
     msg_ptr: [*]const u8,
     msg_len: usize,
 ) callconv(proto.calling_convention) void;
+
+pub extern fn manderrow_agent_set_reload_handler(handler: ?*const proto.ReloadHandler) callconv(proto.calling_convention) void;
+
+pub extern fn manderrow_agent_set_exit_handler(handler: ?*const proto.ExitHandler) callconv(proto.calling_convention) void;
+
+/// `frames` are `module+0xoffset` lines, innermost first.
+pub extern fn manderrow_agent_send_exception(
+    code: u32,
+    msg_ptr: [*]const u8,
+    msg_len: usize,
+    frames_ptr: [*]const u8,
+    frames_len: usize,
+) callconv(proto.calling_convention) void;
+
+pub extern fn manderrow_agent_heartbeat() callconv(proto.calling_convention) void;
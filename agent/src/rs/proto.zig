@@ -31,3 +31,27 @@ pub const send_log = fn (
     msg_ptr: [*]const u8,
     msg_len: usize,
 ) callconv(calling_convention) void;
+
+/// Neither string is nul-terminated. `files` are separated by newlines.
+pub const ReloadHandler = fn (
+    mod_ptr: [*]const u8,
+    mod_len: usize,
+    files_ptr: [*]const u8,
+    files_len: usize,
+) callconv(calling_convention) void;
+
+pub const set_reload_handler = fn (handler: ?*const ReloadHandler) callconv(calling_convention) void;
+
+pub const ExitHandler = fn () callconv(calling_convention) void;
+
+pub const set_exit_handler = fn (handler: ?*const ExitHandler) callconv(calling_convention) void;
+
+pub const send_exception = fn (
+    code: u32,
+    msg_ptr: [*]const u8,
+    msg_len: usize,
+    frames_ptr: [*]const u8,
+    frames_len: usize,
+) callconv(calling_convention) void;
+
+pub const heartbeat = fn () callconv(calling_convention) void;
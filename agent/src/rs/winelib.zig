@@ -51,6 +51,29 @@ pub fn manderrow_agent_send_log(
     (send_log_fn orelse return)(level, scope_ptr, scope_len, msg_ptr, msg_len);
 }
 
+/// `frames` are `module+0xoffset` lines, innermost first.
+pub fn manderrow_agent_send_exception(
+    code: u32,
+    msg_ptr: [*]const u8,
+    msg_len: usize,
+    frames_ptr: [*]const u8,
+    frames_len: usize,
+) void {
+    (send_exception_fn orelse return)(code, msg_ptr, msg_len, frames_ptr, frames_len);
+}
+
+pub fn manderrow_agent_heartbeat() void {
+    (heartbeat_fn orelse return)();
+}
+
+pub fn manderrow_agent_set_reload_handler(handler: ?*const proto.ReloadHandler) void {
+    (set_reload_handler_fn orelse return)(handler);
+}
+
+pub fn manderrow_agent_set_exit_handler(handler: ?*const proto.ExitHandler) void {
+    (set_exit_handler_fn orelse return)(handler);
+}
+
 comptime {
     if (builtin.os.tag != .windows) {
         @compileError("winelib IPC implementation is only supported on Windows");
@@ -62,6 +85,10 @@ var send_exit_fn: ?*const proto.send_exit = null;
 var send_crash_fn: ?*const proto.send_crash = null;
 var send_output_line_fn: ?*const proto.send_output_line = null;
 var send_log_fn: ?*const proto.send_log = null;
+var send_exception_fn: ?*const proto.send_exception = null;
+var heartbeat_fn: ?*const proto.heartbeat = null;
+var set_reload_handler_fn: ?*const proto.set_reload_handler = null;
+var set_exit_handler_fn: ?*const proto.set_exit_handler = null;
 
 pub fn init(host_dlfcn_lib_path: [:0]const u16, host_lib_path: [:0]const u8) void {
     logger.debug("Loading host library", .{});
@@ -79,7 +106,7 @@ pub fn init(host_dlfcn_lib_path: [:0]const u16, host_lib_path: [:0]const u8) voi
 
     logger.debug("Loaded host library", .{});
 
-    inline for ([5][]const u8{ "init", "send_exit", "send_crash", "send_output_line", "send_log" }) |name| {
+    inline for ([_][]const u8{ "init", "send_exit", "send_crash", "send_output_line", "send_log", "send_exception", "set_reload_handler", "set_exit_handler", "heartbeat" }) |name| {
         @field(@This(), name ++ "_fn") = @ptrCast(dlfcns.dlsym(host_lib, "manderrow_agent_" ++ name) orelse {
             std.debug.panic("Unable to locate {s} in host library", .{name});
         });
@@ -28,3 +28,16 @@ pub fn SetEnvironmentVariable(key: [*:0]const u16, value: ?[*:0]const u16) void
         panicWindowsError(@src(), "SetEnvironmentVariableW");
     }
 }
+
+pub const user32 = struct {
+    const windows = std.os.windows;
+
+    pub const WM_CLOSE = 0x0010;
+
+    pub const WNDENUMPROC = *const fn (hwnd: windows.HWND, lparam: windows.LPARAM) callconv(.winapi) windows.BOOL;
+
+    pub extern "user32" fn EnumWindows(lpEnumFunc: WNDENUMPROC, lParam: windows.LPARAM) callconv(.winapi) windows.BOOL;
+    pub extern "user32" fn GetWindowThreadProcessId(hWnd: windows.HWND, lpdwProcessId: ?*windows.DWORD) callconv(.winapi) windows.DWORD;
+    pub extern "user32" fn IsWindowVisible(hWnd: windows.HWND) callconv(.winapi) windows.BOOL;
+    pub extern "user32" fn PostMessageW(hWnd: windows.HWND, Msg: windows.UINT, wParam: windows.WPARAM, lParam: windows.LPARAM) callconv(.winapi) windows.BOOL;
+};
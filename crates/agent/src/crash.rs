@@ -0,0 +1,117 @@
+//! Structured reports of crashes, with the native backtrace of the thread that crashed.
+
+use std::backtrace::Backtrace;
+
+use manderrow_ipc::{CrashReport, StackFrame};
+
+pub fn report(error: String) -> CrashReport {
+    let mut frames = parse_backtrace(&Backtrace::force_capture().to_string());
+    // the frames of reporting the crash come first, and are of no interest
+    if let Some(i) = frames.iter().rposition(|frame| {
+        frame
+            .symbol
+            .as_deref()
+            .is_some_and(|symbol| symbol.contains("manderrow_agent_send_"))
+    }) {
+        frames.drain(..=i);
+    }
+    CrashReport {
+        error,
+        frames,
+        exception_code: None,
+        modules: modules(),
+    }
+}
+
+/// Like [`report`], for an exception whose `frames` were walked from where it was raised, as
+/// `module+0xoffset` lines, innermost first.
+pub fn report_exception(error: String, exception_code: u32, frames: &str) -> CrashReport {
+    CrashReport {
+        error,
+        frames: frames
+            .lines()
+            .map(|frame| StackFrame {
+                symbol: Some(frame.to_owned()),
+                location: None,
+            })
+            .collect(),
+        exception_code: Some(exception_code),
+        modules: modules(),
+    }
+}
+
+/// Parses the frames out of a [`Backtrace`] formatted with [`std::fmt::Display`], which lists
+/// each frame's symbol on a numbered line, followed by its location on a line starting with `at`.
+fn parse_backtrace(s: &str) -> Vec<StackFrame> {
+    let mut frames = Vec::<StackFrame>::new();
+    for line in s.lines() {
+        let line = line.trim_start();
+        if let Some(location) = line.strip_prefix("at ") {
+            // only the first location of a frame is kept
+            if let Some(frame) = frames.last_mut()
+                && frame.location.is_none()
+            {
+                frame.location = Some(location.to_owned());
+            }
+        } else if let Some((index, symbol)) = line.split_once(": ")
+            && index.bytes().all(|b| b.is_ascii_digit())
+        {
+            frames.push(StackFrame {
+                symbol: (symbol != "<unknown>").then(|| symbol.to_owned()),
+                location: None,
+            });
+        }
+    }
+    frames
+}
+
+#[cfg(windows)]
+fn modules() -> Vec<String> {
+    use std::ffi::c_void;
+    use std::ptr::null_mut;
+
+    type HModule = *mut c_void;
+
+    #[link(name = "kernel32")]
+    unsafe extern "system" {
+        fn GetCurrentProcess() -> *mut c_void;
+        fn K32EnumProcessModules(
+            process: *mut c_void,
+            modules: *mut HModule,
+            cb: u32,
+            needed: *mut u32,
+        ) -> i32;
+        fn GetModuleFileNameW(module: HModule, filename: *mut u16, size: u32) -> u32;
+    }
+
+    let process = unsafe { GetCurrentProcess() };
+    let mut handles = vec![null_mut(); 256];
+    // modules may be loaded between calls, so this is repeated until the buffer is large enough
+    loop {
+        let mut needed = 0;
+        let cb = (handles.len() * size_of::<HModule>()) as u32;
+        if unsafe { K32EnumProcessModules(process, handles.as_mut_ptr(), cb, &mut needed) } == 0 {
+            return Vec::new();
+        }
+        let count = needed as usize / size_of::<HModule>();
+        if count <= handles.len() {
+            handles.truncate(count);
+            break;
+        }
+        handles.resize(count, null_mut());
+    }
+
+    let mut buf = [0u16; 1024];
+    handles
+        .into_iter()
+        .filter_map(|module| {
+            let len = unsafe { GetModuleFileNameW(module, buf.as_mut_ptr(), buf.len() as u32) };
+            (len != 0).then(|| String::from_utf16_lossy(&buf[..len as usize]))
+        })
+        .collect()
+}
+
+#[cfg(not(windows))]
+fn modules() -> Vec<String> {
+    Vec::new()
+}
@@ -49,6 +49,32 @@ extern_block! {
     pub fn manderrow_agent_crash(msg_ptr: NonNull<u8>, msg_len: usize) -> !;
 }
 
+/// Reloads the mod given as `Owner-Name`, whose changed files are given relative to its folder and
+/// separated by newlines. Neither string is nul-terminated.
+#[cfg(target_arch = "x86_64")]
+pub type ReloadHandler = unsafe extern "sysv64" fn(
+    mod_ptr: NonNull<u8>,
+    mod_len: usize,
+    files_ptr: NonNull<u8>,
+    files_len: usize,
+);
+
+#[cfg(not(target_arch = "x86_64"))]
+pub type ReloadHandler = unsafe extern "C" fn(
+    mod_ptr: NonNull<u8>,
+    mod_len: usize,
+    files_ptr: NonNull<u8>,
+    files_len: usize,
+);
+
+/// Asks the game to exit. It is called from the thread that receives messages from the app, which
+/// under Wine is a host thread, so it must not call into Windows.
+#[cfg(target_arch = "x86_64")]
+pub type ExitHandler = unsafe extern "sysv64" fn();
+
+#[cfg(not(target_arch = "x86_64"))]
+pub type ExitHandler = unsafe extern "C" fn();
+
 extern_fn!(unsafe manderrow_agent_init(
     c2s_tx_ptr: Option<NonNull<u8>>,
     c2s_tx_len: usize,
@@ -72,3 +98,17 @@ extern_fn!(unsafe manderrow_agent_send_log(
 ));
 
 extern_fn!(unsafe manderrow_agent_send_crash(msg_ptr: NonNull<u8>, msg_len: usize));
+
+extern_fn!(unsafe manderrow_agent_send_exception(
+    code: u32,
+    msg_ptr: NonNull<u8>,
+    msg_len: usize,
+    frames_ptr: NonNull<u8>,
+    frames_len: usize,
+));
+
+extern_fn!(manderrow_agent_set_reload_handler(handler: Option<ReloadHandler>));
+
+extern_fn!(manderrow_agent_set_exit_handler(handler: Option<ExitHandler>));
+
+extern_fn!(manderrow_agent_heartbeat());
@@ -0,0 +1,36 @@
+//! Telling the app that the game is still responsive, so that it can offer to kill the game when
+//! it hangs.
+//!
+//! Heartbeats are sent from a thread of their own, which only notices when the whole process is
+//! stuck. Once [`pulse`] is called from the game's main loop, which the native side of the agent
+//! does on Windows, the heartbeats stop when the main loop does, too.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use manderrow_ipc::{C2SMessage, HEARTBEAT_INTERVAL};
+
+/// Whether [`pulse`] has ever been called.
+static DRIVEN: AtomicBool = AtomicBool::new(false);
+/// Whether the main loop has run since the last heartbeat.
+static PULSED: AtomicBool = AtomicBool::new(false);
+
+pub fn pulse() {
+    PULSED.store(true, Ordering::Relaxed);
+    DRIVEN.store(true, Ordering::Relaxed);
+}
+
+/// Sends heartbeats until the connection is closed.
+pub fn run() {
+    let Some(ipc) = crate::ipc() else {
+        return;
+    };
+    loop {
+        std::thread::sleep(HEARTBEAT_INTERVAL);
+        if DRIVEN.load(Ordering::Relaxed) && !PULSED.swap(false, Ordering::Relaxed) {
+            continue;
+        }
+        if ipc.send(&C2SMessage::Heartbeat).is_err() {
+            return;
+        }
+    }
+}
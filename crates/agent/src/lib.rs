@@ -4,12 +4,16 @@
 #![feature(panic_backtrace_config)]
 #![feature(round_char_boundary)]
 
+mod crash;
 mod externs;
+mod heartbeat;
+mod metrics;
+mod stdin;
 
 use std::mem::MaybeUninit;
 use std::num::NonZeroU32;
 use std::ptr::NonNull;
-use std::sync::OnceLock;
+use std::sync::{Mutex, OnceLock, PoisonError};
 
 use manderrow_ipc::client::Ipc;
 use manderrow_ipc::ipc_channel::ipc::{IpcOneShotServer, IpcSender};
@@ -184,7 +188,118 @@ fn connect_ipc(c2s_tx: &str) -> Result<(), ConnectIpcError> {
     }
 
     IPC.set(Ipc::new(c2s_tx, s2c_rx))
-        .map_err(|_| ConnectIpcError::IpcAlreadySet)
+        .map_err(|_| ConnectIpcError::IpcAlreadySet)?;
+
+    spawn_thread("manderrow-s2c", "s2c receiver", receive_messages);
+    spawn_thread("manderrow-heartbeat", "heartbeat", heartbeat::run);
+    spawn_thread("manderrow-metrics", "metrics", metrics::run);
+
+    Ok(())
+}
+
+/// Spawns a thread named `name` that runs `f`, reporting a failure to the app as a warning, as the
+/// game can still run without it.
+fn spawn_thread(name: &str, description: &str, f: fn()) {
+    if let Err(e) = std::thread::Builder::new().name(name.to_owned()).spawn(f) {
+        if let Some(ipc) = ipc() {
+            _ = ipc.send(&C2SMessage::Log {
+                level: manderrow_ipc::LogLevel::Warning,
+                scope: "manderrow".into(),
+                message: format!("Failed to spawn {description} thread: {e}"),
+            });
+        }
+    }
+}
+
+/// Handles the messages that the app sends after the connection is established, until it closes
+/// the connection.
+fn receive_messages() {
+    let Some(ipc) = ipc() else {
+        return;
+    };
+    while let Ok(msg) = ipc.recv() {
+        match msg {
+            S2CMessage::ReloadMod { r#mod, files } => reload_mod(ipc, &r#mod, &files),
+            S2CMessage::RequestExit => request_exit(ipc),
+            S2CMessage::WriteStdin { data } => {
+                if let Err(e) = stdin::write(&data) {
+                    _ = ipc.send(&C2SMessage::Log {
+                        level: manderrow_ipc::LogLevel::Error,
+                        scope: "manderrow".into(),
+                        message: format!("Failed to write to standard input: {e}"),
+                    });
+                }
+            }
+            S2CMessage::Connect | S2CMessage::PatientResponse { .. } => {}
+        }
+    }
+}
+
+/// Set by the native side of the agent, which passes reloads on to the mod loader.
+static RELOAD_HANDLER: Mutex<Option<externs::ReloadHandler>> = Mutex::new(None);
+
+fn manderrow_agent_set_reload_handler(handler: Option<externs::ReloadHandler>) {
+    *RELOAD_HANDLER
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner) = handler;
+}
+
+fn reload_mod(ipc: &Ipc, r#mod: &str, files: &[String]) {
+    let handler = *RELOAD_HANDLER
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner);
+    match handler {
+        Some(handler) => {
+            let files = files.join("\n");
+            unsafe {
+                handler(
+                    NonNull::from(r#mod.as_bytes()).cast(),
+                    r#mod.len(),
+                    NonNull::from(files.as_bytes()).cast(),
+                    files.len(),
+                )
+            }
+        }
+        None => {
+            _ = ipc.send(&C2SMessage::Log {
+                level: manderrow_ipc::LogLevel::Info,
+                scope: "manderrow".into(),
+                message: format!("Not reloading {mod}, since the mod loader can't reload mods"),
+            });
+        }
+    }
+}
+
+/// Set by the native side of the agent, which passes the request on to the mod loader or closes the
+/// game's windows.
+static EXIT_HANDLER: Mutex<Option<externs::ExitHandler>> = Mutex::new(None);
+
+fn manderrow_agent_set_exit_handler(handler: Option<externs::ExitHandler>) {
+    *EXIT_HANDLER.lock().unwrap_or_else(PoisonError::into_inner) = handler;
+}
+
+fn request_exit(ipc: &Ipc) {
+    let handler = *EXIT_HANDLER.lock().unwrap_or_else(PoisonError::into_inner);
+    match handler {
+        Some(handler) => unsafe { handler() },
+        // signals and exiting from here would skip the engine's own shutdown, so it is left to
+        // the app to kill the game instead
+        None => {
+            _ = ipc.send(&C2SMessage::Log {
+                level: manderrow_ipc::LogLevel::Warning,
+                scope: "manderrow".into(),
+                message: "Not asking the game to exit, since nothing can make it exit".into(),
+            });
+        }
+    }
+}
+
+/// Called once per frame from the game's main loop, so that the app notices when it hangs and can
+/// show the frame rate. On Windows, the native side of the agent calls it from the game's message
+/// loop. Elsewhere, it is up to the mod loader.
+fn manderrow_agent_heartbeat() {
+    heartbeat::pulse();
+    metrics::frame();
 }
 
 fn manderrow_agent_send_exit(code: i32, with_code: bool) {
@@ -262,8 +377,27 @@ unsafe fn manderrow_agent_send_crash(msg_ptr: NonNull<u8>, msg_len: usize) {
     let msg = unsafe { NonNull::slice_from_raw_parts(msg_ptr, msg_len).as_ref() };
     let msg = std::str::from_utf8(msg).unwrap_or("<Crash messaged contained invalid UTF-8>");
     if let Some(ipc) = ipc() {
-        _ = ipc.send(&C2SMessage::Crash {
-            error: msg.to_owned(),
-        });
+        _ = ipc.send(&C2SMessage::CrashReport(crash::report(msg.to_owned())));
+    }
+}
+
+/// Like [`manderrow_agent_send_crash`], for crashes caused by an exception with the code `code`,
+/// reported from the agent's exception handler on Windows. `frames` are walked from where the
+/// exception was raised, as `module+0xoffset` lines, innermost first.
+unsafe fn manderrow_agent_send_exception(
+    code: u32,
+    msg_ptr: NonNull<u8>,
+    msg_len: usize,
+    frames_ptr: NonNull<u8>,
+    frames_len: usize,
+) {
+    let msg = unsafe { NonNull::slice_from_raw_parts(msg_ptr, msg_len).as_ref() };
+    let msg = String::from_utf8_lossy(msg).into_owned();
+    let frames = unsafe { NonNull::slice_from_raw_parts(frames_ptr, frames_len).as_ref() };
+    let frames = String::from_utf8_lossy(frames);
+    if let Some(ipc) = ipc() {
+        _ = ipc.send(&C2SMessage::CrashReport(crash::report_exception(
+            msg, code, &frames,
+        )));
     }
 }
@@ -0,0 +1,113 @@
+//! Sampling how the game performs, so that the app can show whether mods slow it down.
+//!
+//! Frames are counted from the game's main loop, which the native side of the agent follows on
+//! Windows. Elsewhere, they are only counted if the mod loader reports them.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Instant;
+
+use manderrow_ipc::{C2SMessage, METRICS_INTERVAL};
+
+static START: std::sync::OnceLock<Instant> = std::sync::OnceLock::new();
+/// Frames since the last sample.
+static FRAMES: AtomicU64 = AtomicU64::new(0);
+/// When the last frame started, in nanoseconds since [`START`].
+static LAST_FRAME: AtomicU64 = AtomicU64::new(0);
+/// The longest frame since the last sample, in nanoseconds.
+static LONGEST_FRAME: AtomicU64 = AtomicU64::new(0);
+
+pub fn frame() {
+    let now = START.get_or_init(Instant::now).elapsed().as_nanos() as u64;
+    let last = LAST_FRAME.swap(now, Ordering::Relaxed);
+    if last != 0 {
+        LONGEST_FRAME.fetch_max(now - last, Ordering::Relaxed);
+    }
+    FRAMES.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Sends samples until the connection is closed.
+pub fn run() {
+    let Some(ipc) = crate::ipc() else {
+        return;
+    };
+    loop {
+        std::thread::sleep(METRICS_INTERVAL);
+        let frames = FRAMES.swap(0, Ordering::Relaxed);
+        let longest_frame = LONGEST_FRAME.swap(0, Ordering::Relaxed);
+        // no frames either means that they aren't reported, or that the game hangs, which the
+        // heartbeats tell about
+        let (fps, frame_time_ms) = if frames == 0 {
+            (None, None)
+        } else {
+            (
+                Some(frames as f32 / METRICS_INTERVAL.as_secs_f32()),
+                Some(longest_frame as f32 / 1_000_000.0),
+            )
+        };
+        let msg = C2SMessage::Metrics {
+            fps,
+            memory_mb: resident_memory().map(|bytes| bytes as f32 / (1024.0 * 1024.0)),
+            frame_time_ms,
+        };
+        if ipc.send(&msg).is_err() {
+            return;
+        }
+    }
+}
+
+#[cfg(windows)]
+fn resident_memory() -> Option<u64> {
+    use std::ffi::c_void;
+
+    #[repr(C)]
+    #[derive(Default)]
+    #[allow(non_snake_case)]
+    struct ProcessMemoryCounters {
+        cb: u32,
+        PageFaultCount: u32,
+        PeakWorkingSetSize: usize,
+        WorkingSetSize: usize,
+        QuotaPeakPagedPoolUsage: usize,
+        QuotaPagedPoolUsage: usize,
+        QuotaPeakNonPagedPoolUsage: usize,
+        QuotaNonPagedPoolUsage: usize,
+        PagefileUsage: usize,
+        PeakPagefileUsage: usize,
+    }
+
+    #[link(name = "kernel32")]
+    unsafe extern "system" {
+        fn GetCurrentProcess() -> *mut c_void;
+        fn K32GetProcessMemoryInfo(
+            process: *mut c_void,
+            counters: *mut ProcessMemoryCounters,
+            cb: u32,
+        ) -> i32;
+    }
+
+    let mut counters = ProcessMemoryCounters {
+        cb: size_of::<ProcessMemoryCounters>() as u32,
+        ..Default::default()
+    };
+    let ok = unsafe { K32GetProcessMemoryInfo(GetCurrentProcess(), &mut counters, counters.cb) };
+    (ok != 0).then_some(counters.WorkingSetSize as u64)
+}
+
+#[cfg(target_os = "linux")]
+fn resident_memory() -> Option<u64> {
+    unsafe extern "C" {
+        fn sysconf(name: std::ffi::c_int) -> std::ffi::c_long;
+    }
+    const _SC_PAGESIZE: std::ffi::c_int = 30;
+
+    // the second field is the number of resident pages
+    let statm = std::fs::read_to_string("/proc/self/statm").ok()?;
+    let pages = statm.split_whitespace().nth(1)?.parse::<u64>().ok()?;
+    let page_size = u64::try_from(unsafe { sysconf(_SC_PAGESIZE) }).ok()?;
+    Some(pages * page_size)
+}
+
+#[cfg(not(any(windows, target_os = "linux")))]
+fn resident_memory() -> Option<u64> {
+    None
+}
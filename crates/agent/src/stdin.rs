@@ -0,0 +1,77 @@
+//! Writing to the game's standard input on behalf of the app.
+//!
+//! The first write replaces the game's standard input with a pipe, so whatever it was connected
+//! to before, usually nothing when the game is launched by the app, is no longer read.
+//!
+//! On Windows, the standard input handle and file descriptor 0 of the C runtime that the agent is
+//! linked with are replaced. Readers that already opened the previous handle keep reading from it,
+//! like .NET's `Console.In` once it has been used, or a C runtime that the game links statically.
+
+use std::io::{PipeWriter, Write as _};
+use std::sync::{Mutex, PoisonError};
+
+/// The end of the pipe that replaced the game's standard input.
+static STDIN: Mutex<Option<PipeWriter>> = Mutex::new(None);
+
+pub fn write(data: &[u8]) -> std::io::Result<()> {
+    let mut stdin = STDIN.lock().unwrap_or_else(PoisonError::into_inner);
+    let stdin = match &mut *stdin {
+        Some(t) => t,
+        None => stdin.insert(redirect()?),
+    };
+    stdin.write_all(data)?;
+    stdin.flush()
+}
+
+#[cfg(unix)]
+fn redirect() -> std::io::Result<PipeWriter> {
+    use std::ffi::c_int;
+    use std::os::fd::AsRawFd as _;
+
+    unsafe extern "C" {
+        fn dup2(oldfd: c_int, newfd: c_int) -> c_int;
+    }
+
+    let (reader, writer) = std::io::pipe()?;
+    if unsafe { dup2(reader.as_raw_fd(), 0) } == -1 {
+        return Err(std::io::Error::last_os_error());
+    }
+    // the duplicate keeps the pipe open
+    drop(reader);
+    Ok(writer)
+}
+
+#[cfg(windows)]
+fn redirect() -> std::io::Result<PipeWriter> {
+    use std::ffi::c_int;
+    use std::os::windows::io::{IntoRawHandle as _, RawHandle};
+
+    const STD_INPUT_HANDLE: u32 = -10i32 as u32;
+
+    const O_RDONLY: c_int = 0;
+
+    #[link(name = "kernel32")]
+    unsafe extern "system" {
+        fn SetStdHandle(nstdhandle: u32, hhandle: RawHandle) -> i32;
+    }
+
+    unsafe extern "C" {
+        fn _open_osfhandle(osfhandle: isize, flags: c_int) -> c_int;
+        fn _dup2(fd1: c_int, fd2: c_int) -> c_int;
+    }
+
+    let (reader, writer) = std::io::pipe()?;
+    // the handle is owned by the process from now on
+    let reader = reader.into_raw_handle();
+    if unsafe { SetStdHandle(STD_INPUT_HANDLE, reader) } == 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    // the descriptor owns the handle, so it is left open along with it
+    let fd = unsafe { _open_osfhandle(reader as isize, O_RDONLY) };
+    if fd == -1 || unsafe { _dup2(fd, 0) } == -1 {
+        return Err(std::io::Error::other(
+            "Failed to replace file descriptor 0 of the C runtime",
+        ));
+    }
+    Ok(writer)
+}
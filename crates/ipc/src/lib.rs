@@ -11,9 +11,15 @@ pub use ipc_channel;
 use std::collections::HashMap;
 use std::ffi::OsString;
 use std::num::NonZeroU32;
+use std::time::Duration;
 
 use uuid::Uuid;
 
+/// How often the agent sends [`C2SMessage::Heartbeat`] while the game is responsive.
+pub const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(2);
+/// How often the agent sends [`C2SMessage::Metrics`].
+pub const METRICS_INTERVAL: Duration = Duration::from_secs(5);
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash, rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)]
 #[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
 pub enum SafeOsString {
@@ -148,7 +154,41 @@ pub enum C2SMessage {
     Crash {
         error: String,
     },
+    /// Like [`C2SMessage::Crash`], with the details needed to find out where the game crashed.
+    CrashReport(CrashReport),
     DoctorReport(DoctorReport),
+    /// Sent every [`HEARTBEAT_INTERVAL`] while the game is responsive.
+    Heartbeat,
+    /// Sent every [`METRICS_INTERVAL`] with how the game has performed since the last one.
+    Metrics {
+        /// The average frame rate, if the game's frames are counted, which they are on Windows.
+        fps: Option<f32>,
+        /// The memory that the game has resident, if it can be measured on the platform.
+        memory_mb: Option<f32>,
+        /// The longest time between two frames, if the game's frames are counted.
+        frame_time_ms: Option<f32>,
+    },
+}
+
+#[derive(Debug, Clone, rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+pub struct CrashReport {
+    pub error: String,
+    /// The native backtrace of the thread that crashed, innermost frame first.
+    pub frames: Vec<StackFrame>,
+    /// The code of the exception that crashed the game, on Windows.
+    pub exception_code: Option<u32>,
+    /// The paths of the modules loaded into the game, on Windows.
+    pub modules: Vec<String>,
+}
+
+#[derive(Debug, Clone, rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+pub struct StackFrame {
+    /// The name of the function, if it could be resolved.
+    pub symbol: Option<String>,
+    /// The source location, as `file:line:column`, if debug info is available.
+    pub location: Option<String>,
 }
 
 #[derive(Debug, Clone, rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)]
@@ -157,4 +197,18 @@ pub enum C2SMessage {
 pub enum S2CMessage {
     Connect,
     PatientResponse { id: Uuid, choice: String },
+    /// Asks the mod loader to reload a mod whose files changed, if it can. Sent for mods that are
+    /// linked into the profile from the folder they are developed in.
+    ReloadMod {
+        /// The mod, as `Owner-Name`.
+        r#mod: String,
+        /// The files that changed, relative to the mod's folder.
+        files: Vec<String>,
+    },
+    /// Asks the game to exit on its own, giving it the chance to save and clean up, unlike killing
+    /// it.
+    RequestExit,
+    /// Writes to the game's standard input, for games like dedicated servers that read commands
+    /// from it.
+    WriteStdin { data: Vec<u8> },
 }
@@ -163,6 +163,11 @@ pub fn settings(args: TokenStream, input: TokenStream) -> TokenStream {
 
     let field_default: Vec<_> = fields.iter().map(|f| &f.default).collect();
 
+    let field_key: Vec<_> = fields
+        .iter()
+        .map(|f| cruet::to_camel_case(&f.ident.to_string()))
+        .collect();
+
     let name = input.ident;
 
     let defaulted = format_ident!("Defaulted{name}");
@@ -175,10 +180,11 @@ pub fn settings(args: TokenStream, input: TokenStream) -> TokenStream {
             json!({
                 "id": cruet::to_camel_case(&section.to_string()),
                 "settings": fields.iter()
-                    .filter(|field| field.section == *section)
-                    .map(|field| {
+                    .zip(&field_key)
+                    .filter(|(field, _)| field.section == *section)
+                    .map(|(field, key)| {
                         json!({
-                            "key": cruet::to_camel_case(&field.ident.to_string()),
+                            "key": key,
                             "input": field.input.to_string(),
                         })
                     })
@@ -223,18 +229,28 @@ pub fn settings(args: TokenStream, input: TokenStream) -> TokenStream {
                 }
             }
 
-            pub fn update(&mut self, patch: #patch) {
+            /// Applies `patch`, returning the keys of the settings that changed.
+            pub fn update(&mut self, patch: #patch) -> Vec<&'static str> {
+                let mut changed = Vec::new();
                 #(
                     if let Some(change) = patch.#field_ident {
-                        self.#field_ident = match change {
+                        let value = match change {
                             Change::Default => None,
                             Change::Override(value) => Some(value),
                         };
+                        if value != self.#field_ident {
+                            changed.push(#field_key);
+                        }
+                        self.#field_ident = value;
                     }
                 )*
+                changed
             }
         }
 
+        /// The keys of the settings, as they are named in the frontend.
+        pub const KEYS: &[&str] = &[#(#field_key),*];
+
         pub const #ui_ident: &str = #ui;
     };
 
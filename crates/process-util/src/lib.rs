@@ -6,6 +6,7 @@
 pub mod wait_group;
 
 use std::num::NonZeroU32;
+use std::time::Duration;
 
 use anyhow::Result;
 use slog::Logger;
@@ -27,6 +28,40 @@ pub struct Pid(NonZeroU32);
 #[derive(Debug, thiserror::Error)]
 pub enum WaitForExitError {}
 
+/// A sample of the resources used by a process.
+#[derive(Debug, Clone, Copy)]
+pub struct ResourceUsage {
+    /// The CPU time spent by all of the process's threads since it started.
+    pub cpu_time: Duration,
+    /// Bytes of the process's memory that are resident in RAM.
+    pub resident_memory: u64,
+}
+
+/// Returns the PE machine type of the CPU's native architecture, like `0xaa64` for ARM64, even if
+/// Manderrow itself runs under emulation. Returns `None` on other platforms than Windows.
+pub fn native_machine() -> Result<Option<u16>> {
+    #[cfg(windows)]
+    {
+        use windows::Win32::System::SystemInformation::IMAGE_FILE_MACHINE;
+        use windows::Win32::System::Threading::{GetCurrentProcess, IsWow64Process2};
+
+        let mut process_machine = IMAGE_FILE_MACHINE::default();
+        let mut native_machine = IMAGE_FILE_MACHINE::default();
+        unsafe {
+            IsWow64Process2(
+                GetCurrentProcess(),
+                &mut process_machine,
+                Some(&mut native_machine),
+            )
+        }?;
+        Ok(Some(native_machine.0))
+    }
+    #[cfg(not(windows))]
+    {
+        Ok(None)
+    }
+}
+
 impl Pid {
     pub fn from_raw(value: NonZeroU32) -> Self {
         Self(value)
@@ -121,22 +156,127 @@ impl Pid {
         }
     }
 
-    pub fn kill(self, log: &Logger, hard: bool) -> Result<()> {
+    pub fn resource_usage(self) -> Result<ResourceUsage> {
         #[cfg(windows)]
         {
             use winsafe::prelude::*;
 
-            // not supported
-            _ = hard;
+            let proc = winsafe::HPROCESS::OpenProcess(
+                winsafe::co::PROCESS::QUERY_LIMITED_INFORMATION,
+                false,
+                self.0.get(),
+            )?;
+
+            let (_, _, kernel, user) = proc.GetProcessTimes()?;
+            // FILETIMEs count 100ns intervals
+            let ticks = |t: winsafe::FILETIME| {
+                (u64::from(t.dwHighDateTime) << 32) | u64::from(t.dwLowDateTime)
+            };
+            let cpu_time = Duration::from_nanos((ticks(kernel) + ticks(user)) * 100);
+
+            let mut counters =
+                windows::Win32::System::ProcessStatus::PROCESS_MEMORY_COUNTERS::default();
+            unsafe {
+                windows::Win32::System::ProcessStatus::GetProcessMemoryInfo(
+                    windows::Win32::Foundation::HANDLE(proc.ptr()),
+                    &mut counters,
+                    size_of_val(&counters) as u32,
+                )
+            }?;
+
+            Ok(ResourceUsage {
+                cpu_time,
+                resident_memory: counters.WorkingSetSize as u64,
+            })
+        }
+        #[cfg(target_os = "macos")]
+        {
+            use anyhow::Context;
+
+            // TODO: use proc_pidinfo instead of spawning a process every time
+            let output = std::process::Command::new("ps")
+                .args([
+                    "-o",
+                    "time=,rss=",
+                    "-p",
+                    itoa::Buffer::new().format(self.0.get()),
+                ])
+                .output()?;
+            if !output.status.success() {
+                anyhow::bail!("ps exited with {}", output.status);
+            }
+            let output = std::str::from_utf8(&output.stdout)?;
+            let (time, rss) = output
+                .trim()
+                .split_once(char::is_whitespace)
+                .context("Unexpected output from ps")?;
+            // formatted like [[dd-]hh:]mm:ss.cc
+            let mut secs = 0.0;
+            for (i, part) in time.rsplit([':', '-']).enumerate() {
+                let factor = [1.0, 60.0, 60.0 * 60.0, 24.0 * 60.0 * 60.0]
+                    .get(i)
+                    .context("Unexpected time from ps")?;
+                secs += part.parse::<f64>()? * factor;
+            }
+            Ok(ResourceUsage {
+                cpu_time: Duration::from_secs_f64(secs),
+                resident_memory: rss.trim().parse::<u64>()? * 1024,
+            })
+        }
+        #[cfg(target_os = "linux")]
+        {
+            use anyhow::Context;
+
+            let stat = std::fs::read_to_string(format!("/proc/{}/stat", self.0))?;
+            // the name of the executable is parenthesized and may contain anything
+            let (_, fields) = stat.rsplit_once(')').context("Malformed stat")?;
+            // the fields after the name start at the third
+            let field = |n: usize| -> Result<u64> {
+                Ok(fields
+                    .split_ascii_whitespace()
+                    .nth(n - 3)
+                    .context("Missing field in stat")?
+                    .parse()?)
+            };
+            let (utime, stime, rss) = (field(14)?, field(15)?, field(24)?);
+            let ticks_per_sec = rustix::param::clock_ticks_per_second();
+            Ok(ResourceUsage {
+                cpu_time: Duration::from_secs_f64((utime + stime) as f64 / ticks_per_sec as f64),
+                resident_memory: rss * rustix::param::page_size() as u64,
+            })
+        }
+    }
+
+    /// Asks the process to exit, or forces it to if `hard` is true. On Windows, asking means
+    /// closing its windows, so a process without any is not affected.
+    pub fn kill(self, log: &Logger, hard: bool) -> Result<()> {
+        #[cfg(windows)]
+        {
+            use winsafe::prelude::*;
 
             let pid = self.0;
 
-            let proc =
-                winsafe::HPROCESS::OpenProcess(winsafe::co::PROCESS::TERMINATE, false, pid.get())?;
+            if hard {
+                let proc = winsafe::HPROCESS::OpenProcess(
+                    winsafe::co::PROCESS::TERMINATE,
+                    false,
+                    pid.get(),
+                )?;
 
-            slog::info!(log, "Killing process {pid:?}");
+                slog::info!(log, "Killing process {pid:?}");
+
+                proc.TerminateProcess(1)?;
+            } else {
+                slog::info!(log, "Closing the windows of process {pid:?}");
 
-            proc.TerminateProcess(1)?;
+                winsafe::EnumWindows(|hwnd: winsafe::HWND| {
+                    if hwnd.GetWindowThreadProcessId().1 == pid.get() {
+                        // SAFETY: WM_CLOSE carries no pointers
+                        _ = unsafe { hwnd.PostMessage(winsafe::msg::wm::Close {}) };
+                    }
+                    true
+                })?;
+            }
         }
         #[cfg(unix)]
         {
@@ -155,4 +295,16 @@ impl Pid {
         }
         Ok(())
     }
+
+    /// Asks the process to exit, then forces it to if it has not within `grace`.
+    pub async fn graceful_kill(self, log: &Logger, grace: Duration) -> Result<()> {
+        self.kill(log, false)?;
+        match tokio::time::timeout(grace, self.wait_for_exit(log)).await {
+            Ok(result) => result,
+            Err(_) => {
+                slog::info!(log, "Process {:?} did not exit within {grace:?}", self.0);
+                self.kill(log, true)
+            }
+        }
+    }
 }
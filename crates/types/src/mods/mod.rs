@@ -60,6 +60,10 @@ pub struct ModMetadata<'a> {
     pub owner: &'a str,
     #[serde(deserialize_with = "empty_string_as_none")]
     pub donation_link: Option<SmolStr>,
+    /// The mod's page on Thunderstore. Only recorded for installed mods, since the mod index
+    /// doesn't keep it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub package_url: Option<SmolStr>,
     pub date_created: Timestamp,
     pub is_deprecated: bool,
     pub has_nsfw_content: bool,
@@ -152,9 +156,11 @@ pub struct ModVersionRef<'a> {
     pub version_number: Version,
     #[serde(borrow)]
     pub dependencies: Vec<InternedString<'a>>,
-    #[allow(unused)]
+    /// Where Thunderstore serves the package from, which redirects to the CDN. Used as a fallback
+    /// when the package can't be found on the CDN by its usual path.
+    #[rkyv(with = StringIntern)]
     #[serde(skip_serializing)]
-    pub download_url: IgnoredAny,
+    pub download_url: &'a str,
     pub downloads: u64,
     pub date_created: Timestamp,
     #[rkyv(with = NicheInto<InternedStringNiche>)]
@@ -300,7 +306,7 @@ mod tests {
         assert_eq!(size_of::<ArchivedString>(), size_of::<usize>());
         assert_eq!(size_of::<ArchivedInternedString>(), size_of::<FixedIsize>());
         assert_eq!(size_of::<ArchivedModMetadataRef>(), 48);
-        assert_eq!(size_of::<ArchivedModVersionRef>(), 48);
+        assert_eq!(size_of::<ArchivedModVersionRef>(), 56);
     }
 
     #[test]
@@ -437,7 +443,7 @@ mod tests {
                 icon: Default::default(),
                 version_number: Version::from_str("5.4.2100").unwrap(),
                 dependencies: vec![],
-                download_url: Default::default(),
+                download_url: "",
                 downloads: 15784758,
                 date_created: "2023-01-17T16:24:38.784605Z".parse().unwrap(),
                 website_url: Some("https://github.com/BepInEx/BepInEx".into()),
@@ -446,6 +452,6 @@ mod tests {
                 file_size: 0,
             }],
         }]);
-        assert_eq!(buf.len(), 264);
+        assert_eq!(buf.len(), 272);
     }
 }
@@ -4,6 +4,9 @@ use std::env::{var, var_os};
 use std::path::{Path, PathBuf};
 use std::process::Command;
 
+/// The architectures that agents are built for when building for Windows.
+const WINDOWS_AGENT_ARCHS: &[&str] = &["x86_64", "aarch64"];
+
 #[derive(Clone, Copy)]
 struct Env<'a> {
     arch: &'a str,
@@ -54,7 +57,7 @@ fn main() {
 
     let out_dir = PathBuf::from(std::env::var_os("OUT_DIR").unwrap());
 
-    let (native_out_dir, _host_out_dir) = std::thread::scope(|scope| {
+    let (native_out_dir, _host_out_dir, cross_out_dirs) = std::thread::scope(|scope| {
         let native_out_dir = scope.spawn(|| build_agent(&agent_dir, &out_dir, env, false, false));
 
         let host_out_dir = if os == "linux" {
@@ -64,9 +67,40 @@ fn main() {
             None
         };
 
+        // Windows on ARM runs both ARM64 and emulated x64 games, so both agents are bundled
+        let cross_out_dirs = if os == "windows" {
+            WINDOWS_AGENT_ARCHS
+                .iter()
+                .filter(|&&cross_arch| cross_arch != arch)
+                .map(|&cross_arch| {
+                    let (agent_dir, out_dir) = (&agent_dir, &out_dir);
+                    (
+                        cross_arch,
+                        scope.spawn(move || {
+                            zig_build(
+                                agent_dir,
+                                &out_dir.join(format!("agent-{cross_arch}")),
+                                Env {
+                                    arch: cross_arch,
+                                    ..env
+                                },
+                                &[],
+                            )
+                        }),
+                    )
+                })
+                .collect::<Vec<_>>()
+        } else {
+            Vec::new()
+        };
+
         (
             native_out_dir.join().unwrap(),
             host_out_dir.map(|h| h.join().unwrap()),
+            cross_out_dirs
+                .into_iter()
+                .map(|(cross_arch, h)| (cross_arch, h.join().unwrap()))
+                .collect::<Vec<_>>(),
         )
     });
 
@@ -85,6 +119,18 @@ fn main() {
         &to_path,
     );
 
+    if os == "windows" {
+        // see select_agent in src/launching/mod.rs
+        for (cross_arch, cross_out_dir) in std::iter::once((arch, &native_out_dir))
+            .chain(cross_out_dirs.iter().map(|(a, d)| (*a, d)))
+        {
+            copy(
+                &cross_out_dir.join("lib").join("manderrow_agent.dll"),
+                &to_path.with_file_name(format!("libmanderrow_agent-{cross_arch}")),
+            );
+        }
+    }
+
     tauri_build::build()
 }
 
@@ -1,7 +1,9 @@
 use std::ffi::OsString;
+use std::path::PathBuf;
 
 use anyhow::Context;
 use tauri::{AppHandle, Manager, Window};
+use tauri_plugin_opener::OpenerExt as _;
 
 use crate::CommandError;
 
@@ -74,3 +76,35 @@ pub async fn relaunch(app: AppHandle) -> Result<(), CommandError> {
     ];
     tauri::process::restart(&env)
 }
+
+/// Shows `path` in the platform's file manager. Folders are opened, while files are selected in
+/// the folder that contains them, so that nothing is ever run by this.
+#[tauri::command]
+pub async fn open_in_file_manager(app: AppHandle, path: PathBuf) -> Result<(), CommandError> {
+    let path = crate::profiles::paths::canonicalize(&path).await?;
+    let is_dir = tokio::fs::metadata(&path)
+        .await
+        .with_context(|| format!("Failed to read metadata of {path:?}"))?
+        .is_dir();
+    let opener = app.opener();
+    if is_dir {
+        opener
+            .open_path(path.to_string_lossy(), None::<&str>)
+            .with_context(|| format!("Failed to open {path:?}"))?;
+    } else if let Err(e) = opener.reveal_item_in_dir(&path) {
+        // revealing relies on the file manager supporting it on Linux, so fall back to opening
+        // the containing folder instead
+        if cfg!(target_os = "linux") {
+            slog::debug!(slog_scope::logger(), "Failed to reveal {path:?}: {e}");
+            let parent = path.parent().context("File has no parent folder")?;
+            opener
+                .open_path(parent.to_string_lossy(), None::<&str>)
+                .with_context(|| format!("Failed to open {parent:?}"))?;
+        } else {
+            return Err(anyhow::Error::from(e)
+                .context(format!("Failed to reveal {path:?}"))
+                .into());
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,34 @@
+use tauri::AppHandle;
+
+use crate::CommandError;
+
+use super::{Account, Provider};
+
+#[tauri::command]
+pub async fn thunderstore_get_account() -> Result<Option<Account>, CommandError> {
+    super::account().await.map_err(Into::into)
+}
+
+#[tauri::command]
+pub async fn thunderstore_login(
+    app: AppHandle,
+    provider: Provider,
+) -> Result<Account, CommandError> {
+    super::login(&app, &slog_scope::logger(), provider)
+        .await
+        .map_err(Into::into)
+}
+
+#[tauri::command]
+pub async fn thunderstore_login_with_token(session_token: String) -> Result<Account, CommandError> {
+    super::login_with_token(&slog_scope::logger(), session_token)
+        .await
+        .map_err(Into::into)
+}
+
+#[tauri::command]
+pub async fn thunderstore_logout() -> Result<(), CommandError> {
+    super::logout(&slog_scope::logger())
+        .await
+        .map_err(Into::into)
+}
@@ -0,0 +1,329 @@
+//! Thunderstore account sessions.
+//!
+//! Thunderstore delegates authentication to third-party OAuth providers. The user is sent to the
+//! provider in their browser, which redirects back to a short-lived server on the loopback
+//! interface with an authorization code. The code is exchanged with Thunderstore for a session
+//! token, which is kept in [`crate::secrets`] and attached to requests that need an account.
+
+pub mod commands;
+
+use std::time::Duration;
+
+use anyhow::{anyhow, bail, Context as _, Result};
+use slog::{debug, info, warn};
+use tauri::AppHandle;
+use tauri_plugin_opener::OpenerExt as _;
+use tokio::io::{AsyncBufReadExt as _, AsyncWriteExt as _, BufReader};
+use tokio::net::TcpListener;
+use tokio::sync::Mutex;
+
+use crate::secrets::{self, Secret};
+
+/// How long to wait for the user to finish logging in before giving up.
+const LOGIN_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+
+/// The current session. The outer `None` means it has not been loaded from storage yet.
+static SESSION: Mutex<Option<Option<Session>>> = Mutex::const_new(None);
+
+#[derive(Debug, Clone, Copy, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Provider {
+    Github,
+    Discord,
+}
+
+impl Provider {
+    const fn as_str(self) -> &'static str {
+        match self {
+            Self::Github => "github",
+            Self::Discord => "discord",
+        }
+    }
+
+    fn client_id(self) -> Option<&'static str> {
+        match self {
+            Self::Github => option_env!("MANDERROW_THUNDERSTORE_GITHUB_CLIENT_ID"),
+            Self::Discord => option_env!("MANDERROW_THUNDERSTORE_DISCORD_CLIENT_ID"),
+        }
+    }
+
+    fn authorize_url(self, client_id: &str, redirect_uri: &str, state: &str) -> url::Url {
+        let (base, scope) = match self {
+            Self::Github => (
+                "https://github.com/login/oauth/authorize",
+                "read:user user:email",
+            ),
+            Self::Discord => ("https://discord.com/oauth2/authorize", "identify email"),
+        };
+        url::Url::parse_with_params(
+            base,
+            [
+                ("client_id", client_id),
+                ("redirect_uri", redirect_uri),
+                ("response_type", "code"),
+                ("scope", scope),
+                ("state", state),
+            ],
+        )
+        .unwrap()
+    }
+}
+
+#[derive(Clone, serde::Deserialize, serde::Serialize)]
+struct Session {
+    session_id: String,
+    username: String,
+}
+
+impl std::fmt::Debug for Session {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Session")
+            .field("username", &self.username)
+            .finish_non_exhaustive()
+    }
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct Account {
+    pub username: String,
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("Not logged in to Thunderstore")]
+pub struct NotLoggedIn;
+
+async fn read_stored() -> Result<Option<Session>> {
+    Ok(match secrets::get(Secret::ThunderstoreSession).await? {
+        Some(s) => Some(serde_json::from_str(&s)?),
+        None => None,
+    })
+}
+
+async fn write_stored(session: Option<&Session>) -> Result<()> {
+    match session {
+        Some(session) => {
+            secrets::set(Secret::ThunderstoreSession, serde_json::to_string(session)?).await
+        }
+        None => secrets::delete(Secret::ThunderstoreSession).await,
+    }
+}
+
+async fn load(session: &mut Option<Option<Session>>) -> Result<Option<&Session>> {
+    if session.is_none() {
+        *session = Some(
+            read_stored()
+                .await
+                .context("Failed to read Thunderstore session")?,
+        );
+    }
+    Ok(session.as_ref().unwrap().as_ref())
+}
+
+/// Returns the session token to attach to requests made on behalf of the user.
+pub async fn session_token() -> Result<String> {
+    let mut session = SESSION.lock().await;
+    match load(&mut session).await? {
+        Some(s) => Ok(s.session_id.clone()),
+        None => Err(NotLoggedIn.into()),
+    }
+}
+
+pub async fn account() -> Result<Option<Account>> {
+    let mut session = SESSION.lock().await;
+    Ok(load(&mut session).await?.map(|s| Account {
+        username: s.username.clone(),
+    }))
+}
+
+async fn store(session: Option<Session>) -> Result<()> {
+    write_stored(session.as_ref())
+        .await
+        .context("Failed to store Thunderstore session")?;
+    *SESSION.lock().await = Some(session);
+    Ok(())
+}
+
+#[derive(serde::Deserialize)]
+struct CurrentUser {
+    username: String,
+}
+
+/// Checks that `session_id` is valid, returning the name of the user it belongs to.
+async fn validate(session_id: &str) -> Result<String> {
+    let bytes = crate::util::http::client()
+        .get("https://thunderstore.io/api/experimental/current-user/")
+        .header(
+            reqwest::header::AUTHORIZATION,
+            format!("Session {session_id}"),
+        )
+        .send()
+        .await?
+        .error_for_status()?
+        .bytes()
+        .await?;
+    Ok(serde_json::from_slice::<CurrentUser>(&bytes)?.username)
+}
+
+/// Logs in with an existing session token, such as one copied from the Thunderstore website.
+pub async fn login_with_token(log: &slog::Logger, session_id: String) -> Result<Account> {
+    let username = validate(&session_id)
+        .await
+        .context("Thunderstore did not accept the session token")?;
+    info!(log, "Logged in to Thunderstore as {username:?}");
+    store(Some(Session {
+        session_id,
+        username: username.clone(),
+    }))
+    .await?;
+    Ok(Account { username })
+}
+
+/// Waits for the OAuth provider to redirect the browser back to `listener`, returning the
+/// authorization code.
+async fn accept_callback(
+    log: &slog::Logger,
+    listener: &TcpListener,
+    state: &str,
+) -> Result<String> {
+    loop {
+        let (stream, addr) = listener.accept().await?;
+        debug!(log, "Accepted OAuth callback connection from {addr}");
+        let mut stream = BufReader::new(stream);
+
+        let mut request_line = String::new();
+        stream.read_line(&mut request_line).await?;
+        let Some(target) = request_line
+            .strip_prefix("GET ")
+            .and_then(|s| s.split(' ').next())
+        else {
+            continue;
+        };
+        let url = url::Url::parse("http://127.0.0.1")?.join(target)?;
+        if url.path() != "/callback" {
+            stream
+                .get_mut()
+                .write_all(b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\n\r\n")
+                .await?;
+            continue;
+        }
+
+        let mut code = None;
+        let mut error = None;
+        let mut state_matches = false;
+        for (k, v) in url.query_pairs() {
+            match &*k {
+                "code" => code = Some(v.into_owned()),
+                "error" => error = Some(v.into_owned()),
+                "state" => state_matches = v == state,
+                _ => {}
+            }
+        }
+
+        let body = if code.is_some() && state_matches {
+            "Logged in to Thunderstore. You may close this tab and return to Manderrow."
+        } else {
+            "Login failed. You may close this tab and return to Manderrow."
+        };
+        stream
+            .get_mut()
+            .write_all(
+                format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: text/plain; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                    body.len()
+                )
+                .as_bytes(),
+            )
+            .await?;
+
+        if !state_matches {
+            warn!(log, "Ignoring OAuth callback with mismatched state");
+            continue;
+        }
+        if let Some(error) = error {
+            bail!("OAuth provider returned an error: {error}");
+        }
+        return code.ok_or_else(|| anyhow!("OAuth callback is missing the authorization code"));
+    }
+}
+
+#[derive(serde::Deserialize)]
+struct AuthCompleteResponse {
+    session_id: String,
+    username: String,
+}
+
+/// Logs in through `provider` in the user's browser.
+pub async fn login(
+    app: &AppHandle,
+    log: &slog::Logger,
+    provider: Provider,
+) -> Result<Account, crate::Error> {
+    let client_id = provider.client_id().ok_or_else(|| {
+        anyhow!(
+            "This build of Manderrow does not support logging in with {}",
+            provider.as_str()
+        )
+    })?;
+
+    let listener = TcpListener::bind(("127.0.0.1", 0))
+        .await
+        .context("Failed to start OAuth callback server")?;
+    let redirect_uri = format!(
+        "http://127.0.0.1:{}/callback",
+        listener.local_addr()?.port()
+    );
+    let state = std::iter::repeat_with(fastrand::alphanumeric)
+        .take(32)
+        .collect::<String>();
+
+    let authorize_url = provider.authorize_url(client_id, &redirect_uri, &state);
+    info!(log, "Opening {} login page", provider.as_str());
+    app.opener()
+        .open_url(authorize_url.as_str(), None::<&str>)
+        .context("Failed to open login page in browser")?;
+
+    let code =
+        match tokio::time::timeout(LOGIN_TIMEOUT, accept_callback(log, &listener, &state)).await {
+            Ok(r) => r?,
+            Err(_) => return Err(crate::Error::Aborted),
+        };
+    drop(listener);
+
+    let bytes = crate::util::http::client()
+        .post(format!(
+            "https://thunderstore.io/api/experimental/auth/complete/{}/",
+            provider.as_str()
+        ))
+        .header(reqwest::header::CONTENT_TYPE, "application/json")
+        .body(
+            serde_json::to_vec(&serde_json::json!({
+                "code": code,
+                "redirect_uri": redirect_uri,
+            }))
+            .map_err(anyhow::Error::from)?,
+        )
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .context("Failed to exchange authorization code with Thunderstore")?
+        .bytes()
+        .await
+        .context("Failed to exchange authorization code with Thunderstore")?;
+    let resp = serde_json::from_slice::<AuthCompleteResponse>(&bytes)
+        .context("Failed to decode login response from Thunderstore")?;
+
+    info!(log, "Logged in to Thunderstore as {:?}", resp.username);
+    store(Some(Session {
+        session_id: resp.session_id,
+        username: resp.username.clone(),
+    }))
+    .await?;
+    Ok(Account {
+        username: resp.username,
+    })
+}
+
+pub async fn logout(log: &slog::Logger) -> Result<()> {
+    info!(log, "Logging out of Thunderstore");
+    store(None).await
+}
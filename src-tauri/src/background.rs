@@ -0,0 +1,43 @@
+//! A global switch that suspends background network activity, such as scheduled refreshes.
+//!
+//! Anything that runs on its own rather than in response to the user must wait on
+//! [`wait_until_resumed`] before touching the network, or run inside [`run_in_background`] so that
+//! the download queue waits for it. User-initiated actions are never paused.
+
+use std::sync::LazyLock;
+
+use tokio::sync::watch;
+
+static PAUSED: LazyLock<watch::Sender<bool>> = LazyLock::new(|| watch::Sender::new(false));
+
+tokio::task_local! {
+    /// Set while running background activity. See [`run_in_background`].
+    static IN_BACKGROUND: ();
+}
+
+pub fn set_paused(paused: bool) {
+    PAUSED.send_if_modified(|current| std::mem::replace(current, paused) != paused);
+}
+
+pub fn is_paused() -> bool {
+    *PAUSED.borrow()
+}
+
+/// Returns immediately if background activity is not paused. Otherwise, waits until it is
+/// resumed.
+pub async fn wait_until_resumed() {
+    let mut rx = PAUSED.subscribe();
+    // the sender is static and never dropped
+    _ = rx.wait_for(|paused| !*paused).await;
+}
+
+/// Runs `fut` as background activity, so that downloads it starts through the download queue wait
+/// while background activity is paused.
+pub async fn run_in_background<T>(fut: impl Future<Output = T>) -> T {
+    IN_BACKGROUND.scope((), fut).await
+}
+
+/// Returns `true` if called from inside [`run_in_background`].
+pub fn is_in_background() -> bool {
+    IN_BACKGROUND.try_with(|_| ()).is_ok()
+}
@@ -0,0 +1,33 @@
+use std::path::PathBuf;
+
+use tauri::AppHandle;
+
+use crate::{tasks, CommandError};
+
+#[tauri::command]
+pub async fn backup_all_data(
+    app: AppHandle,
+    target_path: PathBuf,
+    task_id: tasks::Id,
+) -> Result<(), CommandError> {
+    let log = slog_scope::logger();
+
+    super::backup_all_data(Some(&app), &log, &target_path, Some(task_id)).await?;
+
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn restore_all_data(
+    app: AppHandle,
+    archive: PathBuf,
+    task_id: tasks::Id,
+) -> Result<(), CommandError> {
+    crate::profiles::read_only::ensure_writable()?;
+
+    let log = slog_scope::logger();
+
+    super::restore_all_data(Some(&app), &log, &archive, Some(task_id)).await?;
+
+    Ok(())
+}
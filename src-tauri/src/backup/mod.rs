@@ -0,0 +1,249 @@
+//! Backing up all of Manderrow's data to a single archive and restoring it, e.g. when moving to
+//! another machine.
+//!
+//! A backup holds the local data directory, which contains the profiles among other things, and
+//! the config directory, which contains the settings. Anything that can be downloaded again, like
+//! the cache, is left out, as are logs and the fallback secrets file, so credentials never end up
+//! in a backup. So are the receipts of the files written to game directories, along with the
+//! backups of the originals, as they only describe the game directories of this machine.
+
+pub mod commands;
+
+use std::collections::HashMap;
+use std::ffi::OsStr;
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::path::{Component, Path, PathBuf};
+
+use anyhow::{bail, ensure, Context as _, Result};
+use manderrow_paths::{cache_dir, config_dir, local_data_dir, logs_dir};
+use slog::{debug, warn};
+use tauri::AppHandle;
+
+use crate::tasks::{self, TaskBuilder};
+
+const MANIFEST_FILE_NAME: &str = "manderrow_backup.json";
+
+/// Bump this when restoring a backup made by an older version would need special handling.
+const FORMAT_VERSION: u32 = 1;
+
+#[derive(serde::Deserialize, serde::Serialize)]
+struct BackupManifest {
+    format_version: u32,
+    /// The version of Manderrow that made the backup.
+    app_version: String,
+}
+
+/// The directories that are backed up, by the name their contents are stored under in the
+/// archive.
+fn roots() -> [(&'static str, &'static Path); 2] {
+    [("data", local_data_dir()), ("config", config_dir())]
+}
+
+fn is_excluded(path: &Path) -> bool {
+    path.starts_with(cache_dir())
+        || path.starts_with(logs_dir())
+        || path.starts_with(crate::launching::receipts_dir())
+        || crate::secrets::fallback_files().contains(&path)
+}
+
+/// Lists the files to back up, with the names to store them under in the archive.
+fn collect_files() -> Result<Vec<(String, PathBuf)>> {
+    let mut files = Vec::new();
+    let mut walked = Vec::<&Path>::new();
+    for (prefix, root) in roots() {
+        // on some platforms, like macOS, the config directory is the data directory
+        if walked.iter().any(|r| root.starts_with(r)) || !root.exists() {
+            continue;
+        }
+        walked.push(root);
+        let iter = walkdir::WalkDir::new(root)
+            .sort_by_file_name()
+            .into_iter()
+            .filter_entry(|e| !is_excluded(e.path()));
+        for e in iter {
+            let e = e?;
+            if !e.file_type().is_file() {
+                continue;
+            }
+            let rel_path = e.path().strip_prefix(root)?;
+            // zip paths always use forward slashes
+            let mut name = prefix.to_owned();
+            for c in rel_path.components() {
+                name.push('/');
+                name.push_str(
+                    c.as_os_str()
+                        .to_str()
+                        .with_context(|| format!("Non-UTF-8 path {:?}", e.path()))?,
+                );
+            }
+            files.push((name, e.into_path()));
+        }
+    }
+    Ok(files)
+}
+
+/// Writes a backup of all data to `target`. The archive is only moved into place once it is
+/// complete, so a failed backup never leaves a partial archive behind.
+pub async fn backup_all_data(
+    app: Option<&AppHandle>,
+    log: &slog::Logger,
+    target: &Path,
+    task_id: Option<tasks::Id>,
+) -> Result<()> {
+    TaskBuilder::with_id(
+        task_id.unwrap_or_else(tasks::allocate_task),
+        "Back up Manderrow data",
+    )
+    .run_with_handle(app, |handle| async move {
+        tokio::task::block_in_place(|| {
+            let files = collect_files().context("Failed to list files to back up")?;
+            let total = files.len() as u64;
+            debug!(log, "Backing up {total} files to {target:?}");
+
+            let parent = target
+                .parent()
+                .context("Target must not be a filesystem root")?;
+            let temp_file = tempfile::NamedTempFile::new_in(parent)?;
+            let options = zip::write::SimpleFileOptions::default().large_file(true);
+            let mut zip = zip::ZipWriter::new(BufWriter::new(temp_file));
+
+            zip.start_file(MANIFEST_FILE_NAME, options)?;
+            serde_json::to_writer(
+                &mut zip,
+                &BackupManifest {
+                    format_version: FORMAT_VERSION,
+                    app_version: env!("CARGO_PKG_VERSION").to_owned(),
+                },
+            )?;
+
+            for (i, (name, path)) in files.iter().enumerate() {
+                zip.start_file(name, options)?;
+                let mut file =
+                    File::open(path).with_context(|| format!("Failed to read {path:?}"))?;
+                std::io::copy(&mut file, &mut zip)?;
+                if let Some(app) = app {
+                    handle.send_progress_manually(app, i as u64 + 1, total)?;
+                }
+            }
+
+            let temp_file = zip.finish()?.into_inner().map_err(|e| e.into_error())?;
+            temp_file
+                .persist(target)
+                .with_context(|| format!("Failed to move backup to {target:?}"))?;
+            Ok::<_, anyhow::Error>(())
+        })?;
+        Ok::<_, anyhow::Error>((None, ()))
+    })
+    .await
+    .map_err(Into::into)
+}
+
+/// Returns the directory that the file stored under `name` in a backup belongs in, and the path of
+/// the file relative to it.
+fn restore_path(name: &Path) -> Option<(&'static Path, &Path)> {
+    let mut components = name.components();
+    let Some(Component::Normal(prefix)) = components.next() else {
+        return None;
+    };
+    let (_, root) = roots().into_iter().find(|(p, _)| prefix == OsStr::new(p))?;
+    Some((root, components.as_path()))
+}
+
+/// Restores the backup at `archive`, overwriting any existing files that it contains. Other
+/// files, such as profiles that aren't in the backup, are kept.
+///
+/// The files are extracted next to the directories they belong in first, and only moved into
+/// place once the whole backup has been extracted, so a broken archive changes nothing.
+///
+/// Restored settings only take effect once Manderrow is relaunched.
+pub async fn restore_all_data(
+    app: Option<&AppHandle>,
+    log: &slog::Logger,
+    archive: &Path,
+    task_id: Option<tasks::Id>,
+) -> Result<()> {
+    TaskBuilder::with_id(
+        task_id.unwrap_or_else(tasks::allocate_task),
+        "Restore Manderrow data",
+    )
+    .run_with_handle(app, |handle| async move {
+        tokio::task::block_in_place(|| {
+            let file =
+                File::open(archive).with_context(|| format!("Failed to open {archive:?}"))?;
+            let mut zip = zip::ZipArchive::new(BufReader::new(file))?;
+
+            let manifest = serde_json::from_reader::<_, BackupManifest>(
+                zip.by_name(MANIFEST_FILE_NAME)
+                    .context("Not a Manderrow backup")?,
+            )
+            .context("Failed to read backup manifest")?;
+            ensure!(
+                manifest.format_version <= FORMAT_VERSION,
+                "The backup was made by a newer version of Manderrow ({})",
+                manifest.app_version
+            );
+            debug!(
+                log,
+                "Restoring backup made by Manderrow {}", manifest.app_version
+            );
+
+            // staged on the same filesystem as the directory they belong in, so they can be renamed
+            let mut staging = HashMap::<&Path, tempfile::TempDir>::new();
+            let mut staged = Vec::new();
+            let total = zip.len() as u64;
+            for i in 0..zip.len() {
+                let mut file = zip.by_index(i)?;
+                if file.is_dir() || file.name() == MANIFEST_FILE_NAME {
+                    continue;
+                }
+                let Some(name) = file.enclosed_name() else {
+                    bail!("Unsafe path {:?} in backup", file.name());
+                };
+                let Some((root, rel_path)) = restore_path(&name) else {
+                    warn!(log, "Skipping unrecognized file {name:?} in backup");
+                    continue;
+                };
+                let path = root.join(rel_path);
+                if is_excluded(&path) {
+                    warn!(log, "Skipping excluded file {name:?} in backup");
+                    continue;
+                }
+                let staging_dir = match staging.entry(root) {
+                    std::collections::hash_map::Entry::Occupied(e) => e.into_mut(),
+                    std::collections::hash_map::Entry::Vacant(e) => {
+                        std::fs::create_dir_all(root)?;
+                        e.insert(
+                            tempfile::Builder::new()
+                                .prefix(".manderrow-restore")
+                                .tempdir_in(root.parent().unwrap_or(root))?,
+                        )
+                    }
+                };
+                let staged_path = staging_dir.path().join(rel_path);
+                if let Some(parent) = staged_path.parent() {
+                    std::fs::create_dir_all(parent)?;
+                }
+                let mut out = File::create(&staged_path)
+                    .with_context(|| format!("Failed to write {staged_path:?}"))?;
+                std::io::copy(&mut file, &mut out)?;
+                staged.push((staged_path, path));
+                if let Some(app) = app {
+                    handle.send_progress_manually(app, i as u64 + 1, total)?;
+                }
+            }
+
+            for (staged_path, path) in staged {
+                if let Some(parent) = path.parent() {
+                    std::fs::create_dir_all(parent)?;
+                }
+                std::fs::rename(&staged_path, &path)
+                    .with_context(|| format!("Failed to move {staged_path:?} to {path:?}"))?;
+            }
+            Ok::<_, anyhow::Error>(())
+        })?;
+        Ok::<_, anyhow::Error>((None, ()))
+    })
+    .await
+    .map_err(Into::into)
+}
@@ -1,3 +1,4 @@
+use crate::startup::Section;
 use crate::CommandError;
 
 #[tauri::command]
@@ -15,3 +16,9 @@ pub async fn bench_exit_interactive() -> Result<(), CommandError> {
     }
     Ok(())
 }
+
+/// Returns how long each section of startup took, to diagnose slow cold starts.
+#[tauri::command]
+pub async fn get_startup_timings() -> Result<Vec<Section>, CommandError> {
+    Ok(crate::startup::sections())
+}
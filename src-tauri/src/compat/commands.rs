@@ -0,0 +1,49 @@
+use smol_str::SmolStr;
+use uuid::Uuid;
+
+use crate::profiles::read_profile;
+use crate::CommandError;
+
+use super::{CompatNote, CompatWarning, NoteKind};
+
+/// Checks the mods installed in the profile against the compatibility notes for its game.
+#[tauri::command]
+pub async fn get_profile_compat_report(id: Uuid) -> Result<Vec<CompatWarning>, CommandError> {
+    let log = slog_scope::logger();
+    let game = read_profile(id).await.map_err(anyhow::Error::from)?.game;
+    super::check_profile(&log, id, &game)
+        .await
+        .map_err(Into::into)
+}
+
+#[tauri::command]
+pub async fn get_compat_notes(game: &str) -> Result<Vec<CompatNote>, CommandError> {
+    let log = slog_scope::logger();
+    super::get_community_notes(&log, game)
+        .await
+        .map_err(Into::into)
+}
+
+#[tauri::command]
+pub async fn get_user_compat_notes(game: &str) -> Result<Vec<CompatNote>, CommandError> {
+    super::read_user_notes(game).await.map_err(Into::into)
+}
+
+#[tauri::command]
+pub async fn add_user_compat_note(
+    game: &str,
+    r#mod: SmolStr,
+    kind: NoteKind,
+    message: Option<String>,
+) -> Result<CompatNote, CommandError> {
+    crate::profiles::read_only::ensure_writable()?;
+    super::add_user_note(game, r#mod, kind, message)
+        .await
+        .map_err(Into::into)
+}
+
+#[tauri::command]
+pub async fn remove_user_compat_note(game: &str, id: &str) -> Result<bool, CommandError> {
+    crate::profiles::read_only::ensure_writable()?;
+    super::remove_user_note(game, id).await.map_err(Into::into)
+}
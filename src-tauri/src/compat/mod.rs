@@ -0,0 +1,353 @@
+//! Compatibility notes about mods: known conflicts between mods, config values that a mod needs
+//! to work, and general caveats.
+//!
+//! Notes come from a community-maintained dataset, which is fetched per game and cached, and from
+//! notes that the user adds locally. They are checked against the mods installed in a profile to
+//! produce warnings.
+
+pub mod commands;
+
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use anyhow::{Context as _, Result};
+use manderrow_paths::{cache_dir, local_data_dir};
+use slog::{debug, error, warn};
+use smol_str::SmolStr;
+use uuid::Uuid;
+
+use crate::games::games_by_id;
+use crate::profiles::{configs, profile_path, MODS_FOLDER};
+use crate::util::IoErrorKindExt as _;
+
+/// How long a fetched copy of the community dataset is used before it is fetched again.
+const COMMUNITY_NOTES_MAX_AGE: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Where the community dataset is published, with a file per game.
+const COMMUNITY_NOTES_URL: &str =
+    "https://raw.githubusercontent.com/manderrow/compat-notes/main/games";
+
+fn community_notes_url(game: &str) -> String {
+    format!("{COMMUNITY_NOTES_URL}/{game}.json")
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum NoteKind {
+    /// The mod does not work when `other` is installed in the same profile.
+    ConflictsWith { other: SmolStr },
+    /// The mod needs a config value to be set to work properly.
+    NeedsConfig {
+        file: String,
+        section: String,
+        key: String,
+        value: String,
+    },
+    /// Anything else worth knowing about the mod.
+    Note,
+}
+
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]
+pub struct CompatNote {
+    pub id: SmolStr,
+    /// The mod that the note is about, as `Owner-Name`.
+    pub r#mod: SmolStr,
+    #[serde(flatten)]
+    pub kind: NoteKind,
+    #[serde(default)]
+    pub message: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NoteSource {
+    Community,
+    User,
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct CompatWarning {
+    pub source: NoteSource,
+    #[serde(flatten)]
+    pub note: CompatNote,
+}
+
+/// Returns the name of the notes file for `game`, which must be a known game, as it comes from the
+/// frontend and is used in paths.
+fn notes_file_name(game: &str) -> Result<String> {
+    let game = games_by_id()?
+        .get(game)
+        .with_context(|| format!("Unrecognized game {game:?}"))?;
+    Ok(format!("{}.json", game.id))
+}
+
+fn community_notes_cache_path(game: &str) -> Result<PathBuf> {
+    Ok(cache_dir()
+        .join("compat_notes")
+        .join(notes_file_name(game)?))
+}
+
+fn user_notes_path(game: &str) -> Result<PathBuf> {
+    Ok(local_data_dir()
+        .join("compat_notes")
+        .join(notes_file_name(game)?))
+}
+
+async fn read_notes_file(path: &Path) -> Result<Option<Vec<CompatNote>>> {
+    let mut bytes = match tokio::fs::read(path).await {
+        Ok(t) => t,
+        Err(e) if e.is_not_found() => return Ok(None),
+        Err(e) => return Err(e).with_context(|| format!("Failed to read {path:?}")),
+    };
+    Ok(Some(
+        simd_json::from_slice(&mut bytes).with_context(|| format!("Failed to parse {path:?}"))?,
+    ))
+}
+
+async fn write_notes_file(path: &Path, notes: &[CompatNote]) -> Result<()> {
+    let bytes = simd_json::to_vec(notes)?;
+    tokio::task::block_in_place(|| configs::write_atomically(path, &bytes))
+}
+
+/// Returns the community notes for `game`, fetching them if the cached copy is missing or stale.
+/// If they can't be fetched, a stale copy is used when there is one.
+pub async fn get_community_notes(log: &slog::Logger, game: &str) -> Result<Vec<CompatNote>> {
+    let path = community_notes_cache_path(game)?;
+    let is_fresh = match tokio::fs::metadata(&path).await {
+        Ok(m) => m
+            .modified()?
+            .elapsed()
+            .is_ok_and(|age| age < COMMUNITY_NOTES_MAX_AGE),
+        Err(e) if e.is_not_found() => false,
+        Err(e) => return Err(e).with_context(|| format!("Failed to read {path:?}")),
+    };
+    if is_fresh {
+        if let Some(notes) = read_notes_file(&path).await? {
+            return Ok(notes);
+        }
+    }
+
+    let fetched = async {
+        let resp = crate::util::http::client()
+            .get(community_notes_url(game))
+            .send()
+            .await?;
+        if resp.status() == reqwest::StatusCode::NOT_FOUND {
+            // no notes have been written for the game yet
+            return Ok(Vec::new());
+        }
+        let mut bytes = resp.error_for_status()?.bytes().await?.to_vec();
+        Ok::<_, anyhow::Error>(simd_json::from_slice::<Vec<CompatNote>>(&mut bytes)?)
+    }
+    .await;
+    match fetched {
+        Ok(notes) => {
+            debug!(log, "Fetched {} community notes for {game}", notes.len());
+            write_notes_file(&path, &notes).await?;
+            Ok(notes)
+        }
+        Err(e) => match read_notes_file(&path).await? {
+            Some(notes) => {
+                warn!(
+                    log,
+                    "Failed to fetch community notes for {game}, using a stale copy: {e:#}"
+                );
+                Ok(notes)
+            }
+            None => Err(e.context("Failed to fetch community compatibility notes")),
+        },
+    }
+}
+
+pub async fn read_user_notes(game: &str) -> Result<Vec<CompatNote>> {
+    Ok(read_notes_file(&user_notes_path(game)?)
+        .await?
+        .unwrap_or_default())
+}
+
+pub async fn add_user_note(
+    game: &str,
+    r#mod: SmolStr,
+    kind: NoteKind,
+    message: Option<String>,
+) -> Result<CompatNote> {
+    let mut notes = read_user_notes(game).await?;
+    let note = CompatNote {
+        id: SmolStr::from(Uuid::new_v4().hyphenated().to_string()),
+        r#mod,
+        kind,
+        message,
+    };
+    notes.push(note.clone());
+    write_notes_file(&user_notes_path(game)?, &notes).await?;
+    Ok(note)
+}
+
+/// Removes the user's note `id`, returning `false` if there was no such note.
+pub async fn remove_user_note(game: &str, id: &str) -> Result<bool> {
+    let mut notes = read_user_notes(game).await?;
+    let len = notes.len();
+    notes.retain(|note| note.id != id);
+    if notes.len() == len {
+        return Ok(false);
+    }
+    write_notes_file(&user_notes_path(game)?, &notes).await?;
+    Ok(true)
+}
+
+/// Lists the mods installed in the profile, as `Owner-Name`.
+async fn read_installed_mods(id: Uuid) -> Result<HashSet<SmolStr>> {
+    let path = profile_path(id).join(MODS_FOLDER);
+    let mut mods = HashSet::new();
+    let mut iter = match tokio::fs::read_dir(&path).await {
+        Ok(t) => t,
+        Err(e) if e.is_not_found() => return Ok(mods),
+        Err(e) => return Err(e).context("Failed to read profile mods folder"),
+    };
+    while let Some(e) = iter.next_entry().await? {
+        if !e.file_type().await?.is_dir() {
+            continue;
+        }
+        if let Some(name) = e.file_name().to_str() {
+            mods.insert(SmolStr::from(name));
+        }
+    }
+    Ok(mods)
+}
+
+/// Returns whether `note` applies to a profile with the `installed` mods. For
+/// [`NoteKind::NeedsConfig`], `config_value` is the current value of the config key, and the
+/// note only applies if it differs from the needed value.
+fn note_applies(
+    note: &CompatNote,
+    installed: &HashSet<SmolStr>,
+    config_value: Option<&str>,
+) -> bool {
+    if !installed.contains(&note.r#mod) {
+        return false;
+    }
+    match &note.kind {
+        NoteKind::ConflictsWith { other } => installed.contains(other),
+        NoteKind::NeedsConfig { value, .. } => config_value != Some(value.as_str()),
+        NoteKind::Note => true,
+    }
+}
+
+/// Checks the profile's mods against the community and user notes for `game`. Failing to fetch
+/// the community notes is not an error, as the user's notes can still be checked.
+pub async fn check_profile(log: &slog::Logger, id: Uuid, game: &str) -> Result<Vec<CompatWarning>> {
+    let community = match get_community_notes(log, game).await {
+        Ok(t) => t,
+        Err(e) => {
+            warn!(log, "{e:#}");
+            Vec::new()
+        }
+    };
+    let user = read_user_notes(game).await?;
+    let installed = read_installed_mods(id).await?;
+
+    let mut warnings = Vec::new();
+    for (source, notes) in [(NoteSource::Community, community), (NoteSource::User, user)] {
+        for note in notes {
+            let config_value = match &note.kind {
+                NoteKind::NeedsConfig {
+                    file, section, key, ..
+                } if installed.contains(&note.r#mod) => {
+                    configs::read_config_value(id, file, section, key).await?
+                }
+                _ => None,
+            };
+            if note_applies(&note, &installed, config_value.as_deref()) {
+                warnings.push(CompatWarning { source, note });
+            }
+        }
+    }
+    for warning in &warnings {
+        warn!(
+            log,
+            "Compatibility warning for {} in profile {id}: {:?}",
+            warning.note.r#mod,
+            warning.note.kind
+        );
+    }
+    Ok(warnings)
+}
+
+/// Like [`check_profile`], for when the check is a courtesy and whatever the profile was checked
+/// after has already happened. Errors are logged, and no warnings are returned.
+pub async fn check_profile_or_log(log: &slog::Logger, id: Uuid, game: &str) -> Vec<CompatWarning> {
+    match check_profile(log, id, game).await {
+        Ok(t) => t,
+        Err(e) => {
+            error!(log, "Failed to check compatibility notes: {e:#}");
+            Vec::new()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn note(r#mod: &str, kind: NoteKind) -> CompatNote {
+        CompatNote {
+            id: SmolStr::from(r#mod),
+            r#mod: SmolStr::from(r#mod),
+            kind,
+            message: None,
+        }
+    }
+
+    #[test]
+    fn test_parse_note() {
+        let mut json =
+            br#"{"id":"a","mod":"Owner-A","kind":"conflicts_with","other":"Owner-B"}"#.to_vec();
+        let note = simd_json::from_slice::<CompatNote>(&mut json).unwrap();
+        assert_eq!(
+            note.kind,
+            NoteKind::ConflictsWith {
+                other: SmolStr::from("Owner-B")
+            }
+        );
+    }
+
+    #[test]
+    fn test_note_applies() {
+        let installed = ["Owner-A", "Owner-B"].map(SmolStr::from).into();
+        let conflict = |other: &str| {
+            note(
+                "Owner-A",
+                NoteKind::ConflictsWith {
+                    other: SmolStr::from(other),
+                },
+            )
+        };
+        assert!(note_applies(&conflict("Owner-B"), &installed, None));
+        assert!(!note_applies(&conflict("Owner-C"), &installed, None));
+
+        let needs_config = note(
+            "Owner-A",
+            NoteKind::NeedsConfig {
+                file: "A.cfg".to_owned(),
+                section: "General".to_owned(),
+                key: "Enabled".to_owned(),
+                value: "true".to_owned(),
+            },
+        );
+        assert!(note_applies(&needs_config, &installed, None));
+        assert!(note_applies(&needs_config, &installed, Some("false")));
+        assert!(!note_applies(&needs_config, &installed, Some("true")));
+
+        assert!(note_applies(
+            &note("Owner-B", NoteKind::Note),
+            &installed,
+            None
+        ));
+        assert!(!note_applies(
+            &note("Owner-D", NoteKind::Note),
+            &installed,
+            None
+        ));
+    }
+}
@@ -1,6 +1,8 @@
 #[derive(Debug, Clone, serde::Serialize)]
 pub enum CommandError {
     Aborted,
+    /// The command would have changed a profile, but profiles are read-only.
+    ReadOnly,
     Error {
         messages: Vec<String>,
         backtrace: String,
@@ -22,6 +24,12 @@ impl From<anyhow::Error> for CommandError {
     }
 }
 
+impl From<crate::profiles::read_only::ReadOnlyError> for CommandError {
+    fn from(_: crate::profiles::read_only::ReadOnlyError) -> Self {
+        Self::ReadOnly
+    }
+}
+
 impl From<Error> for CommandError {
     #[track_caller]
     fn from(value: Error) -> Self {
@@ -0,0 +1,2 @@
+pub mod mod_list;
+pub mod thunderstore;
@@ -0,0 +1,213 @@
+//! Exporting the list of mods in a profile in human-readable formats, e.g. to share it on Discord
+//! or in a modpack's documentation.
+
+use std::fmt::Write as _;
+
+use anyhow::{Context as _, Result};
+use manderrow_types::util::serde::IgnoredAny;
+use uuid::Uuid;
+
+use crate::profiles::{profile_path, read_profile, MANIFEST_FILE_NAME, MODS_FOLDER};
+use crate::util::IoErrorKindExt as _;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ModListFormat {
+    Markdown,
+    Csv,
+    Text,
+}
+
+/// The fields of a mod's `manderrow_mod.json` that are listed.
+#[derive(Debug, serde::Deserialize)]
+struct ListedMod {
+    owner: String,
+    name: String,
+    version: ListedModVersion,
+    #[serde(default)]
+    dev_source: Option<IgnoredAny>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct ListedModVersion {
+    version_number: packed_semver::Version,
+    #[serde(default)]
+    website_url: Option<String>,
+}
+
+impl ListedMod {
+    fn package_url(&self, community: &str) -> String {
+        format!(
+            "https://thunderstore.io/c/{community}/p/{}/{}/",
+            self.owner, self.name
+        )
+    }
+}
+
+/// Reads the mods installed in the profile, sorted by name.
+async fn read_listed_mods(id: Uuid) -> Result<Vec<ListedMod>> {
+    let path = profile_path(id).join(MODS_FOLDER);
+    let mut mods = Vec::new();
+    let mut iter = match tokio::fs::read_dir(&path).await {
+        Ok(t) => t,
+        Err(e) if e.is_not_found() => return Ok(mods),
+        Err(e) => return Err(e).context("Failed to read profile mods folder"),
+    };
+    while let Some(e) = iter.next_entry().await? {
+        if !e.file_type().await?.is_dir() {
+            continue;
+        }
+        let manifest_path = e.path().join(MANIFEST_FILE_NAME);
+        let manifest = match tokio::fs::read(&manifest_path).await {
+            Ok(t) => t,
+            Err(e) if e.is_not_found() => continue,
+            Err(e) => return Err(e).with_context(|| format!("Failed to read {manifest_path:?}")),
+        };
+        let m = serde_json::from_slice::<ListedMod>(&manifest)
+            .with_context(|| format!("Failed to parse {manifest_path:?}"))?;
+        // linked mods are not on Thunderstore, so there is nothing to link to
+        if m.dev_source.is_none() {
+            mods.push(m);
+        }
+    }
+    mods.sort_by(|a, b| {
+        a.name
+            .to_lowercase()
+            .cmp(&b.name.to_lowercase())
+            .then_with(|| a.owner.cmp(&b.owner))
+    });
+    Ok(mods)
+}
+
+/// Quotes `field` for CSV if needed.
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_owned()
+    }
+}
+
+fn render(
+    profile_name: &str,
+    game_name: &str,
+    community: &str,
+    mods: &[ListedMod],
+    format: ModListFormat,
+) -> String {
+    let mut buf = String::new();
+    match format {
+        ModListFormat::Markdown => {
+            _ = writeln!(buf, "# {profile_name}");
+            _ = writeln!(buf);
+            _ = writeln!(buf, "{} mods for {game_name}", mods.len());
+            _ = writeln!(buf);
+            for m in mods {
+                _ = writeln!(
+                    buf,
+                    "- [{}]({}) {} by {}",
+                    m.name,
+                    m.package_url(community),
+                    m.version.version_number,
+                    m.owner
+                );
+            }
+        }
+        ModListFormat::Csv => {
+            _ = writeln!(buf, "owner,name,version,url,website");
+            for m in mods {
+                _ = writeln!(
+                    buf,
+                    "{},{},{},{},{}",
+                    csv_field(&m.owner),
+                    csv_field(&m.name),
+                    m.version.version_number,
+                    csv_field(&m.package_url(community)),
+                    csv_field(m.version.website_url.as_deref().unwrap_or_default()),
+                );
+            }
+        }
+        ModListFormat::Text => {
+            _ = writeln!(buf, "{profile_name} ({game_name}, {} mods)", mods.len());
+            for m in mods {
+                _ = writeln!(buf, "{}-{}-{}", m.owner, m.name, m.version.version_number);
+            }
+        }
+    }
+    buf
+}
+
+/// Lists the mods installed in the profile, with their versions and links to them, in `format`.
+pub async fn export_mod_list(id: Uuid, format: ModListFormat) -> Result<String> {
+    let profile = read_profile(id).await?;
+    let game = *crate::games::games_by_id()?
+        .get(&*profile.game)
+        .context("No such game")?;
+    let mods = read_listed_mods(id).await?;
+    Ok(render(
+        &profile.name,
+        &game.name,
+        game.thunderstore_id,
+        &mods,
+        format,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn listed_mod(
+        owner: &str,
+        name: &str,
+        (major, minor, patch): (u64, u64, u64),
+        website_url: Option<&str>,
+    ) -> ListedMod {
+        ListedMod {
+            owner: owner.to_owned(),
+            name: name.to_owned(),
+            version: ListedModVersion {
+                version_number: packed_semver::Version::new(major, minor, patch).unwrap(),
+                website_url: website_url.map(str::to_owned),
+            },
+            dev_source: None,
+        }
+    }
+
+    #[test]
+    fn test_render() {
+        let mods = [
+            listed_mod("notnotnotswipez", "MoreCompany", (1, 11, 0), None),
+            listed_mod(
+                "x753",
+                "More_Suits",
+                (1, 4, 5),
+                Some("https://example.com/a,b"),
+            ),
+        ];
+        assert_eq!(
+            render("Friends", "Lethal Company", "lethal-company", &mods, ModListFormat::Markdown),
+            "# Friends\n\n2 mods for Lethal Company\n\n\
+             - [MoreCompany](https://thunderstore.io/c/lethal-company/p/notnotnotswipez/MoreCompany/) 1.11.0 by notnotnotswipez\n\
+             - [More_Suits](https://thunderstore.io/c/lethal-company/p/x753/More_Suits/) 1.4.5 by x753\n"
+        );
+        assert_eq!(
+            render("Friends", "Lethal Company", "lethal-company", &mods, ModListFormat::Csv),
+            "owner,name,version,url,website\n\
+             notnotnotswipez,MoreCompany,1.11.0,https://thunderstore.io/c/lethal-company/p/notnotnotswipez/MoreCompany/,\n\
+             x753,More_Suits,1.4.5,https://thunderstore.io/c/lethal-company/p/x753/More_Suits/,\"https://example.com/a,b\"\n"
+        );
+        assert_eq!(
+            render(
+                "Friends",
+                "Lethal Company",
+                "lethal-company",
+                &mods,
+                ModListFormat::Text
+            ),
+            "Friends (Lethal Company, 2 mods)\n\
+             notnotnotswipez-MoreCompany-1.11.0\n\
+             x753-More_Suits-1.4.5\n"
+        );
+    }
+}
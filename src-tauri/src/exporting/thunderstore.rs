@@ -0,0 +1,248 @@
+//! Exporting profiles in the format used by r2modman, optionally sharing them on Thunderstore.
+
+use std::io::{Seek, Write};
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use base64::prelude::BASE64_STANDARD;
+use base64::Engine as _;
+use manderrow_types::util::serde::IgnoredAny;
+use uuid::Uuid;
+
+use crate::importing::thunderstore::{
+    FullName, ProfileManifest, ProfileMod, Version, R2_PROFILE_DATA_PREFIX,
+    R2_PROFILE_MANIFEST_FILE_NAME,
+};
+use crate::profiles::{profile_path, read_profile, CONFIG_FOLDER, MODS_FOLDER};
+use crate::util::IoErrorKindExt as _;
+
+/// The fields of a mod's `manderrow_mod.json` that are needed for an export.
+#[derive(serde::Deserialize)]
+struct InstalledMod<'a> {
+    name: &'a str,
+    owner: &'a str,
+    version: InstalledModVersion,
+    #[serde(default)]
+    dev_source: Option<IgnoredAny>,
+    #[serde(default)]
+    disabled: bool,
+}
+
+#[derive(serde::Deserialize)]
+struct InstalledModVersion {
+    version_number: packed_semver::Version,
+}
+
+/// Lists the mods installed in the profile, sorted by name.
+pub async fn read_installed_mods(log: &slog::Logger, profile: &Path) -> Result<Vec<ProfileMod>> {
+    let path = profile.join(MODS_FOLDER);
+    let mut mods = Vec::new();
+    let mut iter = match tokio::fs::read_dir(&path).await {
+        Ok(t) => t,
+        Err(e) if e.is_not_found() => return Ok(mods),
+        Err(e) => return Err(e).context("Failed to read profile mods folder"),
+    };
+    while let Some(e) = iter.next_entry().await? {
+        if !e.file_type().await?.is_dir() {
+            continue;
+        }
+        let manifest_path = e.path().join(crate::profiles::MANIFEST_FILE_NAME);
+        let manifest = match tokio::fs::read_to_string(&manifest_path).await {
+            Ok(t) => t,
+            Err(e) if e.is_not_found() => {
+                slog::warn!(
+                    log,
+                    "Skipping mod without a manifest at {:?}",
+                    manifest_path
+                );
+                continue;
+            }
+            Err(e) => {
+                return Err(e).with_context(|| format!("Failed to read {manifest_path:?}"));
+            }
+        };
+        let m = serde_json::from_str::<InstalledMod>(&manifest)
+            .with_context(|| format!("Failed to parse {manifest_path:?}"))?;
+        // linked mods can't be installed from Thunderstore by whoever imports the profile
+        if m.dev_source.is_some() {
+            slog::debug!(log, "Not exporting linked mod {}-{}", m.owner, m.name);
+            continue;
+        }
+        let version = m.version.version_number;
+        let disabled = m.disabled || tokio::task::block_in_place(|| plugins_disabled(&e.path()))?;
+        mods.push(ProfileMod {
+            full_name: FullName::new(m.owner, m.name),
+            version: Version {
+                major: version.major(),
+                minor: version.minor(),
+                patch: version.patch(),
+            },
+            enabled: !disabled,
+        });
+    }
+    mods.sort_by(|a, b| (*a.full_name).cmp(&*b.full_name));
+    Ok(mods)
+}
+
+/// Returns `true` if all of the plugins in the mod's `folder` were renamed so that BepInEx doesn't
+/// load them, as r2modman disables mods, even if its manifest doesn't say that it is disabled.
+fn plugins_disabled(folder: &Path) -> Result<bool> {
+    let (mut enabled, mut disabled) = (0, 0);
+    for e in walkdir::WalkDir::new(folder) {
+        let e = e?;
+        let name = e.file_name().to_string_lossy();
+        if name.ends_with(crate::profiles::batch::DISABLED_SUFFIX) {
+            disabled += 1;
+        } else if name.ends_with(".dll") {
+            enabled += 1;
+        }
+    }
+    Ok(disabled != 0 && enabled == 0)
+}
+
+/// Builds an r2modman compatible profile archive (`.r2z`) for the profile: an
+/// [`R2_PROFILE_MANIFEST_FILE_NAME`] listing the installed mods, and the profile's config files
+/// under `BepInEx/config`.
+pub async fn build_profile_archive(log: &slog::Logger, id: Uuid) -> Result<Vec<u8>> {
+    let metadata = read_profile(id).await?;
+    let path = profile_path(id);
+
+    let manifest = ProfileManifest {
+        profile_name: metadata.name.to_string(),
+        mods: read_installed_mods(log, &path).await?,
+    };
+    slog::debug!(
+        log,
+        "Exporting {} mods from profile {id}",
+        manifest.mods.len()
+    );
+
+    let config_dir = path.join(CONFIG_FOLDER);
+    tokio::task::spawn_blocking(move || {
+        let options = zip::write::SimpleFileOptions::default();
+        let mut zip = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+
+        zip.start_file(R2_PROFILE_MANIFEST_FILE_NAME, options)?;
+        serde_yaml::to_writer(&mut zip, &manifest).context("Failed to write profile manifest")?;
+
+        write_config_files(&mut zip, &config_dir, "BepInEx/config")?;
+
+        Ok::<_, anyhow::Error>(zip.finish()?.into_inner())
+    })
+    .await?
+}
+
+/// Adds the config files in `config_dir` to `zip`, under the folder `prefix`.
+pub fn write_config_files<W: Write + Seek>(
+    zip: &mut zip::ZipWriter<W>,
+    config_dir: &Path,
+    prefix: &str,
+) -> Result<()> {
+    if !config_dir.exists() {
+        return Ok(());
+    }
+    let options = zip::write::SimpleFileOptions::default();
+    for e in walkdir::WalkDir::new(config_dir).sort_by_file_name() {
+        let e = e?;
+        if !e.file_type().is_file() {
+            continue;
+        }
+        let rel_path = e.path().strip_prefix(config_dir)?;
+        // zip paths always use forward slashes
+        let mut name = prefix.to_owned();
+        for c in rel_path.components() {
+            name.push('/');
+            name.push_str(
+                c.as_os_str()
+                    .to_str()
+                    .with_context(|| format!("Non-UTF-8 config path {rel_path:?}"))?,
+            );
+        }
+        zip.start_file(name, options)?;
+        let bytes = std::fs::read(e.path())
+            .with_context(|| format!("Failed to read config file {:?}", e.path()))?;
+        zip.write_all(&bytes)?;
+    }
+    Ok(())
+}
+
+/// Encodes a profile archive as the payload that r2modman expects a profile code to point to.
+pub fn encode_profile_payload(archive: &[u8]) -> String {
+    let mut buf = R2_PROFILE_DATA_PREFIX.to_owned();
+    buf.push('\n');
+    BASE64_STANDARD.encode_string(archive, &mut buf);
+    buf
+}
+
+#[derive(serde::Deserialize)]
+struct CreateProfileResponse {
+    key: Uuid,
+}
+
+/// Uploads a profile archive to Thunderstore, returning the code that it can be imported with.
+pub async fn upload_profile(log: &slog::Logger, archive: &[u8]) -> Result<Uuid> {
+    let bytes = crate::util::http::client()
+        .post("https://thunderstore.io/api/experimental/legacyprofile/create/")
+        .header(reqwest::header::CONTENT_TYPE, "application/octet-stream")
+        .body(encode_profile_payload(archive))
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .context("Failed to upload profile to Thunderstore")?
+        .bytes()
+        .await
+        .context("Failed to upload profile to Thunderstore")?;
+    let resp = serde_json::from_slice::<CreateProfileResponse>(&bytes)
+        .context("Failed to decode profile upload response from Thunderstore")?;
+    slog::info!(log, "Uploaded profile to Thunderstore as {}", resp.key);
+    Ok(resp.key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_installed_mods() {
+        let log = slog::Logger::root(slog::Discard, slog::o!());
+        let profile = tempfile::tempdir().unwrap();
+        let add = |name: &str, manifest_extra: &str, plugin: &str| {
+            let folder = profile
+                .path()
+                .join(MODS_FOLDER)
+                .join(format!("Owner-{name}"));
+            std::fs::create_dir_all(&folder).unwrap();
+            std::fs::write(
+                folder.join(crate::profiles::MANIFEST_FILE_NAME),
+                format!(
+                    r#"{{"owner":"Owner","name":"{name}","version":{{"version_number":"1.0.0"}}{manifest_extra}}}"#
+                ),
+            )
+            .unwrap();
+            std::fs::write(folder.join(plugin), b"").unwrap();
+        };
+        add("Enabled", "", "Plugin.dll");
+        add("Disabled", r#","disabled":true"#, "Plugin.dll.old");
+        // disabled by r2modman, or by hand
+        add("Renamed", "", "Plugin.dll.old");
+
+        let mods = tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()
+            .expect("unable to build tokio runtime")
+            .block_on(read_installed_mods(&log, profile.path()))
+            .unwrap();
+        let summary = mods
+            .iter()
+            .map(|m| (&*m.full_name, m.enabled))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            summary,
+            [
+                ("Owner-Disabled", false),
+                ("Owner-Enabled", true),
+                ("Owner-Renamed", false)
+            ]
+        );
+    }
+}
@@ -1,3 +1,5 @@
+use std::collections::HashMap;
+
 use anyhow::Context;
 use tauri::ipc::InvokeResponseBody;
 
@@ -7,7 +9,7 @@ use crate::{
     CommandError,
 };
 
-use super::{games, GAMES_MOD_DOWNLOADS, GAMES_REVIEWS};
+use super::{games, stats, GAMES_MOD_DOWNLOADS, GAMES_REVIEWS};
 
 #[tauri::command]
 pub async fn get_games() -> Result<&'static [Game<'static>], CommandError> {
@@ -59,7 +61,11 @@ pub async fn search_games(
                     SortColumn::Relevance => a_score.cmp(b_score),
                     SortColumn::Name => games[*a_i].name.cmp(&games[*b_i].name),
                     SortColumn::ModDownloads => {
-                        games_mod_downloads[*a_i].cmp(&games_mod_downloads[*b_i])
+                        let mod_downloads = |i: usize| {
+                            stats::latest_mod_downloads(games[i].id)
+                                .unwrap_or(games_mod_downloads[i])
+                        };
+                        mod_downloads(*a_i).cmp(&mod_downloads(*b_i))
                     }
                     SortColumn::Popularity => games_reviews[*a_i].cmp(&games_reviews[*b_i]),
                 };
@@ -89,13 +95,26 @@ pub async fn get_games_popularity() -> Result<InvokeResponseBody, CommandError>
 }
 
 #[tauri::command]
-pub async fn get_game_mods_downloads() -> Result<InvokeResponseBody, CommandError> {
-    // type check the JSON before sending the raw JSON to the frontend
-    GAMES_MOD_DOWNLOADS
+pub async fn get_game_mods_downloads() -> Result<HashMap<&'static str, u64>, CommandError> {
+    let games_mod_downloads = GAMES_MOD_DOWNLOADS
         .as_ref()
         .map_err(Clone::clone)
         .context("Failed to load gameModDownloads.json")?;
-    Ok(InvokeResponseBody::Json(
-        include_str!("gameModDownloads.json").to_owned(),
-    ))
+    // prefer statistics computed from the live mod index over the baked values
+    Ok(games()?
+        .iter()
+        .zip(games_mod_downloads)
+        .map(|(game, &baked)| {
+            (
+                game.thunderstore_id,
+                stats::latest_mod_downloads(game.id).unwrap_or(baked),
+            )
+        })
+        .collect())
+}
+
+#[tauri::command]
+pub async fn get_game_stats_trends() -> Result<HashMap<&'static str, stats::GameTrend>, CommandError>
+{
+    Ok(stats::trends()?)
 }
@@ -1,4 +1,5 @@
 pub mod commands;
+pub mod stats;
 
 pub use manderrow_types::games::*;
 
@@ -97,6 +98,14 @@ pub fn games() -> Result<&'static [Game<'static>]> {
         .context("Failed to load games.json")
 }
 
+/// Returns the URL of the game's community on Thunderstore, like
+/// `https://thunderstore.io/c/riskofrain2`, which the URL of its mod index starts with.
+pub fn community_url<'a>(game: &'a Game<'_>) -> Option<&'a str> {
+    game.thunderstore_url
+        .split_once("/api/")
+        .map(|(url, _)| url)
+}
+
 pub fn games_by_id() -> Result<&'static HashMap<&'static str, &'static Game<'static>>> {
     GAMES_BY_ID
         .as_ref()
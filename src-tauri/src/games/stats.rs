@@ -0,0 +1,253 @@
+//! Per-game aggregate statistics computed from the live mod index.
+//!
+//! Every time a game's mod index is fetched, a [`Snapshot`] of its totals is recorded. The
+//! snapshots are persisted so that trends (e.g. week-over-week download growth) survive app
+//! restarts. Games for which no snapshot exists fall back to the baked `gameModDownloads.json`.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::LazyLock;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use anyhow::{Context as _, Result};
+use manderrow_paths::cache_dir;
+use parking_lot::RwLock;
+use slog::{debug, error, info};
+
+use crate::mod_index::memory::MemoryModIndex;
+use crate::profiles::configs::write_atomically;
+use crate::util::IoErrorKindExt as _;
+
+use super::games;
+
+/// How often the mod indexes that are already loaded are refetched in the background to keep
+/// the statistics fresh.
+const REFRESH_INTERVAL: Duration = Duration::from_secs(6 * 60 * 60);
+
+/// Snapshots older than this are discarded.
+const RETENTION: Duration = Duration::from_secs(8 * 7 * 24 * 60 * 60);
+
+/// A new snapshot taken within this long of the previous one replaces it instead of being
+/// appended.
+const COALESCE_WINDOW: Duration = Duration::from_secs(60 * 60);
+
+const WEEK: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+static PATH: LazyLock<PathBuf> = LazyLock::new(|| cache_dir().join("game_stats.json"));
+
+static STATS: LazyLock<RwLock<StatsFile>> = LazyLock::new(|| {
+    RwLock::new(match read() {
+        Ok(t) => t,
+        Err(e) => {
+            slog_scope::error!("Failed to read game statistics from {:?}: {e}", *PATH);
+            StatsFile::default()
+        }
+    })
+});
+
+#[derive(Debug, Clone, Copy, serde::Deserialize, serde::Serialize)]
+pub struct Snapshot {
+    /// Seconds since the Unix epoch.
+    pub taken_at: u64,
+    pub mod_count: u64,
+    pub mod_downloads: u64,
+}
+
+#[derive(Debug, Default, Clone, serde::Deserialize, serde::Serialize)]
+struct StatsFile {
+    /// Snapshots by game id, oldest first.
+    games: HashMap<String, Vec<Snapshot>>,
+}
+
+#[derive(Debug, Clone, Copy, serde::Serialize)]
+pub struct GameTrend {
+    pub latest: Snapshot,
+    /// Change in total mod downloads since the most recent snapshot that is at least a week
+    /// older than [`Self::latest`], if there is one.
+    pub downloads_delta_week: Option<i64>,
+    /// Change in mod count over the same period as [`Self::downloads_delta_week`].
+    pub mod_count_delta_week: Option<i64>,
+}
+
+/// Reads the snapshots from disk ahead of the first use.
+pub fn preload() {
+    LazyLock::force(&STATS);
+}
+
+fn read() -> Result<StatsFile> {
+    let mut bytes = match std::fs::read(&*PATH) {
+        Ok(t) => t,
+        Err(e) if e.is_not_found() => return Ok(StatsFile::default()),
+        Err(e) => return Err(e.into()),
+    };
+    Ok(simd_json::from_slice(&mut bytes)?)
+}
+
+fn write(stats: &StatsFile) -> Result<()> {
+    write_atomically(&PATH, &simd_json::to_vec(stats)?)
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+pub fn aggregate(mod_index: &MemoryModIndex) -> (u64, u64) {
+    mod_index
+        .chunks
+        .iter()
+        .flat_map(|chunk| chunk.mods().iter())
+        .fold((0, 0), |(count, downloads), m| {
+            (
+                count + 1,
+                downloads
+                    + m.versions
+                        .iter()
+                        .map(|v| v.downloads.to_native())
+                        .sum::<u64>(),
+            )
+        })
+}
+
+/// Records a snapshot of the `(mod_count, mod_downloads)` totals, as computed by [`aggregate`],
+/// for every game that shares the mod index.
+pub fn record(
+    log: &slog::Logger,
+    thunderstore_url: &str,
+    (mod_count, mod_downloads): (u64, u64),
+) -> Result<()> {
+    let snapshot = Snapshot {
+        taken_at: now(),
+        mod_count,
+        mod_downloads,
+    };
+    debug!(
+        log,
+        "Recording game statistics for {thunderstore_url:?}: {snapshot:?}"
+    );
+
+    // readers must not wait on the file write
+    let stats = {
+        let mut stats = STATS.write();
+        for game in games()?
+            .iter()
+            .filter(|g| g.thunderstore_url == thunderstore_url)
+        {
+            let history = stats.games.entry(game.id.to_owned()).or_default();
+            history.retain(|s| snapshot.taken_at.saturating_sub(s.taken_at) < RETENTION.as_secs());
+            match history.last_mut() {
+                Some(last)
+                    if snapshot.taken_at.saturating_sub(last.taken_at)
+                        < COALESCE_WINDOW.as_secs() =>
+                {
+                    *last = snapshot;
+                }
+                _ => history.push(snapshot),
+            }
+        }
+        stats.clone()
+    };
+    tokio::task::block_in_place(|| write(&stats)).context("Failed to write game statistics")
+}
+
+/// Returns the latest known total mod downloads for the game, if any have been recorded.
+pub fn latest_mod_downloads(game_id: &str) -> Option<u64> {
+    STATS
+        .read()
+        .games
+        .get(game_id)
+        .and_then(|h| h.last())
+        .map(|s| s.mod_downloads)
+}
+
+fn trend(history: &[Snapshot]) -> Option<GameTrend> {
+    let latest = *history.last()?;
+    let week_ago = history
+        .iter()
+        .rev()
+        .find(|s| latest.taken_at.saturating_sub(s.taken_at) >= WEEK.as_secs());
+    Some(GameTrend {
+        latest,
+        downloads_delta_week: week_ago
+            .map(|s| latest.mod_downloads as i64 - s.mod_downloads as i64),
+        mod_count_delta_week: week_ago.map(|s| latest.mod_count as i64 - s.mod_count as i64),
+    })
+}
+
+/// Returns the trends by Thunderstore id, the same key that the baked statistics use.
+pub fn trends() -> Result<HashMap<&'static str, GameTrend>> {
+    let stats = STATS.read();
+    Ok(games()?
+        .iter()
+        .filter_map(|game| Some((game.thunderstore_id, trend(stats.games.get(game.id)?)?)))
+        .collect())
+}
+
+/// Periodically refetches the mod indexes that have been loaded, which in turn records fresh
+/// statistics for them.
+pub fn spawn_refresher() {
+    tauri::async_runtime::spawn(async move {
+        let log = slog_scope::logger();
+        loop {
+            tokio::time::sleep(REFRESH_INTERVAL).await;
+            crate::background::wait_until_resumed().await;
+
+            let Ok(games) = games() else {
+                return;
+            };
+            let mut seen = std::collections::HashSet::new();
+            for game in games {
+                if !seen.insert(&*game.thunderstore_url)
+                    || !crate::mod_index::is_loaded(&game.thunderstore_url)
+                {
+                    continue;
+                }
+                if crate::background::is_paused() {
+                    break;
+                }
+                info!(log, "Refreshing mod index of {} for statistics", game.id);
+                if let Err(e) = crate::background::run_in_background(
+                    crate::mod_index::fetch_mod_index(None, game.id, true, None),
+                )
+                .await
+                {
+                    error!(log, "Failed to refresh mod index of {}: {e}", game.id);
+                }
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{trend, Snapshot, WEEK};
+
+    fn snapshot(taken_at: u64, mod_downloads: u64) -> Snapshot {
+        Snapshot {
+            taken_at,
+            mod_count: 10,
+            mod_downloads,
+        }
+    }
+
+    #[test]
+    fn test_trend_requires_a_week_of_history() {
+        let t = trend(&[snapshot(0, 100), snapshot(60, 150)]).unwrap();
+        assert_eq!(t.downloads_delta_week, None);
+    }
+
+    #[test]
+    fn test_trend_uses_most_recent_week_old_snapshot() {
+        let week = WEEK.as_secs();
+        let t = trend(&[
+            snapshot(0, 100),
+            snapshot(10, 120),
+            snapshot(week + 20, 200),
+        ])
+        .unwrap();
+        assert_eq!(t.downloads_delta_week, Some(80));
+        assert_eq!(t.mod_count_delta_week, Some(0));
+    }
+}
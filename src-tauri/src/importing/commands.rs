@@ -1,20 +1,22 @@
+use std::path::{Path, PathBuf};
+
 use anyhow::{anyhow, bail, Context};
 use futures_util::stream::FuturesUnordered;
-use futures_util::TryStreamExt;
+use futures_util::{StreamExt, TryStreamExt};
 use manderrow_types::mods::{ModId, ModMetadata, ModVersion};
 use packed_semver::Version;
 use serde::Serialize;
 use tauri::ipc::{Channel, InvokeResponseBody};
-use tauri::{AppHandle, Manager, State};
+use tauri::{AppHandle, Manager};
 use tokio_util::compat::FuturesAsyncReadCompatExt;
 use uuid::Uuid;
 
 use crate::mod_index::fetch_mod_index;
 use crate::profiles::profile_path;
 use crate::tasks::{TaskBuilder, TaskError, TaskHandle};
-use crate::{tasks, CommandError, Reqwest};
+use crate::{tasks, CommandError};
 
-use super::thunderstore;
+use super::{game_dir, r2modman, thunderstore};
 
 #[derive(Debug, Clone, Serialize)]
 pub struct Modpack {
@@ -46,7 +48,6 @@ pub enum Diff {
 #[tauri::command]
 pub async fn preview_import_modpack_from_thunderstore_code(
     app: AppHandle,
-    reqwest: State<'_, Reqwest>,
     thunderstore_id: Uuid,
     game: &str,
     profile_id: Option<Uuid>,
@@ -55,8 +56,7 @@ pub async fn preview_import_modpack_from_thunderstore_code(
     let log = slog_scope::logger();
 
     let mut profile =
-        thunderstore::lookup_profile(Some(&app), &log, &reqwest, thunderstore_id, Some(task_id))
-            .await?;
+        thunderstore::lookup_profile(Some(&app), &log, thunderstore_id, Some(task_id)).await?;
 
     let mut mods = Vec::with_capacity(profile.manifest.mods.len());
 
@@ -108,17 +108,40 @@ struct ModProgressRegistration<'a> {
     task: tasks::Id,
 }
 
+#[derive(Debug, Clone, Serialize)]
+pub struct ImportReport {
+    pub profile_id: Uuid,
+    pub mods: Vec<ModImportResult>,
+    pub compat_warnings: Vec<crate::compat::CompatWarning>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ModImportResult {
+    pub name: String,
+    pub version: String,
+    #[serde(flatten)]
+    pub outcome: ModImportOutcome,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "status")]
+pub enum ModImportOutcome {
+    Installed,
+    Failed { error: String },
+}
+
 #[tauri::command]
 pub async fn import_modpack_from_thunderstore_code(
     app: AppHandle,
-    reqwest: State<'_, Reqwest>,
     thunderstore_id: Uuid,
     game: &str,
     profile_id: Option<Uuid>,
     // ModProgressRegistration, but can't express the lifetime
     mod_progress_channel: Channel<InvokeResponseBody>,
     task_id: tasks::Id,
-) -> Result<Uuid, CommandError> {
+) -> Result<ImportReport, CommandError> {
+    crate::profiles::read_only::ensure_writable()?;
+
     if profile_id.is_some() {
         return Err(anyhow!("Importing over existing profiles is not yet supported").into());
     }
@@ -147,7 +170,6 @@ pub async fn import_modpack_from_thunderstore_code(
             thunderstore::lookup_profile(
                 Some(app),
                 &log,
-                &reqwest,
                 thunderstore_id,
                 Some(handle.allocate_dependency(app)?),
             )
@@ -166,9 +188,8 @@ pub async fn import_modpack_from_thunderstore_code(
             ),
         };
 
-        if let Err(e) = import_onto_profile(
+        let mods = match import_onto_profile(
             &app,
-            &*reqwest,
             game,
             profile,
             profile_id,
@@ -177,35 +198,316 @@ pub async fn import_modpack_from_thunderstore_code(
         )
         .await
         {
-            if is_new_profile {
-                crate::profiles::delete_profile(profile_id).await?;
+            Ok(t) => t,
+            Err(e) => {
+                if is_new_profile {
+                    crate::profiles::delete_profile(profile_id).await?;
+                }
+                return Err(e.into());
             }
-            return Err(e.into());
-        }
+        };
 
-        Ok((None, profile_id))
+        let report = finish_import(&log, game, profile_id, mods, None).await?;
+        Ok((None, report))
     })
     .await
     .map_err(|e: TaskError<anyhow::Error>| anyhow::Error::from(e).into())
 }
 
+/// Finishes importing `mods` into the new profile `profile_id`. The config files are copied from
+/// `configs_from`, which is laid out like a profile of r2modman, if given, and the profile is
+/// deleted if they can't be. The profile is then checked against the compatibility notes.
+pub(crate) async fn finish_import(
+    log: &slog::Logger,
+    game: &str,
+    profile_id: Uuid,
+    mods: Vec<ModImportResult>,
+    configs_from: Option<&Path>,
+) -> anyhow::Result<ImportReport> {
+    if let Some(path) = configs_from {
+        let target = profile_path(profile_id);
+        match tokio::task::block_in_place(|| r2modman::copy_configs(path, &target)) {
+            Ok(count) => slog::debug!(log, "Copied {count} config files from {path:?}"),
+            Err(e) => {
+                crate::profiles::delete_profile(profile_id).await?;
+                return Err(e.context("Failed to copy config files"));
+            }
+        }
+    }
+
+    let compat_warnings = crate::compat::check_profile_or_log(log, profile_id, game).await;
+
+    Ok(ImportReport {
+        profile_id,
+        mods,
+        compat_warnings,
+    })
+}
+
+/// Lists the profiles of r2modman and Thunderstore Mod Manager that are stored on this computer.
+#[tauri::command]
+pub async fn scan_local_r2modman_profiles() -> Result<Vec<r2modman::LocalProfile>, CommandError> {
+    let log = slog_scope::logger();
+    tokio::task::block_in_place(|| r2modman::scan_profiles(&log)).map_err(Into::into)
+}
+
+/// Creates a new profile from the r2modman or Thunderstore Mod Manager profile at `path`,
+/// installing its mods and copying its config files.
+#[tauri::command]
+pub async fn import_local_r2modman_profile(
+    app: AppHandle,
+    path: PathBuf,
+    game: &str,
+    // ModProgressRegistration, but can't express the lifetime
+    mod_progress_channel: Channel<InvokeResponseBody>,
+    task_id: tasks::Id,
+) -> Result<ImportReport, CommandError> {
+    crate::profiles::read_only::ensure_writable()?;
+
+    let app = &app;
+    let log = slog_scope::logger();
+
+    let name = path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .with_context(|| format!("Invalid profile path {path:?}"))?
+        .to_owned();
+
+    TaskBuilder::with_id(task_id, format!("Import local profile {name}"))
+        .kind(tasks::Kind::Aggregate)
+        .progress_unit(tasks::ProgressUnit::Bytes)
+        .run_with_handle(Some(app), |handle| async move {
+            let mods = tokio::task::block_in_place(|| r2modman::read_mods(&path))?;
+
+            fetch_mod_index(
+                Some(app),
+                &app.state(),
+                game,
+                false,
+                Some(handle.allocate_dependency(app)?),
+            )
+            .await?;
+
+            let profile_id = crate::profiles::create_profile(game.into(), name.into()).await?;
+
+            let mods =
+                install_mods(app, game, &mods, profile_id, &mod_progress_channel, handle).await;
+
+            let report = finish_import(&log, game, profile_id, mods, Some(&path)).await?;
+            Ok((None, report))
+        })
+        .await
+        .map_err(|e: TaskError<anyhow::Error>| anyhow::Error::from(e).into())
+}
+
+/// Finds the directory that `game` is installed in, unless `path` is given.
+async fn resolve_game_dir(
+    log: &slog::Logger,
+    game: &str,
+    path: Option<PathBuf>,
+) -> anyhow::Result<PathBuf> {
+    if let Some(path) = path {
+        return Ok(path);
+    }
+    let game = *crate::games::games_by_id()?
+        .get(game)
+        .context("No such game")?;
+    crate::launching::find_install_dir(log, game, &Default::default())
+        .await?
+        .with_context(|| format!("{} is not installed", game.name))
+}
+
+/// Recognizes the mods that were installed by hand into the directory of `game`, or `path` if
+/// given, so that the user can review them before [`import_game_dir`].
+#[tauri::command]
+pub async fn scan_game_dir(
+    app: AppHandle,
+    game: &str,
+    path: Option<PathBuf>,
+    task_id: tasks::Id,
+) -> Result<game_dir::GameDirScan, CommandError> {
+    let app = &app;
+    let log = slog_scope::logger();
+
+    let path = resolve_game_dir(&log, game, path).await?;
+
+    TaskBuilder::with_id(task_id, format!("Scan game directory of {game}"))
+        .kind(tasks::Kind::Aggregate)
+        .progress_unit(tasks::ProgressUnit::Bytes)
+        .run_with_handle(Some(app), |handle| async move {
+            fetch_mod_index(
+                Some(app),
+                &app.state(),
+                game,
+                false,
+                Some(handle.allocate_dependency(app)?),
+            )
+            .await?;
+
+            let mod_index = crate::mod_index::read_mod_index(game).await?;
+            let scan = tokio::task::block_in_place(|| game_dir::scan(&path, &mod_index))?;
+
+            Ok((None, scan))
+        })
+        .await
+        .map_err(|e: TaskError<anyhow::Error>| anyhow::Error::from(e).into())
+}
+
+/// Creates a new profile named `name` with the `mods` that the user picked from the result of
+/// [`scan_game_dir`], and copies the config files from the directory of `game`, or `path` if
+/// given.
+#[tauri::command]
+pub async fn import_game_dir(
+    app: AppHandle,
+    game: &str,
+    path: Option<PathBuf>,
+    name: String,
+    mods: Vec<game_dir::DetectedMod>,
+    // ModProgressRegistration, but can't express the lifetime
+    mod_progress_channel: Channel<InvokeResponseBody>,
+    task_id: tasks::Id,
+) -> Result<ImportReport, CommandError> {
+    crate::profiles::read_only::ensure_writable()?;
+
+    let app = &app;
+    let log = slog_scope::logger();
+
+    let path = resolve_game_dir(&log, game, path).await?;
+
+    TaskBuilder::with_id(task_id, format!("Import game directory {path:?}"))
+        .kind(tasks::Kind::Aggregate)
+        .progress_unit(tasks::ProgressUnit::Bytes)
+        .run_with_handle(Some(app), |handle| async move {
+            fetch_mod_index(
+                Some(app),
+                &app.state(),
+                game,
+                false,
+                Some(handle.allocate_dependency(app)?),
+            )
+            .await?;
+
+            let mods = game_dir::profile_mods(&mods)?;
+
+            let profile_id = crate::profiles::create_profile(game.into(), name.into()).await?;
+
+            let mods =
+                install_mods(app, game, &mods, profile_id, &mod_progress_channel, handle).await;
+
+            // the game directory is laid out like the profiles of r2modman, so its configs are
+            // copied the same way
+            let report = finish_import(&log, game, profile_id, mods, Some(&path)).await?;
+            Ok((None, report))
+        })
+        .await
+        .map_err(|e: TaskError<anyhow::Error>| anyhow::Error::from(e).into())
+}
+
 async fn import_onto_profile(
     app: &AppHandle,
-    reqwest: &Reqwest,
     game: &str,
     profile: crate::importing::thunderstore::Profile,
     profile_id: Uuid,
     mod_progress_channel: Channel<InvokeResponseBody>,
     handle: TaskHandle,
-) -> Result<(), anyhow::Error> {
-    let mod_progress_channel = &mod_progress_channel;
-    profile
-        .manifest
-        .mods
-        .iter()
-        .map(|m| {
-            async move {
+) -> Result<Vec<ModImportResult>, anyhow::Error> {
+    let results = install_mods(
+        app,
+        game,
+        &profile.manifest.mods,
+        profile_id,
+        &mod_progress_channel,
+        handle,
+    )
+    .await;
+
+    let profile_path = profile_path(profile_id);
+
+    let rt = tokio::runtime::Handle::current();
+    tokio::task::spawn_blocking(move || {
+        let local_set = tokio::task::LocalSet::new();
+        rt.block_on(local_set.run_until(async move {
+            (0..profile.archive.len())
+                .map(|i| {
+                    let mut archive = profile.archive.clone();
+                    let mut target_path = profile_path.clone();
+                    async move {
+                        tokio::task::spawn_local(async move {
+                            loop {
+                                let file = archive
+                                    .by_index(i)
+                                    .context("Failed to open file in archive")?;
+
+                                if file.is_dir() {
+                                    break;
+                                }
+
+                                if file.is_symlink() {
+                                    bail!("Symlinks are not supported");
+                                }
+
+                                let Some(path) = thunderstore::get_archive_file_path(&file)? else {
+                                    break;
+                                };
+
+                                target_path.push(path);
+
+                                tokio::fs::create_dir_all(target_path.parent().unwrap())
+                                    .await
+                                    .context("Unable to create target file parents")?;
+                                let mut target_file = tokio::fs::File::create(&target_path)
+                                    .await
+                                    .with_context(|| {
+                                    format!("Unable to create target file {:?}", target_path)
+                                })?;
+
+                                tokio::io::copy(
+                                    &mut futures_util::io::AllowStdIo::new(file).compat(),
+                                    &mut target_file,
+                                )
+                                .await
+                                .context("Unable to write target file")?;
+
+                                break;
+                            }
+
+                            Ok::<_, anyhow::Error>(())
+                        })
+                        .await
+                        .map_err(anyhow::Error::from)
+                        .and_then(|r| r)
+                    }
+                })
+                .collect::<FuturesUnordered<_>>()
+                .try_collect::<()>()
+                .await?;
+
+            Ok::<_, anyhow::Error>(())
+        }))
+    })
+    .await??;
+
+    Ok(results)
+}
+
+/// Installs `mods` into the profile, reporting the outcome for each.
+pub async fn install_mods(
+    app: &AppHandle,
+    game: &str,
+    mods: &[thunderstore::ProfileMod],
+    profile_id: Uuid,
+    mod_progress_channel: &Channel<InvokeResponseBody>,
+    handle: TaskHandle,
+) -> Vec<ModImportResult> {
+    let log = slog_scope::logger();
+    let log = &log;
+    // Failures of individual mods are collected rather than aborting the import, so that one
+    // missing or broken mod doesn't cost the user the rest of the modpack.
+    mods.iter()
+        .map(|m| async move {
+            let r: anyhow::Result<()> = async move {
                 let version = Version::try_from(m.version).context("Invalid version")?;
+                let enabled = m.enabled;
 
                 let mod_index = crate::mod_index::read_mod_index(game).await?;
 
@@ -247,13 +549,13 @@ async fn import_onto_profile(
                 )?;
                 crate::profiles::install_profile_mod(
                     app,
-                    reqwest,
                     profile_id,
                     // this is kinda gross
                     ModMetadata {
                         name: &m.metadata.name,
                         owner: &m.metadata.owner,
                         donation_link: m.metadata.donation_link.as_ref().map(|s| (**s).into()),
+                        package_url: None,
                         date_created: m.date_created.into(),
                         is_deprecated: m.is_deprecated,
                         has_nsfw_content: m.has_nsfw_content,
@@ -269,78 +571,42 @@ async fn import_onto_profile(
                         is_active: version.is_active,
                         file_size: version.file_size.into(),
                     },
+                    // the imported profile is expected to work as-is
+                    true,
                     sub_task_id,
                 )
-                .await
+                // warnings are checked once the whole profile has been imported
+                .await?;
+
+                if !enabled {
+                    crate::profiles::batch::set_mod_enabled(
+                        log,
+                        &profile_path(profile_id),
+                        &*m.owner,
+                        &*m.name,
+                        false,
+                    )
+                    .await
+                    .context("Failed to disable the mod")?;
+                }
+                Ok(())
+            }
+            .await;
+            ModImportResult {
+                name: m.full_name.to_string(),
+                version: m.version.to_string(),
+                outcome: match r {
+                    Ok(()) => ModImportOutcome::Installed,
+                    Err(e) => {
+                        slog::warn!(log, "Failed to import mod {}: {e:#}", m.full_name);
+                        ModImportOutcome::Failed {
+                            error: format!("{e:#}"),
+                        }
+                    }
+                },
             }
         })
         .collect::<FuturesUnordered<_>>()
-        .try_collect::<()>()
-        .await?;
-
-    let profile_path = profile_path(profile_id);
-
-    let rt = tokio::runtime::Handle::current();
-    tokio::task::spawn_blocking(move || {
-        let local_set = tokio::task::LocalSet::new();
-        rt.block_on(local_set.run_until(async move {
-            (0..profile.archive.len())
-                .map(|i| {
-                    let mut archive = profile.archive.clone();
-                    let mut target_path = profile_path.clone();
-                    async move {
-                        tokio::task::spawn_local(async move {
-                            loop {
-                                let file = archive
-                                    .by_index(i)
-                                    .context("Failed to open file in archive")?;
-
-                                if file.is_dir() {
-                                    break;
-                                }
-
-                                if file.is_symlink() {
-                                    bail!("Symlinks are not supported");
-                                }
-
-                                let Some(path) = thunderstore::get_archive_file_path(&file)? else {
-                                    break;
-                                };
-
-                                target_path.push(path);
-
-                                tokio::fs::create_dir_all(target_path.parent().unwrap())
-                                    .await
-                                    .context("Unable to create target file parents")?;
-                                let mut target_file = tokio::fs::File::create(&target_path)
-                                    .await
-                                    .with_context(|| {
-                                    format!("Unable to create target file {:?}", target_path)
-                                })?;
-
-                                tokio::io::copy(
-                                    &mut futures_util::io::AllowStdIo::new(file).compat(),
-                                    &mut target_file,
-                                )
-                                .await
-                                .context("Unable to write target file")?;
-
-                                break;
-                            }
-
-                            Ok::<_, anyhow::Error>(())
-                        })
-                        .await
-                        .map_err(anyhow::Error::from)
-                        .and_then(|r| r)
-                    }
-                })
-                .collect::<FuturesUnordered<_>>()
-                .try_collect::<()>()
-                .await?;
-
-            Ok(())
-        }))
-    })
-    .await?
+        .collect::<Vec<_>>()
+        .await
 }
@@ -0,0 +1,308 @@
+//! Importing mods that were installed into a game's directory by hand.
+//!
+//! The mod index has no hashes of the files in packages, so the mods are recognized by the names
+//! of their folders under `BepInEx/plugins` and `BepInEx/patchers`, and by the `manifest.json`
+//! that Thunderstore packages include.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context as _, Result};
+use manderrow_types::mods::ArchivedModRef;
+
+use crate::mod_index::memory::MemoryModIndex;
+use crate::util::IoErrorKindExt as _;
+
+use super::normalize_name;
+use super::thunderstore::{FullName, ProfileMod, Version};
+
+/// The folders of the game directory that mods are installed into.
+const MOD_FOLDERS: &[&str] = &["BepInEx/plugins", "BepInEx/patchers"];
+
+/// A file or folder in one of the [`MOD_FOLDERS`].
+#[derive(Debug, Clone)]
+struct Entry {
+    /// Relative to the game directory.
+    path: String,
+    /// The owner, if the folder is named `Owner-Name` like r2modman names them.
+    owner: Option<String>,
+    name: String,
+    /// The version from the `manifest.json`, if there is one.
+    version: Option<Version>,
+}
+
+#[derive(Debug, Clone, Copy, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Detection {
+    /// The folder is named after the owner and name of the mod.
+    FolderName,
+    /// The `manifest.json` names the mod.
+    Manifest,
+    /// The file or folder has the same name as exactly one mod.
+    Name,
+}
+
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]
+pub struct DetectedMod {
+    pub full_name: String,
+    /// From the `manifest.json`, or the latest version if there is none.
+    pub version: String,
+    pub path: String,
+    pub detection: Detection,
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct GameDirScan {
+    pub path: PathBuf,
+    pub mods: Vec<DetectedMod>,
+    /// The files and folders that no mod was recognized in.
+    pub unrecognized: Vec<String>,
+}
+
+#[derive(serde::Deserialize)]
+struct Manifest {
+    name: String,
+    version_number: String,
+}
+
+fn read_manifest(folder: &Path) -> Result<Option<Manifest>> {
+    let path = folder.join("manifest.json");
+    let bytes = match std::fs::read(&path) {
+        Ok(t) => t,
+        Err(e) if e.is_not_found() => return Ok(None),
+        Err(e) => return Err(e).with_context(|| format!("Failed to read {path:?}")),
+    };
+    // some editors and tools write a byte order mark
+    let bytes = bytes.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(&bytes);
+    Ok(Some(
+        serde_json::from_slice(bytes).with_context(|| format!("Failed to parse {path:?}"))?,
+    ))
+}
+
+fn read_entries(game_dir: &Path) -> Result<Vec<Entry>> {
+    let mut entries = Vec::new();
+    for folder in MOD_FOLDERS {
+        let dir = match std::fs::read_dir(game_dir.join(folder)) {
+            Ok(t) => t,
+            Err(e) if e.is_not_found() => continue,
+            Err(e) => return Err(e).with_context(|| format!("Failed to read {folder}")),
+        };
+        for e in dir {
+            let e = e?;
+            let Ok(file_name) = e.file_name().into_string() else {
+                continue;
+            };
+            let path = format!("{folder}/{file_name}");
+            if !e.file_type()?.is_dir() {
+                if let Some(name) = file_name.strip_suffix(".dll") {
+                    entries.push(Entry {
+                        path,
+                        owner: None,
+                        name: name.to_owned(),
+                        version: None,
+                    });
+                }
+                continue;
+            }
+            let manifest = read_manifest(&e.path())?;
+            let (owner, name) = match file_name.split_once('-') {
+                Some((owner, name)) => (Some(owner.to_owned()), name.to_owned()),
+                None => (None, file_name),
+            };
+            entries.push(Entry {
+                path,
+                owner,
+                // the manifest is more reliable than whatever the folder was renamed to
+                name: manifest.as_ref().map_or(name, |m| m.name.clone()),
+                version: manifest.and_then(|m| m.version_number.parse().ok()),
+            });
+        }
+    }
+    Ok(entries)
+}
+
+/// Recognizes the mods installed into `game_dir`, except for BepInEx itself, which Manderrow
+/// manages.
+pub fn scan(game_dir: &Path, mod_index: &MemoryModIndex) -> Result<GameDirScan> {
+    let entries = read_entries(game_dir)?;
+
+    let mut by_name = HashMap::<String, Vec<&ArchivedModRef>>::new();
+    for chunk in &mod_index.chunks {
+        for m in chunk.mods().iter() {
+            by_name.entry(normalize_name(&m.name)).or_default().push(m);
+        }
+    }
+
+    let mut mods = Vec::<DetectedMod>::new();
+    let mut unrecognized = Vec::new();
+    for entry in entries {
+        let candidates = by_name
+            .get(&normalize_name(&entry.name))
+            .map_or(&[][..], Vec::as_slice);
+        let has_version = |m: &&&ArchivedModRef| {
+            entry.version.is_none_or(|v| {
+                m.versions
+                    .iter()
+                    .any(|mv| mv.version_number.get().components() == (v.major, v.minor, v.patch))
+            })
+        };
+        let found = match &entry.owner {
+            Some(owner) => candidates
+                .iter()
+                .find(|m| m.owner.eq_ignore_ascii_case(owner))
+                .map(|m| (m, Detection::FolderName)),
+            None => None,
+        }
+        .or_else(|| {
+            // several mods may share a name, so the version from the manifest picks between them
+            let mut matching = candidates.iter().filter(has_version);
+            match (matching.next(), matching.next()) {
+                (Some(m), None) => Some((
+                    m,
+                    if entry.version.is_some() {
+                        Detection::Manifest
+                    } else {
+                        Detection::Name
+                    },
+                )),
+                _ => None,
+            }
+        });
+        let Some((m, detection)) = found else {
+            unrecognized.push(entry.path);
+            continue;
+        };
+        if &*m.owner == "BepInEx" && &*m.name == "BepInExPack" {
+            continue;
+        }
+        let full_name = format!("{}-{}", &*m.owner, &*m.name);
+        if mods.iter().any(|other| other.full_name == full_name) {
+            continue;
+        }
+        let version = match entry.version {
+            Some(v) => v.to_string(),
+            None => match m.versions.first() {
+                Some(v) => v.version_number.get().to_string(),
+                None => {
+                    unrecognized.push(entry.path);
+                    continue;
+                }
+            },
+        };
+        mods.push(DetectedMod {
+            full_name,
+            version,
+            path: entry.path,
+            detection,
+        });
+    }
+
+    Ok(GameDirScan {
+        path: game_dir.to_owned(),
+        mods,
+        unrecognized,
+    })
+}
+
+/// Returns the `mods` that the user picked from a [`GameDirScan`] in the form that profiles are
+/// imported from.
+pub fn profile_mods(mods: &[DetectedMod]) -> Result<Vec<ProfileMod>> {
+    mods.iter()
+        .map(|m| {
+            let (owner, name) = m
+                .full_name
+                .split_once('-')
+                .with_context(|| format!("Invalid full name {:?}", m.full_name))?;
+            Ok(ProfileMod {
+                full_name: FullName::new(owner, name),
+                version: m.version.parse().map_err(anyhow::Error::msg)?,
+                enabled: true,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_entries() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(read_entries(dir.path()).unwrap().is_empty());
+
+        let plugins = dir.path().join("BepInEx/plugins");
+        std::fs::create_dir_all(plugins.join("RiskofThunder-HookGenPatcher")).unwrap();
+        std::fs::create_dir_all(plugins.join("Renamed")).unwrap();
+        std::fs::write(
+            plugins.join("Renamed/manifest.json"),
+            "\u{FEFF}{\"name\": \"R2API\", \"version_number\": \"5.0.1\"}",
+        )
+        .unwrap();
+        std::fs::write(plugins.join("Loose.dll"), "").unwrap();
+        std::fs::write(plugins.join("README.md"), "").unwrap();
+
+        let mut entries = read_entries(dir.path()).unwrap();
+        entries.sort_by(|a, b| a.path.cmp(&b.path));
+        let summary = entries
+            .iter()
+            .map(|e| {
+                (
+                    &*e.path,
+                    e.owner.as_deref(),
+                    &*e.name,
+                    e.version.map(|v| v.to_string()),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            summary,
+            [
+                ("BepInEx/plugins/Loose.dll", None, "Loose", None),
+                (
+                    "BepInEx/plugins/Renamed",
+                    None,
+                    "R2API",
+                    Some("5.0.1".to_owned())
+                ),
+                (
+                    "BepInEx/plugins/RiskofThunder-HookGenPatcher",
+                    Some("RiskofThunder"),
+                    "HookGenPatcher",
+                    None
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_profile_mods() {
+        let detected = |full_name: &str, version: &str| DetectedMod {
+            full_name: full_name.to_owned(),
+            version: version.to_owned(),
+            path: format!("BepInEx/plugins/{full_name}"),
+            detection: Detection::FolderName,
+        };
+
+        let mods = profile_mods(&[
+            detected("notnotnotswipez-MoreCompany", "1.11.0"),
+            detected("RiskofThunder-HookGenPatcher", "1.2.3"),
+        ])
+        .unwrap();
+        let summary = mods
+            .iter()
+            .map(|m| (&*m.full_name, m.version.to_string(), m.enabled))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            summary,
+            [
+                ("notnotnotswipez-MoreCompany", "1.11.0".to_owned(), true),
+                ("RiskofThunder-HookGenPatcher", "1.2.3".to_owned(), true),
+            ]
+        );
+
+        assert!(profile_mods(&[]).unwrap().is_empty());
+        assert!(profile_mods(&[detected("MoreCompany", "1.11.0")]).is_err());
+        assert!(profile_mods(&[detected("notnotnotswipez-MoreCompany", "latest")]).is_err());
+    }
+}
@@ -1,2 +1,13 @@
 pub mod commands;
+pub mod game_dir;
+pub mod r2modman;
 pub mod thunderstore;
+
+/// Reduces the name of a mod or game to the letters and digits in it, in lowercase, as mod
+/// managers and users tend to drop or change the rest.
+fn normalize_name(name: &str) -> String {
+    name.chars()
+        .filter(char::is_ascii_alphanumeric)
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
@@ -0,0 +1,189 @@
+//! Importing profiles that r2modman and Thunderstore Mod Manager have stored on this computer.
+//!
+//! Each of their profiles is a folder under `<data folder>/<game>/profiles`, containing a
+//! `mods.yml` that lists the installed mods and the files of a BepInEx installation.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context as _, Result};
+use slog::debug;
+
+use crate::games::games;
+use crate::profiles::CONFIG_FOLDER;
+use crate::util::IoErrorKindExt as _;
+
+use super::normalize_name;
+use super::thunderstore::ProfileMod;
+
+/// The file in each profile that lists its mods.
+const MODS_FILE_NAME: &str = "mods.yml";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Manager {
+    R2modman,
+    ThunderstoreModManager,
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct LocalProfile {
+    pub manager: Manager,
+    /// The id of the game, or `None` if the game is not known to Manderrow.
+    pub game: Option<&'static str>,
+    pub name: String,
+    pub path: PathBuf,
+    pub mod_count: usize,
+}
+
+/// Returns the folders that the mod managers may store their data in.
+fn data_dirs() -> Vec<(Manager, PathBuf)> {
+    let mut dirs = Vec::new();
+    // both are Electron apps, which keep their data beside ours
+    if let Some(app_data) = manderrow_paths::config_dir().parent() {
+        dirs.push((Manager::R2modman, app_data.join("r2modmanPlus-local")));
+        if cfg!(windows) {
+            dirs.push((
+                Manager::ThunderstoreModManager,
+                app_data.join("Thunderstore Mod Manager").join("DataFolder"),
+            ));
+        }
+    }
+    if cfg!(target_os = "linux") {
+        dirs.push((
+            Manager::R2modman,
+            manderrow_paths::home_dir()
+                .join(".var/app/com.github.ebkr.r2modman/config/r2modmanPlus-local"),
+        ));
+    }
+    dirs
+}
+
+/// Finds the game that the mod managers store in `folder`. They name the folders after the game,
+/// without spaces or punctuation.
+fn find_game(folder: &str) -> Result<Option<&'static str>> {
+    let folder = normalize_name(folder);
+    Ok(games()?
+        .iter()
+        .find(|game| {
+            [game.id, game.thunderstore_id, &*game.name]
+                .into_iter()
+                .any(|name| normalize_name(name) == folder)
+        })
+        .map(|game| game.id))
+}
+
+/// Reads the mods of the profile at `path`, except for BepInEx, which Manderrow manages itself.
+pub fn read_mods(path: &Path) -> Result<Vec<ProfileMod>> {
+    let path = path.join(MODS_FILE_NAME);
+    let text = match std::fs::read_to_string(&path) {
+        Ok(t) => t,
+        // the file is only created once a mod is installed
+        Err(e) if e.is_not_found() => return Ok(Vec::new()),
+        Err(e) => return Err(e).with_context(|| format!("Failed to read {path:?}")),
+    };
+    if text.trim().is_empty() {
+        return Ok(Vec::new());
+    }
+    let mut mods = serde_yaml::from_str::<Vec<ProfileMod>>(&text)
+        .with_context(|| format!("Failed to parse {path:?}"))?;
+    mods.retain(|m| &*m.full_name != "BepInEx-BepInExPack");
+    Ok(mods)
+}
+
+fn read_dir_if_exists(path: &Path) -> Result<Option<std::fs::ReadDir>> {
+    match std::fs::read_dir(path) {
+        Ok(t) => Ok(Some(t)),
+        Err(e) if e.is_not_found() => Ok(None),
+        Err(e) => Err(e).with_context(|| format!("Failed to read {path:?}")),
+    }
+}
+
+/// Lists the profiles of every mod manager's data folder.
+pub fn scan_profiles(log: &slog::Logger) -> Result<Vec<LocalProfile>> {
+    let mut profiles = Vec::new();
+    for (manager, dir) in data_dirs() {
+        let Some(game_dirs) = read_dir_if_exists(&dir)? else {
+            continue;
+        };
+        for game_dir in game_dirs {
+            let game_dir = game_dir?;
+            let Some(entries) = read_dir_if_exists(&game_dir.path().join("profiles"))? else {
+                continue;
+            };
+            let game = match game_dir.file_name().to_str() {
+                Some(folder) => find_game(folder)?,
+                None => None,
+            };
+            for e in entries {
+                let e = e?;
+                if !e.file_type()?.is_dir() {
+                    continue;
+                }
+                let Ok(name) = e.file_name().into_string() else {
+                    continue;
+                };
+                let path = e.path();
+                match read_mods(&path) {
+                    Ok(mods) => profiles.push(LocalProfile {
+                        manager,
+                        game,
+                        name,
+                        path,
+                        mod_count: mods.len(),
+                    }),
+                    Err(e) => debug!(log, "Skipping profile at {path:?}: {e:#}"),
+                }
+            }
+        }
+    }
+    Ok(profiles)
+}
+
+/// Copies the config files of the mod manager profile at `source` into the Manderrow profile at
+/// `target`, returning the number of files copied.
+pub fn copy_configs(source: &Path, target: &Path) -> Result<usize> {
+    let source = source.join("BepInEx").join("config");
+    if !source.exists() {
+        return Ok(0);
+    }
+    let target = target.join(CONFIG_FOLDER);
+    let mut count = 0;
+    for e in walkdir::WalkDir::new(&source) {
+        let e = e?;
+        if !e.file_type().is_file() {
+            continue;
+        }
+        let path = target.join(e.path().strip_prefix(&source)?);
+        std::fs::create_dir_all(path.parent().unwrap())?;
+        std::fs::copy(e.path(), &path)
+            .with_context(|| format!("Failed to copy {:?} to {path:?}", e.path()))?;
+        count += 1;
+    }
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_mods() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(read_mods(dir.path()).unwrap().is_empty());
+
+        std::fs::write(
+            dir.path().join(MODS_FILE_NAME),
+            "- manifestVersion: 1\n  name: BepInEx-BepInExPack\n  authorName: BepInEx\n  \
+            versionNumber:\n    major: 5\n    minor: 4\n    patch: 2100\n  enabled: true\n\
+            - manifestVersion: 1\n  name: RiskofThunder-HookGenPatcher\n  \
+            authorName: RiskofThunder\n  versionNumber:\n    major: 1\n    minor: 2\n    \
+            patch: 3\n  enabled: false\n",
+        )
+        .unwrap();
+        let mods = read_mods(dir.path()).unwrap();
+        assert_eq!(mods.len(), 1);
+        assert_eq!(&*mods[0].full_name, "RiskofThunder-HookGenPatcher");
+        assert_eq!(mods[0].version.to_string(), "1.2.3");
+        assert!(!mods[0].enabled);
+    }
+}
@@ -15,11 +15,8 @@ use triomphe::Arc;
 use uuid::Uuid;
 use zip::read::ZipFile;
 
+use crate::profiles::{CONFIG_FOLDER, PATCHERS_FOLDER};
 use crate::{installing::fetch_resource_as_bytes, profiles::MODS_FOLDER, tasks};
-use crate::{
-    profiles::{CONFIG_FOLDER, PATCHERS_FOLDER},
-    Reqwest,
-};
 
 #[derive(Clone)]
 pub struct FullName {
@@ -28,6 +25,13 @@ pub struct FullName {
 }
 
 impl FullName {
+    pub fn new(namespace: &str, name: &str) -> Self {
+        Self {
+            value: format!("{namespace}-{name}"),
+            split: namespace.len(),
+        }
+    }
+
     pub fn namespace(&self) -> &str {
         &self.value[..self.split]
     }
@@ -129,13 +133,56 @@ impl serde::Serialize for FullName {
 
 // TODO: replace with a custom deserializer instead of needing two layers of validation
 #[derive(Debug, Clone, Copy, Deserialize, Serialize)]
-#[serde(rename_all = "camelCase")]
+#[serde(rename_all = "camelCase", try_from = "VersionRepr")]
 pub struct Version {
     pub major: u64,
     pub minor: u64,
     pub patch: u64,
 }
 
+/// Older versions of r2modman wrote versions as strings rather than as objects.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum VersionRepr {
+    Object { major: u64, minor: u64, patch: u64 },
+    String(String),
+}
+
+impl TryFrom<VersionRepr> for Version {
+    type Error = String;
+
+    fn try_from(value: VersionRepr) -> Result<Self, Self::Error> {
+        match value {
+            VersionRepr::Object {
+                major,
+                minor,
+                patch,
+            } => Ok(Self {
+                major,
+                minor,
+                patch,
+            }),
+            VersionRepr::String(s) => s.parse(),
+        }
+    }
+}
+
+impl std::str::FromStr for Version {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut iter = s.split('.').map(str::parse::<u64>);
+        match (iter.next(), iter.next(), iter.next(), iter.next()) {
+            (Some(Ok(major)), Some(Ok(minor)), Some(Ok(patch)), None) => Ok(Self {
+                major,
+                minor,
+                patch,
+            }),
+            _ => Err(format!("Invalid version {s:?}")),
+        }
+    }
+}
+
 impl std::fmt::Display for Version {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
@@ -159,34 +206,115 @@ pub struct Profile {
 #[derive(Debug, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ProfileManifest {
+    #[serde(alias = "name")]
     pub profile_name: String,
     pub mods: Vec<ProfileMod>,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct ProfileMod {
-    #[serde(rename = "name")]
+    #[serde(rename = "name", alias = "fullName", alias = "full_name")]
     pub full_name: FullName,
-    #[serde(alias = "versionNumber")]
+    #[serde(alias = "versionNumber", alias = "version_number")]
     pub version: Version,
+    /// Legacy manifests omit this, and only listed enabled mods.
+    #[serde(default = "default_enabled")]
     pub enabled: bool,
 }
 
-const R2_PROFILE_DATA_PREFIX: &str = "#r2modman\n";
+fn default_enabled() -> bool {
+    true
+}
+
+pub const R2_PROFILE_DATA_PREFIX: &str = "#r2modman";
 
 pub const R2_PROFILE_MANIFEST_FILE_NAME: &str = "export.r2x";
 
+/// Manifest file names used by older versions of r2modman, checked in order after
+/// [`R2_PROFILE_MANIFEST_FILE_NAME`].
+///
+/// `mods.yml` is not one of them, as it is r2modman's bare list of a profile's mods, not a manifest.
+const LEGACY_PROFILE_MANIFEST_FILE_NAMES: &[&str] = &["export.yml", "export.yaml"];
+
+const ZIP_MAGIC: &[u8] = b"PK\x03\x04";
+
+/// The encoding of a profile code payload.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PayloadFormat {
+    /// `#r2modman` followed by a base64 encoded zip archive. This is what current versions of
+    /// r2modman produce.
+    Prefixed,
+    /// A base64 encoded zip archive without the `#r2modman` header.
+    Base64,
+    /// A zip archive that has not been encoded at all.
+    Zip,
+}
+
+/// Works out how `bytes` is encoded, returning the format and the zip archive it contains.
+fn decode_payload(bytes: &[u8]) -> Result<(PayloadFormat, Vec<u8>)> {
+    if bytes.starts_with(ZIP_MAGIC) {
+        return Ok((PayloadFormat::Zip, bytes.to_vec()));
+    }
+
+    let (format, encoded) = match bytes.strip_prefix(R2_PROFILE_DATA_PREFIX.as_bytes()) {
+        Some(rest) => (PayloadFormat::Prefixed, rest),
+        None => (PayloadFormat::Base64, bytes),
+    };
+    // line breaks are tolerated, as some versions wrapped the encoded data
+    let encoded = encoded
+        .iter()
+        .copied()
+        .filter(|b| !b.is_ascii_whitespace())
+        .collect::<Vec<_>>();
+    if encoded.is_empty() {
+        bail!("Profile data is empty");
+    }
+
+    let mut buf = Vec::new();
+    if let Err(e) =
+        base64::read::DecoderReader::new(std::io::Cursor::new(&encoded), &BASE64_STANDARD)
+            .read_to_end(&mut buf)
+    {
+        if format == PayloadFormat::Prefixed {
+            return Err(anyhow::Error::from(e).context("Failed to decode base64 profile data"));
+        }
+        bail!(
+            "Unrecognized profile data format: expected an r2modman profile code, a base64 \
+            encoded profile archive, or a profile archive"
+        );
+    }
+    ensure!(
+        buf.starts_with(ZIP_MAGIC),
+        "Profile data decoded successfully, but does not contain a profile archive"
+    );
+    Ok((format, buf))
+}
+
+fn read_manifest(
+    archive: &mut zip::ZipArchive<std::io::Cursor<Arc<[u8]>>>,
+) -> Result<ProfileManifest> {
+    let name = std::iter::once(R2_PROFILE_MANIFEST_FILE_NAME)
+        .chain(LEGACY_PROFILE_MANIFEST_FILE_NAMES.iter().copied())
+        .find(|name| archive.index_for_name(name).is_some())
+        .with_context(|| {
+            format!(
+                "Profile archive is missing manifest file (expected {R2_PROFILE_MANIFEST_FILE_NAME})"
+            )
+        })?;
+    let file = archive.by_name(name)?;
+    serde_yaml::from_reader(file)
+        .with_context(|| format!("Failed to parse profile manifest {name}"))
+}
+
 pub async fn lookup_profile(
     app: Option<&AppHandle>,
     log: &slog::Logger,
-    reqwest: &Reqwest,
     id: Uuid,
     task_id: Option<tasks::Id>,
 ) -> Result<Profile> {
     let bytes = fetch_resource_as_bytes(
         app,
         log,
-        reqwest,
         format!("Profile {id}"),
         &format!("https://thunderstore.io/api/experimental/legacyprofile/get/{id}/"),
         Some(crate::installing::CacheOptions::by_url().with_suffix(".r2z")),
@@ -195,26 +323,13 @@ pub async fn lookup_profile(
     .await?;
 
     tokio::task::block_in_place(move || {
-        let Some((prefix, bytes)) = bytes.split_at_checked(R2_PROFILE_DATA_PREFIX.len()) else {
-            bail!("Invalid profile data")
-        };
-        ensure!(
-            prefix == R2_PROFILE_DATA_PREFIX.as_bytes(),
-            "Invalid profile data"
-        );
-
-        let mut buf = Vec::new();
-        base64::read::DecoderReader::new(std::io::Cursor::new(bytes), &BASE64_STANDARD)
-            .read_to_end(&mut buf)
-            .context("Failed to decode base64 data")?;
+        let (format, buf) = decode_payload(&bytes)?;
+        slog::debug!(log, "Profile {id} is in {format:?} format");
 
-        let mut archive = zip::ZipArchive::new(std::io::Cursor::new(Arc::from(buf)))?;
+        let mut archive = zip::ZipArchive::new(std::io::Cursor::new(Arc::from(buf)))
+            .context("Failed to open profile archive")?;
 
-        let manifest_file = archive
-            .by_name("export.r2x")
-            .context("Profile archive is missing manifest file")?;
-
-        let mut manifest: ProfileManifest = serde_yaml::from_reader(manifest_file)?;
+        let mut manifest = read_manifest(&mut archive)?;
 
         while let Some(i) = manifest
             .mods
@@ -257,3 +372,117 @@ pub fn get_archive_file_path<R: Read>(file: &ZipFile<'_, R>) -> Result<Option<Pa
 
     Ok(Some(path.into_owned()))
 }
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write as _;
+
+    use base64::Engine as _;
+
+    use super::*;
+
+    /// A profile archive with the manifest `manifest` named `name`.
+    fn archive(name: &str, manifest: &str) -> Vec<u8> {
+        let mut writer = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+        writer
+            .start_file(name, zip::write::SimpleFileOptions::default())
+            .unwrap();
+        writer.write_all(manifest.as_bytes()).unwrap();
+        writer
+            .start_file(
+                "BepInEx/config/Mod.cfg",
+                zip::write::SimpleFileOptions::default(),
+            )
+            .unwrap();
+        writer.finish().unwrap().into_inner()
+    }
+
+    fn read(buf: Vec<u8>) -> Result<ProfileManifest> {
+        read_manifest(&mut zip::ZipArchive::new(std::io::Cursor::new(Arc::from(buf))).unwrap())
+    }
+
+    const MANIFEST: &str = "\
+profileName: Modded
+mods:
+  - name: Owner-Name
+    version:
+      major: 1
+      minor: 2
+      patch: 3
+    enabled: false
+";
+
+    /// How older versions of r2modman wrote manifests, with string versions and only the enabled
+    /// mods.
+    const LEGACY_MANIFEST: &str = "\
+name: Modded
+mods:
+  - fullName: Owner-Name
+    versionNumber: 1.2.3
+";
+
+    #[test]
+    fn test_decode_payload() {
+        let zip = archive(R2_PROFILE_MANIFEST_FILE_NAME, MANIFEST);
+        let encoded = BASE64_STANDARD.encode(&zip);
+
+        let prefixed = format!("{R2_PROFILE_DATA_PREFIX}{encoded}");
+        assert_eq!(
+            decode_payload(prefixed.as_bytes()).unwrap(),
+            (PayloadFormat::Prefixed, zip.clone())
+        );
+        assert_eq!(
+            decode_payload(encoded.as_bytes()).unwrap(),
+            (PayloadFormat::Base64, zip.clone())
+        );
+        // wrapped by some versions
+        let (head, tail) = encoded.split_at(encoded.len() / 2);
+        assert_eq!(
+            decode_payload(format!("{head}\n{tail}\n").as_bytes()).unwrap(),
+            (PayloadFormat::Base64, zip.clone())
+        );
+        assert_eq!(
+            decode_payload(&zip).unwrap(),
+            (PayloadFormat::Zip, zip.clone())
+        );
+
+        assert!(decode_payload(R2_PROFILE_DATA_PREFIX.as_bytes()).is_err());
+        assert!(decode_payload(b"not a profile").is_err());
+        // valid base64, but not of an archive
+        assert!(decode_payload(BASE64_STANDARD.encode(b"text").as_bytes()).is_err());
+    }
+
+    #[test]
+    fn test_read_manifest() {
+        let manifest = read(archive(R2_PROFILE_MANIFEST_FILE_NAME, MANIFEST)).unwrap();
+        assert_eq!(manifest.profile_name, "Modded");
+        assert_eq!(manifest.mods.len(), 1);
+        let m = &manifest.mods[0];
+        assert_eq!(m.full_name.components(), ("Owner", "Name"));
+        assert_eq!(m.version.to_string(), "1.2.3");
+        assert!(!m.enabled);
+
+        for name in LEGACY_PROFILE_MANIFEST_FILE_NAMES {
+            let manifest = read(archive(name, LEGACY_MANIFEST)).unwrap();
+            assert_eq!(manifest.profile_name, "Modded");
+            assert_eq!(manifest.mods.len(), 1);
+            let m = &manifest.mods[0];
+            assert_eq!(m.full_name.components(), ("Owner", "Name"));
+            assert_eq!(m.version.to_string(), "1.2.3");
+            assert!(m.enabled);
+        }
+
+        assert!(read(archive("mods.yml", "- Owner-Name-1.2.3\n")).is_err());
+    }
+
+    #[test]
+    fn test_version_repr() {
+        for input in ["{major: 1, minor: 2, patch: 3}", "1.2.3", "'1.2.3'"] {
+            let version = serde_yaml::from_str::<Version>(input).unwrap();
+            assert_eq!(version.to_string(), "1.2.3", "{input}");
+        }
+        for input in ["1.2", "1.2.3.4", "a.b.c", "{major: 1}"] {
+            assert!(serde_yaml::from_str::<Version>(input).is_err(), "{input}");
+        }
+    }
+}
@@ -0,0 +1,195 @@
+//! Extracting zip archives on several threads at once.
+//!
+//! Large packages, like BepInEx packs, contain thousands of files, which take a long time to
+//! extract one after another.
+
+use std::fs::File;
+use std::io::{Read, Seek};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use anyhow::{bail, Context as _, Result};
+use parking_lot::Mutex;
+use zip::ZipArchive;
+
+/// The most threads that a single archive is extracted with.
+const MAX_THREADS: usize = 8;
+
+struct Entry {
+    index: usize,
+    path: PathBuf,
+    size: u64,
+}
+
+/// Extracts the archive returned by `open` into `target`, calling `progress` with the number of
+/// bytes extracted so far and the total number of bytes after each file.
+///
+/// `open` is called once for each thread, as every thread needs its own reader.
+pub fn extract<R: Read + Seek>(
+    open: impl Fn() -> Result<ZipArchive<R>> + Sync,
+    target: &Path,
+    progress: impl Fn(u64, u64) -> Result<()> + Sync,
+) -> Result<()> {
+    let mut archive = open()?;
+
+    let mut entries = Vec::new();
+    for index in 0..archive.len() {
+        let file = archive.by_index_raw(index)?;
+        let Some(rel_path) = file.enclosed_name() else {
+            bail!("Unsafe path {:?} in zip archive", file.name());
+        };
+        if file.is_symlink() {
+            drop(file);
+            // symlinks are rare in packages, so leave them to the sequential implementation
+            archive.extract(target)?;
+            return Ok(());
+        }
+        let path = target.join(rel_path);
+        if file.is_dir() {
+            std::fs::create_dir_all(&path)?;
+        } else {
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            entries.push(Entry {
+                index,
+                path,
+                size: file.size(),
+            });
+        }
+    }
+
+    let total = entries.iter().map(|e| e.size).sum::<u64>();
+    let threads = std::thread::available_parallelism()
+        .map_or(1, usize::from)
+        .min(MAX_THREADS)
+        .min(entries.len())
+        .max(1);
+    drop(archive);
+
+    // hand out the largest files first, each to the thread with the least to extract so far, so
+    // that the threads finish at about the same time
+    entries.sort_unstable_by_key(|e| std::cmp::Reverse(e.size));
+    let mut buckets = (0..threads).map(|_| (0u64, Vec::new())).collect::<Vec<_>>();
+    for entry in entries {
+        let (size, bucket) = buckets.iter_mut().min_by_key(|(size, _)| *size).unwrap();
+        *size += entry.size;
+        bucket.push(entry);
+    }
+
+    let open = &open;
+    let progress = &progress;
+    let done = &Mutex::new(0u64);
+    let failed = &AtomicBool::new(false);
+    std::thread::scope(|s| {
+        let handles = buckets
+            .into_iter()
+            .map(|(_, bucket)| {
+                s.spawn(move || {
+                    let result = extract_bucket(open, &bucket, failed, |size| {
+                        // hold the lock while reporting so that progress never goes backwards
+                        let mut done = done.lock();
+                        *done += size;
+                        progress(*done, total)
+                    });
+                    if result.is_err() {
+                        failed.store(true, Ordering::Relaxed);
+                    }
+                    result
+                })
+            })
+            .collect::<Vec<_>>();
+        for handle in handles {
+            handle
+                .join()
+                .unwrap_or_else(|e| std::panic::resume_unwind(e))?;
+        }
+        Ok(())
+    })
+}
+
+/// Extracts `entries` with a reader of its own, stopping early once another thread has failed.
+fn extract_bucket<R: Read + Seek>(
+    open: impl Fn() -> Result<ZipArchive<R>>,
+    entries: &[Entry],
+    failed: &AtomicBool,
+    mut on_extracted: impl FnMut(u64) -> Result<()>,
+) -> Result<()> {
+    let mut archive = open()?;
+    for entry in entries {
+        if failed.load(Ordering::Relaxed) {
+            break;
+        }
+        extract_file(&mut archive, entry)?;
+        on_extracted(entry.size)?;
+    }
+    Ok(())
+}
+
+fn extract_file<R: Read + Seek>(archive: &mut ZipArchive<R>, entry: &Entry) -> Result<()> {
+    let mut file = archive.by_index(entry.index)?;
+    let mut out =
+        File::create(&entry.path).with_context(|| format!("Failed to create {:?}", entry.path))?;
+    // allocating the whole file up front avoids growing it over and over while writing
+    out.set_len(entry.size)?;
+    std::io::copy(&mut file, &mut out)
+        .with_context(|| format!("Failed to extract {:?}", entry.path))?;
+    #[cfg(unix)]
+    if let Some(mode) = file.unix_mode() {
+        use std::os::unix::fs::PermissionsExt as _;
+        out.set_permissions(std::fs::Permissions::from_mode(mode & 0o777))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Cursor, Write as _};
+
+    use zip::write::SimpleFileOptions;
+
+    use super::*;
+
+    #[test]
+    fn test_extract() {
+        let mut zip = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        zip.add_directory("BepInEx/plugins/", SimpleFileOptions::default())
+            .unwrap();
+        for i in 0..32 {
+            zip.start_file(
+                format!("BepInEx/plugins/Plugin{i}.dll"),
+                SimpleFileOptions::default(),
+            )
+            .unwrap();
+            zip.write_all(&vec![i as u8; i * 100]).unwrap();
+        }
+        zip.start_file("manifest.json", SimpleFileOptions::default())
+            .unwrap();
+        zip.write_all(b"{}").unwrap();
+        let bytes = zip.finish().unwrap().into_inner();
+
+        let target = tempfile::tempdir().unwrap();
+        let last = Mutex::new((0, 0));
+        extract(
+            || Ok(ZipArchive::new(Cursor::new(&bytes))?),
+            target.path(),
+            |done, total| {
+                *last.lock() = (done, total);
+                Ok(())
+            },
+        )
+        .unwrap();
+
+        let total = (0..32).map(|i| i * 100).sum::<u64>() + 2;
+        assert_eq!(*last.lock(), (total, total));
+        for i in 0..32 {
+            let buf = std::fs::read(target.path().join(format!("BepInEx/plugins/Plugin{i}.dll")))
+                .unwrap();
+            assert_eq!(buf, vec![i as u8; i * 100]);
+        }
+        assert_eq!(
+            std::fs::read(target.path().join("manifest.json")).unwrap(),
+            b"{}"
+        );
+    }
+}
@@ -0,0 +1,171 @@
+//! Mirrors of the Thunderstore CDN that packages can be downloaded from instead.
+//!
+//! Requests for URLs on [`DEFAULT_CDN`] are sent to the configured mirrors and the default CDN,
+//! preferring those that have been responding quickest. A host that fails repeatedly is only
+//! tried after the others until it has had time to recover. Since the URL passed in is always
+//! the one on the default CDN, caching by URL is unaffected by which host served a download.
+
+use std::sync::LazyLock;
+use std::time::{Duration, Instant};
+
+use anyhow::{Context as _, Result};
+use parking_lot::Mutex;
+use slog::{debug, warn};
+
+use crate::util::faults;
+
+pub const DEFAULT_CDN: &str = "https://gcdn.thunderstore.io";
+
+/// After this many consecutive failures, a host is tried last until [`FAILURE_COOLDOWN`] has
+/// passed.
+const FAILURE_THRESHOLD: u32 = 3;
+const FAILURE_COOLDOWN: Duration = Duration::from_secs(5 * 60);
+
+/// How much a new latency measurement counts towards a host's average latency.
+const LATENCY_WEIGHT: f64 = 0.3;
+
+struct Host {
+    base: String,
+    consecutive_failures: u32,
+    last_failure: Option<Instant>,
+    /// Moving average of the time taken to receive response headers.
+    latency: Option<Duration>,
+}
+
+impl Host {
+    fn new(base: String) -> Self {
+        Self {
+            base,
+            consecutive_failures: 0,
+            last_failure: None,
+            latency: None,
+        }
+    }
+
+    fn is_failing(&self, now: Instant) -> bool {
+        self.consecutive_failures >= FAILURE_THRESHOLD
+            && self
+                .last_failure
+                .is_some_and(|t| now.duration_since(t) < FAILURE_COOLDOWN)
+    }
+}
+
+static HOSTS: LazyLock<Mutex<Vec<Host>>> =
+    LazyLock::new(|| Mutex::new(vec![Host::new(DEFAULT_CDN.to_owned())]));
+
+/// Parses the mirrors setting, a whitespace- or comma-separated list of base URLs.
+fn parse_mirrors(setting: &str) -> impl Iterator<Item = &str> {
+    setting
+        .split(|c: char| c.is_whitespace() || c == ',')
+        .filter(|s| !s.is_empty())
+        .map(|s| s.trim_end_matches('/'))
+}
+
+/// Replaces the configured mirrors. The default CDN is always kept, after the mirrors, and the
+/// statistics of hosts that remain configured are preserved.
+pub fn set_mirrors(setting: &str) {
+    let mut hosts = HOSTS.lock();
+    let mut old = std::mem::take(&mut *hosts);
+    for base in parse_mirrors(setting).chain(std::iter::once(DEFAULT_CDN)) {
+        if hosts.iter().any(|h| h.base == base) {
+            continue;
+        }
+        hosts.push(match old.iter().position(|h| h.base == base) {
+            Some(i) => old.swap_remove(i),
+            None => Host::new(base.to_owned()),
+        });
+    }
+}
+
+/// Returns the bases of the hosts to try, best first.
+fn ranked_hosts() -> Vec<String> {
+    let now = Instant::now();
+    let hosts = HOSTS.lock();
+    let mut ranked = hosts.iter().enumerate().collect::<Vec<_>>();
+    // hosts without a measurement yet are tried in the configured order before the others
+    ranked.sort_by_key(|(i, h)| (h.is_failing(now), h.latency.unwrap_or(Duration::ZERO), *i));
+    ranked.into_iter().map(|(_, h)| h.base.clone()).collect()
+}
+
+fn report(base: &str, outcome: Result<Duration, ()>) {
+    let mut hosts = HOSTS.lock();
+    let Some(host) = hosts.iter_mut().find(|h| h.base == base) else {
+        // the mirrors were changed during the request
+        return;
+    };
+    match outcome {
+        Ok(latency) => {
+            host.consecutive_failures = 0;
+            host.latency = Some(match host.latency {
+                Some(avg) => avg.mul_f64(1.0 - LATENCY_WEIGHT) + latency.mul_f64(LATENCY_WEIGHT),
+                None => latency,
+            });
+        }
+        Err(()) => {
+            host.consecutive_failures += 1;
+            host.last_failure = Some(Instant::now());
+        }
+    }
+}
+
+/// Sends a GET request for `url`, which is served from the best available mirror if it is on
+/// the default CDN. Fails with the last error if no host could serve it.
+pub async fn get(log: &slog::Logger, url: &str) -> Result<reqwest::Response> {
+    faults::inject("HTTP request").await?;
+
+    let Some(path) = url.strip_prefix(DEFAULT_CDN) else {
+        return Ok(crate::util::http::client()
+            .get(url)
+            .send()
+            .await?
+            .error_for_status()?);
+    };
+
+    let mut last_error = None;
+    for base in ranked_hosts() {
+        let mirror_url = format!("{base}{path}");
+        let start = Instant::now();
+        match crate::util::http::client()
+            .get(&mirror_url)
+            .send()
+            .await
+            .and_then(|resp| resp.error_for_status())
+        {
+            Ok(resp) => {
+                report(&base, Ok(start.elapsed()));
+                if base != DEFAULT_CDN {
+                    debug!(log, "Downloading {url:?} from mirror {base:?}");
+                }
+                return Ok(resp);
+            }
+            Err(e) => {
+                // a missing package is not the host's fault
+                if e.status() != Some(reqwest::StatusCode::NOT_FOUND) {
+                    report(&base, Err(()));
+                }
+                warn!(log, "Failed to download {mirror_url:?}: {e}");
+                last_error = Some(e);
+            }
+        }
+    }
+    Err(last_error.context("No hosts to download from")?.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_mirrors() {
+        assert_eq!(
+            parse_mirrors(" https://a.example/, https://b.example\nhttps://c.example ")
+                .collect::<Vec<_>>(),
+            [
+                "https://a.example",
+                "https://b.example",
+                "https://c.example"
+            ]
+        );
+        assert_eq!(parse_mirrors("").count(), 0);
+    }
+}
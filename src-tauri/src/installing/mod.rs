@@ -3,7 +3,11 @@
 //! Never make changes to `IndexEntryV*` or [`Index`] variants. Make a new version instead.
 
 pub mod commands;
+mod extract;
 mod index;
+pub mod mirrors;
+pub mod pruning;
+pub mod queue;
 
 use std::ffi::OsString;
 use std::io::Write;
@@ -26,13 +30,13 @@ use tempfile::TempDir;
 use tokio::io::{AsyncReadExt as _, AsyncWriteExt as _};
 use trie_rs::TrieBuilder;
 use walkdir::WalkDir;
-use zip::{result::ZipError, ZipArchive};
+use zip::ZipArchive;
 
 use crate::tasks::{self, SuccessInfo, TaskBuilder, TaskHandle};
-use crate::util::{IoErrorKindExt, UsizeExt};
-use crate::Reqwest;
+use crate::util::http::ResponseExt as _;
+use crate::util::{faults, IoErrorKindExt, UsizeExt};
 
-const INDEX_FILE_NAME: &str = ".manderrow_content_index";
+pub const INDEX_FILE_NAME: &str = ".manderrow_content_index";
 
 #[derive(Debug, Clone, Copy, serde::Deserialize, serde::Serialize)]
 pub enum Status {
@@ -70,7 +74,7 @@ pub enum ScanError {
     Internal(#[source] anyhow::Error),
 }
 
-fn hash_file(path: &Path) -> std::io::Result<blake3::Hash> {
+pub fn hash_file(path: &Path) -> std::io::Result<blake3::Hash> {
     Ok(blake3::Hasher::new().update_mmap(&path)?.finalize())
 }
 
@@ -257,6 +261,39 @@ async fn scan_installed_package_for_changes_with_index_buf<'i>(
     Ok(index)
 }
 
+/// Reads the index of the package at `path`, returning the files that came with the package,
+/// relative to `path`, and their hashes. Returns `None` if the package has no index.
+pub async fn read_package_files(path: &Path) -> Result<Option<HashMap<PathBuf, blake3::Hash>>> {
+    let buf = match tokio::fs::read(path.join(INDEX_FILE_NAME)).await {
+        Ok(t) => t,
+        Err(e) if e.is_not_found() => return Ok(None),
+        Err(e) => return Err(ScanError::ReadIndexError(e).into()),
+    };
+    let index = rkyv::access::<ArchivedIndex, rkyv::rancor::Error>(&buf)
+        .map_err(ScanError::InvalidIndexError)?;
+    let mut files = HashMap::new();
+    match index {
+        ArchivedIndex::V1(entries) => {
+            for (path, entry) in entries.iter() {
+                if let ArchivedIndexEntryV1::File { hash } = entry {
+                    files.insert(
+                        path.0.iter().map(|c| c.as_str()).collect(),
+                        blake3::Hash::from_bytes(*hash),
+                    );
+                }
+            }
+        }
+        ArchivedIndex::V2(entries) => {
+            for (path, entry) in entries.iter() {
+                if let ArchivedIndexEntryV1::File { hash } = entry {
+                    files.insert(path.components().collect(), blake3::Hash::from_bytes(*hash));
+                }
+            }
+        }
+    }
+    Ok(Some(files))
+}
+
 async fn generate_package_index(log: &slog::Logger, path: &Path) -> Result<()> {
     debug!(log, "Generating package index for {path:?}");
 
@@ -377,6 +414,7 @@ pub enum AtomicReplaceError {
     },
     #[error("Failed to delete the original: {cause}. Remnants may be found at {deletion_path:?}.")]
     CleanUp {
+        target: PathBuf,
         deletion_path: PathBuf,
         #[source]
         cause: std::io::Error,
@@ -442,7 +480,7 @@ impl ReplaceTransaction {
     pub async fn commit(self, log: &slog::Logger) -> Result<(), AtomicReplaceError> {
         let mut this = ManuallyDrop::new(self);
         debug!(log, "committing replacement at {:?}", this.target);
-        let _target = std::mem::take(&mut this.target);
+        let target = std::mem::take(&mut this.target);
         let previous = std::mem::take(&mut this.previous);
         if let Some(previous) = previous {
             // The replacement has succeeded. Delete the original.
@@ -452,6 +490,7 @@ impl ReplaceTransaction {
                 tokio::fs::remove_file(&previous.deletion_path).await
             } {
                 return Err(AtomicReplaceError::CleanUp {
+                    target,
                     deletion_path: previous.deletion_path,
                     cause,
                 });
@@ -508,7 +547,12 @@ async fn replace(target: &Path, source: &Path) -> Result<ReplaceTransaction, Ato
                     }
                 })?;
             // Move the original to a hidden file just in case replacing it fails.
-            if let Err(cause) = tokio::fs::rename(target, &deletion_path).await {
+            if let Err(cause) = async {
+                faults::inject("staging for deletion").await?;
+                tokio::fs::rename(target, &deletion_path).await
+            }
+            .await
+            {
                 return Err(AtomicReplaceError::StageForDeletion {
                     target: target.to_owned(),
                     deletion_path,
@@ -525,7 +569,12 @@ async fn replace(target: &Path, source: &Path) -> Result<ReplaceTransaction, Ato
     };
     // If this fails, we will likely fail to restore the original, so don't
     // bother trying. Just let the user know where to find it.
-    if let Err(cause) = tokio::fs::rename(&source, &target).await {
+    if let Err(cause) = async {
+        faults::inject("moving into place").await?;
+        tokio::fs::rename(&source, &target).await
+    }
+    .await
+    {
         return Err(AtomicReplaceError::MoveReplacement {
             source: source.to_owned(),
             target: target.to_owned(),
@@ -593,6 +642,7 @@ pub enum CacheKey<'a> {
 pub struct CacheOptions<'a> {
     key: CacheKey<'a>,
     suffix: &'a str,
+    force_download: bool,
 }
 
 impl<'a> CacheOptions<'a> {
@@ -600,6 +650,7 @@ impl<'a> CacheOptions<'a> {
         Self {
             key: CacheKey::Hash(hash),
             suffix: "",
+            force_download: false,
         }
     }
 
@@ -607,6 +658,7 @@ impl<'a> CacheOptions<'a> {
         Self {
             key: CacheKey::Url,
             suffix: "",
+            force_download: false,
         }
     }
 
@@ -614,17 +666,44 @@ impl<'a> CacheOptions<'a> {
         self.suffix = suffix;
         self
     }
+
+    /// Downloads the resource even if it is cached, replacing the cached copy.
+    pub fn force_download(mut self, force_download: bool) -> Self {
+        self.force_download = force_download;
+        self
+    }
+
+    /// Returns where the resource at `url` is cached.
+    fn path(&self, url: &str) -> PathBuf {
+        match self.key {
+            CacheKey::Hash(hash_str) => {
+                let mut path = cache_dir().join(hash_str);
+                path.as_mut_os_string().push(self.suffix);
+                path
+            }
+            CacheKey::Url => url_cache_path(url, self.suffix),
+        }
+    }
 }
 
 pub enum FetchedResource {
-    File(PathBuf),
+    /// A cached file, along with whether it was already cached.
+    File(PathBuf, Option<SuccessInfo>),
     Bytes(BytesMut),
 }
 
+impl FetchedResource {
+    pub fn success_info(&self) -> Option<SuccessInfo> {
+        match self {
+            Self::File(_, success) => *success,
+            Self::Bytes(_) => None,
+        }
+    }
+}
+
 pub async fn fetch_resource<'a>(
     app: Option<&AppHandle>,
     log: &slog::Logger,
-    reqwest: &Reqwest,
     title: String,
     url: &str,
     cache: Option<CacheOptions<'_>>,
@@ -634,18 +713,27 @@ pub async fn fetch_resource<'a>(
         Some(CacheOptions {
             key: CacheKey::Hash(hash_str),
             suffix,
-        }) => {
-            fetch_resource_cached_by_hash(app, log, reqwest, title, url, hash_str, suffix, task_id)
-                .await
-                .map(FetchedResource::File)
-        }
+            force_download,
+        }) => fetch_resource_cached_by_hash(
+            app,
+            log,
+            title,
+            url,
+            hash_str,
+            suffix,
+            force_download,
+            task_id,
+        )
+        .await
+        .map(|(path, success)| FetchedResource::File(path, success)),
         Some(CacheOptions {
             key: CacheKey::Url,
             suffix,
-        }) => fetch_resource_cached_by_url(app, log, reqwest, title, url, suffix, task_id)
+            force_download,
+        }) => fetch_resource_cached_by_url(app, log, title, url, suffix, force_download, task_id)
             .await
-            .map(FetchedResource::File),
-        None => fetch_resource_uncached(app, log, reqwest, title, url, task_id)
+            .map(|(path, success)| FetchedResource::File(path, success)),
+        None => fetch_resource_uncached(app, log, title, url, task_id)
             .await
             .map(FetchedResource::Bytes),
     }
@@ -654,7 +742,6 @@ pub async fn fetch_resource<'a>(
 pub async fn fetch_resource_uncached<'a>(
     app: Option<&AppHandle>,
     log: &slog::Logger,
-    reqwest: &Reqwest,
     title: String,
     url: &str,
     task_id: Option<tasks::Id>,
@@ -667,36 +754,39 @@ pub async fn fetch_resource_uncached<'a>(
         .run_with_handle(app, |handle| async move {
             debug!(log, "Fetching resource from {url:?} without caching");
 
-            let mut resp = reqwest.get(url).send().await?.error_for_status()?;
-            let len = resp.content_length();
-            let bytes = if let Some(len) = len {
-                let len = usize::try_from(len).context("Too large to fit in memory")?;
-                let mut bytes = BytesMut::with_capacity(len);
-                let mut total = 0;
-                while let Some(chunk) = resp.chunk().await? {
-                    bytes.extend_from_slice(&chunk);
-                    if let Some(app) = app {
-                        total += chunk.len();
-                        handle.send_progress_manually(app, total.as_u64(), len.as_u64())?;
+            let bytes = queue::run(log, app, handle, async || {
+                let mut resp = mirrors::get(log, url).await?;
+                let len = resp.content_length();
+                if let Some(len) = len {
+                    let len = usize::try_from(len).context("Too large to fit in memory")?;
+                    let mut bytes = BytesMut::with_capacity(len);
+                    let mut total = 0;
+                    while let Some(chunk) = resp.next_chunk().await? {
+                        bytes.extend_from_slice(&chunk);
+                        if let Some(app) = app {
+                            total += chunk.len();
+                            handle.send_progress_manually(app, total.as_u64(), len.as_u64())?;
+                        }
                     }
-                }
-                bytes
-            } else {
-                let mut buf = Vec::new();
-                let mut total = 0;
-                while let Some(chunk) = resp.chunk().await? {
-                    if let Some(app) = app {
-                        total += chunk.len();
-                        handle.send_progress_manually(app, total.as_u64(), 0)?;
+                    Ok(bytes)
+                } else {
+                    let mut buf = Vec::new();
+                    let mut total = 0;
+                    while let Some(chunk) = resp.next_chunk().await? {
+                        if let Some(app) = app {
+                            total += chunk.len();
+                            handle.send_progress_manually(app, total.as_u64(), 0)?;
+                        }
+                        buf.push(chunk);
                     }
-                    buf.push(chunk);
-                }
-                let mut bytes = BytesMut::with_capacity(total);
-                for chunk in buf {
-                    bytes.extend_from_slice(&chunk);
+                    let mut bytes = BytesMut::with_capacity(total);
+                    for chunk in buf {
+                        bytes.extend_from_slice(&chunk);
+                    }
+                    Ok(bytes)
                 }
-                bytes
-            };
+            })
+            .await?;
 
             Ok::<_, anyhow::Error>((None, bytes))
         })
@@ -707,31 +797,40 @@ pub async fn fetch_resource_uncached<'a>(
 pub async fn fetch_resource_cached_by_hash(
     app: Option<&AppHandle>,
     log: &slog::Logger,
-    reqwest: &Reqwest,
     title: String,
     url: &str,
     hash_str: &str,
     suffix: &str,
+    force_download: bool,
     task_id: Option<tasks::Id>,
-) -> Result<PathBuf> {
+) -> Result<(PathBuf, Option<SuccessInfo>)> {
     let mut path = cache_dir().join(hash_str);
     path.as_mut_os_string().push(suffix);
 
-    fetch_resource_cached_by_hash_at_path(app, log, reqwest, title, url, hash_str, &path, task_id)
-        .await?;
-    Ok(path)
+    let success = fetch_resource_cached_by_hash_at_path(
+        app,
+        log,
+        title,
+        url,
+        hash_str,
+        &path,
+        force_download,
+        task_id,
+    )
+    .await?;
+    Ok((path, success))
 }
 
 pub async fn fetch_resource_cached_by_hash_at_path(
     app: Option<&AppHandle>,
     log: &slog::Logger,
-    reqwest: &Reqwest,
     title: String,
     url: &str,
     hash_str: &str,
     path: &Path,
+    force_download: bool,
     task_id: Option<tasks::Id>,
-) -> Result<()> {
+) -> Result<Option<SuccessInfo>> {
     TaskBuilder::with_id(task_id.unwrap_or_else(tasks::allocate_task), title)
         .kind(tasks::Kind::Download { url: url.to_owned() })
         .progress_unit(tasks::ProgressUnit::Bytes)
@@ -739,7 +838,9 @@ pub async fn fetch_resource_cached_by_hash_at_path(
             debug!(log, "Fetching resource from {url:?} cached by hash");
 
             let hash = blake3::Hash::from_hex(hash_str)?;
-            let hash_on_disk = {
+            let hash_on_disk = if force_download {
+                None
+            } else {
                 let mut hsr = blake3::Hasher::new();
                 match hsr.update_mmap(&path) {
                     Ok(_) => Some(hsr.finalize()),
@@ -748,22 +849,26 @@ pub async fn fetch_resource_cached_by_hash_at_path(
                 }
             };
             let success = if hash_on_disk.map(|h| h != hash).unwrap_or(true) {
-                let mut resp = reqwest.get(url).send().await?.error_for_status()?;
-                tokio::fs::create_dir_all(cache_dir()).await?;
-                // TODO: should this be buffered?
-                let mut wtr = tokio::fs::File::create(&path).await?;
-                let mut written = 0u64;
-                let len = resp.content_length();
-                if let (Some(app), Some(total)) = (app, len) {
-                    handle.send_progress_manually(app, written, total)?;
-                }
-                while let Some(chunk) = resp.chunk().await? {
-                    wtr.write_all(&chunk).await?;
-                    if let Some(app) = app {
-                        written += chunk.len().as_u64();
-                        handle.send_progress_manually(app, written, len.unwrap_or(0))?;
+                queue::run(log, app, handle, async || {
+                    let mut resp = mirrors::get(log, url).await?;
+                    tokio::fs::create_dir_all(cache_dir()).await?;
+                    // TODO: should this be buffered?
+                    let mut wtr = tokio::fs::File::create(&path).await?;
+                    let mut written = 0u64;
+                    let len = resp.content_length();
+                    if let (Some(app), Some(total)) = (app, len) {
+                        handle.send_progress_manually(app, written, total)?;
                     }
-                }
+                    while let Some(chunk) = resp.next_chunk().await? {
+                        wtr.write_all(&chunk).await?;
+                        if let Some(app) = app {
+                            written += chunk.len().as_u64();
+                            handle.send_progress_manually(app, written, len.unwrap_or(0))?;
+                        }
+                    }
+                    Ok(())
+                })
+                .await?;
                 let hash_on_disk = {
                     let mut hsr = blake3::Hasher::new();
                     hsr.update_mmap(&path)?;
@@ -781,7 +886,7 @@ pub async fn fetch_resource_cached_by_hash_at_path(
                 report_progress_from_file_metadata(app, handle, metadata)?;
                 Some(SuccessInfo::Cached)
             };
-            Ok::<_, anyhow::Error>((success, ()))
+            Ok::<_, anyhow::Error>((success, success))
         })
         .await
         .map_err(Into::into)
@@ -808,15 +913,24 @@ fn report_progress_from_file_metadata(
     Ok(())
 }
 
+/// Returns where the resource at `url` is cached by [`fetch_resource_cached_by_url`].
+pub fn url_cache_path(url: &str, suffix: &str) -> PathBuf {
+    let mut path = cache_dir().join("url.");
+    path.as_mut_os_string()
+        .push(base64::engine::general_purpose::URL_SAFE.encode(url));
+    path.as_mut_os_string().push(suffix);
+    path
+}
+
 pub async fn fetch_resource_cached_by_url(
     app: Option<&AppHandle>,
     log: &slog::Logger,
-    reqwest: &Reqwest,
     title: String,
     url: &str,
     suffix: &str,
+    force_download: bool,
     task_id: Option<tasks::Id>,
-) -> Result<PathBuf> {
+) -> Result<(PathBuf, Option<SuccessInfo>)> {
     TaskBuilder::with_id(task_id.unwrap_or_else(tasks::allocate_task), title)
         .kind(tasks::Kind::Download {
             url: url.to_owned(),
@@ -825,49 +939,58 @@ pub async fn fetch_resource_cached_by_url(
         .run_with_handle(app, |handle| async move {
             debug!(log, "Fetching resource from {url:?} cached by url");
 
-            let mut path = cache_dir().join("url.");
-            path.as_mut_os_string()
-                .push(base64::engine::general_purpose::URL_SAFE.encode(url));
-            path.as_mut_os_string().push(suffix);
-            let success = match tokio::fs::metadata(&path).await {
+            let path = url_cache_path(url, suffix);
+            let metadata = if force_download {
+                debug!(log, "Ignoring any cached copy of the resource");
+                Err(std::io::ErrorKind::NotFound.into())
+            } else {
+                tokio::fs::metadata(&path).await
+            };
+            let success = match metadata {
                 Ok(metadata) => {
                     debug!(log, "Resource is cached at {path:?}");
+                    pruning::touch(log, &path);
                     report_progress_from_file_metadata(app, handle, metadata)?;
                     Some(SuccessInfo::Cached)
                 }
                 Err(e) if e.is_not_found() => {
                     tokio::fs::create_dir_all(cache_dir()).await?;
 
-                    let (tmp_file, tmp_path) = tokio::task::block_in_place(|| {
-                        tempfile::NamedTempFile::new_in(
-                            path.parent().context("path must have a parent")?,
-                        )
-                        .map_err(anyhow::Error::from)
-                    })?
-                    .into_parts();
+                    let tmp_path = queue::run(log, app, handle, async || {
+                        let (tmp_file, tmp_path) = tokio::task::block_in_place(|| {
+                            tempfile::NamedTempFile::new_in(
+                                path.parent().context("path must have a parent")?,
+                            )
+                            .map_err(anyhow::Error::from)
+                        })?
+                        .into_parts();
 
-                    let mut resp = reqwest.get(url).send().await?.error_for_status()?;
+                        let mut resp = mirrors::get(log, url).await?;
 
-                    let tmp_file = tokio::fs::File::from_std(tmp_file);
+                        let tmp_file = tokio::fs::File::from_std(tmp_file);
 
-                    let len = resp.content_length();
-                    if let Some(len) = len {
-                        tmp_file.allocate(len).await?;
-                    }
+                        let len = resp.content_length();
+                        if let Some(len) = len {
+                            tmp_file.allocate(len).await?;
+                        }
 
-                    // TODO: should this be buffered?
-                    let mut wtr = tmp_file;
-                    let mut written = 0u64;
-                    if let (Some(app), Some(total)) = (app, len) {
-                        handle.send_progress_manually(app, written, total)?;
-                    }
-                    while let Some(chunk) = resp.chunk().await? {
-                        wtr.write_all(&chunk).await?;
-                        if let Some(app) = app {
-                            written += chunk.len().as_u64();
-                            handle.send_progress_manually(app, written, len.unwrap_or(0))?;
+                        // TODO: should this be buffered?
+                        let mut wtr = tmp_file;
+                        let mut written = 0u64;
+                        if let (Some(app), Some(total)) = (app, len) {
+                            handle.send_progress_manually(app, written, total)?;
                         }
-                    }
+                        while let Some(chunk) = resp.next_chunk().await? {
+                            wtr.write_all(&chunk).await?;
+                            if let Some(app) = app {
+                                written += chunk.len().as_u64();
+                                handle.send_progress_manually(app, written, len.unwrap_or(0))?;
+                            }
+                        }
+
+                        Ok(tmp_path)
+                    })
+                    .await?;
 
                     let tmp_path = tmp_path.keep()?;
                     tokio::fs::rename(&tmp_path, &path)
@@ -880,7 +1003,7 @@ pub async fn fetch_resource_cached_by_url(
                 }
                 Err(e) => return Err(e.into()),
             };
-            Ok::<_, anyhow::Error>((success, path))
+            Ok::<_, anyhow::Error>((success, (path, success)))
         })
         .await
         .map_err(Into::into)
@@ -889,14 +1012,13 @@ pub async fn fetch_resource_cached_by_url(
 pub async fn fetch_resource_as_bytes<'a>(
     app: Option<&AppHandle>,
     log: &slog::Logger,
-    reqwest: &Reqwest,
     title: String,
     url: &str,
     cache: Option<CacheOptions<'_>>,
     task_id: Option<tasks::Id>,
 ) -> Result<BytesMut> {
-    match fetch_resource(app, log, reqwest, title, url, cache, task_id).await? {
-        FetchedResource::File(path_buf) => {
+    match fetch_resource(app, log, title, url, cache, task_id).await? {
+        FetchedResource::File(path_buf, _) => {
             Ok(Bytes::from(tokio::fs::read(&path_buf).await?).into())
         }
         FetchedResource::Bytes(bytes) => Ok(bytes),
@@ -904,16 +1026,20 @@ pub async fn fetch_resource_as_bytes<'a>(
 }
 
 /// Downloads a zip file from `url` and extracts it into a temporary directory *beside* the `target` directory.
+///
+/// The extraction is reported as a separate task with `extract_task_id`.
+///
+/// Returns [`SuccessInfo::Cached`] alongside the directory if the zip was already cached.
 pub async fn prepare_install_zip<'a>(
     app: Option<&AppHandle>,
     log: &slog::Logger,
-    reqwest: &Reqwest,
     title: String,
     url: &str,
     cache: Option<CacheOptions<'_>>,
     target: &'a Path,
     task_id: Option<tasks::Id>,
-) -> anyhow::Result<TempDir> {
+    extract_task_id: Option<tasks::Id>,
+) -> anyhow::Result<(TempDir, Option<SuccessInfo>)> {
     let cache = cache.map(|c| c.with_suffix(".zip"));
 
     let target_parent = target
@@ -926,32 +1052,54 @@ pub async fn prepare_install_zip<'a>(
 
     let temp_dir = tempfile::tempdir_in(target_parent)?;
 
-    match fetch_resource(app, log, reqwest, title, url, cache, task_id).await? {
-        FetchedResource::Bytes(bytes) => {
-            tokio::task::block_in_place(|| {
-                let mut archive = ZipArchive::new(std::io::Cursor::new(bytes))?;
-                archive.extract(temp_dir.path())?;
-                Ok::<_, ZipError>(())
-            })?;
-        }
-        FetchedResource::File(path) => {
-            tokio::task::block_in_place(|| {
-                let mut archive =
-                    ZipArchive::new(std::io::BufReader::new(std::fs::File::open(&path)?))?;
-                archive.extract(temp_dir.path())?;
-                Ok::<_, ZipError>(())
-            })?;
-        }
-    }
+    // the cached copy must not be evicted before it has been extracted
+    let _pin = cache.as_ref().map(|c| pruning::pin(c.path(url)));
+
+    let extract_title = format!("Extract {title}");
+    let resource = fetch_resource(app, log, title, url, cache, task_id).await?;
+    let success = resource.success_info();
+    let temp_path = temp_dir.path();
+    TaskBuilder::with_id(
+        extract_task_id.unwrap_or_else(tasks::allocate_task),
+        extract_title,
+    )
+    .progress_unit(tasks::ProgressUnit::Bytes)
+    .run_with_handle(app, |handle| async move {
+        let progress = |done, total| match app {
+            Some(app) => handle.send_progress_manually(app, done, total),
+            None => Ok(()),
+        };
+        faults::inject("extraction").await?;
+        tokio::task::block_in_place(|| match resource {
+            FetchedResource::Bytes(bytes) => {
+                let bytes = bytes.freeze();
+                extract::extract(
+                    || Ok(ZipArchive::new(std::io::Cursor::new(bytes.clone()))?),
+                    temp_path,
+                    progress,
+                )
+            }
+            FetchedResource::File(path, _) => extract::extract(
+                || {
+                    Ok(ZipArchive::new(std::io::BufReader::new(
+                        std::fs::File::open(&path)?,
+                    ))?)
+                },
+                temp_path,
+                progress,
+            ),
+        })?;
+        Ok::<_, anyhow::Error>((None, ()))
+    })
+    .await?;
 
-    Ok(temp_dir)
+    Ok((temp_dir, success))
 }
 
 /// Downloads a zip file from `url` and installs it into the `target` directory.
 pub async fn install_zip<'a>(
     app: Option<&AppHandle>,
     log: &slog::Logger,
-    reqwest: &Reqwest,
     title: String,
     url: &str,
     cache: Option<CacheOptions<'_>>,
@@ -960,8 +1108,8 @@ pub async fn install_zip<'a>(
 ) -> anyhow::Result<StagedPackage<'a, 'static>> {
     debug!(log, "Installing zip from {url:?} to {target:?}");
 
-    let temp_dir =
-        prepare_install_zip(app, log, reqwest, title, url, cache, target, task_id).await?;
+    let (temp_dir, _) =
+        prepare_install_zip(app, log, title, url, cache, target, task_id, None).await?;
 
     let staged = install_folder(log, temp_dir.path(), target).await?;
 
@@ -1048,7 +1196,6 @@ pub async fn create_dir_if_not_exists(path: &Path) -> anyhow::Result<()> {
 pub async fn install_file<'a>(
     app: Option<&AppHandle>,
     log: &slog::Logger,
-    reqwest: &Reqwest,
     title: String,
     url: &str,
     cache: Option<CacheOptions<'_>>,
@@ -1067,12 +1214,12 @@ pub async fn install_file<'a>(
 
     let mut temp_file = tempfile::NamedTempFile::new_in(target_parent)?;
     let temp_path;
-    match fetch_resource(app, log, reqwest, title, url, cache, task_id).await? {
+    match fetch_resource(app, log, title, url, cache, task_id).await? {
         FetchedResource::Bytes(bytes) => {
             tokio::task::block_in_place(|| temp_file.write_all(&bytes))?;
             temp_path = temp_file.into_temp_path();
         }
-        FetchedResource::File(path) => {
+        FetchedResource::File(path, _) => {
             temp_path = temp_file.into_temp_path();
             tokio::fs::copy(&path, &temp_path).await?;
         }
@@ -0,0 +1,270 @@
+//! Evicting the zips of mod packages and loaders, and mod icons, from the cache once they are older
+//! than the configured maximum age, and the least recently used of them while they take up more
+//! than the configured maximum size.
+//!
+//! Cached files are touched whenever they are used, so their modification time is when they were
+//! last used. Files that are pinned by installs in progress are never evicted.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::LazyLock;
+use std::time::{Duration, SystemTime};
+
+use anyhow::Result;
+use base64::Engine as _;
+use manderrow_paths::cache_dir;
+use parking_lot::Mutex;
+use slog::{debug, info, warn};
+
+use crate::util::IoErrorKindExt as _;
+
+/// How long after startup the cache is first pruned.
+const INITIAL_DELAY: Duration = Duration::from_secs(60);
+const PRUNE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Bytes, or 0 for no limit.
+static MAX_SIZE: AtomicU64 = AtomicU64::new(0);
+/// Seconds, or 0 for no limit.
+static MAX_AGE: AtomicU64 = AtomicU64::new(0);
+
+/// The number of pins of each cached file.
+static PINNED: LazyLock<Mutex<HashMap<PathBuf, usize>>> = LazyLock::new(Default::default);
+
+pub fn set_max_size(mib: u64) {
+    MAX_SIZE.store(mib.saturating_mul(1024 * 1024), Ordering::Relaxed);
+}
+
+pub fn set_max_age(days: u64) {
+    MAX_AGE.store(days.saturating_mul(24 * 60 * 60), Ordering::Relaxed);
+}
+
+/// Keeps the cached file from being evicted until dropped.
+pub struct Pin {
+    path: PathBuf,
+}
+
+impl Drop for Pin {
+    fn drop(&mut self) {
+        let mut pinned = PINNED.lock();
+        if let Some(count) = pinned.get_mut(&self.path) {
+            *count -= 1;
+            if *count == 0 {
+                pinned.remove(&self.path);
+            }
+        }
+    }
+}
+
+pub fn pin(path: PathBuf) -> Pin {
+    *PINNED.lock().entry(path.clone()).or_default() += 1;
+    Pin { path }
+}
+
+/// Records that the cached file at `path` was just used.
+pub fn touch(log: &slog::Logger, path: &Path) {
+    if let Err(e) = std::fs::File::options()
+        .write(true)
+        .open(path)
+        .and_then(|file| file.set_modified(SystemTime::now()))
+    {
+        debug!(log, "Failed to touch cached file at {path:?}: {e}");
+    }
+}
+
+/// Returns `true` if the file is a zip or an icon cached by URL. Anything else in the cache is
+/// managed elsewhere.
+fn is_prunable(file_name: &str) -> bool {
+    let Some(rest) = file_name.strip_prefix("url.") else {
+        return false;
+    };
+    // mod packages are cached with a suffix, but loaders and icons are cached without one
+    if rest.ends_with(".zip") {
+        return true;
+    }
+    base64::engine::general_purpose::URL_SAFE
+        .decode(rest)
+        .is_ok_and(|url| url.ends_with(b".zip") || url.ends_with(b".png"))
+}
+
+struct Candidate {
+    path: PathBuf,
+    size: u64,
+    last_used: SystemTime,
+}
+
+/// Returns the indices of the `candidates` to evict, oldest first. `candidates` must be sorted by
+/// when they were last used, oldest first. Pinned candidates are never selected, but still count
+/// towards the total size.
+fn select(
+    candidates: &[Candidate],
+    now: SystemTime,
+    max_size: u64,
+    max_age: u64,
+    is_pinned: impl Fn(&Path) -> bool,
+) -> Vec<usize> {
+    let mut total = candidates.iter().map(|c| c.size).sum::<u64>();
+    let mut evict = Vec::new();
+    for (i, c) in candidates.iter().enumerate() {
+        if is_pinned(&c.path) {
+            continue;
+        }
+        let too_old = max_age != 0
+            && now
+                .duration_since(c.last_used)
+                .is_ok_and(|age| age.as_secs() > max_age);
+        let too_large = max_size != 0 && total > max_size;
+        if !too_old && !too_large {
+            // the rest were used more recently
+            break;
+        }
+        total -= c.size;
+        evict.push(i);
+    }
+    evict
+}
+
+/// Evicts the files that exceed the limits. Returns the number of files and bytes evicted.
+pub fn prune(log: &slog::Logger) -> Result<(usize, u64)> {
+    let max_size = MAX_SIZE.load(Ordering::Relaxed);
+    let max_age = MAX_AGE.load(Ordering::Relaxed);
+    if max_size == 0 && max_age == 0 {
+        return Ok((0, 0));
+    }
+
+    let dir = match std::fs::read_dir(cache_dir()) {
+        Ok(t) => t,
+        Err(e) if e.is_not_found() => return Ok((0, 0)),
+        Err(e) => return Err(e.into()),
+    };
+    let mut candidates = Vec::new();
+    for e in dir {
+        let e = e?;
+        if !e.file_name().to_str().is_some_and(is_prunable) {
+            continue;
+        }
+        let metadata = e.metadata()?;
+        if !metadata.is_file() {
+            continue;
+        }
+        candidates.push(Candidate {
+            path: e.path(),
+            size: metadata.len(),
+            last_used: metadata.modified()?,
+        });
+    }
+    candidates.sort_by_key(|c| c.last_used);
+
+    let (mut count, mut freed) = (0, 0);
+    let is_pinned = |path: &Path| PINNED.lock().contains_key(path);
+    for i in select(&candidates, SystemTime::now(), max_size, max_age, is_pinned) {
+        let c = &candidates[i];
+        // held while removing, so that the file can't be pinned in the meantime
+        let pinned = PINNED.lock();
+        // it may have been pinned since it was selected
+        if pinned.contains_key(&c.path) {
+            continue;
+        }
+        match std::fs::remove_file(&c.path) {
+            Ok(()) => {
+                count += 1;
+                freed += c.size;
+            }
+            Err(e) if e.is_not_found() => {}
+            Err(e) => warn!(log, "Failed to evict {:?} from the cache: {e}", c.path),
+        }
+    }
+    Ok((count, freed))
+}
+
+/// Periodically prunes the cache, unless background activity is paused.
+pub fn spawn_pruner() {
+    tauri::async_runtime::spawn(async move {
+        let log = slog_scope::logger();
+        tokio::time::sleep(INITIAL_DELAY).await;
+        loop {
+            crate::background::wait_until_resumed().await;
+            let result =
+                crate::background::run_in_background(tauri::async_runtime::spawn_blocking({
+                    let log = log.clone();
+                    move || prune(&log)
+                }))
+                .await;
+            match result {
+                Ok(Ok((0, _))) => {}
+                Ok(Ok((count, freed))) => {
+                    info!(log, "Evicted {count} files ({freed} bytes) from the cache")
+                }
+                Ok(Err(e)) => warn!(log, "Failed to prune the cache: {e:#}"),
+                Err(e) => warn!(log, "Failed to prune the cache: {e}"),
+            }
+            tokio::time::sleep(PRUNE_INTERVAL).await;
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_prunable() {
+        _ = manderrow_paths::init();
+        let cached = |url: &str, suffix: &str| {
+            let path = crate::installing::url_cache_path(url, suffix);
+            is_prunable(path.file_name().unwrap().to_str().unwrap())
+        };
+        assert!(cached(
+            "https://gcdn.thunderstore.io/live/repository/packages/a-b-1.0.0.zip",
+            ".zip"
+        ));
+        assert!(cached(
+            "https://github.com/LavaGang/MelonLoader/releases/download/v0.6.6/MelonLoader.x64.zip",
+            ""
+        ));
+        assert!(cached(
+            "https://gcdn.thunderstore.io/live/repository/icons/a-b-1.0.0.png",
+            ""
+        ));
+        assert!(!cached("https://example.com/README.md", ""));
+        assert!(!cached("https://example.com/profile", ".r2z"));
+        assert!(!is_prunable("game_stats.json"));
+        assert!(!is_prunable("loaders"));
+    }
+
+    #[test]
+    fn test_select() {
+        let now = SystemTime::UNIX_EPOCH + Duration::from_secs(1000);
+        let candidates = [100, 500, 900]
+            .map(|t| Candidate {
+                path: PathBuf::new(),
+                size: 10,
+                last_used: SystemTime::UNIX_EPOCH + Duration::from_secs(t),
+            })
+            .into_iter()
+            .collect::<Vec<_>>();
+        let select = |max_size, max_age| select(&candidates, now, max_size, max_age, |_| false);
+        assert_eq!(select(0, 0), [] as [usize; 0]);
+        assert_eq!(select(0, 200), [0, 1]);
+        assert_eq!(select(15, 0), [0, 1]);
+        assert_eq!(select(20, 0), [0]);
+        assert_eq!(select(25, 600), [0]);
+    }
+
+    #[test]
+    fn test_select_skips_pinned() {
+        let now = SystemTime::UNIX_EPOCH + Duration::from_secs(1000);
+        let candidates = [100, 500, 900]
+            .map(|t| Candidate {
+                path: PathBuf::from(t.to_string()),
+                size: 10,
+                last_used: SystemTime::UNIX_EPOCH + Duration::from_secs(t),
+            })
+            .into_iter()
+            .collect::<Vec<_>>();
+        let is_pinned = |path: &Path| path == Path::new("100");
+        // the pinned file still takes up space, so both of the others have to go
+        assert_eq!(select(&candidates, now, 15, 0, is_pinned), [1, 2]);
+        assert_eq!(select(&candidates, now, 0, 200, is_pinned), [1]);
+    }
+}
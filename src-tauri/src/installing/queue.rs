@@ -0,0 +1,200 @@
+//! The global download queue, which limits how many downloads run at once and retries downloads
+//! that fail transiently.
+//!
+//! Installing a mod with many dependencies would otherwise start every download at once, which
+//! saturates the connection and makes each download more likely to time out.
+
+use std::time::Duration;
+
+use anyhow::Result;
+use parking_lot::Mutex;
+use reqwest::StatusCode;
+use slog::warn;
+use tauri::AppHandle;
+use tokio::sync::{Semaphore, SemaphorePermit};
+
+use crate::tasks::TaskHandle;
+
+pub const DEFAULT_CONCURRENT_DOWNLOADS: usize = 4;
+
+/// How many times a download is attempted before giving up.
+const MAX_ATTEMPTS: u32 = 4;
+/// How long to wait before the first retry. Each retry waits twice as long as the previous one.
+const INITIAL_BACKOFF: Duration = Duration::from_millis(500);
+
+static QUEUE: Queue = Queue::new(DEFAULT_CONCURRENT_DOWNLOADS);
+
+struct Queue {
+    permits: Semaphore,
+    limits: Mutex<Limits>,
+}
+
+struct Limits {
+    limit: usize,
+    /// How many of the permits held by running downloads are over the limit, after it was lowered
+    /// while they were running. They are retired rather than released when the downloads finish.
+    excess: usize,
+}
+
+impl Queue {
+    const fn new(limit: usize) -> Self {
+        Self {
+            permits: Semaphore::const_new(limit),
+            limits: Mutex::new(Limits { limit, excess: 0 }),
+        }
+    }
+
+    fn set_limit(&self, limit: usize) {
+        let limit = limit.max(1);
+        let mut limits = self.limits.lock();
+        let old = std::mem::replace(&mut limits.limit, limit);
+        if limit > old {
+            // running downloads that were over the old limit are within the new one
+            let increase = limit - old;
+            let kept = increase.min(limits.excess);
+            limits.excess -= kept;
+            self.permits.add_permits(increase - kept);
+        } else if limit < old {
+            let decrease = old - limit;
+            let forgotten = self.permits.forget_permits(decrease);
+            limits.excess += decrease - forgotten;
+        }
+    }
+
+    async fn acquire(&self) -> Result<SemaphorePermit<'_>> {
+        Ok(self.permits.acquire().await?)
+    }
+
+    fn release(&self, permit: SemaphorePermit<'_>) {
+        let mut limits = self.limits.lock();
+        if limits.excess > 0 {
+            limits.excess -= 1;
+            permit.forget();
+        } else {
+            drop(permit);
+        }
+    }
+}
+
+/// Changes how many downloads may run at once. Downloads that are already running are not
+/// interrupted when the limit is lowered.
+pub fn set_concurrent_downloads(limit: usize) {
+    QUEUE.set_limit(limit);
+}
+
+/// Returns `true` if `error` may not happen again if the request is retried.
+fn is_transient(error: &anyhow::Error) -> bool {
+    error.chain().any(|e| {
+        let Some(e) = e.downcast_ref::<reqwest::Error>() else {
+            return false;
+        };
+        match e.status() {
+            Some(status) => {
+                status.is_server_error()
+                    || status == StatusCode::REQUEST_TIMEOUT
+                    || status == StatusCode::TOO_MANY_REQUESTS
+            }
+            None => e.is_timeout() || e.is_connect() || e.is_request() || e.is_body(),
+        }
+    })
+}
+
+/// Runs `download` once no more than the configured number of other downloads are running, and,
+/// if it is background activity, once background activity is not paused.
+///
+/// If it fails transiently, it is retried with exponential backoff, so it must start over from
+/// scratch each time it is called. The task's progress is reset before each retry.
+pub async fn run<T>(
+    log: &slog::Logger,
+    app: Option<&AppHandle>,
+    handle: TaskHandle,
+    mut download: impl AsyncFnMut() -> Result<T>,
+) -> Result<T> {
+    let mut backoff = INITIAL_BACKOFF;
+    let mut attempt = 1;
+    loop {
+        if crate::background::is_in_background() {
+            // checked before each attempt, as a download may be retried after a pause
+            crate::background::wait_until_resumed().await;
+        }
+        let permit = QUEUE.acquire().await?;
+        let result = download().await;
+        // let other downloads run during the backoff
+        QUEUE.release(permit);
+        match result {
+            Err(e) if attempt < MAX_ATTEMPTS && is_transient(&e) => {
+                warn!(
+                    log,
+                    "Download failed on attempt {attempt}, retrying in {backoff:?}: {e:#}"
+                );
+                if let Some(app) = app {
+                    handle.send_progress_manually(app, 0, 0)?;
+                }
+                tokio::time::sleep(backoff).await;
+                backoff *= 2;
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn status_error(status: u16) -> anyhow::Error {
+        let resp = reqwest::Response::from(
+            tauri::http::Response::builder()
+                .status(status)
+                .body("")
+                .unwrap(),
+        );
+        anyhow::Error::from(resp.error_for_status().unwrap_err()).context("Failed to download")
+    }
+
+    #[test]
+    fn test_is_transient() {
+        assert!(is_transient(&status_error(503)));
+        assert!(is_transient(&status_error(429)));
+        assert!(is_transient(&status_error(408)));
+        assert!(!is_transient(&status_error(404)));
+        assert!(!is_transient(&status_error(403)));
+
+        let builder_error = reqwest::Client::new().get("not a url").build().unwrap_err();
+        assert!(!is_transient(&builder_error.into()));
+        assert!(!is_transient(&anyhow::anyhow!("Invalid zip archive")));
+    }
+
+    #[test]
+    fn test_set_limit() {
+        let queue = Queue::new(4);
+
+        // lowered while three downloads are running
+        let held = [
+            queue.permits.try_acquire().unwrap(),
+            queue.permits.try_acquire().unwrap(),
+            queue.permits.try_acquire().unwrap(),
+        ];
+        queue.set_limit(1);
+        assert_eq!(queue.permits.available_permits(), 0);
+        assert_eq!(queue.limits.lock().excess, 2);
+
+        // raised again before any of them finish
+        queue.set_limit(3);
+        assert_eq!(queue.permits.available_permits(), 0);
+        assert_eq!(queue.limits.lock().excess, 0);
+
+        for permit in held {
+            queue.release(permit);
+        }
+        assert_eq!(queue.permits.available_permits(), 3);
+
+        queue.set_limit(0);
+        assert_eq!(queue.permits.available_permits(), 1);
+        let permit = queue.permits.try_acquire().unwrap();
+        queue.set_limit(5);
+        queue.release(permit);
+        assert_eq!(queue.permits.available_permits(), 5);
+    }
+}
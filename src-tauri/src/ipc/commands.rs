@@ -1,7 +1,13 @@
+use std::path::PathBuf;
+
 use anyhow::{anyhow, Context};
 use tauri::State;
+use uuid::Uuid;
 
-use crate::ipc::{ConnectionId, IpcState, S2CMessage};
+use crate::ipc::history::HistoryEntry;
+use crate::ipc::{
+    ConnectionId, ConnectionInfo, IpcState, MetricsSample, PendingPrompt, S2CMessage,
+};
 use crate::CommandError;
 
 #[tauri::command]
@@ -26,23 +32,155 @@ pub async fn send_s2c_message(
     Ok(())
 }
 
+/// Asks the game connected through `conn_id` to exit on its own.
+#[tauri::command]
+pub async fn request_exit(
+    ipc_state: State<'_, IpcState>,
+    conn_id: ConnectionId,
+) -> Result<(), CommandError> {
+    send_s2c_message(ipc_state, conn_id, S2CMessage::RequestExit).await
+}
+
+/// Writes `data` to the standard input of the game connected through `conn_id`.
+#[tauri::command]
+pub async fn send_stdin(
+    ipc_state: State<'_, IpcState>,
+    conn_id: ConnectionId,
+    data: String,
+) -> Result<(), CommandError> {
+    send_s2c_message(
+        ipc_state,
+        conn_id,
+        S2CMessage::WriteStdin {
+            data: data.into_bytes(),
+        },
+    )
+    .await
+}
+
+/// Returns the open connections, with what was launched with them and the resource usage of the
+/// games that have started.
 #[tauri::command]
 pub async fn get_ipc_connections(
     ipc_state: State<'_, IpcState>,
-) -> Result<Vec<ConnectionId>, CommandError> {
-    Ok(ipc_state.get_conns())
+) -> Result<Vec<ConnectionInfo>, CommandError> {
+    let log = slog_scope::logger();
+    let mut infos = ipc_state.get_conn_infos(&log);
+    for info in &mut infos {
+        if let Some(instance) = crate::launching::instances::get(info.id) {
+            info.game = Some(instance.game);
+            info.profile = instance.profile;
+        }
+    }
+    Ok(infos)
+}
+
+/// Starts or stops sending the CPU and memory use of the game connected through `conn_id` to the
+/// frontend every few seconds. See [`crate::ipc::monitor`].
+#[tauri::command]
+pub fn set_resource_monitoring(
+    app: tauri::AppHandle,
+    ipc_state: State<'_, IpcState>,
+    conn_id: ConnectionId,
+    enabled: bool,
+) -> Result<(), CommandError> {
+    if !enabled {
+        crate::ipc::monitor::stop(conn_id);
+        return Ok(());
+    }
+    if ipc_state.get_conn(conn_id).is_none() {
+        return Err(anyhow!("No such connection: {conn_id:?}").into());
+    }
+    crate::ipc::monitor::start(app, conn_id);
+    Ok(())
+}
+
+/// Returns the performance samples that the game connected through `conn_id` has sent, oldest
+/// first.
+#[tauri::command]
+pub async fn get_connection_metrics(
+    ipc_state: State<'_, IpcState>,
+    conn_id: ConnectionId,
+) -> Result<Vec<MetricsSample>, CommandError> {
+    let Some(conn) = ipc_state.get_conn(conn_id) else {
+        return Err(anyhow!("No such connection: {conn_id:?}").into());
+    };
+    Ok(conn.metrics())
+}
+
+/// Returns up to `limit` of the messages kept for `conn_id`, starting with the one numbered
+/// `offset`, so that the console can be filled again after the frontend has been reloaded.
+#[tauri::command]
+pub async fn get_connection_history(
+    ipc_state: State<'_, IpcState>,
+    conn_id: ConnectionId,
+    offset: u64,
+    limit: usize,
+) -> Result<Vec<HistoryEntry>, CommandError> {
+    Ok(ipc_state.history().get(conn_id, offset, limit))
 }
 
+/// Kills the game connected through `conn_id`. Unless `force` is true, it is first asked to exit
+/// and only forced to if it has not after a grace period.
 #[tauri::command]
 pub async fn kill_ipc_client(
     ipc_state: State<'_, IpcState>,
     conn_id: ConnectionId,
+    force: Option<bool>,
 ) -> Result<(), CommandError> {
     let log = slog_scope::logger();
     let Some(conn) = ipc_state.get_conn(conn_id) else {
         return Err(anyhow!("No such connection: {conn_id:?}").into());
     };
-    conn.kill_process(&log)
+    conn.kill_process(&log, force.unwrap_or(false))
+        .await
         .context("Failed to kill IPC client")?;
     Ok(())
 }
+
+/// Returns the path of the recorded log for the connection. If `destination` is provided, the
+/// log is first copied there, and the path of the copy is returned instead.
+#[tauri::command]
+pub async fn export_connection_log(
+    ipc_state: State<'_, IpcState>,
+    conn_id: ConnectionId,
+    destination: Option<PathBuf>,
+) -> Result<PathBuf, CommandError> {
+    let recorder = ipc_state.recorder().clone();
+    Ok(tokio::task::spawn_blocking(move || match destination {
+        Some(destination) => recorder.export(conn_id, &destination),
+        None => recorder
+            .path(conn_id)?
+            .with_context(|| format!("No log has been recorded for connection {conn_id}")),
+    })
+    .await
+    .context("Failed to join task")??)
+}
+
+/// Returns the doctor prompts that are still waiting on an answer, so that they can be shown again
+/// after the frontend has been reloaded.
+#[tauri::command]
+pub async fn get_pending_doctor_prompts(
+    ipc_state: State<'_, IpcState>,
+) -> Result<Vec<PendingPrompt>, CommandError> {
+    Ok(ipc_state.pending_prompts().list())
+}
+
+#[tauri::command]
+pub async fn respond_to_prompt(
+    ipc_state: State<'_, IpcState>,
+    conn_id: ConnectionId,
+    id: Uuid,
+    choice: String,
+) -> Result<(), CommandError> {
+    if !ipc_state.pending_prompts().is_pending(conn_id, id) {
+        return Err(anyhow!("No pending prompt {id} for connection {conn_id}").into());
+    }
+    let Some(conn) = ipc_state.get_conn(conn_id) else {
+        return Err(anyhow!("No such connection: {conn_id:?}").into());
+    };
+    conn.send_async(S2CMessage::PatientResponse { id, choice })
+        .await
+        .context("Failed to send prompt response")?;
+    Ok(())
+}
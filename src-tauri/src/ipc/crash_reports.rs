@@ -0,0 +1,89 @@
+//! Crash reports sent by the agent, which are written to the logs directory so that they can be
+//! attached to bug reports, and summarized for the frontend.
+
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use anyhow::{Context as _, Result};
+use manderrow_paths::logs_dir;
+use slog::{error, info};
+use tauri::{AppHandle, Emitter as _};
+
+use crate::util::unix_millis;
+
+use super::{ConnectionId, CrashReport, EVENT_TARGET};
+
+/// The name of the event sent to the frontend when a crash report has been written.
+pub const EVENT: &str = "crash_report";
+
+#[derive(Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CrashReported<'a> {
+    conn_id: ConnectionId,
+    error: &'a str,
+    exception_code: Option<u32>,
+    /// The innermost frame whose function is known, which is usually where the game crashed.
+    frame: Option<&'a str>,
+    path: &'a Path,
+}
+
+/// Writes `report` to a file of its own in the logs directory.
+fn write(conn_id: ConnectionId, report: &CrashReport) -> Result<PathBuf> {
+    let dir = logs_dir().join("crashes");
+    std::fs::create_dir_all(&dir).with_context(|| format!("Failed to create {dir:?}"))?;
+    let timestamp = unix_millis(SystemTime::now());
+    let path = dir.join(format!("crash-{timestamp}-{conn_id}.txt"));
+    let file =
+        std::fs::File::create(&path).with_context(|| format!("Failed to create {path:?}"))?;
+    let mut out = std::io::BufWriter::new(file);
+
+    writeln!(out, "{}", report.error)?;
+    if let Some(code) = report.exception_code {
+        writeln!(out, "\nException code: {code:#010x}")?;
+    }
+    writeln!(out, "\nBacktrace:")?;
+    for (i, frame) in report.frames.iter().enumerate() {
+        writeln!(
+            out,
+            "{i:>4}: {}",
+            frame.symbol.as_deref().unwrap_or("<unknown>")
+        )?;
+        if let Some(location) = &frame.location {
+            writeln!(out, "          at {location}")?;
+        }
+    }
+    if !report.modules.is_empty() {
+        writeln!(out, "\nModules:")?;
+        for module in &report.modules {
+            writeln!(out, "  {module}")?;
+        }
+    }
+    out.flush()?;
+    Ok(path)
+}
+
+/// Writes `report` to the logs directory and tells the frontend where it is.
+pub fn handle(log: &slog::Logger, app: &AppHandle, conn_id: ConnectionId, report: &CrashReport) {
+    let path = match write(conn_id, report) {
+        Ok(path) => path,
+        Err(e) => {
+            error!(log, "Failed to write crash report: {e:#}"; "conn_id" => conn_id);
+            return;
+        }
+    };
+    info!(log, "Wrote crash report to {path:?}"; "conn_id" => conn_id);
+    let summary = CrashReported {
+        conn_id,
+        error: &report.error,
+        exception_code: report.exception_code,
+        frame: report
+            .frames
+            .iter()
+            .find_map(|frame| frame.symbol.as_deref()),
+        path: &path,
+    };
+    if let Err(e) = app.emit_to(EVENT_TARGET, EVENT, summary) {
+        error!(log, "Failed to emit {EVENT} event to {EVENT_TARGET}: {e}"; "conn_id" => conn_id);
+    }
+}
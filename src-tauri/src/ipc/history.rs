@@ -0,0 +1,111 @@
+//! The messages that have been sent to the frontend for each connection, so that the console can
+//! be filled again when the window is reloaded.
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use parking_lot::Mutex;
+use triomphe::Arc;
+
+use super::{C2SMessage, ConnectionId};
+
+pub const DEFAULT_CAPACITY: usize = 10_000;
+
+/// How many messages are kept per connection. The oldest ones are dropped first.
+static CAPACITY: AtomicUsize = AtomicUsize::new(DEFAULT_CAPACITY);
+
+pub fn set_capacity(capacity: usize) {
+    CAPACITY.store(capacity, Ordering::Relaxed);
+}
+
+#[derive(Clone, serde::Serialize)]
+pub struct HistoryEntry {
+    /// Numbers the messages of a connection in the order that they were sent, starting at 0.
+    pub seq: u64,
+    #[serde(flatten)]
+    pub msg: C2SMessage,
+}
+
+#[derive(Default)]
+struct History {
+    next_seq: u64,
+    entries: VecDeque<HistoryEntry>,
+}
+
+#[derive(Clone, Default)]
+pub struct MessageHistory(Arc<Mutex<HashMap<ConnectionId, History>>>);
+
+impl MessageHistory {
+    /// Keeps `msg`, returning its sequence number.
+    pub fn push(&self, conn_id: ConnectionId, msg: &C2SMessage) -> u64 {
+        let capacity = CAPACITY.load(Ordering::Relaxed);
+        let mut histories = self.0.lock();
+        let history = histories.entry(conn_id).or_default();
+        let seq = history.next_seq;
+        history.next_seq += 1;
+        while history.entries.len() >= capacity.max(1) {
+            history.entries.pop_front();
+        }
+        history.entries.push_back(HistoryEntry {
+            seq,
+            msg: msg.clone(),
+        });
+        seq
+    }
+
+    /// Returns up to `limit` of the kept messages, starting with the one numbered `offset`, or the
+    /// oldest one kept if that has been dropped already.
+    pub fn get(&self, conn_id: ConnectionId, offset: u64, limit: usize) -> Vec<HistoryEntry> {
+        let histories = self.0.lock();
+        let Some(history) = histories.get(&conn_id) else {
+            return Vec::new();
+        };
+        let start = history.entries.partition_point(|entry| entry.seq < offset);
+        history
+            .entries
+            .range(start..)
+            .take(limit)
+            .cloned()
+            .collect()
+    }
+
+    /// Drops the history of a connection that has closed.
+    pub fn finish(&self, conn_id: ConnectionId) {
+        self.0.lock().remove(&conn_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn log(message: &str) -> C2SMessage {
+        C2SMessage::Log {
+            level: crate::ipc::LogLevel::Info,
+            scope: "test".into(),
+            message: message.into(),
+        }
+    }
+
+    #[test]
+    fn test_history_pages() {
+        let history = MessageHistory::default();
+        let id = ConnectionId(0);
+        for i in 0..5 {
+            assert_eq!(history.push(id, &log(&i.to_string())), i);
+        }
+        let seqs = |offset, limit| {
+            history
+                .get(id, offset, limit)
+                .into_iter()
+                .map(|entry| entry.seq)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(seqs(0, 2), [0, 1]);
+        assert_eq!(seqs(2, 2), [2, 3]);
+        assert_eq!(seqs(4, 2), [4]);
+        assert!(seqs(5, 2).is_empty());
+        history.finish(id);
+        assert!(seqs(0, 2).is_empty());
+    }
+}
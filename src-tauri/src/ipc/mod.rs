@@ -1,21 +1,47 @@
 pub mod commands;
+pub mod crash_reports;
+pub mod history;
+pub mod monitor;
+mod prompts;
+mod recorder;
 
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 use std::ops::ControlFlow;
-use std::sync::atomic::AtomicU32;
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+use std::time::{Duration, Instant, SystemTime};
 
 use anyhow::{Context, Result};
 use manderrow_ipc::ipc_channel::ipc::{IpcReceiver, IpcSender};
 use manderrow_process_util::Pid;
 use parking_lot::{Mutex, RwLock};
-use slog::{debug, error, warn};
+use slog::{debug, error, info, warn};
 use tauri::{AppHandle, Emitter};
+use uuid::Uuid;
 
+pub use history::MessageHistory;
 pub use manderrow_ipc::*;
+pub use prompts::{PendingPrompt, PendingPrompts};
+pub use recorder::LogRecorder;
 use triomphe::Arc;
 
 pub const EVENT_TARGET: &str = "main";
 pub const EVENT_NAME: &str = "ipc_message";
+/// Sent with [`Responsiveness`] when a connection stops or resumes sending heartbeats.
+pub const RESPONSIVENESS_EVENT_NAME: &str = "ipc_responsiveness";
+
+/// How long a game may go without a [`C2SMessage::Heartbeat`] before it is considered unresponsive.
+const HEARTBEAT_TIMEOUT: Duration = Duration::from_secs(10);
+/// How many [`MetricsSample`]s are kept per connection, which covers the last 10 minutes.
+const METRICS_CAPACITY: usize = 120;
+
+pub const DEFAULT_KILL_GRACE_PERIOD: usize = 10;
+
+/// Seconds that a game is given to exit after being asked to before it is forced to.
+static KILL_GRACE_PERIOD: AtomicU64 = AtomicU64::new(DEFAULT_KILL_GRACE_PERIOD as u64);
+
+pub fn set_kill_grace_period(secs: u64) {
+    KILL_GRACE_PERIOD.store(secs, Ordering::Relaxed);
+}
 
 #[derive(
     Debug,
@@ -31,7 +57,7 @@ pub const EVENT_NAME: &str = "ipc_message";
     rkyv::Deserialize,
 )]
 #[serde(transparent)]
-pub struct ConnectionId(u32);
+pub struct ConnectionId(pub(crate) u32);
 
 impl std::fmt::Display for ConnectionId {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -83,21 +109,77 @@ impl IpcConnection {
         }
     }
 
-    pub fn kill_process(&self, log: &slog::Logger) -> Result<(), KillError> {
-        let state = self.0.lock();
-        match &*state {
+    /// Returns the performance samples that the game has sent, oldest first.
+    pub fn metrics(&self) -> Vec<MetricsSample> {
+        match &*self.0.lock() {
+            IpcConnectionState::External(conn) => conn.metrics.iter().copied().collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Returns the game's process id, once it has started.
+    pub fn pid(&self) -> Option<Pid> {
+        match &*self.0.lock() {
+            IpcConnectionState::External(conn) => conn.pid,
+            _ => None,
+        }
+    }
+
+    /// Returns what is known of the game's process, sampling its resource usage.
+    pub fn info(&self, log: &slog::Logger, id: ConnectionId) -> ConnectionInfo {
+        let mut info = ConnectionInfo {
+            id,
+            pid: None,
+            uptime: None,
+            cpu_percent: None,
+            resident_memory: None,
+            game: None,
+            profile: None,
+        };
+        let (pid, started_at) = match &*self.0.lock() {
+            IpcConnectionState::External(ExternalIpcConnection {
+                pid: Some(pid),
+                started_at,
+                ..
+            }) => (*pid, *started_at),
+            _ => return info,
+        };
+        info.pid = Some(pid);
+        info.uptime = started_at.map(|t| t.elapsed().as_secs());
+        // sampled without holding the lock because it may block on the OS
+        let usage = match pid.resource_usage() {
+            Ok(t) => t,
+            Err(e) => {
+                debug!(log, "Failed to sample resource usage of {pid:?}: {e}"; "conn_id" => id);
+                return info;
+            }
+        };
+        info.resident_memory = Some(usage.resident_memory);
+        if let IpcConnectionState::External(conn) = &mut *self.0.lock() {
+            info.cpu_percent = monitor::sample_cpu(&mut conn.last_cpu_sample, &usage);
+        }
+        info
+    }
+
+    /// Kills the game's process. Unless `force` is true, it is first asked to exit and only
+    /// forced to after the grace period set with [`set_kill_grace_period`].
+    pub async fn kill_process(&self, log: &slog::Logger, force: bool) -> Result<(), KillError> {
+        let pid = match &*self.0.lock() {
             IpcConnectionState::InternalConnecting
             | IpcConnectionState::Internal(_)
             | IpcConnectionState::ExternalConnecting
             | IpcConnectionState::External(ExternalIpcConnection { pid: None, .. }) => {
-                Err(KillError::IncompleteConnection)
-            }
-            IpcConnectionState::External(ExternalIpcConnection { pid: Some(pid), .. }) => {
-                // TODO: kill button tries soft first, then second click tries hard
-                pid.kill(log, true)?;
-                Ok(())
+                return Err(KillError::IncompleteConnection);
             }
+            IpcConnectionState::External(ExternalIpcConnection { pid: Some(pid), .. }) => *pid,
+        };
+        if force {
+            pid.kill(log, true)?;
+        } else {
+            let grace = Duration::from_secs(KILL_GRACE_PERIOD.load(Ordering::Relaxed));
+            pid.graceful_kill(log, grace).await?;
         }
+        Ok(())
     }
 }
 
@@ -128,6 +210,43 @@ struct ExternalIpcConnection {
     /// The id of the receiver in the set.
     c2s_rx: u64,
     pid: Option<Pid>,
+    /// When the last heartbeat was received. Games whose agent never sends any are never
+    /// considered unresponsive.
+    last_heartbeat: Option<Instant>,
+    unresponsive: bool,
+    /// The last [`METRICS_CAPACITY`] samples.
+    metrics: VecDeque<MetricsSample>,
+    /// When the game sent [`C2SMessage::Started`].
+    started_at: Option<Instant>,
+    /// When the CPU time was last sampled by [`IpcConnection::info`], and what it was.
+    last_cpu_sample: Option<(Instant, Duration)>,
+}
+
+/// A connection with what is known of its game's process.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct ConnectionInfo {
+    pub id: ConnectionId,
+    pub pid: Option<Pid>,
+    /// Seconds since the game started.
+    pub uptime: Option<u64>,
+    /// The percentage of one CPU core used since the previous sample, unknown for the first.
+    pub cpu_percent: Option<f32>,
+    /// Bytes of the game's memory that are resident in RAM.
+    pub resident_memory: Option<u64>,
+    /// The game that was launched with the connection, if it was launched by Manderrow.
+    pub game: Option<&'static str>,
+    /// The profile that was launched with the connection, unless it was a vanilla launch.
+    pub profile: Option<Uuid>,
+}
+
+/// A [`C2SMessage::Metrics`], with when it was received.
+#[derive(Debug, Clone, Copy, serde::Serialize)]
+pub struct MetricsSample {
+    /// Milliseconds since the Unix epoch.
+    pub time: u64,
+    pub fps: Option<f32>,
+    pub memory_mb: Option<f32>,
+    pub frame_time_ms: Option<f32>,
 }
 
 enum IpcConnectionState {
@@ -149,19 +268,48 @@ enum ManagementEvent {
     },
 }
 
+#[derive(Clone, serde::Serialize)]
+pub struct Responsiveness {
+    #[serde(rename = "connId")]
+    pub conn_id: ConnectionId,
+    pub unresponsive: bool,
+}
+
 #[derive(Clone, serde::Serialize)]
 pub struct IdentifiedC2SMessage<'a> {
     #[serde(rename = "connId")]
     pub conn_id: ConnectionId,
+    /// The number of the message in the connection's [`MessageHistory`], if it is kept there.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seq: Option<u64>,
     #[serde(flatten)]
     pub msg: &'a C2SMessage,
 }
 
+/// Keeps `msg` in the connection's history and sends it to the frontend.
+pub fn emit_message(
+    app: &AppHandle,
+    history: &MessageHistory,
+    conn_id: ConnectionId,
+    msg: &C2SMessage,
+) -> tauri::Result<()> {
+    // pending prompts are sent again when the window is reloaded instead
+    let seq = (!matches!(msg, C2SMessage::DoctorReport(_))).then(|| history.push(conn_id, msg));
+    app.emit_to(
+        EVENT_TARGET,
+        EVENT_NAME,
+        IdentifiedC2SMessage { conn_id, seq, msg },
+    )
+}
+
 pub struct IpcState {
     next_connection_id: AtomicU32,
     connections: Arc<RwLock<HashMap<ConnectionId, IpcConnection>>>,
     receiver_handle: std::thread::JoinHandle<()>,
     mgmt_tx: Arc<Mutex<IpcSender<ManagementEvent>>>,
+    recorder: LogRecorder,
+    history: MessageHistory,
+    pending_prompts: PendingPrompts,
 }
 
 impl IpcState {
@@ -173,6 +321,8 @@ impl IpcState {
             ipc_channel::ipc::channel::<ManagementEvent>().expect("failed to create ipc");
         let (death_wait_submitter, mut death_waiter) =
             manderrow_process_util::wait_group::wait_group();
+        let recorder = LogRecorder::default();
+        let history = MessageHistory::default();
         {
             let log = log.clone();
             let mgmt_tx = mgmt_tx.clone();
@@ -197,9 +347,44 @@ impl IpcState {
                 })
                 .expect("failed to spawn ipc-reaper thread");
         }
+        {
+            let log = log.clone();
+            let app = app.clone();
+            let connections = connections.clone();
+            std::thread::Builder::new()
+                .name("ipc-watchdog".into())
+                .spawn(move || loop {
+                    std::thread::sleep(HEARTBEAT_INTERVAL);
+                    let now = Instant::now();
+                    for (&id, conn) in connections.read().iter() {
+                        let mut state = conn.0.lock();
+                        let IpcConnectionState::External(conn) = &mut *state else {
+                            continue;
+                        };
+                        // connections are removed when their process dies, so the game is
+                        // still running
+                        if conn.unresponsive
+                            || conn.pid.is_none()
+                            || !conn
+                                .last_heartbeat
+                                .is_some_and(|t| now.duration_since(t) > HEARTBEAT_TIMEOUT)
+                        {
+                            continue;
+                        }
+                        conn.unresponsive = true;
+                        drop(state);
+                        warn!(log, "Game stopped sending heartbeats"; "conn_id" => id);
+                        emit_responsiveness(&log, &app, id, true);
+                    }
+                })
+                .expect("failed to spawn ipc-watchdog thread");
+        }
         Self {
             next_connection_id: AtomicU32::new(0),
             connections: connections.clone(),
+            recorder: recorder.clone(),
+            history: history.clone(),
+            pending_prompts: PendingPrompts::default(),
             receiver_handle: std::thread::Builder::new()
                 .name("ipc-receiver".into())
                 .spawn(move || {
@@ -223,6 +408,8 @@ impl IpcState {
                                     }
                                     _ => {}
                                 }
+                                recorder.finish(&log, id);
+                                history.finish(id);
                                 if let Err(e) = app.emit_to(EVENT_TARGET, "ipc_closed", id) {
                                     error!(log, "Failed to emit ipc_closed event to {}: {}", EVENT_TARGET, e; "conn_id" => id.0);
                                 }
@@ -278,7 +465,7 @@ impl IpcState {
                                                     continue;
                                                 }
                                             };
-                                            *state = IpcConnectionState::External(ExternalIpcConnection { s2c_tx, c2s_rx, pid: None });
+                                            *state = IpcConnectionState::External(ExternalIpcConnection { s2c_tx, c2s_rx, pid: None, last_heartbeat: None, unresponsive: false, metrics: VecDeque::new(), started_at: None, last_cpu_sample: None });
                                             rx_to_id.insert(c2s_rx, id);
                                         }
                                         ManagementEvent::Death { id } => {
@@ -317,6 +504,7 @@ impl IpcState {
                                                 C2SMessage::Started { pid } => {
                                                     let pid = Pid::from_raw(pid);
                                                     conn.pid = Some(pid);
+                                                    conn.started_at = Some(Instant::now());
                                                     match death_wait_submitter.submit(pid, id.0) {
                                                         Ok(()) => {}
                                                         Err(manderrow_process_util::wait_group::SubmitError::Closed) => {
@@ -327,12 +515,41 @@ impl IpcState {
                                                         }
                                                     }
                                                 }
+                                                C2SMessage::Heartbeat => {
+                                                    conn.last_heartbeat = Some(Instant::now());
+                                                    if conn.unresponsive {
+                                                        conn.unresponsive = false;
+                                                        info!(log, "Game is responsive again"; "conn_id" => id);
+                                                        emit_responsiveness(&log, &app, id, false);
+                                                    }
+                                                    // heartbeats are of no interest to the console or the recording
+                                                    continue;
+                                                }
+                                                C2SMessage::Metrics { fps, memory_mb, frame_time_ms } => {
+                                                    if conn.metrics.len() == METRICS_CAPACITY {
+                                                        conn.metrics.pop_front();
+                                                    }
+                                                    conn.metrics.push_back(MetricsSample {
+                                                        time: crate::util::unix_millis(SystemTime::now()),
+                                                        fps,
+                                                        memory_mb,
+                                                        frame_time_ms,
+                                                    });
+                                                    // fetched with get_connection_metrics instead of cluttering the console
+                                                    continue;
+                                                }
                                                 _ => {}
                                             }
                                         }
                                     }
 
-                                    if let Err(e) = app.emit_to(EVENT_TARGET, EVENT_NAME, IdentifiedC2SMessage { conn_id: id, msg: &msg }) {
+                                    recorder.record(&log, id, &msg);
+
+                                    if let C2SMessage::CrashReport(report) = &msg {
+                                        crash_reports::handle(&log, &app, id, report);
+                                    }
+
+                                    if let Err(e) = emit_message(&app, &history, id, &msg) {
                                         error!(log, "Failed to emit ipc_message event to {}: {}", EVENT_TARGET, e; "conn_id" => id, "rx" => rx);
                                     }
 
@@ -348,6 +565,8 @@ impl IpcState {
                                     };
                                     connections.write().remove(&id);
                                     rx_to_id.remove(&rx);
+                                    recorder.finish(&log, id);
+                                    history.finish(id);
                                     if let Err(e) = app.emit_to(EVENT_TARGET, "ipc_closed", id) {
                                         error!(log, "Failed to emit ipc_closed event to {}: {}", EVENT_TARGET, e; "conn_id" => id, "rx" => rx);
                                     }
@@ -387,10 +606,14 @@ impl IpcState {
             return Err(ConnectError::NoSuchConnection(conn_id));
         }
         *state = IpcConnectionState::Internal(InternalIpcConnection { s2c_tx: tx });
+        drop(state);
         Ok(InProcessIpc {
             conn_id,
+            conn,
             s2c_rx: rx,
             app,
+            history: self.history.clone(),
+            pending_prompts: self.pending_prompts.clone(),
         })
     }
 
@@ -398,8 +621,29 @@ impl IpcState {
         self.connections.read().get(&conn_id).cloned()
     }
 
-    pub fn get_conns(&self) -> Vec<ConnectionId> {
-        self.connections.read().keys().copied().collect()
+    pub fn get_conn_infos(&self, log: &slog::Logger) -> Vec<ConnectionInfo> {
+        let conns = self
+            .connections
+            .read()
+            .iter()
+            .map(|(&id, conn)| (id, conn.clone()))
+            .collect::<Vec<_>>();
+        conns
+            .into_iter()
+            .map(|(id, conn)| conn.info(log, id))
+            .collect()
+    }
+
+    pub fn recorder(&self) -> &LogRecorder {
+        &self.recorder
+    }
+
+    pub fn history(&self) -> &MessageHistory {
+        &self.history
+    }
+
+    pub fn pending_prompts(&self) -> &PendingPrompts {
+        &self.pending_prompts
     }
 
     /// The returned string should be passed to [`IpcSender::<C2SMessage>::connect`].
@@ -420,6 +664,7 @@ impl IpcState {
 
         let connections = self.connections.clone();
         let mgmt_tx = self.mgmt_tx.clone();
+        let history = self.history.clone();
 
         std::thread::Builder::new()
             .name(format!("ipc-receiver-server-{}", name))
@@ -431,11 +676,7 @@ impl IpcState {
                         return;
                     }
                 };
-                _ = app.emit_to(
-                    EVENT_TARGET,
-                    EVENT_NAME,
-                    IdentifiedC2SMessage { conn_id, msg: &msg },
-                );
+                _ = emit_message(&app, &history, conn_id, &msg);
                 if let C2SMessage::Connect { s2c_tx } = msg {
                     if let Err(e) = mgmt_tx.lock().send(&ManagementEvent::ExternalRegistration {
                         id: conn_id,
@@ -456,6 +697,24 @@ impl IpcState {
     }
 }
 
+fn emit_responsiveness(
+    log: &slog::Logger,
+    app: &AppHandle,
+    conn_id: ConnectionId,
+    unresponsive: bool,
+) {
+    if let Err(e) = app.emit_to(
+        EVENT_TARGET,
+        RESPONSIVENESS_EVENT_NAME,
+        Responsiveness {
+            conn_id,
+            unresponsive,
+        },
+    ) {
+        error!(log, "Failed to emit {} event to {}: {}", RESPONSIVENESS_EVENT_NAME, EVENT_TARGET, e; "conn_id" => conn_id);
+    }
+}
+
 #[derive(Debug, thiserror::Error)]
 pub enum ConnectError {
     #[error("No such connection {}", .0.0)]
@@ -474,37 +733,47 @@ pub enum SpawnError {
 
 pub struct InProcessIpc {
     conn_id: ConnectionId,
+    conn: IpcConnection,
     s2c_rx: tokio::sync::mpsc::Receiver<S2CMessage>,
     app: AppHandle,
+    history: MessageHistory,
+    pending_prompts: PendingPrompts,
+}
+
+impl Drop for InProcessIpc {
+    fn drop(&mut self) {
+        // once an agent has taken over the connection, its history is dropped when it closes
+        if matches!(*self.conn.0.lock(), IpcConnectionState::Internal(_)) {
+            self.history.finish(self.conn_id);
+        }
+    }
 }
 
 impl InProcessIpc {
     pub async fn send(&self, message: C2SMessage) -> Result<()> {
         let app = self.app.clone();
+        let history = self.history.clone();
         let conn_id = self.conn_id;
-        Ok(tokio::task::spawn_blocking(move || {
-            app.emit_to(
-                EVENT_TARGET,
-                EVENT_NAME,
-                IdentifiedC2SMessage {
-                    conn_id,
-                    msg: &message,
-                },
-            )
-        })
-        .await??)
+        Ok(
+            tokio::task::spawn_blocking(move || emit_message(&app, &history, conn_id, &message))
+                .await??,
+        )
     }
 
     pub async fn recv(&mut self) -> Result<S2CMessage> {
         Ok(self.s2c_rx.recv().await.context("Channel closed")?)
     }
 
+    /// Asks the user to choose one of `fixes`. The prompt is kept in [`IpcState`] until it is
+    /// answered, so that it can be shown again if the window is reloaded. If the user does not
+    /// answer within [`prompts::PROMPT_TIMEOUT`], `default` is chosen.
     pub async fn prompt_patient<T: Send>(
         &mut self,
         translation_key: impl Into<String>,
         message: Option<String>,
         message_args: Option<HashMap<String, String>>,
         fixes: impl IntoIterator<Item = DoctorFix<T>>,
+        default: T,
     ) -> Result<T>
     where
         T: serde::Serialize,
@@ -512,9 +781,25 @@ impl InProcessIpc {
     {
         let (mut receiver, msg) =
             doctor::PatientChoiceReceiver::new(translation_key, message, message_args, fixes);
+        let C2SMessage::DoctorReport(report) = &msg else {
+            unreachable!()
+        };
+        let translation_key = report.translation_key.clone();
+        let _guard = self
+            .pending_prompts
+            .insert(&self.app, self.conn_id, report.clone());
+        prompts::request_attention(&self.app);
         self.send(msg).await?;
+        let deadline = tokio::time::Instant::now() + prompts::PROMPT_TIMEOUT;
         loop {
-            match receiver.process(self.recv().await?)? {
+            let response = match tokio::time::timeout_at(deadline, self.recv()).await {
+                Ok(response) => response?,
+                Err(_) => {
+                    warn!(slog_scope::logger(), "Prompt {translation_key:?} timed out, using the default choice"; "conn_id" => self.conn_id);
+                    return Ok(default);
+                }
+            };
+            match receiver.process(response)? {
                 ControlFlow::Break(choice) => return Ok(choice),
                 ControlFlow::Continue(r) => receiver = r,
             }
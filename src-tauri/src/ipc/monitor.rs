@@ -0,0 +1,167 @@
+//! Sampling the CPU and memory use of games' processes while the console shows them, sent to the
+//! frontend as [`EVENT_NAME`] events.
+//!
+//! Unlike [`super::IpcConnection::info`], which samples only when asked, a monitor samples on its
+//! own, so that memory that keeps growing is noticed even if nobody is looking at the numbers.
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::LazyLock;
+use std::time::{Duration, Instant, SystemTime};
+
+use manderrow_process_util::ResourceUsage;
+use parking_lot::Mutex;
+use slog::{debug, error, warn};
+use tauri::{AppHandle, Emitter, Manager};
+use tokio::task::AbortHandle;
+
+use crate::util::unix_millis;
+
+use super::{ConnectionId, IpcState, EVENT_TARGET};
+
+pub const EVENT_NAME: &str = "ipc_resource_usage";
+
+const INTERVAL: Duration = Duration::from_secs(2);
+/// The number of samples that memory growth is judged over, which covers the last 5 minutes.
+const GROWTH_WINDOW: usize = 150;
+/// How much memory must grow over the [`GROWTH_WINDOW`] to be considered runaway.
+const RUNAWAY_GROWTH: u64 = 512 * 1024 * 1024;
+/// The fraction of samples in the [`GROWTH_WINDOW`] that must be larger than the one before for
+/// memory to be considered runaway, so that a single large allocation isn't.
+const RUNAWAY_RISING_FRACTION: f64 = 0.8;
+
+static MONITORS: LazyLock<Mutex<HashMap<ConnectionId, AbortHandle>>> =
+    LazyLock::new(Default::default);
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct ResourceSample {
+    #[serde(rename = "connId")]
+    pub conn_id: ConnectionId,
+    /// Milliseconds since the Unix epoch.
+    pub time: u64,
+    /// The percentage of one CPU core used since the previous sample, unknown for the first.
+    pub cpu_percent: Option<f32>,
+    /// Bytes of the game's memory that are resident in RAM.
+    pub resident_memory: u64,
+    /// Whether the game's memory has been growing steadily for the last few minutes, which is
+    /// usually a leak in one of its mods.
+    pub runaway_memory: bool,
+}
+
+/// Returns `true` if memory grew by at least [`RUNAWAY_GROWTH`] over the full [`GROWTH_WINDOW`],
+/// and most of the time.
+fn is_runaway(window: &VecDeque<u64>) -> bool {
+    let (Some(&first), Some(&last)) = (window.front(), window.back()) else {
+        return false;
+    };
+    if window.len() < GROWTH_WINDOW || last < first.saturating_add(RUNAWAY_GROWTH) {
+        return false;
+    }
+    let rising = window
+        .iter()
+        .zip(window.iter().skip(1))
+        .filter(|(a, b)| b > a)
+        .count();
+    rising as f64 >= (window.len() - 1) as f64 * RUNAWAY_RISING_FRACTION
+}
+
+/// Returns the CPU use of a process, in percent of one core, since `last` was sampled, and replaces
+/// `last` with `usage`. Returns `None` for the first sample.
+pub(super) fn sample_cpu(
+    last: &mut Option<(Instant, Duration)>,
+    usage: &ResourceUsage,
+) -> Option<f32> {
+    let now = Instant::now();
+    let cpu_percent = last.and_then(|(then, cpu_time)| {
+        let elapsed = now.duration_since(then).as_secs_f32();
+        (elapsed > 0.0)
+            .then(|| usage.cpu_time.saturating_sub(cpu_time).as_secs_f32() / elapsed * 100.0)
+    });
+    *last = Some((now, usage.cpu_time));
+    cpu_percent
+}
+
+/// Starts sampling the game connected through `conn_id`, unless it is already being sampled. The
+/// sampling stops by itself once the connection closes.
+pub fn start(app: AppHandle, conn_id: ConnectionId) {
+    let mut monitors = MONITORS.lock();
+    if monitors.contains_key(&conn_id) {
+        return;
+    }
+    let task = tauri::async_runtime::spawn(async move {
+        let log = slog_scope::logger();
+        let mut interval = tokio::time::interval(INTERVAL);
+        let mut last_cpu_sample = None::<(Instant, Duration)>;
+        let mut window = VecDeque::with_capacity(GROWTH_WINDOW);
+        let mut warned = false;
+        loop {
+            interval.tick().await;
+            let Some(conn) = app.state::<IpcState>().get_conn(conn_id) else {
+                break;
+            };
+            // the game may not have started yet
+            let Some(pid) = conn.pid() else {
+                continue;
+            };
+            let usage = match pid.resource_usage() {
+                Ok(t) => t,
+                Err(e) => {
+                    debug!(log, "Failed to sample resource usage of {pid:?}: {e}"; "conn_id" => conn_id);
+                    continue;
+                }
+            };
+            let cpu_percent = sample_cpu(&mut last_cpu_sample, &usage);
+
+            if window.len() == GROWTH_WINDOW {
+                window.pop_front();
+            }
+            window.push_back(usage.resident_memory);
+            let runaway_memory = is_runaway(&window);
+            if runaway_memory && !warned {
+                warn!(log, "Memory of {pid:?} has grown steadily to {} bytes", usage.resident_memory; "conn_id" => conn_id);
+            }
+            warned = runaway_memory;
+
+            let sample = ResourceSample {
+                conn_id,
+                time: unix_millis(SystemTime::now()),
+                cpu_percent,
+                resident_memory: usage.resident_memory,
+                runaway_memory,
+            };
+            if let Err(e) = app.emit_to(EVENT_TARGET, EVENT_NAME, sample) {
+                error!(log, "Failed to emit {} event to {}: {}", EVENT_NAME, EVENT_TARGET, e; "conn_id" => conn_id);
+            }
+        }
+        MONITORS.lock().remove(&conn_id);
+    });
+    monitors.insert(conn_id, task.inner().abort_handle());
+}
+
+/// Stops sampling the game connected through `conn_id`.
+pub fn stop(conn_id: ConnectionId) {
+    if let Some(task) = MONITORS.lock().remove(&conn_id) {
+        task.abort();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_runaway() {
+        const MIB: u64 = 1024 * 1024;
+
+        let steady = (0..GROWTH_WINDOW as u64).map(|i| 1024 * MIB + i * 4 * MIB);
+        assert!(is_runaway(&steady.clone().collect()));
+        // not enough samples yet
+        assert!(!is_runaway(&steady.take(GROWTH_WINDOW / 2).collect()));
+        // a single large allocation
+        let jump = (0..GROWTH_WINDOW).map(|i| if i < 10 { 1024 * MIB } else { 2048 * MIB });
+        assert!(!is_runaway(&jump.collect()));
+        // growing, but not by much
+        let slow = (0..GROWTH_WINDOW as u64).map(|i| 1024 * MIB + i * MIB);
+        assert!(!is_runaway(&slow.collect()));
+        assert!(!is_runaway(&VecDeque::new()));
+    }
+}
@@ -0,0 +1,112 @@
+//! Tracking of doctor prompts that are waiting on the user, so that they survive the window being
+//! reloaded and never block a launch forever.
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use parking_lot::Mutex;
+use slog::error;
+use tauri::{AppHandle, Emitter, Manager};
+use triomphe::Arc;
+use uuid::Uuid;
+
+use super::{
+    C2SMessage, ConnectionId, DoctorReport, IdentifiedC2SMessage, EVENT_NAME, EVENT_TARGET,
+};
+
+/// How long to wait for the user to answer a prompt before falling back to its default choice.
+pub const PROMPT_TIMEOUT: Duration = Duration::from_secs(15 * 60);
+
+/// Emitted with the id of a prompt once it no longer needs an answer, whether because it was
+/// answered or because it timed out.
+pub const RESOLVED_EVENT_NAME: &str = "doctor_prompt_resolved";
+
+/// A prompt, serialized in the same shape as the [`EVENT_NAME`] event that first showed it.
+#[derive(Clone, serde::Serialize)]
+pub struct PendingPrompt {
+    #[serde(rename = "connId")]
+    pub conn_id: ConnectionId,
+    #[serde(flatten)]
+    pub msg: C2SMessage,
+}
+
+#[derive(Clone, Default)]
+pub struct PendingPrompts(Arc<Mutex<HashMap<Uuid, PendingPrompt>>>);
+
+impl PendingPrompts {
+    /// Records `report` as pending until the returned guard is dropped.
+    pub fn insert(
+        &self,
+        app: &AppHandle,
+        conn_id: ConnectionId,
+        report: DoctorReport,
+    ) -> PendingPromptGuard {
+        let id = report.id;
+        self.0.lock().insert(
+            id,
+            PendingPrompt {
+                conn_id,
+                msg: C2SMessage::DoctorReport(report),
+            },
+        );
+        PendingPromptGuard {
+            prompts: self.clone(),
+            app: app.clone(),
+            id,
+        }
+    }
+
+    pub fn list(&self) -> Vec<PendingPrompt> {
+        self.0.lock().values().cloned().collect()
+    }
+
+    /// Returns whether `id` is a prompt that `conn_id` is waiting on.
+    pub fn is_pending(&self, conn_id: ConnectionId, id: Uuid) -> bool {
+        self.0
+            .lock()
+            .get(&id)
+            .is_some_and(|prompt| prompt.conn_id == conn_id)
+    }
+
+    /// Emits every pending prompt to the frontend again, as if they had just been sent.
+    pub fn emit_all(&self, log: &slog::Logger, app: &AppHandle) {
+        for prompt in self.0.lock().values() {
+            if let Err(e) = app.emit_to(
+                EVENT_TARGET,
+                EVENT_NAME,
+                IdentifiedC2SMessage {
+                    conn_id: prompt.conn_id,
+                    seq: None,
+                    msg: &prompt.msg,
+                },
+            ) {
+                error!(log, "Failed to emit pending prompt to {}: {}", EVENT_TARGET, e; "conn_id" => prompt.conn_id);
+            }
+        }
+    }
+}
+
+pub struct PendingPromptGuard {
+    prompts: PendingPrompts,
+    app: AppHandle,
+    id: Uuid,
+}
+
+impl Drop for PendingPromptGuard {
+    fn drop(&mut self) {
+        self.prompts.0.lock().remove(&self.id);
+        _ = self.app.emit_to(EVENT_TARGET, RESOLVED_EVENT_NAME, self.id);
+    }
+}
+
+/// Asks for the user's attention if the main window is not focused, so that a prompt doesn't go
+/// unnoticed.
+pub fn request_attention(app: &AppHandle) {
+    let Some(window) = app.get_webview_window(EVENT_TARGET) else {
+        return;
+    };
+    if !window.is_focused().unwrap_or(false) {
+        _ = window.unminimize();
+        _ = window.request_user_attention(Some(tauri::UserAttentionType::Critical));
+    }
+}
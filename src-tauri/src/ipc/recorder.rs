@@ -0,0 +1,220 @@
+//! Persistent recording of the output and logs of each connection, so that they can be looked at
+//! after the game has closed.
+
+use std::collections::{HashMap, VecDeque};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{Instant, SystemTime};
+
+use anyhow::{Context as _, Result};
+use manderrow_paths::logs_dir;
+use parking_lot::Mutex;
+use triomphe::Arc;
+
+use crate::util::unix_millis;
+
+use super::{C2SMessage, ConnectionId, CrashReport, OutputLine};
+
+/// The most bytes of messages that are held back waiting for the header before giving up on it.
+const MAX_PENDING: usize = 1 << 20;
+
+/// How many finished recordings can still be exported. The oldest ones are forgotten first, but
+/// their files are kept.
+const MAX_FINISHED: usize = 64;
+
+struct Recording {
+    path: PathBuf,
+    file: std::io::BufWriter<std::fs::File>,
+    /// Messages recorded before the header, which are held back so that the header is at the top
+    /// of the file. `None` once the header has been written.
+    pending: Option<Vec<u8>>,
+    started_at: Instant,
+}
+
+impl Recording {
+    fn create(conn_id: ConnectionId) -> Result<Self> {
+        let dir = logs_dir().join("launches");
+        std::fs::create_dir_all(&dir).with_context(|| format!("Failed to create {dir:?}"))?;
+        let timestamp = unix_millis(SystemTime::now());
+        let path = dir.join(format!("launch-{timestamp}-{conn_id}.log"));
+        let file =
+            std::fs::File::create(&path).with_context(|| format!("Failed to create {path:?}"))?;
+        Ok(Self {
+            path,
+            file: std::io::BufWriter::new(file),
+            pending: Some(Vec::new()),
+            started_at: Instant::now(),
+        })
+    }
+
+    /// Writes `header` above everything recorded so far.
+    fn write_header(&mut self, header: &str) -> std::io::Result<()> {
+        let pending = self.pending.take().unwrap_or_default();
+        self.file.write_all(header.as_bytes())?;
+        if !header.ends_with('\n') {
+            self.file.write_all(b"\n")?;
+        }
+        self.file.write_all(b"\n")?;
+        self.file.write_all(&pending)
+    }
+
+    /// Writes out anything held back for the header, which may never come if the launch failed,
+    /// and flushes the file.
+    fn flush(&mut self) -> std::io::Result<()> {
+        if let Some(pending) = self.pending.take() {
+            self.file.write_all(&pending)?;
+        }
+        self.file.flush()
+    }
+
+    fn write(&mut self, msg: &C2SMessage) -> std::io::Result<()> {
+        self.write_message(msg)?;
+        if self.pending.as_ref().is_some_and(|p| p.len() > MAX_PENDING) {
+            let pending = self.pending.take().unwrap();
+            self.file.write_all(&pending)?;
+        }
+        Ok(())
+    }
+
+    fn write_message(&mut self, msg: &C2SMessage) -> std::io::Result<()> {
+        let elapsed = self.started_at.elapsed();
+        let (secs, millis) = (elapsed.as_secs(), elapsed.subsec_millis());
+        let out: &mut dyn Write = match &mut self.pending {
+            Some(pending) => pending,
+            None => &mut self.file,
+        };
+        match msg {
+            C2SMessage::Log {
+                level,
+                scope,
+                message,
+            } => writeln!(
+                out,
+                "[{secs:>5}.{millis:03}] [{level:?}] [{scope}] {message}"
+            ),
+            C2SMessage::Output { channel, line } => {
+                write!(out, "[{secs:>5}.{millis:03}] [{}] ", channel.name())?;
+                let bytes = match line {
+                    OutputLine::Unicode(s) => s.as_bytes(),
+                    OutputLine::Bytes(b) => b,
+                };
+                out.write_all(bytes)?;
+                if !bytes.ends_with(b"\n") {
+                    out.write_all(b"\n")?;
+                }
+                Ok(())
+            }
+            C2SMessage::Exit { code } => {
+                writeln!(out, "[{secs:>5}.{millis:03}] Exited with code {code:?}")
+            }
+            C2SMessage::Crash { error } | C2SMessage::CrashReport(CrashReport { error, .. }) => {
+                writeln!(out, "[{secs:>5}.{millis:03}] Crashed: {error}")
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Writes every [`C2SMessage::Output`] and [`C2SMessage::Log`] of a connection, along with how
+/// the game exited, to a per-launch file in the logs directory.
+#[derive(Clone, Default)]
+pub struct LogRecorder {
+    recordings: Arc<Mutex<HashMap<ConnectionId, Recording>>>,
+    /// Paths of recordings that have been finished, so they can still be exported, oldest first.
+    finished: Arc<Mutex<VecDeque<(ConnectionId, PathBuf)>>>,
+}
+
+impl LogRecorder {
+    /// Records `msg`, starting a new recording for `conn_id` if there isn't one already.
+    pub fn record(&self, log: &slog::Logger, conn_id: ConnectionId, msg: &C2SMessage) {
+        if !matches!(
+            msg,
+            C2SMessage::Log { .. }
+                | C2SMessage::Output { .. }
+                | C2SMessage::Exit { .. }
+                | C2SMessage::Crash { .. }
+                | C2SMessage::CrashReport(_)
+        ) {
+            return;
+        }
+        let mut recordings = self.recordings.lock();
+        let Some(recording) = Self::recording(&mut recordings, log, conn_id) else {
+            return;
+        };
+        if let Err(e) = recording.write(msg) {
+            slog::error!(log, "Failed to record message to {:?}: {e}", recording.path; "conn_id" => conn_id);
+        }
+    }
+
+    /// Writes `header` at the top of the recording for `conn_id`, above any messages recorded
+    /// before it, unless so many were recorded that they had to be written out already.
+    pub fn record_header(&self, log: &slog::Logger, conn_id: ConnectionId, header: &str) {
+        let mut recordings = self.recordings.lock();
+        let Some(recording) = Self::recording(&mut recordings, log, conn_id) else {
+            return;
+        };
+        if let Err(e) = recording.write_header(header) {
+            slog::error!(log, "Failed to record header to {:?}: {e}", recording.path; "conn_id" => conn_id);
+        }
+    }
+
+    /// Returns the recording for `conn_id`, starting a new one if there isn't one already.
+    fn recording<'a>(
+        recordings: &'a mut HashMap<ConnectionId, Recording>,
+        log: &slog::Logger,
+        conn_id: ConnectionId,
+    ) -> Option<&'a mut Recording> {
+        match recordings.entry(conn_id) {
+            std::collections::hash_map::Entry::Occupied(e) => Some(e.into_mut()),
+            std::collections::hash_map::Entry::Vacant(e) => match Recording::create(conn_id) {
+                Ok(t) => Some(e.insert(t)),
+                Err(e) => {
+                    slog::error!(log, "Failed to start log recording: {e:#}"; "conn_id" => conn_id);
+                    None
+                }
+            },
+        }
+    }
+
+    /// Flushes and closes the recording for `conn_id`.
+    pub fn finish(&self, log: &slog::Logger, conn_id: ConnectionId) {
+        let Some(mut recording) = self.recordings.lock().remove(&conn_id) else {
+            return;
+        };
+        if let Err(e) = recording.flush() {
+            slog::error!(log, "Failed to flush {:?}: {e}", recording.path; "conn_id" => conn_id);
+        }
+        let mut finished = self.finished.lock();
+        if finished.len() >= MAX_FINISHED {
+            finished.pop_front();
+        }
+        finished.push_back((conn_id, recording.path));
+    }
+
+    /// Returns the path of the recording for `conn_id`, flushing it first if it is still in
+    /// progress.
+    pub fn path(&self, conn_id: ConnectionId) -> Result<Option<PathBuf>> {
+        if let Some(recording) = self.recordings.lock().get_mut(&conn_id) {
+            recording
+                .flush()
+                .with_context(|| format!("Failed to flush {:?}", recording.path))?;
+            return Ok(Some(recording.path.clone()));
+        }
+        Ok(self
+            .finished
+            .lock()
+            .iter()
+            .find(|(id, _)| *id == conn_id)
+            .map(|(_, path)| path.clone()))
+    }
+
+    /// Copies the recording for `conn_id` to `destination`.
+    pub fn export(&self, conn_id: ConnectionId, destination: &Path) -> Result<PathBuf> {
+        let path = self
+            .path(conn_id)?
+            .with_context(|| format!("No log has been recorded for connection {conn_id}"))?;
+        std::fs::copy(&path, destination)
+            .with_context(|| format!("Failed to copy {path:?} to {destination:?}"))?;
+        Ok(destination.to_owned())
+    }
+}
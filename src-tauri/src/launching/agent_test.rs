@@ -0,0 +1,410 @@
+//! A dry run of a modded launch that checks that the agent can be injected and can talk to the
+//! app, without waiting for a game to boot.
+//!
+//! Manderrow's own executable stands in for the game, started with [`TARGET_COMMAND`]. It is run
+//! through the same wrapper as a game would be, or has the agent injected into it on Windows, and
+//! each step of the handshake with the agent is reported as it completes.
+
+use std::ffi::{c_void, CStr};
+use std::path::PathBuf;
+use std::ptr::NonNull;
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, bail, Context as _, Result};
+use manderrow_ipc::ipc_channel::ipc::{IpcOneShotServer, IpcReceiver, IpcSender, TryRecvError};
+use manderrow_ipc::{S2CMessage, HEARTBEAT_INTERVAL};
+use manderrow_types::games::Game;
+use slog::{debug, info};
+use tauri::ipc::Channel;
+use tauri::{AppHandle, Manager as _};
+use tokio::process::{Child, Command};
+
+use crate::ipc::C2SMessage;
+
+use super::{macos, wrappers};
+
+/// The subcommand of the Manderrow executable that stands in for the game, see [`run_target`].
+pub const TARGET_COMMAND: &str = "agent-test-target";
+
+/// How long the stand-in waits to be asked to exit before exiting on its own.
+const TARGET_LIFETIME: Duration = Duration::from_secs(60);
+
+/// How long each step may take, except for waiting for a heartbeat.
+const STEP_TIMEOUT: Duration = Duration::from_secs(15);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Step {
+    /// The agent was found.
+    ResolveAgent,
+    /// The stand-in was started with the agent.
+    Spawn,
+    /// The agent connected to the app.
+    Connect,
+    /// The agent reported the stand-in's pid.
+    Started,
+    /// The app connected back to the agent.
+    Handshake,
+    /// The agent sent a heartbeat.
+    Heartbeat,
+    /// The stand-in exited when asked to through the agent.
+    Exit,
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct StepReport {
+    pub step: Step,
+    /// Milliseconds since the test began.
+    pub elapsed_ms: u64,
+    /// Why the step failed. The test stops at the first step that fails.
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct AgentTestReport {
+    pub steps: Vec<StepReport>,
+    /// Whether the game runs through Proton, in which case the Windows agent that it would load is
+    /// not covered by the test.
+    pub uses_proton: bool,
+}
+
+/// Runs in the stand-in process until the agent makes it exit, which it asks for through the exit
+/// handler that is registered with it here, like a mod loader would.
+pub fn run_target() -> Result<()> {
+    type SetExitHandler = unsafe extern "C" fn(handler: Option<extern "C" fn()>);
+
+    extern "C" fn exit() {
+        std::process::exit(0);
+    }
+
+    let start = Instant::now();
+    // on Windows, the agent is injected after the stand-in has started
+    while start.elapsed() < STEP_TIMEOUT {
+        if let Some(f) = find_agent_fn(c"manderrow_set_exit_handler") {
+            let set_exit_handler = unsafe { std::mem::transmute::<_, SetExitHandler>(f) };
+            unsafe { set_exit_handler(Some(exit)) };
+            break;
+        }
+        std::thread::sleep(Duration::from_millis(100));
+    }
+
+    std::thread::sleep(TARGET_LIFETIME.saturating_sub(start.elapsed()));
+    Ok(())
+}
+
+/// Looks up a function that the agent exports in the stand-in process.
+#[cfg(unix)]
+fn find_agent_fn(name: &CStr) -> Option<NonNull<c_void>> {
+    use std::ffi::c_char;
+
+    unsafe extern "C" {
+        fn dlsym(handle: *mut c_void, symbol: *const c_char) -> *mut c_void;
+    }
+    #[cfg(target_os = "macos")]
+    const RTLD_DEFAULT: *mut c_void = -2isize as *mut c_void;
+    #[cfg(not(target_os = "macos"))]
+    const RTLD_DEFAULT: *mut c_void = std::ptr::null_mut();
+
+    NonNull::new(unsafe { dlsym(RTLD_DEFAULT, name.as_ptr()) })
+}
+
+/// Looks up a function that the agent exports in the stand-in process.
+#[cfg(windows)]
+fn find_agent_fn(name: &CStr) -> Option<NonNull<c_void>> {
+    use windows::core::PCSTR;
+    use windows::Win32::Foundation::CloseHandle;
+    use windows::Win32::System::Diagnostics::ToolHelp::{
+        CreateToolhelp32Snapshot, Module32FirstW, Module32NextW, MODULEENTRY32W, TH32CS_SNAPMODULE,
+    };
+    use windows::Win32::System::LibraryLoader::GetProcAddress;
+
+    let snapshot = unsafe { CreateToolhelp32Snapshot(TH32CS_SNAPMODULE, 0) }.ok()?;
+    let mut entry = MODULEENTRY32W {
+        dwSize: size_of::<MODULEENTRY32W>() as u32,
+        ..Default::default()
+    };
+    let mut found = None;
+    let mut more = unsafe { Module32FirstW(snapshot, &mut entry) }.is_ok();
+    while more {
+        if let Some(f) = unsafe { GetProcAddress(entry.hModule, PCSTR(name.as_ptr().cast())) } {
+            found = NonNull::new(f as *mut c_void);
+            break;
+        }
+        more = unsafe { Module32NextW(snapshot, &mut entry) }.is_ok();
+    }
+    _ = unsafe { CloseHandle(snapshot) };
+    found
+}
+
+/// Marks a step as failed, after it has been reported.
+struct Failed;
+
+struct Steps<'a> {
+    log: &'a slog::Logger,
+    on_step: &'a Channel<StepReport>,
+    start: Instant,
+    reports: Vec<StepReport>,
+}
+
+impl Steps<'_> {
+    async fn run<T>(
+        &mut self,
+        step: Step,
+        fut: impl Future<Output = Result<T>>,
+    ) -> Result<T, Failed> {
+        let result = fut.await;
+        let report = StepReport {
+            step,
+            elapsed_ms: self.start.elapsed().as_millis() as u64,
+            error: result.as_ref().err().map(|e| format!("{e:#}")),
+        };
+        match &report.error {
+            None => debug!(self.log, "Agent test step {step:?} passed"),
+            Some(e) => info!(self.log, "Agent test step {step:?} failed: {e}"),
+        }
+        _ = self.on_step.send(report.clone());
+        self.reports.push(report);
+        result.map_err(|_| Failed)
+    }
+}
+
+/// Receives messages until one that `f` accepts, failing if none arrives before `timeout` or a
+/// crash is reported.
+async fn recv_until<T: Send + 'static>(
+    rx: &mut Option<IpcReceiver<C2SMessage>>,
+    timeout: Duration,
+    f: impl Fn(C2SMessage) -> Option<T> + Send + 'static,
+) -> Result<T> {
+    let receiver = rx
+        .take()
+        .context("The connection was lost in an earlier step")?;
+    let (receiver, result) = tokio::task::spawn_blocking(move || {
+        let deadline = Instant::now() + timeout;
+        let result = loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            match receiver.try_recv_timeout(remaining) {
+                Ok(C2SMessage::Crash { error }) => break Err(anyhow!("{error}")),
+                Ok(C2SMessage::CrashReport(report)) => break Err(anyhow!("{}", report.error)),
+                Ok(msg) => {
+                    if let Some(t) = f(msg) {
+                        break Ok(t);
+                    }
+                }
+                Err(TryRecvError::Empty) => {
+                    break Err(anyhow!("Timed out after {}s", timeout.as_secs()))
+                }
+                Err(TryRecvError::IpcError(e)) => {
+                    break Err(anyhow!("The connection was closed: {e:?}"))
+                }
+            }
+        };
+        (receiver, result)
+    })
+    .await?;
+    *rx = Some(receiver);
+    result
+}
+
+/// Fails if the stand-in exits before `fut` completes.
+async fn while_running<T>(child: &mut Child, fut: impl Future<Output = Result<T>>) -> Result<T> {
+    tokio::select! {
+        result = fut => result,
+        status = child.wait() => bail!("The process exited early with {}", status?),
+    }
+}
+
+/// Launches the stand-in with the agent and checks each step of the handshake with it, sending
+/// the report of each step to `on_step` as it completes.
+pub async fn test_agent_ipc(
+    app: &AppHandle,
+    log: &slog::Logger,
+    game: &Game<'_>,
+    on_step: &Channel<StepReport>,
+) -> Result<AgentTestReport> {
+    let uses_proton = match game
+        .store_platform_metadata
+        .iter()
+        .find_map(|m| m.steam_or_direct())
+    {
+        Some(steam) if cfg!(target_os = "linux") => {
+            crate::stores::steam::proton::uses_proton(log, steam.id)
+                .await
+                .unwrap_or(false)
+        }
+        _ => false,
+    };
+
+    let mut steps = Steps {
+        log,
+        on_step,
+        start: Instant::now(),
+        reports: Vec::new(),
+    };
+    _ = run_steps(app, log, &mut steps).await;
+    Ok(AgentTestReport {
+        steps: steps.reports,
+        uses_proton,
+    })
+}
+
+async fn run_steps(
+    app: &AppHandle,
+    log: &slog::Logger,
+    steps: &mut Steps<'_>,
+) -> Result<(), Failed> {
+    let agent_path = steps
+        .run(Step::ResolveAgent, async {
+            let path = match std::env::var_os("MANDERROW_AGENT_PATH") {
+                Some(path) => PathBuf::from(path),
+                None => app
+                    .path()
+                    .resolve("libmanderrow_agent", tauri::path::BaseDirectory::Resource)
+                    .context("Failed to resolve agent path")?,
+            };
+            if !tokio::fs::try_exists(&path).await? {
+                bail!("The agent is missing from {path:?}");
+            }
+            if cfg!(target_os = "macos") {
+                macos::stage_library(log, &path)
+                    .await
+                    .context("Failed to prepare agent for injection")
+            } else {
+                Ok(path)
+            }
+        })
+        .await?;
+
+    let (mut child, server, c2s_tx, _work_dir) = steps
+        .run(Step::Spawn, async {
+            let (server, c2s_tx) =
+                IpcOneShotServer::<C2SMessage>::new().context("Failed to create IPC server")?;
+            // the wrapper writes its logs to the working directory
+            let work_dir = tempfile::tempdir()?;
+
+            let exe = std::env::current_exe().context("Failed to get current exe path")?;
+            let mut command = Command::new(&exe);
+            if cfg!(windows) {
+                // the agent is injected once the process has started
+                command.arg(TARGET_COMMAND);
+                command.arg("{manderrow");
+            } else {
+                command.arg("wrap-with-injection");
+                command.arg(&exe);
+                command.arg(TARGET_COMMAND);
+                command.arg("{manderrow");
+                command.arg("--agent-path");
+                command.arg(&agent_path);
+                for arg in wrappers::for_launch(&Default::default())? {
+                    command.arg("--wrapper-arg");
+                    command.arg(arg);
+                }
+            }
+            command.arg("--enable");
+            command.arg("--c2s-tx");
+            command.arg(&c2s_tx);
+            command.arg("manderrow}");
+            command.current_dir(work_dir.path());
+            command.kill_on_drop(true);
+
+            info!(log, "Launching agent test: {command:?}");
+            let child = command
+                .spawn()
+                .context("Failed to start the test process")?;
+
+            #[cfg(windows)]
+            {
+                let handle = child
+                    .raw_handle()
+                    .context("The test process already exited")?;
+                let handle = windows::Win32::Foundation::HANDLE(handle);
+                let agent_path = agent_path.clone();
+                tokio::task::spawn_blocking(move || {
+                    crate::stores::gamepass::activate::inject_library(handle, &agent_path)
+                })
+                .await?
+                .with_context(|| format!("Failed to inject {agent_path:?}"))?;
+            }
+
+            Ok((child, server, c2s_tx, work_dir))
+        })
+        .await?;
+
+    let (c2s_rx, s2c_tx) = steps
+        .run(Step::Connect, async {
+            let accept = tokio::task::spawn_blocking(move || server.accept());
+            let result = while_running(&mut child, async {
+                tokio::time::timeout(STEP_TIMEOUT, accept)
+                    .await
+                    .map_err(|_| anyhow!("Timed out after {}s", STEP_TIMEOUT.as_secs()))?
+                    .map_err(anyhow::Error::from)
+            })
+            .await;
+            let result = match result {
+                Ok(t) => t,
+                Err(e) => {
+                    // connect and drop so that the server stops waiting
+                    _ = manderrow_ipc::ipc_channel::platform::OsIpcSender::connect(&c2s_tx);
+                    return Err(e);
+                }
+            };
+            match result.context("Failed to accept connection")? {
+                (c2s_rx, C2SMessage::Connect { s2c_tx }) => Ok((c2s_rx, s2c_tx)),
+                (_, msg) => bail!("Unexpected initial message: {msg:?}"),
+            }
+        })
+        .await?;
+    let mut c2s_rx = Some(c2s_rx);
+
+    steps
+        .run(Step::Started, async {
+            let pid = while_running(
+                &mut child,
+                recv_until(&mut c2s_rx, STEP_TIMEOUT, |msg| match msg {
+                    C2SMessage::Started { pid } => Some(pid),
+                    _ => None,
+                }),
+            )
+            .await?;
+            debug!(log, "Agent test process has pid {pid}");
+            Ok(())
+        })
+        .await?;
+
+    let s2c_tx = steps
+        .run(Step::Handshake, async {
+            let s2c_tx = IpcSender::<S2CMessage>::connect(s2c_tx)
+                .context("Failed to connect to the agent")?;
+            s2c_tx
+                .send(&S2CMessage::Connect)
+                .context("Failed to send to the agent")?;
+            Ok(s2c_tx)
+        })
+        .await?;
+
+    steps
+        .run(
+            Step::Heartbeat,
+            while_running(
+                &mut child,
+                recv_until(&mut c2s_rx, HEARTBEAT_INTERVAL * 3, |msg| {
+                    matches!(msg, C2SMessage::Heartbeat).then_some(())
+                }),
+            ),
+        )
+        .await?;
+
+    steps
+        .run(Step::Exit, async {
+            s2c_tx
+                .send(&S2CMessage::RequestExit)
+                .context("Failed to send to the agent")?;
+            let status = tokio::time::timeout(STEP_TIMEOUT, child.wait())
+                .await
+                .map_err(|_| anyhow!("Timed out after {}s", STEP_TIMEOUT.as_secs()))??;
+            debug!(log, "Agent test process exited with {status}");
+            Ok(())
+        })
+        .await?;
+
+    Ok(())
+}
@@ -0,0 +1,336 @@
+//! Detection of the CPU architecture that an executable or library was built for, by inspecting
+//! its PE, ELF, or Mach-O header.
+
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context as _, Result};
+use manderrow_types::games::Game;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Arch {
+    X86,
+    X86_64,
+    Arm,
+    Aarch64,
+    /// ARM64 code that follows the x64 calling conventions, so that it can be mixed with emulated
+    /// x64 code on Windows on ARM.
+    Arm64EC,
+}
+
+impl Arch {
+    pub const ALL: &[Self] = &[
+        Self::X86,
+        Self::X86_64,
+        Self::Arm,
+        Self::Aarch64,
+        Self::Arm64EC,
+    ];
+
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::X86 => "x86",
+            Self::X86_64 => "x86_64",
+            Self::Arm => "arm",
+            Self::Aarch64 => "aarch64",
+            Self::Arm64EC => "arm64ec",
+        }
+    }
+
+    /// Whether a library built for `module` can be loaded into a process running as `self`. On
+    /// Windows on ARM, x64 and ARM64EC code can be loaded into each other's processes.
+    pub fn can_load(self, module: Arch) -> bool {
+        self == module
+            || matches!(
+                (self, module),
+                (Self::X86_64, Self::Arm64EC) | (Self::Arm64EC, Self::X86_64)
+            )
+    }
+
+    /// Returns the CPU's native architecture, which may differ from the one Manderrow was built
+    /// for if it runs under emulation.
+    pub fn native() -> Option<Self> {
+        match manderrow_process_util::native_machine() {
+            Ok(Some(machine)) => return pe_archs(machine).ok().map(|archs| archs[0]),
+            Ok(None) => {}
+            Err(e) => slog_scope::warn!("Unable to determine native architecture: {e:#}"),
+        }
+        match std::env::consts::ARCH {
+            "x86" => Some(Self::X86),
+            "x86_64" => Some(Self::X86_64),
+            "arm" => Some(Self::Arm),
+            "aarch64" => Some(Self::Aarch64),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for Arch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryFormat {
+    Pe,
+    Elf,
+    MachO,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BinaryInfo {
+    pub format: BinaryFormat,
+    /// The architectures the binary contains code for. Only universal Mach-O binaries have more
+    /// than one.
+    pub archs: Vec<Arch>,
+}
+
+impl BinaryInfo {
+    pub fn supports(&self, arch: Arch) -> bool {
+        self.archs.contains(&arch)
+    }
+
+    /// Whether the library can be loaded into a process running as `arch`.
+    pub fn loadable_into(&self, arch: Arch) -> bool {
+        self.archs.iter().any(|&a| arch.can_load(a))
+    }
+}
+
+fn read_array<const N: usize>(rdr: &mut impl Read) -> std::io::Result<[u8; N]> {
+    let mut buf = [0u8; N];
+    rdr.read_exact(&mut buf)?;
+    Ok(buf)
+}
+
+/// Most ARM64EC images declare themselves as x64 and are reported as such, which is harmless since
+/// the agent for x64 can be loaded into them. Only those that declare the ARM64EC machine type,
+/// and ARM64X images, are reported as [`Arch::Arm64EC`].
+fn pe_archs(machine: u16) -> Result<&'static [Arch]> {
+    Ok(match machine {
+        0x014c => &[Arch::X86],
+        0x8664 => &[Arch::X86_64],
+        0x01c0 | 0x01c2 | 0x01c4 => &[Arch::Arm],
+        0xaa64 => &[Arch::Aarch64],
+        0xa641 => &[Arch::Arm64EC],
+        // ARM64X, which contains both ARM64 and ARM64EC code
+        0xa64e => &[Arch::Aarch64, Arch::Arm64EC],
+        _ => bail!("Unsupported PE machine type {machine:#06x}"),
+    })
+}
+
+fn elf_arch(machine: u16) -> Result<Arch> {
+    Ok(match machine {
+        3 => Arch::X86,
+        62 => Arch::X86_64,
+        40 => Arch::Arm,
+        183 => Arch::Aarch64,
+        _ => bail!("Unsupported ELF machine type {machine}"),
+    })
+}
+
+fn mach_o_arch(cpu_type: u32) -> Result<Arch> {
+    const CPU_ARCH_ABI64: u32 = 0x0100_0000;
+    const CPU_TYPE_X86: u32 = 7;
+    const CPU_TYPE_X86_64: u32 = CPU_TYPE_X86 | CPU_ARCH_ABI64;
+    const CPU_TYPE_ARM: u32 = 12;
+    const CPU_TYPE_ARM64: u32 = CPU_TYPE_ARM | CPU_ARCH_ABI64;
+    Ok(match cpu_type {
+        CPU_TYPE_X86 => Arch::X86,
+        CPU_TYPE_X86_64 => Arch::X86_64,
+        CPU_TYPE_ARM => Arch::Arm,
+        CPU_TYPE_ARM64 => Arch::Aarch64,
+        _ => bail!("Unsupported Mach-O CPU type {cpu_type:#x}"),
+    })
+}
+
+/// Reads the header of a binary to determine its format and architecture.
+pub fn read_binary_info(mut rdr: impl Read + Seek) -> Result<BinaryInfo> {
+    let magic = read_array::<4>(&mut rdr).context("File is too short to be a binary")?;
+    match magic {
+        [b'M', b'Z', ..] => {
+            rdr.seek(SeekFrom::Start(0x3c))?;
+            let pe_offset = u32::from_le_bytes(read_array(&mut rdr)?);
+            rdr.seek(SeekFrom::Start(pe_offset.into()))?;
+            if read_array::<4>(&mut rdr)? != *b"PE\0\0" {
+                bail!("Missing PE signature");
+            }
+            let machine = u16::from_le_bytes(read_array(&mut rdr)?);
+            Ok(BinaryInfo {
+                format: BinaryFormat::Pe,
+                archs: pe_archs(machine)?.to_vec(),
+            })
+        }
+        [0x7f, b'E', b'L', b'F'] => {
+            let [_class, data] = read_array::<2>(&mut rdr)?;
+            rdr.seek(SeekFrom::Start(18))?;
+            let machine = read_array(&mut rdr)?;
+            let machine = match data {
+                1 => u16::from_le_bytes(machine),
+                2 => u16::from_be_bytes(machine),
+                _ => bail!("Invalid ELF data encoding {data}"),
+            };
+            Ok(BinaryInfo {
+                format: BinaryFormat::Elf,
+                archs: vec![elf_arch(machine)?],
+            })
+        }
+        [0xfe, 0xed, 0xfa, 0xce | 0xcf] => Ok(BinaryInfo {
+            format: BinaryFormat::MachO,
+            archs: vec![mach_o_arch(u32::from_be_bytes(read_array(&mut rdr)?))?],
+        }),
+        [0xce | 0xcf, 0xfa, 0xed, 0xfe] => Ok(BinaryInfo {
+            format: BinaryFormat::MachO,
+            archs: vec![mach_o_arch(u32::from_le_bytes(read_array(&mut rdr)?))?],
+        }),
+        [0xca, 0xfe, 0xba, 0xbe | 0xbf] => {
+            // fat_arch is 20 bytes, fat_arch_64 is 32
+            let entry_len = if magic[3] == 0xbe { 20 } else { 32 };
+            let count = u32::from_be_bytes(read_array(&mut rdr)?);
+            // Java class files share the magic number, but their version is always well above
+            // any sane number of architectures.
+            if count == 0 || count > 16 {
+                bail!("Unrecognized binary format");
+            }
+            let mut archs = Vec::with_capacity(count as usize);
+            for i in 0..count {
+                rdr.seek(SeekFrom::Start(8 + u64::from(i) * entry_len))?;
+                archs.push(mach_o_arch(u32::from_be_bytes(read_array(&mut rdr)?))?);
+            }
+            Ok(BinaryInfo {
+                format: BinaryFormat::MachO,
+                archs,
+            })
+        }
+        _ => bail!("Unrecognized binary format"),
+    }
+}
+
+/// Reads the header of the binary at `path`. See [`read_binary_info`].
+pub async fn binary_info(path: &Path) -> Result<BinaryInfo> {
+    let path = path.to_owned();
+    tokio::task::spawn_blocking(move || {
+        let file =
+            std::fs::File::open(&path).with_context(|| format!("Failed to open {path:?}"))?;
+        read_binary_info(std::io::BufReader::new(file))
+            .with_context(|| format!("Failed to read binary header of {path:?}"))
+    })
+    .await?
+}
+
+/// Finds the game's executable in `install_dir`. For app bundles, this is the binary inside of
+/// the bundle.
+pub async fn find_game_executable(game: &Game<'_>, install_dir: &Path) -> Result<Option<PathBuf>> {
+    for name in &game.exe_names {
+        let path = install_dir.join(&**name);
+        if !tokio::fs::try_exists(&path).await? {
+            continue;
+        }
+        if name.ends_with(".app") {
+            let mut macos_dir = path;
+            macos_dir.push("Contents");
+            macos_dir.push("MacOS");
+            let mut iter = tokio::fs::read_dir(&macos_dir)
+                .await
+                .with_context(|| format!("Failed to read {macos_dir:?}"))?;
+            while let Some(e) = iter.next_entry().await? {
+                if e.file_type().await?.is_file() {
+                    return Ok(Some(e.path()));
+                }
+            }
+        } else {
+            return Ok(Some(path));
+        }
+    }
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    fn pe(machine: u16) -> Vec<u8> {
+        let mut buf = vec![0u8; 0x80];
+        buf[..2].copy_from_slice(b"MZ");
+        buf[0x3c..0x40].copy_from_slice(&0x40u32.to_le_bytes());
+        buf[0x40..0x44].copy_from_slice(b"PE\0\0");
+        buf[0x44..0x46].copy_from_slice(&machine.to_le_bytes());
+        buf
+    }
+
+    #[test]
+    fn test_pe() {
+        let info = read_binary_info(Cursor::new(pe(0x8664))).unwrap();
+        assert_eq!(info.format, BinaryFormat::Pe);
+        assert_eq!(info.archs, [Arch::X86_64]);
+        assert_eq!(
+            read_binary_info(Cursor::new(pe(0x014c))).unwrap().archs,
+            [Arch::X86]
+        );
+        assert!(read_binary_info(Cursor::new(pe(0x0200))).is_err());
+    }
+
+    #[test]
+    fn test_pe_arm64() {
+        assert_eq!(
+            read_binary_info(Cursor::new(pe(0xaa64))).unwrap().archs,
+            [Arch::Aarch64]
+        );
+        let arm64x = read_binary_info(Cursor::new(pe(0xa64e))).unwrap();
+        assert_eq!(arm64x.archs, [Arch::Aarch64, Arch::Arm64EC]);
+        assert!(arm64x.loadable_into(Arch::X86_64));
+        assert!(arm64x.loadable_into(Arch::Aarch64));
+        assert!(!arm64x.loadable_into(Arch::X86));
+    }
+
+    #[test]
+    fn test_can_load() {
+        assert!(Arch::X86_64.can_load(Arch::X86_64));
+        assert!(Arch::X86_64.can_load(Arch::Arm64EC));
+        assert!(Arch::Arm64EC.can_load(Arch::X86_64));
+        assert!(!Arch::Aarch64.can_load(Arch::X86_64));
+        assert!(!Arch::X86_64.can_load(Arch::X86));
+    }
+
+    #[test]
+    fn test_elf() {
+        let mut buf = vec![0u8; 64];
+        buf[..4].copy_from_slice(b"\x7fELF");
+        buf[4] = 2;
+        buf[5] = 1;
+        buf[18..20].copy_from_slice(&183u16.to_le_bytes());
+        let info = read_binary_info(Cursor::new(buf)).unwrap();
+        assert_eq!(info.format, BinaryFormat::Elf);
+        assert_eq!(info.archs, [Arch::Aarch64]);
+    }
+
+    #[test]
+    fn test_mach_o() {
+        let mut buf = vec![0xcf, 0xfa, 0xed, 0xfe];
+        buf.extend_from_slice(&0x0100_0007u32.to_le_bytes());
+        let info = read_binary_info(Cursor::new(buf)).unwrap();
+        assert_eq!(info.format, BinaryFormat::MachO);
+        assert_eq!(info.archs, [Arch::X86_64]);
+    }
+
+    #[test]
+    fn test_mach_o_universal() {
+        let mut buf = vec![0xca, 0xfe, 0xba, 0xbe];
+        buf.extend_from_slice(&2u32.to_be_bytes());
+        for cpu_type in [0x0100_0007u32, 0x0100_000c] {
+            buf.extend_from_slice(&cpu_type.to_be_bytes());
+            buf.extend_from_slice(&[0u8; 16]);
+        }
+        let info = read_binary_info(Cursor::new(buf)).unwrap();
+        assert_eq!(info.archs, [Arch::X86_64, Arch::Aarch64]);
+        assert!(info.supports(Arch::Aarch64));
+    }
+
+    #[test]
+    fn test_unrecognized() {
+        assert!(read_binary_info(Cursor::new(b"#!/bin/sh\n")).is_err());
+        assert!(read_binary_info(Cursor::new(b"MZ")).is_err());
+    }
+}
@@ -1,20 +1,32 @@
 use std::path::PathBuf;
 
-use anyhow::{bail, Result};
+use anyhow::{bail, Context as _, Result};
 use manderrow_types::games::Game;
+use slog::debug;
 use tauri::AppHandle;
 use tempfile::tempdir;
 use uuid::Uuid;
 
 use crate::installing::{fetch_resource_cached_by_hash_at_path, install_zip};
-use crate::profiles::{profile_path, CONFIG_FOLDER, MODS_FOLDER, PATCHERS_FOLDER};
+use crate::profiles::configs::{write_managed_config, ConfigValues};
+use crate::profiles::{
+    profile_path, LaunchOptions, LoaderLogLevel, LoaderVersion, CONFIG_FOLDER, MODS_FOLDER,
+    PATCHERS_FOLDER,
+};
 use crate::stores::steam::proton::adapt_host_path;
-use crate::Reqwest;
 
+use super::github::{self, ReleaseAsset};
 use super::InstructionEmitter;
 
+/// The version of BepInEx that Manderrow's builds are based on.
+const VERSION: &str = "5.4.23.2";
+/// The build of Manderrow's fork of [`VERSION`] that is used for stable launches.
+const BUILD: u32 = 20;
+
+const DOORSTOP_VERSION: &str = "4.3.0";
+const DOORSTOP_BUILD: u32 = 14;
+
 fn get_url_and_hash(uses_proton: bool) -> Result<(String, &'static str)> {
-    let build = 20;
     let (target, hash) = match (std::env::consts::OS, std::env::consts::ARCH, uses_proton) {
         ("linux", "x86_64", false) => (
             "linux_x64",
@@ -28,7 +40,8 @@ fn get_url_and_hash(uses_proton: bool) -> Result<(String, &'static str)> {
             "macos_x64",
             "84895f02a4fe22526bc52f53cd59025494e721635fb330d41e370d1d310548b4",
         ),
-        ("linux", "x86_64", true) | ("windows", "x86_64", false) => (
+        // see get_doorstop_url_and_hash
+        ("linux", "x86_64", true) | ("windows", "x86_64" | "aarch64", false) => (
             "win_x64",
             "84895f02a4fe22526bc52f53cd59025494e721635fb330d41e370d1d310548b4",
         ),
@@ -40,27 +53,85 @@ fn get_url_and_hash(uses_proton: bool) -> Result<(String, &'static str)> {
             "Unsupported platform combo: (os: {os:?}, arch: {arch:?}, uses_proton: {uses_proton})"
         ),
     };
-    let url = format!("https://github.com/manderrow/BepInEx/releases/download/v5.4.23.2%2Bbuild.{build}/BepInEx_{target}_5.4.23.2.zip");
+    let url = format!("https://github.com/manderrow/BepInEx/releases/download/v{VERSION}%2Bbuild.{BUILD}/BepInEx_{target}_{VERSION}.zip");
 
     Ok((url, hash))
 }
 
+fn get_target(uses_proton: bool) -> Result<&'static str> {
+    Ok(
+        match (std::env::consts::OS, std::env::consts::ARCH, uses_proton) {
+            ("linux", "x86_64", false) => "linux_x64",
+            ("linux", "x86", false) => "linux_x86",
+            ("macos", "x86_64", false) => "macos_x64",
+            ("linux", "x86_64", true) | ("windows", "x86_64" | "aarch64", false) => "win_x64",
+            ("linux", "x86", true) | ("windows", "x86", false) => "win_x86",
+            (os, arch, uses_proton) => bail!(
+                "Unsupported platform combo: (os: {os:?}, arch: {arch:?}, uses_proton: {uses_proton})"
+            ),
+        },
+    )
+}
+
 fn get_ci_url(uses_proton: bool) -> Result<String> {
-    let target = match (std::env::consts::OS, std::env::consts::ARCH, uses_proton) {
-        ("linux", "x86_64", false) => "linux_x64",
-        ("linux", "x86", false) => "linux_x86",
-        ("macos", "x86_64", false) => "macos_x64",
-        ("linux", "x86_64", true) | ("windows", "x86_64", false) => "win_x64",
-        ("linux", "x86", true) | ("windows", "x86", false) => "win_x86",
-        (os, arch, uses_proton) => bail!(
-            "Unsupported platform combo: (os: {os:?}, arch: {arch:?}, uses_proton: {uses_proton})"
-        ),
-    };
+    let target = get_target(uses_proton)?;
     Ok(format!(
-        "https://github.com/manderrow/BepInEx/releases/download/ci/BepInEx_{target}_5.4.23.2.zip"
+        "https://github.com/manderrow/BepInEx/releases/download/ci/BepInEx_{target}_{VERSION}.zip"
     ))
 }
 
+/// `release` is a version as returned by [`list_releases`], like `5.4.23.2+build.20`.
+/// Returns the directory that `release` is extracted to. Each target gets its own directory, as
+/// a game may be launched both natively and through Proton.
+fn get_release_path(release: &str, uses_proton: bool) -> Result<PathBuf> {
+    anyhow::ensure!(
+        release
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '+' | '-')),
+        "Invalid BepInEx version {release:?}"
+    );
+    let target = get_target(uses_proton)?;
+    Ok(crate::launching::LOADERS_DIR.join(format!("BepInEx-{release}-{target}")))
+}
+
+/// `release` must have been checked by [`get_release_path`].
+async fn get_release_asset(release: &str, uses_proton: bool) -> Result<ReleaseAsset> {
+    let target = get_target(uses_proton)?;
+    let base = release.split_once('+').map_or(release, |(base, _)| base);
+    let tag = release.replace('+', "%2B");
+    github::get_release_asset(
+        "manderrow/BepInEx",
+        &format!("v{tag}"),
+        &format!("BepInEx_{target}_{base}.zip"),
+    )
+    .await
+}
+
+/// Lists the versions of Manderrow's BepInEx builds that have been released, newest first.
+pub async fn list_releases() -> Result<Vec<String>> {
+    #[derive(serde::Deserialize)]
+    struct Release {
+        tag_name: String,
+        draft: bool,
+    }
+
+    let bytes = crate::util::http::client()
+        .get("https://api.github.com/repos/manderrow/BepInEx/releases?per_page=100")
+        .header(reqwest::header::ACCEPT, "application/vnd.github+json")
+        .send()
+        .await?
+        .error_for_status()?
+        .bytes()
+        .await?;
+    Ok(serde_json::from_slice::<Vec<Release>>(&bytes)
+        .context("Failed to parse BepInEx releases")?
+        .into_iter()
+        .filter(|release| !release.draft)
+        // the CI build is published as a release too
+        .filter_map(|release| release.tag_name.strip_prefix('v').map(str::to_owned))
+        .collect())
+}
+
 struct PdbArtifact {
     url: String,
     hash: &'static str,
@@ -74,8 +145,7 @@ struct LibraryArtifact {
 }
 
 fn doorstop_url(artifact: &str, suffix: &str) -> String {
-    let build = 14;
-    format!("https://github.com/manderrow/UnityDoorstop/releases/download/v4.3.0%2Bmanderrow.{build}/{artifact}{suffix}")
+    format!("https://github.com/manderrow/UnityDoorstop/releases/download/v{DOORSTOP_VERSION}%2Bmanderrow.{DOORSTOP_BUILD}/{artifact}{suffix}")
 }
 
 fn get_doorstop_url_and_hash(uses_proton: bool) -> Result<LibraryArtifact> {
@@ -101,7 +171,9 @@ fn get_doorstop_url_and_hash(uses_proton: bool) -> Result<LibraryArtifact> {
                 "f2816390111a1979998e66cc1d67962aaac8c689c343b47e4c65028dbdb48e4c",
                 pdb_hash=None
             ),
-            ("linux", "x86", false) => todo!(),
+            ("linux", "x86", false) => {
+                bail!("UnityDoorstop is not available for 32-bit Linux games")
+            }
             ("macos", "x86_64", false) => doorstop_artifact!(
                 "libUnityDoorstop_x86_64",
                 ".dylib",
@@ -114,7 +186,8 @@ fn get_doorstop_url_and_hash(uses_proton: bool) -> Result<LibraryArtifact> {
                 "fe0048c13ffdb867cff765a058a06151bfb9bf8096329fe4063cd61d9fcb5149",
                 pdb_hash=None
             ),
-            ("linux", "x86_64", true) | ("windows", "x86_64", false) => doorstop_artifact!(
+            // Unity games for Windows on ARM are nearly always x64 builds that run under emulation
+            ("linux", "x86_64", true) | ("windows", "x86_64" | "aarch64", false) => doorstop_artifact!(
                 "UnityDoorstop_x86_64",
                 ".dll",
                 "5fac088052e6256f362d4b951c46d2c017900a9655c0e9464aacb4664ba636c8",
@@ -133,10 +206,104 @@ fn get_doorstop_url_and_hash(uses_proton: bool) -> Result<LibraryArtifact> {
     )
 }
 
-#[derive(Clone, Copy)]
+/// Whether BepInEx can be loaded into games on this platform.
+pub fn is_supported(uses_proton: bool) -> bool {
+    // the same artifacts that emit_instructions uses
+    get_url_and_hash(false).is_ok() && get_doorstop_url_and_hash(uses_proton).is_ok()
+}
+
+#[derive(Clone)]
 pub enum BepInExVersion {
     Stable,
     Ci,
+    /// A specific release, as returned by [`list_releases`].
+    Release(String),
+}
+
+impl BepInExVersion {
+    /// Picks the version for a profile that is launched with `launch_options`. The CI build can
+    /// also be forced with the `BEPINEX_CI` environment variable.
+    pub fn for_launch(launch_options: &LaunchOptions) -> Self {
+        match std::env::var_os("BEPINEX_CI") {
+            Some(s) if !s.is_empty() && s != "0" => return Self::Ci,
+            _ => {}
+        }
+        match &launch_options.loader_version {
+            LoaderVersion::Stable => Self::Stable,
+            LoaderVersion::Ci => Self::Ci,
+            LoaderVersion::Release(release) => Self::Release(release.to_string()),
+        }
+    }
+}
+
+/// Where the BepInEx installation used for a launch comes from.
+#[derive(Clone)]
+pub enum BepInExSource {
+    /// A copy downloaded and managed by Manderrow.
+    Managed(BepInExVersion),
+    /// An installation found in the game directory that the user chose to adopt. The path is
+    /// the directory containing the `BepInEx` folder.
+    Adopted(PathBuf),
+}
+
+impl BepInExSource {
+    /// Describes the loader for the launch's log, including the version of the doorstop that
+    /// loads it unless `doorstop_overridden`.
+    pub fn describe(&self, doorstop_overridden: bool) -> String {
+        let mut s = match self {
+            Self::Managed(BepInExVersion::Stable) => format!("BepInEx {VERSION}+build.{BUILD}"),
+            Self::Managed(BepInExVersion::Ci) => format!("BepInEx {VERSION} (CI build)"),
+            Self::Managed(BepInExVersion::Release(release)) => {
+                format!("BepInEx {release} (pinned)")
+            }
+            Self::Adopted(path) => format!("BepInEx adopted from {path:?}"),
+        };
+        if !doorstop_overridden {
+            s.push_str(&format!(
+                ", UnityDoorstop {DOORSTOP_VERSION}+manderrow.{DOORSTOP_BUILD}"
+            ));
+        }
+        s
+    }
+}
+
+/// The name of BepInEx's own config file, in the profile's config folder.
+pub const CORE_CONFIG_FILE_NAME: &str = "BepInEx.cfg";
+
+/// BepInEx's `LogLevels` setting for `level`.
+fn log_levels(level: LoaderLogLevel) -> &'static str {
+    match level {
+        LoaderLogLevel::Error => "Fatal, Error",
+        LoaderLogLevel::Warning => "Fatal, Error, Warning",
+        LoaderLogLevel::Info => "Fatal, Error, Warning, Message, Info",
+        LoaderLogLevel::Debug => "Fatal, Error, Warning, Message, Info, Debug",
+        LoaderLogLevel::All => "All",
+    }
+}
+
+/// The values that Manderrow manages in [`CORE_CONFIG_FILE_NAME`], derived from the profile's
+/// launch options. The log levels apply both to BepInEx's console and to what is sent to
+/// Manderrow.
+fn core_config_values(options: &LaunchOptions) -> ConfigValues {
+    let mut values = ConfigValues::new();
+    let mut set = |section: &str, key: &str, value: &str| {
+        values
+            .entry(section.to_owned())
+            .or_default()
+            .insert(key.to_owned(), value.to_owned());
+    };
+    set("Logging", "UnityLogListening", "true");
+    set(
+        "Logging.Console",
+        "Enabled",
+        &options.show_console.to_string(),
+    );
+    set(
+        "Logging.Console",
+        "LogLevels",
+        log_levels(options.log_level),
+    );
+    values
 }
 
 /// Returns the absolute path to the BepInEx installation. If BepInEx has not yet been
@@ -146,28 +313,52 @@ pub async fn get_bep_in_ex_path(
     version: BepInExVersion,
     uses_proton: bool,
 ) -> Result<PathBuf> {
-    let (url, cache, path) = match version {
+    // the digest is only known for specific releases, as the stable build is cached by its hash
+    let (url, asset, cache, path) = match version {
         BepInExVersion::Stable => {
             let (url, hash) = get_url_and_hash(uses_proton)?;
             (
                 url,
+                None,
                 Some(crate::installing::CacheOptions::by_hash(hash)),
                 crate::launching::LOADERS_DIR.join(hash),
             )
         }
         BepInExVersion::Ci => (
             get_ci_url(uses_proton)?,
+            None,
             // TODO: maybe cache by etag or something?
             None,
             crate::launching::LOADERS_DIR.join("BepInEx-ci"),
         ),
+        BepInExVersion::Release(release) => {
+            let path = get_release_path(&release, uses_proton)?;
+            // a release never changes once published, so there is no need to ask GitHub (which
+            // allows only 60 unauthenticated requests per hour) again, and launching works offline
+            if tokio::fs::try_exists(&path).await? {
+                debug!(log, "Using extracted BepInEx {release} at {path:?}");
+                return Ok(path);
+            }
+            let asset = get_release_asset(&release, uses_proton).await?;
+            (
+                asset.url.clone(),
+                Some(asset),
+                Some(crate::installing::CacheOptions::by_url()),
+                path,
+            )
+        }
+    };
+
+    // keeps the verified copy cached until it has been extracted
+    let _pin = match &asset {
+        Some(asset) => Some(github::fetch_verified(None, log, "BepInEx", asset).await?),
+        None => None,
     };
 
     install_zip(
         // TODO: communicate via IPC
         None,
         log,
-        &Reqwest(reqwest::Client::new()),
         format!("BepInEx"),
         &url,
         cache,
@@ -189,15 +380,27 @@ pub async fn emit_instructions(
     em: &mut InstructionEmitter<'_>,
     game: &Game<'_>,
     profile_id: Uuid,
-    version: BepInExVersion,
+    source: BepInExSource,
+    options: &LaunchOptions,
     doorstop_path: Option<PathBuf>,
     legacy_doorstop: bool,
     uses_proton: bool,
 ) -> anyhow::Result<()> {
-    let bep_in_ex = get_bep_in_ex_path(log, version, false).await?;
+    let bep_in_ex = match source {
+        BepInExSource::Managed(version) => get_bep_in_ex_path(log, version, false).await?,
+        BepInExSource::Adopted(path) => path,
+    };
 
     let profile_path = profile_path(profile_id);
 
+    write_managed_config(
+        profile_id,
+        CORE_CONFIG_FILE_NAME,
+        &core_config_values(options),
+    )
+    .await
+    .context("Failed to write BepInEx core config")?;
+
     let temp_dir = tempdir()?.keep();
 
     em.set_var(
@@ -290,11 +493,11 @@ pub async fn emit_instructions(
                 fetch_resource_cached_by_hash_at_path(
                     app,
                     log,
-                    &Reqwest(reqwest::Client::new()),
                     format!("UnityDoorstop debug info"),
                     &pdb.url,
                     pdb.hash,
                     &path,
+                    false,
                     None,
                 )
                 .await?;
@@ -308,11 +511,11 @@ pub async fn emit_instructions(
             fetch_resource_cached_by_hash_at_path(
                 app,
                 log,
-                &Reqwest(reqwest::Client::new()),
                 format!("UnityDoorstop"),
                 &url,
                 hash,
                 &path,
+                false,
                 None,
             )
             .await?;
@@ -321,7 +524,13 @@ pub async fn emit_instructions(
         }
     };
 
-    em.load_library(doorstop_path);
+    if cfg!(target_os = "linux") && !uses_proton {
+        // Doorstop hooks the game's startup, which native Linux games may be past by the time the
+        // agent could load it
+        em.preload_library(doorstop_path);
+    } else {
+        em.load_library(doorstop_path);
+    }
 
     Ok(())
 }
@@ -1,8 +1,15 @@
+use anyhow::Context as _;
+use tauri::ipc::Channel;
 use tauri::{AppHandle, State};
 
+use crate::games::games_by_id;
 use crate::ipc::{ConnectionId, IpcState};
 use crate::CommandError;
 
+use super::agent_test::{AgentTestReport, StepReport};
+use super::failures::WrapperFailure;
+use super::receipts::Restored;
+use super::support::GameSupport;
 use super::LaunchTarget;
 
 #[tauri::command]
@@ -13,7 +20,111 @@ pub async fn launch_profile(
     modded: bool,
     conn_id: ConnectionId,
 ) -> Result<(), CommandError> {
+    if let LaunchTarget::Profile(_) = target {
+        // the loader's config is written into the profile, and so are the mods' own files
+        crate::profiles::read_only::ensure_writable()?;
+    }
     super::launch_profile(app, &*ipc_state, target, modded, conn_id)
         .await
         .map_err(Into::into)
 }
+
+/// Launches the game without any mods, after putting back the files that Manderrow has replaced
+/// in the game's directory, so that nothing but the game itself is loaded.
+#[tauri::command]
+pub async fn launch_vanilla(
+    app: AppHandle,
+    ipc_state: State<'_, IpcState>,
+    game: &str,
+    conn_id: ConnectionId,
+) -> Result<(), CommandError> {
+    super::launch_profile(
+        app,
+        &*ipc_state,
+        LaunchTarget::Vanilla(game),
+        false,
+        conn_id,
+    )
+    .await
+    .map_err(Into::into)
+}
+
+/// Removes everything that Manderrow has written into the game's directory, putting back any
+/// files that it replaced, so that the game can be checked to run unmodded. Files that were
+/// changed by something else since Manderrow wrote them are left alone.
+#[tauri::command]
+pub async fn restore_vanilla(game: &str) -> Result<Restored, CommandError> {
+    let log = slog_scope::logger();
+    Ok(super::receipts::restore(&log, game).await?)
+}
+
+/// Removes the launch options that Manderrow applied to the game in Steam, so that Steam can
+/// still launch it once Manderrow is uninstalled. Launch options that were changed since are left
+/// alone. Returns `true` if they were removed.
+///
+/// If `conn_id` is given, the user is asked to confirm through it, and Steam is closed if it is
+/// running. Otherwise, this fails if Steam is running.
+#[tauri::command]
+pub async fn remove_steam_launch_options(
+    app: AppHandle,
+    ipc_state: State<'_, IpcState>,
+    game: &str,
+    conn_id: Option<ConnectionId>,
+) -> Result<bool, CommandError> {
+    let log = slog_scope::logger();
+    let mut ipc = conn_id
+        .map(|conn_id| ipc_state.connect(conn_id, app))
+        .transpose()
+        .context("Failed to complete internal IPC connection")?;
+    let game = games_by_id()?
+        .get(game)
+        .copied()
+        .with_context(|| format!("Unrecognized game {game:?}"))?;
+    let steam = game
+        .store_platform_metadata
+        .iter()
+        .find_map(|m| m.steam_or_direct())
+        .with_context(|| format!("{} is not on Steam", game.name))?;
+    crate::stores::steam::launching::remove_launch_args(&log, ipc.as_mut(), steam.id)
+        .await
+        .map_err(Into::into)
+}
+
+/// Returns what Manderrow can do for the game on this platform, and why not where it can't.
+#[tauri::command]
+pub async fn get_game_support(game: &str) -> Result<GameSupport, CommandError> {
+    let log = slog_scope::logger();
+    let game = games_by_id()?
+        .get(game)
+        .copied()
+        .with_context(|| format!("Unrecognized game {game:?}"))?;
+    Ok(super::support::get_game_support(&log, game).await)
+}
+
+/// Launches a stand-in for the game with the agent and checks each step of the handshake with it,
+/// so that the setup can be verified without waiting for the game to boot. Each step's report is
+/// sent to `on_step` as it completes.
+#[tauri::command]
+pub async fn test_agent_ipc(
+    app: AppHandle,
+    game: &str,
+    on_step: Channel<StepReport>,
+) -> Result<AgentTestReport, CommandError> {
+    let log = slog_scope::logger();
+    let game = games_by_id()?
+        .get(game)
+        .copied()
+        .with_context(|| format!("Unrecognized game {game:?}"))?;
+    Ok(super::agent_test::test_agent_ipc(&app, &log, game, &on_step).await?)
+}
+
+/// Returns the failures of the wrapper that happened since this was last called, which are
+/// otherwise invisible when the game was launched from Steam.
+#[tauri::command]
+pub async fn take_wrapper_failures() -> Result<Vec<WrapperFailure>, CommandError> {
+    let log = slog_scope::logger();
+    let failures = tokio::task::spawn_blocking(move || super::failures::take(&log))
+        .await
+        .map_err(anyhow::Error::from)??;
+    Ok(failures)
+}
@@ -0,0 +1,120 @@
+//! A summary of how a launch was set up, written at the top of its log so that a log attached to a
+//! bug report says what it came from without anyone having to remember their setup.
+
+use std::fmt::Write as _;
+
+use anyhow::Result;
+use manderrow_types::games::{Game, StorePlatformMetadata};
+use slog::warn;
+use tokio::process::Command;
+use uuid::Uuid;
+
+use crate::exporting::thunderstore::read_installed_mods;
+use crate::profiles::profile_path;
+
+use super::{AgentSource, LaunchTarget};
+
+/// Variables in Manderrow's own environment that change how games are launched. The rest of the
+/// environment is left out, as it may contain secrets.
+const HOST_VARS: &[&str] = &[
+    "MANDERROW_AGENT_PATH",
+    "BEPINEX_CI",
+    "OVERRIDE_DOORSTOP_LIBRARY_PATH",
+    "LEGACY_DOORSTOP",
+    "WINEDLLOVERRIDES",
+];
+
+pub struct LaunchEnvironment<'a> {
+    pub game: &'a Game<'a>,
+    pub target: LaunchTarget<'a>,
+    /// `None` if the game is launched directly.
+    pub store: Option<&'a StorePlatformMetadata<'a>>,
+    pub uses_proton: bool,
+    /// `None` if the game is launched without the agent.
+    pub agent: Option<String>,
+    /// `None` if the game is launched without mods.
+    pub loader: Option<String>,
+    pub command: &'a Command,
+}
+
+pub fn describe_agent(agent_src: &AgentSource) -> String {
+    let version = env!("CARGO_PKG_VERSION");
+    match agent_src {
+        AgentSource::Path(path) if std::env::var_os("MANDERROW_AGENT_PATH").is_some() => {
+            format!("custom build at {path:?}")
+        }
+        AgentSource::Path(path) => format!("{version} at {path:?}"),
+        AgentSource::Embedded(_) => format!("{version} embedded for Proton"),
+    }
+}
+
+/// Returns the number of mods installed in the profile, and a hash of which versions of them are
+/// installed and enabled. Profiles with the same mods have the same fingerprint.
+async fn profile_fingerprint(log: &slog::Logger, id: Uuid) -> Result<(usize, blake3::Hash)> {
+    let mods = read_installed_mods(log, &profile_path(id)).await?;
+    let mut hasher = blake3::Hasher::new();
+    for m in &mods {
+        hasher.update(format!("{}-{} {}\n", m.full_name, m.version, m.enabled).as_bytes());
+    }
+    Ok((mods.len(), hasher.finalize()))
+}
+
+impl LaunchEnvironment<'_> {
+    pub async fn render(&self, log: &slog::Logger) -> String {
+        // writing to a String can't fail
+        let mut s = String::new();
+        _ = writeln!(
+            s,
+            "Manderrow {} on {} {}",
+            env!("CARGO_PKG_VERSION"),
+            std::env::consts::OS,
+            std::env::consts::ARCH
+        );
+        _ = writeln!(s, "Game: {} ({})", self.game.name, self.game.id);
+        match self.store {
+            Some(store) => _ = writeln!(s, "Store: {store:?}"),
+            None => _ = writeln!(s, "Store: none, launched directly"),
+        }
+        _ = writeln!(s, "Proton: {}", self.uses_proton);
+        match self.target {
+            LaunchTarget::Profile(id) => match profile_fingerprint(log, id).await {
+                Ok((count, hash)) => {
+                    _ = writeln!(
+                        s,
+                        "Profile: {id}, {count} mods, fingerprint {}",
+                        &hash.to_hex()[..16]
+                    )
+                }
+                Err(e) => {
+                    warn!(log, "Unable to fingerprint profile {id}: {e:#}");
+                    _ = writeln!(s, "Profile: {id}");
+                }
+            },
+            LaunchTarget::Vanilla(_) => _ = writeln!(s, "Profile: none, vanilla"),
+        }
+        _ = writeln!(s, "Agent: {}", self.agent.as_deref().unwrap_or("none"));
+        _ = writeln!(s, "Loader: {}", self.loader.as_deref().unwrap_or("none"));
+
+        let command = self.command.as_std();
+        _ = write!(s, "Command: {:?}", command.get_program());
+        for arg in command.get_args() {
+            _ = write!(s, " {arg:?}");
+        }
+        s.push('\n');
+        if let Some(dir) = command.get_current_dir() {
+            _ = writeln!(s, "Working directory: {dir:?}");
+        }
+        for (key, value) in command.get_envs() {
+            match value {
+                Some(value) => _ = writeln!(s, "Environment: {key:?}={value:?}"),
+                None => _ = writeln!(s, "Environment: {key:?} removed"),
+            }
+        }
+        for key in HOST_VARS {
+            if let Some(value) = std::env::var_os(key) {
+                _ = writeln!(s, "Host environment: {key}={value:?}");
+            }
+        }
+        s
+    }
+}
@@ -0,0 +1,396 @@
+//! Detection of mod loaders that were installed into the game directory by something other than
+//! Manderrow, e.g. by hand or by another mod manager.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context as _, Result};
+use manderrow_types::games::Game;
+use slog::{debug, info};
+
+use crate::ipc::{DoctorFix, InProcessIpc};
+use crate::util::IoErrorKindExt as _;
+
+use super::bep_in_ex::{get_bep_in_ex_path, BepInExSource, BepInExVersion};
+use super::receipts;
+
+/// Marker file placed inside an adopted `BepInEx` folder. Its presence means the user has
+/// already chosen to let Manderrow use that installation, so they won't be asked again.
+const ADOPTED_MARKER_FILE_NAME: &str = ".manderrow_adopted";
+
+/// Folder inside the game directory that quarantined loaders are moved into.
+pub const QUARANTINE_FOLDER: &str = ".manderrow_quarantine";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+pub enum ExistingLoaderKind {
+    BepInEx,
+    MelonLoader,
+}
+
+impl ExistingLoaderKind {
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::BepInEx => "BepInEx",
+            Self::MelonLoader => "MelonLoader",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ExistingLoader {
+    pub kind: ExistingLoaderKind,
+    /// The game install directory in which the loader was found.
+    pub root: PathBuf,
+    /// Paths, relative to [`Self::root`], of the files and folders that make up the loader.
+    pub files: Vec<PathBuf>,
+    /// Whether the user has previously chosen to adopt this installation.
+    pub adopted: bool,
+}
+
+/// Files that make up a BepInEx installation, other than the `BepInEx` folder itself.
+const BEP_IN_EX_FILES: &[&str] = &[
+    "doorstop_config.ini",
+    ".doorstop_version",
+    "changelog.txt",
+    "run_bepinex.sh",
+    "libdoorstop.so",
+    "libdoorstop.dylib",
+];
+
+/// Files that make up a MelonLoader installation, other than the `MelonLoader` folder itself.
+const MELON_LOADER_FILES: &[&str] = &["version.dll", "dobby.dll", "NOTICE.txt"];
+
+/// Folders created by MelonLoader that belong to the user rather than the loader.
+const MELON_LOADER_USER_FOLDERS: &[&str] = &["Mods", "Plugins", "UserData", "UserLibs"];
+
+async fn collect_existing(root: &Path, names: &[&str], buf: &mut Vec<PathBuf>) -> Result<()> {
+    for &name in names {
+        if tokio::fs::try_exists(root.join(name)).await? {
+            buf.push(PathBuf::from(name));
+        }
+    }
+    Ok(())
+}
+
+/// Lists the files anywhere under `dir`, relative to it. A missing `dir` has no files.
+fn list_files(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for e in walkdir::WalkDir::new(dir) {
+        let e = match e {
+            Ok(t) => t,
+            Err(e) if e.depth() == 0 && e.io_error().is_some_and(|e| e.is_not_found()) => break,
+            Err(e) => return Err(e).with_context(|| format!("Failed to read {dir:?}")),
+        };
+        if e.file_type().is_file() {
+            files.push(e.path().strip_prefix(dir)?.to_owned());
+        }
+    }
+    Ok(files)
+}
+
+/// Looks for a loader installation in `install_dir` that was not put there by Manderrow.
+///
+/// Manderrow never installs loaders into the game directory (only the agent), so any
+/// `BepInEx/core` or `MelonLoader` folder found there is foreign.
+pub async fn detect(log: &slog::Logger, install_dir: &Path) -> Result<Option<ExistingLoader>> {
+    let bep_in_ex_dir = install_dir.join("BepInEx");
+    if tokio::fs::try_exists(bep_in_ex_dir.join("core").join("BepInEx.Preloader.dll")).await? {
+        let mut files = vec![PathBuf::from("BepInEx")];
+        collect_existing(install_dir, BEP_IN_EX_FILES, &mut files).await?;
+        let adopted = tokio::fs::try_exists(bep_in_ex_dir.join(ADOPTED_MARKER_FILE_NAME)).await?;
+        debug!(
+            log,
+            "Found existing BepInEx installation in {install_dir:?}: {files:?}"
+        );
+        return Ok(Some(ExistingLoader {
+            kind: ExistingLoaderKind::BepInEx,
+            root: install_dir.to_owned(),
+            files,
+            adopted,
+        }));
+    }
+
+    if tokio::fs::try_exists(install_dir.join("MelonLoader")).await? {
+        let mut files = vec![PathBuf::from("MelonLoader")];
+        collect_existing(install_dir, MELON_LOADER_FILES, &mut files).await?;
+        debug!(
+            log,
+            "Found existing MelonLoader installation in {install_dir:?}: {files:?}"
+        );
+        return Ok(Some(ExistingLoader {
+            kind: ExistingLoaderKind::MelonLoader,
+            root: install_dir.to_owned(),
+            files,
+            adopted: false,
+        }));
+    }
+
+    Ok(None)
+}
+
+impl ExistingLoader {
+    /// Records that the user wants Manderrow to use this installation from now on.
+    pub async fn mark_adopted(&mut self) -> Result<()> {
+        if self.kind == ExistingLoaderKind::BepInEx {
+            let path = self.root.join("BepInEx").join(ADOPTED_MARKER_FILE_NAME);
+            tokio::fs::write(&path, b"")
+                .await
+                .with_context(|| format!("Failed to write adoption marker at {path:?}"))?;
+            self.adopted = true;
+        }
+        Ok(())
+    }
+
+    /// Replaces the `BepInEx/core` folder of this installation with the one from `managed`,
+    /// leaving plugins, patchers, and configs untouched. The replaced files are recorded in the
+    /// game's receipt.
+    pub async fn upgrade_from(
+        &mut self,
+        log: &slog::Logger,
+        game_id: &str,
+        managed: &Path,
+    ) -> Result<()> {
+        self.upgrade_from_in(log, &receipts::receipts_dir(), game_id, managed)
+            .await
+    }
+
+    /// Like [`Self::upgrade_from`], with the receipt kept under `receipts_dir`.
+    async fn upgrade_from_in(
+        &mut self,
+        log: &slog::Logger,
+        receipts_dir: &Path,
+        game_id: &str,
+        managed: &Path,
+    ) -> Result<()> {
+        anyhow::ensure!(
+            self.kind == ExistingLoaderKind::BepInEx,
+            "Only BepInEx installations can be upgraded"
+        );
+        let target = self.root.join("BepInEx").join("core");
+        let source = managed.join("BepInEx").join("core");
+        info!(
+            log,
+            "Upgrading existing BepInEx core at {target:?} from {source:?}"
+        );
+
+        let core = Path::new("BepInEx").join("core");
+        let (replaced, upgraded) = tokio::task::block_in_place(|| {
+            Ok::<_, anyhow::Error>((list_files(&target)?, list_files(&source)?))
+        })?;
+        anyhow::ensure!(
+            !upgraded.is_empty(),
+            "The managed BepInEx core at {source:?} is empty"
+        );
+        receipts::record_before_replacing_in(
+            log,
+            receipts_dir,
+            game_id,
+            &self.root,
+            replaced.iter().chain(&upgraded).map(|p| core.join(p)),
+        )
+        .await
+        .context("Failed to back up existing BepInEx core")?;
+        match tokio::fs::remove_dir_all(&target).await {
+            Ok(()) => {}
+            Err(e) if e.is_not_found() => {}
+            Err(e) => return Err(e.into()),
+        }
+        let mut written = Vec::new();
+        for rel_path in upgraded {
+            let path = target.join(&rel_path);
+            if let Some(parent) = path.parent() {
+                tokio::fs::create_dir_all(parent).await?;
+            }
+            tokio::fs::copy(source.join(&rel_path), &path)
+                .await
+                .with_context(|| format!("Failed to copy {rel_path:?} to {target:?}"))?;
+            written.push(core.join(rel_path));
+        }
+        receipts::record_written_in(receipts_dir, game_id, &self.root, written)
+            .await
+            .context("Failed to record upgraded BepInEx core")?;
+        self.mark_adopted().await
+    }
+
+    /// Moves the loader's files into a timestamped folder under [`QUARANTINE_FOLDER`] in the game
+    /// directory, where neither the game nor the loader will find them, returning the path of
+    /// that folder. Staying inside the game directory keeps the moves on the same filesystem.
+    ///
+    /// For MelonLoader, the user's `Mods`, `Plugins`, `UserData`, and `UserLibs` folders are
+    /// moved alongside the loader so that they don't get picked up by anything else.
+    pub async fn quarantine(self, log: &slog::Logger) -> Result<PathBuf> {
+        let timestamp = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .context("System clock is set before the Unix epoch")?
+            .as_secs();
+        let mut dest = self.root.join(QUARANTINE_FOLDER);
+        dest.push(format!("{}-{timestamp}", self.kind.as_str()));
+        tokio::fs::create_dir_all(&dest)
+            .await
+            .with_context(|| format!("Failed to create quarantine folder at {dest:?}"))?;
+
+        let mut files = self.files;
+        if self.kind == ExistingLoaderKind::MelonLoader {
+            collect_existing(&self.root, MELON_LOADER_USER_FOLDERS, &mut files).await?;
+        }
+
+        for rel_path in files {
+            let src = self.root.join(&rel_path);
+            let dst = dest.join(&rel_path);
+            info!(log, "Quarantining {src:?} to {dst:?}");
+            tokio::fs::rename(&src, &dst)
+                .await
+                .with_context(|| format!("Failed to move {src:?} to {dst:?}"))?;
+        }
+
+        Ok(dest)
+    }
+}
+
+/// Checks the game directory for a foreign BepInEx or MelonLoader installation and, if one is
+/// found, asks the user whether to adopt, upgrade, or quarantine it before launching.
+///
+/// Returns the BepInEx installation that should be used for the launch.
+pub async fn reconcile_bep_in_ex(
+    log: &slog::Logger,
+    ipc: &mut InProcessIpc,
+    game: &Game<'_>,
+    install_dir: Option<&Path>,
+    version: BepInExVersion,
+    uses_proton: bool,
+) -> Result<BepInExSource, crate::Error> {
+    let managed = BepInExSource::Managed(version);
+
+    let Some(install_dir) = install_dir else {
+        return Ok(managed);
+    };
+
+    let Some(mut existing) = detect(log, install_dir).await? else {
+        return Ok(managed);
+    };
+
+    if existing.adopted {
+        debug!(
+            log,
+            "Using previously adopted BepInEx installation at {install_dir:?}"
+        );
+        return Ok(BepInExSource::Adopted(existing.root));
+    }
+
+    #[derive(Clone, Copy, serde::Deserialize, serde::Serialize)]
+    #[serde(rename_all = "snake_case")]
+    enum Fix {
+        Adopt,
+        Upgrade,
+        Quarantine,
+        Ignore,
+        Abort,
+    }
+
+    let is_bep_in_ex = existing.kind == ExistingLoaderKind::BepInEx;
+    let fixes = [
+        Fix::Adopt,
+        Fix::Upgrade,
+        Fix::Quarantine,
+        Fix::Ignore,
+        Fix::Abort,
+    ]
+    .into_iter()
+    .filter(|fix| is_bep_in_ex || !matches!(fix, Fix::Adopt | Fix::Upgrade))
+    .map(|id| DoctorFix {
+        id,
+        label: None,
+        confirm_label: None,
+        description: None,
+    });
+
+    let choice = ipc
+        .prompt_patient(
+            "existing_loader",
+            None,
+            Some(
+                [
+                    ("loader".to_owned(), existing.kind.as_str().to_owned()),
+                    (
+                        "path".to_owned(),
+                        existing.root.to_string_lossy().into_owned(),
+                    ),
+                ]
+                .into(),
+            ),
+            fixes,
+            Fix::Abort,
+        )
+        .await?;
+
+    match choice {
+        Fix::Adopt => {
+            existing.mark_adopted().await?;
+            Ok(BepInExSource::Adopted(existing.root))
+        }
+        Fix::Upgrade => {
+            let managed_path = get_bep_in_ex_path(log, version, uses_proton).await?;
+            existing.upgrade_from(log, game.id, &managed_path).await?;
+            Ok(BepInExSource::Adopted(existing.root))
+        }
+        Fix::Quarantine => {
+            let dest = existing.quarantine(log).await?;
+            info!(log, "Quarantined existing loader to {dest:?}");
+            Ok(managed)
+        }
+        Fix::Ignore => Ok(managed),
+        Fix::Abort => Err(crate::Error::Aborted),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::{detect, receipts, ExistingLoaderKind};
+
+    #[test]
+    fn test_upgrade_from_copies_nested_files() {
+        let log = slog::Logger::root(slog::Discard, slog::o!());
+        let game_id = "ror2";
+        let receipts_dir = tempfile::tempdir().unwrap();
+        let install_dir = tempfile::tempdir().unwrap();
+        let managed = tempfile::tempdir().unwrap();
+        let write = |root: &Path, rel_path: &str, contents: &str| {
+            let path = root.join("BepInEx").join("core").join(rel_path);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, contents).unwrap();
+        };
+        write(install_dir.path(), "BepInEx.Preloader.dll", "old");
+        write(install_dir.path(), "Removed.dll", "old");
+        write(managed.path(), "BepInEx.Preloader.dll", "new");
+        write(managed.path(), "nested/Harmony.dll", "new");
+
+        tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()
+            .unwrap()
+            .block_on(async {
+                let mut existing = detect(&log, install_dir.path()).await.unwrap().unwrap();
+                assert_eq!(existing.kind, ExistingLoaderKind::BepInEx);
+                existing
+                    .upgrade_from_in(&log, receipts_dir.path(), game_id, managed.path())
+                    .await
+                    .unwrap();
+                assert!(existing.adopted);
+
+                let core = install_dir.path().join("BepInEx").join("core");
+                let read = |rel_path: &str| std::fs::read_to_string(core.join(rel_path)).ok();
+                assert_eq!(read("BepInEx.Preloader.dll").as_deref(), Some("new"));
+                assert_eq!(read("nested/Harmony.dll").as_deref(), Some("new"));
+                assert_eq!(read("Removed.dll"), None);
+
+                let restored = receipts::restore_in(&log, receipts_dir.path(), game_id)
+                    .await
+                    .unwrap();
+                assert!(restored.drifted.is_empty());
+                assert_eq!(read("BepInEx.Preloader.dll").as_deref(), Some("old"));
+                assert_eq!(read("Removed.dll").as_deref(), Some("old"));
+                assert_eq!(read("nested/Harmony.dll"), None);
+            });
+    }
+}
@@ -0,0 +1,83 @@
+//! Records of the wrapper failing before the game started. When the game is launched from Steam,
+//! nothing else would show that Manderrow was involved at all, so the wrapper writes them to the
+//! runtime directory and the app shows them the next time it is focused.
+
+use std::io::Write as _;
+use std::path::PathBuf;
+use std::time::SystemTime;
+
+use anyhow::{Context as _, Result};
+use manderrow_paths::runtime_dir;
+use slog::warn;
+
+use crate::util::{unix_millis, IoErrorKindExt as _};
+
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WrapperFailure {
+    /// Milliseconds since the Unix epoch.
+    pub time: u64,
+    /// The command line that the wrapper was run with.
+    pub args: Vec<String>,
+    /// The error followed by its causes.
+    pub messages: Vec<String>,
+}
+
+fn failures_dir() -> PathBuf {
+    runtime_dir().join("wrapper-failures")
+}
+
+/// Writes a record of the wrapper failing with `messages`. Called by the wrapper.
+pub fn record(messages: Vec<String>) -> Result<PathBuf> {
+    let failure = WrapperFailure {
+        time: unix_millis(SystemTime::now()),
+        args: std::env::args_os()
+            .map(|arg| arg.to_string_lossy().into_owned())
+            .collect(),
+        messages,
+    };
+
+    let dir = failures_dir();
+    std::fs::create_dir_all(&dir).with_context(|| format!("Failed to create {dir:?}"))?;
+    // written to a temporary file first so that the app never reads a partial record
+    let mut file = tempfile::NamedTempFile::new_in(&dir)?;
+    serde_json::to_writer(&mut file, &failure)?;
+    file.flush()?;
+    let path = dir.join(format!("{time}-{}.json", std::process::id()));
+    file.persist(&path)
+        .with_context(|| format!("Failed to write {path:?}"))?;
+    Ok(path)
+}
+
+/// Returns the recorded failures, oldest first, and removes them so that each is only shown
+/// once.
+pub fn take(log: &slog::Logger) -> Result<Vec<WrapperFailure>> {
+    let dir = failures_dir();
+    let entries = match std::fs::read_dir(&dir) {
+        Ok(t) => t,
+        Err(e) if e.is_not_found() => return Ok(Vec::new()),
+        Err(e) => return Err(anyhow::Error::new(e).context(format!("Failed to read {dir:?}"))),
+    };
+
+    let mut failures = Vec::new();
+    for entry in entries {
+        let path = entry?.path();
+        if path.extension().is_none_or(|ext| ext != "json") {
+            continue;
+        }
+        match std::fs::read(&path)
+            .map_err(anyhow::Error::from)
+            .and_then(|buf| Ok(serde_json::from_slice::<WrapperFailure>(&buf)?))
+        {
+            Ok(failure) => failures.push(failure),
+            Err(e) => warn!(log, "Failed to read wrapper failure from {path:?}: {e}"),
+        }
+        if let Err(e) = std::fs::remove_file(&path) {
+            if !e.is_not_found() {
+                warn!(log, "Failed to remove wrapper failure {path:?}: {e}");
+            }
+        }
+    }
+    failures.sort_by_key(|failure| failure.time);
+    Ok(failures)
+}
@@ -0,0 +1,95 @@
+//! Downloads of loaders published as GitHub release assets, which are verified against the
+//! SHA-256 digests that GitHub publishes for them.
+
+use std::path::Path;
+
+use anyhow::{bail, Context as _, Result};
+use sha2::{Digest as _, Sha256};
+use tauri::AppHandle;
+
+use crate::installing::{fetch_resource_cached_by_url, pruning};
+
+/// A release asset, along with the SHA-256 digest that GitHub published for it.
+pub(super) struct ReleaseAsset {
+    pub url: String,
+    pub sha256: String,
+}
+
+/// Looks up the asset called `name` of the release tagged `tag` in `repo`, given as
+/// `owner/name`. `tag` must already be percent-encoded.
+pub(super) async fn get_release_asset(repo: &str, tag: &str, name: &str) -> Result<ReleaseAsset> {
+    #[derive(serde::Deserialize)]
+    struct Release {
+        assets: Vec<Asset>,
+    }
+
+    #[derive(serde::Deserialize)]
+    struct Asset {
+        name: String,
+        browser_download_url: String,
+        digest: Option<String>,
+    }
+
+    let bytes = crate::util::http::client()
+        .get(format!(
+            "https://api.github.com/repos/{repo}/releases/tags/{tag}"
+        ))
+        .header(reqwest::header::ACCEPT, "application/vnd.github+json")
+        .send()
+        .await?
+        .error_for_status()?
+        .bytes()
+        .await?;
+    let asset = serde_json::from_slice::<Release>(&bytes)
+        .with_context(|| format!("Failed to parse release {tag} of {repo}"))?
+        .assets
+        .into_iter()
+        .find(|asset| asset.name == name)
+        .with_context(|| format!("Release {tag} of {repo} has no {name}"))?;
+    let sha256 = asset
+        .digest
+        .as_deref()
+        .and_then(|digest| digest.strip_prefix("sha256:"))
+        .with_context(|| format!("Release {tag} of {repo} has no SHA-256 digest for {name}"))?
+        .to_owned();
+    Ok(ReleaseAsset {
+        url: asset.browser_download_url,
+        sha256,
+    })
+}
+
+/// Fails, and removes the file so that it is downloaded again next time, unless the SHA-256
+/// digest of the file at `path` is `expected`.
+async fn verify_sha256(path: &Path, expected: &str) -> Result<()> {
+    let path = path.to_owned();
+    let expected = expected.to_ascii_lowercase();
+    tokio::task::spawn_blocking(move || {
+        let mut hasher = Sha256::new();
+        std::io::copy(&mut std::fs::File::open(&path)?, &mut hasher)?;
+        let found = format!("{:x}", hasher.finalize());
+        if found != expected {
+            std::fs::remove_file(&path)?;
+            bail!(
+                "Bad hash of downloaded resource at {path:?}: expected {expected}, found {found}"
+            );
+        }
+        Ok(())
+    })
+    .await?
+}
+
+/// Downloads `asset` into the cache and verifies it. The returned pin keeps the verified copy
+/// cached, so that it can be installed from the cache by URL.
+pub(super) async fn fetch_verified(
+    app: Option<&AppHandle>,
+    log: &slog::Logger,
+    title: &str,
+    asset: &ReleaseAsset,
+) -> Result<pruning::Pin> {
+    let (zip, _) =
+        fetch_resource_cached_by_url(app, log, title.to_owned(), &asset.url, ".zip", false, None)
+            .await?;
+    let pin = pruning::pin(zip.clone());
+    verify_sha256(&zip, &asset.sha256).await?;
+    Ok(pin)
+}
@@ -0,0 +1,216 @@
+//! The games that have been launched and are still running, by the IPC connection that they were
+//! launched with. A game counts as running until its connection closes, or, if its agent never
+//! connected, until a while after its launcher exited.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::LazyLock;
+use std::time::{Duration, Instant};
+
+use parking_lot::Mutex;
+use uuid::Uuid;
+
+use crate::ipc::{ConnectionId, IpcState};
+
+/// Whether a game may be launched while it is already running.
+static ALLOW_CONCURRENT: AtomicBool = AtomicBool::new(false);
+
+/// How long a game may take to start after its launcher exited, like Steam does when asked to
+/// launch a game, before it is assumed to have failed to.
+const START_TIMEOUT: Duration = Duration::from_secs(2 * 60);
+
+static INSTANCES: LazyLock<Mutex<HashMap<ConnectionId, Entry>>> = LazyLock::new(Default::default);
+
+pub fn set_allow_concurrent(allow: bool) {
+    ALLOW_CONCURRENT.store(allow, Ordering::Relaxed);
+}
+
+#[derive(Debug, Clone, Copy, serde::Serialize)]
+pub struct Instance {
+    pub game: &'static str,
+    /// `None` for vanilla launches.
+    pub profile: Option<Uuid>,
+}
+
+struct Entry {
+    instance: Instance,
+    launcher_exited_at: Option<Instant>,
+}
+
+/// What is known of the game through the connection that it was launched with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Status {
+    Closed,
+    /// The game's agent has not told Manderrow that the game started, and may never.
+    Starting,
+    Started,
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("{game} is already running on connection {conn_id}")]
+pub struct AlreadyRunningError {
+    pub game: &'static str,
+    pub conn_id: ConnectionId,
+}
+
+/// Unregisters the instance when dropped, unless forgotten once the game has been launched.
+pub struct Registration {
+    conn_id: ConnectionId,
+}
+
+impl Drop for Registration {
+    fn drop(&mut self) {
+        INSTANCES.lock().remove(&self.conn_id);
+    }
+}
+
+fn status(ipc_state: &IpcState, conn_id: ConnectionId) -> Status {
+    match ipc_state.get_conn(conn_id) {
+        None => Status::Closed,
+        Some(conn) if conn.pid().is_some() => Status::Started,
+        Some(_) => Status::Starting,
+    }
+}
+
+/// Forgets the instances whose connections have closed, and those that have not started long
+/// after their launcher exited. A game that dies before its agent connects never closes its
+/// connection.
+fn prune(
+    instances: &mut HashMap<ConnectionId, Entry>,
+    now: Instant,
+    status: impl Fn(ConnectionId) -> Status,
+) {
+    instances.retain(|&conn_id, entry| match status(conn_id) {
+        Status::Closed => false,
+        Status::Starting => !entry
+            .launcher_exited_at
+            .is_some_and(|t| now.duration_since(t) > START_TIMEOUT),
+        Status::Started => true,
+    });
+}
+
+/// Records that `instance` is being launched with `conn_id`. Fails if the same game is already
+/// running, unless concurrent launches are allowed.
+pub fn register(
+    ipc_state: &IpcState,
+    conn_id: ConnectionId,
+    instance: Instance,
+) -> Result<Registration, AlreadyRunningError> {
+    register_in(
+        &mut INSTANCES.lock(),
+        Instant::now(),
+        |conn_id| status(ipc_state, conn_id),
+        ALLOW_CONCURRENT.load(Ordering::Relaxed),
+        conn_id,
+        instance,
+    )?;
+    Ok(Registration { conn_id })
+}
+
+fn register_in(
+    instances: &mut HashMap<ConnectionId, Entry>,
+    now: Instant,
+    status: impl Fn(ConnectionId) -> Status,
+    allow_concurrent: bool,
+    conn_id: ConnectionId,
+    instance: Instance,
+) -> Result<(), AlreadyRunningError> {
+    prune(instances, now, status);
+    if !allow_concurrent {
+        if let Some((&other, _)) = instances
+            .iter()
+            .find(|(_, other)| other.instance.game == instance.game)
+        {
+            return Err(AlreadyRunningError {
+                game: instance.game,
+                conn_id: other,
+            });
+        }
+    }
+    instances.insert(
+        conn_id,
+        Entry {
+            instance,
+            launcher_exited_at: None,
+        },
+    );
+    Ok(())
+}
+
+/// Records that the launcher of what was launched with `conn_id` has exited, which the game may
+/// have outlived.
+pub fn launcher_exited(conn_id: ConnectionId) {
+    if let Some(entry) = INSTANCES.lock().get_mut(&conn_id) {
+        entry.launcher_exited_at = Some(Instant::now());
+    }
+}
+
+/// Returns what was launched with `conn_id`, if anything.
+pub fn get(conn_id: ConnectionId) -> Option<Instance> {
+    INSTANCES.lock().get(&conn_id).map(|entry| entry.instance)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn instance(game: &'static str) -> Instance {
+        Instance {
+            game,
+            profile: None,
+        }
+    }
+
+    #[test]
+    fn test_register() {
+        let mut instances = HashMap::new();
+        let now = Instant::now();
+        let (a, b, c) = (ConnectionId(0), ConnectionId(1), ConnectionId(2));
+        let started = |_| Status::Started;
+
+        register_in(&mut instances, now, started, false, a, instance("ror2")).unwrap();
+        let e = register_in(&mut instances, now, started, false, b, instance("ror2")).unwrap_err();
+        assert_eq!(e.conn_id, a);
+        register_in(&mut instances, now, started, false, c, instance("valheim")).unwrap();
+        register_in(&mut instances, now, started, true, b, instance("ror2")).unwrap();
+
+        // the connection of the first closed
+        let status = |id| {
+            if id == a {
+                Status::Closed
+            } else {
+                Status::Started
+            }
+        };
+        let mut instances = HashMap::new();
+        register_in(&mut instances, now, started, false, a, instance("ror2")).unwrap();
+        register_in(&mut instances, now, status, false, b, instance("ror2")).unwrap();
+        assert!(!instances.contains_key(&a));
+    }
+
+    #[test]
+    fn test_register_never_started() {
+        let mut instances = HashMap::new();
+        let now = Instant::now();
+        let (a, b) = (ConnectionId(0), ConnectionId(1));
+        let starting = |_| Status::Starting;
+
+        register_in(&mut instances, now, starting, false, a, instance("ror2")).unwrap();
+        // the launcher is still running
+        let later = now + START_TIMEOUT * 2;
+        register_in(&mut instances, later, starting, false, b, instance("ror2")).unwrap_err();
+
+        instances.get_mut(&a).unwrap().launcher_exited_at = Some(now);
+        register_in(
+            &mut instances,
+            now + START_TIMEOUT / 2,
+            starting,
+            false,
+            b,
+            instance("ror2"),
+        )
+        .unwrap_err();
+        register_in(&mut instances, later, starting, false, b, instance("ror2")).unwrap();
+        assert!(!instances.contains_key(&a));
+    }
+}
@@ -0,0 +1,313 @@
+//! Detection of game installs that were left broken by earlier launches, other mod managers, or
+//! interrupted updates, e.g. an agent proxy that is no longer used, the files of a doorstop whose
+//! loader was removed, a BepInEx core that was partially overwritten, or files that Manderrow
+//! installed which have since been changed by something else.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context as _, Result};
+use manderrow_types::games::{Game, PackageLoader, SteamMetadata};
+use slog::{debug, info, warn};
+use tauri::AppHandle;
+use tauri_plugin_opener::OpenerExt as _;
+
+use crate::ipc::{DoctorFix, InProcessIpc};
+
+use super::bep_in_ex::{get_bep_in_ex_path, BepInExVersion};
+use super::existing_loader::{ExistingLoader, ExistingLoaderKind};
+use super::receipts;
+
+/// The proxy DLL that the agent is installed as.
+const AGENT_PROXY: &str = "winhttp.dll";
+
+/// Files that Unity Doorstop places beside the game to load BepInEx.
+const DOORSTOP_FILES: &[&str] = &[
+    "doorstop_config.ini",
+    ".doorstop_version",
+    "libdoorstop.so",
+    "libdoorstop.dylib",
+    "run_bepinex.sh",
+];
+
+/// For each major version of BepInEx, a file in `BepInEx/core` that identifies it, and the files
+/// of which it needs at least one to be loaded. Which one depends on the game's runtime.
+const CORE_FILES: &[(&str, &[&str])] = &[
+    // BepInEx 5
+    ("BepInEx.dll", &["BepInEx.Preloader.dll"]),
+    // BepInEx 6
+    (
+        "BepInEx.Core.dll",
+        &[
+            "BepInEx.Unity.Mono.Preloader.dll",
+            "BepInEx.Unity.IL2CPP.dll",
+            "BepInEx.NET.Framework.Launcher.dll",
+            "BepInEx.NET.CoreCLR.dll",
+        ],
+    ),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Problem {
+    /// The agent proxy is left over from an earlier launch, but won't be replaced by this one.
+    StaleAgent,
+    /// Doorstop is still set up to load a BepInEx installation that no longer exists.
+    LeftoverDoorstop,
+    /// A `BepInEx/core` folder exists, but is missing the files needed to load the version of
+    /// BepInEx it belongs to.
+    BrokenCore,
+    /// Files that Manderrow wrote have been changed by something else since.
+    Drifted,
+}
+
+impl Problem {
+    const fn as_str(self) -> &'static str {
+        match self {
+            Self::StaleAgent => "stale_agent",
+            Self::LeftoverDoorstop => "leftover_doorstop",
+            Self::BrokenCore => "broken_core",
+            Self::Drifted => "drifted",
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Finding {
+    problem: Problem,
+    /// Paths, relative to the game directory, of the files involved.
+    files: Vec<PathBuf>,
+}
+
+async fn exists(path: &Path) -> Result<bool> {
+    tokio::fs::try_exists(path)
+        .await
+        .with_context(|| format!("Failed to check for {path:?}"))
+}
+
+/// Whether the `BepInEx/core` folder at `core` has the files needed to load the version of BepInEx
+/// it belongs to. If it doesn't identify as any version, it can't be loaded either.
+async fn is_core_loadable(core: &Path) -> Result<bool> {
+    for &(identity, entry_points) in CORE_FILES {
+        if !exists(&core.join(identity)).await? {
+            continue;
+        }
+        for &name in entry_points {
+            if exists(&core.join(name)).await? {
+                return Ok(true);
+            }
+        }
+    }
+    Ok(false)
+}
+
+async fn detect(
+    game_id: &str,
+    install_dir: &Path,
+    installs_agent_proxy: bool,
+) -> Result<Option<Finding>> {
+    let core = Path::new("BepInEx").join("core");
+    let has_core = exists(&install_dir.join(&core)).await?;
+    if has_core && !is_core_loadable(&install_dir.join(&core)).await? {
+        return Ok(Some(Finding {
+            problem: Problem::BrokenCore,
+            files: vec![core],
+        }));
+    }
+
+    let agent_is_ours = receipts::is_recorded(game_id, install_dir, Path::new(AGENT_PROXY)).await?;
+
+    if !has_core {
+        let mut files = Vec::new();
+        for &name in DOORSTOP_FILES {
+            if exists(&install_dir.join(name)).await? {
+                files.push(PathBuf::from(name));
+            }
+        }
+        if !files.is_empty() {
+            // BepInEx 5 installs doorstop as the same proxy DLL as the agent
+            if !agent_is_ours && exists(&install_dir.join(AGENT_PROXY)).await? {
+                files.push(PathBuf::from(AGENT_PROXY));
+            }
+            return Ok(Some(Finding {
+                problem: Problem::LeftoverDoorstop,
+                files,
+            }));
+        }
+    }
+
+    if !installs_agent_proxy && agent_is_ours && exists(&install_dir.join(AGENT_PROXY)).await? {
+        return Ok(Some(Finding {
+            problem: Problem::StaleAgent,
+            files: vec![PathBuf::from(AGENT_PROXY)],
+        }));
+    }
+
+    let files = receipts::find_drifted(game_id, install_dir).await?;
+    if !files.is_empty() {
+        return Ok(Some(Finding {
+            problem: Problem::Drifted,
+            files,
+        }));
+    }
+
+    Ok(None)
+}
+
+/// Checks the game directory for files that would stop the game or its mods from loading
+/// correctly and, if any are found, asks the user how to fix them before launching.
+pub async fn check(
+    app: &AppHandle,
+    log: &slog::Logger,
+    ipc: &mut InProcessIpc,
+    game: &Game<'_>,
+    install_dir: &Path,
+    steam: Option<SteamMetadata<'_>>,
+    installs_agent_proxy: bool,
+    bep_in_ex_version: BepInExVersion,
+    uses_proton: bool,
+) -> Result<(), crate::Error> {
+    let finding = match detect(game.id, install_dir, installs_agent_proxy).await {
+        Ok(Some(t)) => t,
+        Ok(None) => return Ok(()),
+        Err(e) => {
+            warn!(log, "Unable to check game files: {e:#}");
+            return Ok(());
+        }
+    };
+    warn!(
+        log,
+        "Found problem with game files in {install_dir:?}: {:?}", finding
+    );
+
+    #[derive(Clone, Copy, serde::Deserialize, serde::Serialize)]
+    #[serde(rename_all = "snake_case")]
+    enum Fix {
+        Remove,
+        Reinstall,
+        Verify,
+        Ignore,
+        Abort,
+    }
+
+    let fixes = [
+        Fix::Remove,
+        Fix::Reinstall,
+        Fix::Verify,
+        Fix::Ignore,
+        Fix::Abort,
+    ]
+    .into_iter()
+    .filter(|fix| match fix {
+        // the files aren't Manderrow's anymore, and a core that merely looks broken may belong to
+        // a loader that is still in use, along with the user's plugins and configs
+        Fix::Remove => !matches!(finding.problem, Problem::Drifted | Problem::BrokenCore),
+        Fix::Reinstall => {
+            finding.problem == Problem::BrokenCore
+                && matches!(game.package_loader, PackageLoader::BepInEx)
+        }
+        Fix::Verify => steam.is_some() && finding.problem != Problem::StaleAgent,
+        _ => true,
+    })
+    .map(|id| DoctorFix {
+        id,
+        label: None,
+        confirm_label: None,
+        description: None,
+    });
+
+    let choice = ipc
+        .prompt_patient(
+            "game_files",
+            Some(format!(
+                "doctor.game_files.message_{}",
+                finding.problem.as_str()
+            )),
+            Some(
+                [
+                    (
+                        "path".to_owned(),
+                        install_dir.to_string_lossy().into_owned(),
+                    ),
+                    (
+                        "files".to_owned(),
+                        finding
+                            .files
+                            .iter()
+                            .map(|path| path.to_string_lossy())
+                            .collect::<Vec<_>>()
+                            .join(", "),
+                    ),
+                ]
+                .into(),
+            ),
+            fixes,
+            Fix::Abort,
+        )
+        .await?;
+
+    let mut loader = ExistingLoader {
+        kind: ExistingLoaderKind::BepInEx,
+        root: install_dir.to_owned(),
+        files: finding.files,
+        adopted: false,
+    };
+    match choice {
+        Fix::Remove if finding.problem == Problem::StaleAgent => {
+            receipts::restore_one(log, game.id, Path::new(AGENT_PROXY)).await?;
+            Ok(())
+        }
+        Fix::Remove => {
+            let dest = loader.quarantine(log).await?;
+            info!(log, "Quarantined broken game files to {dest:?}");
+            Ok(())
+        }
+        Fix::Reinstall => {
+            let managed = get_bep_in_ex_path(log, bep_in_ex_version, uses_proton).await?;
+            loader.upgrade_from(log, game.id, &managed).await?;
+            info!(log, "Reinstalled BepInEx core in {install_dir:?}");
+            Ok(())
+        }
+        Fix::Verify => {
+            let steam = steam.expect("verifying is only offered for Steam games");
+            debug!(log, "Asking Steam to verify the files of app {}", steam.id);
+            app.opener()
+                .open_url(format!("steam://validate/{}", steam.id), None::<&str>)
+                .context("Failed to open Steam")?;
+            // the files may change under the game while Steam verifies them
+            Err(crate::Error::Aborted)
+        }
+        Fix::Ignore => Ok(()),
+        Fix::Abort => Err(crate::Error::Aborted),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_core_loadable() {
+        let dir = tempfile::tempdir().unwrap();
+        let core = dir.path();
+        let touch = |name: &str| std::fs::write(core.join(name), "").unwrap();
+        tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()
+            .expect("unable to build tokio runtime")
+            .block_on(async {
+                assert!(!is_core_loadable(core).await.unwrap());
+
+                // BepInEx 6 has no BepInEx.Preloader.dll
+                touch("BepInEx.Core.dll");
+                assert!(!is_core_loadable(core).await.unwrap());
+                touch("BepInEx.Unity.IL2CPP.dll");
+                assert!(is_core_loadable(core).await.unwrap());
+
+                let dir = tempfile::tempdir().unwrap();
+                let core = dir.path();
+                std::fs::write(core.join("BepInEx.dll"), "").unwrap();
+                assert!(!is_core_loadable(core).await.unwrap());
+                std::fs::write(core.join("BepInEx.Preloader.dll"), "").unwrap();
+                assert!(is_core_loadable(core).await.unwrap());
+            });
+    }
+}
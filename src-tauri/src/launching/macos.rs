@@ -0,0 +1,327 @@
+//! Preparation of injected libraries for macOS, where Gatekeeper and the hardened runtime get
+//! between the agent and the game.
+//!
+//! Libraries that were downloaded, or copied out of a downloaded app bundle, carry the
+//! `com.apple.quarantine` attribute, and copying can invalidate their signature. Either will
+//! cause `dyld` to refuse to load them, so everything we inject is staged in the cache, cleared
+//! of quarantine, and given an ad-hoc signature first.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context as _, Result};
+use manderrow_paths::cache_dir;
+use slog::{debug, info, warn};
+use tokio::process::Command;
+
+use crate::ipc::{DoctorFix, InProcessIpc, OutputLine};
+
+use super::receipts;
+
+const QUARANTINE_ATTR: &str = "com.apple.quarantine";
+
+/// Entitlement that allows `DYLD_INSERT_LIBRARIES` to take effect in a binary that has opted into
+/// the hardened runtime.
+const ALLOW_DYLD_ENV_ENTITLEMENT: &str = "com.apple.security.cs.allow-dyld-environment-variables";
+
+async fn run(command: &mut Command) -> Result<std::process::Output> {
+    let output = command
+        .output()
+        .await
+        .with_context(|| format!("Failed to run {command:?}"))?;
+    Ok(output)
+}
+
+async fn run_ok(command: &mut Command) -> Result<std::process::Output> {
+    let output = run(command).await?;
+    output.status.exit_ok().with_context(|| {
+        format!(
+            "{command:?} failed\nstdout: {:?}\nstderr: {:?}",
+            OutputLine::new(output.stdout.clone()),
+            OutputLine::new(output.stderr.clone())
+        )
+    })?;
+    Ok(output)
+}
+
+/// Removes the quarantine attribute from `path`, if it has one.
+pub async fn remove_quarantine(log: &slog::Logger, path: &Path) -> Result<()> {
+    let output = run(Command::new("/usr/bin/xattr")
+        .arg("-d")
+        .arg(QUARANTINE_ATTR)
+        .arg(path))
+    .await?;
+    if output.status.success() {
+        debug!(log, "Removed quarantine attribute from {path:?}");
+    } else if !String::from_utf8_lossy(&output.stderr).contains("No such xattr") {
+        return Err(anyhow::anyhow!(
+            "Failed to remove quarantine attribute from {path:?}: {:?}",
+            OutputLine::new(output.stderr)
+        ));
+    }
+    Ok(())
+}
+
+/// Signs `path` with an ad-hoc signature, replacing any existing signature.
+pub async fn sign_ad_hoc(log: &slog::Logger, path: &Path) -> Result<()> {
+    run_ok(
+        Command::new("/usr/bin/codesign")
+            .args(["--force", "--sign", "-"])
+            .arg(path),
+    )
+    .await?;
+    debug!(log, "Signed {path:?} ad-hoc");
+    Ok(())
+}
+
+/// Copies the library at `src` into the cache, clears it of quarantine, and signs it so that it
+/// can be injected. Returns the path of the staged copy.
+pub async fn stage_library(log: &slog::Logger, src: &Path) -> Result<PathBuf> {
+    let hash = {
+        let src = src.to_owned();
+        tokio::task::spawn_blocking(move || {
+            let mut hasher = blake3::Hasher::new();
+            hasher.update_mmap(&src)?;
+            Ok::<_, std::io::Error>(hasher.finalize())
+        })
+        .await??
+    };
+
+    let mut path = cache_dir().join("staged");
+    tokio::fs::create_dir_all(&path)
+        .await
+        .with_context(|| format!("Failed to create {path:?}"))?;
+    path.push(hash.to_hex().as_str());
+    if let Some(ext) = src.extension() {
+        path.set_extension(ext);
+    }
+
+    if !tokio::fs::try_exists(&path).await? {
+        let temp_path = path.with_added_extension("tmp");
+        tokio::fs::copy(src, &temp_path)
+            .await
+            .with_context(|| format!("Failed to copy {src:?} to {temp_path:?}"))?;
+        remove_quarantine(log, &temp_path).await?;
+        sign_ad_hoc(log, &temp_path).await?;
+        tokio::fs::rename(&temp_path, &path).await?;
+        info!(log, "Staged {src:?} at {path:?}");
+    } else {
+        // the attribute may have been added back by something else in the meantime
+        remove_quarantine(log, &path).await?;
+    }
+
+    Ok(path)
+}
+
+/// Whether System Integrity Protection is enabled. Returns `None` if it could not be determined.
+pub async fn sip_enabled() -> Option<bool> {
+    let output = run(Command::new("/usr/bin/csrutil").arg("status"))
+        .await
+        .ok()?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    if stdout.contains("status: enabled") {
+        Some(true)
+    } else if stdout.contains("status: disabled") {
+        Some(false)
+    } else {
+        None
+    }
+}
+
+/// Why `DYLD_INSERT_LIBRARIES` will be ignored when launching a binary.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InjectionBlocker {
+    /// The binary was signed with the hardened runtime, as is required for notarization, and
+    /// does not have the entitlement that would let it load injected libraries.
+    HardenedRuntime,
+    /// The binary is a system binary, which SIP protects from injection.
+    SystemIntegrityProtection,
+}
+
+impl InjectionBlocker {
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::HardenedRuntime => "hardened_runtime",
+            Self::SystemIntegrityProtection => "sip",
+        }
+    }
+}
+
+/// Checks whether the binary at `path` (or the app bundle, if `path` is one) will refuse
+/// injected libraries.
+pub async fn find_injection_blocker(
+    log: &slog::Logger,
+    path: &Path,
+) -> Result<Option<InjectionBlocker>> {
+    const SYSTEM_DIRS: &[&str] = &["/System", "/usr", "/bin", "/sbin"];
+
+    if SYSTEM_DIRS.iter().any(|dir| path.starts_with(dir)) && sip_enabled().await != Some(false) {
+        return Ok(Some(InjectionBlocker::SystemIntegrityProtection));
+    }
+
+    // codesign writes the details to stderr
+    let output = run(Command::new("/usr/bin/codesign")
+        .args(["--display", "--verbose"])
+        .arg(path))
+    .await?;
+    if !output.status.success() {
+        debug!(log, "{path:?} is not signed");
+        return Ok(None);
+    }
+    let details = String::from_utf8_lossy(&output.stderr);
+    let hardened = details
+        .lines()
+        .filter_map(|line| line.split_once("flags="))
+        .any(|(_, flags)| flags.contains("(runtime)") || flags.contains(",runtime"));
+    if !hardened {
+        return Ok(None);
+    }
+
+    let output = run(Command::new("/usr/bin/codesign")
+        .args(["--display", "--entitlements", "-", "--xml"])
+        .arg(path))
+    .await?;
+    let entitlements = String::from_utf8_lossy(&output.stdout);
+    if entitlements.contains(ALLOW_DYLD_ENV_ENTITLEMENT) {
+        debug!(
+            log,
+            "{path:?} uses the hardened runtime, but allows DYLD environment variables"
+        );
+        return Ok(None);
+    }
+
+    Ok(Some(InjectionBlocker::HardenedRuntime))
+}
+
+/// Finds the app bundles in the game's install directory.
+async fn find_app_bundles(install_dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut bundles = Vec::new();
+    let mut iter = tokio::fs::read_dir(install_dir)
+        .await
+        .with_context(|| format!("Failed to read {install_dir:?}"))?;
+    while let Some(e) = iter.next_entry().await? {
+        if e.file_name().as_encoded_bytes().ends_with(b".app") {
+            bundles.push(e.path());
+        }
+    }
+    Ok(bundles)
+}
+
+/// Returns the paths, relative to `bundle`, of the files that signing the bundle replaces.
+async fn signed_files(bundle: &Path) -> Result<Vec<PathBuf>> {
+    let contents = Path::new("Contents");
+    let mut files = vec![contents.join("_CodeSignature").join("CodeResources")];
+    let macos_dir = bundle.join(contents).join("MacOS");
+    let mut iter = tokio::fs::read_dir(&macos_dir)
+        .await
+        .with_context(|| format!("Failed to read {macos_dir:?}"))?;
+    while let Some(e) = iter.next_entry().await? {
+        if e.file_type().await?.is_file() {
+            files.push(contents.join("MacOS").join(e.file_name()));
+        }
+    }
+    Ok(files)
+}
+
+/// Replaces the signature of the app bundle at `bundle` with an ad-hoc one that does not opt into
+/// the hardened runtime, so that it accepts injected libraries.
+///
+/// The replaced files are recorded in the game's receipt, so restoring the game to vanilla puts
+/// the original signature back.
+pub async fn resign_without_hardened_runtime(
+    log: &slog::Logger,
+    game_id: &str,
+    install_dir: &Path,
+    bundle: &Path,
+) -> Result<()> {
+    let rel_bundle = bundle
+        .strip_prefix(install_dir)
+        .with_context(|| format!("{bundle:?} is not inside of {install_dir:?}"))?;
+    let files = signed_files(bundle)
+        .await?
+        .into_iter()
+        .map(|path| rel_bundle.join(path))
+        .collect::<Vec<_>>();
+    receipts::record_before_replacing(log, game_id, install_dir, files.iter().cloned())
+        .await
+        .context("Failed to back up game files")?;
+    // the hardened runtime is a signing option, which is not kept when re-signing without it
+    sign_ad_hoc(log, bundle).await?;
+    receipts::record_written(game_id, install_dir, files)
+        .await
+        .context("Failed to record re-signed game files")?;
+    info!(log, "Re-signed {bundle:?} without the hardened runtime");
+    Ok(())
+}
+
+/// Warns the user via a doctor's note if the game in `install_dir` will not accept the agent.
+///
+/// Games that are blocked by the hardened runtime can be re-signed without it instead. Nothing
+/// can be done about System Integrity Protection short of the user disabling it.
+pub async fn check_injection_allowed(
+    log: &slog::Logger,
+    ipc: &mut InProcessIpc,
+    game_id: &str,
+    install_dir: &Path,
+) -> Result<(), crate::Error> {
+    let mut blocked = None;
+    for bundle in find_app_bundles(install_dir).await? {
+        match find_injection_blocker(log, &bundle).await {
+            Ok(Some(blocker)) => {
+                blocked = Some((bundle, blocker));
+                break;
+            }
+            Ok(None) => {}
+            Err(e) => warn!(log, "Unable to inspect signature of {bundle:?}: {e}"),
+        }
+    }
+    let Some((bundle, blocker)) = blocked else {
+        return Ok(());
+    };
+    warn!(
+        log,
+        "{bundle:?} will not load injected libraries: {}",
+        blocker.as_str()
+    );
+
+    #[derive(Clone, Copy, serde::Deserialize, serde::Serialize)]
+    #[serde(rename_all = "snake_case")]
+    enum Fix {
+        Resign,
+        Ignore,
+        Abort,
+    }
+
+    let fixes = [Fix::Resign, Fix::Ignore, Fix::Abort]
+        .into_iter()
+        .filter(|fix| match fix {
+            Fix::Resign => blocker == InjectionBlocker::HardenedRuntime,
+            _ => true,
+        })
+        .map(|id| DoctorFix {
+            id,
+            label: None,
+            confirm_label: None,
+            description: None,
+        });
+
+    let choice = ipc
+        .prompt_patient(
+            "macos_injection_blocked",
+            Some(format!(
+                "doctor.macos_injection_blocked.message_{}",
+                blocker.as_str()
+            )),
+            Some([("path".to_owned(), bundle.to_string_lossy().into_owned())].into()),
+            fixes,
+            Fix::Abort,
+        )
+        .await?;
+    match choice {
+        Fix::Resign => {
+            resign_without_hardened_runtime(log, game_id, install_dir, &bundle).await?;
+            Ok(())
+        }
+        Fix::Ignore => Ok(()),
+        Fix::Abort => Err(crate::Error::Aborted),
+    }
+}
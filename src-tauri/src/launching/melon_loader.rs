@@ -0,0 +1,175 @@
+//! Launching games with MelonLoader.
+//!
+//! MelonLoader is normally installed straight into the game directory, where its `version.dll`
+//! proxy gets loaded by the game. Instead, Manderrow keeps a shared copy under [`LOADERS_DIR`],
+//! has the agent load its proxy, and points `--melonloader.basedir` at the profile, so that the
+//! profile's folders are used for mods and user data.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context as _, Result};
+use slog::debug;
+use tauri::AppHandle;
+use uuid::Uuid;
+
+use crate::installing::{install_zip, CacheOptions};
+use crate::profiles::{profile_path, LaunchOptions, LoaderLogLevel};
+use crate::stores::steam::proton::adapt_host_path;
+use crate::util::IoErrorKindExt as _;
+
+use super::{github, InstructionEmitter, LOADERS_DIR};
+
+pub const VERSION: &str = "0.6.6";
+
+/// The folder holding MelonLoader's own assemblies and dependencies, which must be found in the
+/// base directory.
+const LOADER_FOLDER: &str = "MelonLoader";
+
+/// MelonLoader only runs natively on Windows, so everywhere else the game must use Proton.
+fn get_target(uses_proton: bool) -> Result<&'static str> {
+    Ok(
+        match (std::env::consts::OS, std::env::consts::ARCH, uses_proton) {
+            // x64 games run under emulation on Windows on ARM
+            ("linux", "x86_64", true) | ("windows", "x86_64" | "aarch64", false) => "x64",
+            ("linux", "x86", true) | ("windows", "x86", false) => "x86",
+            (os, arch, uses_proton) => bail!(
+                "Unsupported platform combo for MelonLoader: (os: {os:?}, arch: {arch:?}, uses_proton: {uses_proton})"
+            ),
+        },
+    )
+}
+
+/// Whether MelonLoader can be loaded into games on this platform.
+pub fn is_supported(uses_proton: bool) -> bool {
+    get_target(uses_proton).is_ok()
+}
+
+/// Returns the absolute path to the MelonLoader installation. If MelonLoader has not yet been
+/// installed, this function will take care of that before returning.
+pub async fn get_melon_loader_path(
+    app: Option<&AppHandle>,
+    log: &slog::Logger,
+    uses_proton: bool,
+) -> Result<PathBuf> {
+    let target = get_target(uses_proton)?;
+    let path = LOADERS_DIR.join(format!("MelonLoader-{VERSION}-{target}"));
+    // like BepInEx releases, the version never changes once published, so GitHub only needs to
+    // be asked for its digest once
+    if tokio::fs::try_exists(&path).await? {
+        debug!(log, "Using extracted MelonLoader at {path:?}");
+        return Ok(path);
+    }
+
+    let asset = github::get_release_asset(
+        "LavaGang/MelonLoader",
+        &format!("v{VERSION}"),
+        &format!("MelonLoader.{target}.zip"),
+    )
+    .await?;
+    // keeps the verified copy cached until it has been extracted
+    let _pin = github::fetch_verified(app, log, "MelonLoader", &asset).await?;
+
+    install_zip(
+        app,
+        log,
+        format!("MelonLoader"),
+        &asset.url,
+        Some(CacheOptions::by_url()),
+        &path,
+        None,
+    )
+    .await?
+    .apply(log)
+    .await?
+    .commit(log)
+    .await?;
+
+    Ok(path)
+}
+
+/// Makes the `MelonLoader` folder of the installation at `melon_loader` available in the
+/// profile, which MelonLoader expects to find in its base directory.
+async fn link_loader_folder(log: &slog::Logger, melon_loader: &Path, profile: &Path) -> Result<()> {
+    let src = melon_loader.join(LOADER_FOLDER);
+    let dst = profile.join(LOADER_FOLDER);
+
+    match tokio::fs::symlink_metadata(&dst).await {
+        Ok(m) if m.is_symlink() => {
+            if tokio::fs::read_link(&dst).await? == src {
+                return Ok(());
+            }
+            tokio::fs::remove_file(&dst).await?;
+        }
+        Ok(m) if m.is_dir() => tokio::fs::remove_dir_all(&dst).await?,
+        Ok(_) => tokio::fs::remove_file(&dst).await?,
+        Err(e) if e.is_not_found() => {}
+        Err(e) => return Err(e).with_context(|| format!("Failed to inspect {dst:?}")),
+    }
+
+    debug!(log, "Linking {dst:?} to {src:?}");
+    #[cfg(unix)]
+    tokio::fs::symlink(&src, &dst).await?;
+    #[cfg(windows)]
+    if let Err(e) = tokio::fs::symlink_dir(&src, &dst).await {
+        // creating symlinks requires Developer Mode or admin rights, so fall back to a copy
+        debug!(log, "Unable to symlink, copying instead: {e}");
+        let (src, dst) = (src.clone(), dst.clone());
+        tokio::task::spawn_blocking(move || {
+            for e in walkdir::WalkDir::new(&src) {
+                let e = e?;
+                let path = dst.join(e.path().strip_prefix(&src)?);
+                if e.file_type().is_dir() {
+                    std::fs::create_dir_all(&path)?;
+                } else {
+                    std::fs::copy(e.path(), &path)?;
+                }
+            }
+            Ok::<_, anyhow::Error>(())
+        })
+        .await??;
+    }
+    Ok(())
+}
+
+/// MelonLoader's command line flags for the profile's launch options.
+fn option_args(options: &LaunchOptions) -> Vec<&'static str> {
+    let mut args = Vec::new();
+    if !options.show_console {
+        args.push("--melonloader.hideconsole");
+    }
+    match options.log_level {
+        LoaderLogLevel::Error => args.push("--melonloader.hidewarnings"),
+        LoaderLogLevel::Warning | LoaderLogLevel::Info => {}
+        LoaderLogLevel::Debug | LoaderLogLevel::All => args.push("--melonloader.debug"),
+    }
+    args
+}
+
+pub async fn emit_instructions(
+    app: Option<&AppHandle>,
+    log: &slog::Logger,
+    em: &mut InstructionEmitter<'_>,
+    profile_id: Uuid,
+    options: &LaunchOptions,
+    uses_proton: bool,
+) -> Result<()> {
+    let melon_loader = get_melon_loader_path(app, log, uses_proton).await?;
+    let profile_path = profile_path(profile_id);
+
+    link_loader_folder(log, &melon_loader, &profile_path)
+        .await
+        .context("Failed to link MelonLoader into the profile")?;
+
+    // Loaded ahead of the proxy so that the bootstrap finds it, as it expects it to be beside
+    // the game executable.
+    em.load_library(melon_loader.join("dobby.dll"));
+    em.load_library(melon_loader.join("version.dll"));
+
+    em.append_arg("--melonloader.basedir");
+    em.append_arg(adapt_host_path(&profile_path, uses_proton).as_ref());
+    for arg in option_args(options) {
+        em.append_arg(arg);
+    }
+
+    Ok(())
+}
@@ -1,27 +1,43 @@
+pub mod agent_test;
+mod arch;
 mod bep_in_ex;
 pub mod commands;
+mod environment;
+mod existing_loader;
+pub mod failures;
+mod github;
+pub mod instances;
+mod integrity;
+mod macos;
+mod melon_loader;
+mod receipts;
+mod support;
+pub mod wrappers;
 
 use std::ffi::OsStr;
 use std::panic::AssertUnwindSafe;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::LazyLock;
 
-use anyhow::{anyhow, Context, Result};
-use manderrow_paths::{cache_dir, logs_dir};
-use manderrow_types::games::PackageLoader;
-use slog::{debug, info, o};
-use tauri::Emitter;
+use anyhow::{anyhow, bail, Context, Result};
+use manderrow_paths::{cache_dir, local_data_dir, logs_dir};
+use manderrow_types::games::{Game, PackageLoader};
+use slog::{debug, info, o, warn};
 use tauri::{AppHandle, Manager};
 use tokio::process::Command;
 use uuid::Uuid;
 
 use crate::games::games_by_id;
-use crate::ipc::ConnectionId;
-use crate::ipc::{C2SMessage, IdentifiedC2SMessage, IpcState};
-use crate::profiles::{profile_path, read_profile_file};
+use crate::ipc::{C2SMessage, IpcState, LogRecorder, MessageHistory};
+use crate::ipc::{ConnectionId, DoctorFix, InProcessIpc};
+use crate::profiles::{profile_path, read_profile_file, LaunchOptions};
+use crate::stores::steam::paths::SteamPackaging;
 use crate::stores::steam::proton::{adapt_host_path, host_path_to_win_path};
 use crate::wrap::WrapperMode;
 
+pub use bep_in_ex::list_releases as list_bep_in_ex_releases;
+pub(crate) use receipts::receipts_dir;
+
 pub static LOADERS_DIR: LazyLock<PathBuf> = LazyLock::new(|| cache_dir().join("loaders"));
 
 #[derive(Debug, Clone, Copy, serde::Deserialize)]
@@ -41,6 +57,8 @@ pub async fn launch_profile(
 ) -> Result<(), crate::Error> {
     struct Logger {
         app: AssertUnwindSafe<AppHandle>,
+        recorder: LogRecorder,
+        history: MessageHistory,
         conn_id: ConnectionId,
     }
 
@@ -54,24 +72,22 @@ pub async fn launch_profile(
             record: &slog::Record<'_>,
             _values: &slog::OwnedKVList,
         ) -> Result<Self::Ok, Self::Err> {
-            _ = self.app.emit_to(
-                crate::ipc::EVENT_TARGET,
-                crate::ipc::EVENT_NAME,
-                IdentifiedC2SMessage {
-                    conn_id: self.conn_id,
-                    msg: &C2SMessage::Log {
-                        level: record.level().into(),
-                        scope: "manderrow".into(),
-                        message: record.msg().to_string(),
-                    },
-                },
-            );
+            let msg = C2SMessage::Log {
+                level: record.level().into(),
+                scope: "manderrow".into(),
+                message: record.msg().to_string(),
+            };
+            self.recorder
+                .record(&slog_scope::logger(), self.conn_id, &msg);
+            _ = crate::ipc::emit_message(&self.app, &self.history, self.conn_id, &msg);
             Ok(())
         }
     }
     let log = slog::Logger::root(
         Logger {
             app: AssertUnwindSafe(app.clone()),
+            recorder: ipc_state.recorder().clone(),
+            history: ipc_state.history().clone(),
             conn_id,
         },
         o!(),
@@ -81,7 +97,7 @@ pub async fn launch_profile(
         .connect(conn_id, app.clone())
         .context("Failed to complete internal IPC connection")?;
 
-    let game = match target {
+    let (game, launch_options) = match target {
         LaunchTarget::Profile(id) => {
             let mut path = profile_path(id);
             path.push("profile.json");
@@ -89,25 +105,77 @@ pub async fn launch_profile(
                 .await
                 .map_err(anyhow::Error::from)?;
             path.pop();
-            games_by_id()?
+            if !metadata.incomplete_replacements.is_empty() {
+                // an earlier installation was interrupted, so the profile may be missing mods
+                if let Err(e) = crate::profiles::repair::repair(&log, id, |_| true).await {
+                    warn!(log, "Failed to repair profile: {e:#}");
+                }
+            }
+            let game = games_by_id()?
                 .get(&*metadata.game)
                 .copied()
-                .with_context(|| format!("Unrecognized game {:?}", metadata.game))?
+                .with_context(|| format!("Unrecognized game {:?}", metadata.game))?;
+            (game, metadata.launch)
         }
-        LaunchTarget::Vanilla(id) => games_by_id()?
-            .get(id)
-            .copied()
-            .with_context(|| format!("Unrecognized game {:?}", id))?,
-    };
-    let Some(store_metadata) = game.store_platform_metadata.iter().next() else {
-        return Err(anyhow!("Unable to launch game").into());
+        LaunchTarget::Vanilla(id) => (
+            games_by_id()?
+                .get(id)
+                .copied()
+                .with_context(|| format!("Unrecognized game {:?}", id))?,
+            LaunchOptions::default(),
+        ),
     };
-    enum AgentSource {
-        Path(PathBuf),
-        Embedded(&'static [u8]),
+    let registration = instances::register(
+        ipc_state,
+        conn_id,
+        instances::Instance {
+            game: game.id,
+            profile: match target {
+                LaunchTarget::Profile(id) => Some(id),
+                LaunchTarget::Vanilla(_) => None,
+            },
+        },
+    )
+    .map_err(anyhow::Error::from)?;
+    if let LaunchTarget::Vanilla(_) = target {
+        // anything left in the game directory by a modded launch, such as the agent proxy DLL or
+        // the loader's files, would otherwise still be loaded
+        let restored = receipts::restore(&log, game.id)
+            .await
+            .context("Failed to restore game files for a vanilla launch")?;
+        if !restored.restored.is_empty() {
+            info!(
+                log,
+                "Restored {} game files for a vanilla launch",
+                restored.restored.len()
+            );
+        }
+        if !restored.drifted.is_empty() {
+            warn!(
+                log,
+                "Left game files that were changed since Manderrow wrote them: {:?}",
+                restored.drifted
+            );
+        }
     }
+    let store_metadata = match &launch_options.game_executable {
+        // launched directly, without going through a store
+        Some(_) => None,
+        None => Some(
+            select_store(&log, game)
+                .await
+                .context("Unable to launch game")?,
+        ),
+    };
+    let gamepass_package = match store_metadata {
+        Some(crate::games::StorePlatformMetadata::Xbox { store_identifier }) => Some(
+            crate::stores::gamepass::find_package(store_identifier)?
+                .with_context(|| format!("{} is not installed through the Xbox app", game.name))?,
+        ),
+        _ => None,
+    };
     let uses_proton = match store_metadata {
-        crate::games::StorePlatformMetadata::Steam { .. } => {
+        Some(crate::games::StorePlatformMetadata::Steam { .. }) => {
             let steam_metadata = game
                 .store_platform_metadata
                 .iter()
@@ -142,104 +210,254 @@ pub async fn launch_profile(
             }
         }
     };
+    let game_dir = match (
+        &launch_options.game_executable,
+        store_metadata.and_then(|m| m.steam_or_direct()),
+        &gamepass_package,
+    ) {
+        (Some(exe), _, _) => exe.parent().map(Path::to_owned),
+        (None, Some(steam_metadata), _) => {
+            Some(resolve_steam_install_dir(&app, &log, &mut ipc, game, steam_metadata.id).await?)
+        }
+        (None, None, Some(package)) => Some(package.install_dir.clone()),
+        (None, None, None) => None,
+    };
+    let agent_src = match &game_dir {
+        Some(install_dir) => {
+            select_agent(&log, game, install_dir, agent_src, &host_agent_path).await?
+        }
+        None => agent_src,
+    };
     match &agent_src {
         AgentSource::Path(path) => debug!(log, "Using bundled agent at {:?}", path),
         AgentSource::Embedded(_) => debug!(log, "Using embedded agent"),
     }
+    // A vanilla launch leaves the game directory untouched, so the agent is loaded without placing
+    // a proxy DLL there, just to connect to the console. It is preloaded by the wrapper, or injected
+    // into the games that Manderrow starts itself. Games that Steam starts on Windows or through
+    // Proton can only load it through the proxy DLL, so only the logs from Manderrow itself reach
+    // the console for them.
+    let vanilla = matches!(target, LaunchTarget::Vanilla(_));
+    let with_agent = loads_agent(store_metadata, vanilla, uses_proton);
+    if !with_agent {
+        info!(log, "Launching without the agent");
+    }
+    let bep_in_ex_version = bep_in_ex::BepInExVersion::for_launch(&launch_options);
+    let agent = with_agent.then(|| environment::describe_agent(&agent_src));
+    if let Some(install_dir) = &game_dir {
+        let installs_agent_proxy = with_agent
+            && !vanilla
+            && match store_metadata {
+                None => cfg!(windows),
+                Some(crate::games::StorePlatformMetadata::Xbox { .. }) => !gamepass_package
+                    .as_ref()
+                    .is_some_and(|package| package.is_protected()),
+                Some(_) => cfg!(windows) || uses_proton,
+            };
+        integrity::check(
+            &app,
+            &log,
+            &mut ipc,
+            game,
+            install_dir,
+            store_metadata.and_then(|m| m.steam_or_direct()),
+            installs_agent_proxy,
+            bep_in_ex_version.clone(),
+            uses_proton,
+        )
+        .await?;
+    }
+    let wrapper = wrappers::for_launch(&launch_options)?;
+    // only Manderrow's wrapper can run the game through other wrappers
+    let mut wrapped = false;
+    // the agent to inject into the game once it has started, instead of installing its proxy DLL
+    let mut inject_agent = None::<PathBuf>;
     let mut command: Command;
     match store_metadata {
-        crate::games::StorePlatformMetadata::Steam {
+        None => {
+            let exe = launch_options
+                .game_executable
+                .as_deref()
+                .expect("a store is selected unless the game is launched directly");
+            if !tokio::fs::try_exists(exe).await? {
+                return Err(anyhow!("The game executable {exe:?} does not exist").into());
+            }
+            let install_dir = game_dir
+                .as_deref()
+                .context("The game executable must be inside a directory")?;
+
+            if cfg!(windows) {
+                command = Command::new(exe);
+                if with_agent {
+                    if vanilla {
+                        let AgentSource::Path(agent_path) = agent_src else {
+                            unreachable!("embedded is only used when uses_proton is true")
+                        };
+                        inject_agent = Some(agent_path);
+                    } else {
+                        install_agent_proxy(&log, game, install_dir, agent_src).await?;
+                    }
+                    command.arg("{manderrow");
+                }
+            } else {
+                let AgentSource::Path(agent_path) = agent_src else {
+                    unreachable!("embedded is only used when uses_proton is true")
+                };
+                let agent_path = if cfg!(target_os = "macos") {
+                    macos::check_injection_allowed(&log, &mut ipc, game.id, install_dir).await?;
+                    macos::stage_library(&log, &agent_path)
+                        .await
+                        .context("Failed to prepare agent for injection")?
+                } else {
+                    agent_path
+                };
+                // the wrapper that Steam would otherwise run the game through
+                command = Command::new(
+                    std::env::current_exe().context("Failed to get current exe path")?,
+                );
+                command.arg("wrap-with-injection");
+                command.arg(exe);
+                command.arg("{manderrow");
+                command.arg("--agent-path");
+                command.arg(agent_path);
+                for arg in &wrapper {
+                    command.arg("--wrapper-arg");
+                    command.arg(arg);
+                }
+                wrapped = true;
+            }
+            command.current_dir(install_dir);
+        }
+        Some(crate::games::StorePlatformMetadata::Steam {
             store_identifier, ..
-        } => {
+        }) => {
             let steam_metadata = game
                 .store_platform_metadata
                 .iter()
                 .find_map(|m| m.steam_or_direct())
                 .context("Unsupported store platform")?;
 
-            command = if cfg!(windows) {
-                #[cfg(windows)]
-                {
-                    let mut p =
-                        crate::stores::steam::paths::get_steam_install_path_from_registry()?;
-                    p.push("steam.exe");
-                    Command::new(p)
-                }
-                #[cfg(not(windows))]
-                unreachable!()
-            } else if cfg!(target_os = "macos") {
-                Command::new("/Applications/Steam.app/Contents/MacOS/steam_osx")
-            } else if cfg!(unix) {
-                Command::new("steam")
+            let packaging = if cfg!(target_os = "linux") {
+                crate::stores::steam::paths::resolve_steam_packaging().await?
             } else {
-                return Err(anyhow!("Unsupported platform for Steam").into());
+                SteamPackaging::Native
             };
+            command = packaging.command()?;
             command.arg("-applaunch").arg(&**store_identifier);
 
-            command.arg("{manderrow");
+            if with_agent {
+                command.arg("{manderrow");
 
-            if !cfg!(windows) && !uses_proton {
-                crate::stores::steam::launching::ensure_unix_launch_args_are_applied(
-                    &log,
-                    Some(&mut ipc),
-                    steam_metadata.id,
-                    WrapperMode::Injection,
-                )
-                .await?;
-            }
-
-            if cfg!(windows) || uses_proton {
-                if uses_proton {
-                    // TODO: don't overwrite anything without checking with the user
-                    //       via a doctor's note.
-                    crate::stores::steam::proton::ensure_wine_will_load_dll_override(
+                if !cfg!(windows) && !uses_proton {
+                    crate::stores::steam::launching::ensure_unix_launch_args_are_applied(
                         &log,
+                        Some(&mut ipc),
                         steam_metadata.id,
-                        "winhttp",
+                        WrapperMode::Injection,
                     )
                     .await?;
                 }
 
-                let agent_install_target =
-                    crate::stores::steam::paths::resolve_app_install_directory(
+                if cfg!(windows) || uses_proton {
+                    if uses_proton {
+                        // TODO: don't overwrite anything without checking with the user
+                        //       via a doctor's note.
+                        crate::stores::steam::proton::ensure_wine_will_load_dll_override(
+                            &log,
+                            steam_metadata.id,
+                            "winhttp",
+                        )
+                        .await?;
+                    }
+
+                    let install_dir = crate::stores::steam::paths::resolve_app_install_directory(
                         &log,
                         steam_metadata.id,
                     )
-                    .await?
-                    .join("winhttp.dll");
-                match agent_src {
-                    AgentSource::Path(agent_path) => {
-                        tokio::fs::copy(&agent_path, &agent_install_target)
-                            .await
-                            .with_context(|| {
-                                format!(
-                                    "Failed to install agent from {:?} at {:?}",
-                                    agent_path, agent_install_target
-                                )
-                            })?;
+                    .await?;
+                    install_agent_proxy(&log, game, &install_dir, agent_src).await?;
+                } else {
+                    let AgentSource::Path(agent_path) = agent_src else {
+                        unreachable!("embedded is only used when uses_proton is true")
+                    };
+                    if cfg!(target_os = "linux") {
+                        if packaging == SteamPackaging::Flatpak {
+                            check_flatpak_access(&log, &mut ipc, &agent_path).await?;
+                        }
+                        check_steam_runtime(&log, &mut ipc, steam_metadata.id, &agent_path).await?;
                     }
-                    AgentSource::Embedded(agent_bytes) => {
-                        tokio::fs::write(&agent_install_target, agent_bytes)
+                    let agent_path = if cfg!(target_os = "macos") {
+                        let install_dir =
+                            crate::stores::steam::paths::resolve_app_install_directory(
+                                &log,
+                                steam_metadata.id,
+                            )
+                            .await?;
+                        macos::check_injection_allowed(&log, &mut ipc, game.id, &install_dir)
+                            .await?;
+                        macos::stage_library(&log, &agent_path)
                             .await
-                            .with_context(|| {
-                                format!(
-                                    "Failed to install agent from embedded bytes at {agent_install_target:?}",
-                                )
-                            })?;
+                            .context("Failed to prepare agent for injection")?
+                    } else {
+                        agent_path
+                    };
+                    command.arg("--agent-path");
+                    command.arg(packaging.host_to_sandbox(&agent_path));
+                    for arg in &wrapper {
+                        command.arg("--wrapper-arg");
+                        command.arg(arg);
                     }
+                    wrapped = true;
                 }
-            } else {
+            }
+        }
+        Some(crate::games::StorePlatformMetadata::Xbox { .. }) => {
+            if !cfg!(windows) {
+                return Err(anyhow!("Xbox app games can only be launched on Windows").into());
+            }
+            let package = gamepass_package
+                .as_ref()
+                .expect("the package is found for Xbox games");
+
+            debug!(
+                log,
+                "Launching package {} ({})",
+                package.full_name,
+                package.launch_uri()
+            );
+            command = crate::stores::gamepass::activate_command(package)?;
+
+            if !with_agent {
+                // nothing to set up
+            } else if package.is_protected() || vanilla {
                 let AgentSource::Path(agent_path) = agent_src else {
                     unreachable!("embedded is only used when uses_proton is true")
                 };
-                command.arg("--agent-path");
+                // the game folder is read-only, or left untouched for a vanilla launch, so the
+                // agent is injected once the game starts
+                command.arg("--inject");
                 command.arg(agent_path);
+            } else {
+                install_agent_proxy(&log, game, &package.install_dir, agent_src).await?;
+            }
+
+            if with_agent {
+                command.arg("--");
+                command.arg("{manderrow");
             }
         }
-        _ => return Err(anyhow!("Unsupported game store: {store_metadata:?}").into()),
+        Some(store_metadata) => {
+            return Err(anyhow!("Unsupported game store: {store_metadata:?}").into())
+        }
+    }
+    if !wrapper.is_empty() && !wrapped {
+        warn!(
+            log,
+            "Not running the game through {wrapper:?}, as it is not launched through Manderrow's wrapper"
+        );
     }
 
-    if uses_proton {
+    if uses_proton && with_agent {
         #[cfg(target_os = "linux")]
         {
             let path = cache_dir().join("host_dlfcn.dll.so");
@@ -280,12 +498,27 @@ pub async fn launch_profile(
         }
     }
 
-    command.arg("--enable");
+    if with_agent {
+        command.arg("--enable");
+    }
 
+    let mut loader = None;
     if modded {
         match (target, game.package_loader) {
             (LaunchTarget::Vanilla(_), _) => {}
             (LaunchTarget::Profile(profile), PackageLoader::BepInEx) => {
+                let source = existing_loader::reconcile_bep_in_ex(
+                    &log,
+                    &mut ipc,
+                    game,
+                    game_dir.as_deref(),
+                    bep_in_ex_version,
+                    uses_proton,
+                )
+                .await?;
+                let doorstop_path =
+                    std::env::var_os("OVERRIDE_DOORSTOP_LIBRARY_PATH").map(PathBuf::from);
+                loader = Some(source.describe(doorstop_path.is_some()));
                 let mut em = InstructionEmitter {
                     command: &mut command,
                     insns: true,
@@ -296,11 +529,9 @@ pub async fn launch_profile(
                     &mut em,
                     game,
                     profile,
-                    match std::env::var_os("BEPINEX_CI") {
-                        Some(s) if !s.is_empty() && s != "0" => bep_in_ex::BepInExVersion::Ci,
-                        _ => bep_in_ex::BepInExVersion::Stable,
-                    },
-                    std::env::var_os("OVERRIDE_DOORSTOP_LIBRARY_PATH").map(PathBuf::from),
+                    source,
+                    &launch_options,
+                    doorstop_path,
                     std::env::var_os("LEGACY_DOORSTOP")
                         .map(|s| s != "0")
                         .unwrap_or(false),
@@ -309,15 +540,38 @@ pub async fn launch_profile(
                 .await?;
                 em.start_insns();
             }
+            (LaunchTarget::Profile(profile), PackageLoader::MelonLoader) => {
+                loader = Some(format!("MelonLoader {}", melon_loader::VERSION));
+                let mut em = InstructionEmitter {
+                    command: &mut command,
+                    insns: true,
+                };
+                melon_loader::emit_instructions(
+                    Some(&app),
+                    &log,
+                    &mut em,
+                    profile,
+                    &launch_options,
+                    uses_proton,
+                )
+                .await?;
+                em.start_insns();
+            }
             (_, loader) => {
                 return Err(anyhow!("The mod loader {loader:?} is not yet supported").into())
             }
         }
     }
 
-    let c2s_tx = ipc_state
-        .spawn_external(log.clone(), app, conn_id)
-        .context("Failed to setup external IPC connection")?;
+    let c2s_tx = if with_agent {
+        Some(
+            ipc_state
+                .spawn_external(log.clone(), app.clone(), conn_id)
+                .context("Failed to setup external IPC connection")?,
+        )
+    } else {
+        None
+    };
 
     struct FailureGuard<'a> {
         c2s_tx: &'a str,
@@ -330,31 +584,496 @@ pub async fn launch_profile(
     }
 
     // TODO: come up with something nicer than this
-    let failure_guard = FailureGuard { c2s_tx: &c2s_tx };
+    let failure_guard = c2s_tx.as_deref().map(|c2s_tx| FailureGuard { c2s_tx });
 
-    command.arg("--c2s-tx");
-    command.arg(&c2s_tx);
+    if let Some(c2s_tx) = &c2s_tx {
+        push_connection_args(&mut command, c2s_tx, logs_dir(), uses_proton);
+    }
 
-    command.arg("--log-to-file");
-    command.arg("--logs-dir");
-    command.arg(adapt_host_path(logs_dir(), uses_proton).as_ref());
+    if let (LaunchTarget::Profile(id), Some(_)) = (target, &c2s_tx) {
+        crate::profiles::configs::watcher::spawn(slog_scope::logger(), app, id, conn_id);
+        crate::profiles::dev_mods::attach(id, conn_id);
+    }
 
-    command.arg("manderrow}");
+    let environment = environment::LaunchEnvironment {
+        game,
+        target,
+        store: store_metadata,
+        uses_proton,
+        agent,
+        loader,
+        command: &command,
+    };
+    // not `log`, which records into the same recording
+    ipc_state.recorder().record_header(
+        &slog_scope::logger(),
+        conn_id,
+        &environment.render(&log).await,
+    );
 
     info!(log, "Launching game: {command:?}");
-    let status = command
-        .status()
+    let mut child = command.spawn().context("Failed to start subprocess")?;
+    if let Some(agent_path) = inject_agent {
+        inject_into_child(&child, &agent_path).await?;
+    }
+    let status = child
+        .wait()
         .await
         .context("Failed to wait for subprocess to exit")?;
 
-    // no failure, forget the guard.
+    // no failure, forget the guards.
     std::mem::forget(failure_guard);
+    std::mem::forget(registration);
+    instances::launcher_exited(conn_id);
 
     info!(log, "Launcher exited with status code {status}");
 
     Ok(())
 }
 
+/// Returns whether the agent is loaded into the game. Vanilla launches load it wherever that
+/// doesn't require placing its proxy DLL in the game directory.
+fn loads_agent(
+    store_metadata: Option<&crate::games::StorePlatformMetadata>,
+    vanilla: bool,
+    uses_proton: bool,
+) -> bool {
+    match store_metadata {
+        Some(crate::games::StorePlatformMetadata::Steam { .. }) if vanilla => {
+            !cfg!(windows) && !uses_proton
+        }
+        _ => true,
+    }
+}
+
+/// Passes the agent what it needs to connect to Manderrow, and closes its arguments.
+fn push_connection_args(command: &mut Command, c2s_tx: &str, logs_dir: &Path, uses_proton: bool) {
+    command.arg("--c2s-tx");
+    command.arg(c2s_tx);
+
+    command.arg("--log-to-file");
+    command.arg("--logs-dir");
+    command.arg(adapt_host_path(logs_dir, uses_proton).as_ref());
+
+    command.arg("manderrow}");
+}
+
+/// Injects the agent into the game, which Manderrow started itself.
+async fn inject_into_child(child: &tokio::process::Child, agent_path: &Path) -> Result<()> {
+    #[cfg(windows)]
+    {
+        let handle = child.raw_handle().context("The game already exited")?;
+        let handle = windows::Win32::Foundation::HANDLE(handle);
+        let agent_path = agent_path.to_owned();
+        tokio::task::spawn_blocking(move || {
+            crate::stores::gamepass::activate::inject_library(handle, &agent_path)
+                .with_context(|| format!("Failed to inject {agent_path:?}"))
+        })
+        .await?
+    }
+    #[cfg(not(windows))]
+    {
+        _ = (child, agent_path);
+        unreachable!("the agent is only injected on Windows")
+    }
+}
+
+enum AgentSource {
+    Path(PathBuf),
+    Embedded(&'static [u8]),
+}
+
+/// Picks the store to launch the game through: the first one that the game is installed from,
+/// falling back to the first one listed.
+async fn select_store<'a>(
+    log: &slog::Logger,
+    game: &'a Game<'a>,
+) -> Option<&'a crate::games::StorePlatformMetadata<'a>> {
+    for store_metadata in &game.store_platform_metadata {
+        let installed = match store_metadata {
+            crate::games::StorePlatformMetadata::Xbox { store_identifier } => {
+                match crate::stores::gamepass::find_package(store_identifier) {
+                    Ok(package) => package.is_some(),
+                    Err(e) => {
+                        warn!(log, "Unable to look up Xbox app package: {e:#}");
+                        false
+                    }
+                }
+            }
+            _ => match store_metadata.steam_or_direct() {
+                Some(steam_metadata) => crate::stores::steam::paths::resolve_app_install_directory(
+                    log,
+                    steam_metadata.id,
+                )
+                .await
+                .is_ok(),
+                None => false,
+            },
+        };
+        if installed {
+            return Some(store_metadata);
+        }
+    }
+    game.store_platform_metadata.first()
+}
+
+/// Finds the directory that the game is installed in, the same way a launch with
+/// `launch_options` would, but without asking the user anything. Returns `None` if the game isn't
+/// installed.
+pub async fn find_install_dir(
+    log: &slog::Logger,
+    game: &Game<'_>,
+    launch_options: &LaunchOptions,
+) -> Result<Option<PathBuf>> {
+    if let Some(exe) = &launch_options.game_executable {
+        return Ok(exe.parent().map(Path::to_owned));
+    }
+    match select_store(log, game).await {
+        Some(crate::games::StorePlatformMetadata::Xbox { store_identifier }) => {
+            Ok(crate::stores::gamepass::find_package(store_identifier)?
+                .map(|package| package.install_dir))
+        }
+        Some(store_metadata) => match store_metadata.steam_or_direct() {
+            Some(steam_metadata) => {
+                use crate::stores::steam::paths::{
+                    resolve_app_install_directory, AppNotInstalledError,
+                };
+
+                match resolve_app_install_directory(log, steam_metadata.id).await {
+                    Ok(t) => Ok(Some(t)),
+                    Err(e) if e.is::<AppNotInstalledError>() => Ok(None),
+                    Err(e) => Err(e),
+                }
+            }
+            None => Ok(None),
+        },
+        None => Ok(None),
+    }
+}
+
+/// Installs the agent into the game directory as `winhttp.dll`, which the game loads on startup.
+async fn install_agent_proxy(
+    log: &slog::Logger,
+    game: &Game<'_>,
+    install_dir: &Path,
+    agent_src: AgentSource,
+) -> Result<()> {
+    receipts::record_before_replacing(log, game.id, install_dir, [PathBuf::from("winhttp.dll")])
+        .await
+        .context("Failed to back up game files")?;
+    let agent_install_target = install_dir.join("winhttp.dll");
+    match agent_src {
+        AgentSource::Path(agent_path) => {
+            tokio::fs::copy(&agent_path, &agent_install_target)
+                .await
+                .with_context(|| {
+                    format!(
+                        "Failed to install agent from {:?} at {:?}",
+                        agent_path, agent_install_target
+                    )
+                })?;
+        }
+        AgentSource::Embedded(agent_bytes) => {
+            tokio::fs::write(&agent_install_target, agent_bytes)
+                .await
+                .with_context(|| {
+                    format!(
+                        "Failed to install agent from embedded bytes at {agent_install_target:?}",
+                    )
+                })?;
+        }
+    }
+    receipts::record_written(game.id, install_dir, [PathBuf::from("winhttp.dll")])
+        .await
+        .context("Failed to record installed agent")?;
+    Ok(())
+}
+
+/// Checks that the agent was built for the same architecture as the game, switching to a bundled
+/// agent for the game's architecture if it was not.
+async fn select_agent(
+    log: &slog::Logger,
+    game: &Game<'_>,
+    install_dir: &Path,
+    agent_src: AgentSource,
+    host_agent_path: &Path,
+) -> Result<AgentSource> {
+    let exe = match arch::find_game_executable(game, install_dir).await {
+        Ok(Some(exe)) => exe,
+        Ok(None) => {
+            debug!(log, "Unable to find game executable in {install_dir:?}");
+            return Ok(agent_src);
+        }
+        Err(e) => {
+            warn!(log, "Unable to find game executable: {e}");
+            return Ok(agent_src);
+        }
+    };
+    let exe_info = match arch::binary_info(&exe).await {
+        Ok(t) => t,
+        Err(e) => {
+            warn!(
+                log,
+                "Unable to determine architecture of game executable: {e:#}"
+            );
+            return Ok(agent_src);
+        }
+    };
+    let agent_info = match &agent_src {
+        AgentSource::Path(path) => arch::binary_info(path).await,
+        AgentSource::Embedded(bytes) => arch::read_binary_info(std::io::Cursor::new(bytes)),
+    };
+    let agent_info = match agent_info {
+        Ok(t) => t,
+        Err(e) => {
+            warn!(log, "Unable to determine architecture of agent: {e:#}");
+            return Ok(agent_src);
+        }
+    };
+    debug!(
+        log,
+        "Game executable {exe:?} is {exe_info:?}, agent is {agent_info:?}"
+    );
+
+    // a binary with code for several architectures runs as the native one if it can, e.g. ARM64X
+    // games on Windows on ARM
+    let targets = match arch::Arch::native().filter(|&a| exe_info.supports(a)) {
+        Some(native) => vec![native],
+        None => exe_info.archs.clone(),
+    };
+    if targets.iter().any(|&a| agent_info.loadable_into(a)) {
+        return Ok(agent_src);
+    }
+
+    if matches!(agent_src, AgentSource::Path(_))
+        && std::env::var_os("MANDERROW_AGENT_PATH").is_none()
+    {
+        for &target in &targets {
+            // the agent for the target itself is preferred over one that can merely be loaded
+            let candidates = std::iter::once(target).chain(
+                arch::Arch::ALL
+                    .iter()
+                    .copied()
+                    .filter(|&a| a != target && target.can_load(a)),
+            );
+            for candidate in candidates {
+                let path =
+                    host_agent_path.with_file_name(format!("libmanderrow_agent-{candidate}"));
+                if !tokio::fs::try_exists(&path).await? {
+                    continue;
+                }
+                let info = arch::binary_info(&path).await?;
+                if info.format == agent_info.format && info.loadable_into(target) {
+                    info!(log, "Using {candidate} agent at {path:?} for {target} game");
+                    return Ok(AgentSource::Path(path));
+                }
+            }
+        }
+    }
+
+    let fmt_archs = |archs: &[arch::Arch]| {
+        archs
+            .iter()
+            .map(|a| a.as_str())
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    bail!(
+        "The game executable {exe:?} was built for {}, but the agent was built for {}. Manderrow \
+         does not include an agent for this game's architecture.",
+        fmt_archs(&exe_info.archs),
+        fmt_archs(&agent_info.archs),
+    )
+}
+
+/// Reports whether the game will run inside the Steam Linux Runtime, and makes sure the agent
+/// can be shared with its container.
+async fn check_steam_runtime(
+    log: &slog::Logger,
+    ipc: &mut InProcessIpc,
+    game_id: &str,
+    agent_path: &Path,
+) -> Result<(), crate::Error> {
+    use crate::stores::steam::runtime;
+
+    let tool = match runtime::compat_tool(log, game_id).await {
+        Ok(Some(tool)) if runtime::is_steam_linux_runtime(&tool) => tool,
+        Ok(_) => return Ok(()),
+        Err(e) => {
+            warn!(log, "Unable to determine compatibility tool: {e}");
+            return Ok(());
+        }
+    };
+    info!(
+        log,
+        "Game runs inside the Steam Linux Runtime ({tool}), the agent will be shared with its container"
+    );
+
+    let dir = agent_path.parent().unwrap_or(agent_path);
+    if let Err(e) = runtime::expose_to_container(None, dir) {
+        warn!(log, "{e}");
+
+        #[derive(Clone, Copy, serde::Deserialize, serde::Serialize)]
+        #[serde(rename_all = "snake_case")]
+        enum Fix {
+            Ignore,
+            Abort,
+        }
+
+        let choice = ipc
+            .prompt_patient(
+                "steam_runtime",
+                None,
+                Some(
+                    [
+                        ("runtime".to_owned(), tool),
+                        ("path".to_owned(), dir.to_string_lossy().into_owned()),
+                    ]
+                    .into(),
+                ),
+                [Fix::Ignore, Fix::Abort].map(|id| DoctorFix {
+                    id,
+                    label: None,
+                    confirm_label: None,
+                    description: None,
+                }),
+                Fix::Abort,
+            )
+            .await?;
+        match choice {
+            Fix::Ignore => {}
+            Fix::Abort => return Err(crate::Error::Aborted),
+        }
+    }
+
+    Ok(())
+}
+
+/// Makes sure that Steam's Flatpak sandbox, which the game and Manderrow's wrapper run inside of,
+/// can see Manderrow's executable, the agent, and the loaders and profiles that it launches with.
+async fn check_flatpak_access(
+    log: &slog::Logger,
+    ipc: &mut InProcessIpc,
+    agent_path: &Path,
+) -> Result<(), crate::Error> {
+    use crate::stores::steam::flatpak;
+
+    let exe = std::env::current_exe().context("Failed to get current exe path")?;
+    let mut paths = vec![
+        exe.parent().unwrap_or(&exe),
+        agent_path.parent().unwrap_or(agent_path),
+        cache_dir().as_path(),
+        local_data_dir().as_path(),
+    ];
+    paths.sort();
+    // the parents sort before their children
+    paths.dedup_by(|a, b| a.starts_with(*b));
+    let hidden = flatpak::find_hidden(&paths).await?;
+    if hidden.is_empty() {
+        return Ok(());
+    }
+    warn!(log, "Steam's Flatpak sandbox can't see {hidden:?}");
+
+    #[derive(Clone, Copy, serde::Deserialize, serde::Serialize)]
+    #[serde(rename_all = "snake_case")]
+    enum Fix {
+        Expose,
+        Ignore,
+        Abort,
+    }
+
+    let choice = ipc
+        .prompt_patient(
+            "steam_flatpak",
+            None,
+            Some(
+                [(
+                    "paths".to_owned(),
+                    hidden
+                        .iter()
+                        .map(|path| path.to_string_lossy())
+                        .collect::<Vec<_>>()
+                        .join(", "),
+                )]
+                .into(),
+            ),
+            [Fix::Expose, Fix::Ignore, Fix::Abort].map(|id| DoctorFix {
+                id,
+                label: None,
+                confirm_label: None,
+                description: None,
+            }),
+            Fix::Abort,
+        )
+        .await?;
+    match choice {
+        Fix::Expose => {
+            flatpak::expose(log, &hidden).await?;
+            // the override only applies once Steam is started again
+            crate::stores::steam::launching::kill_steam(log).await?;
+        }
+        Fix::Ignore => {}
+        Fix::Abort => return Err(crate::Error::Aborted),
+    }
+    Ok(())
+}
+
+/// Resolves the install directory of the Steam game, asking the user what to do via a doctor's
+/// note if it is not installed in any of their Steam libraries.
+async fn resolve_steam_install_dir(
+    app: &AppHandle,
+    log: &slog::Logger,
+    ipc: &mut InProcessIpc,
+    game: &Game<'_>,
+    steam_id: &str,
+) -> Result<PathBuf, crate::Error> {
+    use crate::stores::steam::paths::{resolve_app_install_directory, AppNotInstalledError};
+
+    loop {
+        match resolve_app_install_directory(log, steam_id).await {
+            Ok(t) => return Ok(t),
+            Err(e) if e.is::<AppNotInstalledError>() => warn!(log, "{e}"),
+            Err(e) => return Err(e.into()),
+        }
+
+        #[derive(Clone, Copy, serde::Deserialize, serde::Serialize)]
+        #[serde(rename_all = "snake_case")]
+        enum Fix {
+            Install,
+            Retry,
+            Abort,
+        }
+
+        let choice = ipc
+            .prompt_patient(
+                "steam_app_missing",
+                None,
+                Some([("game".to_owned(), game.name.to_string())].into()),
+                [Fix::Install, Fix::Retry, Fix::Abort].map(|id| DoctorFix {
+                    id,
+                    label: None,
+                    confirm_label: None,
+                    description: None,
+                }),
+                Fix::Abort,
+            )
+            .await?;
+        match choice {
+            Fix::Install => {
+                use tauri_plugin_opener::OpenerExt as _;
+
+                app.opener()
+                    .open_url(format!("steam://install/{steam_id}"), None::<&str>)
+                    .context("Failed to open Steam")?;
+                // installing takes a while, so the game can be launched again once it's done
+                return Err(crate::Error::Aborted);
+            }
+            Fix::Retry => {}
+            Fix::Abort => return Err(crate::Error::Aborted),
+        }
+    }
+}
+
 struct InstructionEmitter<'a> {
     command: &'a mut Command,
     insns: bool,
@@ -381,6 +1100,14 @@ impl<'a> InstructionEmitter<'a> {
             .args(["--insn-load-library".as_ref(), path.as_ref()]);
     }
 
+    /// Has the wrapper preload the library alongside the agent, rather than the agent loading it
+    /// once it has started. Only works where the game is launched through the wrapper.
+    pub fn preload_library(&mut self, path: impl AsRef<OsStr>) {
+        self.start_insns();
+        self.command
+            .args(["--preload-library".as_ref(), path.as_ref()]);
+    }
+
     pub fn set_var(&mut self, key: impl AsRef<OsStr>, value: impl AsRef<OsStr>) {
         self.start_insns();
         let mut kv = key.as_ref().to_owned();
@@ -407,3 +1134,54 @@ impl<'a> InstructionEmitter<'a> {
         self.command.arg(arg.as_ref());
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::borrow::Cow;
+    use std::path::Path;
+
+    use tokio::process::Command;
+
+    use crate::games::StorePlatformMetadata;
+
+    use super::{loads_agent, push_connection_args};
+
+    #[test]
+    fn test_vanilla_loads_agent_without_proxy() {
+        let steam = StorePlatformMetadata::Steam {
+            store_identifier: Cow::Borrowed("1966720"),
+            store_page_identifier: None,
+        };
+        let xbox = StorePlatformMetadata::Xbox {
+            store_identifier: Cow::Borrowed("Game"),
+        };
+        for store in [None, Some(&xbox)] {
+            assert!(loads_agent(store, true, false));
+            assert!(loads_agent(store, false, false));
+        }
+        assert_eq!(loads_agent(Some(&steam), true, false), !cfg!(windows));
+        assert!(!loads_agent(Some(&steam), true, true));
+        assert!(loads_agent(Some(&steam), false, true));
+    }
+
+    #[test]
+    fn test_push_connection_args() {
+        let mut command = Command::new("game");
+        command.arg("{manderrow");
+        command.arg("--enable");
+        push_connection_args(&mut command, "tx", Path::new("logs"), false);
+        assert_eq!(
+            command.as_std().get_args().collect::<Vec<_>>(),
+            [
+                "{manderrow",
+                "--enable",
+                "--c2s-tx",
+                "tx",
+                "--log-to-file",
+                "--logs-dir",
+                "logs",
+                "manderrow}",
+            ]
+        );
+    }
+}
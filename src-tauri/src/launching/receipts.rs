@@ -0,0 +1,420 @@
+//! Receipts of the files that Manderrow has written into game directories.
+//!
+//! Before a file in a game directory is created or replaced, the original (if any) is backed up
+//! and recorded in the game's receipt under the local data directory. The receipt can later be
+//! used to put the game directory back the way it was.
+//!
+//! Once a file has been written, its hash is recorded too, so that files which have since been
+//! changed by something else, like a game update or another mod manager, can be told apart from
+//! Manderrow's own.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context as _, Result};
+use manderrow_paths::local_data_dir;
+use slog::{debug, info, warn};
+
+use crate::util::IoErrorKindExt as _;
+
+pub(crate) fn receipts_dir() -> PathBuf {
+    local_data_dir().join("receipts")
+}
+
+/// Where the originals recorded in the receipts under `dir` are backed up.
+fn backups_dir(dir: &Path) -> PathBuf {
+    dir.join("backups")
+}
+
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]
+pub struct ReceiptEntry {
+    /// BLAKE3 hash of the file that was there before Manderrow first wrote to the path, or
+    /// `None` if there was no file. The original is backed up under its hash.
+    pub original: Option<blake3::Hash>,
+    /// BLAKE3 hash of the file that Manderrow last wrote to the path, or `None` if it was written
+    /// before these were recorded.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub written: Option<blake3::Hash>,
+}
+
+#[derive(Debug, Default, Clone, serde::Deserialize, serde::Serialize)]
+pub struct Receipt {
+    /// The game directory that the entries are relative to.
+    pub install_dir: PathBuf,
+    pub entries: BTreeMap<PathBuf, ReceiptEntry>,
+}
+
+impl Receipt {
+    fn path(dir: &Path, game_id: &str) -> PathBuf {
+        dir.join(format!("{game_id}.json"))
+    }
+
+    pub async fn read(game_id: &str) -> Result<Option<Self>> {
+        Self::read_in(&receipts_dir(), game_id).await
+    }
+
+    async fn read_in(dir: &Path, game_id: &str) -> Result<Option<Self>> {
+        let path = Self::path(dir, game_id);
+        let mut bytes = match tokio::fs::read(&path).await {
+            Ok(t) => t,
+            Err(e) if e.is_not_found() => return Ok(None),
+            Err(e) => return Err(anyhow::Error::new(e).context(format!("Failed to read {path:?}"))),
+        };
+        Ok(Some(simd_json::from_slice(&mut bytes).with_context(
+            || format!("Failed to parse receipt at {path:?}"),
+        )?))
+    }
+
+    async fn write(&self, dir: &Path, game_id: &str) -> Result<()> {
+        tokio::fs::create_dir_all(dir).await?;
+        let path = Self::path(dir, game_id);
+        tokio::fs::write(&path, simd_json::to_vec(self)?)
+            .await
+            .with_context(|| format!("Failed to write receipt at {path:?}"))?;
+        Ok(())
+    }
+}
+
+async fn hash_file(path: &Path) -> Result<blake3::Hash> {
+    let path = path.to_owned();
+    tokio::task::spawn_blocking(move || {
+        let mut hasher = blake3::Hasher::new();
+        hasher
+            .update_mmap(&path)
+            .with_context(|| format!("Failed to hash {path:?}"))?;
+        Ok(hasher.finalize())
+    })
+    .await?
+}
+
+/// Records the current state of the files at `rel_paths` in `install_dir`, backing up any that
+/// exist, so that they can be restored later. Must be called before the files are overwritten.
+///
+/// Paths that are already in the receipt are left alone, as the receipt must describe the state
+/// of the game directory from before Manderrow ever touched it. The exception is files that were
+/// changed by something else after Manderrow wrote them, which are backed up again as the new
+/// originals.
+pub async fn record_before_replacing(
+    log: &slog::Logger,
+    game_id: &str,
+    install_dir: &Path,
+    rel_paths: impl IntoIterator<Item = PathBuf>,
+) -> Result<()> {
+    record_before_replacing_in(log, &receipts_dir(), game_id, install_dir, rel_paths).await
+}
+
+/// Like [`record_before_replacing`], with the receipts kept under `dir`.
+pub(super) async fn record_before_replacing_in(
+    log: &slog::Logger,
+    dir: &Path,
+    game_id: &str,
+    install_dir: &Path,
+    rel_paths: impl IntoIterator<Item = PathBuf>,
+) -> Result<()> {
+    let mut receipt = read_for(log, dir, game_id, install_dir).await?;
+
+    let mut changed = false;
+    for rel_path in rel_paths {
+        let path = install_dir.join(&rel_path);
+        if let Some(entry) = receipt.entries.get(&rel_path) {
+            if !drifted(&path, entry).await? {
+                continue;
+            }
+            // the original has been replaced since, and restoring would undo that
+            warn!(
+                log,
+                "{path:?} was changed by something other than Manderrow, backing it up again"
+            );
+        }
+        let original = back_up(log, dir, &path).await?;
+        receipt.entries.insert(
+            rel_path,
+            ReceiptEntry {
+                original,
+                written: None,
+            },
+        );
+        changed = true;
+    }
+
+    if changed {
+        receipt.write(dir, game_id).await?;
+    }
+    Ok(())
+}
+
+/// Reads the game's receipt, starting a new one if there is none or the game has moved away from
+/// the directory that it describes.
+async fn read_for(
+    log: &slog::Logger,
+    dir: &Path,
+    game_id: &str,
+    install_dir: &Path,
+) -> Result<Receipt> {
+    Ok(match Receipt::read_in(dir, game_id).await? {
+        Some(receipt) if receipt.install_dir == install_dir => receipt,
+        Some(receipt) => {
+            warn!(
+                log,
+                "Game directory moved from {:?} to {install_dir:?}, starting a new receipt",
+                receipt.install_dir
+            );
+            Receipt {
+                install_dir: install_dir.to_owned(),
+                ..Default::default()
+            }
+        }
+        None => Receipt {
+            install_dir: install_dir.to_owned(),
+            ..Default::default()
+        },
+    })
+}
+
+/// Backs up the file at `path`, if there is one, under `dir`, returning its hash.
+async fn back_up(log: &slog::Logger, dir: &Path, path: &Path) -> Result<Option<blake3::Hash>> {
+    Ok(match tokio::fs::metadata(path).await {
+        Ok(m) if m.is_file() => {
+            let hash = hash_file(path).await?;
+            let backups_dir = backups_dir(dir);
+            let backup = backups_dir.join(hash.to_hex().as_str());
+            if !tokio::fs::try_exists(&backup).await? {
+                tokio::fs::create_dir_all(&backups_dir).await?;
+                tokio::fs::copy(path, &backup)
+                    .await
+                    .with_context(|| format!("Failed to back up {path:?} to {backup:?}"))?;
+            }
+            debug!(log, "Backed up {path:?} to {backup:?}");
+            Some(hash)
+        }
+        Ok(_) => bail!("Refusing to replace {path:?}, which is not a file"),
+        Err(e) if e.is_not_found() => None,
+        Err(e) => return Err(e.into()),
+    })
+}
+
+/// Returns `true` if the file at `path` is no longer the one that Manderrow last wrote there.
+/// Files that were removed, or written before their hashes were recorded, have not drifted.
+async fn drifted(path: &Path, entry: &ReceiptEntry) -> Result<bool> {
+    let Some(written) = entry.written else {
+        return Ok(false);
+    };
+    match tokio::fs::metadata(path).await {
+        Ok(m) if m.is_file() => Ok(hash_file(path).await? != written),
+        Ok(_) => Ok(true),
+        Err(e) if e.is_not_found() => Ok(false),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Records the hashes of the files at `rel_paths` in `install_dir`, which Manderrow has just
+/// written. They must have been passed to [`record_before_replacing`] first.
+pub async fn record_written(
+    game_id: &str,
+    install_dir: &Path,
+    rel_paths: impl IntoIterator<Item = PathBuf>,
+) -> Result<()> {
+    record_written_in(&receipts_dir(), game_id, install_dir, rel_paths).await
+}
+
+/// Like [`record_written`], with the receipts kept under `dir`.
+pub(super) async fn record_written_in(
+    dir: &Path,
+    game_id: &str,
+    install_dir: &Path,
+    rel_paths: impl IntoIterator<Item = PathBuf>,
+) -> Result<()> {
+    let mut receipt = Receipt::read_in(dir, game_id)
+        .await?
+        .filter(|receipt| receipt.install_dir == install_dir)
+        .context("Files were written without a receipt")?;
+    for rel_path in rel_paths {
+        let hash = hash_file(&install_dir.join(&rel_path)).await?;
+        receipt
+            .entries
+            .get_mut(&rel_path)
+            .with_context(|| format!("{rel_path:?} was written without being recorded"))?
+            .written = Some(hash);
+    }
+    receipt.write(dir, game_id).await
+}
+
+/// Returns the paths of the files that Manderrow wrote in `install_dir` which have since been
+/// changed by something else.
+pub async fn find_drifted(game_id: &str, install_dir: &Path) -> Result<Vec<PathBuf>> {
+    let Some(receipt) = Receipt::read(game_id)
+        .await?
+        .filter(|receipt| receipt.install_dir == install_dir)
+    else {
+        return Ok(Vec::new());
+    };
+    let mut paths = Vec::new();
+    for (rel_path, entry) in &receipt.entries {
+        if drifted(&install_dir.join(rel_path), entry).await? {
+            paths.push(rel_path.clone());
+        }
+    }
+    Ok(paths)
+}
+
+/// Puts the file at `path` back the way `entry` says it was, unless it was changed by something
+/// else after Manderrow wrote it, in which case restoring would undo that and it is left alone.
+///
+/// Returns `false` if it was left alone.
+async fn restore_entry(
+    log: &slog::Logger,
+    dir: &Path,
+    path: &Path,
+    entry: &ReceiptEntry,
+) -> Result<bool> {
+    if drifted(path, entry).await? {
+        warn!(
+            log,
+            "Not restoring {path:?}, which was changed by something other than Manderrow"
+        );
+        return Ok(false);
+    }
+    match entry.original {
+        Some(hash) => {
+            let backup = backups_dir(dir).join(hash.to_hex().as_str());
+            if hash_file(&backup).await? != hash {
+                bail!("Backup of {path:?} at {backup:?} is corrupt");
+            }
+            if let Some(parent) = path.parent() {
+                tokio::fs::create_dir_all(parent).await?;
+            }
+            tokio::fs::copy(&backup, path)
+                .await
+                .with_context(|| format!("Failed to restore {path:?} from {backup:?}"))?;
+            info!(log, "Restored {path:?}");
+        }
+        None => match tokio::fs::remove_file(path).await {
+            Ok(()) => info!(log, "Removed {path:?}"),
+            Err(e) if e.is_not_found() => {}
+            Err(e) => {
+                return Err(anyhow::Error::new(e).context(format!("Failed to remove {path:?}")))
+            }
+        },
+    }
+    Ok(true)
+}
+
+/// Returns `true` if Manderrow wrote the file at `rel_path` in `install_dir`.
+pub async fn is_recorded(game_id: &str, install_dir: &Path, rel_path: &Path) -> Result<bool> {
+    Ok(Receipt::read(game_id).await?.is_some_and(|receipt| {
+        receipt.install_dir == install_dir && receipt.entries.contains_key(rel_path)
+    }))
+}
+
+/// Puts the file at `rel_path` back the way it was before Manderrow wrote to it, and removes it
+/// from the game's receipt. Returns `false` if it was not in the receipt, or was changed by
+/// something else since and left alone.
+pub async fn restore_one(log: &slog::Logger, game_id: &str, rel_path: &Path) -> Result<bool> {
+    let dir = receipts_dir();
+    let Some(mut receipt) = Receipt::read_in(&dir, game_id).await? else {
+        return Ok(false);
+    };
+    let Some(entry) = receipt.entries.remove(rel_path) else {
+        return Ok(false);
+    };
+    let restored = restore_entry(log, &dir, &receipt.install_dir.join(rel_path), &entry).await?;
+    if receipt.entries.is_empty() {
+        tokio::fs::remove_file(Receipt::path(&dir, game_id)).await?;
+    } else {
+        receipt.write(&dir, game_id).await?;
+    }
+    Ok(restored)
+}
+
+/// The files in a game's receipt, by what [`restore`] did with them.
+#[derive(Debug, Default, Clone, serde::Serialize)]
+pub struct Restored {
+    /// The files that were restored or removed.
+    pub restored: Vec<PathBuf>,
+    /// The files that were left alone, as something else changed them after Manderrow wrote them.
+    pub drifted: Vec<PathBuf>,
+}
+
+/// Puts every file in the game's receipt back the way it was before Manderrow wrote to it, then
+/// discards the receipt. Files that were changed by something else since are left alone. Both are
+/// empty if there was no receipt.
+pub async fn restore(log: &slog::Logger, game_id: &str) -> Result<Restored> {
+    restore_in(log, &receipts_dir(), game_id).await
+}
+
+/// Like [`restore`], with the receipts kept under `dir`.
+pub(super) async fn restore_in(log: &slog::Logger, dir: &Path, game_id: &str) -> Result<Restored> {
+    let Some(mut receipt) = Receipt::read_in(dir, game_id).await? else {
+        return Ok(Restored::default());
+    };
+
+    let mut restored = Restored::default();
+    while let Some((rel_path, entry)) = receipt.entries.pop_first() {
+        let path = receipt.install_dir.join(&rel_path);
+        match restore_entry(log, dir, &path, &entry).await {
+            Ok(true) => restored.restored.push(path),
+            Ok(false) => restored.drifted.push(path),
+            Err(e) => {
+                // keep what is left, so that the restore can be retried
+                receipt.entries.insert(rel_path, entry);
+                receipt.write(dir, game_id).await?;
+                return Err(e);
+            }
+        }
+    }
+
+    tokio::fs::remove_file(Receipt::path(dir, game_id)).await?;
+    Ok(restored)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::{record_before_replacing_in, record_written_in, restore_in, Receipt};
+
+    #[test]
+    fn test_restore() {
+        let log = slog::Logger::root(slog::Discard, slog::o!());
+        let game_id = "ror2";
+        let receipts = tempfile::tempdir().unwrap();
+        let receipts = receipts.path();
+        let dir = tempfile::tempdir().unwrap();
+        let install_dir = dir.path();
+        std::fs::write(install_dir.join("winhttp.dll"), "original").unwrap();
+
+        tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()
+            .unwrap()
+            .block_on(async {
+                let rel_paths = || ["winhttp.dll", "doorstop_config.ini"].map(PathBuf::from);
+                record_before_replacing_in(&log, receipts, game_id, install_dir, rel_paths())
+                    .await
+                    .unwrap();
+                for rel_path in rel_paths() {
+                    std::fs::write(install_dir.join(rel_path), "manderrow").unwrap();
+                }
+                record_written_in(receipts, game_id, install_dir, rel_paths())
+                    .await
+                    .unwrap();
+
+                // like a game update
+                std::fs::write(install_dir.join("doorstop_config.ini"), "updated").unwrap();
+
+                let restored = restore_in(&log, receipts, game_id).await.unwrap();
+                assert_eq!(restored.restored, [install_dir.join("winhttp.dll")]);
+                assert_eq!(restored.drifted, [install_dir.join("doorstop_config.ini")]);
+                assert!(Receipt::read_in(receipts, game_id).await.unwrap().is_none());
+            });
+
+        assert_eq!(
+            std::fs::read_to_string(install_dir.join("winhttp.dll")).unwrap(),
+            "original"
+        );
+        assert_eq!(
+            std::fs::read_to_string(install_dir.join("doorstop_config.ini")).unwrap(),
+            "updated"
+        );
+    }
+}
@@ -0,0 +1,124 @@
+//! What Manderrow can do for a game on the current platform, so that the UI can explain why an
+//! action is unavailable instead of letting it fail.
+
+use manderrow_types::games::{Game, PackageLoader, StorePlatformMetadata};
+use slog::warn;
+
+use super::{bep_in_ex, melon_loader, select_store};
+
+/// Why an action is unavailable.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Reason {
+    /// The game's mod loader is not supported.
+    Loader,
+    /// None of the stores that the game is sold on are supported.
+    Store,
+    /// Supported, but not on this operating system, or not with how the game runs on it.
+    Platform,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(tag = "status", content = "reason", rename_all = "snake_case")]
+pub enum Support {
+    Supported,
+    Unsupported(Reason),
+}
+
+impl Support {
+    fn and(self, other: Self) -> Self {
+        match self {
+            Self::Supported => other,
+            Self::Unsupported(_) => self,
+        }
+    }
+}
+
+/// How the agent is loaded into the game.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InjectionMethod {
+    /// Installed in the game's directory as a proxy DLL that the game loads.
+    ProxyDll,
+    /// Preloaded by the wrapper, through `LD_PRELOAD` or `DYLD_INSERT_LIBRARIES`.
+    Preload,
+    /// Injected once the game has started, as its directory is read-only.
+    Remote,
+}
+
+#[derive(Debug, serde::Serialize)]
+pub struct GameSupport {
+    pub modded_launch: Support,
+    pub vanilla_launch: Support,
+    pub config_editing: Support,
+    /// `None` if the game can't be launched.
+    pub injection: Option<InjectionMethod>,
+}
+
+/// Works out what Manderrow can do for the game, through the store that it would be launched
+/// from. Profiles that launch a game executable directly skip the store, and are not covered.
+pub async fn get_game_support(log: &slog::Logger, game: &Game<'_>) -> GameSupport {
+    let (store, uses_proton, injection) = match select_store(log, game).await {
+        Some(StorePlatformMetadata::Steam {
+            store_identifier, ..
+        }) => {
+            let uses_proton =
+                match crate::stores::steam::proton::uses_proton(log, store_identifier).await {
+                    Ok(t) => t,
+                    Err(e) => {
+                        // most likely not installed, which is checked again when launching
+                        warn!(
+                            log,
+                            "Unable to check whether {} uses Proton: {e:#}", game.id
+                        );
+                        false
+                    }
+                };
+            let injection = if cfg!(windows) || uses_proton {
+                InjectionMethod::ProxyDll
+            } else {
+                InjectionMethod::Preload
+            };
+            (Support::Supported, uses_proton, Some(injection))
+        }
+        Some(StorePlatformMetadata::Xbox { store_identifier }) if cfg!(windows) => {
+            let protected = match crate::stores::gamepass::find_package(store_identifier) {
+                Ok(package) => package.is_some_and(|package| package.is_protected()),
+                Err(e) => {
+                    warn!(log, "Unable to look up Xbox app package: {e:#}");
+                    false
+                }
+            };
+            let injection = if protected {
+                InjectionMethod::Remote
+            } else {
+                InjectionMethod::ProxyDll
+            };
+            (Support::Supported, false, Some(injection))
+        }
+        Some(StorePlatformMetadata::Xbox { .. }) => {
+            (Support::Unsupported(Reason::Platform), false, None)
+        }
+        _ => (Support::Unsupported(Reason::Store), false, None),
+    };
+
+    let loader = match game.package_loader {
+        PackageLoader::BepInEx if bep_in_ex::is_supported(uses_proton) => Support::Supported,
+        PackageLoader::MelonLoader if melon_loader::is_supported(uses_proton) => Support::Supported,
+        PackageLoader::BepInEx | PackageLoader::MelonLoader => {
+            Support::Unsupported(Reason::Platform)
+        }
+        _ => Support::Unsupported(Reason::Loader),
+    };
+
+    GameSupport {
+        modded_launch: store.and(loader),
+        vanilla_launch: store,
+        // only BepInEx keeps its configs in the profile's config folder
+        config_editing: match game.package_loader {
+            PackageLoader::BepInEx => Support::Supported,
+            _ => Support::Unsupported(Reason::Loader),
+        },
+        injection,
+    }
+}
@@ -0,0 +1,97 @@
+//! Commands that games are run through, like `gamemoderun`, `mangohud`, or `gamescope`, written
+//! the same way as they would be before `%command%` in Steam's launch options.
+//!
+//! They are run by Manderrow's wrapper, around the game, so that the launch options that Steam is
+//! given don't have to change between profiles. Launch options that already run the game through
+//! wrappers are kept when Manderrow's wrapper is added to them.
+
+use std::ffi::{OsStr, OsString};
+
+use anyhow::{Context as _, Result};
+use parking_lot::Mutex;
+use slog::warn;
+
+use crate::profiles::LaunchOptions;
+
+/// The wrapper command line used by profiles that don't set their own.
+static DEFAULT: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+/// Splits `setting` into arguments the way a shell would, so that arguments can be quoted.
+fn parse(setting: &str) -> Result<Vec<String>> {
+    shlex::split(setting).with_context(|| format!("Invalid wrapper command line {setting:?}"))
+}
+
+pub fn set_default(setting: &str) {
+    *DEFAULT.lock() = match parse(setting) {
+        Ok(wrapper) => wrapper,
+        Err(e) => {
+            warn!(slog_scope::logger(), "Ignoring the default wrapper: {e}");
+            Vec::new()
+        }
+    };
+}
+
+/// Returns the wrapper command line to launch with, which is empty if the game is run directly.
+pub fn for_launch(options: &LaunchOptions) -> Result<Vec<String>> {
+    match &options.wrapper {
+        Some(wrapper) => parse(wrapper),
+        None => Ok(DEFAULT.lock().clone()),
+    }
+}
+
+/// Splits the leading `NAME=value` arguments off of `wrapper`. Like in a shell, they set
+/// environment variables for the rest of the command line.
+pub fn split_assignments(wrapper: &[OsString]) -> (Vec<(&str, &str)>, &[OsString]) {
+    let n = wrapper
+        .iter()
+        .take_while(|arg| assignment(arg).is_some())
+        .count();
+    let (assignments, rest) = wrapper.split_at(n);
+    (
+        assignments
+            .iter()
+            .filter_map(|arg| assignment(arg))
+            .collect(),
+        rest,
+    )
+}
+
+fn assignment(arg: &OsStr) -> Option<(&str, &str)> {
+    let (name, value) = arg.to_str()?.split_once('=')?;
+    let valid = name.starts_with(|c: char| c == '_' || c.is_ascii_alphabetic())
+        && name.chars().all(|c| c == '_' || c.is_ascii_alphanumeric());
+    valid.then_some((name, value))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::OsString;
+
+    use super::{parse, split_assignments};
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            parse(r#"gamescope -W 1920 -- "/opt/my tools/run" 'a b'"#).unwrap(),
+            ["gamescope", "-W", "1920", "--", "/opt/my tools/run", "a b"],
+        );
+        assert!(parse(r#"gamemoderun "unterminated"#).is_err());
+    }
+
+    #[test]
+    fn test_split_assignments() {
+        let wrapper =
+            ["DXVK_HUD=1", "MANGOHUD_CONFIG=fps,gpu=1", "mangohud", "A=1"].map(OsString::from);
+        let (assignments, rest) = split_assignments(&wrapper);
+        assert_eq!(
+            assignments,
+            [("DXVK_HUD", "1"), ("MANGOHUD_CONFIG", "fps,gpu=1")]
+        );
+        assert_eq!(rest, [OsString::from("mangohud"), OsString::from("A=1")]);
+
+        let wrapper = ["1A=1", "./run=x"].map(OsString::from);
+        let (assignments, rest) = split_assignments(&wrapper);
+        assert!(assignments.is_empty());
+        assert_eq!(rest.len(), 2);
+    }
+}
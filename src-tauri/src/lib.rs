@@ -16,8 +16,13 @@
 #![feature(vec_push_within_capacity)]
 
 mod app_commands;
+mod auth;
+mod background;
+mod backup;
 mod bench_commands;
+mod compat;
 mod error;
+mod exporting;
 mod games;
 mod i18n;
 mod importing;
@@ -26,7 +31,9 @@ mod ipc;
 mod launching;
 mod mod_index;
 mod profiles;
+mod secrets;
 mod settings;
+mod startup;
 mod stores;
 mod tasks;
 mod util;
@@ -35,7 +42,6 @@ mod wrap;
 mod wrap_with_injection;
 
 use std::num::NonZeroU32;
-use std::ops::Deref;
 
 use anyhow::{anyhow, bail, Context};
 use ipc::IpcState;
@@ -44,26 +50,20 @@ pub use error::{CommandError, Error};
 use lexopt::ValueExt;
 use tauri::Manager;
 
-#[derive(Clone)]
-struct Reqwest(reqwest::Client);
-
-impl Deref for Reqwest {
-    type Target = reqwest::Client;
-
-    fn deref(&self) -> &Self::Target {
-        &self.0
-    }
-}
-
 fn run_app(ctx: tauri::Context<tauri::Wry>) -> anyhow::Result<()> {
     tauri::Builder::default()
-        .plugin(tauri_plugin_single_instance::init(|app, _, _| {
+        .plugin(tauri_plugin_single_instance::init(|app, args, _| {
+            if let Some(id) = stores::steam::shortcuts::find_requested_profile(&args) {
+                stores::steam::shortcuts::request_profile(app, id);
+            }
+
             let window = app.get_webview_window("main").expect("no main window");
 
             window.unminimize().ok();
             window.set_focus().ok();
         }))
         .setup(|app| {
+            startup::mark("window_created");
             let window = app.get_webview_window("main").context("no main window")?;
 
             #[cfg(target_os = "macos")]
@@ -84,12 +84,29 @@ fn run_app(ctx: tauri::Context<tauri::Wry>) -> anyhow::Result<()> {
                 }
             }
 
-            assert!(app.manage(IpcState::new(app.handle().clone(), slog_scope::logger())));
+            assert!(app.manage(startup::time("ipc", || IpcState::new(
+                app.handle().clone(),
+                slog_scope::logger()
+            ))));
+
+            games::stats::spawn_refresher();
+            installing::pruning::spawn_pruner();
+            stores::steam::watchdog::spawn();
 
             Ok(())
         })
-        .manage(settings::try_read())
-        .manage(Reqwest(reqwest::Client::builder().build()?))
+        .on_page_load(|webview, payload| {
+            // prompts that were shown before a reload would otherwise be lost
+            if payload.event() == tauri::webview::PageLoadEvent::Finished {
+                startup::finish();
+                if let Some(ipc_state) = webview.try_state::<IpcState>() {
+                    ipc_state
+                        .pending_prompts()
+                        .emit_all(&slog_scope::logger(), webview.app_handle());
+                }
+            }
+        })
+        .manage(startup::time("settings", settings::try_read))
         .plugin(tauri_plugin_clipboard_manager::init())
         .plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_os::init())
@@ -98,47 +115,120 @@ fn run_app(ctx: tauri::Context<tauri::Wry>) -> anyhow::Result<()> {
             app_commands::close,
             app_commands::is_maximized,
             app_commands::minimize,
+            app_commands::open_in_file_manager,
             app_commands::relaunch,
             app_commands::set_maximized,
             app_commands::start_dragging,
+            auth::commands::thunderstore_get_account,
+            auth::commands::thunderstore_login,
+            auth::commands::thunderstore_login_with_token,
+            auth::commands::thunderstore_logout,
+            backup::commands::backup_all_data,
+            backup::commands::restore_all_data,
             bench_commands::bench_exit_interactive,
             bench_commands::bench_exit_splash,
+            bench_commands::get_startup_timings,
+            compat::commands::add_user_compat_note,
+            compat::commands::get_compat_notes,
+            compat::commands::get_profile_compat_report,
+            compat::commands::get_user_compat_notes,
+            compat::commands::remove_user_compat_note,
             games::commands::get_games,
             games::commands::search_games,
             games::commands::get_games_popularity,
             games::commands::get_game_mods_downloads,
+            games::commands::get_game_stats_trends,
             i18n::get_preferred_locales,
             importing::commands::preview_import_modpack_from_thunderstore_code,
             importing::commands::import_modpack_from_thunderstore_code,
+            importing::commands::import_game_dir,
+            importing::commands::import_local_r2modman_profile,
+            importing::commands::scan_game_dir,
+            importing::commands::scan_local_r2modman_profiles,
             installing::commands::clear_cache,
             ipc::commands::allocate_ipc_connection,
+            ipc::commands::export_connection_log,
+            ipc::commands::get_connection_history,
+            ipc::commands::get_connection_metrics,
             ipc::commands::get_ipc_connections,
+            ipc::commands::get_pending_doctor_prompts,
             ipc::commands::kill_ipc_client,
+            ipc::commands::request_exit,
+            ipc::commands::respond_to_prompt,
             ipc::commands::send_s2c_message,
+            ipc::commands::send_stdin,
+            ipc::commands::set_resource_monitoring,
             launching::commands::launch_profile,
+            launching::commands::launch_vanilla,
+            launching::commands::restore_vanilla,
+            launching::commands::remove_steam_launch_options,
+            launching::commands::get_game_support,
+            launching::commands::test_agent_ipc,
+            launching::commands::take_wrapper_failures,
             mod_index::commands::fetch_mod_index,
             mod_index::commands::count_mod_index,
             mod_index::commands::query_mod_index,
             mod_index::commands::get_from_mod_index,
+            mod_index::commands::get_mod_sizes,
+            mod_index::commands::get_mod_version_list,
+            mod_index::commands::get_mod_index_categories,
+            mod_index::commands::get_mod_index_changes,
+            mod_index::commands::get_mod_index_status,
+            mod_index::commands::get_index_diagnostics,
+            mod_index::commands::get_cached_mod_icon,
             mod_index::thunderstore::commands::thunderstore_fetch_mod_markdown,
+            mod_index::thunderstore::commands::thunderstore_fetch_mod_changelogs,
+            mod_index::thunderstore::commands::thunderstore_get_rated_mods,
+            mod_index::thunderstore::commands::thunderstore_rate_mod,
+            mod_index::thunderstore::commands::thunderstore_report_mod,
             profiles::commands::get_profiles,
+            profiles::commands::batch_profile_ops,
+            profiles::commands::get_loader_versions,
             profiles::commands::create_profile,
+            profiles::commands::create_profile_steam_shortcut,
+            profiles::commands::take_requested_profile,
             profiles::commands::overwrite_profile_metadata,
             profiles::commands::delete_profile,
+            profiles::commands::diff_profile_config_snapshot,
+            profiles::commands::export_mod_list,
+            profiles::commands::export_profile,
+            profiles::commands::export_profile_to_file,
+            profiles::commands::get_profile_file_overlaps,
             profiles::commands::get_profile_mods,
+            profiles::commands::get_profile_mod_docs,
+            profiles::commands::get_profile_mod_path,
+            profiles::commands::get_profile_paths,
+            profiles::commands::get_profile_plugins,
+            profiles::commands::get_profile_config_overrides,
+            profiles::commands::get_profile_config_snapshots,
+            profiles::commands::import_profile_from_file,
+            profiles::commands::is_read_only,
+            profiles::commands::set_profile_config_override,
             profiles::commands::install_profile_mod,
+            profiles::commands::reinstall_profile_mod,
+            profiles::commands::restore_profile_config_snapshot,
+            profiles::commands::run_profile_tool,
+            profiles::commands::search_profile_configs,
+            profiles::commands::set_profile_loader_version,
+            profiles::commands::link_dev_mod,
+            profiles::commands::repair_profile_mod,
             profiles::commands::uninstall_profile_mod,
+            profiles::commands::update_profile_config,
             settings::commands::get_settings,
             settings::commands::get_settings_ui,
+            settings::commands::parse_launch_options_templates,
             settings::commands::update_settings,
             tasks::commands::allocate_task,
             tasks::commands::cancel_task,
+            tasks::commands::get_task_history,
         ])
         .run(ctx)
         .context("error while running tauri application")
 }
 
 pub fn main() -> anyhow::Result<()> {
+    startup::begin();
+
     if cfg!(target_os = "linux") {
         // Only provide a default value, don't override the user's choice.
         if std::env::var_os("WEBKIT_DISABLE_DMABUF_RENDERER").is_none() {
@@ -150,9 +240,9 @@ pub fn main() -> anyhow::Result<()> {
         }
     }
 
-    let ctx = tauri::generate_context!();
+    let ctx = startup::time("context", || tauri::generate_context!());
 
-    manderrow_paths::init().unwrap();
+    startup::time("paths", manderrow_paths::init).unwrap();
 
     let mut args = lexopt::Parser::from_env();
 
@@ -164,8 +254,19 @@ pub fn main() -> anyhow::Result<()> {
             Value(cmd) if cmd == "wrap-with-injection" => {
                 return wrap::run(args, wrap::WrapperMode::Injection)
             }
+            Value(cmd) if cmd == launching::agent_test::TARGET_COMMAND => {
+                return launching::agent_test::run_target()
+            }
+            #[cfg(windows)]
+            Value(cmd) if cmd == stores::gamepass::ACTIVATE_COMMAND => {
+                return stores::gamepass::activate::run(args)
+            }
             Value(cmd) => bail!("Unrecognized command {cmd:?}"),
             Long("relaunch") => relaunch = Some(args.value()?.parse()?),
+            Long("read-only") => profiles::read_only::set_read_only(true),
+            Long(stores::steam::shortcuts::PROFILE_ARG) => {
+                stores::steam::shortcuts::set_requested_profile(args.value()?.parse()?)
+            }
             arg => return Err(arg.unexpected().into()),
         }
     }
@@ -182,5 +283,9 @@ pub fn main() -> anyhow::Result<()> {
         })?;
     }
 
+    // neither is needed to show the window, so they are loaded while it is being created
+    startup::defer("games", || _ = games::games());
+    startup::defer("game_stats", games::stats::preload);
+
     run_app(ctx)
 }
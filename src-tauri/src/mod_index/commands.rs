@@ -1,25 +1,77 @@
 use std::num::NonZeroUsize;
 
+use anyhow::Context as _;
 use manderrow_types::mods::ModId;
-use tauri::{AppHandle, State};
+use packed_semver::Version;
+use tauri::AppHandle;
 
-use crate::{tasks, CommandError, Reqwest};
+use crate::games::games_by_id;
+use crate::{tasks, CommandError};
 
-use super::{read_mod_index, SortColumn, SortOption};
+use super::diff::Changeset;
+use super::sizes::{mod_sizes, ModSizes};
+use super::versions::{version_list, VersionListing};
+use super::{
+    get_one_from_mod_index, read_mod_index, IndexDiagnostics, ModFilter, ModIndexStatus,
+    SortColumn, SortOption,
+};
 
 #[tauri::command]
 pub async fn fetch_mod_index(
     app_handle: AppHandle,
-    reqwest: State<'_, Reqwest>,
     game: &str,
     refresh: bool,
     task_id: tasks::Id,
 ) -> Result<(), CommandError> {
-    super::fetch_mod_index(Some(&app_handle), &reqwest, game, refresh, Some(task_id)).await?;
+    super::fetch_mod_index(Some(&app_handle), game, refresh, Some(task_id)).await?;
 
     Ok(())
 }
 
+/// Returns how fresh the game's mod index is, so that stale data can be pointed out.
+#[tauri::command]
+pub fn get_mod_index_status(game: &str) -> Result<ModIndexStatus, CommandError> {
+    super::get_mod_index_status(game).map_err(Into::into)
+}
+
+/// Returns how the last fetch of the game's mod index went, e.g. how well its strings were
+/// interned and how long each chunk took to encode.
+#[tauri::command]
+pub fn get_index_diagnostics(game: &str) -> Result<IndexDiagnostics, CommandError> {
+    super::get_index_diagnostics(game).map_err(Into::into)
+}
+
+/// Returns the categories that the game's mods are in, sorted by name, for filtering by.
+#[tauri::command]
+pub async fn get_mod_index_categories(game: &str) -> Result<Vec<String>, CommandError> {
+    let mod_index = read_mod_index(game).await?;
+    Ok(super::get_categories(&mod_index))
+}
+
+/// Returns what changed in the game's mod index with its last fetch, if it was compared against
+/// an earlier one. The same changes are sent as [`super::diff::EVENT`] events as they are found.
+#[tauri::command]
+pub fn get_mod_index_changes(game: &str) -> Result<Option<Changeset>, CommandError> {
+    let game = *games_by_id()?.get(game).context("No such game")?;
+    Ok(super::diff::get_changes(game.id))
+}
+
+/// Returns the mod's icon if it has been downloaded, or nothing otherwise.
+#[tauri::command]
+pub async fn get_cached_mod_icon(
+    owner: &str,
+    name: &str,
+    version: Version,
+) -> Result<tauri::ipc::Response, CommandError> {
+    let bytes = match super::prefetch::cached_icon(owner, name, version).await {
+        Some(path) => tokio::fs::read(&path)
+            .await
+            .with_context(|| format!("Failed to read {path:?}"))?,
+        None => Vec::new(),
+    };
+    Ok(tauri::ipc::Response::new(bytes))
+}
+
 fn map_to_json<T: serde::Serialize>(buf: &mut Vec<u8>, it: impl Iterator<Item = T>) {
     let mut it = it.peekable();
     while let Some(m) = it.next() {
@@ -31,23 +83,32 @@ fn map_to_json<T: serde::Serialize>(buf: &mut Vec<u8>, it: impl Iterator<Item =
 }
 
 #[tauri::command]
-pub async fn count_mod_index(game: &str, query: &str) -> Result<usize, CommandError> {
+pub async fn count_mod_index(
+    game: &str,
+    query: &str,
+    filter: Option<ModFilter>,
+) -> Result<usize, CommandError> {
     let mod_index = read_mod_index(game).await?;
 
-    Ok(super::count_mod_index(&mod_index, query)?)
+    Ok(super::count_mod_index(
+        &mod_index,
+        query,
+        &filter.unwrap_or_default(),
+    )?)
 }
 
 #[tauri::command]
 pub async fn query_mod_index(
     game: &str,
     query: &str,
+    filter: Option<ModFilter>,
     sort: Vec<SortOption<SortColumn>>,
     skip: Option<usize>,
     limit: Option<NonZeroUsize>,
 ) -> Result<tauri::ipc::Response, CommandError> {
     let mod_index = read_mod_index(game).await?;
 
-    let buf = super::query_mod_index(&mod_index, query, &sort)?;
+    let buf = super::query_mod_index(&mod_index, query, &filter.unwrap_or_default(), &sort)?;
 
     let count = buf.len();
 
@@ -85,3 +146,29 @@ pub async fn get_from_mod_index(
         String::from_utf8_unchecked(out_buf)
     }))
 }
+
+#[tauri::command]
+pub async fn get_mod_sizes(
+    game: &str,
+    mod_id: ModId<'_>,
+) -> Result<Option<ModSizes>, CommandError> {
+    let mod_index = read_mod_index(game).await?;
+
+    Ok(get_one_from_mod_index(&mod_index, mod_id)
+        .await?
+        .map(mod_sizes))
+}
+
+/// Returns the versions of the mod with their release dates and sizes, latest first, or nothing if
+/// the mod is not in the index.
+#[tauri::command]
+pub async fn get_mod_version_list(
+    game: &str,
+    mod_id: ModId<'_>,
+) -> Result<Option<Vec<VersionListing>>, CommandError> {
+    let mod_index = read_mod_index(game).await?;
+
+    Ok(get_one_from_mod_index(&mod_index, mod_id)
+        .await?
+        .map(version_list))
+}
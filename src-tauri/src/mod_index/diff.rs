@@ -0,0 +1,201 @@
+//! What changed in a game's mod index between two fetches, so that the frontend can point out new
+//! and updated mods without querying the whole index again.
+
+use std::collections::HashMap;
+use std::sync::LazyLock;
+
+use packed_semver::Version;
+use parking_lot::Mutex;
+use slog::error;
+use smol_str::SmolStr;
+use tauri::{AppHandle, Emitter};
+
+use super::memory::MemoryModIndex;
+
+/// The name of the event sent to the frontend with the [`Changeset`] of each fetch.
+pub const EVENT: &str = "mod_index_changes";
+
+/// The changes found by the last fetch of each game's mod index, by game id.
+static CHANGES: LazyLock<Mutex<HashMap<&'static str, Changeset>>> = LazyLock::new(Default::default);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Entry {
+    latest: Option<Version>,
+    is_deprecated: bool,
+}
+
+/// What a mod index contained, as far as [`diff`] is concerned.
+#[derive(Default)]
+pub struct Snapshot(HashMap<(SmolStr, SmolStr), Entry>);
+
+impl Snapshot {
+    pub fn new(index: &MemoryModIndex) -> Self {
+        Self(
+            index
+                .chunks
+                .iter()
+                .flat_map(|chunk| chunk.mods().iter())
+                .map(|m| {
+                    (
+                        (SmolStr::from(&*m.owner), SmolStr::from(&*m.name)),
+                        Entry {
+                            latest: m.versions.first().map(|v| v.version_number.get()),
+                            is_deprecated: m.is_deprecated,
+                        },
+                    )
+                })
+                .collect(),
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, serde::Serialize)]
+pub struct ChangedMod {
+    pub owner: SmolStr,
+    pub name: SmolStr,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct UpdatedMod {
+    pub owner: SmolStr,
+    pub name: SmolStr,
+    pub from: Version,
+    pub to: Version,
+}
+
+/// The differences between two fetches of a game's mod index. Each list is sorted by owner and
+/// name.
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize)]
+pub struct Changeset {
+    pub game: &'static str,
+    /// Milliseconds since the Unix epoch at which the newer index was fetched.
+    pub fetched_at: u64,
+    pub new_mods: Vec<ChangedMod>,
+    /// Mods whose latest version changed.
+    pub updated_mods: Vec<UpdatedMod>,
+    /// Mods that were deprecated since the older index.
+    pub deprecated_mods: Vec<ChangedMod>,
+}
+
+impl Changeset {
+    pub fn is_empty(&self) -> bool {
+        self.new_mods.is_empty() && self.updated_mods.is_empty() && self.deprecated_mods.is_empty()
+    }
+}
+
+/// Compares the `old` and `new` snapshots of the same game's mod index.
+pub fn diff(old: &Snapshot, new: &Snapshot) -> Changeset {
+    let mut changes = Changeset::default();
+    for ((owner, name), entry) in &new.0 {
+        let changed = || ChangedMod {
+            owner: owner.clone(),
+            name: name.clone(),
+        };
+        let Some(old_entry) = old.0.get(&(owner.clone(), name.clone())) else {
+            changes.new_mods.push(changed());
+            continue;
+        };
+        if let (Some(from), Some(to)) = (old_entry.latest, entry.latest) {
+            if from != to {
+                changes.updated_mods.push(UpdatedMod {
+                    owner: owner.clone(),
+                    name: name.clone(),
+                    from,
+                    to,
+                });
+            }
+        }
+        if entry.is_deprecated && !old_entry.is_deprecated {
+            changes.deprecated_mods.push(changed());
+        }
+    }
+    changes.new_mods.sort_unstable();
+    changes
+        .updated_mods
+        .sort_unstable_by(|a, b| (&a.owner, &a.name).cmp(&(&b.owner, &b.name)));
+    changes.deprecated_mods.sort_unstable();
+    changes
+}
+
+/// Remembers the changes found by a fetch of the game's mod index and sends them to the frontend.
+pub fn publish(app: Option<&AppHandle>, game: &'static str, fetched_at: u64, changes: Changeset) {
+    let changes = Changeset {
+        game,
+        fetched_at,
+        ..changes
+    };
+    if let Some(app) = app {
+        if let Err(e) = app.emit(EVENT, &changes) {
+            error!(slog_scope::logger(), "Failed to emit {EVENT} event: {e}");
+        }
+    }
+    CHANGES.lock().insert(game, changes);
+}
+
+/// Returns the changes found by the last fetch of the game's mod index, if it was compared
+/// against an earlier one.
+pub fn get_changes(game: &str) -> Option<Changeset> {
+    CHANGES.lock().get(game).cloned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot(mods: &[(&str, &str, &str, bool)]) -> Snapshot {
+        Snapshot(
+            mods.iter()
+                .map(|&(owner, name, version, is_deprecated)| {
+                    (
+                        (owner.into(), name.into()),
+                        Entry {
+                            latest: Some(Version::from_str(version).unwrap()),
+                            is_deprecated,
+                        },
+                    )
+                })
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn test_diff() {
+        let old = snapshot(&[
+            ("A", "Kept", "1.0.0", false),
+            ("A", "Updated", "1.0.0", false),
+            ("B", "Deprecated", "2.0.0", false),
+            ("B", "Removed", "1.0.0", false),
+        ]);
+        let new = snapshot(&[
+            ("A", "Kept", "1.0.0", false),
+            ("A", "Updated", "1.1.0", false),
+            ("B", "Deprecated", "2.0.0", true),
+            ("C", "New", "0.1.0", false),
+        ]);
+        let changes = diff(&old, &new);
+        assert_eq!(
+            changes.new_mods,
+            [ChangedMod {
+                owner: "C".into(),
+                name: "New".into()
+            }]
+        );
+        assert_eq!(
+            changes.updated_mods,
+            [UpdatedMod {
+                owner: "A".into(),
+                name: "Updated".into(),
+                from: Version::new(1, 0, 0).unwrap(),
+                to: Version::new(1, 1, 0).unwrap(),
+            }]
+        );
+        assert_eq!(
+            changes.deprecated_mods,
+            [ChangedMod {
+                owner: "B".into(),
+                name: "Deprecated".into()
+            }]
+        );
+        assert!(diff(&new, &new).is_empty());
+    }
+}
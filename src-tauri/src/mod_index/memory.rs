@@ -1,22 +1,42 @@
+use std::path::Path;
 use std::ptr::NonNull;
 
+use anyhow::{Context as _, Result};
 use rkyv::util::AlignedVec;
 use rkyv::vec::ArchivedVec;
+use url::Url;
 
 use manderrow_types::mods::ArchivedModRef;
 
+use crate::util::IoErrorKindExt as _;
+
 #[derive(Default)]
 pub struct MemoryModIndex {
     pub chunks: Vec<MemoryModIndexChunk>,
+    /// The `ETag` of the list of chunk URLs, to revalidate it against.
+    pub etag: Option<String>,
+}
+
+/// Where a chunk was fetched from.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct ChunkSource {
+    pub url: Url,
+    /// The `ETag` of the chunk, to revalidate it against.
+    pub etag: Option<String>,
 }
 
 pub struct MemoryModIndexChunk {
     data: NonNull<[u8]>,
     mods: &'static ArchivedVec<ArchivedModRef<'static>>,
+    pub source: ChunkSource,
 }
 
 impl MemoryModIndexChunk {
-    pub fn new<F, E>(mut data: AlignedVec<16>, mods_constructor: F) -> Result<Self, E>
+    pub fn new<F, E>(
+        mut data: AlignedVec<16>,
+        source: ChunkSource,
+        mods_constructor: F,
+    ) -> Result<Self, E>
     where
         F: for<'a> FnOnce(&'a [u8]) -> Result<&'a ArchivedVec<ArchivedModRef<'a>>, E>,
     {
@@ -26,6 +46,7 @@ impl MemoryModIndexChunk {
         Ok(Self {
             data: data_ptr,
             mods: mods_constructor(unsafe { data_ptr.as_ref() })?,
+            source,
         })
     }
 }
@@ -35,6 +56,10 @@ impl MemoryModIndexChunk {
         // SAFETY: i have a hunch the lifetime issue is a non-issue
         unsafe { NonNull::from(self.mods).cast().as_ref() }
     }
+
+    fn data(&self) -> &[u8] {
+        unsafe { self.data.as_ref() }
+    }
 }
 
 unsafe impl Send for MemoryModIndexChunk {}
@@ -50,3 +75,175 @@ impl Drop for MemoryModIndexChunk {
         }
     }
 }
+
+/// The name of the file in a stored index's folder that describes its chunks. It is written
+/// last, so an index without it is incomplete.
+const METADATA_FILE_NAME: &str = "index.json";
+
+#[derive(serde::Serialize, serde::Deserialize)]
+struct StoredMetadata {
+    /// The layout of the chunks may change between versions of Manderrow, so indexes stored by
+    /// other versions are ignored.
+    app_version: String,
+    /// Milliseconds since the Unix epoch at which the index was fetched.
+    fetched_at: u64,
+    etag: Option<String>,
+    chunks: Vec<ChunkSource>,
+}
+
+fn chunk_file_name(i: usize) -> String {
+    format!("{i}.rkyv")
+}
+
+/// Writes the index to the folder at `path`, replacing whatever was stored there.
+pub fn store(path: &Path, index: &MemoryModIndex, fetched_at: u64) -> Result<()> {
+    let temp_path = path.with_extension("tmp");
+    match std::fs::remove_dir_all(&temp_path) {
+        Ok(()) => {}
+        Err(e) if e.is_not_found() => {}
+        Err(e) => return Err(e).with_context(|| format!("Failed to remove {temp_path:?}")),
+    }
+    std::fs::create_dir_all(&temp_path)
+        .with_context(|| format!("Failed to create {temp_path:?}"))?;
+
+    for (i, chunk) in index.chunks.iter().enumerate() {
+        std::fs::write(temp_path.join(chunk_file_name(i)), chunk.data())
+            .context("Failed to write mod index chunk")?;
+    }
+    let metadata = StoredMetadata {
+        app_version: env!("CARGO_PKG_VERSION").to_owned(),
+        fetched_at,
+        etag: index.etag.clone(),
+        chunks: index
+            .chunks
+            .iter()
+            .map(|chunk| chunk.source.clone())
+            .collect(),
+    };
+    std::fs::write(
+        temp_path.join(METADATA_FILE_NAME),
+        serde_json::to_vec(&metadata)?,
+    )
+    .context("Failed to write mod index metadata")?;
+
+    match std::fs::remove_dir_all(path) {
+        Ok(()) => {}
+        Err(e) if e.is_not_found() => {}
+        Err(e) => return Err(e).with_context(|| format!("Failed to remove {path:?}")),
+    }
+    std::fs::rename(&temp_path, path)
+        .with_context(|| format!("Failed to move {temp_path:?} to {path:?}"))?;
+    Ok(())
+}
+
+/// Reads the index stored in the folder at `path` by [`store`], along with when it was fetched.
+/// Returns `None` if there is none, or if it was stored by another version of Manderrow.
+pub fn load(path: &Path) -> Result<Option<(MemoryModIndex, u64)>> {
+    let metadata_path = path.join(METADATA_FILE_NAME);
+    let metadata = match std::fs::read(&metadata_path) {
+        Ok(t) => serde_json::from_slice::<StoredMetadata>(&t)
+            .with_context(|| format!("Failed to parse {metadata_path:?}"))?,
+        Err(e) if e.is_not_found() => return Ok(None),
+        Err(e) => return Err(e).with_context(|| format!("Failed to read {metadata_path:?}")),
+    };
+    if metadata.app_version != env!("CARGO_PKG_VERSION") {
+        return Ok(None);
+    }
+
+    let mut chunks = Vec::with_capacity(metadata.chunks.len());
+    for (i, source) in metadata.chunks.into_iter().enumerate() {
+        let chunk_path = path.join(chunk_file_name(i));
+        let bytes =
+            std::fs::read(&chunk_path).with_context(|| format!("Failed to read {chunk_path:?}"))?;
+        let mut data = AlignedVec::<16>::with_capacity(bytes.len());
+        data.extend_from_slice(&bytes);
+        drop(bytes);
+        // the file may have been corrupted, so it is always validated
+        chunks.push(
+            MemoryModIndexChunk::new(data, source, |data| {
+                rkyv::access::<_, rkyv::rancor::Error>(data)
+            })
+            .with_context(|| format!("Invalid mod index chunk at {chunk_path:?}"))?,
+        );
+    }
+    Ok(Some((
+        MemoryModIndex {
+            chunks,
+            etag: metadata.etag,
+        },
+        metadata.fetched_at,
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use manderrow_types::mods::{ModId, ModRef};
+    use rkyv::util::AlignedVec;
+    use rkyv_intern::{Interner, InterningAdapter};
+    use url::Url;
+
+    use super::{load, store, ChunkSource, MemoryModIndex, MemoryModIndexChunk};
+
+    fn chunk(url: &str, etag: Option<&str>) -> MemoryModIndexChunk {
+        let data = rkyv::util::with_arena(|arena| {
+            let mut serializer = InterningAdapter::new(
+                InterningAdapter::new(
+                    rkyv::ser::Serializer::new(
+                        AlignedVec::<16>::new(),
+                        arena.acquire(),
+                        rkyv::ser::sharing::Share::new(),
+                    ),
+                    Interner::<ModId<'_>>::default(),
+                ),
+                Interner::<String>::default(),
+            );
+            rkyv::api::serialize_using::<_, rkyv::rancor::Error>(
+                &Vec::<ModRef>::new(),
+                &mut serializer,
+            )?;
+            let (serializer, _interner) = serializer.into_components();
+            Ok::<_, rkyv::rancor::Error>(serializer.into_serializer().into_writer())
+        })
+        .unwrap();
+        let source = ChunkSource {
+            url: Url::parse(url).unwrap(),
+            etag: etag.map(str::to_owned),
+        };
+        MemoryModIndexChunk::new(data, source, |data| {
+            rkyv::access::<_, rkyv::rancor::Error>(data)
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn test_store_and_load() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("index");
+        assert!(load(&path).unwrap().is_none());
+
+        let index = MemoryModIndex {
+            chunks: vec![
+                chunk("https://thunderstore.io/chunks/0.json.gz", Some("\"0\"")),
+                chunk("https://thunderstore.io/chunks/1.json.gz", None),
+            ],
+            etag: Some("\"urls\"".to_owned()),
+        };
+        store(&path, &index, 1234).unwrap();
+        let (loaded, fetched_at) = load(&path).unwrap().unwrap();
+        assert_eq!(fetched_at, 1234);
+        assert_eq!(loaded.etag, index.etag);
+        assert_eq!(loaded.chunks.len(), index.chunks.len());
+        for (loaded, stored) in loaded.chunks.iter().zip(&index.chunks) {
+            assert_eq!(loaded.source.url, stored.source.url);
+            assert_eq!(loaded.source.etag, stored.source.etag);
+            assert_eq!(loaded.data(), stored.data());
+        }
+
+        // storing again replaces the previous index
+        store(&path, &MemoryModIndex::default(), 5678).unwrap();
+        let (loaded, fetched_at) = load(&path).unwrap().unwrap();
+        assert_eq!(fetched_at, 5678);
+        assert_eq!(loaded.etag, None);
+        assert!(loaded.chunks.is_empty());
+    }
+}
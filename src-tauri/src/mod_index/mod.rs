@@ -1,17 +1,25 @@
 pub mod commands;
-mod memory;
+pub mod diff;
+pub mod memory;
+pub mod prefetch;
+pub mod sizes;
 pub mod thunderstore;
+pub mod versions;
 
 use std::collections::HashMap;
+use std::path::PathBuf;
 use std::sync::LazyLock;
-use std::time::Instant;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 use anyhow::{Context as _, Result};
 use async_compression::tokio::bufread::GzipDecoder;
-use manderrow_types::mods::{ArchivedModRef, ModId, ModRef};
+use manderrow_paths::cache_dir;
+use manderrow_types::mods::{ArchivedModRef, ModId, ModRef, Timestamp};
 use manderrow_types::util::rkyv::InternedString;
+use reqwest::header::{ETAG, IF_NONE_MATCH};
+use reqwest::StatusCode;
 use rkyv_intern::Interner;
-use slog::{debug, info, trace};
+use slog::{debug, info, trace, warn};
 use tauri::AppHandle;
 use tokio::io::AsyncReadExt;
 use tokio::select;
@@ -19,19 +27,112 @@ use tokio::sync::{Mutex, RwLock, RwLockReadGuard};
 use url::Url;
 
 use crate::games::{games, games_by_id};
-use crate::tasks::{self, TaskBuilder};
+use crate::tasks::{self, SuccessInfo, TaskBuilder};
 use crate::util::http::ResponseExt;
 use crate::util::search::{Score, SortOption};
-use crate::util::{search, Progress};
-use crate::Reqwest;
+use crate::util::{search, unix_millis, Progress};
 
-use memory::{MemoryModIndex, MemoryModIndexChunk};
+use memory::{ChunkSource, MemoryModIndex, MemoryModIndexChunk};
 
 #[derive(Default)]
 struct ModIndex {
     data: RwLock<MemoryModIndex>,
     refresh_lock: Mutex<()>,
     pub progress: Progress,
+    status: parking_lot::Mutex<ModIndexStatus>,
+    diagnostics: parking_lot::Mutex<IndexDiagnostics>,
+}
+
+/// How the last fetch of a game's mod index went, for diagnosing slow or memory hungry indexes.
+#[derive(Clone, Debug, Default, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IndexDiagnostics {
+    /// The number of versions that were small enough to be stored inline. These counts are shared
+    /// between games, so they are only accurate if no other index was fetched at the same time.
+    pub inline_version_count: u32,
+    pub out_of_line_version_count: u32,
+    pub chunks: Vec<ChunkDiagnostics>,
+}
+
+#[derive(Clone, Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChunkDiagnostics {
+    pub url: Url,
+    pub json_bytes: usize,
+    pub memory_bytes: usize,
+    pub interned_strings: usize,
+    pub interned_bytes: usize,
+    pub average_uses: f64,
+    pub single_use_strings: usize,
+    /// Milliseconds between starting the fetch and the chunk's task starting.
+    pub spawn_ms: f64,
+    pub fetch_ms: f64,
+    pub decode_ms: f64,
+    pub encode_ms: f64,
+}
+
+impl std::fmt::Display for ChunkDiagnostics {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let Self {
+            url: _,
+            json_bytes,
+            memory_bytes,
+            interned_strings,
+            interned_bytes,
+            average_uses,
+            single_use_strings,
+            spawn_ms,
+            fetch_ms,
+            decode_ms,
+            encode_ms,
+        } = self;
+        write!(
+            f,
+            "{json_bytes} bytes of JSON -> {memory_bytes} bytes in memory ({:.2}%, {interned_strings} strings interned, {interned_bytes} bytes, avg. {average_uses:.2} uses/string, {single_use_strings} single-use strings), {spawn_ms:.2}ms spawning, {fetch_ms:.2}ms fetching, {decode_ms:.2}ms decoding, {encode_ms:.2}ms encoding",
+            (*memory_bytes as f64 / *json_bytes as f64) * 100.0
+        )
+    }
+}
+
+/// How fresh a game's mod index is.
+#[derive(Clone, Debug, Default, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ModIndexStatus {
+    /// Milliseconds since the Unix epoch at which the index was last fetched, or `None` if it has
+    /// not been fetched since Manderrow started.
+    pub fetched_at: Option<u64>,
+    pub chunk_count: usize,
+    pub entry_count: usize,
+    /// The number of chunks that could not be fetched, whose mods are missing from the index.
+    pub failed_chunk_count: usize,
+    /// The chunks that could not be fetched, which are fetched again on the next request for the
+    /// index.
+    #[serde(skip)]
+    failed_chunks: Vec<Url>,
+    /// The error of the last attempt to fetch the index or one of its chunks, if it failed. If the
+    /// whole attempt failed, the index from the last successful fetch, if any, is still being
+    /// served.
+    pub last_error: Option<String>,
+    /// Whether the index is currently being fetched.
+    pub refreshing: bool,
+}
+
+/// How many times each chunk is fetched before giving up on it.
+const CHUNK_FETCH_ATTEMPTS: u32 = 3;
+const CHUNK_RETRY_DELAY: std::time::Duration = std::time::Duration::from_secs(2);
+
+/// How long an index stored on disk is served after it was fetched before it is revalidated.
+const STORED_MAX_AGE: Duration = Duration::from_secs(60 * 60);
+
+/// Where the game's mod index is stored between sessions. See [`memory::store`].
+fn stored_path(game: &str) -> PathBuf {
+    cache_dir().join("mod-index").join(game)
+}
+
+enum FetchedChunk {
+    New(MemoryModIndexChunk, ChunkDiagnostics),
+    /// The server responded that the chunk has not changed since it was last fetched.
+    Unchanged(Url),
 }
 
 static MOD_INDEXES: LazyLock<HashMap<&'static str, ModIndex>> = LazyLock::new(|| {
@@ -46,7 +147,6 @@ static MOD_INDEXES: LazyLock<HashMap<&'static str, ModIndex>> = LazyLock::new(||
 
 pub async fn fetch_mod_index(
     app: Option<&AppHandle>,
-    reqwest: &Reqwest,
     game: &str,
     refresh: bool,
     task_id: Option<tasks::Id>,
@@ -63,6 +163,7 @@ pub async fn fetch_mod_index(
             .try_read()
             .map(|data| data.chunks.is_empty())
             .unwrap_or(true)
+        || !mod_index.status.lock().failed_chunks.is_empty()
     {
         TaskBuilder::with_id(task_id.unwrap_or_else(tasks::allocate_task), format!("Fetch mod index for {}", game.id))
             .progress_unit(tasks::ProgressUnit::Bytes)
@@ -75,7 +176,29 @@ pub async fn fetch_mod_index(
                     return Ok((None, ()));
                 };
 
-                #[cfg(feature = "statistics")]
+                // after a restart, the index from the last session is served until it is stale
+                if mod_index.data.read().await.chunks.is_empty() {
+                    match tokio::task::block_in_place(|| memory::load(&stored_path(game.id))) {
+                        Ok(Some((stored, fetched_at))) => {
+                            let age = Duration::from_millis(unix_millis(SystemTime::now()).saturating_sub(fetched_at));
+                            info!(log, "Loaded mod index stored {}s ago", age.as_secs());
+                            *mod_index.status.lock() = ModIndexStatus {
+                                fetched_at: Some(fetched_at),
+                                chunk_count: stored.chunks.len(),
+                                entry_count: stored.chunks.iter().map(|chunk| chunk.mods().len()).sum(),
+                                ..Default::default()
+                            };
+                            *mod_index.data.write().await = stored;
+                            if !refresh && age < STORED_MAX_AGE {
+                                prefetch::spawn(game.id);
+                                return Ok((Some(SuccessInfo::Cached), ()));
+                            }
+                        }
+                        Ok(None) => {}
+                        Err(e) => warn!(log, "Failed to load stored mod index: {e:#}"),
+                    }
+                }
+
                 packed_semver::reset_version_repr_stats();
 
                 mod_index.progress.reset();
@@ -92,45 +215,81 @@ pub async fn fetch_mod_index(
                     }
                 };
 
+                // what is already in memory only needs to be fetched again if it has changed
+                let (known_etag, known_chunks) = {
+                    let data = mod_index.data.read().await;
+                    (
+                        data.etag.clone(),
+                        data.chunks.iter().map(|chunk| (chunk.source.url.clone(), chunk.source.etag.clone())).collect::<HashMap<_, _>>(),
+                    )
+                };
+
                 let new_mod_index = async {
-                    let mut chunk_urls = Vec::new();
-                    GzipDecoder::new(
-                        reqwest
-                            .get(&*game.thunderstore_url)
+                    // unless asked to refresh, only the chunks that failed last time are fetched again
+                    let retrying = if refresh { Vec::new() } else { mod_index.status.lock().failed_chunks.clone() };
+                    let is_retry = !retrying.is_empty();
+                    let (chunk_urls, etag) = if is_retry {
+                        info!(log, "Retrying {} chunks that failed to fetch", retrying.len());
+                        (retrying, known_etag.clone())
+                    } else {
+                        let mut request = crate::util::http::client().get(&*game.thunderstore_url);
+                        if let Some(etag) = &known_etag {
+                            request = request.header(IF_NONE_MATCH, etag);
+                        }
+                        let resp = request
                             .send()
                             .await
                             .context("Failed to fetch chunk URLs from Thunderstore")?
                             .error_for_status()
-                            .context("Failed to fetch chunk URLs from Thunderstore")?
-                            .reader_with_progress(&mod_index.progress),
-                    )
-                    .read_to_end(&mut chunk_urls)
-                    .await
-                    .context("Failed to fetch chunk URLs from Thunderstore")?;
-                    let chunk_urls =
-                        tokio::task::block_in_place(|| simd_json::from_slice::<Vec<Url>>(&mut chunk_urls))
-                            .context("Unable to decode chunk URLs from Thunderstore")?;
+                            .context("Failed to fetch chunk URLs from Thunderstore")?;
+                        if resp.status() == StatusCode::NOT_MODIFIED {
+                            debug!(log, "Chunk URLs have not changed");
+                            let mut chunk_urls = known_chunks.keys().cloned().collect::<Vec<_>>();
+                            chunk_urls.extend(mod_index.status.lock().failed_chunks.iter().cloned());
+                            (chunk_urls, known_etag.clone())
+                        } else {
+                            let etag = resp.headers().get(ETAG).and_then(|v| v.to_str().ok()).map(str::to_owned);
+                            let mut chunk_urls = Vec::new();
+                            GzipDecoder::new(resp.reader_with_progress(&mod_index.progress))
+                                .read_to_end(&mut chunk_urls)
+                                .await
+                                .context("Failed to fetch chunk URLs from Thunderstore")?;
+                            (
+                                tokio::task::block_in_place(|| simd_json::from_slice::<Vec<Url>>(&mut chunk_urls))
+                                    .context("Unable to decode chunk URLs from Thunderstore")?,
+                                etag,
+                            )
+                        }
+                    };
 
                     let started_at = std::time::Instant::now();
 
-                    futures_util::future::try_join_all(chunk_urls.into_iter().map(|url| async {
+                    let fetch_chunk = |url: Url| async {
                         let log = log.clone();
-                        let reqwest = reqwest.clone();
+                        let known_etag = known_chunks.get(&url).cloned().flatten();
                         tokio::task::spawn(async move {
                             let spawned_at = std::time::Instant::now();
                             let latency = spawned_at.duration_since(started_at);
+                            let mut request = crate::util::http::client().get(url.clone());
+                            if let Some(etag) = &known_etag {
+                                request = request.header(IF_NONE_MATCH, etag);
+                            }
+                            let resp = request
+                                .send()
+                                .await
+                                .context("Failed to fetch chunk from Thunderstore")?
+                                .error_for_status()
+                                .context("Failed to fetch chunk from Thunderstore")?;
+                            if resp.status() == StatusCode::NOT_MODIFIED {
+                                return Ok(FetchedChunk::Unchanged(url));
+                            }
+                            let source = ChunkSource {
+                                url: url.clone(),
+                                etag: resp.headers().get(ETAG).and_then(|v| v.to_str().ok()).map(str::to_owned),
+                            };
                             let mut buf = Vec::new();
                             {
-                                let mut rdr = GzipDecoder::new(
-                                    reqwest
-                                        .get(url.clone())
-                                        .send()
-                                        .await
-                                        .context("Failed to fetch chunk from Thunderstore")?
-                                        .error_for_status()
-                                        .context("Failed to fetch chunk from Thunderstore")?
-                                        .reader_with_progress(&mod_index.progress),
-                                );
+                                let mut rdr = GzipDecoder::new(resp.reader_with_progress(&mod_index.progress));
                                 rdr.read_to_end(&mut buf).await?;
                             }
                             let fetched_at = std::time::Instant::now();
@@ -142,28 +301,7 @@ pub async fn fetch_mod_index(
                                 let decoded_at = std::time::Instant::now();
                                 let decoded_in = decoded_at.duration_since(fetched_at);
 
-                                #[cfg(feature = "statistics")]
-                                #[derive(Default)]
-                                struct Statistics {
-                                    values: usize,
-                                    total_bytes: usize,
-                                    average_uses: f64,
-                                    single_use_entries: usize,
-                                }
-                                #[cfg(not(feature = "statistics"))]
-                                struct Statistics;
-                                impl std::fmt::Display for Statistics {
-                                    fn fmt(&self, _f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-                                        #[cfg(feature = "statistics")]
-                                        {
-                                            let Statistics { values, total_bytes, average_uses, single_use_entries } = self;
-                                            write!(_f, "{values} strings interned, {total_bytes} bytes, avg. {average_uses} uses/string, {single_use_entries} single-use strings")?;
-                                        }
-                                        Ok(())
-                                    }
-                                }
-
-                                let (buf, stats) = rkyv::util::with_arena(|arena| {
+                                let (buf, interner) = rkyv::util::with_arena(|arena| {
                                     let mut serializer = rkyv_intern::InterningAdapter::new(
                                         rkyv_intern::InterningAdapter::new(
                                             rkyv::ser::Serializer::new(
@@ -179,49 +317,33 @@ pub async fn fetch_mod_index(
                                         &mods,
                                         &mut serializer,
                                     )?;
-                                    let (serializer, _interner) = serializer.into_components();
-                                    #[cfg(feature = "statistics")]
-                                    #[derive(Default)]
-                                    struct StatisticsAccumulator {
-                                        total_bytes: usize,
-                                        total_uses: usize,
-                                        single_use_entries: usize,
-                                    }
-                                    #[cfg(feature = "statistics")]
-                                    let stats = _interner.iter().map(|(s, e)| (s.len(), e.ref_cnt.get())).fold(StatisticsAccumulator::default(), |mut stats, (len, ref_cnt)| {
-                                        stats.total_bytes += len;
-                                        stats.total_uses += ref_cnt;
-                                        if ref_cnt == 1 {
-                                            stats.single_use_entries += 1;
-                                        }
-                                        stats
-                                    });
-                                    Ok::<_, rkyv::rancor::Error>((serializer.into_serializer().into_writer(), {
-                                        #[cfg(feature = "statistics")]
-                                        {
-                                            Statistics {
-                                                values: _interner.len(),
-                                                total_bytes: stats.total_bytes,
-                                                average_uses: stats.total_uses as f64 / _interner.len() as f64,
-                                                single_use_entries: stats.single_use_entries,
-                                            }
-                                        }
-                                        #[cfg(not(feature = "statistics"))]
-                                        {
-                                            Statistics
-                                        }
-                                    }))
+                                    let (serializer, interner) = serializer.into_components();
+                                    Ok::<_, rkyv::rancor::Error>((serializer.into_serializer().into_writer(), interner))
                                 })?;
                                 let encoded_at = std::time::Instant::now();
                                 let encoded_in = encoded_at.duration_since(decoded_at);
-                                let stats_prefix = if cfg!(feature = "statistics") { ", " } else { "" };
-                                info!(
-                                    log,
-                                    "{buf_len} bytes of JSON -> {} bytes in memory ({:.2}%{stats_prefix}{stats}), {latency:?} spawning, {fetched_in:?} fetching, {decoded_in:?} decoding, {encoded_in:?} encoding",
-                                    buf.len(),
-                                    (buf.len() as f64 / buf_len as f64) * 100.0
-                                );
-                                let index = MemoryModIndexChunk::new(buf, |data| {
+                                let (interned_bytes, total_uses, single_use_strings) = interner
+                                    .iter()
+                                    .map(|(s, e)| (s.len(), e.ref_cnt.get()))
+                                    .fold((0, 0, 0), |(bytes, uses, single_use), (len, ref_cnt)| {
+                                        (bytes + len, uses + ref_cnt, single_use + usize::from(ref_cnt == 1))
+                                    });
+                                let interned_strings = interner.len();
+                                let diagnostics = ChunkDiagnostics {
+                                    url: url.clone(),
+                                    json_bytes: buf_len,
+                                    memory_bytes: buf.len(),
+                                    interned_strings,
+                                    interned_bytes,
+                                    average_uses: if interned_strings == 0 { 0.0 } else { total_uses as f64 / interned_strings as f64 },
+                                    single_use_strings,
+                                    spawn_ms: latency.as_secs_f64() * 1000.0,
+                                    fetch_ms: fetched_in.as_secs_f64() * 1000.0,
+                                    decode_ms: decoded_in.as_secs_f64() * 1000.0,
+                                    encode_ms: encoded_in.as_secs_f64() * 1000.0,
+                                };
+                                info!(log, "{diagnostics}");
+                                let index = MemoryModIndexChunk::new(buf, source, |data| {
                                     if cfg!(debug_assertions) {
                                         rkyv::access::<_, rkyv::rancor::Error>(data)
                                     } else{
@@ -229,28 +351,131 @@ pub async fn fetch_mod_index(
                                         Ok(unsafe { rkyv::access_unchecked(data) })
                                     }
                                 }).with_context(|| format!("Failed to create mod index from chunk at {url:?}"))?;
-                                Ok::<_, anyhow::Error>(index)
+                                Ok::<_, anyhow::Error>(FetchedChunk::New(index, diagnostics))
                             })
                         })
                         .await?
-                    })).await
+                    };
+
+                    let mut chunks = Vec::new();
+                    let mut diagnostics = Vec::new();
+                    let mut unchanged = Vec::new();
+                    let mut failed = chunk_urls;
+                    let mut last_error = None;
+                    for attempt in 1..=CHUNK_FETCH_ATTEMPTS {
+                        if attempt != 1 {
+                            tokio::time::sleep(CHUNK_RETRY_DELAY).await;
+                        }
+                        let results = futures_util::future::join_all(failed.iter().cloned().map(&fetch_chunk)).await;
+                        let mut still_failed = Vec::new();
+                        for (url, r) in failed.into_iter().zip(results) {
+                            match r {
+                                Ok(FetchedChunk::New(chunk, d)) => {
+                                    chunks.push(chunk);
+                                    diagnostics.push(d);
+                                }
+                                Ok(FetchedChunk::Unchanged(url)) => unchanged.push(url),
+                                Err(e) => {
+                                    warn!(log, "Failed to fetch chunk at {url} (attempt {attempt}/{CHUNK_FETCH_ATTEMPTS}): {e:#}");
+                                    last_error = Some(e);
+                                    still_failed.push(url);
+                                }
+                            }
+                        }
+                        failed = still_failed;
+                        if failed.is_empty() {
+                            break;
+                        }
+                    }
+                    Ok::<_, anyhow::Error>((is_retry, etag, chunks, unchanged, diagnostics, failed, last_error))
                 };
-                let new_mod_index = select! {
+                let (is_retry, etag, mut chunks, unchanged, chunk_diagnostics, failed, last_error) = select! {
                     // The "fair" strategy employed by select! should be entirely unnecessary for
                     // this particular use case. `progress_updater` never polls Ready, so it cannot
                     // starve new_mod_index.
                     biased;
                     _ = progress_updater => unreachable!(),
-                    r = new_mod_index => r?,
+                    r = new_mod_index => match r {
+                        Ok(t) => t,
+                        Err(e) => {
+                            mod_index.status.lock().last_error = Some(format!("{e:#}"));
+                            return Err(e);
+                        }
+                    },
+                };
+                // with nothing new to show, keep serving the previous index
+                if chunks.is_empty() && unchanged.is_empty() {
+                    if let Some(e) = last_error {
+                        mod_index.status.lock().last_error = Some(format!("{e:#}"));
+                        return Err(e);
+                    }
+                }
+                let mut data = mod_index.data.write().await;
+                // the mods in the chunks that an incomplete index is missing would all look new
+                let before = (!is_retry && !data.chunks.is_empty() && mod_index.status.lock().failed_chunks.is_empty())
+                    .then(|| tokio::task::block_in_place(|| diff::Snapshot::new(&data)));
+                let mut previous = std::mem::take(&mut data.chunks)
+                    .into_iter()
+                    .map(|chunk| (chunk.source.url.clone(), chunk))
+                    .collect::<HashMap<_, _>>();
+                chunks.extend(unchanged.iter().filter_map(|url| previous.remove(url)));
+                if is_retry {
+                    for chunk in &chunks {
+                        previous.remove(&chunk.source.url);
+                    }
+                    chunks.extend(previous.into_values());
+                } else {
+                    // keep serving what we had for the chunks that failed until a retry replaces them
+                    chunks.extend(failed.iter().filter_map(|url| previous.remove(url)));
+                }
+                *data = MemoryModIndex { chunks, etag };
+                let complete = failed.is_empty();
+                if !complete {
+                    warn!(log, "Mod index is missing {} chunks that failed to fetch", failed.len());
+                }
+                let fetched_at = unix_millis(SystemTime::now());
+                *mod_index.status.lock() = ModIndexStatus {
+                    fetched_at: Some(fetched_at),
+                    chunk_count: data.chunks.len(),
+                    entry_count: data.chunks.iter().map(|chunk| chunk.mods().len()).sum(),
+                    failed_chunk_count: failed.len(),
+                    failed_chunks: failed,
+                    last_error: last_error.map(|e| format!("{e:#}")),
+                    refreshing: false,
                 };
-                *mod_index.data.write().await = MemoryModIndex { chunks: new_mod_index };
+                let data = data.downgrade();
+                let totals = tokio::task::block_in_place(|| crate::games::stats::aggregate(&data));
+                if let Some(before) = before.filter(|_| complete) {
+                    let changes = tokio::task::block_in_place(|| diff::diff(&before, &diff::Snapshot::new(&data)));
+                    info!(log, "Mod index has {} new, {} updated and {} deprecated mods", changes.new_mods.len(), changes.updated_mods.len(), changes.deprecated_mods.len());
+                    diff::publish(app, game.id, fetched_at, changes);
+                }
+                // an incomplete index would be served as if it were complete after a restart
+                if complete {
+                    if let Err(e) = tokio::task::block_in_place(|| memory::store(&stored_path(game.id), &data, fetched_at)) {
+                        warn!(log, "Failed to store mod index: {e:#}");
+                    }
+                }
+                drop(data);
+
+                if let Err(e) = crate::games::stats::record(&log, &game.thunderstore_url, totals) {
+                    warn!(log, "Failed to record game statistics: {e}");
+                }
 
-                #[cfg(feature = "statistics")]
                 let (inline_version_count, out_of_line_version_count) = packed_semver::get_version_repr_stats();
-                #[cfg(not(feature = "statistics"))]
-                let (inline_version_count, out_of_line_version_count) = (None::<u32>, None::<u32>);
+                {
+                    let mut diagnostics = mod_index.diagnostics.lock();
+                    if !is_retry {
+                        diagnostics.chunks.clear();
+                    }
+                    diagnostics.inline_version_count = inline_version_count;
+                    diagnostics.out_of_line_version_count = out_of_line_version_count;
+                    diagnostics.chunks.extend(chunk_diagnostics);
+                }
                 info!(log, "Finished fetching mods"; "inline_version_count" => inline_version_count, "out_of_line_version_count" => out_of_line_version_count);
 
+                prefetch::spawn(game.id);
+
                 Ok::<_, anyhow::Error>((None, ()))
             })
             .await
@@ -283,6 +508,126 @@ impl SortColumn {
     pub const VALUE_COUNT: usize = Self::VALUES.len();
 }
 
+/// Narrows down the mods that a query matches.
+#[derive(Debug, Default, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ModFilter {
+    /// Only mods whose latest version depends on the package with this full name, like
+    /// `BepInEx-BepInExPack`.
+    #[serde(default)]
+    pub depends_on: Option<String>,
+    /// Only mods in at least one of these categories, unless empty.
+    #[serde(default)]
+    pub categories: Vec<String>,
+    /// No mods in any of these categories.
+    #[serde(default)]
+    pub exclude_categories: Vec<String>,
+    #[serde(default)]
+    pub hide_deprecated: bool,
+    #[serde(default)]
+    pub hide_nsfw: bool,
+    /// Only mods with at least this many downloads across all of their versions.
+    #[serde(default)]
+    pub min_downloads: u64,
+    /// Only mods whose latest version was published within this many days.
+    #[serde(default)]
+    pub updated_within_days: Option<u32>,
+}
+
+impl ModFilter {
+    /// Returns a function that tells whether a mod matches the filter.
+    fn matcher(&self) -> impl Fn(&ArchivedModRef) -> bool + '_ {
+        // in microseconds since the Unix epoch, like the timestamps in the index
+        let updated_after = self.updated_within_days.map(|days| {
+            let now = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |d| d.as_micros() as i64);
+            now - i64::from(days) * 24 * 60 * 60 * 1_000_000
+        });
+        move |m| self.matches(m, updated_after)
+    }
+
+    fn matches(&self, m: &ArchivedModRef, updated_after: Option<i64>) -> bool {
+        if self.hide_deprecated && m.is_deprecated {
+            return false;
+        }
+        if self.hide_nsfw && m.has_nsfw_content {
+            return false;
+        }
+        if !self.categories.is_empty()
+            && !m
+                .categories
+                .iter()
+                .any(|c| self.categories.iter().any(|f| **c == **f))
+        {
+            return false;
+        }
+        if m.categories
+            .iter()
+            .any(|c| self.exclude_categories.iter().any(|f| **c == **f))
+        {
+            return false;
+        }
+        if self.min_downloads != 0
+            && m.versions
+                .iter()
+                .map(|v| u64::from(v.downloads))
+                .sum::<u64>()
+                < self.min_downloads
+        {
+            return false;
+        }
+        if let Some(updated_after) = updated_after {
+            let Some(latest) = m.versions.first() else {
+                return false;
+            };
+            if Timestamp::from(latest.date_created)
+                .get()
+                .timestamp_micros()
+                < updated_after
+            {
+                return false;
+            }
+        }
+        if let Some(full_name) = &self.depends_on {
+            // dependencies are full names followed by a version, like `Owner-Name-1.0.0`
+            let Some(latest) = m.versions.first() else {
+                return false;
+            };
+            if !latest.dependencies.iter().any(|dep| {
+                dep.strip_prefix(full_name.as_str())
+                    .is_some_and(|rest| rest.starts_with('-'))
+            }) {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// Returns `true` if the mod index at `thunderstore_url` has been fetched at least once.
+pub fn is_loaded(thunderstore_url: &str) -> bool {
+    MOD_INDEXES
+        .get(thunderstore_url)
+        .and_then(|mod_index| mod_index.data.try_read().ok().map(|data| !data.chunks.is_empty()))
+        .unwrap_or(false)
+}
+
+pub fn get_mod_index_status(game: &str) -> Result<ModIndexStatus> {
+    let game = *games_by_id()?.get(game).context("No such game")?;
+    let mod_index = MOD_INDEXES.get(&*game.thunderstore_url).unwrap();
+    let mut status = mod_index.status.lock().clone();
+    status.refreshing = mod_index.refresh_lock.try_lock().is_err();
+    Ok(status)
+}
+
+/// Returns how the last fetch of the game's mod index went.
+pub fn get_index_diagnostics(game: &str) -> Result<IndexDiagnostics> {
+    let game = *games_by_id()?.get(game).context("No such game")?;
+    let mod_index = MOD_INDEXES.get(&*game.thunderstore_url).unwrap();
+    Ok(mod_index.diagnostics.lock().clone())
+}
+
 pub type ModIndexReadGuard = RwLockReadGuard<'static, MemoryModIndex>;
 
 pub async fn read_mod_index(game: &str) -> Result<ModIndexReadGuard> {
@@ -295,19 +640,41 @@ pub async fn read_mod_index(game: &str) -> Result<ModIndexReadGuard> {
         .await)
 }
 
-pub fn count_mod_index<'a>(mod_index: &'a ModIndexReadGuard, query: &str) -> Result<usize> {
+/// Returns the categories that the mods in the index are in, sorted by name.
+pub fn get_categories(mod_index: &ModIndexReadGuard) -> Vec<String> {
+    let mut categories = mod_index
+        .chunks
+        .iter()
+        .flat_map(|chunk| chunk.mods().iter())
+        .flat_map(|m| m.categories.iter())
+        .map(|c| &**c)
+        .collect::<std::collections::HashSet<_>>()
+        .into_iter()
+        .map(str::to_owned)
+        .collect::<Vec<_>>();
+    categories.sort_unstable();
+    categories
+}
+
+pub fn count_mod_index<'a>(
+    mod_index: &'a ModIndexReadGuard,
+    query: &str,
+    filter: &ModFilter,
+) -> Result<usize> {
     let log = slog_scope::logger();
 
     trace!(log, "Counting mods in mod index");
 
     let start = Instant::now();
 
+    let matches = filter.matcher();
     let count = mod_index
         .chunks
         .iter()
         .map(|mi| {
             mi.mods()
                 .iter()
+                .filter(|m| matches(*m))
                 .filter_map(|m| score_mod(&log, query, m))
                 .filter(|&(_, score)| search::should_include(score))
                 .count()
@@ -325,6 +692,7 @@ pub fn count_mod_index<'a>(mod_index: &'a ModIndexReadGuard, query: &str) -> Res
 pub fn query_mod_index<'a>(
     mod_index: &'a ModIndexReadGuard,
     query: &str,
+    filter: &ModFilter,
     sort: &[SortOption<SortColumn>],
 ) -> Result<Vec<(&'a ArchivedModRef<'a>, Score)>> {
     let log = slog_scope::logger();
@@ -335,10 +703,12 @@ pub fn query_mod_index<'a>(
 
     let mut buf = Vec::new();
 
+    let matches = filter.matcher();
     for mi in mod_index.chunks.iter() {
         buf.extend(
             mi.mods()
                 .iter()
+                .filter(|m| matches(*m))
                 .filter_map(|m| score_mod(&log, query, m))
                 .filter(|&(_, score)| search::should_include(score)),
         );
@@ -365,11 +735,7 @@ pub fn query_mod_index<'a>(
                         };
                         sum_downloads(m1).cmp(&sum_downloads(m2))
                     }
-                    SortColumn::Size => {
-                        let latest_size =
-                            |m: &ArchivedModRef| m.versions.first().map(|v| v.file_size);
-                        latest_size(m1).cmp(&latest_size(m2))
-                    }
+                    SortColumn::Size => sizes::latest_size(m1).cmp(&sizes::latest_size(m2)),
                 };
                 if descending {
                     ordering = ordering.reverse();
@@ -474,12 +840,13 @@ pub async fn get_one_from_mod_index<'a>(
 
 #[cfg(test)]
 mod tests {
-    use manderrow_types::mods::ArchivedModRef;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    use manderrow_types::mods::{ArchivedModRef, Timestamp};
 
     use crate::{
         mod_index::ModIndexReadGuard,
         util::search::{Score, SortOption},
-        Reqwest,
     };
 
     #[test]
@@ -489,21 +856,22 @@ mod tests {
             .build()
             .expect("unable to build tokio runtime")
             .block_on(async {
-                let reqwest = Reqwest(reqwest::Client::new());
-                super::fetch_mod_index(None, &reqwest, "lethal-company", true, None)
+                super::fetch_mod_index(None, "lethal-company", true, None)
                     .await
                     .unwrap();
 
                 let mod_index = super::read_mod_index("lethal-company").await.unwrap();
 
-                let mod_count = super::count_mod_index(&mod_index, "").unwrap();
+                let mod_count =
+                    super::count_mod_index(&mod_index, "", &Default::default()).unwrap();
                 assert!(
                     mod_count >= 40_000,
                     "mod count is lower than expected: {}",
                     mod_count
                 );
 
-                let mods = super::query_mod_index(&mod_index, "", &[]).unwrap();
+                let mods =
+                    super::query_mod_index(&mod_index, "", &Default::default(), &[]).unwrap();
                 assert_eq!(mods.len(), mod_count);
             });
     }
@@ -515,8 +883,7 @@ mod tests {
             .build()
             .expect("unable to build tokio runtime")
             .block_on(async {
-                let reqwest = Reqwest(reqwest::Client::new());
-                super::fetch_mod_index(None, &reqwest, "lethal-company", true, None)
+                super::fetch_mod_index(None, "lethal-company", true, None)
                     .await
                     .unwrap();
 
@@ -544,12 +911,137 @@ mod tests {
             });
     }
 
+    #[test]
+    fn mod_index_filtering_depends_on() {
+        tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()
+            .expect("unable to build tokio runtime")
+            .block_on(async {
+                super::fetch_mod_index(None, "lethal-company", true, None)
+                    .await
+                    .unwrap();
+
+                let mod_index = super::read_mod_index("lethal-company").await.unwrap();
+
+                let filter = super::ModFilter {
+                    depends_on: Some("BepInEx-BepInExPack".to_owned()),
+                    ..Default::default()
+                };
+                let mods = assert_filtered(&mod_index, &filter);
+                for m in mods {
+                    // not `BepInEx-BepInExPack_Something`
+                    assert!(
+                        m.versions.first().is_some_and(|v| v
+                            .dependencies
+                            .iter()
+                            .any(|dep| dep.starts_with("BepInEx-BepInExPack-"))),
+                        "{}-{} does not depend on BepInExPack",
+                        &*m.owner,
+                        &*m.name
+                    );
+                }
+            });
+    }
+
+    #[test]
+    fn mod_index_filtering() {
+        tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()
+            .expect("unable to build tokio runtime")
+            .block_on(async {
+                super::fetch_mod_index(None, "lethal-company", true, None)
+                    .await
+                    .unwrap();
+
+                let mod_index = super::read_mod_index("lethal-company").await.unwrap();
+
+                let is_suit = |m: &ArchivedModRef| m.categories.iter().any(|c| **c == *"Suits");
+                let filter = super::ModFilter {
+                    categories: vec!["Suits".to_owned()],
+                    ..Default::default()
+                };
+                assert!(assert_filtered(&mod_index, &filter)
+                    .into_iter()
+                    .all(is_suit));
+                let filter = super::ModFilter {
+                    exclude_categories: vec!["Suits".to_owned()],
+                    ..Default::default()
+                };
+                assert!(!assert_filtered(&mod_index, &filter)
+                    .into_iter()
+                    .any(is_suit));
+
+                let filter = super::ModFilter {
+                    hide_deprecated: true,
+                    ..Default::default()
+                };
+                assert!(assert_filtered(&mod_index, &filter)
+                    .into_iter()
+                    .all(|m| !m.is_deprecated));
+
+                let filter = super::ModFilter {
+                    hide_nsfw: true,
+                    ..Default::default()
+                };
+                assert!(assert_filtered(&mod_index, &filter)
+                    .into_iter()
+                    .all(|m| !m.has_nsfw_content));
+
+                let filter = super::ModFilter {
+                    min_downloads: 1_000_000,
+                    ..Default::default()
+                };
+                assert!(assert_filtered(&mod_index, &filter).into_iter().all(|m| {
+                    m.versions
+                        .iter()
+                        .map(|v| u64::from(v.downloads))
+                        .sum::<u64>()
+                        >= 1_000_000
+                }));
+
+                let filter = super::ModFilter {
+                    updated_within_days: Some(30),
+                    ..Default::default()
+                };
+                // a day of leeway for the time that passed since the query
+                let updated_after = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap()
+                    .as_micros() as i64
+                    - 31 * 24 * 60 * 60 * 1_000_000;
+                assert!(assert_filtered(&mod_index, &filter).into_iter().all(|m| {
+                    m.versions.first().is_some_and(|v| {
+                        Timestamp::from(v.date_created).get().timestamp_micros() >= updated_after
+                    })
+                }));
+            });
+    }
+
+    /// Returns the mods that `filter` matches, checking that it matches some, but not all, of
+    /// them.
+    fn assert_filtered<'a>(
+        mod_index: &'a ModIndexReadGuard,
+        filter: &super::ModFilter,
+    ) -> Vec<&'a ArchivedModRef<'a>> {
+        let total = super::count_mod_index(mod_index, "", &Default::default()).unwrap();
+        let count = super::count_mod_index(mod_index, "", filter).unwrap();
+        assert!(
+            count != 0 && count < total,
+            "{filter:?} matched {count} of {total} mods"
+        );
+        let mods = super::query_mod_index(mod_index, "", filter, &[]).unwrap();
+        assert_eq!(mods.len(), count);
+        mods.into_iter().map(|(m, _)| m).collect()
+    }
+
     async fn assert_top_result(
         mod_index: &ModIndexReadGuard,
         query: &str,
         top_expected: &[(&str, &str)],
     ) {
-        let mod_count = super::count_mod_index(&mod_index, query).unwrap();
+        let mod_count = super::count_mod_index(&mod_index, query, &Default::default()).unwrap();
         assert!(
             mod_count >= top_expected.len(),
             "mod count is lower than expected: {}",
@@ -559,6 +1051,7 @@ mod tests {
         let mods = super::query_mod_index(
             &mod_index,
             query,
+            &Default::default(),
             &[SortOption {
                 column: super::SortColumn::Relevance,
                 descending: true,
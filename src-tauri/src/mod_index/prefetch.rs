@@ -0,0 +1,122 @@
+//! Prefetching the icons and READMEs of a game's most downloaded mods once its mod index has
+//! loaded, so that browsing the mods for the first time doesn't wait on each of them.
+//!
+//! This is background activity: it stops while background activity is paused, and downloads one
+//! resource at a time, so that it never takes more than one slot of the download queue from
+//! anything the user asked for.
+
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::LazyLock;
+
+use anyhow::Result;
+use packed_semver::Version;
+use parking_lot::Mutex;
+use slog::{debug, info, warn};
+
+use crate::installing::{fetch_resource, url_cache_path, CacheOptions};
+
+use super::read_mod_index;
+use super::thunderstore::{fetch_mod_markdown, ModMarkdown};
+
+/// The number of mods to prefetch for each game, or 0 to not prefetch at all.
+static LIMIT: AtomicUsize = AtomicUsize::new(0);
+
+/// The games that are being prefetched for.
+static RUNNING: LazyLock<Mutex<HashSet<&'static str>>> = LazyLock::new(Default::default);
+
+pub fn set_limit(limit: usize) {
+    LIMIT.store(limit, Ordering::Relaxed);
+}
+
+pub fn icon_url(owner: &str, name: &str, version: Version) -> String {
+    format!("https://gcdn.thunderstore.io/live/repository/icons/{owner}-{name}-{version}.png")
+}
+
+/// Returns the path of the mod's icon if it has been downloaded.
+pub async fn cached_icon(owner: &str, name: &str, version: Version) -> Option<std::path::PathBuf> {
+    let path = url_cache_path(&icon_url(owner, name, version), "");
+    tokio::fs::try_exists(&path)
+        .await
+        .unwrap_or(false)
+        .then_some(path)
+}
+
+/// Starts prefetching for the game in the background, unless prefetching is disabled or already
+/// running for the game.
+pub fn spawn(game: &'static str) {
+    if LIMIT.load(Ordering::Relaxed) == 0 || !RUNNING.lock().insert(game) {
+        return;
+    }
+    tauri::async_runtime::spawn(async move {
+        let log = slog_scope::logger();
+        if let Err(e) = crate::background::run_in_background(prefetch(&log, game)).await {
+            warn!(log, "Failed to prefetch mods of {game}: {e:#}");
+        }
+        RUNNING.lock().remove(game);
+    });
+}
+
+/// Returns the latest version of the `limit` most downloaded mods that are not deprecated.
+async fn top_mods(game: &str, limit: usize) -> Result<Vec<(String, String, Version)>> {
+    let mod_index = read_mod_index(game).await?;
+    let mut mods = mod_index
+        .chunks
+        .iter()
+        .flat_map(|chunk| chunk.mods().iter())
+        .filter(|m| !m.is_deprecated)
+        .filter_map(|m| {
+            let latest = m.versions.first()?;
+            let downloads = m
+                .versions
+                .iter()
+                .map(|v| v.downloads.to_native())
+                .sum::<u64>();
+            Some((downloads, m, latest.version_number.get()))
+        })
+        .collect::<Vec<_>>();
+    mods.sort_unstable_by(|(a, _, _), (b, _, _)| b.cmp(a));
+    Ok(mods
+        .into_iter()
+        .take(limit)
+        .map(|(_, m, version)| ((*m.owner).to_owned(), (*m.name).to_owned(), version))
+        .collect())
+}
+
+async fn prefetch(log: &slog::Logger, game: &str) -> Result<()> {
+    let mods = top_mods(game, LIMIT.load(Ordering::Relaxed)).await?;
+    info!(log, "Prefetching {} mods of {game}", mods.len());
+    for (owner, name, version) in mods {
+        crate::background::wait_until_resumed().await;
+        // the setting may have been changed in the meantime
+        if LIMIT.load(Ordering::Relaxed) == 0 {
+            break;
+        }
+        // both are cached by URL, so anything fetched before is not downloaded again
+        if let Err(e) =
+            fetch_mod_markdown(None, log, &owner, &name, version, ModMarkdown::Readme, None).await
+        {
+            debug!(
+                log,
+                "Failed to prefetch README of {owner}-{name}-{version}: {e:#}"
+            );
+        }
+        if let Err(e) = fetch_resource(
+            None,
+            log,
+            format!("Icon of mod {owner}-{name}-{version}"),
+            &icon_url(&owner, &name, version),
+            Some(CacheOptions::by_url()),
+            None,
+        )
+        .await
+        {
+            debug!(
+                log,
+                "Failed to prefetch icon of {owner}-{name}-{version}: {e:#}"
+            );
+        }
+    }
+    info!(log, "Finished prefetching mods of {game}");
+    Ok(())
+}
@@ -0,0 +1,200 @@
+//! Sizes of mod packages, as reported by Thunderstore.
+//!
+//! Thunderstore only reports the size of each version's archive, so these are the sizes of the
+//! downloads rather than of the installed files.
+
+use std::collections::HashSet;
+use std::path::Path;
+
+use anyhow::{anyhow, ensure, Context as _, Result};
+use manderrow_paths::cache_dir;
+use manderrow_types::mods::{ArchivedModRef, ModId, ModSpec};
+use packed_semver::Version;
+
+use super::{get_one_from_mod_index, ModIndexReadGuard};
+
+/// A unit to display a size in. Decimal units are used, like in the frontend's
+/// `humanizeFileSize`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+pub enum SizeUnit {
+    B,
+    KB,
+    MB,
+    GB,
+    TB,
+}
+
+impl SizeUnit {
+    const VALUES: &[Self] = &[Self::B, Self::KB, Self::MB, Self::GB, Self::TB];
+
+    /// The largest unit that `size` is at least one of.
+    pub fn for_size(size: u64) -> Self {
+        let i = size.checked_ilog(1000).unwrap_or(0) as usize;
+        Self::VALUES[i.min(Self::VALUES.len() - 1)]
+    }
+
+    pub fn bytes(self) -> u64 {
+        1000u64.pow(self as u32)
+    }
+}
+
+/// Formats `size` like the frontend's `humanizeFileSize`, e.g. `1.5MB`.
+pub fn format_size(size: u64) -> String {
+    let unit = SizeUnit::for_size(size);
+    format!("{:.1}{unit:?}", size as f64 / unit.bytes() as f64)
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct VersionSize {
+    pub version_number: Version,
+    pub file_size: u64,
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct ModSizes {
+    /// The size of the latest version.
+    pub latest: u64,
+    /// The combined size of every version.
+    pub total: u64,
+    /// A unit that suits [`latest`](Self::latest) for display.
+    pub unit: SizeUnit,
+    /// The size of each version, latest first.
+    pub versions: Vec<VersionSize>,
+}
+
+/// The size of the latest version of `m`, which is what the size sort column sorts by.
+pub fn latest_size(m: &ArchivedModRef) -> u64 {
+    m.versions
+        .first()
+        .map(|v| v.file_size.to_native())
+        .unwrap_or(0)
+}
+
+pub fn total_size(m: &ArchivedModRef) -> u64 {
+    m.versions.iter().map(|v| v.file_size.to_native()).sum()
+}
+
+pub fn mod_sizes(m: &ArchivedModRef) -> ModSizes {
+    let latest = latest_size(m);
+    ModSizes {
+        latest,
+        total: total_size(m),
+        unit: SizeUnit::for_size(latest),
+        versions: m
+            .versions
+            .iter()
+            .map(|v| VersionSize {
+                version_number: v.version_number.get(),
+                file_size: v.file_size.to_native(),
+            })
+            .collect(),
+    }
+}
+
+/// Sums the sizes of `version` of the mod and of every dependency it pulls in. Dependencies that
+/// are missing from the index are skipped, as installing will report them.
+pub async fn install_size(
+    mod_index: &ModIndexReadGuard,
+    owner: &str,
+    name: &str,
+    version: Version,
+) -> Result<u64> {
+    let mut seen = HashSet::new();
+    let mut pending = vec![(
+        ModId {
+            owner: owner.into(),
+            name: name.into(),
+        },
+        version,
+    )];
+    let mut size = 0;
+    while let Some((mod_id, version)) = pending.pop() {
+        if !seen.insert(mod_id) {
+            continue;
+        }
+        let Some(m) = get_one_from_mod_index(mod_index, mod_id).await? else {
+            continue;
+        };
+        let Some(v) = m
+            .versions
+            .iter()
+            .find(|v| v.version_number.get() == version)
+        else {
+            continue;
+        };
+        size += v.file_size.to_native();
+        for dep in v.dependencies.iter() {
+            let spec = ModSpec::from_str(dep).map_err(|e| anyhow!("{e}"))?;
+            pending.push((spec.id(), spec.version));
+        }
+    }
+    Ok(size)
+}
+
+/// Fails if there isn't room to download packages of `install_size` bytes into the cache and to
+/// extract them into the profile at `profile_path`. Extracted files are at least as large as the
+/// archive, and the cache may be on another disk than the profile.
+pub fn check_install_space(profile_path: &Path, install_size: u64) -> Result<()> {
+    let cache_dir: &Path = cache_dir();
+    // the cache is created by the first download
+    let cache_dir = cache_dir
+        .ancestors()
+        .find(|path| path.exists())
+        .unwrap_or(cache_dir);
+    if same_volume(cache_dir, profile_path)? {
+        check_available_space(profile_path, install_size.saturating_mul(2))
+    } else {
+        check_available_space(cache_dir, install_size)?;
+        check_available_space(profile_path, install_size)
+    }
+}
+
+#[cfg(unix)]
+fn same_volume(a: &Path, b: &Path) -> Result<bool> {
+    use std::os::unix::fs::MetadataExt as _;
+
+    let dev = |path: &Path| {
+        std::fs::metadata(path)
+            .map(|m| m.dev())
+            .with_context(|| format!("Failed to read metadata of {path:?}"))
+    };
+    Ok(dev(a)? == dev(b)?)
+}
+
+#[cfg(windows)]
+fn same_volume(a: &Path, b: &Path) -> Result<bool> {
+    // the drive or network share
+    let prefix = |path: &Path| {
+        std::fs::canonicalize(path)
+            .map(|path| path.components().next().map(|c| c.as_os_str().to_owned()))
+            .with_context(|| format!("Failed to resolve {path:?}"))
+    };
+    Ok(prefix(a)? == prefix(b)?)
+}
+
+/// Fails if the disk that `path` is on has less than `required` bytes available.
+fn check_available_space(path: &Path, required: u64) -> Result<()> {
+    let available = fs4::available_space(path)
+        .with_context(|| format!("Failed to get available disk space at {path:?}"))?;
+    ensure!(
+        available >= required,
+        "Not enough disk space at {path:?}: {} is needed, but only {} is available",
+        format_size(required),
+        format_size(available)
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_size() {
+        assert_eq!(format_size(0), "0.0B");
+        assert_eq!(format_size(999), "999.0B");
+        assert_eq!(format_size(1500), "1.5KB");
+        assert_eq!(format_size(25_300_000), "25.3MB");
+        assert_eq!(SizeUnit::for_size(u64::MAX), SizeUnit::TB);
+    }
+}
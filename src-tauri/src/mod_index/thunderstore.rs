@@ -1,12 +1,16 @@
 pub mod commands;
+pub mod ratings;
 
 use anyhow::Result;
+use futures_util::StreamExt as _;
 use packed_semver::Version;
+use reqwest::StatusCode;
 use slog::Logger;
 use tauri::AppHandle;
 
 use crate::installing::{fetch_resource_as_bytes, CacheOptions};
-use crate::{tasks, Reqwest};
+use crate::tasks;
+use crate::util::http::has_status;
 
 #[derive(Clone, Copy, serde::Deserialize)]
 pub enum ModMarkdown {
@@ -16,10 +20,16 @@ pub enum ModMarkdown {
     Changelog,
 }
 
+/// The body of a response from the endpoints of [`ModMarkdown`].
+#[derive(serde::Deserialize)]
+pub struct MarkdownResponse {
+    /// `None` if the mod has no such document.
+    pub markdown: Option<String>,
+}
+
 pub async fn fetch_mod_markdown(
     app: Option<&AppHandle>,
     log: &Logger,
-    reqwest: &Reqwest,
     owner: &str,
     name: &str,
     version: Version,
@@ -29,7 +39,6 @@ pub async fn fetch_mod_markdown(
     let bytes = fetch_resource_as_bytes(
         app,
         log,
-        reqwest,
         format!(
             "{} of mod {owner}-{name}-{version}",
             match endpoint {
@@ -50,3 +59,55 @@ pub async fn fetch_mod_markdown(
     .await?;
     Ok(String::from_utf8(Vec::from(bytes))?)
 }
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct VersionChangelog {
+    pub version: Version,
+    /// `None` if the version has no changelog, or it could not be fetched.
+    pub markdown: Option<String>,
+    /// Why the changelog could not be fetched.
+    pub error: Option<String>,
+}
+
+/// How many changelogs [`fetch_mod_changelogs`] fetches at a time.
+const CONCURRENT_CHANGELOGS: usize = 4;
+
+/// Fetches the CHANGELOG of each of the mod's `versions`, a few at a time. A version whose
+/// changelog can't be fetched doesn't stop the others from being fetched.
+pub async fn fetch_mod_changelogs(
+    app: Option<&AppHandle>,
+    log: &Logger,
+    owner: &str,
+    name: &str,
+    versions: &[Version],
+) -> Vec<VersionChangelog> {
+    futures_util::stream::iter(versions.iter().map(|&version| async move {
+        let result = async {
+            let json =
+                fetch_mod_markdown(app, log, owner, name, version, ModMarkdown::Changelog, None)
+                    .await?;
+            Ok::<_, anyhow::Error>(serde_json::from_str::<MarkdownResponse>(&json)?.markdown)
+        }
+        .await;
+        match result {
+            Ok(markdown) => VersionChangelog {
+                version,
+                markdown,
+                error: None,
+            },
+            Err(e) if has_status(&e, StatusCode::NOT_FOUND) => VersionChangelog {
+                version,
+                markdown: None,
+                error: None,
+            },
+            Err(e) => VersionChangelog {
+                version,
+                markdown: None,
+                error: Some(format!("{e:#}")),
+            },
+        }
+    }))
+    .buffered(CONCURRENT_CHANGELOGS)
+    .collect()
+    .await
+}
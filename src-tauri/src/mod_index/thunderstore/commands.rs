@@ -1,15 +1,15 @@
 use packed_semver::Version;
 use tauri::ipc::InvokeResponseBody;
-use tauri::{AppHandle, State};
+use tauri::AppHandle;
 
-use crate::{tasks, CommandError, Reqwest};
+use crate::{tasks, CommandError};
 
-use super::ModMarkdown;
+use super::ratings::{RateResponse, RatingState, ReportReason};
+use super::{ModMarkdown, VersionChangelog};
 
 #[tauri::command]
 pub async fn thunderstore_fetch_mod_markdown(
     app: AppHandle,
-    reqwest: State<'_, Reqwest>,
     owner: &str,
     name: &str,
     version: Version,
@@ -19,7 +19,6 @@ pub async fn thunderstore_fetch_mod_markdown(
     super::fetch_mod_markdown(
         Some(&app),
         &slog_scope::logger(),
-        &reqwest,
         owner,
         name,
         version,
@@ -30,3 +29,63 @@ pub async fn thunderstore_fetch_mod_markdown(
     .map_err(Into::into)
     .map(InvokeResponseBody::Json)
 }
+
+/// Fetches the CHANGELOG of each of the mod's `versions`, so that what changed between the
+/// installed version and a newer one can be reviewed before upgrading.
+#[tauri::command]
+pub async fn thunderstore_fetch_mod_changelogs(
+    app: AppHandle,
+    owner: &str,
+    name: &str,
+    versions: Vec<Version>,
+) -> Result<Vec<VersionChangelog>, CommandError> {
+    Ok(
+        super::fetch_mod_changelogs(Some(&app), &slog_scope::logger(), owner, name, &versions)
+            .await,
+    )
+}
+
+#[tauri::command]
+pub async fn thunderstore_get_rated_mods() -> Result<Vec<String>, CommandError> {
+    super::ratings::get_rated_mods().await.map_err(Into::into)
+}
+
+#[tauri::command]
+pub async fn thunderstore_rate_mod(
+    owner: &str,
+    name: &str,
+    target_state: RatingState,
+) -> Result<RateResponse, CommandError> {
+    super::ratings::rate_mod(
+        &slog_scope::logger(),
+        &crate::auth::session_token().await?,
+        owner,
+        name,
+        target_state,
+    )
+    .await
+    .map_err(Into::into)
+}
+
+#[tauri::command]
+pub async fn thunderstore_report_mod(
+    community: &str,
+    owner: &str,
+    name: &str,
+    version: Option<Version>,
+    reason: ReportReason,
+    description: Option<&str>,
+) -> Result<(), CommandError> {
+    super::ratings::report_mod(
+        &slog_scope::logger(),
+        &crate::auth::session_token().await?,
+        community,
+        owner,
+        name,
+        version,
+        reason,
+        description,
+    )
+    .await
+    .map_err(Into::into)
+}
@@ -0,0 +1,153 @@
+//! Rating and reporting mods with a Thunderstore account.
+//!
+//! Thunderstore does not offer a way to list the packages a user has rated, so the state of each
+//! rating made through Manderrow is cached locally for the UI to reflect.
+
+use std::collections::BTreeSet;
+use std::path::PathBuf;
+use std::sync::LazyLock;
+
+use anyhow::{Context as _, Result};
+use manderrow_paths::local_data_dir;
+use slog::debug;
+use tokio::sync::Mutex;
+
+use crate::profiles::configs::write_atomically;
+use crate::util::IoErrorKindExt as _;
+
+static PATH: LazyLock<PathBuf> =
+    LazyLock::new(|| local_data_dir().join("thunderstore_ratings.json"));
+
+/// The full names (`owner-name`) of the packages rated by the user. `None` until loaded.
+static RATED: Mutex<Option<BTreeSet<String>>> = Mutex::const_new(None);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RatingState {
+    Rated,
+    Unrated,
+}
+
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]
+pub struct RateResponse {
+    pub state: RatingState,
+    pub score: u64,
+}
+
+#[derive(Debug, Clone, Copy, serde::Deserialize, serde::Serialize)]
+pub enum ReportReason {
+    Spam,
+    Malware,
+    Reupload,
+    CopyrightOrLicense,
+    WrongCommunity,
+    WrongCategories,
+    Other,
+}
+
+async fn load(rated: &mut Option<BTreeSet<String>>) -> Result<&mut BTreeSet<String>> {
+    if rated.is_none() {
+        *rated = Some(match tokio::fs::read(&*PATH).await {
+            Ok(bytes) => serde_json::from_slice(&bytes)
+                .with_context(|| format!("Failed to parse rating cache at {:?}", *PATH))?,
+            Err(e) if e.is_not_found() => BTreeSet::new(),
+            Err(e) => return Err(e.into()),
+        });
+    }
+    Ok(rated.as_mut().unwrap())
+}
+
+async fn post_json(
+    session_token: &str,
+    url: String,
+    body: &serde_json::Value,
+) -> Result<bytes::Bytes> {
+    Ok(crate::util::http::client()
+        .post(url)
+        .header(
+            reqwest::header::AUTHORIZATION,
+            format!("Session {session_token}"),
+        )
+        .header(reqwest::header::CONTENT_TYPE, "application/json")
+        .body(serde_json::to_vec(body)?)
+        .send()
+        .await?
+        .error_for_status()?
+        .bytes()
+        .await?)
+}
+
+/// Returns the full names of the packages that the user has rated through Manderrow.
+pub async fn get_rated_mods() -> Result<Vec<String>> {
+    let mut rated = RATED.lock().await;
+    Ok(load(&mut rated).await?.iter().cloned().collect())
+}
+
+/// Rates or unrates a package on behalf of the user.
+pub async fn rate_mod(
+    log: &slog::Logger,
+    session_token: &str,
+    owner: &str,
+    name: &str,
+    target_state: RatingState,
+) -> Result<RateResponse> {
+    debug!(log, "Setting rating of {owner}-{name} to {target_state:?}");
+
+    let bytes = post_json(
+        session_token,
+        format!("https://thunderstore.io/api/cyberstorm/package/{owner}/{name}/rate/"),
+        &serde_json::json!({ "target_state": target_state }),
+    )
+    .await
+    .context("Failed to submit rating to Thunderstore")?;
+    let resp = serde_json::from_slice::<RateResponse>(&bytes)
+        .context("Failed to decode rating response from Thunderstore")?;
+
+    let mut rated = RATED.lock().await;
+    let set = load(&mut rated).await?;
+    let full_name = format!("{owner}-{name}");
+    let changed = match resp.state {
+        RatingState::Rated => set.insert(full_name),
+        RatingState::Unrated => set.remove(&full_name),
+    };
+    if changed {
+        let bytes = serde_json::to_vec(&*set)?;
+        tokio::task::block_in_place(|| write_atomically(&PATH, &bytes))
+            .with_context(|| format!("Failed to write rating cache to {:?}", *PATH))?;
+    }
+
+    Ok(resp)
+}
+
+/// Submits a report about a package to the moderators of `community`.
+pub async fn report_mod(
+    log: &slog::Logger,
+    session_token: &str,
+    community: &str,
+    owner: &str,
+    name: &str,
+    version: Option<packed_semver::Version>,
+    reason: ReportReason,
+    description: Option<&str>,
+) -> Result<()> {
+    debug!(
+        log,
+        "Reporting {owner}-{name} in {community} for {reason:?}"
+    );
+
+    post_json(
+        session_token,
+        format!(
+            "https://thunderstore.io/api/cyberstorm/listing/{community}/{owner}/{name}/report/"
+        ),
+        &serde_json::json!({
+            "version": version.map(|v| v.to_string()),
+            "reason": reason,
+            "description": description,
+        }),
+    )
+    .await
+    .context("Failed to submit report to Thunderstore")?;
+
+    Ok(())
+}
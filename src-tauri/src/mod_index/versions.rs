@@ -0,0 +1,28 @@
+//! The versions of a mod that the index knows of, for reviewing what changed before upgrading.
+
+use manderrow_types::mods::{ArchivedModRef, Timestamp};
+use packed_semver::Version;
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct VersionListing {
+    pub version_number: Version,
+    pub date_created: Timestamp,
+    /// The size of the version's archive in bytes.
+    pub file_size: u64,
+    pub downloads: u64,
+    pub is_active: bool,
+}
+
+/// Lists the versions of `m`, latest first.
+pub fn version_list(m: &ArchivedModRef) -> Vec<VersionListing> {
+    m.versions
+        .iter()
+        .map(|v| VersionListing {
+            version_number: v.version_number.get(),
+            date_created: Timestamp::from(v.date_created),
+            file_size: v.file_size.to_native(),
+            downloads: v.downloads.to_native(),
+            is_active: v.is_active,
+        })
+        .collect()
+}
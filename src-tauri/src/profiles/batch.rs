@@ -0,0 +1,617 @@
+//! Applying a list of operations to the mods of a profile as one task, for the actions on a
+//! selection of mods.
+//!
+//! Installs and updates are staged together, and the other operations are applied afterwards, in
+//! order. Those can't be staged, so the files they change are copied first. If any operation
+//! fails, the copies are put back and the staged installs are rolled back, so either every
+//! operation is applied or none are.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, ensure, Context as _, Result};
+use manderrow_types::mods::ModId;
+use packed_semver::Version;
+use parking_lot::Mutex;
+use slog::{debug, error};
+use smol_str::SmolStr;
+use tauri::AppHandle;
+use tempfile::TempDir;
+use uuid::Uuid;
+use walkdir::WalkDir;
+
+use crate::mod_index::sizes;
+use crate::tasks::{self, TaskBuilder, TaskError};
+use crate::util::IoErrorKindExt as _;
+
+use super::{
+    commit_installs, install_profile_mod_inner, overlaps, profile_path, push_mod_folder,
+    read_installed_manifest, read_mod_manifest, read_profile_file, MANIFEST_FILE_NAME, MODS_FOLDER,
+    PATCHERS_FOLDER, TOOLS_FOLDER,
+};
+
+/// The suffix that disabled plugins are renamed to have, like r2modman does.
+pub const DISABLED_SUFFIX: &str = ".dll.old";
+
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum Op {
+    /// Installs the version of the mod, along with its dependencies.
+    Install {
+        owner: SmolStr,
+        name: SmolStr,
+        version: Version,
+    },
+    /// Installs the latest version of an installed mod, unless it is already installed.
+    Update {
+        owner: SmolStr,
+        name: SmolStr,
+    },
+    Uninstall {
+        owner: SmolStr,
+        name: SmolStr,
+    },
+    Enable {
+        owner: SmolStr,
+        name: SmolStr,
+    },
+    Disable {
+        owner: SmolStr,
+        name: SmolStr,
+    },
+}
+
+async fn read_installed_version(id: Uuid, owner: &str, name: &str) -> Result<Option<Version>> {
+    #[derive(serde::Deserialize)]
+    struct InstalledManifest {
+        version: InstalledVersion,
+    }
+    #[derive(serde::Deserialize)]
+    struct InstalledVersion {
+        version_number: Version,
+    }
+
+    Ok(
+        read_installed_manifest::<InstalledManifest>(&profile_path(id), owner, name)
+            .await?
+            .map(|manifest| manifest.version.version_number),
+    )
+}
+
+/// Applies `ops` to the profile as one task. If any install or update fails, none of them are
+/// applied, and neither are the other operations.
+pub async fn apply(
+    app: &AppHandle,
+    id: Uuid,
+    ops: &[Op],
+    allow_overlaps: bool,
+    task_id: tasks::Id,
+) -> Result<Vec<crate::compat::CompatWarning>> {
+    let log = &slog_scope::logger();
+
+    let mut profile_path = profile_path(id);
+    profile_path.push("profile.json");
+    let game = read_profile_file(&profile_path).await?.game;
+    profile_path.pop();
+    let profile_path = &profile_path;
+
+    TaskBuilder::with_id(task_id, format!("Apply {} changes to profile", ops.len()))
+        .kind(tasks::Kind::Aggregate)
+        .run_with_handle(Some(app), |handle| async move {
+            let mod_index = crate::mod_index::read_mod_index(&game).await?;
+            let community_url = crate::games::games_by_id()?
+                .get(&*game)
+                .and_then(|game| crate::games::community_url(game));
+
+            let mut installs = Vec::<(&str, &str, Version)>::new();
+            for op in ops {
+                match op {
+                    Op::Install {
+                        owner,
+                        name,
+                        version,
+                    } => installs.push((owner.as_str(), name.as_str(), *version)),
+                    Op::Update { owner, name } => {
+                        let installed = read_installed_version(id, owner, name)
+                            .await?
+                            .ok_or_else(|| anyhow!("{owner}-{name} is not installed"))?;
+                        let latest = crate::mod_index::get_one_from_mod_index(
+                            &mod_index,
+                            ModId {
+                                owner: owner.as_str().into(),
+                                name: name.as_str().into(),
+                            },
+                        )
+                        .await?
+                        .and_then(|m| m.versions.first())
+                        .map(|v| v.version_number.get())
+                        .ok_or_else(|| anyhow!("{owner}-{name} is not in the mod index"))?;
+                        if latest != installed {
+                            installs.push((owner.as_str(), name.as_str(), latest));
+                        }
+                    }
+                    // fail before anything is downloaded
+                    Op::Uninstall { owner, name }
+                    | Op::Enable { owner, name }
+                    | Op::Disable { owner, name } => {
+                        ensure!(
+                            read_installed_version(id, owner, name).await?.is_some(),
+                            "{owner}-{name} is not installed"
+                        );
+                    }
+                }
+            }
+
+            // dropping the staged installations rolls them back
+            let seen = Mutex::new(HashMap::new());
+            if !installs.is_empty() {
+                let mut install_size = 0u64;
+                for &(owner, name, version) in &installs {
+                    ensure!(
+                        !(owner == "BepInEx" && name == "BepInExPack"),
+                        "BepInEx pack is managed by manderrow and will be installed automatically if required"
+                    );
+                    install_size = install_size.saturating_add(
+                        sizes::install_size(&mod_index, owner, name, version).await?,
+                    );
+                }
+                sizes::check_install_space(profile_path, install_size)?;
+
+                futures_util::future::try_join_all(installs.iter().map(
+                    |&(owner, name, version)| {
+                        let (mod_index, seen) = (&mod_index, &seen);
+                        async move {
+                            install_profile_mod_inner(
+                                log,
+                                app,
+                                id,
+                                profile_path,
+                                mod_index,
+                                community_url,
+                                owner,
+                                name,
+                                version,
+                                allow_overlaps,
+                                false,
+                                handle.allocate_dependency(app)?,
+                                seen,
+                            )
+                            .await
+                        }
+                    },
+                ))
+                .await?;
+            }
+
+            let rollback = apply_other_ops(log, id, profile_path, ops).await?;
+            if !installs.is_empty() {
+                if let Err(e) = commit_installs(log, id, seen.into_inner()).await {
+                    rollback.restore(log);
+                    return Err(e);
+                }
+            }
+
+            // the changes are applied either way, so don't fail because of the check
+            let warnings = crate::compat::check_profile_or_log(log, id, &game).await;
+            Ok((None, warnings))
+        })
+        .await
+        .map_err(|e: TaskError<anyhow::Error>| anyhow::Error::from(e))
+}
+
+/// Copies of the files that the operations other than installs and updates change, which are put
+/// back if the batch fails. Unlike installs, those operations can't be staged.
+struct Rollback {
+    dir: TempDir,
+    /// The saved paths, along with where they were copied to, or `None` if they didn't exist.
+    saved: Vec<(PathBuf, Option<PathBuf>)>,
+}
+
+impl Rollback {
+    fn new(profile_path: &Path) -> Result<Self> {
+        Ok(Self {
+            // in the profile, so that the copies can be moved back
+            dir: tempfile::Builder::new()
+                .prefix(".batch-")
+                .tempdir_in(profile_path)
+                .context("Failed to create rollback directory")?,
+            saved: Vec::new(),
+        })
+    }
+
+    /// Copies the file or folder at `path`, unless it was saved already.
+    fn save(&mut self, path: PathBuf) -> Result<()> {
+        if self.saved.iter().any(|(saved, _)| *saved == path) {
+            return Ok(());
+        }
+        let copy = self.dir.path().join(self.saved.len().to_string());
+        let copied = tokio::task::block_in_place(|| copy_all(&path, &copy))
+            .with_context(|| format!("Failed to save {path:?}"))?;
+        self.saved.push((path, copied.then_some(copy)));
+        Ok(())
+    }
+
+    /// Puts back everything that was saved, most recent first.
+    fn restore(self, log: &slog::Logger) {
+        tokio::task::block_in_place(|| {
+            for (path, copy) in self.saved.iter().rev() {
+                debug!(log, "Rolling back {path:?}");
+                if let Err(e) = remove_all(path) {
+                    error!(log, "Failed to roll back {path:?}: {e}");
+                    continue;
+                }
+                if let Some(copy) = copy {
+                    if let Err(e) = std::fs::rename(copy, path) {
+                        error!(log, "Failed to roll back {path:?}: {e}");
+                    }
+                }
+            }
+        })
+    }
+}
+
+/// Copies the file or folder at `from` to `to`. Returns `false` if there is nothing at `from`.
+fn copy_all(from: &Path, to: &Path) -> Result<bool> {
+    match std::fs::symlink_metadata(from) {
+        Ok(_) => {}
+        Err(e) if e.is_not_found() => return Ok(false),
+        Err(e) => return Err(e.into()),
+    }
+    for e in WalkDir::new(from) {
+        let e = e?;
+        let rel_path = e.path().strip_prefix(from)?;
+        let path = if rel_path.as_os_str().is_empty() {
+            to.to_owned()
+        } else {
+            to.join(rel_path)
+        };
+        if e.file_type().is_dir() {
+            std::fs::create_dir_all(&path)?;
+        } else {
+            std::fs::copy(e.path(), &path)?;
+        }
+    }
+    Ok(true)
+}
+
+fn remove_all(path: &Path) -> std::io::Result<()> {
+    match std::fs::symlink_metadata(path) {
+        Ok(m) if m.is_dir() => std::fs::remove_dir_all(path),
+        Ok(_) => std::fs::remove_file(path),
+        Err(e) if e.is_not_found() => Ok(()),
+        Err(e) => Err(e),
+    }
+}
+
+/// Applies the operations in `ops` other than installs and updates, in order. If one fails, the
+/// ones before it are rolled back. Otherwise, returns the [`Rollback`] so that they can still be
+/// rolled back if committing the installs fails.
+async fn apply_other_ops(
+    log: &slog::Logger,
+    id: Uuid,
+    profile_path: &Path,
+    ops: &[Op],
+) -> Result<Rollback> {
+    let mut rollback = Rollback::new(profile_path)?;
+    for op in ops {
+        let r = async {
+            let (owner, name) = match op {
+                Op::Uninstall { owner, name }
+                | Op::Enable { owner, name }
+                | Op::Disable { owner, name } => (owner, name),
+                Op::Install { .. } | Op::Update { .. } => return Ok(()),
+            };
+            for folder in [MODS_FOLDER, PATCHERS_FOLDER, TOOLS_FOLDER] {
+                let mut path = profile_path.join(folder);
+                push_mod_folder(&mut path, owner, name);
+                rollback.save(path)?;
+            }
+            match op {
+                Op::Uninstall { .. } => {
+                    rollback.save(profile_path.join(overlaps::OVERLAPS_FILE_NAME))?;
+                    super::uninstall_profile_mod(id, owner, name).await
+                }
+                Op::Enable { .. } => set_mod_enabled(log, profile_path, owner, name, true).await,
+                Op::Disable { .. } => set_mod_enabled(log, profile_path, owner, name, false).await,
+                Op::Install { .. } | Op::Update { .. } => Ok(()),
+            }
+        }
+        .await;
+        if let Err(e) = r {
+            rollback.restore(log);
+            return Err(e);
+        }
+    }
+    Ok(rollback)
+}
+
+/// Enables or disables a mod installed in the profile by renaming the plugins in its folder, and
+/// those in its patchers folder, so that BepInEx does not load them.
+///
+/// Whether the mod is disabled is recorded as `disabled` in its [`MANIFEST_FILE_NAME`].
+pub async fn set_mod_enabled(
+    log: &slog::Logger,
+    profile_path: &Path,
+    owner: &str,
+    name: &str,
+    enabled: bool,
+) -> Result<()> {
+    let mut manifest = read_installed_manifest::<serde_json::Map<String, serde_json::Value>>(
+        profile_path,
+        owner,
+        name,
+    )
+    .await?
+    .with_context(|| format!("{owner}-{name} is not installed"))?;
+
+    let disabled = manifest
+        .get("disabled")
+        .and_then(serde_json::Value::as_bool)
+        .unwrap_or(false);
+    if disabled != enabled {
+        return Ok(());
+    }
+
+    let mut path = profile_path.join(MODS_FOLDER);
+    push_mod_folder(&mut path, owner, name);
+    rename_plugins(log, &path, enabled).await?;
+
+    let mut patchers_path = profile_path.join(PATCHERS_FOLDER);
+    push_mod_folder(&mut patchers_path, owner, name);
+    if tokio::fs::try_exists(&patchers_path).await? {
+        rename_plugins(log, &patchers_path, enabled).await?;
+    }
+
+    if enabled {
+        manifest.remove("disabled");
+    } else {
+        manifest.insert("disabled".to_owned(), true.into());
+    }
+    path.push(MANIFEST_FILE_NAME);
+    tokio::fs::write(&path, serde_json::to_vec(&manifest)?)
+        .await
+        .with_context(|| format!("Failed to write mod manifest {path:?}"))?;
+    Ok(())
+}
+
+/// Keeps the mod installed in `mod_folder` disabled when a new copy of it is staged, by an update
+/// or a reinstall, by renaming the plugins in `staged_mod` and `staged_patchers` too. Returns
+/// whether the mod is disabled, to be recorded in the new copy's manifest.
+pub(super) async fn keep_disabled(
+    log: &slog::Logger,
+    mod_folder: &Path,
+    staged_mod: &Path,
+    staged_patchers: Option<&Path>,
+) -> Result<bool> {
+    #[derive(serde::Deserialize)]
+    struct DisabledManifest {
+        #[serde(default)]
+        disabled: bool,
+    }
+
+    let disabled = read_mod_manifest::<DisabledManifest>(mod_folder)
+        .await?
+        .is_some_and(|manifest| manifest.disabled);
+    if disabled {
+        rename_plugins(log, staged_mod, false).await?;
+        if let Some(staged_patchers) = staged_patchers {
+            rename_plugins(log, staged_patchers, false).await?;
+        }
+    }
+    Ok(disabled)
+}
+
+/// Renames the `.dll` files in `folder` to end with [`DISABLED_SUFFIX`], or back if `enabled`.
+pub(super) async fn rename_plugins(log: &slog::Logger, folder: &Path, enabled: bool) -> Result<()> {
+    let (from, to) = if enabled {
+        (DISABLED_SUFFIX, ".dll")
+    } else {
+        (".dll", DISABLED_SUFFIX)
+    };
+    tokio::task::block_in_place(|| {
+        for e in WalkDir::new(folder) {
+            let e = e?;
+            if !e.file_type().is_file() {
+                continue;
+            }
+            let Some(stem) = e.file_name().to_str().and_then(|s| s.strip_suffix(from)) else {
+                continue;
+            };
+            let target = e.path().with_file_name(format!("{stem}{to}"));
+            debug!(log, "Renaming {:?} to {target:?}", e.path());
+            std::fs::rename(e.path(), &target)
+                .with_context(|| format!("Failed to rename {:?}", e.path()))?;
+        }
+        Ok(())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn block_on<F: std::future::Future>(f: F) -> F::Output {
+        tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()
+            .expect("unable to build tokio runtime")
+            .block_on(f)
+    }
+
+    /// Creates a profile with the mod `Owner-Name`, which has a plugin and a patcher.
+    fn profile() -> TempDir {
+        let profile = tempfile::tempdir().unwrap();
+        let mods = profile.path().join("mods/Owner-Name");
+        std::fs::create_dir_all(mods.join("plugins")).unwrap();
+        std::fs::write(mods.join(MANIFEST_FILE_NAME), b"{}").unwrap();
+        std::fs::write(mods.join("plugins/Plugin.dll"), b"plugin").unwrap();
+        std::fs::write(mods.join("README.md"), b"readme").unwrap();
+        let patchers = profile.path().join("patchers/Owner-Name");
+        std::fs::create_dir_all(&patchers).unwrap();
+        std::fs::write(patchers.join("Patcher.dll"), b"patcher").unwrap();
+        profile
+    }
+
+    fn disable(owner: &str, name: &str) -> Op {
+        Op::Disable {
+            owner: owner.into(),
+            name: name.into(),
+        }
+    }
+
+    fn is_disabled(profile: &Path) -> bool {
+        let manifest =
+            std::fs::read(profile.join("mods/Owner-Name").join(MANIFEST_FILE_NAME)).unwrap();
+        let manifest = serde_json::from_slice::<serde_json::Value>(&manifest).unwrap();
+        let disabled = manifest["disabled"] == true;
+        for path in [
+            "mods/Owner-Name/plugins/Plugin.dll",
+            "patchers/Owner-Name/Patcher.dll",
+        ] {
+            let old = profile.join(format!("{path}.old"));
+            assert_eq!(profile.join(path).exists(), !disabled, "{path}");
+            assert_eq!(old.exists(), disabled, "{path}");
+        }
+        assert!(profile.join("mods/Owner-Name/README.md").exists());
+        disabled
+    }
+
+    #[test]
+    fn test_rename_plugins() {
+        let log = slog::Logger::root(slog::Discard, slog::o!());
+        let folder = tempfile::tempdir().unwrap();
+        std::fs::create_dir(folder.path().join("sub")).unwrap();
+        std::fs::write(folder.path().join("A.dll"), b"a").unwrap();
+        std::fs::write(folder.path().join("sub/B.dll"), b"b").unwrap();
+        std::fs::write(folder.path().join("C.txt"), b"c").unwrap();
+
+        block_on(rename_plugins(&log, folder.path(), false)).unwrap();
+        assert_eq!(
+            std::fs::read(folder.path().join("A.dll.old")).unwrap(),
+            b"a"
+        );
+        assert!(folder.path().join("sub/B.dll.old").exists());
+        assert!(!folder.path().join("A.dll").exists());
+        assert!(folder.path().join("C.txt").exists());
+
+        block_on(rename_plugins(&log, folder.path(), true)).unwrap();
+        assert_eq!(std::fs::read(folder.path().join("A.dll")).unwrap(), b"a");
+        assert!(folder.path().join("sub/B.dll").exists());
+        assert!(!folder.path().join("sub/B.dll.old").exists());
+    }
+
+    #[test]
+    fn test_set_mod_enabled() {
+        let log = slog::Logger::root(slog::Discard, slog::o!());
+        let profile = profile();
+        let set = |name: &str, enabled: bool| {
+            block_on(set_mod_enabled(
+                &log,
+                profile.path(),
+                "Owner",
+                name,
+                enabled,
+            ))
+        };
+
+        set("Name", false).unwrap();
+        assert!(is_disabled(profile.path()));
+        // already disabled
+        set("Name", false).unwrap();
+        assert!(is_disabled(profile.path()));
+
+        set("Name", true).unwrap();
+        assert!(!is_disabled(profile.path()));
+
+        assert!(set("Missing", false).is_err());
+    }
+
+    #[test]
+    fn test_update_keeps_disabled() {
+        let log = slog::Logger::root(slog::Discard, slog::o!());
+        let profile = profile();
+        block_on(apply_other_ops(
+            &log,
+            Uuid::new_v4(),
+            profile.path(),
+            &[disable("Owner", "Name")],
+        ))
+        .unwrap();
+
+        // a new version is staged, with plugins that would be loaded
+        let staged = tempfile::tempdir().unwrap();
+        let staged_mod = staged.path().join("mod");
+        std::fs::create_dir_all(staged_mod.join("plugins")).unwrap();
+        std::fs::write(staged_mod.join("plugins/Plugin.dll"), b"plugin 2").unwrap();
+        std::fs::write(staged_mod.join("README.md"), b"readme").unwrap();
+        let staged_patchers = staged.path().join("patchers");
+        std::fs::create_dir(&staged_patchers).unwrap();
+        std::fs::write(staged_patchers.join("Patcher.dll"), b"patcher 2").unwrap();
+
+        let mod_folder = profile.path().join("mods/Owner-Name");
+        let disabled = block_on(keep_disabled(
+            &log,
+            &mod_folder,
+            &staged_mod,
+            Some(&staged_patchers),
+        ))
+        .unwrap();
+        assert!(disabled);
+        std::fs::write(
+            staged_mod.join(MANIFEST_FILE_NAME),
+            serde_json::to_vec(&serde_json::json!({ "disabled": disabled })).unwrap(),
+        )
+        .unwrap();
+
+        // and applied
+        for (staged, target) in [
+            (&staged_mod, mod_folder),
+            (&staged_patchers, profile.path().join("patchers/Owner-Name")),
+        ] {
+            std::fs::remove_dir_all(&target).unwrap();
+            std::fs::rename(staged, &target).unwrap();
+        }
+        assert!(is_disabled(profile.path()));
+        let plugin = profile
+            .path()
+            .join("mods/Owner-Name/plugins/Plugin.dll.old");
+        assert_eq!(std::fs::read(plugin).unwrap(), b"plugin 2");
+    }
+
+    #[test]
+    fn test_apply_other_ops() {
+        let log = slog::Logger::root(slog::Discard, slog::o!());
+        let profile = profile();
+        let id = Uuid::new_v4();
+
+        block_on(apply_other_ops(
+            &log,
+            id,
+            profile.path(),
+            &[disable("Owner", "Name")],
+        ))
+        .unwrap();
+        assert!(is_disabled(profile.path()));
+
+        // the mod is enabled, but then disabling a missing mod fails, so it is disabled again
+        let ops = [
+            Op::Enable {
+                owner: "Owner".into(),
+                name: "Name".into(),
+            },
+            disable("Owner", "Missing"),
+        ];
+        assert!(block_on(apply_other_ops(&log, id, profile.path(), &ops)).is_err());
+        assert!(is_disabled(profile.path()));
+        let plugin = profile
+            .path()
+            .join("mods/Owner-Name/plugins/Plugin.dll.old");
+        assert_eq!(std::fs::read(plugin).unwrap(), b"plugin");
+
+        // nothing is left behind
+        for entry in std::fs::read_dir(profile.path()).unwrap() {
+            let name = entry.unwrap().file_name();
+            assert!(name == "mods" || name == "patchers", "{name:?}");
+        }
+    }
+}
@@ -1,12 +1,18 @@
-use anyhow::Result;
+use std::path::PathBuf;
+
+use anyhow::{Context as _, Result};
 use manderrow_types::mods::{ModMetadata, ModVersion};
 use smol_str::SmolStr;
-use tauri::{AppHandle, State};
+use tauri::ipc::{Channel, InvokeResponseBody};
+use tauri::{AppHandle, Manager as _};
 use uuid::Uuid;
 
-use crate::{tasks, CommandError, Reqwest};
+use crate::importing::commands::{finish_import, install_mods, ImportReport};
+use crate::mod_index::fetch_mod_index;
+use crate::tasks::{TaskBuilder, TaskError};
+use crate::{tasks, CommandError};
 
-use super::{Profile, ProfileWithId};
+use super::{LoaderVersion, Profile, ProfileWithId};
 
 #[tauri::command]
 pub async fn get_profiles() -> Result<Vec<ProfileWithId>, CommandError> {
@@ -15,11 +21,35 @@ pub async fn get_profiles() -> Result<Vec<ProfileWithId>, CommandError> {
 
 #[tauri::command]
 pub async fn create_profile(game: SmolStr, name: SmolStr) -> Result<Uuid, CommandError> {
+    super::read_only::ensure_writable()?;
     super::create_profile(game, name).await.map_err(Into::into)
 }
 
+/// Adds a shortcut that opens the profile to every Steam user's library, with the game's artwork.
+/// Returns the shortcut's app id.
+#[tauri::command]
+pub async fn create_profile_steam_shortcut(app: AppHandle, id: Uuid) -> Result<u32, CommandError> {
+    let profile = super::read_profile(id).await.map_err(anyhow::Error::from)?;
+    crate::stores::steam::shortcuts::create_profile_shortcut(
+        Some(&app),
+        &slog_scope::logger(),
+        id,
+        &profile,
+    )
+    .await
+    .map_err(Into::into)
+}
+
+/// Takes the profile that Manderrow was last opened with through a shortcut, if any, so that it
+/// is only opened once.
+#[tauri::command]
+pub fn take_requested_profile() -> Option<Uuid> {
+    crate::stores::steam::shortcuts::take_requested_profile()
+}
+
 #[tauri::command]
 pub async fn overwrite_profile_metadata(id: Uuid, metadata: Profile) -> Result<(), CommandError> {
+    super::read_only::ensure_writable()?;
     super::write_profile(id, &metadata)
         .await
         .map_err(anyhow::Error::from)
@@ -28,31 +58,412 @@ pub async fn overwrite_profile_metadata(id: Uuid, metadata: Profile) -> Result<(
 
 #[tauri::command]
 pub async fn delete_profile(id: Uuid) -> Result<(), CommandError> {
+    super::read_only::ensure_writable()?;
     super::delete_profile(id).await.map_err(Into::into)
 }
 
 #[tauri::command]
-pub async fn get_profile_mods(id: Uuid) -> Result<tauri::ipc::Response, CommandError> {
+pub async fn get_profile_mods(
+    app: AppHandle,
+    id: Uuid,
+) -> Result<tauri::ipc::Response, CommandError> {
+    if !super::read_only::is_read_only() {
+        let log = slog_scope::logger();
+        // linked mods are only watched while their profile is in use
+        if let Err(e) = super::dev_mods::watch_profile(&log, &app, id).await {
+            slog::warn!(log, "Failed to watch linked mods of profile {id}: {e:#}");
+        }
+    }
     super::get_profile_mods(id).await.map_err(Into::into)
 }
 
 #[tauri::command]
 pub async fn install_profile_mod(
     app: AppHandle,
-    reqwest: State<'_, Reqwest>,
     id: Uuid,
     r#mod: ModMetadata<'_>,
     version: ModVersion<'_>,
+    allow_overlaps: bool,
     task_id: tasks::Id,
-) -> Result<(), CommandError> {
-    super::install_profile_mod(&app, &*reqwest, id, r#mod, version, task_id)
+) -> Result<Vec<crate::compat::CompatWarning>, CommandError> {
+    super::read_only::ensure_writable()?;
+    super::install_profile_mod(&app, id, r#mod, version, allow_overlaps, task_id)
+        .await
+        .map_err(Into::into)
+}
+
+#[tauri::command]
+pub async fn reinstall_profile_mod(
+    app: AppHandle,
+    id: Uuid,
+    owner: &str,
+    name: &str,
+    force_download: bool,
+    task_id: tasks::Id,
+) -> Result<Vec<crate::compat::CompatWarning>, CommandError> {
+    super::read_only::ensure_writable()?;
+    super::reinstall_profile_mod(&app, id, owner, name, force_download, task_id)
+        .await
+        .map_err(Into::into)
+}
+
+/// Applies a list of install, update, uninstall, enable and disable operations to the profile's
+/// mods as one task. See [`super::batch`].
+#[tauri::command]
+pub async fn batch_profile_ops(
+    app: AppHandle,
+    id: Uuid,
+    ops: Vec<super::batch::Op>,
+    allow_overlaps: bool,
+    task_id: tasks::Id,
+) -> Result<Vec<crate::compat::CompatWarning>, CommandError> {
+    super::read_only::ensure_writable()?;
+    super::batch::apply(&app, id, &ops, allow_overlaps, task_id)
+        .await
+        .map_err(Into::into)
+}
+
+/// Lists the files that are shipped by more than one of the profile's mods, by mod.
+#[tauri::command]
+pub async fn get_profile_file_overlaps(
+    id: Uuid,
+) -> Result<super::overlaps::FileOverlaps, CommandError> {
+    super::overlaps::read_overlaps(id).await.map_err(Into::into)
+}
+
+/// Lists the BepInEx plugins in each of the profile's mods, and the plugin GUIDs that are shipped
+/// by more than one mod.
+#[tauri::command]
+pub async fn get_profile_plugins(id: Uuid) -> Result<super::plugins::ProfilePlugins, CommandError> {
+    let log = slog_scope::logger();
+    super::plugins::inspect_plugins(&log, id)
+        .await
+        .map_err(Into::into)
+}
+
+/// Returns the README or CHANGELOG stored in the folder of a mod installed in the profile, or
+/// `null` if there is none.
+#[tauri::command]
+pub async fn get_profile_mod_docs(
+    id: Uuid,
+    owner: &str,
+    name: &str,
+    endpoint: crate::mod_index::thunderstore::ModMarkdown,
+) -> Result<Option<String>, CommandError> {
+    super::docs::read_doc(id, owner, name, endpoint)
+        .await
+        .map_err(Into::into)
+}
+
+#[tauri::command]
+pub async fn get_profile_paths(id: Uuid) -> Result<super::paths::ProfilePaths, CommandError> {
+    let log = slog_scope::logger();
+    super::paths::get_paths(&log, id).await.map_err(Into::into)
+}
+
+/// Returns the folder of a mod installed in the profile.
+#[tauri::command]
+pub async fn get_profile_mod_path(
+    id: Uuid,
+    owner: &str,
+    name: &str,
+) -> Result<PathBuf, CommandError> {
+    super::paths::get_mod_path(id, owner, name)
+        .await
+        .map_err(Into::into)
+}
+
+/// Links the folder at `source`, like the output folder of a mod's build, into the profile as a
+/// mod that is copied again whenever the folder changes. Returns the mod's name, whose owner is
+/// [`super::dev_mods::DEV_OWNER`].
+#[tauri::command]
+pub async fn link_dev_mod(
+    app: AppHandle,
+    id: Uuid,
+    source: PathBuf,
+) -> Result<String, CommandError> {
+    super::read_only::ensure_writable()?;
+    let log = slog_scope::logger();
+    super::dev_mods::link(&log, &app, id, &source)
         .await
         .map_err(Into::into)
 }
 
 #[tauri::command]
 pub async fn uninstall_profile_mod(id: Uuid, owner: &str, name: &str) -> Result<(), CommandError> {
+    super::read_only::ensure_writable()?;
     super::uninstall_profile_mod(id, owner, name)
         .await
         .map_err(Into::into)
 }
+
+/// Finishes or rolls back the replacements of the mod's files that were interrupted by a failed
+/// installation. See [`super::repair`].
+#[tauri::command]
+pub async fn repair_profile_mod(
+    id: Uuid,
+    owner: &str,
+    name: &str,
+) -> Result<Vec<(super::repair::IncompleteReplacement, super::repair::Repair)>, CommandError> {
+    super::read_only::ensure_writable()?;
+    let r#mod = format!("{owner}-{name}");
+    super::repair::repair(&slog_scope::logger(), id, |r| r.r#mod == r#mod)
+        .await
+        .map_err(Into::into)
+}
+
+/// Lists the BepInEx releases that a profile's loader can be pinned to, newest first.
+#[tauri::command]
+pub async fn get_loader_versions() -> Result<Vec<String>, CommandError> {
+    crate::launching::list_bep_in_ex_releases()
+        .await
+        .map_err(Into::into)
+}
+
+#[tauri::command]
+pub async fn set_profile_loader_version(
+    id: Uuid,
+    version: LoaderVersion,
+) -> Result<(), CommandError> {
+    super::read_only::ensure_writable()?;
+    super::set_loader_version(id, version)
+        .await
+        .map_err(Into::into)
+}
+
+/// Runs `executable` from a tool package that is installed in the profile. See
+/// [`super::tools`].
+#[tauri::command]
+pub async fn run_profile_tool(
+    id: Uuid,
+    owner: &str,
+    name: &str,
+    executable: PathBuf,
+) -> Result<(), CommandError> {
+    // tools change the profile's files
+    super::read_only::ensure_writable()?;
+
+    let log = slog_scope::logger();
+    super::tools::run_tool(&log, id, owner, name, &executable)
+        .await
+        .map_err(Into::into)
+}
+
+/// Returns `true` if Manderrow was started with `--read-only`, in which case every command that
+/// changes a profile fails.
+#[tauri::command]
+pub fn is_read_only() -> bool {
+    super::read_only::is_read_only()
+}
+
+/// Returns the user's overrides for the profile's config files.
+#[tauri::command]
+pub async fn get_profile_config_overrides(
+    id: Uuid,
+) -> Result<super::configs::ConfigOverrides, CommandError> {
+    super::configs::read_overrides(id).await.map_err(Into::into)
+}
+
+/// Sets an override for a value in one of the profile's config files, or removes it if `value`
+/// is `null`. Overrides are applied when Manderrow next writes the file.
+#[tauri::command]
+pub async fn set_profile_config_override(
+    id: Uuid,
+    file: String,
+    section: String,
+    key: String,
+    value: Option<String>,
+) -> Result<(), CommandError> {
+    super::read_only::ensure_writable()?;
+    super::configs::set_override(id, file, section, key, value)
+        .await
+        .map_err(Into::into)
+}
+
+/// Applies `patches` to one of the profile's JSON config files, leaving the rest of the file,
+/// including its formatting, as it was.
+#[tauri::command]
+pub async fn update_profile_config(
+    id: Uuid,
+    file: String,
+    patches: Vec<super::configs::json::Patch>,
+) -> Result<(), CommandError> {
+    super::read_only::ensure_writable()?;
+    super::configs::update_config(id, &file, &patches)
+        .await
+        .map_err(Into::into)
+}
+
+/// Finds the values in the profile's config files whose section, key, or value contains `query`,
+/// ignoring case.
+#[tauri::command]
+pub async fn search_profile_configs(
+    id: Uuid,
+    query: String,
+) -> Result<Vec<super::configs::search::ConfigMatch>, CommandError> {
+    let log = slog_scope::logger();
+    super::configs::search::search_configs(&log, id, &query)
+        .await
+        .map_err(Into::into)
+}
+
+/// Lists the snapshots of the profile's config folder, newest first.
+#[tauri::command]
+pub async fn get_profile_config_snapshots(
+    id: Uuid,
+) -> Result<Vec<super::configs::backup::Snapshot>, CommandError> {
+    super::configs::backup::list_snapshots(id)
+        .await
+        .map_err(Into::into)
+}
+
+/// Compares a snapshot of the profile's config folder with its current state.
+#[tauri::command]
+pub async fn diff_profile_config_snapshot(
+    id: Uuid,
+    snapshot: &str,
+) -> Result<Vec<super::configs::backup::FileDiff>, CommandError> {
+    super::configs::backup::diff_snapshot(id, snapshot)
+        .await
+        .map_err(Into::into)
+}
+
+/// Replaces the profile's config folder with a snapshot of it, after taking a snapshot of its
+/// current state.
+#[tauri::command]
+pub async fn restore_profile_config_snapshot(id: Uuid, snapshot: &str) -> Result<(), CommandError> {
+    super::read_only::ensure_writable()?;
+    let log = slog_scope::logger();
+    super::configs::backup::restore_snapshot(&log, id, snapshot)
+        .await
+        .map_err(Into::into)
+}
+
+#[derive(serde::Serialize)]
+pub struct ExportedProfile {
+    /// Where the archive was written, if a destination was given.
+    pub path: Option<PathBuf>,
+    /// The Thunderstore profile code, if the profile was uploaded.
+    pub code: Option<Uuid>,
+}
+
+/// Exports the profile in the r2modman format, writing the archive to `destination` and/or
+/// uploading it to Thunderstore to get a code that others can import it with.
+#[tauri::command]
+pub async fn export_profile(
+    id: Uuid,
+    destination: Option<PathBuf>,
+    upload: bool,
+) -> Result<ExportedProfile, CommandError> {
+    let log = slog_scope::logger();
+
+    let archive = crate::exporting::thunderstore::build_profile_archive(&log, id).await?;
+
+    if let Some(destination) = &destination {
+        tokio::fs::write(destination, &archive)
+            .await
+            .with_context(|| format!("Failed to write profile archive to {destination:?}"))?;
+    }
+
+    let code = if upload {
+        Some(crate::exporting::thunderstore::upload_profile(&log, &archive).await?)
+    } else {
+        None
+    };
+
+    Ok(ExportedProfile {
+        path: destination,
+        code,
+    })
+}
+
+/// Lists the mods installed in the profile, with their versions and links to them, as Markdown,
+/// CSV, or plain text.
+#[tauri::command]
+pub async fn export_mod_list(
+    id: Uuid,
+    format: crate::exporting::mod_list::ModListFormat,
+) -> Result<String, CommandError> {
+    crate::exporting::mod_list::export_mod_list(id, format)
+        .await
+        .map_err(Into::into)
+}
+
+/// Writes the profile to `destination` as a portable archive, containing its metadata, the
+/// versions of its mods, and its config files, but not the mods themselves.
+#[tauri::command]
+pub async fn export_profile_to_file(id: Uuid, destination: PathBuf) -> Result<(), CommandError> {
+    let log = slog_scope::logger();
+    let archive = super::portable::build_archive(&log, id).await?;
+    tokio::fs::write(&destination, &archive)
+        .await
+        .with_context(|| format!("Failed to write profile archive to {destination:?}"))?;
+    Ok(())
+}
+
+/// Creates a new profile from the portable archive at `path`, installing its mods and copying its
+/// config files.
+#[tauri::command]
+pub async fn import_profile_from_file(
+    app: AppHandle,
+    path: PathBuf,
+    // ModProgressRegistration, but can't express the lifetime
+    mod_progress_channel: Channel<InvokeResponseBody>,
+    task_id: tasks::Id,
+) -> Result<ImportReport, CommandError> {
+    super::read_only::ensure_writable()?;
+
+    let app = &app;
+    let log = slog_scope::logger();
+
+    let bytes = tokio::fs::read(&path)
+        .await
+        .with_context(|| format!("Failed to read {path:?}"))?;
+    let archive = tokio::task::block_in_place(|| super::portable::read_archive(&bytes))?;
+
+    TaskBuilder::with_id(task_id, format!("Import profile {}", archive.profile.name))
+        .kind(tasks::Kind::Aggregate)
+        .progress_unit(tasks::ProgressUnit::Bytes)
+        .run_with_handle(Some(app), |handle| async move {
+            let game = &*archive.profile.game;
+
+            fetch_mod_index(
+                Some(app),
+                &app.state(),
+                game,
+                false,
+                Some(handle.allocate_dependency(app)?),
+            )
+            .await?;
+
+            let profile_id =
+                super::create_profile(game.into(), archive.profile.name.clone()).await?;
+
+            let result = async {
+                let mut metadata = super::read_profile(profile_id).await?;
+                metadata.launch = archive.profile.launch.clone();
+                super::write_profile(profile_id, &metadata).await?;
+                tokio::task::block_in_place(|| archive.write_configs(profile_id))
+            }
+            .await;
+            if let Err(e) = result {
+                super::delete_profile(profile_id).await?;
+                return Err(e.context("Failed to restore profile settings"));
+            }
+
+            let mods = install_mods(
+                app,
+                game,
+                &archive.mods,
+                profile_id,
+                &mod_progress_channel,
+                handle,
+            )
+            .await;
+
+            let report = finish_import(&log, game, profile_id, mods, None).await?;
+            Ok((None, report))
+        })
+        .await
+        .map_err(|e: TaskError<anyhow::Error>| anyhow::Error::from(e).into())
+}
@@ -0,0 +1,412 @@
+//! Snapshots of a profile's config folder, so that changes made by installing mods or by writing
+//! config files can be undone.
+//!
+//! Each snapshot is a gzipped tarball of the whole [`CONFIG_FOLDER`], stored under
+//! [`SNAPSHOTS_FOLDER`] in the profile directory and named after when and why it was taken. Only
+//! the latest [`MAX_SNAPSHOTS`] are kept.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read as _};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{anyhow, Context as _, Result};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use slog::debug;
+use uuid::Uuid;
+
+use crate::util::IoErrorKindExt as _;
+
+use super::super::{profile_path, CONFIG_FOLDER};
+use super::{entry_name, parse_key, parse_section_header};
+
+pub const SNAPSHOTS_FOLDER: &str = "config_snapshots";
+
+const SNAPSHOT_EXTENSION: &str = ".tar.gz";
+
+/// How many snapshots are kept per profile. The oldest are deleted when a new one is taken.
+const MAX_SNAPSHOTS: usize = 20;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SnapshotReason {
+    /// Taken before mods were installed or updated.
+    ModInstall,
+    /// Taken before Manderrow wrote to a config file.
+    ConfigEdit,
+    /// Taken before another snapshot was restored, so that restoring can be undone.
+    Restore,
+}
+
+impl SnapshotReason {
+    const VALUES: &[Self] = &[Self::ModInstall, Self::ConfigEdit, Self::Restore];
+
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::ModInstall => "mod_install",
+            Self::ConfigEdit => "config_edit",
+            Self::Restore => "restore",
+        }
+    }
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct Snapshot {
+    /// Identifies the snapshot within its profile.
+    pub id: String,
+    /// When the snapshot was taken, in milliseconds since the Unix epoch.
+    pub created_at: u64,
+    pub reason: SnapshotReason,
+}
+
+impl Snapshot {
+    /// Parses the file name of a snapshot, which is of the form `{created_at}-{reason}.tar.gz`.
+    fn from_file_name(file_name: &str) -> Option<Self> {
+        let id = file_name.strip_suffix(SNAPSHOT_EXTENSION)?;
+        let (created_at, reason) = id.split_once('-')?;
+        Some(Self {
+            id: id.to_owned(),
+            created_at: created_at.parse().ok()?,
+            reason: *SnapshotReason::VALUES
+                .iter()
+                .find(|r| r.as_str() == reason)?,
+        })
+    }
+
+    fn file_name(&self) -> String {
+        format!("{}{SNAPSHOT_EXTENSION}", self.id)
+    }
+}
+
+fn snapshots_path(id: Uuid) -> PathBuf {
+    profile_path(id).join(SNAPSHOTS_FOLDER)
+}
+
+/// Lists the profile's snapshots, newest first.
+pub async fn list_snapshots(id: Uuid) -> Result<Vec<Snapshot>> {
+    let path = snapshots_path(id);
+    let mut iter = match tokio::fs::read_dir(&path).await {
+        Ok(t) => t,
+        Err(e) if e.is_not_found() => return Ok(Vec::new()),
+        Err(e) => return Err(e).with_context(|| format!("Failed to read {path:?}")),
+    };
+    let mut snapshots = Vec::new();
+    while let Some(e) = iter.next_entry().await? {
+        if let Some(snapshot) = e.file_name().to_str().and_then(Snapshot::from_file_name) {
+            snapshots.push(snapshot);
+        }
+    }
+    snapshots.sort_by(|a, b| b.created_at.cmp(&a.created_at).then(b.id.cmp(&a.id)));
+    Ok(snapshots)
+}
+
+async fn find_snapshot(id: Uuid, snapshot: &str) -> Result<Snapshot> {
+    list_snapshots(id)
+        .await?
+        .into_iter()
+        .find(|s| s.id == snapshot)
+        .ok_or_else(|| anyhow!("No config snapshot {snapshot:?} in profile {id}"))
+}
+
+/// Takes a snapshot of the profile's config folder. Returns `None` if the profile has no config
+/// folder, as there is nothing to snapshot.
+pub async fn take_snapshot(
+    log: &slog::Logger,
+    id: Uuid,
+    reason: SnapshotReason,
+) -> Result<Option<Snapshot>> {
+    let config_path = profile_path(id).join(CONFIG_FOLDER);
+    if !tokio::fs::try_exists(&config_path).await? {
+        return Ok(None);
+    }
+
+    let created_at = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .context("System clock is set before the Unix epoch")?
+        .as_millis() as u64;
+    let snapshot = Snapshot {
+        id: format!("{created_at}-{}", reason.as_str()),
+        created_at,
+        reason,
+    };
+
+    let snapshots_path = snapshots_path(id);
+    tokio::fs::create_dir_all(&snapshots_path).await?;
+    let path = snapshots_path.join(snapshot.file_name());
+    tokio::task::block_in_place(|| {
+        let temp_file = tempfile::NamedTempFile::new_in(&snapshots_path)?;
+        let mut tar = tar::Builder::new(GzEncoder::new(
+            BufWriter::new(temp_file),
+            flate2::Compression::default(),
+        ));
+        tar.follow_symlinks(false);
+        tar.append_dir_all("", &config_path)
+            .with_context(|| format!("Failed to archive {config_path:?}"))?;
+        let temp_file = tar
+            .into_inner()?
+            .finish()?
+            .into_inner()
+            .map_err(|e| e.into_error())?;
+        temp_file
+            .persist(&path)
+            .with_context(|| format!("Failed to write snapshot to {path:?}"))?;
+        Ok::<_, anyhow::Error>(())
+    })?;
+    debug!(
+        log,
+        "Took config snapshot {:?} of profile {id}", snapshot.id
+    );
+
+    for old in list_snapshots(id).await?.into_iter().skip(MAX_SNAPSHOTS) {
+        let path = snapshots_path.join(old.file_name());
+        tokio::fs::remove_file(&path)
+            .await
+            .with_context(|| format!("Failed to remove old snapshot {path:?}"))?;
+    }
+
+    Ok(Some(snapshot))
+}
+
+/// Reads the files in a snapshot, by path relative to the config folder.
+fn read_snapshot_files(path: &Path) -> Result<BTreeMap<String, Vec<u8>>> {
+    let mut tar = tar::Archive::new(GzDecoder::new(BufReader::new(
+        File::open(path).with_context(|| format!("Failed to open {path:?}"))?,
+    )));
+    let mut files = BTreeMap::new();
+    for entry in tar.entries()? {
+        let mut entry = entry?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+        let name = entry_name(&entry.path()?)?;
+        let mut buf = Vec::new();
+        entry.read_to_end(&mut buf)?;
+        files.insert(name, buf);
+    }
+    Ok(files)
+}
+
+/// Reads the files in the config folder at `path`, by path relative to it.
+fn read_config_files(path: &Path) -> Result<BTreeMap<String, Vec<u8>>> {
+    let mut files = BTreeMap::new();
+    if !path.exists() {
+        return Ok(files);
+    }
+    for e in walkdir::WalkDir::new(path) {
+        let e = e?;
+        if !e.file_type().is_file() {
+            continue;
+        }
+        let name = entry_name(e.path().strip_prefix(path)?)?;
+        files.insert(name, std::fs::read(e.path())?);
+    }
+    Ok(files)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+pub enum FileStatus {
+    /// The file is in the snapshot, but has since been deleted.
+    Deleted,
+    /// The file has been created since the snapshot was taken.
+    Created,
+    Modified,
+}
+
+/// A value that differs between a snapshot and the current state of an INI-style config file.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct ValueChange {
+    pub section: String,
+    pub key: String,
+    /// The value in the snapshot, or `None` if it wasn't set.
+    pub snapshot: Option<String>,
+    /// The current value, or `None` if it isn't set.
+    pub current: Option<String>,
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct FileDiff {
+    /// The path of the file, relative to the config folder.
+    pub file: String,
+    pub status: FileStatus,
+    /// The values that changed, if both versions of the file are INI-style configs.
+    pub values: Vec<ValueChange>,
+}
+
+/// Reads every value in the INI-style config `text`, by key, by section.
+fn ini_values(text: &str) -> BTreeMap<(&str, &str), &str> {
+    let mut values = BTreeMap::new();
+    let mut current = "";
+    for line in text.lines() {
+        if let Some(header) = parse_section_header(line) {
+            current = header;
+        } else if let Some(key) = parse_key(line) {
+            let value = line.split_once('=').map_or("", |(_, value)| value.trim());
+            values.insert((current, key), value);
+        }
+    }
+    values
+}
+
+fn diff_values(snapshot: &str, current: &str) -> Vec<ValueChange> {
+    let snapshot = ini_values(snapshot);
+    let current = ini_values(current);
+    snapshot
+        .keys()
+        .chain(current.keys())
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .filter_map(|k @ &(section, key)| {
+            let old = snapshot.get(k);
+            let new = current.get(k);
+            (old != new).then(|| ValueChange {
+                section: section.to_owned(),
+                key: key.to_owned(),
+                snapshot: old.map(|s| (*s).to_owned()),
+                current: new.map(|s| (*s).to_owned()),
+            })
+        })
+        .collect()
+}
+
+fn diff_files(
+    snapshot: &BTreeMap<String, Vec<u8>>,
+    current: &BTreeMap<String, Vec<u8>>,
+) -> Vec<FileDiff> {
+    snapshot
+        .keys()
+        .chain(current.keys())
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .filter_map(|file| {
+            let (status, values) = match (snapshot.get(file), current.get(file)) {
+                (Some(old), Some(new)) if old == new => return None,
+                (Some(old), Some(new)) => {
+                    let values = match (std::str::from_utf8(old), std::str::from_utf8(new)) {
+                        (Ok(old), Ok(new)) if file.ends_with(".cfg") => diff_values(old, new),
+                        _ => Vec::new(),
+                    };
+                    (FileStatus::Modified, values)
+                }
+                (Some(_), None) => (FileStatus::Deleted, Vec::new()),
+                (None, _) => (FileStatus::Created, Vec::new()),
+            };
+            Some(FileDiff {
+                file: file.clone(),
+                status,
+                values,
+            })
+        })
+        .collect()
+}
+
+/// Compares a snapshot with the current state of the profile's config folder.
+pub async fn diff_snapshot(id: Uuid, snapshot: &str) -> Result<Vec<FileDiff>> {
+    let snapshot = find_snapshot(id, snapshot).await?;
+    let path = snapshots_path(id).join(snapshot.file_name());
+    let config_path = profile_path(id).join(CONFIG_FOLDER);
+    tokio::task::block_in_place(|| {
+        let snapshot = read_snapshot_files(&path)?;
+        let current = read_config_files(&config_path)?;
+        Ok(diff_files(&snapshot, &current))
+    })
+}
+
+/// Replaces the profile's config folder with the contents of a snapshot. A snapshot of the
+/// current state is taken first.
+pub async fn restore_snapshot(log: &slog::Logger, id: Uuid, snapshot: &str) -> Result<()> {
+    let snapshot = find_snapshot(id, snapshot).await?;
+    let path = snapshots_path(id).join(snapshot.file_name());
+
+    take_snapshot(log, id, SnapshotReason::Restore).await?;
+
+    let profile_path = profile_path(id);
+    let config_path = profile_path.join(CONFIG_FOLDER);
+    tokio::task::block_in_place(|| {
+        // unpack beside the config folder first, so that a broken snapshot leaves it untouched
+        let temp_dir = tempfile::tempdir_in(&profile_path)?;
+        let mut tar = tar::Archive::new(GzDecoder::new(BufReader::new(
+            File::open(&path).with_context(|| format!("Failed to open {path:?}"))?,
+        )));
+        tar.unpack(temp_dir.path())
+            .with_context(|| format!("Failed to unpack snapshot {path:?}"))?;
+
+        match std::fs::remove_dir_all(&config_path) {
+            Ok(()) => {}
+            Err(e) if e.is_not_found() => {}
+            Err(e) => return Err(e).with_context(|| format!("Failed to remove {config_path:?}")),
+        }
+        std::fs::rename(temp_dir.path(), &config_path)
+            .with_context(|| format!("Failed to move snapshot into {config_path:?}"))?;
+        Ok::<_, anyhow::Error>(())
+    })?;
+    debug!(
+        log,
+        "Restored config snapshot {:?} of profile {id}", snapshot.id
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snapshot_file_name() {
+        let snapshot = Snapshot::from_file_name("1700000000000-config_edit.tar.gz").unwrap();
+        assert_eq!(snapshot.created_at, 1700000000000);
+        assert_eq!(snapshot.reason, SnapshotReason::ConfigEdit);
+        assert_eq!(snapshot.file_name(), "1700000000000-config_edit.tar.gz");
+        assert!(Snapshot::from_file_name("1700000000000-unknown.tar.gz").is_none());
+        assert!(Snapshot::from_file_name("../1700000000000-restore.tar.gz").is_none());
+    }
+
+    #[test]
+    fn test_diff_files() {
+        let snapshot = BTreeMap::from([
+            (
+                "BepInEx.cfg".to_owned(),
+                b"[Logging]\nEnabled = true\nLevel = Info\n".to_vec(),
+            ),
+            ("removed.cfg".to_owned(), b"".to_vec()),
+            ("same.txt".to_owned(), b"same".to_vec()),
+        ]);
+        let current = BTreeMap::from([
+            (
+                "BepInEx.cfg".to_owned(),
+                b"[Logging]\nEnabled = false\n\n[Console]\nEnabled = true\n".to_vec(),
+            ),
+            ("added.cfg".to_owned(), b"".to_vec()),
+            ("same.txt".to_owned(), b"same".to_vec()),
+        ]);
+        let diff = diff_files(&snapshot, &current);
+        let statuses = diff
+            .iter()
+            .map(|d| (d.file.as_str(), d.status))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            statuses,
+            [
+                ("BepInEx.cfg", FileStatus::Modified),
+                ("added.cfg", FileStatus::Created),
+                ("removed.cfg", FileStatus::Deleted),
+            ]
+        );
+        let change =
+            |section: &str, key: &str, snapshot: Option<&str>, current: Option<&str>| ValueChange {
+                section: section.to_owned(),
+                key: key.to_owned(),
+                snapshot: snapshot.map(str::to_owned),
+                current: current.map(str::to_owned),
+            };
+        assert_eq!(
+            diff[0].values,
+            [
+                change("Console", "Enabled", None, Some("true")),
+                change("Logging", "Enabled", Some("true"), Some("false")),
+                change("Logging", "Level", Some("Info"), None),
+            ]
+        );
+    }
+}
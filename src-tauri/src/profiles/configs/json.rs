@@ -0,0 +1,723 @@
+//! Lossless editing of JSON config files.
+//!
+//! Mods that store their options as JSON expect them to stay editable by hand, so instead of
+//! reserializing the whole document, each change is spliced into the original text. Key order,
+//! whitespace, and the formatting of untouched values are kept as they were.
+//!
+//! [`Format::Jsonc`] files are parsed tolerantly, allowing comments, trailing commas, and JSON5's
+//! unquoted keys. Comments are kept when the file is edited, except for those inside removed
+//! values.
+
+use std::ops::Range;
+
+use anyhow::{bail, Context as _, Result};
+use serde_json::Value;
+
+use super::Format;
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Deserialize)]
+#[serde(untagged)]
+pub enum PathSegment {
+    Key(String),
+    Index(usize),
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Deserialize)]
+#[serde(tag = "type", content = "value", rename_all = "snake_case")]
+pub enum Change {
+    /// Sets the value, creating any objects missing along the path.
+    Set(Value),
+    /// Appends to the array, creating it if it is missing.
+    Append(Value),
+    /// Removes the value if it is present.
+    Remove,
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Deserialize)]
+pub struct Patch {
+    pub path: Vec<PathSegment>,
+    pub change: Change,
+}
+
+#[derive(Debug)]
+enum Node {
+    Object {
+        span: Range<usize>,
+        members: Vec<Member>,
+    },
+    Array {
+        span: Range<usize>,
+        items: Vec<Node>,
+    },
+    Scalar {
+        span: Range<usize>,
+    },
+}
+
+#[derive(Debug)]
+struct Member {
+    key: String,
+    key_span: Range<usize>,
+    value: Node,
+}
+
+impl Node {
+    fn span(&self) -> Range<usize> {
+        match self {
+            Node::Object { span, .. } | Node::Array { span, .. } | Node::Scalar { span } => {
+                span.clone()
+            }
+        }
+    }
+}
+
+impl Node {
+    fn to_value(&self, text: &str) -> Result<Value> {
+        Ok(match self {
+            Node::Object { members, .. } => Value::Object(
+                members
+                    .iter()
+                    .map(|m| Ok((m.key.clone(), m.value.to_value(text)?)))
+                    .collect::<Result<_>>()?,
+            ),
+            Node::Array { items, .. } => Value::Array(
+                items
+                    .iter()
+                    .map(|item| item.to_value(text))
+                    .collect::<Result<_>>()?,
+            ),
+            Node::Scalar { span } => serde_json::from_str(&text[span.clone()])?,
+        })
+    }
+}
+
+impl Member {
+    fn span(&self) -> Range<usize> {
+        self.key_span.start..self.value.span().end
+    }
+}
+
+struct Parser<'a> {
+    text: &'a str,
+    pos: usize,
+    /// Whether to accept the extensions of [`Format::Jsonc`].
+    tolerant: bool,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<u8> {
+        self.text.as_bytes().get(self.pos).copied()
+    }
+
+    fn next(&mut self) -> Option<u8> {
+        let b = self.peek()?;
+        self.pos += 1;
+        Some(b)
+    }
+
+    /// Skips whitespace, and comments if tolerant.
+    fn skip_ws(&mut self) -> Result<()> {
+        loop {
+            match self.peek() {
+                Some(b' ' | b'\t' | b'\n' | b'\r') => self.pos += 1,
+                Some(b'/') if self.tolerant => {
+                    let rest = &self.text[self.pos..];
+                    if rest.starts_with("//") {
+                        self.pos += rest.find('\n').unwrap_or(rest.len());
+                    } else if rest.starts_with("/*") {
+                        let Some(end) = rest[2..].find("*/") else {
+                            bail!("Unterminated comment at byte {}", self.pos);
+                        };
+                        self.pos += end + 4;
+                    } else {
+                        return Ok(());
+                    }
+                }
+                _ => return Ok(()),
+            }
+        }
+    }
+
+    fn expect(&mut self, expected: u8) -> Result<()> {
+        match self.next() {
+            Some(b) if b == expected => Ok(()),
+            _ => bail!(
+                "Expected {:?} at byte {}",
+                char::from(expected),
+                self.pos.saturating_sub(1)
+            ),
+        }
+    }
+
+    fn parse_string(&mut self) -> Result<Range<usize>> {
+        let start = self.pos;
+        self.expect(b'"')?;
+        loop {
+            match self.next() {
+                Some(b'"') => break,
+                Some(b'\\') => self.pos += 1,
+                Some(_) => {}
+                None => bail!("Unterminated string at byte {start}"),
+            }
+        }
+        let span = start..self.pos;
+        serde_json::from_str::<String>(&self.text[span.clone()])
+            .with_context(|| format!("Invalid string at byte {start}"))?;
+        Ok(span)
+    }
+
+    fn parse_key(&mut self) -> Result<(String, Range<usize>)> {
+        let start = self.pos;
+        let is_ident_start = |b: u8| b.is_ascii_alphabetic() || b == b'_' || b == b'$';
+        if self.tolerant && self.peek().is_some_and(is_ident_start) {
+            while self
+                .peek()
+                .is_some_and(|b| is_ident_start(b) || b.is_ascii_digit())
+            {
+                self.pos += 1;
+            }
+            return Ok((self.text[start..self.pos].to_owned(), start..self.pos));
+        }
+        let span = self.parse_string()?;
+        Ok((serde_json::from_str(&self.text[span.clone()])?, span))
+    }
+
+    /// Consumes the `}` or `]` closing a container if it follows a comma, as a trailing comma.
+    fn trailing_comma(&mut self, close: u8) -> Result<bool> {
+        if !self.tolerant {
+            return Ok(false);
+        }
+        self.skip_ws()?;
+        if self.peek() == Some(close) {
+            self.pos += 1;
+            return Ok(true);
+        }
+        Ok(false)
+    }
+
+    fn parse_value(&mut self) -> Result<Node> {
+        self.skip_ws()?;
+        let start = self.pos;
+        match self.peek() {
+            Some(b'{') => {
+                self.pos += 1;
+                let mut members = Vec::new();
+                self.skip_ws()?;
+                if self.peek() == Some(b'}') {
+                    self.pos += 1;
+                } else {
+                    loop {
+                        self.skip_ws()?;
+                        let (key, key_span) = self.parse_key()?;
+                        self.skip_ws()?;
+                        self.expect(b':')?;
+                        let value = self.parse_value()?;
+                        members.push(Member {
+                            key,
+                            key_span,
+                            value,
+                        });
+                        self.skip_ws()?;
+                        match self.next() {
+                            Some(b',') if self.trailing_comma(b'}')? => break,
+                            Some(b',') => {}
+                            Some(b'}') => break,
+                            _ => bail!("Expected ',' or '}}' at byte {}", self.pos - 1),
+                        }
+                    }
+                }
+                Ok(Node::Object {
+                    span: start..self.pos,
+                    members,
+                })
+            }
+            Some(b'[') => {
+                self.pos += 1;
+                let mut items = Vec::new();
+                self.skip_ws()?;
+                if self.peek() == Some(b']') {
+                    self.pos += 1;
+                } else {
+                    loop {
+                        items.push(self.parse_value()?);
+                        self.skip_ws()?;
+                        match self.next() {
+                            Some(b',') if self.trailing_comma(b']')? => break,
+                            Some(b',') => {}
+                            Some(b']') => break,
+                            _ => bail!("Expected ',' or ']' at byte {}", self.pos - 1),
+                        }
+                    }
+                }
+                Ok(Node::Array {
+                    span: start..self.pos,
+                    items,
+                })
+            }
+            Some(b'"') => Ok(Node::Scalar {
+                span: self.parse_string()?,
+            }),
+            Some(_) => {
+                while self.peek().is_some_and(|b| {
+                    !matches!(b, b',' | b']' | b'}' | b'/' | b' ' | b'\t' | b'\n' | b'\r')
+                }) {
+                    self.pos += 1;
+                }
+                let span = start..self.pos;
+                serde_json::from_str::<Value>(&self.text[span.clone()])
+                    .with_context(|| format!("Invalid value at byte {start}"))?;
+                Ok(Node::Scalar { span })
+            }
+            None => bail!("Unexpected end of JSON"),
+        }
+    }
+}
+
+fn parse(text: &str, tolerant: bool) -> Result<Node> {
+    let mut parser = Parser {
+        text,
+        pos: 0,
+        tolerant,
+    };
+    let root = parser.parse_value()?;
+    parser.skip_ws()?;
+    if parser.pos != text.len() {
+        bail!("Unexpected trailing characters at byte {}", parser.pos);
+    }
+    Ok(root)
+}
+
+/// How the existing document is formatted, so that inserted values fit in.
+struct Style<'a> {
+    multiline: bool,
+    indent: &'a str,
+}
+
+impl<'a> Style<'a> {
+    fn detect(text: &'a str) -> Self {
+        let indent = text
+            .lines()
+            .skip(1)
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| &line[..line.len() - line.trim_start().len()])
+            .find(|indent| !indent.is_empty())
+            .unwrap_or("  ");
+        Self {
+            multiline: text.contains('\n'),
+            indent,
+        }
+    }
+
+    /// Serializes `value` for insertion on a line indented by `line_indent`.
+    fn render(&self, value: &Value, line_indent: &str) -> Result<String> {
+        if !self.multiline {
+            return Ok(serde_json::to_string(value)?);
+        }
+        let mut buf = Vec::new();
+        let formatter = serde_json::ser::PrettyFormatter::with_indent(self.indent.as_bytes());
+        serde::Serialize::serialize(
+            value,
+            &mut serde_json::Serializer::with_formatter(&mut buf, formatter),
+        )?;
+        Ok(String::from_utf8(buf)?.replace('\n', &format!("\n{line_indent}")))
+    }
+}
+
+/// Returns the whitespace at the start of the line that contains `pos`.
+fn line_indent(text: &str, pos: usize) -> &str {
+    let line_start = text[..pos].rfind('\n').map_or(0, |i| i + 1);
+    let line = &text[line_start..];
+    &line[..line.len() - line.trim_start().len()]
+}
+
+/// A replacement of the text in a range.
+type Edit = (Range<usize>, String);
+
+/// Inserts an entry after the last of `entries` in the container at `span`. `render` is given the
+/// indentation of the entry's line.
+fn insert_entry(
+    text: &str,
+    style: &Style,
+    span: Range<usize>,
+    entries: &[Range<usize>],
+    render: impl FnOnce(&str) -> Result<String>,
+) -> Result<Edit> {
+    let (Some(first), Some(last)) = (entries.first(), entries.last()) else {
+        // keep any comments in the container
+        let inner =
+            span.start + 1 + text[span.start + 1..span.end - 1].trim_end().len()..span.end - 1;
+        if !style.multiline {
+            return Ok((inner, render("")?));
+        }
+        let outer = line_indent(text, span.start);
+        let indent = format!("{outer}{}", style.indent);
+        return Ok((inner, format!("\n{indent}{}\n{outer}", render(&indent)?)));
+    };
+    // copy the whitespace that separates the existing entries
+    let between = match entries.get(1) {
+        Some(second) => &text[first.end..second.start],
+        None => &text[span.start + 1..first.start],
+    };
+    let sep = &between[between.trim_end().len()..];
+    let indent = sep.rfind('\n').map_or("", |i| &sep[i + 1..]);
+    Ok((last.end..last.end, format!(",{sep}{}", render(indent)?)))
+}
+
+/// Removes the entry at `index` of `entries` in the container at `span`, along with the comma
+/// that separates it from its neighbours.
+fn remove_entry(
+    text: &str,
+    tolerant: bool,
+    span: Range<usize>,
+    entries: &[Range<usize>],
+    index: usize,
+) -> Result<Edit> {
+    let entry = &entries[index];
+    let range = if index + 1 < entries.len() {
+        // remove up to the next entry, but not the comments before it
+        let mut parser = Parser {
+            text,
+            pos: entry.end,
+            tolerant,
+        };
+        parser.skip_ws()?;
+        parser.expect(b',')?;
+        let rest = &text[parser.pos..];
+        entry.start..parser.pos + (rest.len() - rest.trim_start().len())
+    } else if index > 0 {
+        entries[index - 1].end..entry.end
+    } else {
+        let inner = span.start + 1..span.end - 1;
+        let start = text[..entry.start].trim_end().len().max(inner.start);
+        let rest = &text[entry.end..];
+        let end = match rest.trim_start().strip_prefix(',') {
+            Some(after) => text.len() - after.len(),
+            None => entry.end,
+        };
+        if text[inner.start..start].trim().is_empty() && text[end..inner.end].trim().is_empty() {
+            inner
+        } else {
+            start..end
+        }
+    };
+    Ok((range, String::new()))
+}
+
+/// Follows `path` from `node` as far as it exists, returning the deepest node reached and the
+/// number of segments that were followed.
+fn follow<'n>(mut node: &'n Node, path: &[PathSegment]) -> Result<(&'n Node, usize)> {
+    for (i, segment) in path.iter().enumerate() {
+        let child = match (node, segment) {
+            (Node::Object { members, .. }, PathSegment::Key(key)) => {
+                members.iter().find(|m| m.key == *key).map(|m| &m.value)
+            }
+            (Node::Array { items, .. }, PathSegment::Index(index)) => items.get(*index),
+            _ => bail!("Cannot follow {segment:?} at {:?}", &path[..i]),
+        };
+        match child {
+            Some(child) => node = child,
+            None => return Ok((node, i)),
+        }
+    }
+    Ok((node, path.len()))
+}
+
+/// Inserts `value` at `path`, where `parent` is the deepest existing node along it and `rest` are
+/// the segments that don't exist yet.
+fn insert_value(
+    text: &str,
+    style: &Style,
+    parent: &Node,
+    rest: &[PathSegment],
+    value: &Value,
+) -> Result<Edit> {
+    let mut value = value.clone();
+    for segment in rest[1..].iter().rev() {
+        match segment {
+            PathSegment::Key(key) => {
+                value = Value::Object(serde_json::Map::from_iter([(key.clone(), value)]))
+            }
+            PathSegment::Index(_) => bail!("Cannot create array element {segment:?}"),
+        }
+    }
+    match (parent, &rest[0]) {
+        (Node::Object { span, members }, PathSegment::Key(key)) => {
+            let colon = match members.first() {
+                Some(m) => &text[m.key_span.end..m.value.span().start],
+                None if style.multiline => ": ",
+                None => ":",
+            };
+            let entries = members.iter().map(Member::span).collect::<Vec<_>>();
+            insert_entry(text, style, span.clone(), &entries, |indent| {
+                Ok(format!(
+                    "{}{colon}{}",
+                    serde_json::to_string(key)?,
+                    style.render(&value, indent)?
+                ))
+            })
+        }
+        (Node::Array { span, items }, PathSegment::Index(index)) if *index == items.len() => {
+            let entries = items.iter().map(Node::span).collect::<Vec<_>>();
+            insert_entry(text, style, span.clone(), &entries, |indent| {
+                style.render(&value, indent)
+            })
+        }
+        (_, segment) => bail!("Cannot create {segment:?}"),
+    }
+}
+
+fn patch_edit(text: &str, tolerant: bool, root: &Node, patch: &Patch) -> Result<Option<Edit>> {
+    let style = Style::detect(text);
+    let path = &patch.path[..];
+    let (node, depth) = follow(root, path)?;
+    let exists = depth == path.len();
+    Ok(Some(match &patch.change {
+        Change::Set(value) if exists => {
+            let indent = line_indent(text, node.span().start);
+            (node.span(), style.render(value, indent)?)
+        }
+        Change::Set(value) => insert_value(text, &style, node, &path[depth..], value)?,
+        Change::Append(value) if exists => match node {
+            Node::Array { span, items } => {
+                let entries = items.iter().map(Node::span).collect::<Vec<_>>();
+                insert_entry(text, &style, span.clone(), &entries, |indent| {
+                    style.render(value, indent)
+                })?
+            }
+            _ => bail!("Cannot append to {path:?}, as it is not an array"),
+        },
+        Change::Append(value) => insert_value(
+            text,
+            &style,
+            node,
+            &path[depth..],
+            &Value::Array(vec![value.clone()]),
+        )?,
+        Change::Remove if !exists => return Ok(None),
+        Change::Remove => {
+            let Some((last, parent_path)) = path.split_last() else {
+                bail!("Cannot remove the root value");
+            };
+            let (parent, _) = follow(root, parent_path)?;
+            match (parent, last) {
+                (Node::Object { span, members }, PathSegment::Key(key)) => {
+                    let index = members.iter().position(|m| m.key == *key).unwrap();
+                    let entries = members.iter().map(Member::span).collect::<Vec<_>>();
+                    remove_entry(text, tolerant, span.clone(), &entries, index)?
+                }
+                (Node::Array { span, items }, PathSegment::Index(index)) => {
+                    let entries = items.iter().map(Node::span).collect::<Vec<_>>();
+                    remove_entry(text, tolerant, span.clone(), &entries, *index)?
+                }
+                _ => unreachable!(),
+            }
+        }
+    }))
+}
+
+fn is_tolerant(format: Format) -> Result<bool> {
+    match format {
+        Format::Json => Ok(false),
+        Format::Jsonc => Ok(true),
+        Format::Ini => bail!("Not a JSON format"),
+    }
+}
+
+/// Applies `patches` to the JSON document `text` in order, leaving the rest of it untouched.
+pub fn apply_patches(format: Format, text: &str, patches: &[Patch]) -> Result<String> {
+    let tolerant = is_tolerant(format)?;
+    let mut text = text.to_owned();
+    for patch in patches {
+        let root = parse(&text, tolerant)?;
+        if let Some((range, replacement)) = patch_edit(&text, tolerant, &root, patch)? {
+            text.replace_range(range, &replacement);
+        }
+    }
+    Ok(text)
+}
+
+/// Returns the value at `path` in the JSON document `text`, if there is one.
+pub fn get_value(format: Format, text: &str, path: &[PathSegment]) -> Result<Option<Value>> {
+    let root = parse(text, is_tolerant(format)?)?;
+    let (node, depth) = follow(&root, path)?;
+    if depth != path.len() {
+        return Ok(None);
+    }
+    Ok(Some(node.to_value(text)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn key_path(keys: &[&str]) -> Vec<PathSegment> {
+        keys.iter()
+            .map(|&k| PathSegment::Key(k.to_owned()))
+            .collect()
+    }
+
+    fn set(keys: &[&str], value: Value) -> Patch {
+        Patch {
+            path: key_path(keys),
+            change: Change::Set(value),
+        }
+    }
+
+    #[test]
+    fn test_set_preserves_formatting() {
+        let text = "{\n    \"zeta\": 1,\n    \"alpha\": {\"enabled\":   false}\n}\n";
+        assert_eq!(
+            apply_patches(
+                Format::Json,
+                text,
+                &[set(&["alpha", "enabled"], json!(true))]
+            )
+            .unwrap(),
+            "{\n    \"zeta\": 1,\n    \"alpha\": {\"enabled\":   true}\n}\n"
+        );
+    }
+
+    #[test]
+    fn test_set_inserts_missing() {
+        let text = "{\n    \"zeta\": 1\n}\n";
+        assert_eq!(
+            apply_patches(
+                Format::Json,
+                text,
+                &[set(&["alpha", "enabled"], json!(true))]
+            )
+            .unwrap(),
+            "{\n    \"zeta\": 1,\n    \"alpha\": {\n        \"enabled\": true\n    }\n}\n"
+        );
+        assert_eq!(
+            apply_patches(Format::Json, "{}\n", &[set(&["volume"], json!(0.5))]).unwrap(),
+            "{\n  \"volume\": 0.5\n}\n"
+        );
+        assert_eq!(
+            apply_patches(Format::Json, "{\"a\":1}", &[set(&["b"], json!("x"))]).unwrap(),
+            "{\"a\":1,\"b\":\"x\"}"
+        );
+    }
+
+    #[test]
+    fn test_append() {
+        let text = "{\n  \"list\": [1, 2]\n}";
+        let patches = [
+            Patch {
+                path: key_path(&["list"]),
+                change: Change::Append(json!(3)),
+            },
+            Patch {
+                path: key_path(&["other"]),
+                change: Change::Append(json!("a")),
+            },
+        ];
+        assert_eq!(
+            apply_patches(Format::Json, text, &patches).unwrap(),
+            "{\n  \"list\": [1, 2, 3],\n  \"other\": [\n    \"a\"\n  ]\n}"
+        );
+    }
+
+    #[test]
+    fn test_remove() {
+        let text = "{\n  \"a\": 1,\n  \"b\": [1, 2, 3],\n  \"c\": 3\n}";
+        let remove = |path| Patch {
+            path,
+            change: Change::Remove,
+        };
+        assert_eq!(
+            apply_patches(
+                Format::Json,
+                text,
+                &[
+                    remove(key_path(&["a"])),
+                    remove(vec![
+                        PathSegment::Key("b".to_owned()),
+                        PathSegment::Index(2)
+                    ]),
+                    remove(key_path(&["c"])),
+                    remove(key_path(&["missing"])),
+                ]
+            )
+            .unwrap(),
+            "{\n  \"b\": [1, 2]\n}"
+        );
+        assert_eq!(
+            apply_patches(Format::Json, "{ \"a\": 1 }", &[remove(key_path(&["a"]))]).unwrap(),
+            "{}"
+        );
+    }
+
+    #[test]
+    fn test_get_value() {
+        let text = "{\"a\": {\"b\": [true, \"x\"]}}";
+        assert_eq!(
+            get_value(
+                Format::Json,
+                text,
+                &[
+                    PathSegment::Key("a".to_owned()),
+                    PathSegment::Key("b".to_owned()),
+                    PathSegment::Index(1)
+                ]
+            )
+            .unwrap(),
+            Some(json!("x"))
+        );
+        assert_eq!(
+            get_value(Format::Json, text, &key_path(&["a", "c"])).unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_jsonc_preserves_comments() {
+        let text = "{\n  // the volume, from 0 to 1\n  volume: 1, /* loud */\n  \"list\": [\n    1,\n  ],\n}\n";
+        assert!(apply_patches(Format::Json, text, &[set(&["volume"], json!(0))]).is_err());
+        assert_eq!(
+            apply_patches(
+                Format::Jsonc,
+                text,
+                &[
+                    set(&["volume"], json!(0.5)),
+                    Patch {
+                        path: key_path(&["list"]),
+                        change: Change::Append(json!(2)),
+                    },
+                    set(&["name"], json!("Player")),
+                ]
+            )
+            .unwrap(),
+            "{\n  // the volume, from 0 to 1\n  volume: 0.5, /* loud */\n  \"list\": [\n    1,\n    2,\n  ],\n  \"name\": \"Player\",\n}\n"
+        );
+        assert_eq!(
+            get_value(Format::Jsonc, text, &key_path(&["list"])).unwrap(),
+            Some(json!([1]))
+        );
+    }
+
+    #[test]
+    fn test_jsonc_remove() {
+        let text = "{\n  \"a\": 1, // about a\n  // about b\n  \"b\": 2,\n}";
+        let remove = |key| Patch {
+            path: key_path(&[key]),
+            change: Change::Remove,
+        };
+        assert_eq!(
+            apply_patches(Format::Jsonc, text, &[remove("a")]).unwrap(),
+            "{\n  // about a\n  // about b\n  \"b\": 2,\n}"
+        );
+        assert_eq!(
+            apply_patches(Format::Jsonc, text, &[remove("a"), remove("b")]).unwrap(),
+            "{\n  // about a\n  // about b\n}"
+        );
+    }
+
+    #[test]
+    fn test_rejects_invalid() {
+        assert!(apply_patches(Format::Json, "{\"a\": }", &[set(&["a"], json!(1))]).is_err());
+        assert!(apply_patches(Format::Json, "{\"a\": 1} x", &[set(&["a"], json!(1))]).is_err());
+    }
+}
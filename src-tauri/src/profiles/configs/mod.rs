@@ -0,0 +1,504 @@
+//! Config files that Manderrow manages on behalf of a profile.
+//!
+//! Some values in a profile's config files, such as the logging settings in `BepInEx.cfg`, are
+//! derived from the profile's settings and rewritten at launch, rather than left to whatever a
+//! modpack shipped. Users can override any value, managed or not, and their overrides are layered
+//! on top every time the file is written.
+
+pub mod backup;
+pub mod json;
+pub mod search;
+pub mod watcher;
+
+use std::collections::BTreeMap;
+use std::io::Write as _;
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context as _, Result};
+use uuid::Uuid;
+
+use crate::util::IoErrorKindExt as _;
+
+use super::{profile_path, CONFIG_FOLDER};
+
+const OVERRIDES_FILE_NAME: &str = "config_overrides.json";
+
+/// Values, by key, by section.
+pub type ConfigValues = BTreeMap<String, BTreeMap<String, String>>;
+
+/// User overrides, by config file name (relative to the profile's config folder).
+pub type ConfigOverrides = BTreeMap<String, ConfigValues>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// BepInEx's `.cfg` files and other INI-style files.
+    Ini,
+    /// JSON, where a section is the dot-separated path of the object that holds the key, and
+    /// values are JSON, falling back to a string if they don't parse as such.
+    Json,
+    /// JSON with comments and trailing commas, as well as JSON5's unquoted keys. Treated like
+    /// [`Format::Json`] otherwise.
+    Jsonc,
+}
+
+impl Format {
+    /// Guesses the format of the config file `file` from its extension.
+    pub fn guess(file: &str) -> Self {
+        match Path::new(file).extension() {
+            Some(ext) if ext.eq_ignore_ascii_case("json") => Self::Json,
+            Some(ext) if ext.eq_ignore_ascii_case("jsonc") || ext.eq_ignore_ascii_case("json5") => {
+                Self::Jsonc
+            }
+            _ => Self::Ini,
+        }
+    }
+}
+
+fn overrides_path(id: Uuid) -> PathBuf {
+    profile_path(id).join(OVERRIDES_FILE_NAME)
+}
+
+pub async fn read_overrides(id: Uuid) -> Result<ConfigOverrides> {
+    let path = overrides_path(id);
+    let mut bytes = match tokio::fs::read(&path).await {
+        Ok(t) => t,
+        Err(e) if e.is_not_found() => return Ok(ConfigOverrides::new()),
+        Err(e) => return Err(e).with_context(|| format!("Failed to read {path:?}")),
+    };
+    simd_json::from_slice(&mut bytes).with_context(|| format!("Failed to parse {path:?}"))
+}
+
+async fn write_overrides(id: Uuid, overrides: &ConfigOverrides) -> Result<()> {
+    let path = overrides_path(id);
+    let bytes = simd_json::to_vec(overrides)?;
+    tokio::task::block_in_place(|| write_atomically(&path, &bytes))
+}
+
+/// Sets, or with a `value` of `None` removes, a user override.
+pub async fn set_override(
+    id: Uuid,
+    file: String,
+    section: String,
+    key: String,
+    value: Option<String>,
+) -> Result<()> {
+    let mut overrides = read_overrides(id).await?;
+    match value {
+        Some(value) => {
+            overrides
+                .entry(file)
+                .or_default()
+                .entry(section)
+                .or_default()
+                .insert(key, value);
+        }
+        None => {
+            if let Some(sections) = overrides.get_mut(&file) {
+                if let Some(values) = sections.get_mut(&section) {
+                    values.remove(&key);
+                    if values.is_empty() {
+                        sections.remove(&section);
+                    }
+                }
+                if sections.is_empty() {
+                    overrides.remove(&file);
+                }
+            }
+        }
+    }
+    write_overrides(id, &overrides).await
+}
+
+/// Resolves the config file `file`, relative to the profile's config folder, rejecting anything
+/// that could point outside of it.
+fn config_path(id: Uuid, file: &str) -> Result<PathBuf> {
+    let rel = Path::new(file);
+    if rel.as_os_str().is_empty()
+        || !rel
+            .components()
+            .all(|c| matches!(c, std::path::Component::Normal(_)))
+    {
+        bail!("Invalid config file path {file:?}");
+    }
+    let mut path = profile_path(id);
+    path.push(CONFIG_FOLDER);
+    path.push(rel);
+    Ok(path)
+}
+
+/// Joins the components of `path` with forward slashes, so that names are the same on every
+/// platform.
+pub(crate) fn entry_name(path: &Path) -> Result<String> {
+    let mut name = String::new();
+    for c in path.components() {
+        let std::path::Component::Normal(c) = c else {
+            continue;
+        };
+        let c = c
+            .to_str()
+            .with_context(|| format!("Non-UTF-8 path {path:?}"))?;
+        if !name.is_empty() {
+            name.push('/');
+        }
+        name.push_str(c);
+    }
+    Ok(name)
+}
+
+fn parse_section_header(line: &str) -> Option<&str> {
+    line.trim()
+        .strip_prefix('[')
+        .and_then(|s| s.strip_suffix(']'))
+        .map(str::trim)
+}
+
+fn parse_key(line: &str) -> Option<&str> {
+    let line = line.trim_start();
+    if line.starts_with(['#', ';']) {
+        return None;
+    }
+    line.split_once('=').map(|(key, _)| key.trim())
+}
+
+/// Returns the value of `key` in `section` of the INI-style config `text`, if it is set.
+pub fn get_ini_value<'a>(text: &'a str, section: &str, key: &str) -> Option<&'a str> {
+    let mut current = None::<&str>;
+    for line in text.lines() {
+        if let Some(header) = parse_section_header(line) {
+            current = Some(header);
+        } else if current == Some(section) && parse_key(line) == Some(key) {
+            return line.split_once('=').map(|(_, value)| value.trim());
+        }
+    }
+    None
+}
+
+fn json_path(section: &str, key: &str) -> Vec<json::PathSegment> {
+    section
+        .split('.')
+        .filter(|s| !s.is_empty())
+        .chain([key])
+        .map(|s| json::PathSegment::Key(s.to_owned()))
+        .collect()
+}
+
+fn json_patches(values: &ConfigValues) -> Vec<json::Patch> {
+    values
+        .iter()
+        .flat_map(|(section, values)| {
+            values.iter().map(|(key, value)| json::Patch {
+                path: json_path(section, key),
+                change: json::Change::Set(
+                    serde_json::from_str(value)
+                        .unwrap_or_else(|_| serde_json::Value::String(value.clone())),
+                ),
+            })
+        })
+        .collect()
+}
+
+/// Returns the value of `key` in `section` of the config `text`, if it is set.
+pub fn get_value(format: Format, text: &str, section: &str, key: &str) -> Result<Option<String>> {
+    match format {
+        Format::Ini => Ok(get_ini_value(text, section, key).map(str::to_owned)),
+        Format::Json | Format::Jsonc => {
+            Ok(
+                json::get_value(format, text, &json_path(section, key))?.map(|value| match value {
+                    serde_json::Value::String(s) => s,
+                    value => value.to_string(),
+                }),
+            )
+        }
+    }
+}
+
+/// Sets `values` in the config `text`, keeping everything else intact.
+pub fn set_values(format: Format, text: &str, values: &ConfigValues) -> Result<String> {
+    match format {
+        Format::Ini => Ok(set_ini_values(text, values)),
+        Format::Json | Format::Jsonc if values.is_empty() => Ok(text.to_owned()),
+        Format::Json | Format::Jsonc => {
+            json::apply_patches(format, json_or_empty(text), &json_patches(values))
+        }
+    }
+}
+
+/// Treats a blank JSON file as an empty object, so that values can be added to new files.
+fn json_or_empty(text: &str) -> &str {
+    if text.trim().is_empty() {
+        "{}\n"
+    } else {
+        text
+    }
+}
+
+/// Replaces the file at `path` with `contents`, such that it is never left half-written. Missing
+/// parent directories are created.
+pub(crate) fn write_atomically(path: &Path, contents: &[u8]) -> Result<()> {
+    let parent = path
+        .parent()
+        .with_context(|| format!("{path:?} has no parent"))?;
+    std::fs::create_dir_all(parent)?;
+    let mut temp_file = tempfile::NamedTempFile::new_in(parent)?;
+    temp_file.write_all(contents)?;
+    temp_file
+        .persist(path)
+        .with_context(|| format!("Failed to write {path:?}"))?;
+    Ok(())
+}
+
+/// Reads the value of `key` in `section` of the profile's config file `file`.
+pub async fn read_config_value(
+    id: Uuid,
+    file: &str,
+    section: &str,
+    key: &str,
+) -> Result<Option<String>> {
+    let path = config_path(id, file)?;
+    let text = match tokio::fs::read_to_string(&path).await {
+        Ok(t) => t,
+        Err(e) if e.is_not_found() => return Ok(None),
+        Err(e) => return Err(e).with_context(|| format!("Failed to read {path:?}")),
+    };
+    get_value(Format::guess(file), &text, section, key)
+        .with_context(|| format!("Failed to parse {path:?}"))
+}
+
+/// Sets `values` in the INI-style config `text`, keeping everything else, including comments,
+/// intact. Keys missing from a section are appended to it, and missing sections are appended to
+/// the end.
+pub fn set_ini_values(text: &str, values: &ConfigValues) -> String {
+    let mut lines = text.lines().map(str::to_owned).collect::<Vec<_>>();
+
+    for (section, values) in values {
+        // the range of lines belonging to the section, excluding its header
+        let mut range = None::<(usize, usize)>;
+        let mut current = None::<&str>;
+        for (i, line) in lines.iter().enumerate() {
+            if let Some(header) = parse_section_header(line) {
+                if current == Some(section.as_str()) {
+                    break;
+                }
+                current = Some(header);
+                if header == section {
+                    range = Some((i + 1, i + 1));
+                }
+            } else if current == Some(section.as_str()) {
+                if let Some((_, end)) = &mut range {
+                    *end = i + 1;
+                }
+            }
+        }
+
+        let (start, mut end) = match range {
+            Some(t) => t,
+            None => {
+                if lines.last().is_some_and(|l| !l.trim().is_empty()) {
+                    lines.push(String::new());
+                }
+                lines.push(format!("[{section}]"));
+                (lines.len(), lines.len())
+            }
+        };
+        // insert after the last non-blank line of the section
+        let mut insert_at = (start..end)
+            .rev()
+            .find(|&i| !lines[i].trim().is_empty())
+            .map_or(start, |i| i + 1);
+
+        for (key, value) in values {
+            let line = format!("{key} = {value}");
+            match (start..end).find(|&i| parse_key(&lines[i]) == Some(key.as_str())) {
+                Some(i) => lines[i] = line,
+                None => {
+                    lines.insert(insert_at, line);
+                    insert_at += 1;
+                    end += 1;
+                }
+            }
+        }
+    }
+
+    let mut buf = lines.join("\n");
+    buf.push('\n');
+    buf
+}
+
+/// Writes `managed` values into the profile's config file `file`, followed by the user's
+/// overrides for it.
+pub async fn write_managed_config(id: Uuid, file: &str, managed: &ConfigValues) -> Result<()> {
+    let path = config_path(id, file)?;
+
+    let text = match tokio::fs::read_to_string(&path).await {
+        Ok(t) => t,
+        Err(e) if e.is_not_found() => String::new(),
+        Err(e) => return Err(e).with_context(|| format!("Failed to read {path:?}")),
+    };
+
+    let format = Format::guess(file);
+    let mut new_text =
+        set_values(format, &text, managed).with_context(|| format!("Failed to edit {path:?}"))?;
+    if let Some(overrides) = read_overrides(id).await?.get(file) {
+        new_text = set_values(format, &new_text, overrides)
+            .with_context(|| format!("Failed to edit {path:?}"))?;
+    }
+
+    if new_text != text {
+        backup::take_snapshot(
+            &slog_scope::logger(),
+            id,
+            backup::SnapshotReason::ConfigEdit,
+        )
+        .await
+        .context("Failed to snapshot config before writing")?;
+        tokio::task::block_in_place(|| write_atomically(&path, new_text.as_bytes()))?;
+    }
+    Ok(())
+}
+
+/// Applies `patches` to the profile's JSON config file `file`, creating it if it is missing.
+pub async fn update_config(id: Uuid, file: &str, patches: &[json::Patch]) -> Result<()> {
+    let path = config_path(id, file)?;
+
+    let format = Format::guess(file);
+    if format == Format::Ini {
+        bail!("{file:?} is not a JSON config file");
+    }
+
+    let text = match tokio::fs::read_to_string(&path).await {
+        Ok(t) => t,
+        Err(e) if e.is_not_found() => String::new(),
+        Err(e) => return Err(e).with_context(|| format!("Failed to read {path:?}")),
+    };
+    let new_text = json::apply_patches(format, json_or_empty(&text), patches)
+        .with_context(|| format!("Failed to edit {path:?}"))?;
+
+    if new_text != text {
+        backup::take_snapshot(
+            &slog_scope::logger(),
+            id,
+            backup::SnapshotReason::ConfigEdit,
+        )
+        .await
+        .context("Failed to snapshot config before writing")?;
+        tokio::task::block_in_place(|| write_atomically(&path, new_text.as_bytes()))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn values(entries: &[(&str, &str, &str)]) -> ConfigValues {
+        let mut values = ConfigValues::new();
+        for &(section, key, value) in entries {
+            values
+                .entry(section.to_owned())
+                .or_default()
+                .insert(key.to_owned(), value.to_owned());
+        }
+        values
+    }
+
+    #[test]
+    fn test_set_ini_values_replaces() {
+        let text = "[Logging.Console]\n## Enables showing a console\n# Setting type: Boolean\nEnabled = true\n\n[Logging.Disk]\nEnabled = true\n";
+        assert_eq!(
+            set_ini_values(text, &values(&[("Logging.Console", "Enabled", "false")])),
+            "[Logging.Console]\n## Enables showing a console\n# Setting type: Boolean\nEnabled = false\n\n[Logging.Disk]\nEnabled = true\n"
+        );
+    }
+
+    #[test]
+    fn test_set_ini_values_appends_key() {
+        let text = "[Logging.Console]\nEnabled = true\n\n[Logging.Disk]\nEnabled = true\n";
+        assert_eq!(
+            set_ini_values(text, &values(&[("Logging.Console", "LogLevels", "All")])),
+            "[Logging.Console]\nEnabled = true\nLogLevels = All\n\n[Logging.Disk]\nEnabled = true\n"
+        );
+    }
+
+    #[test]
+    fn test_set_ini_values_appends_section() {
+        assert_eq!(
+            set_ini_values("", &values(&[("Logging", "UnityLogListening", "true")])),
+            "[Logging]\nUnityLogListening = true\n"
+        );
+        assert_eq!(
+            set_ini_values(
+                "[Chainloader]\nHideManagerGameObject = false\n",
+                &values(&[("Logging", "UnityLogListening", "true")])
+            ),
+            "[Chainloader]\nHideManagerGameObject = false\n\n[Logging]\nUnityLogListening = true\n"
+        );
+    }
+
+    #[test]
+    fn test_get_ini_value() {
+        let text =
+            "[Logging]\n# Enabled = true\nEnabled = false\n\n[Logging.Console]\nEnabled = true\n";
+        assert_eq!(get_ini_value(text, "Logging", "Enabled"), Some("false"));
+        assert_eq!(
+            get_ini_value(text, "Logging.Console", "Enabled"),
+            Some("true")
+        );
+        assert_eq!(get_ini_value(text, "Logging.Disk", "Enabled"), None);
+    }
+
+    #[test]
+    fn test_config_path_rejects_escapes() {
+        for file in [
+            "",
+            "../profile.json",
+            "BepInEx/../../profile.json",
+            "./BepInEx.cfg",
+        ] {
+            assert!(config_path(Uuid::nil(), file).is_err(), "{file:?}");
+        }
+        #[cfg(unix)]
+        assert!(config_path(Uuid::nil(), "/etc/passwd").is_err());
+        #[cfg(windows)]
+        assert!(config_path(Uuid::nil(), "C:\\Windows\\win.ini").is_err());
+    }
+
+    #[test]
+    fn test_format_guess() {
+        assert_eq!(Format::guess("BepInEx.cfg"), Format::Ini);
+        assert_eq!(Format::guess("MoreCompany/options.JSON"), Format::Json);
+        assert_eq!(Format::guess("options.jsonc"), Format::Jsonc);
+        assert_eq!(Format::guess("options.json5"), Format::Jsonc);
+    }
+
+    #[test]
+    fn test_set_values_json() {
+        let text = "{\n  \"General\": {\n    \"Volume\": 1\n  }\n}\n";
+        let values = values(&[
+            ("General", "Volume", "0.5"),
+            ("General", "Name", "Player"),
+            ("", "Enabled", "true"),
+        ]);
+        let new_text = set_values(Format::Json, text, &values).unwrap();
+        assert_eq!(
+            new_text,
+            "{\n  \"General\": {\n    \"Volume\": 0.5,\n    \"Name\": \"Player\"\n  },\n  \"Enabled\": true\n}\n"
+        );
+        assert_eq!(
+            get_value(Format::Json, &new_text, "General", "Name").unwrap(),
+            Some("Player".to_owned())
+        );
+        assert_eq!(
+            get_value(Format::Json, &new_text, "", "Enabled").unwrap(),
+            Some("true".to_owned())
+        );
+    }
+
+    #[test]
+    fn test_set_ini_values_ignores_comments() {
+        let text = "[Logging]\n# Enabled = true\n";
+        assert_eq!(
+            set_ini_values(text, &values(&[("Logging", "Enabled", "false")])),
+            "[Logging]\n# Enabled = true\nEnabled = false\n"
+        );
+    }
+}
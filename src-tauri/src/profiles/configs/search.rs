@@ -0,0 +1,188 @@
+//! Searching the values in every config file of a profile, to find which of many files contains a
+//! setting.
+
+use std::path::Path;
+
+use anyhow::{Context as _, Result};
+use uuid::Uuid;
+
+use super::{entry_name, json, parse_key, parse_section_header, Format};
+use crate::profiles::{profile_path, CONFIG_FOLDER};
+
+/// The most matches that are returned for a single search.
+const MAX_MATCHES: usize = 500;
+/// The number of characters of a value that are included in a match.
+const PREVIEW_LENGTH: usize = 100;
+
+/// Extensions of the files that are searched.
+const EXTENSIONS: &[&str] = &["cfg", "ini", "json", "jsonc", "json5"];
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct ConfigMatch {
+    /// The file, relative to the profile's config folder.
+    pub file: String,
+    pub section: String,
+    pub key: String,
+    /// The value, shortened if it is long.
+    pub value: String,
+}
+
+/// Returns the values in the config `text`, as `(section, key, value)`.
+fn read_entries(format: Format, text: &str) -> Result<Vec<(String, String, String)>> {
+    let mut entries = Vec::new();
+    match format {
+        Format::Ini => {
+            let mut section = "";
+            for line in text.lines() {
+                if let Some(header) = parse_section_header(line) {
+                    section = header;
+                } else if let Some(key) = parse_key(line) {
+                    let value = line.split_once('=').map_or("", |(_, value)| value.trim());
+                    entries.push((section.to_owned(), key.to_owned(), value.to_owned()));
+                }
+            }
+        }
+        Format::Json | Format::Jsonc => {
+            fn flatten(
+                section: &str,
+                value: &serde_json::Value,
+                entries: &mut Vec<(String, String, String)>,
+            ) {
+                let serde_json::Value::Object(map) = value else {
+                    return;
+                };
+                for (key, value) in map {
+                    match value {
+                        serde_json::Value::Object(_) => {
+                            let section = if section.is_empty() {
+                                key.clone()
+                            } else {
+                                format!("{section}.{key}")
+                            };
+                            flatten(&section, value, entries);
+                        }
+                        serde_json::Value::String(s) => {
+                            entries.push((section.to_owned(), key.clone(), s.clone()))
+                        }
+                        value => entries.push((section.to_owned(), key.clone(), value.to_string())),
+                    }
+                }
+            }
+            if let Some(root) = json::get_value(format, text, &[])? {
+                flatten("", &root, &mut entries);
+            }
+        }
+    }
+    Ok(entries)
+}
+
+fn preview(value: &str) -> String {
+    match value.char_indices().nth(PREVIEW_LENGTH) {
+        Some((i, _)) => format!("{}…", &value[..i]),
+        None => value.to_owned(),
+    }
+}
+
+/// Finds the values in the config `text` whose section, key, or value contains `query`, ignoring
+/// case.
+fn search_text(file: &str, format: Format, text: &str, query: &str) -> Result<Vec<ConfigMatch>> {
+    let query = query.to_lowercase();
+    Ok(read_entries(format, text)?
+        .into_iter()
+        .filter(|(section, key, value)| {
+            [section, key, value]
+                .into_iter()
+                .any(|s| s.to_lowercase().contains(&query))
+        })
+        .map(|(section, key, value)| ConfigMatch {
+            file: file.to_owned(),
+            section,
+            key,
+            value: preview(&value),
+        })
+        .collect())
+}
+
+fn search_folder(log: &slog::Logger, path: &Path, query: &str) -> Result<Vec<ConfigMatch>> {
+    let mut matches = Vec::new();
+    if !path.exists() {
+        return Ok(matches);
+    }
+    for e in walkdir::WalkDir::new(path).sort_by_file_name() {
+        let e = e?;
+        if !e.file_type().is_file()
+            || !e.path().extension().is_some_and(|ext| {
+                EXTENSIONS
+                    .iter()
+                    .any(|candidate| ext.eq_ignore_ascii_case(candidate))
+            })
+        {
+            continue;
+        }
+        let file = entry_name(e.path().strip_prefix(path)?)?;
+        let text = match std::fs::read_to_string(e.path()) {
+            Ok(t) => t,
+            Err(error) if error.kind() == std::io::ErrorKind::InvalidData => {
+                slog::debug!(log, "Skipping {file:?}, as it is not UTF-8");
+                continue;
+            }
+            Err(error) => {
+                return Err(error).with_context(|| format!("Failed to read {:?}", e.path()))
+            }
+        };
+        match search_text(&file, Format::guess(&file), &text, query) {
+            Ok(t) => matches.extend(t),
+            Err(error) => slog::debug!(log, "Skipping {file:?}: {error:#}"),
+        }
+        if matches.len() >= MAX_MATCHES {
+            matches.truncate(MAX_MATCHES);
+            break;
+        }
+    }
+    Ok(matches)
+}
+
+/// Finds the values in the profile's config files whose section, key, or value contains `query`,
+/// ignoring case.
+pub async fn search_configs(log: &slog::Logger, id: Uuid, query: &str) -> Result<Vec<ConfigMatch>> {
+    let path = profile_path(id).join(CONFIG_FOLDER);
+    tokio::task::block_in_place(|| search_folder(log, &path, query))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_search_text() {
+        let cfg =
+            "[Vision]\n## Enables night vision\nNightVision = true\n\n[Audio]\nVolume = 0.5\n";
+        assert_eq!(
+            search_text("Mod.cfg", Format::Ini, cfg, "nightvision").unwrap(),
+            [ConfigMatch {
+                file: "Mod.cfg".to_owned(),
+                section: "Vision".to_owned(),
+                key: "NightVision".to_owned(),
+                value: "true".to_owned(),
+            }]
+        );
+
+        let json =
+            "{\n  // comment\n  \"vision\": { \"nightVision\": false },\n  \"name\": \"night\"\n}";
+        let matches = search_text("options.jsonc", Format::Jsonc, json, "NIGHT").unwrap();
+        assert_eq!(
+            matches
+                .iter()
+                .map(|m| (m.section.as_str(), m.key.as_str(), m.value.as_str()))
+                .collect::<Vec<_>>(),
+            [("", "name", "night"), ("vision", "nightVision", "false")]
+        );
+    }
+
+    #[test]
+    fn test_preview() {
+        assert_eq!(preview("short"), "short");
+        let long = "x".repeat(PREVIEW_LENGTH + 1);
+        assert_eq!(preview(&long), format!("{}…", "x".repeat(PREVIEW_LENGTH)));
+    }
+}
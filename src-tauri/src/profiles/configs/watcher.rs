@@ -0,0 +1,113 @@
+//! Watching a profile's config folder while its game is running.
+//!
+//! Mods often write their config files while the game runs, e.g. when they are first loaded or
+//! when the player changes a setting in game. The frontend is told about these changes so that it
+//! can show the new values instead of stale ones, and doesn't overwrite them.
+
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use anyhow::{Context as _, Result};
+use slog::{debug, warn};
+use tauri::{AppHandle, Emitter as _, Manager as _};
+use uuid::Uuid;
+
+use crate::ipc::{ConnectionId, IpcState};
+use crate::profiles::{profile_path, CONFIG_FOLDER};
+use crate::util::watch::DebouncedWatcher;
+
+use super::entry_name;
+
+/// The name of the event sent to the frontend when config files change.
+pub const EVENT: &str = "profile_config_changed";
+
+/// Mods tend to write several files at once, so changes are collected for this long before they
+/// are reported together.
+const DEBOUNCE: Duration = Duration::from_millis(500);
+/// How often to check whether the game is still running.
+const LIVENESS_INTERVAL: Duration = Duration::from_secs(2);
+
+#[derive(Clone, serde::Serialize)]
+struct ConfigChanged {
+    profile: Uuid,
+    /// The files that changed, relative to the profile's config folder.
+    files: Vec<String>,
+}
+
+/// Watches the profile's config folder until the game connected through `conn_id` exits.
+pub fn spawn(log: slog::Logger, app: AppHandle, id: Uuid, conn_id: ConnectionId) {
+    tauri::async_runtime::spawn(async move {
+        if let Err(e) = watch(&log, &app, id, conn_id).await {
+            warn!(log, "Failed to watch config folder of profile {id}: {e:#}");
+        }
+    });
+}
+
+async fn watch(log: &slog::Logger, app: &AppHandle, id: Uuid, conn_id: ConnectionId) -> Result<()> {
+    let path = profile_path(id).join(CONFIG_FOLDER);
+    // the game may create the folder only once it has started
+    tokio::fs::create_dir_all(&path)
+        .await
+        .with_context(|| format!("Failed to create {path:?}"))?;
+    // watchers may report the paths of changes with symlinks resolved
+    let path = tokio::fs::canonicalize(&path).await?;
+
+    let mut watcher = DebouncedWatcher::new(&path, DEBOUNCE)?;
+    debug!(log, "Watching {path:?}");
+
+    let mut liveness = tokio::time::interval(LIVENESS_INTERVAL);
+    loop {
+        tokio::select! {
+            changes = watcher.next(log) => {
+                let Some(changes) = changes else {
+                    break;
+                };
+                let files = changed_files(&path, changes);
+                if files.is_empty() {
+                    continue;
+                }
+                debug!(log, "Config files of profile {id} changed: {files:?}");
+                app.emit(
+                    EVENT,
+                    ConfigChanged {
+                        profile: id,
+                        files: files.into_iter().collect(),
+                    },
+                )?;
+            }
+            _ = liveness.tick() => {
+                if app.state::<IpcState>().get_conn(conn_id).is_none() {
+                    break;
+                }
+            }
+        }
+    }
+    debug!(log, "Stopped watching {path:?}");
+    Ok(())
+}
+
+/// Returns the names of the config files at `changes`, relative to `root`.
+fn changed_files(root: &Path, changes: Vec<PathBuf>) -> BTreeSet<String> {
+    let mut files = BTreeSet::new();
+    for path in changes {
+        // temporary files used to write config files atomically
+        if path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .is_some_and(|name| name.starts_with(".tmp"))
+        {
+            continue;
+        }
+        if let Ok(name) = path
+            .strip_prefix(root)
+            .map_err(anyhow::Error::from)
+            .and_then(entry_name)
+        {
+            if !name.is_empty() {
+                files.insert(name);
+            }
+        }
+    }
+    files
+}
@@ -0,0 +1,613 @@
+//! Mods under development, linked into a profile from a folder outside of it, like the output
+//! folder of the mod's build.
+//!
+//! The folder's files are copied into the profile, and copied again whenever they change, so that
+//! a mod can be rebuilt and tested without being packaged. Copies are used instead of links since
+//! creating links needs extra privileges on Windows. Linked mods are not on Thunderstore, so they
+//! are never updated from it or included in exports.
+//!
+//! While a game is running the profile, it is asked to reload a linked mod whenever its files are
+//! copied again, which only works if the mod loader can reload mods.
+
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+use std::time::{Duration, UNIX_EPOCH};
+
+use anyhow::{bail, ensure, Context as _, Result};
+use manderrow_ipc::S2CMessage;
+use manderrow_types::mods::{ModAndVersion, ModMetadata, ModVersion, Timestamp};
+use packed_semver::Version;
+use parking_lot::Mutex;
+use slog::{debug, info, warn};
+use smol_str::SmolStr;
+use tauri::async_runtime::JoinHandle;
+use tauri::{AppHandle, Emitter as _, Manager as _};
+use uuid::Uuid;
+use walkdir::WalkDir;
+
+use crate::ipc::{ConnectionId, IpcState};
+use crate::util::watch::DebouncedWatcher;
+use crate::util::IoErrorKindExt as _;
+
+use super::configs::entry_name;
+use super::{
+    profile_path, push_mod_folder, read_mod_manifest, InstalledModManifest, MANIFEST_FILE_NAME,
+    MODS_FOLDER, PATCHERS_FOLDER,
+};
+
+/// The owner that linked mods are installed under. Thunderstore team names can't start with an
+/// underscore, so no team can have it.
+pub const DEV_OWNER: &str = "_dev";
+
+/// The name of the event sent to the frontend when a linked mod is copied again.
+pub const EVENT: &str = "dev_mod_synced";
+
+/// Builds tend to write many files at once, so changes are collected for this long before the mod
+/// is copied again.
+const DEBOUNCE: Duration = Duration::from_millis(500);
+
+/// The watchers of linked mods, by profile and mod name.
+static WATCHERS: LazyLock<Mutex<HashMap<(Uuid, String), JoinHandle<()>>>> =
+    LazyLock::new(Default::default);
+
+/// The connections of the games running each profile.
+static CONNECTIONS: LazyLock<Mutex<HashMap<Uuid, Vec<ConnectionId>>>> =
+    LazyLock::new(Default::default);
+
+#[derive(Clone, serde::Serialize)]
+struct DevModSynced {
+    profile: Uuid,
+    /// The mod, as `Owner-Name`.
+    r#mod: String,
+}
+
+/// The fields of a Thunderstore `manifest.json` that a linked mod's metadata is taken from.
+#[derive(serde::Deserialize)]
+struct PackageManifest {
+    name: String,
+    version_number: Version,
+    #[serde(default)]
+    description: String,
+    #[serde(default)]
+    website_url: String,
+    #[serde(default)]
+    dependencies: Vec<String>,
+}
+
+/// The parts of [`InstalledModManifest`] that identify a linked mod.
+#[derive(serde::Deserialize)]
+struct LinkedManifest {
+    name: String,
+    #[serde(default)]
+    dev_source: Option<PathBuf>,
+}
+
+/// Reads the `manifest.json` in `source`, if it has one.
+async fn read_package_manifest(source: &Path) -> Result<Option<PackageManifest>> {
+    let path = source.join("manifest.json");
+    match tokio::fs::read(&path).await {
+        // Thunderstore allows manifests to start with a BOM
+        Ok(buf) => Ok(Some(
+            serde_json::from_slice(buf.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(&buf))
+                .with_context(|| format!("Failed to parse {path:?}"))?,
+        )),
+        Err(e) if e.is_not_found() => Ok(None),
+        Err(e) => Err(anyhow::Error::from(e).context(format!("Failed to read {path:?}"))),
+    }
+}
+
+/// Replaces anything that can't be in a Thunderstore package name.
+fn sanitize_name(name: &str) -> String {
+    name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect()
+}
+
+fn mod_folder(id: Uuid, name: &str) -> PathBuf {
+    let mut path = profile_path(id).join(MODS_FOLDER);
+    push_mod_folder(&mut path, DEV_OWNER, name);
+    path
+}
+
+/// The folder in the profile's [`PATCHERS_FOLDER`] that the linked mod's `patchers` folder is
+/// copied to, like the patchers of an installed package.
+pub(super) fn patchers_folder(id: Uuid, name: &str) -> PathBuf {
+    let mut path = profile_path(id).join(PATCHERS_FOLDER);
+    push_mod_folder(&mut path, DEV_OWNER, name);
+    path
+}
+
+/// Links the folder at `source` into the profile and starts watching it. The mod is named after
+/// the folder's `manifest.json`, or the folder itself if it has none. Returns the mod's name.
+pub async fn link(log: &slog::Logger, app: &AppHandle, id: Uuid, source: &Path) -> Result<String> {
+    let source = tokio::fs::canonicalize(source)
+        .await
+        .with_context(|| format!("Failed to find {source:?}"))?;
+    ensure!(
+        tokio::fs::metadata(&source).await?.is_dir(),
+        "{source:?} is not a folder"
+    );
+
+    let name = match read_package_manifest(&source).await? {
+        Some(manifest) => manifest.name,
+        None => source
+            .file_name()
+            .with_context(|| format!("{source:?} has no name"))?
+            .to_string_lossy()
+            .into_owned(),
+    };
+    let name = sanitize_name(&name);
+
+    if let Some(manifest) = read_mod_manifest::<LinkedManifest>(&mod_folder(id, &name)).await? {
+        if manifest.dev_source.as_deref() != Some(&source) {
+            bail!("{DEV_OWNER}-{name} is already installed");
+        }
+    }
+
+    refresh(id, &name, &source).await?;
+    info!(
+        log,
+        "Linked {source:?} into profile {id} as {DEV_OWNER}-{name}"
+    );
+    spawn_watcher(log, app, id, name.clone(), source);
+    Ok(name)
+}
+
+/// Copies the linked mod's files into the profile again and updates its manifest. Returns the files
+/// that changed, relative to the mod's folder.
+pub async fn refresh(id: Uuid, name: &str, source: &Path) -> Result<Vec<String>> {
+    let target = mod_folder(id, name);
+    let patchers_target = patchers_folder(id, name);
+    let manifest = read_package_manifest(source).await?;
+    let version_number = match &manifest {
+        Some(m) => m.version_number,
+        None => Version::new(0, 0, 0)?,
+    };
+
+    let (file_size, changed, patchers) = tokio::task::spawn_blocking({
+        let source = source.to_owned();
+        let target = target.clone();
+        move || {
+            let mut changed = Vec::new();
+            let size = sync(
+                &source,
+                &target,
+                Some(Path::new(PATCHERS_FOLDER)),
+                &mut changed,
+            )?;
+            let (patchers_size, patchers) = sync_patchers(
+                &source.join(PATCHERS_FOLDER),
+                &patchers_target,
+                &mut changed,
+            )?;
+            changed.sort_unstable();
+            Ok::<_, anyhow::Error>((size + patchers_size, changed, patchers))
+        }
+    })
+    .await??;
+
+    let modified = tokio::fs::metadata(source)
+        .await?
+        .modified()?
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or(0);
+    let date_created: Timestamp = chrono::DateTime::from_timestamp_millis(modified)
+        .unwrap_or_default()
+        .into();
+
+    let dependencies = manifest
+        .as_ref()
+        .map(|m| m.dependencies.iter().map(|s| s.as_str().into()).collect())
+        .unwrap_or_default();
+    let manifest = InstalledModManifest {
+        r#mod: ModAndVersion {
+            r#mod: ModMetadata {
+                name,
+                owner: DEV_OWNER,
+                donation_link: None,
+                package_url: None,
+                date_created,
+                is_deprecated: false,
+                has_nsfw_content: false,
+                categories: Vec::new(),
+            },
+            version: ModVersion {
+                description: manifest
+                    .as_ref()
+                    .map(|m| SmolStr::from(&m.description))
+                    .unwrap_or_default(),
+                version_number,
+                dependencies,
+                downloads: 0,
+                date_created,
+                website_url: manifest
+                    .as_ref()
+                    .filter(|m| !m.website_url.is_empty())
+                    .map(|m| SmolStr::from(&m.website_url)),
+                is_active: true,
+                file_size,
+            },
+        },
+        installed_from_cache: false,
+        patchers,
+        tool: None,
+        dev_source: Some(source.to_owned()),
+        disabled: false,
+    };
+    let path = target.join(MANIFEST_FILE_NAME);
+    tokio::fs::write(&path, serde_json::to_vec(&manifest)?)
+        .await
+        .with_context(|| format!("Failed to write {path:?}"))?;
+    Ok(changed)
+}
+
+/// Makes `target` a copy of `source`, copying only the files that changed and removing those that
+/// no longer exist in `source`, except for the mod's manifest. The `exclude` folder of `source`, if
+/// any, is treated as if it didn't exist. The files that are copied or removed are added to
+/// `changed`, relative to `target`. Returns the total size of the files.
+fn sync(
+    source: &Path,
+    target: &Path,
+    exclude: Option<&Path>,
+    changed: &mut Vec<String>,
+) -> Result<u64> {
+    std::fs::create_dir_all(target).with_context(|| format!("Failed to create {target:?}"))?;
+
+    let mut size = 0;
+    let mut present = HashSet::new();
+    let exclude = exclude.map(|exclude| source.join(exclude));
+    let iter = WalkDir::new(source)
+        .min_depth(1)
+        .into_iter()
+        .filter_entry(|e| exclude.as_deref() != Some(e.path()));
+    for entry in iter {
+        let entry = entry?;
+        let rel_path = entry.path().strip_prefix(source)?.to_owned();
+        let dest = target.join(&rel_path);
+        if entry.file_type().is_dir() {
+            std::fs::create_dir_all(&dest).with_context(|| format!("Failed to create {dest:?}"))?;
+        } else {
+            let metadata = entry.metadata()?;
+            size += metadata.len();
+            if !is_up_to_date(&metadata, &dest) {
+                std::fs::copy(entry.path(), &dest)
+                    .with_context(|| format!("Failed to copy {:?} to {dest:?}", entry.path()))?;
+                changed.push(entry_name(&rel_path)?);
+            }
+        }
+        present.insert(rel_path);
+    }
+
+    let mut stale = Vec::new();
+    for entry in WalkDir::new(target).min_depth(1).contents_first(true) {
+        let entry = entry?;
+        let rel_path = entry.path().strip_prefix(target)?;
+        if rel_path != Path::new(MANIFEST_FILE_NAME) && !present.contains(rel_path) {
+            if !entry.file_type().is_dir() {
+                changed.push(entry_name(rel_path)?);
+            }
+            stale.push((entry.path().to_owned(), entry.file_type().is_dir()));
+        }
+    }
+    // directories come after their contents, so they are empty by the time they are removed
+    for (path, is_dir) in stale {
+        if is_dir {
+            std::fs::remove_dir(&path)
+        } else {
+            std::fs::remove_file(&path)
+        }
+        .with_context(|| format!("Failed to remove {path:?}"))?;
+    }
+
+    changed.sort_unstable();
+    Ok(size)
+}
+
+/// Makes `target` a copy of the linked mod's `patchers` folder at `source` like [`sync`], or
+/// removes it if the mod has none. The files that are copied or removed are added to `changed`,
+/// relative to the mod's folder. Returns the total size of the files, and the files relative to
+/// the profile's [`PATCHERS_FOLDER`], as recorded for installed packages.
+fn sync_patchers(
+    source: &Path,
+    target: &Path,
+    changed: &mut Vec<String>,
+) -> Result<(u64, Vec<PathBuf>)> {
+    if !source.is_dir() {
+        match std::fs::remove_dir_all(target) {
+            Ok(()) => {}
+            Err(e) if e.is_not_found() => {}
+            Err(e) => return Err(e).with_context(|| format!("Failed to remove {target:?}")),
+        }
+        return Ok((0, Vec::new()));
+    }
+
+    let mut patchers_changed = Vec::new();
+    let size = sync(source, target, None, &mut patchers_changed)?;
+    changed.extend(
+        patchers_changed
+            .into_iter()
+            .map(|file| format!("{PATCHERS_FOLDER}/{file}")),
+    );
+
+    let folder = Path::new(target.file_name().context("Patchers folder has no name")?);
+    let mut files = Vec::new();
+    for entry in WalkDir::new(target).min_depth(1).sort_by_file_name() {
+        let entry = entry?;
+        if entry.file_type().is_file() {
+            files.push(folder.join(entry.path().strip_prefix(target)?));
+        }
+    }
+    Ok((size, files))
+}
+
+/// Returns `true` if the file at `dest` was copied from the one described by `source` since it
+/// last changed.
+fn is_up_to_date(source: &std::fs::Metadata, dest: &Path) -> bool {
+    let Ok(dest) = std::fs::metadata(dest) else {
+        return false;
+    };
+    dest.len() == source.len()
+        && matches!((source.modified(), dest.modified()), (Ok(a), Ok(b)) if b >= a)
+}
+
+fn spawn_watcher(log: &slog::Logger, app: &AppHandle, id: Uuid, name: String, source: PathBuf) {
+    let log = log.new(slog::o!("profile" => id.to_string(), "mod" => name.clone()));
+    let app = app.clone();
+    let mut watchers = WATCHERS.lock();
+    if watchers.contains_key(&(id, name.clone())) {
+        return;
+    }
+    let task = tauri::async_runtime::spawn({
+        let name = name.clone();
+        async move {
+            if let Err(e) = watch(&log, &app, id, &name, &source).await {
+                warn!(log, "Failed to watch {source:?}: {e:#}");
+            }
+            WATCHERS.lock().remove(&(id, name));
+        }
+    });
+    watchers.insert((id, name), task);
+}
+
+async fn watch(
+    log: &slog::Logger,
+    app: &AppHandle,
+    id: Uuid,
+    name: &str,
+    source: &Path,
+) -> Result<()> {
+    let mut watcher = DebouncedWatcher::new(source, DEBOUNCE)?;
+    debug!(log, "Watching {source:?}");
+
+    while watcher.next(log).await.is_some() {
+        match refresh(id, name, source).await {
+            Ok(files) => {
+                debug!(log, "Copied {source:?} again"; "changed" => files.len());
+                if !files.is_empty() {
+                    request_reload(log, app, id, name, files).await;
+                }
+                app.emit(
+                    EVENT,
+                    DevModSynced {
+                        profile: id,
+                        r#mod: format!("{DEV_OWNER}-{name}"),
+                    },
+                )?;
+            }
+            // the build may still be writing files, in which case it will be copied again once
+            // it is done
+            Err(e) => warn!(log, "Failed to copy {source:?}: {e:#}"),
+        }
+    }
+    Ok(())
+}
+
+/// Asks the games running the profile to reload the linked mod.
+async fn request_reload(
+    log: &slog::Logger,
+    app: &AppHandle,
+    id: Uuid,
+    name: &str,
+    files: Vec<String>,
+) {
+    let ipc_state = app.state::<IpcState>();
+    let conns = {
+        let mut connections = CONNECTIONS.lock();
+        let Some(conn_ids) = connections.get_mut(&id) else {
+            return;
+        };
+        let mut conns = Vec::new();
+        // connections are dropped once their game exits
+        conn_ids.retain(|&conn_id| match ipc_state.get_conn(conn_id) {
+            Some(conn) => {
+                conns.push((conn_id, conn));
+                true
+            }
+            None => false,
+        });
+        if conn_ids.is_empty() {
+            connections.remove(&id);
+        }
+        conns
+    };
+    for (conn_id, conn) in conns {
+        let msg = S2CMessage::ReloadMod {
+            r#mod: format!("{DEV_OWNER}-{name}"),
+            files: files.clone(),
+        };
+        if let Err(e) = conn.send_async(msg).await {
+            warn!(
+                log,
+                "Failed to ask connection {conn_id} to reload the mod: {e}"
+            );
+        }
+    }
+}
+
+/// Starts watching the mods linked into the profile that aren't already watched, copying them
+/// again first in case they changed while they weren't.
+pub async fn watch_profile(log: &slog::Logger, app: &AppHandle, id: Uuid) -> Result<()> {
+    if crate::profiles::read_only::is_read_only() {
+        // the copies in the profile are used as they are
+        return Ok(());
+    }
+    let path = profile_path(id).join(MODS_FOLDER);
+    let mut iter = match tokio::fs::read_dir(&path).await {
+        Ok(t) => t,
+        Err(e) if e.is_not_found() => return Ok(()),
+        Err(e) => return Err(anyhow::Error::from(e).context(format!("Failed to read {path:?}"))),
+    };
+    while let Some(e) = iter.next_entry().await? {
+        let Some(manifest) = read_mod_manifest::<LinkedManifest>(&e.path()).await? else {
+            continue;
+        };
+        let Some(source) = manifest.dev_source else {
+            continue;
+        };
+        if WATCHERS.lock().contains_key(&(id, manifest.name.clone())) {
+            continue;
+        }
+        if let Err(e) = refresh(id, &manifest.name, &source).await {
+            // the folder may have been moved or deleted, but the last copy can still be used
+            warn!(log, "Failed to copy {source:?}: {e:#}");
+            continue;
+        }
+        spawn_watcher(log, app, id, manifest.name, source);
+    }
+    Ok(())
+}
+
+/// Asks the game connected through `conn_id` to reload the profile's linked mods whenever they
+/// change, until it exits.
+pub fn attach(id: Uuid, conn_id: ConnectionId) {
+    CONNECTIONS.lock().entry(id).or_default().push(conn_id);
+}
+
+/// Stops watching the linked mod, if it is being watched.
+pub fn unwatch(id: Uuid, name: &str) {
+    if let Some(task) = WATCHERS.lock().remove(&(id, name.to_owned())) {
+        task.abort();
+    }
+}
+
+/// Stops watching all mods linked into the profile.
+pub fn unwatch_profile(id: Uuid) {
+    WATCHERS.lock().retain(|(profile, _), task| {
+        if *profile == id {
+            task.abort();
+        }
+        *profile != id
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sync() {
+        let source = tempfile::tempdir().unwrap();
+        let target = tempfile::tempdir().unwrap();
+        std::fs::create_dir(source.path().join("plugins")).unwrap();
+        std::fs::write(source.path().join("plugins/Mod.dll"), b"build 1").unwrap();
+        std::fs::write(source.path().join("old.txt"), b"old").unwrap();
+        std::fs::write(target.path().join(MANIFEST_FILE_NAME), b"{}").unwrap();
+
+        let mut changed = Vec::new();
+        assert_eq!(
+            sync(source.path(), target.path(), None, &mut changed).unwrap(),
+            10
+        );
+        assert_eq!(changed, ["old.txt", "plugins/Mod.dll"]);
+        assert_eq!(
+            std::fs::read(target.path().join("plugins/Mod.dll")).unwrap(),
+            b"build 1"
+        );
+
+        std::fs::remove_file(source.path().join("old.txt")).unwrap();
+        std::fs::remove_dir_all(source.path().join("plugins")).unwrap();
+        std::fs::create_dir(source.path().join("patchers")).unwrap();
+        std::fs::write(source.path().join("patchers/Patcher.dll"), b"patcher").unwrap();
+
+        let mut changed = Vec::new();
+        assert_eq!(
+            sync(source.path(), target.path(), None, &mut changed).unwrap(),
+            7
+        );
+        assert_eq!(
+            changed,
+            ["old.txt", "patchers/Patcher.dll", "plugins/Mod.dll"]
+        );
+
+        // nothing changed since the last copy
+        let mut changed = Vec::new();
+        sync(source.path(), target.path(), None, &mut changed).unwrap();
+        assert!(changed.is_empty());
+        assert!(!target.path().join("old.txt").exists());
+        assert!(!target.path().join("plugins").exists());
+        assert_eq!(
+            std::fs::read(target.path().join("patchers/Patcher.dll")).unwrap(),
+            b"patcher"
+        );
+        // the manifest is Manderrow's, not the mod's
+        assert!(target.path().join(MANIFEST_FILE_NAME).exists());
+    }
+
+    #[test]
+    fn test_sync_patchers() {
+        let source = tempfile::tempdir().unwrap();
+        let profile = tempfile::tempdir().unwrap();
+        let target = profile.path().join("mods").join("_dev-Mod");
+        let patchers_target = profile.path().join("patchers").join("_dev-Mod");
+        std::fs::create_dir_all(source.path().join("patchers/nested")).unwrap();
+        std::fs::write(source.path().join("Mod.dll"), b"mod").unwrap();
+        std::fs::write(
+            source.path().join("patchers/nested/Patcher.dll"),
+            b"patcher",
+        )
+        .unwrap();
+
+        let mut changed = Vec::new();
+        let size = sync(
+            source.path(),
+            &target,
+            Some(Path::new(PATCHERS_FOLDER)),
+            &mut changed,
+        )
+        .unwrap();
+        assert_eq!(size, 3);
+        let (size, files) = sync_patchers(
+            &source.path().join(PATCHERS_FOLDER),
+            &patchers_target,
+            &mut changed,
+        )
+        .unwrap();
+        assert_eq!(size, 7);
+        assert_eq!(changed, ["Mod.dll", "patchers/nested/Patcher.dll"]);
+        assert_eq!(files, [Path::new("_dev-Mod/nested/Patcher.dll")]);
+        assert!(!target.join(PATCHERS_FOLDER).exists());
+        assert_eq!(
+            std::fs::read(patchers_target.join("nested/Patcher.dll")).unwrap(),
+            b"patcher"
+        );
+
+        std::fs::remove_dir_all(source.path().join(PATCHERS_FOLDER)).unwrap();
+        let (size, files) = sync_patchers(
+            &source.path().join(PATCHERS_FOLDER),
+            &patchers_target,
+            &mut Vec::new(),
+        )
+        .unwrap();
+        assert_eq!(size, 0);
+        assert!(files.is_empty());
+        assert!(!patchers_target.exists());
+    }
+
+    #[test]
+    fn test_sanitize_name() {
+        assert_eq!(sanitize_name("My Mod-1.0"), "My_Mod_1_0");
+        assert_eq!(sanitize_name("Plain_Name"), "Plain_Name");
+    }
+}
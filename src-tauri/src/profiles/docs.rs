@@ -0,0 +1,121 @@
+//! Offline copies of the README and CHANGELOG of installed mods.
+//!
+//! Thunderstore packages usually ship their README, but not always their CHANGELOG. If enabled in
+//! the settings, any that a package lacks are fetched while installing it and stored in the mod's
+//! folder, so the documentation can be read offline and always matches the installed version.
+
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use anyhow::{Context as _, Result};
+use packed_semver::Version;
+use slog::{debug, warn};
+use tauri::AppHandle;
+use uuid::Uuid;
+
+use crate::mod_index::thunderstore::{fetch_mod_markdown, MarkdownResponse, ModMarkdown};
+use crate::tasks;
+use crate::util::IoErrorKindExt as _;
+
+use super::{profile_path, push_mod_folder, MODS_FOLDER};
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+pub fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+fn file_name(doc: ModMarkdown) -> &'static str {
+    match doc {
+        ModMarkdown::Readme => "README.md",
+        ModMarkdown::Changelog => "CHANGELOG.md",
+    }
+}
+
+/// Finds the file for `doc` at the root of the mod folder at `path`, ignoring case.
+async fn find_doc(path: &Path, doc: ModMarkdown) -> Result<Option<PathBuf>> {
+    let name = file_name(doc);
+    let mut iter = match tokio::fs::read_dir(path).await {
+        Ok(t) => t,
+        Err(e) if e.is_not_found() => return Ok(None),
+        Err(e) => return Err(e).with_context(|| format!("Failed to read {path:?}")),
+    };
+    while let Some(e) = iter.next_entry().await? {
+        if e.file_name()
+            .to_str()
+            .is_some_and(|s| s.eq_ignore_ascii_case(name))
+            && e.file_type().await?.is_file()
+        {
+            return Ok(Some(e.path()));
+        }
+    }
+    Ok(None)
+}
+
+/// Stores the documentation that is missing from the package extracted to `path`.
+///
+/// Failures are only logged, as the mod is usable without its documentation.
+pub async fn store_missing_docs(
+    app: &AppHandle,
+    log: &slog::Logger,
+    path: &Path,
+    owner: &str,
+    name: &str,
+    version: Version,
+    handle: &tasks::TaskHandle,
+) {
+    for doc in [ModMarkdown::Readme, ModMarkdown::Changelog] {
+        let result = async {
+            if find_doc(path, doc).await?.is_some() {
+                return Ok(());
+            }
+            let json = fetch_mod_markdown(
+                Some(app),
+                log,
+                owner,
+                name,
+                version,
+                doc,
+                Some(handle.allocate_dependency(app)?),
+            )
+            .await?;
+            match serde_json::from_str::<MarkdownResponse>(&json)?.markdown {
+                Some(markdown) => tokio::fs::write(path.join(file_name(doc)), markdown).await?,
+                None => debug!(log, "{owner}-{name}-{version} has no {}", file_name(doc)),
+            }
+            Ok::<_, anyhow::Error>(())
+        }
+        .await;
+        if let Err(e) = result {
+            warn!(
+                log,
+                "Failed to store {} of {owner}-{name}-{version}: {e:#}",
+                file_name(doc)
+            );
+        }
+    }
+}
+
+/// Returns the stored documentation of the mod installed in the profile, or `None` if there is
+/// none.
+pub async fn read_doc(
+    id: Uuid,
+    owner: &str,
+    name: &str,
+    doc: ModMarkdown,
+) -> Result<Option<String>> {
+    let mut path = profile_path(id).join(MODS_FOLDER);
+    push_mod_folder(&mut path, owner, name);
+    let Some(path) = find_doc(&path, doc).await? else {
+        return Ok(None);
+    };
+    match tokio::fs::read(&path).await {
+        Ok(bytes) => Ok(Some(String::from_utf8_lossy(&bytes).into_owned())),
+        Err(e) if e.is_not_found() => Ok(None),
+        Err(e) => Err(e).with_context(|| format!("Failed to read {path:?}")),
+    }
+}
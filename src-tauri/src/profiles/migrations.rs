@@ -0,0 +1,119 @@
+//! Migrations of `profile.json` between versions of its schema.
+//!
+//! Every `profile.json` records the [schema version](super::Profile::schema_version) it was
+//! written with. Files written before the schema was versioned have none and are treated as
+//! version 0. When an older file is read, the migrations from its version up to
+//! [`CURRENT_SCHEMA_VERSION`] are applied to the raw JSON in order, and the upgraded file is
+//! written back.
+//!
+//! Fields that can simply be defaulted when missing don't need a migration. For any other change
+//! to the schema, add a migration to [`MIGRATIONS`], which bumps [`CURRENT_SCHEMA_VERSION`]. Never
+//! change an existing migration, as profiles may have been migrated by it already.
+
+use anyhow::{ensure, Context as _, Result};
+use serde_json::{Map, Value};
+
+const SCHEMA_VERSION_KEY: &str = "schema_version";
+
+type Migration = fn(&mut Map<String, Value>) -> Result<()>;
+
+/// `MIGRATIONS[i]` upgrades a profile from schema version `i` to version `i + 1`.
+const MIGRATIONS: &[Migration] = &[v0_to_v1];
+
+pub const CURRENT_SCHEMA_VERSION: u32 = MIGRATIONS.len() as u32;
+
+/// Version 1 introduced the schema version itself, and always writes out whether the profile is
+/// pinned.
+fn v0_to_v1(profile: &mut Map<String, Value>) -> Result<()> {
+    for key in ["name", "game"] {
+        ensure!(
+            profile.get(key).is_some_and(Value::is_string),
+            "Profile is missing its {key}"
+        );
+    }
+    profile
+        .entry("pinned")
+        .or_insert_with(|| Value::Bool(false));
+    Ok(())
+}
+
+/// Upgrades `profile` to [`CURRENT_SCHEMA_VERSION`]. Returns `false` if it was already current.
+pub fn migrate(profile: &mut Value) -> Result<bool> {
+    let profile = profile
+        .as_object_mut()
+        .context("Profile is not an object")?;
+    let version = match profile.get(SCHEMA_VERSION_KEY) {
+        Some(version) => version
+            .as_u64()
+            .and_then(|v| u32::try_from(v).ok())
+            .with_context(|| format!("Invalid schema version {version}"))?,
+        None => 0,
+    };
+    ensure!(
+        version <= CURRENT_SCHEMA_VERSION,
+        "Profile has schema version {version}, but only up to {CURRENT_SCHEMA_VERSION} is \
+         supported. It was probably written by a newer version of Manderrow."
+    );
+    if version == CURRENT_SCHEMA_VERSION {
+        return Ok(false);
+    }
+    for (from, migration) in MIGRATIONS.iter().enumerate().skip(version as usize) {
+        migration(profile)
+            .with_context(|| format!("Failed to migrate profile from schema version {from}"))?;
+        profile.insert(SCHEMA_VERSION_KEY.to_owned(), (from + 1).into());
+    }
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::super::Profile;
+    use super::*;
+
+    #[test]
+    fn test_v0_to_v1() {
+        let mut profile = json!({
+            "name": "Default",
+            "game": "lethal-company",
+            "pinned": true,
+        });
+        assert!(migrate(&mut profile).unwrap());
+        assert_eq!(profile[SCHEMA_VERSION_KEY], 1);
+        let profile = serde_json::from_value::<Profile>(profile).unwrap();
+        assert_eq!(profile.name, "Default");
+        assert!(profile.pinned);
+
+        let mut profile = json!({
+            "name": "Default",
+            "game": "lethal-company",
+        });
+        assert!(migrate(&mut profile).unwrap());
+        assert_eq!(profile["pinned"], false);
+
+        assert!(migrate(&mut json!({ "name": "Default" })).is_err());
+    }
+
+    #[test]
+    fn test_current_is_unchanged() {
+        let mut profile = json!({
+            "schema_version": CURRENT_SCHEMA_VERSION,
+            "name": "Default",
+            "game": "lethal-company",
+        });
+        let before = profile.clone();
+        assert!(!migrate(&mut profile).unwrap());
+        assert_eq!(profile, before);
+    }
+
+    #[test]
+    fn test_newer_is_rejected() {
+        let mut profile = json!({
+            "schema_version": CURRENT_SCHEMA_VERSION + 1,
+            "name": "Default",
+            "game": "lethal-company",
+        });
+        assert!(migrate(&mut profile).is_err());
+    }
+}
@@ -1,4 +1,16 @@
+pub mod batch;
 pub mod commands;
+pub mod configs;
+pub mod dev_mods;
+pub mod docs;
+pub mod migrations;
+pub mod overlaps;
+pub mod paths;
+pub mod plugins;
+pub mod portable;
+pub mod read_only;
+pub mod repair;
+pub mod tools;
 
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
@@ -8,30 +20,90 @@ use anyhow::{anyhow, ensure, Context as _, Result};
 use futures_util::stream::FuturesOrdered;
 use futures_util::StreamExt as _;
 use manderrow_paths::local_data_dir;
+use manderrow_types::games::PackageLoader;
 use manderrow_types::mods::{ModAndVersion, ModId, ModMetadata, ModSpec, ModVersion};
 use manderrow_types::util::serde::IgnoredAny;
 use packed_semver::Version;
 use parking_lot::Mutex;
-use slog::{debug, error};
+use reqwest::StatusCode;
+use slog::{debug, error, warn};
 use smol_str::SmolStr;
 use tauri::AppHandle;
 use uuid::Uuid;
 
 use crate::installing::{
-    create_dir_if_not_exists, install_folder, prepare_install_zip, uninstall_package, StagedPackage,
+    create_dir_if_not_exists, hash_file, install_folder, prepare_install_zip, read_package_files,
+    uninstall_package, StagedPackage,
 };
+use crate::mod_index::sizes;
+use crate::tasks::{self, SuccessInfo};
+use crate::util::http::{has_status, is_web_url};
 use crate::util::{hyphenated_uuid, IoErrorKindExt as _};
-use crate::{tasks, Reqwest};
 
 pub static PROFILES_DIR: LazyLock<PathBuf> = LazyLock::new(|| local_data_dir().join("profiles"));
 
 #[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
 #[serde(deny_unknown_fields)]
 pub struct Profile {
+    /// The version of the schema that the profile is stored with. See [`migrations`].
+    #[serde(default)]
+    pub schema_version: u32,
     pub name: SmolStr,
     pub game: SmolStr,
     #[serde(default)]
     pub pinned: bool,
+    #[serde(default)]
+    pub launch: LaunchOptions,
+    /// Replacements of mods' files that were interrupted. See [`repair`].
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub incomplete_replacements: Vec<repair::IncompleteReplacement>,
+}
+
+/// Per-profile options for launching the profile. Most are translated into loader configuration
+/// by the mod loader backends.
+#[derive(Clone, Debug, Default, serde::Deserialize, serde::Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct LaunchOptions {
+    /// Whether the mod loader should open its own console window.
+    #[serde(default)]
+    pub show_console: bool,
+    #[serde(default)]
+    pub log_level: LoaderLogLevel,
+    /// If set, the game is launched by running this executable directly instead of through a
+    /// store, e.g. for DRM-free copies of the game.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub game_executable: Option<PathBuf>,
+    #[serde(default)]
+    pub loader_version: LoaderVersion,
+    /// Commands to run the game through, like `gamemoderun mangohud`. If unset, the ones from the
+    /// settings are used.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wrapper: Option<String>,
+}
+
+/// The most verbose level of messages that the mod loader should log.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LoaderLogLevel {
+    Error,
+    Warning,
+    #[default]
+    Info,
+    Debug,
+    All,
+}
+
+/// Which build of the mod loader to launch the profile with. Only BepInEx can be pinned.
+#[derive(Clone, Debug, Default, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LoaderVersion {
+    /// The build that this version of Manderrow was released with.
+    #[default]
+    Stable,
+    /// The latest build from the loader's CI, which is untested.
+    Ci,
+    /// A specific release, like `5.4.23.2+build.20`.
+    Release(SmolStr),
 }
 
 #[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
@@ -47,6 +119,10 @@ pub enum ReadProfileError {
     Io(#[from] std::io::Error),
     #[error("failed to parse profile.json: {0}")]
     Decoding(#[from] serde_json::Error),
+    #[error("failed to migrate profile.json: {0:#}")]
+    Migration(anyhow::Error),
+    #[error(transparent)]
+    WriteMigrated(#[from] WriteProfileError),
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -57,8 +133,16 @@ pub enum WriteProfileError {
     Encoding(#[from] serde_json::Error),
 }
 
+/// Reads the profile at `path`, migrating it to the current schema if it is older. The migrated
+/// profile is written back unless profiles are read-only.
 pub async fn read_profile_file(path: &Path) -> Result<Profile, ReadProfileError> {
-    Ok(serde_json::from_slice(&tokio::fs::read(path).await?)?)
+    let mut profile = serde_json::from_slice(&tokio::fs::read(path).await?)?;
+    let migrated = migrations::migrate(&mut profile).map_err(ReadProfileError::Migration)?;
+    let profile = serde_json::from_value(profile)?;
+    if migrated && !read_only::is_read_only() {
+        write_profile_file(path, &profile).await?;
+    }
+    Ok(profile)
 }
 
 pub async fn write_profile_file(path: &Path, metadata: &Profile) -> Result<(), WriteProfileError> {
@@ -133,9 +217,12 @@ pub async fn create_profile(game: SmolStr, name: SmolStr) -> Result<Uuid> {
     write_profile_file(
         &path,
         &Profile {
+            schema_version: migrations::CURRENT_SCHEMA_VERSION,
             name,
             game,
             pinned: false,
+            launch: LaunchOptions::default(),
+            incomplete_replacements: Vec::new(),
         },
     )
     .await
@@ -144,6 +231,7 @@ pub async fn create_profile(game: SmolStr, name: SmolStr) -> Result<Uuid> {
 }
 
 pub async fn delete_profile(id: Uuid) -> Result<()> {
+    dev_mods::unwatch_profile(id);
     let path = profile_path(id);
     tokio::fs::remove_dir_all(&path)
         .await
@@ -151,11 +239,69 @@ pub async fn delete_profile(id: Uuid) -> Result<()> {
     Ok(())
 }
 
+/// Pins the profile's mod loader to `version`, after checking that the version exists.
+pub async fn set_loader_version(id: Uuid, version: LoaderVersion) -> Result<()> {
+    let mut profile = read_profile(id).await?;
+    if version != LoaderVersion::Stable {
+        let game = crate::games::games_by_id()?
+            .get(&*profile.game)
+            .copied()
+            .with_context(|| format!("Unrecognized game {:?}", profile.game))?;
+        ensure!(
+            matches!(game.package_loader, PackageLoader::BepInEx),
+            "Only BepInEx can be pinned, but {} uses {:?}",
+            game.name,
+            game.package_loader
+        );
+    }
+    if let LoaderVersion::Release(release) = &version {
+        let releases = crate::launching::list_bep_in_ex_releases()
+            .await
+            .context("Failed to list BepInEx releases")?;
+        ensure!(
+            releases.iter().any(|r| r == release),
+            "BepInEx {release} has not been released"
+        );
+    }
+    profile.launch.loader_version = version;
+    write_profile(id, &profile).await?;
+    Ok(())
+}
+
 pub const MODS_FOLDER: &str = "mods";
 pub const CONFIG_FOLDER: &str = "config";
 pub const PATCHERS_FOLDER: &str = "patchers";
+pub const TOOLS_FOLDER: &str = "tools";
 
-const MANIFEST_FILE_NAME: &str = "manderrow_mod.json";
+pub const MANIFEST_FILE_NAME: &str = "manderrow_mod.json";
+
+/// Reads the [`MANIFEST_FILE_NAME`] of the mod installed as `owner`-`name` in the profile at
+/// `profile`, or returns `None` if it is not installed.
+pub async fn read_installed_manifest<T: serde::de::DeserializeOwned>(
+    profile: &Path,
+    owner: &str,
+    name: &str,
+) -> Result<Option<T>> {
+    let mut path = profile.join(MODS_FOLDER);
+    push_mod_folder(&mut path, owner, name);
+    read_mod_manifest(&path).await
+}
+
+/// Like [`read_installed_manifest`], for the mod installed in `mod_folder`.
+pub(crate) async fn read_mod_manifest<T: serde::de::DeserializeOwned>(
+    mod_folder: &Path,
+) -> Result<Option<T>> {
+    let path = mod_folder.join(MANIFEST_FILE_NAME);
+    match tokio::fs::read(&path).await {
+        Ok(t) => Ok(Some(serde_json::from_slice(&t).with_context(|| {
+            format!("Failed to parse mod manifest {path:?}")
+        })?)),
+        Err(e) if e.is_not_found() => Ok(None),
+        Err(e) => {
+            Err(anyhow::Error::from(e).context(format!("Failed to read mod manifest {path:?}")))
+        }
+    }
+}
 
 pub async fn get_profile_mods(id: Uuid) -> Result<tauri::ipc::Response> {
     let mut path = profile_path(id);
@@ -203,15 +349,84 @@ pub async fn get_profile_mods(id: Uuid) -> Result<tauri::ipc::Response> {
 
 pub async fn install_profile_mod(
     app: &AppHandle,
-    reqwest: &Reqwest,
     id: Uuid,
     r#mod: ModMetadata<'_>,
     version: ModVersion<'_>,
+    allow_overlaps: bool,
     task_id: tasks::Id,
-) -> Result<()> {
+) -> Result<Vec<crate::compat::CompatWarning>> {
+    install_profile_mod_version(
+        app,
+        id,
+        r#mod.owner,
+        r#mod.name,
+        version.version_number,
+        allow_overlaps,
+        false,
+        task_id,
+    )
+    .await
+}
+
+/// Installs the version of the mod that is already installed in the profile again. If
+/// `force_download` is true, the mod is downloaded even if it is cached, in case the cached copy
+/// is corrupt.
+pub async fn reinstall_profile_mod(
+    app: &AppHandle,
+    id: Uuid,
+    owner: &str,
+    name: &str,
+    force_download: bool,
+    task_id: tasks::Id,
+) -> Result<Vec<crate::compat::CompatWarning>> {
+    #[derive(serde::Deserialize)]
+    struct InstalledManifest {
+        version: InstalledVersion,
+        #[serde(default)]
+        dev_source: Option<PathBuf>,
+    }
+    #[derive(serde::Deserialize)]
+    struct InstalledVersion {
+        version_number: Version,
+    }
+
+    let manifest = read_installed_manifest::<InstalledManifest>(&profile_path(id), owner, name)
+        .await?
+        .with_context(|| format!("{owner}-{name} is not installed"))?;
+
+    // a linked mod can only be copied from its folder again
+    if let Some(source) = manifest.dev_source {
+        dev_mods::refresh(id, name, &source).await?;
+        return Ok(Vec::new());
+    }
+
+    install_profile_mod_version(
+        app,
+        id,
+        owner,
+        name,
+        manifest.version.version_number,
+        // the mod was already installed alongside the other mods
+        true,
+        force_download,
+        task_id,
+    )
+    .await
+}
+
+async fn install_profile_mod_version(
+    app: &AppHandle,
+    id: Uuid,
+    owner: &str,
+    name: &str,
+    version: Version,
+    allow_overlaps: bool,
+    force_download: bool,
+    task_id: tasks::Id,
+) -> Result<Vec<crate::compat::CompatWarning>> {
     let log = slog_scope::logger();
 
-    if r#mod.owner == "BepInEx" && r#mod.name == "BepInExPack" {
+    if owner == "BepInEx" && name == "BepInExPack" {
         return Err(anyhow!(
             "BepInEx pack is managed by manderrow and will be installed automatically if required"
         ));
@@ -223,36 +438,98 @@ pub async fn install_profile_mod(
     profile_path.pop();
 
     let mod_index = crate::mod_index::read_mod_index(&game).await?;
+    let community_url = crate::games::games_by_id()?
+        .get(&*game)
+        .and_then(|game| crate::games::community_url(game));
+
+    let install_size = sizes::install_size(&mod_index, owner, name, version).await?;
+    sizes::check_install_space(&profile_path, install_size)?;
 
     let seen = Mutex::new(HashMap::new());
     install_profile_mod_inner(
         &log,
         app,
-        reqwest,
         id,
         &profile_path,
         &mod_index,
-        r#mod.owner,
-        r#mod.name,
-        version.version_number,
+        community_url,
+        owner,
+        name,
+        version,
+        allow_overlaps,
+        force_download,
         task_id,
         &seen,
     )
     .await?;
 
-    for (id, m) in seen.into_inner() {
-        debug!(log, "committing installation of {}-{}", id, m.version);
-        for transaction in m.transactions {
-            transaction.commit(&log).await?;
-        }
-    }
+    commit_installs(&log, id, seen.into_inner()).await?;
 
-    Ok(())
+    // the mod is installed either way, so don't fail because of the check
+    Ok(crate::compat::check_profile_or_log(&log, id, &game).await)
+}
+
+/// The contents of [`MANIFEST_FILE_NAME`].
+#[derive(serde::Serialize)]
+struct InstalledModManifest<'a> {
+    #[serde(flatten)]
+    r#mod: ModAndVersion<'a>,
+    /// Whether the package was extracted from a previously downloaded copy.
+    installed_from_cache: bool,
+    /// The files that the package installed to the profile's [`PATCHERS_FOLDER`], relative to it.
+    patchers: Vec<PathBuf>,
+    /// Present if the package is a tool, whose files were installed to the profile's
+    /// [`TOOLS_FOLDER`]. See [`tools`].
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tool: Option<tools::ToolManifest>,
+    /// Present if the mod is linked from a folder outside of the profile. See [`dev_mods`].
+    #[serde(skip_serializing_if = "Option::is_none")]
+    dev_source: Option<PathBuf>,
+    /// Whether the mod is disabled. See [`batch::set_mod_enabled`].
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    disabled: bool,
+}
+
+/// The parts of [`InstalledModManifest`] that are needed to uninstall the mod.
+#[derive(serde::Deserialize)]
+struct InstalledModFiles {
+    /// `None` if the mod was installed before patcher files were recorded.
+    #[serde(default)]
+    patchers: Option<Vec<PathBuf>>,
+    #[serde(default)]
+    tool: Option<IgnoredAny>,
+    #[serde(default)]
+    dev_source: Option<IgnoredAny>,
+    /// See [`batch::set_mod_enabled`].
+    #[serde(default)]
+    disabled: bool,
 }
 
 struct InstallingMod {
     version: Version,
     transactions: Vec<crate::installing::ReplaceTransaction>,
+    overlaps: Vec<overlaps::FileOverlap>,
+}
+
+/// Snapshots the profile's config and then commits the installations staged by
+/// [`install_profile_mod_inner`].
+async fn commit_installs(
+    log: &slog::Logger,
+    id: Uuid,
+    seen: HashMap<ModId<'_>, InstallingMod>,
+) -> Result<()> {
+    configs::backup::take_snapshot(log, id, configs::backup::SnapshotReason::ModInstall)
+        .await
+        .context("Failed to snapshot config before installing")?;
+
+    for (mod_id, m) in seen {
+        debug!(log, "committing installation of {}-{}", mod_id, m.version);
+        for transaction in m.transactions {
+            repair::commit(log, id, &mod_id.to_string(), transaction).await?;
+        }
+        overlaps::record_overlaps(id, mod_id.to_string().into(), m.overlaps).await?;
+    }
+    Ok(())
 }
 
 /// `game` must match the profile's game.
@@ -262,13 +539,15 @@ struct InstallingMod {
 async fn install_profile_mod_inner<'a, 'b>(
     log: &slog::Logger,
     app: &AppHandle,
-    reqwest: &Reqwest,
     id: Uuid,
     profile_path: &Path,
     mod_index: &'a crate::mod_index::ModIndexReadGuard,
+    community_url: Option<&'a str>,
     mod_owner: &'a str,
     mod_name: &'a str,
     mod_version: Version,
+    allow_overlaps: bool,
+    force_download: bool,
     task_id: tasks::Id,
     seen: &Mutex<HashMap<ModId<'a>, InstallingMod>>,
 ) -> Result<()> {
@@ -285,6 +564,7 @@ async fn install_profile_mod_inner<'a, 'b>(
             InstallingMod {
                 version: mod_version,
                 transactions: Vec::new(),
+                overlaps: Vec::new(),
             },
         )
         .is_err()
@@ -301,7 +581,7 @@ async fn install_profile_mod_inner<'a, 'b>(
     .create(app)
     .await?;
 
-    let (handle, ()) = tasks::run_non_terminal(Some(handle), |handle| async move {
+    let (handle, success) = tasks::run_non_terminal(Some(handle), |handle| async move {
         let Some(m) = crate::mod_index::get_one_from_mod_index(
             mod_index,
             ModId {
@@ -327,6 +607,7 @@ async fn install_profile_mod_inner<'a, 'b>(
 
         let mut mod_folder_path = profile_path.join(MODS_FOLDER);
         let mut patchers_folder_path = profile_path.join(PATCHERS_FOLDER);
+        let mut tools_folder_path = profile_path.join(TOOLS_FOLDER);
 
         create_dir_if_not_exists(&patchers_folder_path)
             .await
@@ -334,6 +615,7 @@ async fn install_profile_mod_inner<'a, 'b>(
 
         push_mod_folder(&mut mod_folder_path, mod_owner, mod_name);
         push_mod_folder(&mut patchers_folder_path, mod_owner, mod_name);
+        push_mod_folder(&mut tools_folder_path, mod_owner, mod_name);
 
         let url = format!(
             "https://gcdn.thunderstore.io/live/repository/packages/{}-{}-{}.zip",
@@ -357,13 +639,16 @@ async fn install_profile_mod_inner<'a, 'b>(
                 install_profile_mod_inner(
                     log,
                     app,
-                    reqwest,
                     id,
                     profile_path,
                     mod_index,
+                    community_url,
                     mod_spec.id().owner.0,
                     mod_spec.id().name.0,
                     mod_spec.version,
+                    allow_overlaps,
+                    // only the requested mod is suspected to be corrupt
+                    false,
                     tasks::allocate_task(),
                     seen,
                 )
@@ -372,17 +657,59 @@ async fn install_profile_mod_inner<'a, 'b>(
         ))
         .await?;
 
-        let mod_temp_dir = prepare_install_zip(
+        let title = format!("{mod_owner}-{mod_name}-{mod_version}");
+        let cache =
+            || Some(crate::installing::CacheOptions::by_url().force_download(force_download));
+        // extraction only starts once the download succeeds, so a retry can reuse the sub-task
+        let extract_task_id = handle.allocate_dependency(app)?;
+        let (mod_temp_dir, success) = match prepare_install_zip(
             Some(app),
             &log,
-            reqwest,
-            format!("{mod_owner}-{mod_name}-{mod_version}"),
+            title.clone(),
             &url,
-            Some(crate::installing::CacheOptions::by_url()),
+            cache(),
             &mod_folder_path,
             Some(handle.allocate_dependency(app)?),
+            Some(extract_task_id),
         )
-        .await?;
+        .await
+        {
+            // the version may have been renamed or moved, but Thunderstore still knows where it is
+            Err(e)
+                if has_status(&e, StatusCode::NOT_FOUND)
+                    && !version.download_url.is_empty()
+                    && *version.download_url != url =>
+            {
+                let download_url = &*version.download_url;
+                warn!(log, "{url:?} was not found, retrying from {download_url:?}");
+                prepare_install_zip(
+                    Some(app),
+                    &log,
+                    title,
+                    download_url,
+                    cache(),
+                    &mod_folder_path,
+                    Some(handle.allocate_dependency(app)?),
+                    Some(extract_task_id),
+                )
+                .await
+                .with_context(|| format!("Failed to download from {url:?} or {download_url:?}"))?
+            }
+            r => r?,
+        };
+
+        if docs::is_enabled() {
+            docs::store_missing_docs(
+                app,
+                log,
+                mod_temp_dir.path(),
+                mod_owner,
+                mod_name,
+                mod_version,
+                &handle,
+            )
+            .await;
+        }
 
         {
             let mut entries = Vec::new();
@@ -397,6 +724,7 @@ async fn install_profile_mod_inner<'a, 'b>(
             crate::installing::generate_temp_path(&patchers_folder_path, ".tmp-").await?;
         let patchers_og_dir = mod_temp_dir.path().join(PATCHERS_FOLDER);
         let patchers_staged: Option<StagedPackage>;
+        let mut patcher_files = Vec::new();
         match tokio::fs::rename(&patchers_og_dir, &patchers_temp_dir).await {
             Ok(()) => {
                 patchers_staged =
@@ -406,6 +734,19 @@ async fn install_profile_mod_inner<'a, 'b>(
                     tokio::fs::try_exists(patchers_staged.as_ref().unwrap().path()).await?,
                     "must exist after patchers install"
                 );
+
+                let folder = Path::new(
+                    patchers_folder_path
+                        .file_name()
+                        .context("Patchers folder has no name")?,
+                );
+                patcher_files = read_package_files(&patchers_temp_dir)
+                    .await?
+                    .context("Patchers were installed without an index")?
+                    .into_keys()
+                    .map(|path| folder.join(path))
+                    .collect();
+                patcher_files.sort();
             }
             Err(e) if e.is_not_found() => {
                 patchers_staged = None;
@@ -413,9 +754,51 @@ async fn install_profile_mod_inner<'a, 'b>(
             Err(e) => return Err(e.into()),
         }
 
+        let tool = tools::find_tool(m.categories.iter().map(|s| &**s), mod_temp_dir.path())?;
+        let tool_temp_dir;
+        let tool_staged = match tool {
+            Some(_) => {
+                create_dir_if_not_exists(&profile_path.join(TOOLS_FOLDER))
+                    .await
+                    .context("failed to create profile tools folder")?;
+                tool_temp_dir =
+                    crate::installing::generate_temp_path(&tools_folder_path, ".tmp-").await?;
+                tools::split_package(mod_temp_dir.path(), &tool_temp_dir).await?;
+                Some(install_folder(&log, &tool_temp_dir, &tools_folder_path).await?)
+            }
+            None => None,
+        };
+
         let staged = install_folder(&log, mod_temp_dir.path(), &mod_folder_path).await?;
         staged.check_with_temp_dir(&mod_temp_dir);
 
+        let file_overlaps = match read_package_files(mod_temp_dir.path()).await? {
+            Some(files) => {
+                overlaps::find_overlaps(profile_path, &mod_id.to_string(), &files).await?
+            }
+            None => Vec::new(),
+        };
+        let conflicting = file_overlaps
+            .iter()
+            .filter(|overlap| !overlap.identical)
+            .collect::<Vec<_>>();
+        if !conflicting.is_empty() {
+            let list = conflicting
+                .iter()
+                .map(|overlap| format!("{:?} (from {})", overlap.path, overlap.other))
+                .collect::<Vec<_>>()
+                .join(", ");
+            if !allow_overlaps {
+                return Err(anyhow!(
+                    "{mod_id} ships files that differ from those of other installed mods: {list}"
+                ));
+            }
+            warn!(
+                log,
+                "{mod_id} overwrites files of other installed mods: {list}"
+            );
+        }
+
         let mods_staged = StagedPackage {
             target: &mod_folder_path,
             source: crate::installing::StagedPackageSource::TempDir(mod_temp_dir),
@@ -428,32 +811,54 @@ async fn install_profile_mod_inner<'a, 'b>(
             );
         }
 
+        let disabled = batch::keep_disabled(
+            log,
+            &mod_folder_path,
+            mods_staged.path(),
+            patchers_staged.as_ref().map(|staged| staged.path()),
+        )
+        .await?;
+
         // TODO: create a dedicated ModManifest type that is saved locally, with some fields stripped (all IgnoredAny, and some others)
         tokio::task::block_in_place(|| {
             serde_json::to_writer(
                 std::io::BufWriter::new(std::fs::File::create(
                     mods_staged.path().join(MANIFEST_FILE_NAME),
                 )?),
-                &ModAndVersion {
-                    r#mod: ModMetadata {
-                        name: &m.name,
-                        owner: &m.owner,
-                        donation_link: m.donation_link.as_ref().map(|s| SmolStr::from(&**s)),
-                        date_created: m.date_created.into(),
-                        is_deprecated: m.is_deprecated,
-                        has_nsfw_content: m.has_nsfw_content,
-                        categories: m.categories.iter().map(|s| SmolStr::from(&**s)).collect(),
-                    },
-                    version: ModVersion {
-                        description: SmolStr::from(&*version.description),
-                        version_number: version.version_number.get(),
-                        dependencies: version.dependencies.iter().map(|s| s.into()).collect(),
-                        // TODO: don't save this locally
-                        downloads: version.downloads.into(),
-                        date_created: version.date_created.into(),
-                        website_url: version.website_url.as_ref().map(|s| SmolStr::from(&**s)),
-                        is_active: version.is_active,
-                        file_size: version.file_size.into(),
+                &InstalledModManifest {
+                    installed_from_cache: matches!(success, Some(SuccessInfo::Cached)),
+                    patchers: patcher_files,
+                    tool,
+                    dev_source: None,
+                    disabled,
+                    r#mod: ModAndVersion {
+                        r#mod: ModMetadata {
+                            name: &m.name,
+                            owner: &m.owner,
+                            donation_link: m
+                                .donation_link
+                                .as_ref()
+                                .map(|s| &**s)
+                                .filter(|url| is_web_url(url))
+                                .map(SmolStr::from),
+                            package_url: community_url
+                                .map(|url| format!("{url}/p/{}/{}/", &*m.owner, &*m.name).into()),
+                            date_created: m.date_created.into(),
+                            is_deprecated: m.is_deprecated,
+                            has_nsfw_content: m.has_nsfw_content,
+                            categories: m.categories.iter().map(|s| SmolStr::from(&**s)).collect(),
+                        },
+                        version: ModVersion {
+                            description: SmolStr::from(&*version.description),
+                            version_number: version.version_number.get(),
+                            dependencies: version.dependencies.iter().map(|s| s.into()).collect(),
+                            // TODO: don't save this locally
+                            downloads: version.downloads.into(),
+                            date_created: version.date_created.into(),
+                            website_url: version.website_url.as_ref().map(|s| SmolStr::from(&**s)),
+                            is_active: version.is_active,
+                            file_size: version.file_size.into(),
+                        },
                     },
                 },
             )?;
@@ -461,29 +866,38 @@ async fn install_profile_mod_inner<'a, 'b>(
         })?;
 
         let patchers_transaction = if let Some(patchers_staged) = patchers_staged {
-            Some(patchers_staged.apply(&log).await?)
+            Some(repair::apply(&log, id, &mod_id.to_string(), patchers_staged).await?)
         } else {
             None
         };
-        let mods_transaction = mods_staged.apply(&log).await?;
+        let tool_transaction = if let Some(tool_staged) = tool_staged {
+            Some(repair::apply(&log, id, &mod_id.to_string(), tool_staged).await?)
+        } else {
+            None
+        };
+        let mods_transaction = repair::apply(&log, id, &mod_id.to_string(), mods_staged).await?;
 
         // must not hold the lock across an await
         let mut seen = seen.lock();
-        let transactions = &mut seen.get_mut(&mod_id).unwrap().transactions;
+        let installing = seen.get_mut(&mod_id).unwrap();
 
         if let Some(transaction) = patchers_transaction {
-            transactions.push(transaction);
+            installing.transactions.push(transaction);
         }
-        transactions.push(mods_transaction);
+        if let Some(transaction) = tool_transaction {
+            installing.transactions.push(transaction);
+        }
+        installing.transactions.push(mods_transaction);
+        installing.overlaps = file_overlaps;
 
-        Ok(())
+        Ok(success)
     })
     .await?;
 
     // FIXME: include queued transactions in this task, handle cancellation, etc.
     handle
         .unwrap()
-        .drop(tasks::DropStatus::Success { success: None })?;
+        .drop(tasks::DropStatus::Success { success })?;
 
     Ok(())
 }
@@ -497,29 +911,167 @@ fn push_mod_folder(path: &mut PathBuf, owner: &str, name: &str) {
 pub async fn uninstall_profile_mod(id: Uuid, owner: &str, name: &str) -> Result<()> {
     let log = slog_scope::logger();
 
-    let mut path = profile_path(id);
+    let profile_path = profile_path(id);
 
-    for folder in [MODS_FOLDER, PATCHERS_FOLDER] {
-        path.push(folder);
-        push_mod_folder(&mut path, owner, name);
+    let mut path = profile_path.join(MODS_FOLDER);
+    push_mod_folder(&mut path, owner, name);
 
-        // remove the manifest so it isn't left over after uninstalling the package
-        path.push(MANIFEST_FILE_NAME);
-        match tokio::fs::remove_file(&path).await {
+    let files = read_mod_manifest::<InstalledModFiles>(&path).await?;
+    path.push(MANIFEST_FILE_NAME);
+    // remove the manifest so it isn't left over after uninstalling the package
+    match tokio::fs::remove_file(&path).await {
+        Ok(()) => {}
+        Err(e) if e.is_not_found() => {}
+        Err(e) => {
+            return Err(anyhow::Error::from(e)
+                .context(format!("Failed to remove manifest file at {path:?}")))
+        }
+    }
+    path.pop();
+
+    if files
+        .as_ref()
+        .is_some_and(|files| files.dev_source.is_some())
+    {
+        // the files are only copies of those in the linked folder
+        dev_mods::unwatch(id, name);
+        tokio::fs::remove_dir_all(&path)
+            .await
+            .with_context(|| format!("Failed to remove {path:?}"))?;
+        let patchers_path = dev_mods::patchers_folder(id, name);
+        match tokio::fs::remove_dir_all(&patchers_path).await {
             Ok(()) => {}
             Err(e) if e.is_not_found() => {}
             Err(e) => {
-                return Err(anyhow::Error::from(e)
-                    .context(format!("Failed to remove manifest file at {path:?}")))
+                return Err(
+                    anyhow::Error::from(e).context(format!("Failed to remove {patchers_path:?}"))
+                )
             }
         }
-        path.pop();
+        overlaps::forget_overlaps(id, &format!("{owner}-{name}")).await?;
+        return Ok(());
+    }
 
-        // keep_changes is true so that configs and any other changes are
-        // preserved. Zero-risk uninstallation!
+    // otherwise the renamed files would be kept as changes
+    if files.as_ref().is_some_and(|files| files.disabled) {
+        batch::rename_plugins(&log, &path, true).await?;
+    }
+
+    // keep_changes is true so that configs and any other changes are
+    // preserved. Zero-risk uninstallation!
+    uninstall_package(&log, &path, true).await?;
+
+    let mut path = profile_path.join(PATCHERS_FOLDER);
+    // the recorded patcher files have their original names
+    if files.as_ref().is_some_and(|files| files.disabled) {
+        let mut patchers_path = path.clone();
+        push_mod_folder(&mut patchers_path, owner, name);
+        if tokio::fs::try_exists(&patchers_path).await? {
+            batch::rename_plugins(&log, &patchers_path, true).await?;
+        }
+    }
+    match files.as_ref().and_then(|files| files.patchers.as_deref()) {
+        Some(files) => uninstall_patchers(&log, &path, owner, name, files).await?,
+        None => {
+            push_mod_folder(&mut path, owner, name);
+            uninstall_package(&log, &path, true).await?;
+        }
+    }
+
+    if files.is_some_and(|files| files.tool.is_some()) {
+        let mut path = profile_path.join(TOOLS_FOLDER);
+        push_mod_folder(&mut path, owner, name);
         uninstall_package(&log, &path, true).await?;
-        path.pop();
-        path.pop();
+    }
+
+    overlaps::forget_overlaps(id, &format!("{owner}-{name}")).await?;
+    Ok(())
+}
+
+/// Removes exactly the `files` that a mod installed to the profile's patchers folder at
+/// `patchers_path`, along with any directories that are left empty, so that nothing belonging to
+/// another mod is touched. Like [`uninstall_package`] with `keep_changes`, files that were changed
+/// since they were installed are kept.
+async fn uninstall_patchers(
+    log: &slog::Logger,
+    patchers_path: &Path,
+    owner: &str,
+    name: &str,
+    files: &[PathBuf],
+) -> Result<()> {
+    async fn remove(log: &slog::Logger, path: &Path) -> Result<()> {
+        match tokio::fs::remove_file(path).await {
+            Ok(()) => {
+                debug!(log, "Removed patcher file at {path:?}");
+                Ok(())
+            }
+            Err(e) if e.is_not_found() => Ok(()),
+            Err(e) => Err(anyhow::Error::from(e)
+                .context(format!("Failed to remove patcher file at {path:?}"))),
+        }
+    }
+
+    let mut mod_path = patchers_path.to_owned();
+    push_mod_folder(&mut mod_path, owner, name);
+    let folder = Path::new(
+        mod_path
+            .file_name()
+            .context("Patchers folder has no name")?,
+    );
+    // the hashes of the files as they were installed, relative to the mod's folder
+    let hashes = read_package_files(&mod_path).await?.unwrap_or_default();
+
+    let mut dirs = Vec::new();
+    for file in files {
+        ensure!(
+            file.components()
+                .all(|c| matches!(c, std::path::Component::Normal(_))),
+            "Invalid patcher file path {file:?} in manifest"
+        );
+        let path = patchers_path.join(file);
+        dirs.extend(
+            path.ancestors()
+                .skip(1)
+                .take_while(|p| *p != patchers_path)
+                .map(Path::to_owned),
+        );
+        let installed_hash = file
+            .strip_prefix(folder)
+            .ok()
+            .and_then(|file| hashes.get(file));
+        if let Some(installed_hash) = installed_hash {
+            match tokio::task::block_in_place(|| hash_file(&path)) {
+                Ok(hash) if hash != *installed_hash => {
+                    debug!(log, "Keeping changed patcher file at {path:?}");
+                    continue;
+                }
+                Ok(_) => {}
+                Err(e) if e.is_not_found() => continue,
+                Err(e) => {
+                    return Err(anyhow::Error::from(e)
+                        .context(format!("Failed to hash patcher file at {path:?}")))
+                }
+            }
+        }
+        remove(log, &path).await?;
+    }
+
+    mod_path.push(crate::installing::INDEX_FILE_NAME);
+    remove(log, &mod_path).await?;
+
+    // deepest first, so that parents are empty by the time they are reached
+    dirs.sort();
+    dirs.dedup();
+    dirs.sort_by_key(|p| std::cmp::Reverse(p.components().count()));
+    for dir in dirs {
+        match tokio::fs::remove_dir(&dir).await {
+            Ok(()) => {}
+            Err(e) if e.is_not_found() || e.kind() == std::io::ErrorKind::DirectoryNotEmpty => {}
+            Err(e) => {
+                return Err(anyhow::Error::from(e)
+                    .context(format!("Failed to remove directory at {dir:?}")))
+            }
+        }
     }
     Ok(())
 }
@@ -0,0 +1,188 @@
+//! Detection of files that are shipped by more than one mod in a profile.
+//!
+//! Mods are installed into their own folders, but mod loaders generally don't care which folder
+//! a file came from, so two mods shipping e.g. the same assembly can still clash. Overlaps where
+//! both mods ship the exact same file are harmless and only recorded, while others block the
+//! installation unless explicitly allowed.
+
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context as _, Result};
+use smol_str::SmolStr;
+use uuid::Uuid;
+
+use crate::installing::read_package_files;
+use crate::util::IoErrorKindExt as _;
+
+use super::{configs, profile_path, MODS_FOLDER};
+
+pub(super) const OVERLAPS_FILE_NAME: &str = "file_overlaps.json";
+
+/// Files at the root of every Thunderstore package, which are never loaded by mod loaders.
+pub(super) const PACKAGE_METADATA_FILES: &[&str] = &[
+    "manifest.json",
+    "icon.png",
+    "README.md",
+    "CHANGELOG.md",
+    "LICENSE",
+    "LICENSE.md",
+];
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub struct FileOverlap {
+    /// The path of the file, relative to the mod folders.
+    pub path: PathBuf,
+    /// The other mod that ships the file, as `Owner-Name`.
+    pub other: SmolStr,
+    /// Whether both mods ship identical files.
+    pub identical: bool,
+}
+
+/// Overlaps by mod, as `Owner-Name`.
+pub type FileOverlaps = BTreeMap<SmolStr, Vec<FileOverlap>>;
+
+fn is_package_metadata(path: &Path) -> bool {
+    path.parent() == Some(Path::new(""))
+        && path
+            .to_str()
+            .is_some_and(|name| PACKAGE_METADATA_FILES.contains(&name))
+}
+
+/// Compares the `incoming` files of `r#mod` against the files of the other mods installed in the
+/// profile at `profile`.
+pub async fn find_overlaps(
+    profile: &Path,
+    r#mod: &str,
+    incoming: &HashMap<PathBuf, blake3::Hash>,
+) -> Result<Vec<FileOverlap>> {
+    let path = profile.join(MODS_FOLDER);
+    let mut overlaps = Vec::new();
+    let mut iter = match tokio::fs::read_dir(&path).await {
+        Ok(t) => t,
+        Err(e) if e.is_not_found() => return Ok(overlaps),
+        Err(e) => return Err(e).context("Failed to read profile mods folder"),
+    };
+    while let Some(e) = iter.next_entry().await? {
+        if !e.file_type().await?.is_dir() {
+            continue;
+        }
+        let Some(other) = e.file_name().to_str().map(SmolStr::from) else {
+            continue;
+        };
+        if other == r#mod {
+            continue;
+        }
+        let Some(files) = read_package_files(&e.path()).await? else {
+            continue;
+        };
+        overlaps.extend(find_overlaps_with(incoming, &other, &files));
+    }
+    overlaps.sort_by(|a, b| (&a.path, &a.other).cmp(&(&b.path, &b.other)));
+    Ok(overlaps)
+}
+
+fn find_overlaps_with<'a>(
+    incoming: &'a HashMap<PathBuf, blake3::Hash>,
+    other: &'a SmolStr,
+    files: &'a HashMap<PathBuf, blake3::Hash>,
+) -> impl Iterator<Item = FileOverlap> + 'a {
+    incoming
+        .iter()
+        .filter(|(path, _)| !is_package_metadata(path))
+        .filter_map(move |(path, hash)| {
+            files.get(path).map(|other_hash| FileOverlap {
+                path: path.clone(),
+                other: other.clone(),
+                identical: hash == other_hash,
+            })
+        })
+}
+
+fn overlaps_path(id: Uuid) -> PathBuf {
+    profile_path(id).join(OVERLAPS_FILE_NAME)
+}
+
+pub async fn read_overlaps(id: Uuid) -> Result<FileOverlaps> {
+    let path = overlaps_path(id);
+    let mut bytes = match tokio::fs::read(&path).await {
+        Ok(t) => t,
+        Err(e) if e.is_not_found() => return Ok(FileOverlaps::new()),
+        Err(e) => return Err(e).with_context(|| format!("Failed to read {path:?}")),
+    };
+    simd_json::from_slice(&mut bytes).with_context(|| format!("Failed to parse {path:?}"))
+}
+
+async fn write_overlaps(id: Uuid, overlaps: &FileOverlaps) -> Result<()> {
+    let path = overlaps_path(id);
+    let bytes = simd_json::to_vec(overlaps)?;
+    tokio::task::block_in_place(|| configs::write_atomically(&path, &bytes))
+}
+
+/// Replaces the recorded overlaps of `r#mod`.
+pub async fn record_overlaps(id: Uuid, r#mod: SmolStr, overlaps: Vec<FileOverlap>) -> Result<()> {
+    let mut recorded = read_overlaps(id).await?;
+    if overlaps.is_empty() {
+        if recorded.remove(&r#mod).is_none() {
+            return Ok(());
+        }
+    } else {
+        recorded.insert(r#mod, overlaps);
+    }
+    write_overlaps(id, &recorded).await
+}
+
+/// Forgets every overlap involving `r#mod`, e.g. after it has been uninstalled.
+pub async fn forget_overlaps(id: Uuid, r#mod: &str) -> Result<()> {
+    let before = read_overlaps(id).await?;
+    let mut recorded = before.clone();
+    recorded.remove(r#mod);
+    recorded.retain(|_, overlaps| {
+        overlaps.retain(|overlap| overlap.other != r#mod);
+        !overlaps.is_empty()
+    });
+    if recorded == before {
+        return Ok(());
+    }
+    write_overlaps(id, &recorded).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_overlaps_with() {
+        let (a, b) = (blake3::hash(b"a"), blake3::hash(b"b"));
+        let incoming = HashMap::from([
+            (PathBuf::from("manifest.json"), a),
+            (PathBuf::from("plugins/Shared.dll"), a),
+            (PathBuf::from("plugins/Mine.dll"), a),
+            (PathBuf::from("config/Theirs.cfg"), a),
+        ]);
+        let files = HashMap::from([
+            (PathBuf::from("manifest.json"), b),
+            (PathBuf::from("plugins/Shared.dll"), a),
+            (PathBuf::from("plugins/Theirs.dll"), b),
+            (PathBuf::from("config/Theirs.cfg"), b),
+        ]);
+        let other = SmolStr::from("Owner-Other");
+        let mut overlaps = find_overlaps_with(&incoming, &other, &files).collect::<Vec<_>>();
+        overlaps.sort_by(|a, b| a.path.cmp(&b.path));
+        assert_eq!(
+            overlaps,
+            [
+                FileOverlap {
+                    path: PathBuf::from("config/Theirs.cfg"),
+                    other: other.clone(),
+                    identical: false,
+                },
+                FileOverlap {
+                    path: PathBuf::from("plugins/Shared.dll"),
+                    other: other.clone(),
+                    identical: true,
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,88 @@
+//! The paths of a profile's folders and of its game, resolved here so that the frontend never has
+//! to build them itself.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{ensure, Context as _, Result};
+use uuid::Uuid;
+
+use crate::games::games_by_id;
+
+use super::{profile_path, push_mod_folder, read_profile_file, CONFIG_FOLDER, MODS_FOLDER};
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct ProfilePaths {
+    pub profile: PathBuf,
+    pub mods: PathBuf,
+    pub config: PathBuf,
+    /// The directory that the game is installed in, or `None` if it isn't installed.
+    pub game_install: Option<PathBuf>,
+}
+
+/// Resolves `path` to its canonical form, without the `\\?\` prefix that Windows adds to it, which
+/// file managers and other programs tend not to understand.
+pub async fn canonicalize(path: &Path) -> Result<PathBuf> {
+    let path = tokio::fs::canonicalize(path)
+        .await
+        .with_context(|| format!("Failed to find {path:?}"))?;
+    #[cfg(windows)]
+    if let Some(s) = path.to_str().and_then(|s| s.strip_prefix(r"\\?\")) {
+        // only drive paths can go without the prefix, and only if they aren't too long for it
+        if s.as_bytes().get(1) == Some(&b':') && s.len() < 260 {
+            return Ok(PathBuf::from(s));
+        }
+    }
+    Ok(path)
+}
+
+/// Canonicalizes `path` if it exists. Folders that the game or its mods create, like the config
+/// folder, may not exist yet.
+async fn canonicalize_child(parent: &Path, name: &str) -> Result<PathBuf> {
+    let path = parent.join(name);
+    if tokio::fs::try_exists(&path).await? {
+        canonicalize(&path).await
+    } else {
+        Ok(path)
+    }
+}
+
+pub async fn get_paths(log: &slog::Logger, id: Uuid) -> Result<ProfilePaths> {
+    let profile = canonicalize(&profile_path(id)).await?;
+    let metadata = read_profile_file(&profile.join("profile.json"))
+        .await
+        .map_err(anyhow::Error::from)?;
+    let game = games_by_id()?
+        .get(&*metadata.game)
+        .copied()
+        .with_context(|| format!("Unrecognized game {:?}", metadata.game))?;
+    let game_install = match crate::launching::find_install_dir(log, game, &metadata.launch).await {
+        Ok(Some(path)) => canonicalize(&path).await.ok(),
+        Ok(None) => None,
+        Err(e) => {
+            slog::warn!(
+                log,
+                "Unable to find the install directory of {}: {e:#}",
+                game.id
+            );
+            None
+        }
+    };
+    Ok(ProfilePaths {
+        mods: canonicalize_child(&profile, MODS_FOLDER).await?,
+        config: canonicalize_child(&profile, CONFIG_FOLDER).await?,
+        profile,
+        game_install,
+    })
+}
+
+/// Returns the folder of a mod installed in the profile.
+pub async fn get_mod_path(id: Uuid, owner: &str, name: &str) -> Result<PathBuf> {
+    // the folder must be inside the profile's mods folder
+    ensure!(
+        !owner.contains(['/', '\\']) && !name.contains(['/', '\\']),
+        "Invalid mod {owner}-{name}"
+    );
+    let mut path = profile_path(id).join(MODS_FOLDER);
+    push_mod_folder(&mut path, owner, name);
+    canonicalize(&path).await
+}
@@ -0,0 +1,181 @@
+//! Inspection of the BepInEx plugins that a profile's mods actually contain.
+//!
+//! A package's name says little about the plugins inside it, and two packages can ship the same
+//! plugin under different names. BepInEx identifies plugins by the GUID in their `BepInPlugin`
+//! attribute and only loads one plugin per GUID, so that is read from every assembly.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context as _, Result};
+use slog::debug;
+use smol_str::SmolStr;
+use uuid::Uuid;
+
+use crate::util::dotnet::{attribute_string_args, Metadata};
+use crate::util::IoErrorKindExt as _;
+
+use super::{profile_path, MODS_FOLDER, PATCHERS_FOLDER};
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct PluginInfo {
+    pub guid: String,
+    pub name: String,
+    pub version: String,
+    /// The full name of the plugin's class.
+    pub type_name: String,
+    /// The assembly that contains the plugin, relative to the mod's folder.
+    pub path: PathBuf,
+}
+
+/// A plugin GUID that is shipped by more than one mod. BepInEx only loads one of them.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct DuplicatePlugin {
+    pub guid: String,
+    /// The mods that ship a plugin with the GUID, as `Owner-Name`.
+    pub mods: Vec<SmolStr>,
+}
+
+#[derive(Debug, Clone, Default, serde::Serialize)]
+pub struct ProfilePlugins {
+    /// The plugins in each mod, by mod as `Owner-Name`.
+    pub plugins: BTreeMap<SmolStr, Vec<PluginInfo>>,
+    pub duplicates: Vec<DuplicatePlugin>,
+}
+
+/// Reads the plugins declared in the .NET assembly `image`.
+fn read_plugins(image: &[u8]) -> Result<Vec<(String, [Option<String>; 3])>> {
+    let metadata = Metadata::parse(image)?;
+    metadata
+        .type_attributes("BepInEx", "BepInPlugin")?
+        .into_iter()
+        .map(|(type_name, blob)| {
+            // BepInPlugin(string GUID, string Name, string Version)
+            let args = attribute_string_args(blob, 3)?;
+            Ok((type_name, args.try_into().unwrap()))
+        })
+        .collect()
+}
+
+/// Reads the plugins in every assembly under the mod folder at `path`. Files that aren't .NET
+/// assemblies, like native libraries, are skipped.
+fn read_mod_plugins(log: &slog::Logger, path: &Path, plugins: &mut Vec<PluginInfo>) -> Result<()> {
+    for e in walkdir::WalkDir::new(path).sort_by_file_name() {
+        let e = e?;
+        if !e.file_type().is_file()
+            || !e
+                .path()
+                .extension()
+                .is_some_and(|ext| ext.eq_ignore_ascii_case("dll"))
+        {
+            continue;
+        }
+        let image =
+            std::fs::read(e.path()).with_context(|| format!("Failed to read {:?}", e.path()))?;
+        let found = match read_plugins(&image) {
+            Ok(t) => t,
+            Err(error) => {
+                debug!(log, "Skipping {:?}: {error:#}", e.path());
+                continue;
+            }
+        };
+        let rel_path = e.path().strip_prefix(path)?;
+        for (type_name, [guid, name, version]) in found {
+            plugins.push(PluginInfo {
+                guid: guid.unwrap_or_default(),
+                name: name.unwrap_or_default(),
+                version: version.unwrap_or_default(),
+                type_name,
+                path: rel_path.to_owned(),
+            });
+        }
+    }
+    Ok(())
+}
+
+fn find_duplicates(plugins: &BTreeMap<SmolStr, Vec<PluginInfo>>) -> Vec<DuplicatePlugin> {
+    let mut by_guid = BTreeMap::<&str, Vec<SmolStr>>::new();
+    for (mod_id, plugins) in plugins {
+        for plugin in plugins {
+            let mods = by_guid.entry(plugin.guid.as_str()).or_default();
+            if !mods.contains(mod_id) {
+                mods.push(mod_id.clone());
+            }
+        }
+    }
+    by_guid
+        .into_iter()
+        .filter(|(_, mods)| mods.len() > 1)
+        .map(|(guid, mods)| DuplicatePlugin {
+            guid: guid.to_owned(),
+            mods,
+        })
+        .collect()
+}
+
+/// Lists the plugins in each of the profile's mods, and the plugin GUIDs that more than one mod
+/// ships.
+pub async fn inspect_plugins(log: &slog::Logger, id: Uuid) -> Result<ProfilePlugins> {
+    let profile_path = profile_path(id);
+    tokio::task::block_in_place(|| {
+        let mut plugins = BTreeMap::<SmolStr, Vec<PluginInfo>>::new();
+        for folder in [MODS_FOLDER, PATCHERS_FOLDER] {
+            let path = profile_path.join(folder);
+            let iter = match std::fs::read_dir(&path) {
+                Ok(t) => t,
+                Err(e) if e.is_not_found() => continue,
+                Err(e) => return Err(e).with_context(|| format!("Failed to read {path:?}")),
+            };
+            for e in iter {
+                let e = e?;
+                if !e.file_type()?.is_dir() {
+                    continue;
+                }
+                let Some(mod_id) = e.file_name().to_str().map(SmolStr::from) else {
+                    continue;
+                };
+                read_mod_plugins(log, &e.path(), plugins.entry(mod_id).or_default())?;
+            }
+        }
+        plugins.retain(|_, plugins| !plugins.is_empty());
+        let duplicates = find_duplicates(&plugins);
+        Ok(ProfilePlugins {
+            plugins,
+            duplicates,
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn plugin(guid: &str) -> PluginInfo {
+        PluginInfo {
+            guid: guid.to_owned(),
+            name: String::new(),
+            version: String::new(),
+            type_name: String::new(),
+            path: PathBuf::new(),
+        }
+    }
+
+    #[test]
+    fn test_find_duplicates() {
+        let plugins = BTreeMap::from([
+            (
+                SmolStr::from("A-One"),
+                vec![plugin("com.a.one"), plugin("com.shared")],
+            ),
+            (SmolStr::from("B-Two"), vec![plugin("com.shared")]),
+            (
+                SmolStr::from("C-Three"),
+                vec![plugin("com.c.three"), plugin("com.c.three")],
+            ),
+        ]);
+        let duplicates = find_duplicates(&plugins);
+        assert_eq!(duplicates.len(), 1);
+        assert_eq!(duplicates[0].guid, "com.shared");
+        assert_eq!(duplicates[0].mods, ["A-One", "B-Two"]);
+    }
+}
@@ -0,0 +1,178 @@
+//! Portable profile archives, which contain everything needed to recreate a profile except the
+//! mods themselves: its metadata, the exact versions of its mods, and its config files. Unlike
+//! profile codes, they can be shared without uploading anything to Thunderstore.
+
+use std::io::{Cursor, Read as _};
+use std::path::PathBuf;
+
+use anyhow::{bail, Context as _, Result};
+use uuid::Uuid;
+
+use crate::exporting::thunderstore::{read_installed_mods, write_config_files};
+use crate::importing::thunderstore::ProfileMod;
+
+use super::{migrations, profile_path, read_profile, Profile, CONFIG_FOLDER};
+
+const PROFILE_FILE_NAME: &str = "profile.json";
+const MODS_FILE_NAME: &str = "mods.json";
+
+#[derive(Debug)]
+pub struct Archive {
+    pub profile: Profile,
+    pub mods: Vec<ProfileMod>,
+    /// The config files, by their path relative to the config folder.
+    configs: Vec<(PathBuf, Vec<u8>)>,
+}
+
+/// Builds a portable archive of the profile.
+pub async fn build_archive(log: &slog::Logger, id: Uuid) -> Result<Vec<u8>> {
+    let mut profile = read_profile(id).await?;
+    // the executable is specific to this computer, and so are the commands it is run through
+    profile.launch.game_executable = None;
+    profile.launch.wrapper = None;
+    profile.pinned = false;
+    // records of interrupted installs only make sense for the files on this computer
+    profile.incomplete_replacements.clear();
+
+    let path = profile_path(id);
+    let mods = read_installed_mods(log, &path).await?;
+    slog::debug!(log, "Exporting {} mods from profile {id}", mods.len());
+
+    let config_dir = path.join(CONFIG_FOLDER);
+    tokio::task::spawn_blocking(move || {
+        let options = zip::write::SimpleFileOptions::default();
+        let mut zip = zip::ZipWriter::new(Cursor::new(Vec::new()));
+
+        zip.start_file(PROFILE_FILE_NAME, options)?;
+        serde_json::to_writer_pretty(&mut zip, &profile).context("Failed to write profile")?;
+
+        zip.start_file(MODS_FILE_NAME, options)?;
+        serde_json::to_writer_pretty(&mut zip, &mods).context("Failed to write mod list")?;
+
+        write_config_files(&mut zip, &config_dir, CONFIG_FOLDER)?;
+
+        Ok::<_, anyhow::Error>(zip.finish()?.into_inner())
+    })
+    .await?
+}
+
+/// Reads a portable archive built by [`build_archive`].
+pub fn read_archive(bytes: &[u8]) -> Result<Archive> {
+    let mut archive =
+        zip::ZipArchive::new(Cursor::new(bytes)).context("Invalid profile archive")?;
+
+    let mut profile = serde_json::from_reader::<_, serde_json::Value>(
+        archive
+            .by_name(PROFILE_FILE_NAME)
+            .context("Profile archive is missing its profile")?,
+    )
+    .context("Failed to parse profile")?;
+    // archives may have been exported by an older version of Manderrow
+    migrations::migrate(&mut profile)?;
+    let mut profile =
+        serde_json::from_value::<Profile>(profile).context("Failed to parse profile")?;
+    // archives are shared, and must not be able to make the importer run arbitrary programs
+    profile.launch.game_executable = None;
+    profile.launch.wrapper = None;
+    // and must not make the importer's repairs touch its files
+    profile.incomplete_replacements.clear();
+    let mods = serde_json::from_reader::<_, Vec<ProfileMod>>(
+        archive
+            .by_name(MODS_FILE_NAME)
+            .context("Profile archive is missing its mod list")?,
+    )
+    .context("Failed to parse mod list")?;
+
+    let mut configs = Vec::new();
+    for i in 0..archive.len() {
+        let mut file = archive
+            .by_index(i)
+            .context("Failed to open file in archive")?;
+        if file.is_symlink() {
+            bail!("Symlinks are not supported");
+        }
+        if !file.is_file() {
+            continue;
+        }
+        let path = file
+            .enclosed_name()
+            .with_context(|| format!("File in archive has a bad path: {:?}", file.name()))?;
+        let Ok(path) = path.strip_prefix(CONFIG_FOLDER) else {
+            continue;
+        };
+        let path = path.to_owned();
+        let mut buf = Vec::new();
+        file.read_to_end(&mut buf)
+            .with_context(|| format!("Failed to read {path:?} from archive"))?;
+        configs.push((path, buf));
+    }
+
+    Ok(Archive {
+        profile,
+        mods,
+        configs,
+    })
+}
+
+impl Archive {
+    /// Writes the config files into the profile, replacing any that already exist.
+    pub fn write_configs(&self, id: Uuid) -> Result<()> {
+        let config_dir = profile_path(id).join(CONFIG_FOLDER);
+        for (path, bytes) in &self.configs {
+            let path = config_dir.join(path);
+            std::fs::create_dir_all(path.parent().unwrap())?;
+            std::fs::write(&path, bytes).with_context(|| format!("Failed to write {path:?}"))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write as _;
+
+    use super::*;
+
+    #[test]
+    fn test_read_archive() {
+        let options = zip::write::SimpleFileOptions::default();
+        let mut zip = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        zip.start_file(PROFILE_FILE_NAME, options).unwrap();
+        zip.write_all(
+            br#"{"name":"Shared","game":"lethal-company","launch":{"show_console":true,"game_executable":"/tmp/payload","wrapper":"sh -c payload"},"incomplete_replacements":[{"mod":"Owner-Name","target":"mods/Owner-Name","deletion_path":"mods/.tbd-abcdef-Owner-Name"}]}"#,
+        )
+        .unwrap();
+        zip.start_file(MODS_FILE_NAME, options).unwrap();
+        zip.write_all(
+            br#"[{"name":"notnotnotswipez-MoreCompany","version":{"major":1,"minor":11,"patch":0},"enabled":true}]"#,
+        )
+        .unwrap();
+        zip.start_file("config/MoreCompany.cfg", options).unwrap();
+        zip.write_all(b"[General]\nPlayers = 8\n").unwrap();
+        zip.start_file("mods/Plugin.dll", options).unwrap();
+        zip.write_all(b"").unwrap();
+        let bytes = zip.finish().unwrap().into_inner();
+
+        let archive = read_archive(&bytes).unwrap();
+        assert_eq!(
+            archive.profile.schema_version,
+            migrations::CURRENT_SCHEMA_VERSION
+        );
+        assert_eq!(&*archive.profile.name, "Shared");
+        assert_eq!(&*archive.profile.game, "lethal-company");
+        assert!(archive.profile.launch.show_console);
+        assert_eq!(archive.profile.launch.game_executable, None);
+        assert_eq!(archive.profile.launch.wrapper, None);
+        assert!(archive.profile.incomplete_replacements.is_empty());
+        assert_eq!(archive.mods.len(), 1);
+        assert_eq!(&*archive.mods[0].full_name, "notnotnotswipez-MoreCompany");
+        assert_eq!(archive.mods[0].version.to_string(), "1.11.0");
+        assert_eq!(
+            archive.configs,
+            [(
+                PathBuf::from("MoreCompany.cfg"),
+                b"[General]\nPlayers = 8\n".to_vec()
+            )]
+        );
+    }
+}
@@ -0,0 +1,30 @@
+//! Read-only mode, for installs whose profiles are shared with other users or machines and must
+//! not be changed from this one.
+//!
+//! It is enabled with the `--read-only` command line flag. While it is enabled, every command that
+//! would change a profile fails with [`ReadOnlyError`] before doing anything.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static READ_ONLY: AtomicBool = AtomicBool::new(false);
+
+pub fn set_read_only(read_only: bool) {
+    READ_ONLY.store(read_only, Ordering::Relaxed);
+}
+
+pub fn is_read_only() -> bool {
+    READ_ONLY.load(Ordering::Relaxed)
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("Profiles are read-only")]
+pub struct ReadOnlyError;
+
+/// Fails if profiles are read-only. Call this before changing a profile in any way.
+pub fn ensure_writable() -> Result<(), ReadOnlyError> {
+    if is_read_only() {
+        Err(ReadOnlyError)
+    } else {
+        Ok(())
+    }
+}
@@ -0,0 +1,281 @@
+//! Recovering from installations that were interrupted halfway through replacing a mod's files.
+//!
+//! Files are replaced by moving the originals aside and the replacements into place (see
+//! [`crate::installing::StagedPackage::apply`]). If either the replacement can't be moved into
+//! place, or the originals can't be deleted afterwards, the originals are left behind. Those are
+//! recorded in the profile's [`Profile::incomplete_replacements`] and dealt with by [`repair`].
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context as _, Result};
+use slog::{info, warn};
+use smol_str::SmolStr;
+use uuid::Uuid;
+
+use crate::installing::{AtomicReplaceError, ReplaceTransaction, StagedPackage};
+use crate::util::IoErrorKindExt as _;
+
+use super::{profile_path, read_profile, write_profile, Profile};
+
+/// Held while reading and writing [`Profile::incomplete_replacements`], so that concurrent
+/// installations don't lose each other's records.
+static LOCK: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+/// A replacement of a mod's files that was not completed.
+#[derive(Clone, Debug, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct IncompleteReplacement {
+    /// The mod, as `Owner-Name`.
+    pub r#mod: SmolStr,
+    /// Where the replacement was to be moved, relative to the profile.
+    pub target: PathBuf,
+    /// Where the original was moved aside to, relative to the profile.
+    pub deletion_path: PathBuf,
+}
+
+/// What [`repair_one`] did.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Repair {
+    /// The replacement was in place, so the original was deleted.
+    Finished,
+    /// The replacement was not in place, so the original was moved back.
+    RolledBack,
+    /// The original was already gone.
+    Nothing,
+}
+
+/// Whether `path` is relative and only descends, so that it can't point outside of the profile.
+fn is_contained(path: &Path) -> bool {
+    path.components().next().is_some()
+        && path
+            .components()
+            .all(|c| matches!(c, std::path::Component::Normal(_)))
+}
+
+/// Returns `path` relative to `profile`, if it is inside of it.
+fn relative_to<'a>(profile: &Path, path: &'a Path) -> Option<&'a Path> {
+    path.strip_prefix(profile)
+        .ok()
+        .filter(|path| is_contained(path))
+}
+
+/// Records in the profile that replacing `target` with a new version of `r#mod` left the original
+/// at `deletion_path`.
+pub async fn record(id: Uuid, r#mod: SmolStr, target: &Path, deletion_path: &Path) -> Result<()> {
+    let profile = profile_path(id);
+    let replacement = IncompleteReplacement {
+        r#mod,
+        target: relative_to(&profile, target)
+            .with_context(|| format!("{target:?} is not inside of the profile"))?
+            .to_owned(),
+        deletion_path: relative_to(&profile, deletion_path)
+            .with_context(|| format!("{deletion_path:?} is not inside of the profile"))?
+            .to_owned(),
+    };
+    let _guard = LOCK.lock().await;
+    let mut metadata = read_profile(id).await?;
+    metadata.incomplete_replacements.push(replacement);
+    write_profile(id, &metadata).await?;
+    Ok(())
+}
+
+/// Applies the staged package, recording the replacement if it fails after the original has been
+/// moved aside.
+pub(super) async fn apply(
+    log: &slog::Logger,
+    id: Uuid,
+    r#mod: &str,
+    staged: StagedPackage<'_, '_>,
+) -> Result<ReplaceTransaction> {
+    let target = staged.target.to_owned();
+    let e = match staged.apply(log).await {
+        Ok(transaction) => return Ok(transaction),
+        Err(e) => e,
+    };
+    if let Some(AtomicReplaceError::MoveReplacement {
+        deletion_path: Some(deletion_path),
+        ..
+    }) = e.downcast_ref::<AtomicReplaceError>()
+    {
+        if let Err(e) = record(id, r#mod.into(), &target, deletion_path).await {
+            warn!(
+                log,
+                "Failed to record incomplete replacement of {target:?}: {e:#}"
+            );
+        }
+    }
+    Err(e)
+}
+
+/// Commits the transaction. If the original can't be deleted, the replacement is kept and the
+/// original is recorded to be deleted by [`repair`].
+pub(super) async fn commit(
+    log: &slog::Logger,
+    id: Uuid,
+    r#mod: &str,
+    transaction: ReplaceTransaction,
+) -> Result<()> {
+    match transaction.commit(log).await {
+        Ok(()) => Ok(()),
+        Err(AtomicReplaceError::CleanUp {
+            target,
+            deletion_path,
+            cause,
+        }) => {
+            warn!(
+                log,
+                "Failed to delete the original of {target:?} at {deletion_path:?}: {cause}"
+            );
+            record(id, r#mod.into(), &target, &deletion_path).await
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Finishes the replacement if the replacement is in place, or otherwise rolls it back, so that
+/// the result only depends on what is on disk.
+async fn repair_one(profile: &Path, replacement: &IncompleteReplacement) -> Result<Repair> {
+    let target = profile.join(&replacement.target);
+    let deletion_path = profile.join(&replacement.deletion_path);
+    let original = match tokio::fs::symlink_metadata(&deletion_path).await {
+        Ok(t) => t,
+        Err(e) if e.is_not_found() => return Ok(Repair::Nothing),
+        Err(e) => return Err(e).with_context(|| format!("Failed to inspect {deletion_path:?}")),
+    };
+    match tokio::fs::symlink_metadata(&target).await {
+        Ok(_) => {
+            if original.is_dir() {
+                tokio::fs::remove_dir_all(&deletion_path).await
+            } else {
+                tokio::fs::remove_file(&deletion_path).await
+            }
+            .with_context(|| format!("Failed to delete {deletion_path:?}"))?;
+            Ok(Repair::Finished)
+        }
+        Err(e) if e.is_not_found() => {
+            tokio::fs::rename(&deletion_path, &target)
+                .await
+                .with_context(|| format!("Failed to move {deletion_path:?} to {target:?}"))?;
+            Ok(Repair::RolledBack)
+        }
+        Err(e) => Err(e).with_context(|| format!("Failed to inspect {target:?}")),
+    }
+}
+
+/// Repairs the incomplete replacements of the profile's mods for which `filter` returns `true`.
+/// Replacements that can't be repaired stay recorded, and the first error is returned after
+/// trying the rest.
+pub async fn repair(
+    log: &slog::Logger,
+    id: Uuid,
+    filter: impl Fn(&IncompleteReplacement) -> bool,
+) -> Result<Vec<(IncompleteReplacement, Repair)>> {
+    let profile = profile_path(id);
+    let _guard = LOCK.lock().await;
+    let mut metadata: Profile = read_profile(id).await?;
+    let mut repaired = Vec::new();
+    let mut remaining = Vec::new();
+    let mut error = None;
+    for replacement in std::mem::take(&mut metadata.incomplete_replacements) {
+        if !is_contained(&replacement.target) || !is_contained(&replacement.deletion_path) {
+            // only ever recorded by hand or by another machine, and acting on it could touch
+            // anything outside of the profile
+            warn!(
+                log,
+                "Ignoring invalid incomplete replacement {replacement:?} of profile {id}"
+            );
+            continue;
+        }
+        if !filter(&replacement) {
+            remaining.push(replacement);
+            continue;
+        }
+        match repair_one(&profile, &replacement).await {
+            Ok(repair) => {
+                info!(log, "Repaired {replacement:?} of profile {id}: {repair:?}");
+                repaired.push((replacement, repair));
+            }
+            Err(e) => {
+                warn!(
+                    log,
+                    "Failed to repair {replacement:?} of profile {id}: {e:#}"
+                );
+                error.get_or_insert(e);
+                remaining.push(replacement);
+            }
+        }
+    }
+    metadata.incomplete_replacements = remaining;
+    write_profile(id, &metadata).await?;
+    match error {
+        Some(e) => Err(e),
+        None => Ok(repaired),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn replacement(target: &str, deletion_path: &str) -> IncompleteReplacement {
+        IncompleteReplacement {
+            r#mod: "Owner-Name".into(),
+            target: target.into(),
+            deletion_path: deletion_path.into(),
+        }
+    }
+
+    #[test]
+    fn test_repair_one() {
+        let profile = tempfile::tempdir().unwrap();
+        let profile = profile.path();
+        tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()
+            .expect("unable to build tokio runtime")
+            .block_on(async {
+                // the replacement is in place
+                std::fs::create_dir_all(profile.join("mods/Owner-Name")).unwrap();
+                std::fs::create_dir_all(profile.join("mods/.tbd-abcdef-Owner-Name")).unwrap();
+                let r = replacement("mods/Owner-Name", "mods/.tbd-abcdef-Owner-Name");
+                assert_eq!(repair_one(profile, &r).await.unwrap(), Repair::Finished);
+                assert!(profile.join("mods/Owner-Name").exists());
+                assert!(!profile.join("mods/.tbd-abcdef-Owner-Name").exists());
+                assert_eq!(repair_one(profile, &r).await.unwrap(), Repair::Nothing);
+
+                // the replacement never made it
+                std::fs::write(profile.join("mods/.tbd-ghijkl-Other"), "original").unwrap();
+                let r = replacement("mods/Other", "mods/.tbd-ghijkl-Other");
+                assert_eq!(repair_one(profile, &r).await.unwrap(), Repair::RolledBack);
+                assert_eq!(
+                    std::fs::read_to_string(profile.join("mods/Other")).unwrap(),
+                    "original"
+                );
+            });
+    }
+
+    #[test]
+    fn test_relative_to() {
+        let profile = Path::new("/profiles/abc");
+        assert_eq!(
+            relative_to(profile, Path::new("/profiles/abc/mods/A-B")),
+            Some(Path::new("mods/A-B"))
+        );
+        assert_eq!(relative_to(profile, Path::new("/elsewhere/A-B")), None);
+        assert_eq!(relative_to(profile, Path::new("/profiles/abc")), None);
+        assert_eq!(
+            relative_to(profile, Path::new("/profiles/abc/mods/../../def")),
+            None
+        );
+    }
+
+    #[test]
+    fn test_is_contained() {
+        assert!(is_contained(Path::new("mods/A-B")));
+        assert!(!is_contained(Path::new("")));
+        assert!(!is_contained(Path::new("../A-B")));
+        assert!(!is_contained(Path::new("mods/../../A-B")));
+        assert!(!is_contained(&std::env::temp_dir()));
+    }
+}
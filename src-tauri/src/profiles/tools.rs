@@ -0,0 +1,206 @@
+//! Tool packages, which are run on their own instead of being loaded by the mod loader, like
+//! standalone executables and console tools.
+//!
+//! A tool's files are installed to the profile's [`TOOLS_FOLDER`] rather than its mods folder, so
+//! that the mod loader never tries to load them. Its manifest stays in the mods folder, so that
+//! the tool is listed, updated, exported and uninstalled like any other mod.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, ensure, Context as _, Result};
+use slog::{info, warn};
+use uuid::Uuid;
+
+use super::overlaps::PACKAGE_METADATA_FILES;
+use super::{
+    profile_path, push_mod_folder, read_installed_manifest, CONFIG_FOLDER, MODS_FOLDER,
+    PATCHERS_FOLDER, TOOLS_FOLDER,
+};
+
+/// The Thunderstore category that tool packages are listed under.
+const TOOLS_CATEGORY: &str = "Tools";
+
+/// Stored in the [`MANIFEST_FILE_NAME`](super::MANIFEST_FILE_NAME) of tool packages.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub struct ToolManifest {
+    /// The executables that the tool ships, relative to its folder.
+    pub executables: Vec<PathBuf>,
+}
+
+fn is_windows_executable(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("exe"))
+}
+
+/// Whether `entry` is an executable that this platform can run without a compatibility layer.
+/// Packages only keep their permissions if they were zipped on a Unix system.
+#[cfg(unix)]
+fn is_native_executable(entry: &walkdir::DirEntry) -> Result<bool> {
+    use std::os::unix::fs::PermissionsExt as _;
+    Ok(!is_windows_executable(entry.path()) && entry.metadata()?.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(windows)]
+fn is_native_executable(entry: &walkdir::DirEntry) -> Result<bool> {
+    Ok(is_windows_executable(entry.path()))
+}
+
+/// Returns the manifest of the package extracted to `path` if it is a tool.
+///
+/// Packages in the tools category are often plugins that help with development, so only those
+/// that also ship an executable are treated as tools. Windows executables are recorded on every
+/// platform, so that the manifest doesn't depend on where the tool was installed.
+pub fn find_tool<'a>(
+    categories: impl IntoIterator<Item = &'a str>,
+    path: &Path,
+) -> Result<Option<ToolManifest>> {
+    if !categories
+        .into_iter()
+        .any(|c| c.eq_ignore_ascii_case(TOOLS_CATEGORY))
+    {
+        return Ok(None);
+    }
+    let mut executables = Vec::new();
+    for e in walkdir::WalkDir::new(path).sort_by_file_name() {
+        let e = e?;
+        if e.file_type().is_file() && (is_windows_executable(e.path()) || is_native_executable(&e)?)
+        {
+            executables.push(e.path().strip_prefix(path)?.to_owned());
+        }
+    }
+    if executables.is_empty() {
+        return Ok(None);
+    }
+    Ok(Some(ToolManifest { executables }))
+}
+
+/// Moves everything except the package's metadata from the package extracted to `package` into
+/// `dest`, which must not exist yet.
+pub async fn split_package(package: &Path, dest: &Path) -> Result<()> {
+    tokio::fs::create_dir(dest)
+        .await
+        .with_context(|| format!("Failed to create {dest:?}"))?;
+    let mut iter = tokio::fs::read_dir(package).await?;
+    while let Some(e) = iter.next_entry().await? {
+        let name = e.file_name();
+        if name.to_str().is_some_and(|s| {
+            PACKAGE_METADATA_FILES
+                .iter()
+                .any(|m| s.eq_ignore_ascii_case(m))
+        }) {
+            continue;
+        }
+        let dst = dest.join(&name);
+        tokio::fs::rename(e.path(), &dst)
+            .await
+            .with_context(|| format!("Failed to move {:?} to {dst:?}", e.path()))?;
+    }
+    Ok(())
+}
+
+/// Runs `executable` from a tool that is installed in the profile, in the tool's folder.
+///
+/// The tool is told where the profile is through the same environment variables that the mod
+/// loader is configured with, along with `MANDERROW_PROFILE_ID` and `MANDERROW_PROFILE_PATH`.
+pub async fn run_tool(
+    log: &slog::Logger,
+    id: Uuid,
+    owner: &str,
+    name: &str,
+    executable: &Path,
+) -> Result<()> {
+    #[derive(serde::Deserialize)]
+    struct InstalledTool {
+        #[serde(default)]
+        tool: Option<ToolManifest>,
+    }
+
+    let profile_path = profile_path(id);
+
+    let tool = read_installed_manifest::<InstalledTool>(&profile_path, owner, name)
+        .await?
+        .with_context(|| format!("{owner}-{name} is not installed"))?
+        .tool
+        .with_context(|| format!("{owner}-{name} is not a tool"))?;
+    // only ever run what the package shipped
+    ensure!(
+        tool.executables.iter().any(|p| p == executable),
+        "{executable:?} is not an executable of {owner}-{name}"
+    );
+    if !cfg!(windows) && is_windows_executable(executable) {
+        bail!("{executable:?} of {owner}-{name} can only be run on Windows");
+    }
+
+    let mut dir = profile_path.join(TOOLS_FOLDER);
+    push_mod_folder(&mut dir, owner, name);
+    let path = dir.join(executable);
+    let mut child = tokio::process::Command::new(&path)
+        .current_dir(&dir)
+        .env("MANDERROW_PROFILE_ID", id.to_string())
+        .env("MANDERROW_PROFILE_PATH", &profile_path)
+        .env("BEPINEX_CONFIGS", profile_path.join(CONFIG_FOLDER))
+        .env("BEPINEX_PLUGINS", profile_path.join(MODS_FOLDER))
+        .env(
+            "BEPINEX_PATCHER_PLUGINS",
+            profile_path.join(PATCHERS_FOLDER),
+        )
+        .spawn()
+        .with_context(|| format!("Failed to run {path:?}"))?;
+    info!(log, "Started tool {path:?}");
+
+    let log = log.clone();
+    tauri::async_runtime::spawn(async move {
+        match child.wait().await {
+            Ok(status) => info!(log, "Tool {path:?} exited with {status}"),
+            Err(e) => warn!(log, "Failed to wait for tool {path:?}: {e}"),
+        }
+    });
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_tool() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("bin")).unwrap();
+        std::fs::write(dir.path().join("bin").join("Editor.EXE"), b"").unwrap();
+        std::fs::write(dir.path().join("bin").join("Editor.dll"), b"").unwrap();
+        std::fs::write(dir.path().join("manifest.json"), b"{}").unwrap();
+
+        assert_eq!(find_tool(["Misc"], dir.path()).unwrap(), None);
+        assert_eq!(
+            find_tool(["Misc", "tools"], dir.path()).unwrap(),
+            Some(ToolManifest {
+                executables: vec![Path::new("bin").join("Editor.EXE")],
+            })
+        );
+
+        std::fs::remove_file(dir.path().join("bin").join("Editor.EXE")).unwrap();
+        assert_eq!(find_tool(["Tools"], dir.path()).unwrap(), None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_find_tool_native() {
+        use std::os::unix::fs::PermissionsExt as _;
+
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("editor"), b"").unwrap();
+        std::fs::write(dir.path().join("editor.sh"), b"").unwrap();
+        std::fs::set_permissions(
+            dir.path().join("editor"),
+            std::fs::Permissions::from_mode(0o755),
+        )
+        .unwrap();
+
+        assert_eq!(
+            find_tool(["Tools"], dir.path()).unwrap(),
+            Some(ToolManifest {
+                executables: vec![PathBuf::from("editor")],
+            })
+        );
+    }
+}
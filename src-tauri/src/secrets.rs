@@ -0,0 +1,229 @@
+//! Storage for API tokens and other credentials.
+//!
+//! Secrets are kept in the OS credential store (Windows Credential Manager, macOS Keychain, or the
+//! Secret Service on Linux). When the credential store is unavailable, as on Linux desktops
+//! without a Secret Service provider, they fall back to a file encrypted with a key stored next to
+//! it. The key file is only readable by the current user, so this protects against copies of the
+//! secrets file alone, not against other software running as the user.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+
+use anyhow::{Context as _, Result};
+use base64::Engine as _;
+use chacha20poly1305::aead::{Aead as _, AeadCore as _, KeyInit as _, OsRng};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use manderrow_paths::local_data_dir;
+use parking_lot::Mutex;
+
+use crate::profiles::configs::write_atomically;
+use crate::util::IoErrorKindExt as _;
+
+const KEYRING_SERVICE: &str = "manderrow";
+
+static FILE_PATH: LazyLock<PathBuf> = LazyLock::new(|| local_data_dir().join("secrets.json"));
+static KEY_PATH: LazyLock<PathBuf> = LazyLock::new(|| local_data_dir().join("secrets.key"));
+
+/// Serializes access to the fallback file.
+static FILE_LOCK: Mutex<()> = Mutex::new(());
+
+/// The files that secrets are kept in when the credential store is unavailable.
+pub fn fallback_files() -> [&'static Path; 2] {
+    [&FILE_PATH, &KEY_PATH]
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Secret {
+    ThunderstoreSession,
+}
+
+impl Secret {
+    const fn as_str(self) -> &'static str {
+        match self {
+            Self::ThunderstoreSession => "thunderstore",
+        }
+    }
+
+    fn entry(self) -> keyring::Result<keyring::Entry> {
+        keyring::Entry::new(KEYRING_SERVICE, self.as_str())
+    }
+}
+
+/// Whether `e` means the credential store itself is unusable, rather than that something is
+/// wrong with the particular secret.
+fn is_unavailable(e: &keyring::Error) -> bool {
+    matches!(
+        e,
+        keyring::Error::PlatformFailure(_) | keyring::Error::NoStorageAccess(_)
+    )
+}
+
+type EncryptedFile = BTreeMap<String, String>;
+
+/// Reads the key of the fallback `file`, generating one if the file has no secrets yet.
+fn read_key(file: &EncryptedFile) -> Result<ChaCha20Poly1305> {
+    let key = match std::fs::read(&*KEY_PATH) {
+        Ok(bytes) => {
+            anyhow::ensure!(
+                bytes.len() == 32,
+                "Secrets key at {:?} is corrupt",
+                *KEY_PATH
+            );
+            *Key::from_slice(&bytes)
+        }
+        Err(e) if e.is_not_found() => {
+            // a new key would make the existing secrets impossible to decrypt, and silently
+            // losing them is worse than failing
+            anyhow::ensure!(
+                file.is_empty(),
+                "Secrets key at {:?} is missing, so the stored secrets can't be decrypted",
+                *KEY_PATH
+            );
+            let key = ChaCha20Poly1305::generate_key(&mut OsRng);
+            std::fs::create_dir_all(local_data_dir())?;
+            let mut options = std::fs::OpenOptions::new();
+            options.write(true).create_new(true);
+            #[cfg(unix)]
+            std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+            std::io::Write::write_all(&mut options.open(&*KEY_PATH)?, &key)?;
+            key
+        }
+        Err(e) => return Err(e.into()),
+    };
+    Ok(ChaCha20Poly1305::new(&key))
+}
+
+fn read_file() -> Result<EncryptedFile> {
+    match std::fs::read(&*FILE_PATH) {
+        Ok(bytes) => Ok(serde_json::from_slice(&bytes)?),
+        Err(e) if e.is_not_found() => Ok(EncryptedFile::new()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn write_file(file: &EncryptedFile) -> Result<()> {
+    write_atomically(&FILE_PATH, &serde_json::to_vec(file)?)
+}
+
+/// Encrypts `value` with a fresh nonce, which is prepended to the ciphertext.
+fn encrypt(key: &ChaCha20Poly1305, value: &str) -> Result<String> {
+    let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+    let mut bytes = nonce.to_vec();
+    bytes.extend(
+        key.encrypt(&nonce, value.as_bytes())
+            .map_err(|_| anyhow::anyhow!("Failed to encrypt secret"))?,
+    );
+    Ok(base64::engine::general_purpose::STANDARD.encode(bytes))
+}
+
+fn decrypt(key: &ChaCha20Poly1305, encoded: &str) -> Result<String> {
+    let bytes = base64::engine::general_purpose::STANDARD.decode(encoded)?;
+    anyhow::ensure!(bytes.len() >= 12, "Encrypted secret is truncated");
+    let (nonce, ciphertext) = bytes.split_at(12);
+    let plaintext = key
+        .decrypt(Nonce::from_slice(nonce), ciphertext)
+        .map_err(|_| anyhow::anyhow!("Failed to decrypt secret"))?;
+    Ok(String::from_utf8(plaintext)?)
+}
+
+fn file_get(secret: Secret) -> Result<Option<String>> {
+    let _guard = FILE_LOCK.lock();
+    let file = read_file()?;
+    let Some(encoded) = file.get(secret.as_str()) else {
+        return Ok(None);
+    };
+    Ok(Some(decrypt(&read_key(&file)?, encoded)?))
+}
+
+fn file_set(secret: Secret, value: Option<&str>) -> Result<()> {
+    let _guard = FILE_LOCK.lock();
+    let mut file = read_file()?;
+    match value {
+        Some(value) => {
+            // the old value is being replaced, so it doesn't need to stay decryptable
+            file.remove(secret.as_str());
+            let encrypted = encrypt(&read_key(&file)?, value)?;
+            file.insert(secret.as_str().to_owned(), encrypted);
+        }
+        None => {
+            if file.remove(secret.as_str()).is_none() {
+                return Ok(());
+            }
+        }
+    }
+    write_file(&file)
+}
+
+fn get_blocking(secret: Secret) -> Result<Option<String>> {
+    match secret.entry().and_then(|e| e.get_password()) {
+        Ok(s) => Ok(Some(s)),
+        // it may have been stored while the credential store was unavailable
+        Err(keyring::Error::NoEntry) => file_get(secret),
+        Err(e) if is_unavailable(&e) => {
+            slog_scope::debug!("Credential store is unavailable, using fallback: {e}");
+            file_get(secret)
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn set_blocking(secret: Secret, value: &str) -> Result<()> {
+    match secret.entry().and_then(|e| e.set_password(value)) {
+        // don't leave a stale copy around to be found by `get_blocking`
+        Ok(()) => file_set(secret, None),
+        Err(e) if is_unavailable(&e) => {
+            slog_scope::warn!("Credential store is unavailable, using fallback: {e}");
+            file_set(secret, Some(value))
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn delete_blocking(secret: Secret) -> Result<()> {
+    match secret.entry().and_then(|e| e.delete_credential()) {
+        Ok(()) | Err(keyring::Error::NoEntry) => {}
+        Err(e) if is_unavailable(&e) => {}
+        Err(e) => return Err(e.into()),
+    }
+    file_set(secret, None)
+}
+
+pub async fn get(secret: Secret) -> Result<Option<String>> {
+    tokio::task::spawn_blocking(move || get_blocking(secret))
+        .await?
+        .with_context(|| format!("Failed to read secret {:?}", secret.as_str()))
+}
+
+pub async fn set(secret: Secret, value: String) -> Result<()> {
+    tokio::task::spawn_blocking(move || set_blocking(secret, &value))
+        .await?
+        .with_context(|| format!("Failed to store secret {:?}", secret.as_str()))
+}
+
+pub async fn delete(secret: Secret) -> Result<()> {
+    tokio::task::spawn_blocking(move || delete_blocking(secret))
+        .await?
+        .with_context(|| format!("Failed to delete secret {:?}", secret.as_str()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encrypt_round_trip() {
+        let key = ChaCha20Poly1305::new(&ChaCha20Poly1305::generate_key(&mut OsRng));
+        let encrypted = encrypt(&key, "session token").unwrap();
+        assert_ne!(encrypted, encrypt(&key, "session token").unwrap());
+        assert_eq!(decrypt(&key, &encrypted).unwrap(), "session token");
+    }
+
+    #[test]
+    fn test_decrypt_with_wrong_key() {
+        let key = ChaCha20Poly1305::new(&ChaCha20Poly1305::generate_key(&mut OsRng));
+        let other = ChaCha20Poly1305::new(&ChaCha20Poly1305::generate_key(&mut OsRng));
+        let encrypted = encrypt(&key, "session token").unwrap();
+        assert!(decrypt(&other, &encrypted).is_err());
+    }
+}
@@ -1,8 +1,9 @@
 use tauri::{ipc::Response, AppHandle, Emitter};
 
+use crate::stores::steam::templates::{self, LaunchOptionsTemplate};
 use crate::CommandError;
 
-use super::{SettingsPatch, SettingsState, EVENT};
+use super::{SettingsPatch, SettingsState, CHANGED_EVENT, EVENT};
 
 #[tauri::command]
 pub async fn get_settings(settings: SettingsState<'_>) -> Result<Response, CommandError> {
@@ -18,6 +19,15 @@ pub async fn get_settings_ui() -> Result<Response, CommandError> {
     Ok(Response::new(super::UI.to_owned()))
 }
 
+/// Parses launch options templates that the user is importing, so that they can be added to the
+/// setting.
+#[tauri::command]
+pub async fn parse_launch_options_templates(
+    json: &str,
+) -> Result<Vec<LaunchOptionsTemplate>, CommandError> {
+    Ok(templates::parse_exported(json)?)
+}
+
 #[tauri::command]
 pub async fn update_settings(
     app: AppHandle,
@@ -25,11 +35,18 @@ pub async fn update_settings(
     patch: SettingsPatch,
 ) -> Result<(), CommandError> {
     let mut settings = settings.write().await;
-    settings.as_mut().map_err(|e| e.clone())?.update(patch);
+    let updated = settings.as_mut().map_err(|e| e.clone())?;
+    let changed = updated.update(patch);
+    if changed.is_empty() {
+        return Ok(());
+    }
+    super::apply(updated, Some(&changed));
     let settings = settings.downgrade();
     let settings = settings.as_ref().unwrap();
     app.emit(EVENT, settings.defaulted())
         .map_err(anyhow::Error::from)?;
+    app.emit(CHANGED_EVENT, &changed)
+        .map_err(anyhow::Error::from)?;
     super::write(settings).await?;
     Ok(())
 }
@@ -5,18 +5,23 @@
 
 use std::path::PathBuf;
 use std::sync::LazyLock;
+use std::time::Duration;
 
 use manderrow_paths::{config_dir, PRODUCT_NAME};
 use tauri::State;
 use tokio::sync::RwLock;
 use triomphe::Arc;
 
+use crate::stores::steam::templates::LaunchOptionsTemplate;
+use crate::util::{faults::FaultOptions, http::ClientOptions, UsizeExt as _};
 use crate::{util::IoErrorKindExt, CommandError};
 
 pub mod commands;
 
 /// The name of the event used to send the settings to the frontend.
 pub const EVENT: &str = "settings";
+/// The name of the event sent along with [`EVENT`], with the keys of the settings that changed.
+pub const CHANGED_EVENT: &str = "settings_changed";
 
 pub type SettingsStateInner = Arc<RwLock<Result<Settings, CommandError>>>;
 pub type SettingsState<'a> = State<'a, SettingsStateInner>;
@@ -30,10 +35,48 @@ fn read() -> anyhow::Result<Option<Settings>> {
     let SettingsOnDisk {
         default_game,
         open_console_on_launch,
+        pause_background_activity,
+        package_mirrors,
+        concurrent_downloads,
+        store_mod_docs,
+        cache_max_size,
+        cache_max_age,
+        request_connect_timeout,
+        request_read_timeout,
+        user_agent,
+        use_http2,
+        prefetch_top_mods,
+        simulated_latency,
+        simulated_bandwidth,
+        simulated_failure_rate,
+        launch_wrapper,
+        launch_options_templates,
+        console_history_size,
+        kill_grace_period,
+        allow_concurrent_launches,
     } = simd_json::from_slice::<SettingsOnDisk>(&mut bytes)?;
     Ok(Some(Settings {
         default_game,
         open_console_on_launch,
+        pause_background_activity,
+        package_mirrors,
+        concurrent_downloads,
+        store_mod_docs,
+        cache_max_size,
+        cache_max_age,
+        request_connect_timeout,
+        request_read_timeout,
+        user_agent,
+        use_http2,
+        prefetch_top_mods,
+        simulated_latency,
+        simulated_bandwidth,
+        simulated_failure_rate,
+        launch_wrapper,
+        launch_options_templates,
+        console_history_size,
+        kill_grace_period,
+        allow_concurrent_launches,
     }))
 }
 
@@ -41,11 +84,49 @@ async fn write(
     &Settings {
         ref default_game,
         open_console_on_launch,
+        pause_background_activity,
+        ref package_mirrors,
+        concurrent_downloads,
+        store_mod_docs,
+        cache_max_size,
+        cache_max_age,
+        request_connect_timeout,
+        request_read_timeout,
+        ref user_agent,
+        use_http2,
+        prefetch_top_mods,
+        simulated_latency,
+        simulated_bandwidth,
+        simulated_failure_rate,
+        ref launch_wrapper,
+        ref launch_options_templates,
+        console_history_size,
+        kill_grace_period,
+        allow_concurrent_launches,
     }: &Settings,
 ) -> anyhow::Result<()> {
     let settings = SettingsOnDisk {
         default_game: default_game.clone(),
         open_console_on_launch,
+        pause_background_activity,
+        package_mirrors: package_mirrors.clone(),
+        concurrent_downloads,
+        store_mod_docs,
+        cache_max_size,
+        cache_max_age,
+        request_connect_timeout,
+        request_read_timeout,
+        user_agent: user_agent.clone(),
+        use_http2,
+        prefetch_top_mods,
+        simulated_latency,
+        simulated_bandwidth,
+        simulated_failure_rate,
+        launch_wrapper: launch_wrapper.clone(),
+        launch_options_templates: launch_options_templates.clone(),
+        console_history_size,
+        kill_grace_period,
+        allow_concurrent_launches,
     };
     tokio::task::spawn_blocking(move || {
         let path = get_path();
@@ -66,11 +147,133 @@ fn get_path() -> &'static PathBuf {
 }
 
 pub fn try_read() -> SettingsStateInner {
-    Arc::new(RwLock::new(match read() {
+    let settings = match read() {
         Ok(Some(t)) => Ok(t),
         Ok(None) => Ok(Default::default()),
         Err(e) => Err(CommandError::from(e)),
-    }))
+    };
+    if let Ok(settings) = &settings {
+        apply(settings, None);
+    }
+    Arc::new(RwLock::new(settings))
+}
+
+/// A part of the app that enforces some settings outside of the settings module.
+struct Subscriber {
+    /// The keys of the settings that it depends on, from [`KEYS`].
+    keys: &'static [&'static str],
+    apply: fn(&Settings),
+}
+
+/// Told about the settings when they are read and whenever their keys change.
+const SUBSCRIBERS: &[Subscriber] = &[
+    Subscriber {
+        keys: &["pauseBackgroundActivity"],
+        apply: |settings| crate::background::set_paused(settings.pause_background_activity().value),
+    },
+    Subscriber {
+        keys: &["packageMirrors"],
+        apply: |settings| crate::installing::mirrors::set_mirrors(settings.package_mirrors().value),
+    },
+    Subscriber {
+        keys: &["concurrentDownloads"],
+        apply: |settings| {
+            crate::installing::queue::set_concurrent_downloads(
+                settings.concurrent_downloads().value,
+            )
+        },
+    },
+    Subscriber {
+        keys: &["storeModDocs"],
+        apply: |settings| crate::profiles::docs::set_enabled(settings.store_mod_docs().value),
+    },
+    Subscriber {
+        keys: &["cacheMaxSize", "cacheMaxAge"],
+        apply: |settings| {
+            crate::installing::pruning::set_max_size(settings.cache_max_size().value.as_u64());
+            crate::installing::pruning::set_max_age(settings.cache_max_age().value.as_u64());
+        },
+    },
+    Subscriber {
+        keys: &["prefetchTopMods"],
+        apply: |settings| crate::mod_index::prefetch::set_limit(settings.prefetch_top_mods().value),
+    },
+    Subscriber {
+        keys: &["launchWrapper"],
+        apply: |settings| crate::launching::wrappers::set_default(settings.launch_wrapper().value),
+    },
+    Subscriber {
+        keys: &["launchOptionsTemplates"],
+        apply: |settings| {
+            crate::stores::steam::templates::set(settings.launch_options_templates().value)
+        },
+    },
+    Subscriber {
+        keys: &["consoleHistorySize"],
+        apply: |settings| crate::ipc::history::set_capacity(settings.console_history_size().value),
+    },
+    Subscriber {
+        keys: &["killGracePeriod"],
+        apply: |settings| {
+            crate::ipc::set_kill_grace_period(settings.kill_grace_period().value.as_u64())
+        },
+    },
+    Subscriber {
+        keys: &["allowConcurrentLaunches"],
+        apply: |settings| {
+            crate::launching::instances::set_allow_concurrent(
+                settings.allow_concurrent_launches().value,
+            )
+        },
+    },
+    Subscriber {
+        keys: &[
+            "simulatedLatency",
+            "simulatedBandwidth",
+            "simulatedFailureRate",
+        ],
+        apply: |settings| {
+            crate::util::faults::configure(FaultOptions {
+                latency: Duration::from_millis(settings.simulated_latency().value.as_u64()),
+                bandwidth: settings.simulated_bandwidth().value.as_u64() * 1024,
+                failure_rate: settings
+                    .simulated_failure_rate()
+                    .value
+                    .try_into()
+                    .unwrap_or(100),
+            })
+        },
+    },
+    // rebuilding the HTTP client drops its connections, so it is only done when necessary
+    Subscriber {
+        keys: &[
+            "requestConnectTimeout",
+            "requestReadTimeout",
+            "userAgent",
+            "useHttp2",
+        ],
+        apply: |settings| {
+            let timeout = |secs: usize| (secs != 0).then(|| Duration::from_secs(secs.as_u64()));
+            if let Err(e) = crate::util::http::configure(ClientOptions {
+                connect_timeout: timeout(settings.request_connect_timeout().value),
+                read_timeout: timeout(settings.request_read_timeout().value),
+                user_agent: settings.user_agent().value.to_owned(),
+                http2: settings.use_http2().value,
+            }) {
+                slog_scope::error!("Failed to rebuild the HTTP client: {e}");
+            }
+        },
+    },
+];
+
+/// Propagates settings that are enforced outside of the settings module. If `changed` is given,
+/// only the subscribers of those keys are told.
+fn apply(settings: &Settings, changed: Option<&[&str]>) {
+    for subscriber in SUBSCRIBERS {
+        if changed.is_none_or(|changed| subscriber.keys.iter().any(|key| changed.contains(key))) {
+            (subscriber.apply)(settings);
+        }
+    }
 }
 
 #[derive(Debug, Clone, Copy, serde::Serialize)]
@@ -97,7 +300,7 @@ enum Change<T> {
     Override(T),
 }
 
-#[manderrow_macros::settings(sections = [general, launching])]
+#[manderrow_macros::settings(sections = [general, network, launching, developer])]
 struct Settings {
     #[section(general)]
     #[default(None)]
@@ -110,6 +313,139 @@ struct Settings {
     #[input(toggle)]
     #[ref_by(bool, bool::clone)]
     open_console_on_launch: bool,
+
+    #[section(general)]
+    #[default(false)]
+    #[input(toggle)]
+    #[ref_by(bool, bool::clone)]
+    pause_background_activity: bool,
+
+    #[section(general)]
+    #[default("")]
+    #[input(text)]
+    #[ref_by(&'a str, String::as_str)]
+    package_mirrors: String,
+
+    #[section(general)]
+    #[default(crate::installing::queue::DEFAULT_CONCURRENT_DOWNLOADS)]
+    #[input(number)]
+    #[ref_by(usize, usize::clone)]
+    concurrent_downloads: usize,
+
+    #[section(general)]
+    #[default(false)]
+    #[input(toggle)]
+    #[ref_by(bool, bool::clone)]
+    store_mod_docs: bool,
+
+    // MiB that package zips and mod icons may take up in the cache before the least recently used
+    // of them are evicted, or 0 for no limit.
+    #[section(general)]
+    #[default(0)]
+    #[input(number)]
+    #[ref_by(usize, usize::clone)]
+    cache_max_size: usize,
+
+    // Days after which package zips and mod icons that have not been used are evicted from the
+    // cache, or 0 to keep them.
+    #[section(general)]
+    #[default(0)]
+    #[input(number)]
+    #[ref_by(usize, usize::clone)]
+    cache_max_age: usize,
+
+    // Seconds to wait for a connection to a server, or 0 to wait indefinitely.
+    #[section(network)]
+    #[default(30)]
+    #[input(number)]
+    #[ref_by(usize, usize::clone)]
+    request_connect_timeout: usize,
+
+    // Seconds to wait for more of a response, or 0 to wait indefinitely.
+    #[section(network)]
+    #[default(60)]
+    #[input(number)]
+    #[ref_by(usize, usize::clone)]
+    request_read_timeout: usize,
+
+    // Replaces the default user agent, unless empty.
+    #[section(network)]
+    #[default("")]
+    #[input(text)]
+    #[ref_by(&'a str, String::as_str)]
+    user_agent: String,
+
+    #[section(network)]
+    #[default(true)]
+    #[input(toggle)]
+    #[ref_by(bool, bool::clone)]
+    use_http2: bool,
+
+    // The number of each game's most downloaded mods whose icons and READMEs are downloaded in
+    // the background, or 0 to download them only when needed.
+    #[section(network)]
+    #[default(0)]
+    #[input(number)]
+    #[ref_by(usize, usize::clone)]
+    prefetch_top_mods: usize,
+
+    // Milliseconds to delay HTTP requests and install operations by, for testing.
+    #[section(developer)]
+    #[default(0)]
+    #[input(number)]
+    #[ref_by(usize, usize::clone)]
+    simulated_latency: usize,
+
+    // KiB per second to limit downloads to, or 0 to not limit them, for testing.
+    #[section(developer)]
+    #[default(0)]
+    #[input(number)]
+    #[ref_by(usize, usize::clone)]
+    simulated_bandwidth: usize,
+
+    // Percentage of HTTP requests and install operations to fail, for testing.
+    #[section(developer)]
+    #[default(0)]
+    #[input(number)]
+    #[ref_by(usize, usize::clone)]
+    simulated_failure_rate: usize,
+
+    // Commands to run games through, like `gamemoderun mangohud`, unless a profile sets its own.
+    #[section(launching)]
+    #[default("")]
+    #[input(text)]
+    #[ref_by(&'a str, String::as_str)]
+    launch_wrapper: String,
+
+    // Launch options like `mangohud %command%` that are added to Steam's launch options along
+    // with Manderrow's, for the games that they are selected for.
+    #[section(launching)]
+    #[default(&[])]
+    #[input(launch_options_templates)]
+    #[ref_by(&'a [LaunchOptionsTemplate], Vec::as_slice)]
+    launch_options_templates: Vec<LaunchOptionsTemplate>,
+
+    // The number of messages from each game that are kept to fill the console again when the
+    // window is reloaded.
+    #[section(launching)]
+    #[default(crate::ipc::history::DEFAULT_CAPACITY)]
+    #[input(number)]
+    #[ref_by(usize, usize::clone)]
+    console_history_size: usize,
+
+    // Seconds that a game is given to exit after being asked to, before it is killed.
+    #[section(launching)]
+    #[default(crate::ipc::DEFAULT_KILL_GRACE_PERIOD)]
+    #[input(number)]
+    #[ref_by(usize, usize::clone)]
+    kill_grace_period: usize,
+
+    // Whether a game may be launched again while it is still running.
+    #[section(launching)]
+    #[default(false)]
+    #[input(toggle)]
+    #[ref_by(bool, bool::clone)]
+    allow_concurrent_launches: bool,
 }
 
 /// A representation of settings that must retain complete backwards compatibility. Any necessary
@@ -121,4 +457,75 @@ pub struct SettingsOnDisk {
 
     #[serde(default, skip_serializing_if = "Option::is_none")]
     open_console_on_launch: Option<bool>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pause_background_activity: Option<bool>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    package_mirrors: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    concurrent_downloads: Option<usize>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    store_mod_docs: Option<bool>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    cache_max_size: Option<usize>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    cache_max_age: Option<usize>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    request_connect_timeout: Option<usize>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    request_read_timeout: Option<usize>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    user_agent: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    use_http2: Option<bool>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    prefetch_top_mods: Option<usize>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    simulated_latency: Option<usize>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    simulated_bandwidth: Option<usize>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    simulated_failure_rate: Option<usize>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    launch_wrapper: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    launch_options_templates: Option<Vec<LaunchOptionsTemplate>>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    console_history_size: Option<usize>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    kill_grace_period: Option<usize>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    allow_concurrent_launches: Option<bool>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_subscriber_keys_exist() {
+        for subscriber in SUBSCRIBERS {
+            for key in subscriber.keys {
+                assert!(KEYS.contains(key), "unknown setting {key:?}");
+            }
+        }
+    }
 }
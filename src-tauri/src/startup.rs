@@ -0,0 +1,89 @@
+//! Timings of the sections of app startup, to find out what makes cold starts slow.
+//!
+//! Work that the first window does not need is started on a separate thread with [`defer`], so
+//! that it overlaps with the creation of the window instead of delaying it.
+
+use std::sync::{LazyLock, Once};
+use std::time::{Duration, Instant};
+
+use parking_lot::Mutex;
+
+static START: LazyLock<Instant> = LazyLock::new(Instant::now);
+
+static SECTIONS: Mutex<Vec<Section>> = Mutex::new(Vec::new());
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct Section {
+    pub name: &'static str,
+    /// Milliseconds from the start of the process to the start of the section.
+    pub start_ms: f64,
+    /// Zero for sections that only mark a point in time.
+    pub duration_ms: f64,
+    /// Whether the section ran off the critical path, in parallel with the others.
+    pub deferred: bool,
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+fn record(name: &'static str, started: Instant, deferred: bool) {
+    let section = Section {
+        name,
+        start_ms: millis(started.saturating_duration_since(*START)),
+        duration_ms: millis(started.elapsed()),
+        deferred,
+    };
+    slog_scope::debug!(
+        "Startup section {} took {:.1}ms",
+        section.name,
+        section.duration_ms
+    );
+    SECTIONS.lock().push(section);
+}
+
+/// Starts the clock. Should be called as early as possible in `main`.
+pub fn begin() {
+    LazyLock::force(&START);
+}
+
+/// Runs `f` and records how long it took as the section `name`.
+pub fn time<T>(name: &'static str, f: impl FnOnce() -> T) -> T {
+    let started = Instant::now();
+    let result = f();
+    record(name, started, false);
+    result
+}
+
+/// Records that `name` happened now.
+pub fn mark(name: &'static str) {
+    record(name, Instant::now(), false);
+}
+
+/// Runs `f` on a separate thread and records how long it took as the section `name`.
+pub fn defer(name: &'static str, f: impl FnOnce() + Send + 'static) {
+    if let Err(e) = std::thread::Builder::new()
+        .name(format!("startup-{name}"))
+        .spawn(move || {
+            let started = Instant::now();
+            f();
+            record(name, started, true);
+        })
+    {
+        slog_scope::error!("Failed to spawn thread for startup section {name}: {e}");
+    }
+}
+
+/// Records that the first page has loaded, which ends startup, and logs how long it took.
+pub fn finish() {
+    static FINISHED: Once = Once::new();
+    FINISHED.call_once(|| {
+        mark("page_loaded");
+        slog_scope::info!("Started in {:.1}ms", millis(START.elapsed()));
+    });
+}
+
+/// Returns the sections recorded so far, in the order that they finished.
+pub fn sections() -> Vec<Section> {
+    SECTIONS.lock().clone()
+}
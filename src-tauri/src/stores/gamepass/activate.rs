@@ -0,0 +1,157 @@
+//! The [`ACTIVATE_COMMAND`](super::ACTIVATE_COMMAND) subcommand, which stands in for the game
+//! process while launching a packaged game.
+//!
+//! Packaged games can't be spawned directly, so the package is activated with the game's
+//! arguments instead. If the agent could not be placed beside the game executable, it is then
+//! injected into the new process by having it call `LoadLibraryW`.
+
+use std::ffi::{c_void, OsString};
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context as _, Result};
+use windows::core::{s, w, HSTRING};
+use windows::Win32::Foundation::{CloseHandle, HANDLE, WAIT_OBJECT_0};
+use windows::Win32::System::Com::{
+    CoCreateInstance, CoInitializeEx, CLSCTX_LOCAL_SERVER, COINIT_APARTMENTTHREADED,
+};
+use windows::Win32::System::Diagnostics::Debug::WriteProcessMemory;
+use windows::Win32::System::LibraryLoader::{GetModuleHandleW, GetProcAddress};
+use windows::Win32::System::Memory::{
+    VirtualAllocEx, VirtualFreeEx, MEM_COMMIT, MEM_RELEASE, MEM_RESERVE, PAGE_READWRITE,
+};
+use windows::Win32::System::Threading::{
+    CreateRemoteThread, GetExitCodeProcess, GetExitCodeThread, OpenProcess, WaitForSingleObject,
+    INFINITE, LPTHREAD_START_ROUTINE, PROCESS_CREATE_THREAD, PROCESS_QUERY_INFORMATION,
+    PROCESS_SYNCHRONIZE, PROCESS_VM_OPERATION, PROCESS_VM_WRITE,
+};
+use windows::Win32::UI::Shell::{
+    ApplicationActivationManager, IApplicationActivationManager, AO_NONE,
+};
+
+struct OwnedHandle(HANDLE);
+
+impl Drop for OwnedHandle {
+    fn drop(&mut self) {
+        _ = unsafe { CloseHandle(self.0) };
+    }
+}
+
+/// Usage: `activate-package <AUMID> [--inject <DLL>] [--] <GAME ARGS>...`
+pub fn run(mut args: lexopt::Parser) -> Result<()> {
+    use lexopt::Arg::*;
+
+    let aumid = args.value()?;
+    let mut inject = None::<PathBuf>;
+    let mut game_args = Vec::<OsString>::new();
+    while let Some(arg) = args.next()? {
+        match arg {
+            Long("inject") => inject = Some(args.value()?.into()),
+            Value(arg) => {
+                game_args.push(arg);
+                game_args.extend(args.raw_args()?);
+            }
+            arg => return Err(arg.unexpected().into()),
+        }
+    }
+
+    let command_line = super::build_command_line(game_args)?;
+
+    let pid = unsafe {
+        CoInitializeEx(None, COINIT_APARTMENTTHREADED).ok()?;
+        let manager: IApplicationActivationManager =
+            CoCreateInstance(&ApplicationActivationManager, None, CLSCTX_LOCAL_SERVER)
+                .context("Failed to create activation manager")?;
+        manager
+            .ActivateApplication(
+                &HSTRING::from(aumid.as_os_str()),
+                &HSTRING::from(command_line),
+                AO_NONE,
+            )
+            .with_context(|| format!("Failed to activate {aumid:?}"))?
+    };
+
+    let process = OwnedHandle(unsafe {
+        OpenProcess(
+            PROCESS_CREATE_THREAD
+                | PROCESS_QUERY_INFORMATION
+                | PROCESS_SYNCHRONIZE
+                | PROCESS_VM_OPERATION
+                | PROCESS_VM_WRITE,
+            false,
+            pid,
+        )
+        .with_context(|| format!("Failed to open game process {pid}"))?
+    });
+
+    if let Some(path) = inject {
+        inject_library(process.0, &path)
+            .with_context(|| format!("Failed to inject {path:?} into game process {pid}"))?;
+    }
+
+    let mut code = 0u32;
+    unsafe {
+        if WaitForSingleObject(process.0, INFINITE) != WAIT_OBJECT_0 {
+            bail!("Failed to wait for game process {pid} to exit");
+        }
+        GetExitCodeProcess(process.0, &mut code)?;
+    }
+    std::process::exit(code as i32)
+}
+
+/// Loads the library at `path` into `process` by running `LoadLibraryW` on a remote thread.
+pub(crate) fn inject_library(process: HANDLE, path: &Path) -> Result<()> {
+    let path = HSTRING::from(path);
+    let path = path.as_wide();
+    let size = (path.len() + 1) * size_of::<u16>();
+
+    unsafe {
+        // kernel32 is mapped at the same address in every process of a session
+        let kernel32 = GetModuleHandleW(w!("kernel32.dll"))?;
+        let load_library =
+            GetProcAddress(kernel32, s!("LoadLibraryW")).context("Missing LoadLibraryW")?;
+        let load_library: LPTHREAD_START_ROUTINE = Some(std::mem::transmute::<
+            unsafe extern "system" fn() -> isize,
+            unsafe extern "system" fn(*mut c_void) -> u32,
+        >(load_library));
+
+        let remote_path = VirtualAllocEx(
+            process,
+            None,
+            size,
+            MEM_COMMIT | MEM_RESERVE,
+            PAGE_READWRITE,
+        );
+        if remote_path.is_null() {
+            bail!("Failed to allocate memory in the game process");
+        }
+        struct RemoteAlloc(HANDLE, *mut c_void);
+        impl Drop for RemoteAlloc {
+            fn drop(&mut self) {
+                _ = unsafe { VirtualFreeEx(self.0, self.1, 0, MEM_RELEASE) };
+            }
+        }
+        let remote_path = RemoteAlloc(process, remote_path);
+
+        // HSTRING's buffer is nul-terminated, so this includes the terminator
+        WriteProcessMemory(process, remote_path.1, path.as_ptr().cast(), size, None)?;
+
+        let thread = OwnedHandle(CreateRemoteThread(
+            process,
+            None,
+            0,
+            load_library,
+            Some(remote_path.1.cast_const()),
+            0,
+            None,
+        )?);
+        if WaitForSingleObject(thread.0, INFINITE) != WAIT_OBJECT_0 {
+            bail!("Failed to wait for the library to load");
+        }
+        let mut module = 0u32;
+        GetExitCodeThread(thread.0, &mut module)?;
+        if module == 0 {
+            bail!("LoadLibraryW failed in the game process");
+        }
+    }
+    Ok(())
+}
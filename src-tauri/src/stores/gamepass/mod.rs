@@ -0,0 +1,209 @@
+//! Games installed through the Xbox app or the Microsoft Store, which is how Game Pass games are
+//! installed on PC.
+//!
+//! These games are packaged apps. Older installs live under the protected `WindowsApps` folder,
+//! which can't be written to, while newer installs keep their files in a writable folder such as
+//! `C:\XboxGames\<Game>\Content`. Either way, the game must be started by activating its package
+//! rather than by running its executable directly.
+
+#[cfg(windows)]
+pub mod activate;
+
+use std::ffi::OsString;
+use std::path::PathBuf;
+
+use anyhow::{anyhow, Context as _, Result};
+
+/// The subcommand of the Manderrow executable that activates a package, see [`activate`].
+pub const ACTIVATE_COMMAND: &str = "activate-package";
+
+#[derive(Debug, Clone)]
+pub struct Package {
+    /// e.g. `CoffeeStainStudios.Valheim_0.217.46.0_x64__gsb4ysv3a1h4r`
+    pub full_name: String,
+    /// e.g. `CoffeeStainStudios.Valheim_gsb4ysv3a1h4r`
+    pub family_name: String,
+    /// The id of the game's entry in the package manifest, usually `Game` or `App`.
+    pub app_id: String,
+    pub install_dir: PathBuf,
+}
+
+impl Package {
+    /// The id that the package's app is activated by.
+    pub fn app_user_model_id(&self) -> String {
+        format!("{}!{}", self.family_name, self.app_id)
+    }
+
+    /// The URI that launches the game from the shell, e.g. for a desktop shortcut.
+    pub fn launch_uri(&self) -> String {
+        format!("shell:AppsFolder\\{}", self.app_user_model_id())
+    }
+
+    /// Returns `true` if the game files can't be modified, so the agent can't be placed beside
+    /// the game executable and must be injected once the game has started instead.
+    pub fn is_protected(&self) -> bool {
+        self.install_dir
+            .components()
+            .any(|c| c.as_os_str().eq_ignore_ascii_case("WindowsApps"))
+    }
+}
+
+/// Returns the package family name for a package full name, which drops the version,
+/// architecture, and resource id.
+fn family_name_from_full_name(full_name: &str) -> Option<String> {
+    let mut parts = full_name.split('_');
+    let name = parts.next()?;
+    let publisher_id = parts.nth(3)?;
+    if parts.next().is_some() {
+        return None;
+    }
+    Some(format!("{name}_{publisher_id}"))
+}
+
+/// Finds the id of the first app declared in the contents of an `AppxManifest.xml` file.
+fn read_app_id(manifest: &str) -> Option<&str> {
+    let (_, rest) = manifest.split_once("<Application ")?;
+    let (attrs, _) = rest.split_once('>')?;
+    let (_, rest) = attrs.split_once(" Id=\"")?;
+    let (id, _) = rest.split_once('"')?;
+    Some(id)
+}
+
+/// Finds the installed package with the package name `identifier`, as found in the game's Xbox
+/// store metadata. Returns `None` if the game is not installed.
+#[cfg(windows)]
+pub fn find_package(identifier: &str) -> Result<Option<Package>> {
+    use registry::{Data, Hive, Security};
+
+    const PACKAGES_KEY: &str = r"Software\Classes\Local Settings\Software\Microsoft\Windows\CurrentVersion\AppModel\Repository\Packages";
+
+    let packages = Hive::CurrentUser.open(PACKAGES_KEY, Security::Read)?;
+    let prefix = format!("{identifier}_");
+    for key in packages.keys() {
+        let key = key?;
+        let full_name = key.to_string();
+        if !full_name.starts_with(&prefix) {
+            continue;
+        }
+        let key = key.open(Security::Read)?;
+        let install_dir = match key.value("PackageRootFolder")? {
+            Data::String(s) | Data::ExpandString(s) => PathBuf::from(s.to_string()?),
+            _ => return Err(anyhow!("Unexpected data type in registry")),
+        };
+        let family_name = family_name_from_full_name(&full_name)
+            .with_context(|| format!("Invalid package full name {full_name:?}"))?;
+        let manifest_path = install_dir.join("AppxManifest.xml");
+        let manifest = std::fs::read_to_string(&manifest_path)
+            .with_context(|| format!("Failed to read {manifest_path:?}"))?;
+        let app_id = read_app_id(&manifest)
+            .with_context(|| format!("No app declared in {manifest_path:?}"))?
+            .to_owned();
+        return Ok(Some(Package {
+            full_name,
+            family_name,
+            app_id,
+            install_dir,
+        }));
+    }
+    Ok(None)
+}
+
+#[cfg(not(windows))]
+pub fn find_package(_identifier: &str) -> Result<Option<Package>> {
+    Ok(None)
+}
+
+/// Builds a command that runs [`ACTIVATE_COMMAND`] to launch the package. Arguments for the
+/// game, such as the agent's, must follow the command's own arguments.
+pub fn activate_command(package: &Package) -> Result<tokio::process::Command> {
+    let mut command = tokio::process::Command::new(
+        std::env::current_exe().context("Failed to get current exe path")?,
+    );
+    command.arg(ACTIVATE_COMMAND);
+    command.arg(package.app_user_model_id());
+    Ok(command)
+}
+
+/// Joins `args` into a single command line, quoting them such that the game's C runtime splits
+/// them back into the same arguments.
+pub fn build_command_line(args: impl IntoIterator<Item = OsString>) -> Result<String> {
+    let mut buf = String::new();
+    for arg in args {
+        let arg = arg
+            .into_string()
+            .map_err(|s| anyhow!("Non-Unicode argument: {s:?}"))?;
+        if !buf.is_empty() {
+            buf.push(' ');
+        }
+        if !arg.is_empty() && !arg.contains([' ', '\t', '\n', '"']) {
+            buf.push_str(&arg);
+            continue;
+        }
+        buf.push('"');
+        let mut backslashes = 0;
+        for c in arg.chars() {
+            if c == '\\' {
+                backslashes += 1;
+            } else {
+                if c == '"' {
+                    // escape the preceding backslashes and the quote itself
+                    buf.extend(std::iter::repeat_n('\\', backslashes + 1));
+                }
+                backslashes = 0;
+            }
+            buf.push(c);
+        }
+        // escape trailing backslashes so that they don't escape the closing quote
+        buf.extend(std::iter::repeat_n('\\', backslashes));
+        buf.push('"');
+    }
+    Ok(buf)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_family_name_from_full_name() {
+        assert_eq!(
+            family_name_from_full_name("CoffeeStainStudios.Valheim_0.217.46.0_x64__gsb4ysv3a1h4r")
+                .as_deref(),
+            Some("CoffeeStainStudios.Valheim_gsb4ysv3a1h4r")
+        );
+        assert_eq!(
+            family_name_from_full_name("CoffeeStainStudios.Valheim"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_read_app_id() {
+        let manifest = r#"<Package><Applications>
+            <Application Id="Game" Executable="gamelaunchhelper.exe" EntryPoint="Windows.FullTrustApplication">
+            </Application>
+        </Applications></Package>"#;
+        assert_eq!(read_app_id(manifest), Some("Game"));
+        assert_eq!(read_app_id("<Package></Package>"), None);
+    }
+
+    #[test]
+    fn test_build_command_line() {
+        let line = build_command_line(
+            [
+                "{manderrow",
+                "--logs-dir",
+                r"C:\Users\Some One\logs\",
+                r#"say "hi""#,
+                "",
+                "manderrow}",
+            ]
+            .map(OsString::from),
+        )
+        .unwrap();
+        assert_eq!(
+            line,
+            r#"{manderrow --logs-dir "C:\Users\Some One\logs\\" "say \"hi\"" "" manderrow}"#
+        );
+    }
+}
@@ -1 +1,2 @@
+pub mod gamepass;
 pub mod steam;
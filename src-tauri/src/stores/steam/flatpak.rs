@@ -0,0 +1,130 @@
+//! Giving Steam's Flatpak sandbox access to Manderrow.
+//!
+//! Games launched by Steam from Flatpak run inside of its sandbox, and so does Manderrow's wrapper.
+//! The sandbox can only see its own home directory and whatever the user has given it access to
+//! through `flatpak override`, so Manderrow's executable, the agent, and the profiles have to be
+//! shared with it before a game can be launched with mods.
+
+use std::borrow::Cow;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context as _, Result};
+use manderrow_paths::home_dir;
+use slog::info;
+
+use crate::util::IoErrorKindExt as _;
+
+use super::paths::FLATPAK_APP_ID;
+
+/// Returns the `--filesystem` permission that makes `path` visible to the sandbox, read-only.
+pub fn filesystem_permission(path: &Path) -> Cow<'static, str> {
+    if path.starts_with("/usr") {
+        // /usr can't be shared on its own, only along with the rest of the host's OS
+        Cow::Borrowed("host-os:ro")
+    } else {
+        Cow::Owned(format!("{}:ro", path.display()))
+    }
+}
+
+/// Whether any of the `filesystems` permissions, as listed in a Flatpak override file, make `path`
+/// visible to the sandbox.
+pub(super) fn is_exposed_by(filesystems: &str, home: &Path, path: &Path) -> bool {
+    filesystems
+        .split(';')
+        .filter(|entry| !entry.is_empty() && !entry.starts_with('!'))
+        .map(|entry| {
+            entry
+                .rsplit_once(':')
+                .filter(|(_, mode)| matches!(*mode, "ro" | "rw" | "create"))
+                .map_or(entry, |(entry, _)| entry)
+        })
+        .any(|entry| match entry {
+            "host-os" => path.starts_with("/usr"),
+            "host" | "host-etc" if path.starts_with("/usr") => false,
+            "host" => true,
+            "home" => path.starts_with(home),
+            _ => match entry.strip_prefix("~/") {
+                Some(rel) => path.starts_with(home.join(rel)),
+                None => entry.starts_with('/') && path.starts_with(entry),
+            },
+        })
+}
+
+/// Returns the `filesystems` permissions that the user and system have granted to Steam.
+pub(super) async fn read_filesystem_overrides() -> Result<String> {
+    let mut filesystems = String::new();
+    for dir in [
+        home_dir().join(".local/share/flatpak/overrides"),
+        PathBuf::from("/var/lib/flatpak/overrides"),
+    ] {
+        let path = dir.join(FLATPAK_APP_ID);
+        let contents = match tokio::fs::read_to_string(&path).await {
+            Ok(t) => t,
+            Err(e) if e.is_not_found() => continue,
+            Err(e) => {
+                return Err(anyhow::Error::from(e).context(format!("Failed to read {path:?}")))
+            }
+        };
+        for line in contents.lines() {
+            if let Some(value) = line.trim().strip_prefix("filesystems=") {
+                filesystems.push_str(value);
+                filesystems.push(';');
+            }
+        }
+    }
+    Ok(filesystems)
+}
+
+/// Returns the paths that the sandbox has not been given access to.
+pub async fn find_hidden<'a>(paths: &[&'a Path]) -> Result<Vec<&'a Path>> {
+    let filesystems = read_filesystem_overrides().await?;
+    let sandbox_home = home_dir().join(".var/app").join(FLATPAK_APP_ID);
+    Ok(paths
+        .iter()
+        .copied()
+        .filter(|path| {
+            !path.starts_with(&sandbox_home) && !is_exposed_by(&filesystems, home_dir(), path)
+        })
+        .collect())
+}
+
+/// Gives the sandbox read-only access to `paths`, for the current user.
+pub async fn expose(log: &slog::Logger, paths: &[&Path]) -> Result<()> {
+    let mut command = tokio::process::Command::new("flatpak");
+    command.args(["override", "--user"]);
+    for path in paths {
+        command.arg(format!("--filesystem={}", filesystem_permission(path)));
+    }
+    command.arg(FLATPAK_APP_ID);
+    command
+        .status()
+        .await
+        .context("Failed to run flatpak")?
+        .exit_ok()
+        .context("Failed to override Steam's Flatpak permissions")?;
+    info!(log, "Gave Steam's Flatpak sandbox access to {paths:?}");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_exposed_by() {
+        let home = Path::new("/home/user");
+        let data = Path::new("/home/user/.local/share/manderrow");
+        let exe = Path::new("/usr/bin/manderrow");
+
+        assert!(!is_exposed_by("", home, data));
+        assert!(is_exposed_by("xdg-music:ro;home;", home, data));
+        assert!(is_exposed_by("~/.local/share/manderrow:ro;", home, data));
+        assert!(is_exposed_by("/home/user/.local:rw;", home, data));
+        assert!(!is_exposed_by("!home;", home, data));
+        assert!(!is_exposed_by("/home/user/.loc;", home, data));
+
+        assert!(!is_exposed_by("host;", home, exe));
+        assert!(is_exposed_by("host-os:ro;", home, exe));
+        assert!(!is_exposed_by("host-os:ro;", home, data));
+    }
+}
@@ -3,15 +3,18 @@ use std::ops::BitOrAssign;
 
 use anyhow::{anyhow, bail, Context as _, Result};
 use slog::{debug, info};
-use tokio::process::Command;
 
-use super::paths::{get_steam_exe, resolve_steam_directory};
+use super::paths::{resolve_steam_directory, resolve_steam_packaging};
+use super::templates;
+use super::watchdog::{self, Consent};
 use crate::{
     ipc::{DoctorFix, InProcessIpc, OutputLine},
     wrap::WrapperMode,
 };
 
-pub async fn kill_steam(log: &slog::Logger) -> Result<()> {
+/// Finds the running Steam processes.
+async fn find_steam_processes(log: &slog::Logger) -> Result<Vec<manderrow_process_util::Pid>> {
+    let mut pids = Vec::new();
     #[cfg(windows)]
     {
         use std::num::NonZeroU32;
@@ -19,7 +22,8 @@ pub async fn kill_steam(log: &slog::Logger) -> Result<()> {
 
         use winsafe::prelude::*;
 
-        let mut issued_shutdown = false;
+        _ = log;
+
         for proc in
             winsafe::HPROCESSLIST::CreateToolhelp32Snapshot(winsafe::co::TH32CS::SNAPPROCESS, None)?
                 .iter_processes()
@@ -34,25 +38,9 @@ pub async fn kill_steam(log: &slog::Logger) -> Result<()> {
             let name = unsafe { NonNull::from(&proc.szExeFile).cast::<[u8; 260]>().as_ref() };
             let name = std::ffi::CStr::from_bytes_until_nul(name)?;
             if name.to_bytes() == b"steam.exe" {
-                if !issued_shutdown {
-                    issued_shutdown = true;
-                    info!(log, "Steam is open. Issuing shutdown request.");
-                    Command::new(get_steam_exe()?.as_ref())
-                        .arg("-shutdown")
-                        .status()
-                        .await?
-                        .exit_ok()?;
-                }
-
-                info!(
-                    log,
-                    "Waiting for Steam process {} to shut down", proc.th32ProcessID
-                );
-                manderrow_process_util::Pid::from_raw(
+                pids.push(manderrow_process_util::Pid::from_raw(
                     NonZeroU32::new(proc.th32ProcessID).context("null pid")?,
-                )
-                .wait_for_exit(log)
-                .await?;
+                ));
             }
         }
     }
@@ -72,7 +60,7 @@ pub async fn kill_steam(log: &slog::Logger) -> Result<()> {
                     log,
                     "pgrep exited with code 1 and no output. Assuming no processes found."
                 );
-                return Ok(());
+                return Ok(pids);
             }
         }
         match output.status.exit_ok() {
@@ -86,52 +74,168 @@ pub async fn kill_steam(log: &slog::Logger) -> Result<()> {
             }
         }
 
-        info!(log, "Steam is open. Issuing shutdown request.");
-        Command::new(get_steam_exe()?.as_ref())
-            .arg("-shutdown")
-            .status()
-            .await?
-            .exit_ok()?;
-
         let output = String::from_utf8(output.stdout)?;
 
         for pid in output.lines() {
             let pid = pid.parse().context("Invalid pid from pgrep")?;
-            manderrow_process_util::Pid::from_raw(pid)
-                .wait_for_exit(log)
-                .await?;
+            pids.push(manderrow_process_util::Pid::from_raw(pid));
         }
     }
+    Ok(pids)
+}
+
+/// Steam writes its own copy of `localconfig.vdf` when it exits, overwriting any changes made to
+/// it while it was running.
+#[derive(Debug, thiserror::Error)]
+#[error("Steam is running and would overwrite changes to its configuration when it exits")]
+pub struct SteamRunningError;
+
+pub async fn is_steam_running(log: &slog::Logger) -> Result<bool> {
+    Ok(!find_steam_processes(log).await?.is_empty())
+}
+
+pub async fn kill_steam(log: &slog::Logger) -> Result<()> {
+    let pids = find_steam_processes(log).await?;
+    if pids.is_empty() {
+        return Ok(());
+    }
+
+    info!(log, "Steam is open. Issuing shutdown request.");
+    resolve_steam_packaging()
+        .await?
+        .command()?
+        .arg("-shutdown")
+        .status()
+        .await?
+        .exit_ok()?;
+
+    for pid in pids {
+        info!(log, "Waiting for Steam process {pid:?} to shut down");
+        pid.wait_for_exit(log).await?;
+    }
     Ok(())
 }
 
-pub fn generate_launch_options(mode: WrapperMode) -> Result<String> {
-    let bin = std::env::current_exe()
-        .context("Failed to get current exe path")?
+/// Makes sure that Steam is closed before its configuration is changed. If possible, the user is
+/// asked whether to close Steam or to wait for them to close it.
+async fn ensure_steam_closed(
+    log: &slog::Logger,
+    comms: Option<&mut InProcessIpc>,
+) -> Result<(), crate::Error> {
+    let pids = find_steam_processes(log).await?;
+    if pids.is_empty() {
+        return Ok(());
+    }
+    let Some(ipc) = comms else {
+        kill_steam(log).await?;
+        return Ok(());
+    };
+
+    #[derive(Clone, Copy, serde::Deserialize, serde::Serialize)]
+    #[serde(rename_all = "snake_case")]
+    enum Fix {
+        Close,
+        Wait,
+        Abort,
+    }
+    let choice = ipc
+        .prompt_patient(
+            "steam_running",
+            None,
+            None,
+            [Fix::Close, Fix::Wait, Fix::Abort].map(|id| DoctorFix {
+                id,
+                label: None,
+                confirm_label: None,
+                description: None,
+            }),
+            Fix::Abort,
+        )
+        .await?;
+    match choice {
+        Fix::Close => kill_steam(log).await?,
+        Fix::Wait => {
+            info!(log, "Waiting for the user to close Steam");
+            for pid in pids {
+                pid.wait_for_exit(log).await?;
+            }
+        }
+        Fix::Abort => return Err(crate::Error::Aborted),
+    }
+    Ok(())
+}
+
+/// Returns the launch options that make Steam run the game with the Steam app id `game_id`
+/// through Manderrow's wrapper, along with the templates selected for it. Steam runs them inside
+/// of its sandbox, if it has one, so they refer to Manderrow by the path that it has there.
+pub async fn generate_launch_options(game_id: &str, mode: WrapperMode) -> Result<String> {
+    let bin = std::env::current_exe().context("Failed to get current exe path")?;
+    let bin = resolve_steam_packaging()
+        .await?
+        .host_to_sandbox(&bin)
+        .into_owned()
         .into_os_string()
         .into_string()
         .map_err(|s| anyhow!("Non-Unicode executable name: {s:?}"))?;
-    Ok(format!(
+    let wrapper = format!(
         "{bin:?} wrap-{} %command%",
         match mode {
             WrapperMode::Injection => "with-injection",
         }
+    );
+    Ok(compose_launch_args(
+        &templates::for_steam_game(game_id),
+        &wrapper,
     ))
 }
 
+/// Adds the options of `templates` around `wrapper`, which are Manderrow's launch options. The
+/// commands of the templates run Manderrow's wrapper, in the order that they are given in.
+fn compose_launch_args(templates: &[String], wrapper: &str) -> String {
+    let mut composed = String::new();
+    let mut suffix = String::new();
+    for template in templates {
+        let (before, after) = template.split_once("%command%").unwrap_or((template, ""));
+        let (before, after) = (before.trim(), after.trim());
+        if !before.is_empty() {
+            composed.push_str(before);
+            composed.push(' ');
+        }
+        if !after.is_empty() {
+            suffix.push(' ');
+            suffix.push_str(after);
+        }
+    }
+    composed.push_str(wrapper);
+    composed.push_str(&suffix);
+    composed
+}
+
 pub async fn ensure_unix_launch_args_are_applied(
     log: &slog::Logger,
     mut comms: Option<&mut InProcessIpc>,
     game_id: &str,
     mode: WrapperMode,
 ) -> Result<(), crate::Error> {
-    let args = generate_launch_options(mode)?;
+    let args = generate_launch_options(game_id, mode).await?;
     loop {
-        let result = apply_launch_args(game_id, &args, true, true).await?;
+        let consent = watchdog::consent(game_id).await?;
+        let previous = consent.as_ref().and_then(|c| c.args.as_deref());
+        let result = apply_launch_args(log, game_id, &args, previous, true, true).await?;
         if matches!(
             result,
             AppliedLaunchArgs::Applied | AppliedLaunchArgs::Overwrote
         ) {
+            if let Some(consent) = &consent {
+                if consent.overwrite || matches!(result, AppliedLaunchArgs::Applied) {
+                    // the options were reverted or changed since the user agreed to apply them
+                    ensure_steam_closed(log, comms.as_deref_mut()).await?;
+                    if reapply_launch_args(log, game_id, &args, consent).await? {
+                        watchdog::remember_consent(game_id, consent.overwrite, &args).await?;
+                    }
+                    break;
+                }
+            }
             #[derive(serde::Deserialize, serde::Serialize)]
             #[serde(rename_all = "snake_case")]
             enum Fix {
@@ -143,15 +247,28 @@ pub async fn ensure_unix_launch_args_are_applied(
             let Some(ipc) = &mut comms else {
                 return Err(anyhow!("Not adding launch options without consent").into());
             };
+            // show exactly what the launch options will be, since the user's own are kept
+            let changes = preview_launch_args(log, game_id, &args, previous).await?;
+            let join = |f: fn(&LaunchArgsChange) -> &str| {
+                changes.iter().map(f).collect::<Vec<_>>().join("\n")
+            };
+            let after = join(|c| c.after.as_str());
+            let message_args = [
+                ("before".to_owned(), join(|c| c.before.as_str())),
+                ("after".to_owned(), after.clone()),
+            ];
+            let message = if matches!(result, AppliedLaunchArgs::Overwrote) {
+                Some("doctor.launch_options.message_overwrite".to_owned())
+            } else if changes.iter().any(|c| !c.before.is_empty()) {
+                Some("doctor.launch_options.message_wrap".to_owned())
+            } else {
+                None
+            };
             let choice = ipc
                 .prompt_patient(
                     "launch_options",
-                    if matches!(result, AppliedLaunchArgs::Overwrote) {
-                        Some("doctor.launch_options.message_overwrite".to_owned())
-                    } else {
-                        None
-                    },
-                    None,
+                    message,
+                    Some(message_args.into()),
                     [
                         DoctorFix {
                             id: Fix::Apply,
@@ -163,7 +280,7 @@ pub async fn ensure_unix_launch_args_are_applied(
                             id: Fix::Retry,
                             label: None,
                             confirm_label: None,
-                            description: Some([("launch_options".to_owned(), args.clone())].into()),
+                            description: Some([("launch_options".to_owned(), after)].into()),
                         },
                         DoctorFix {
                             id: Fix::Ignore,
@@ -178,18 +295,15 @@ pub async fn ensure_unix_launch_args_are_applied(
                             description: None,
                         },
                     ],
+                    Fix::Abort,
                 )
                 .await?;
             match choice {
                 Fix::Apply => {
+                    let overwrite = matches!(result, AppliedLaunchArgs::Overwrote);
                     kill_steam(log).await?;
-                    apply_launch_args(
-                        game_id,
-                        &args,
-                        matches!(result, AppliedLaunchArgs::Overwrote),
-                        false,
-                    )
-                    .await?;
+                    apply_launch_args(log, game_id, &args, previous, overwrite, false).await?;
+                    watchdog::remember_consent(game_id, overwrite, &args).await?;
                     break;
                 }
                 Fix::Retry => {}
@@ -203,6 +317,144 @@ pub async fn ensure_unix_launch_args_are_applied(
     Ok(())
 }
 
+/// Re-applies launch options that the user has agreed to, if they are no longer applied or have
+/// changed. Steam must not be running.
+///
+/// Returns `true` if they were re-applied, in which case the caller must record that `args` were
+/// applied in the consent.
+pub async fn reapply_launch_args(
+    log: &slog::Logger,
+    game_id: &str,
+    args: &str,
+    consent: &Consent,
+) -> Result<bool> {
+    let previous = consent.args.as_deref();
+    match apply_launch_args(log, game_id, args, previous, true, true).await? {
+        AppliedLaunchArgs::Unchanged => Ok(false),
+        AppliedLaunchArgs::Overwrote if !consent.overwrite => {
+            debug!(
+                log,
+                "Launch options for {game_id} were replaced, but the user has not agreed to overwrite them"
+            );
+            Ok(false)
+        }
+        AppliedLaunchArgs::Applied | AppliedLaunchArgs::Overwrote => {
+            info!(
+                log,
+                "Launch options for {game_id} were reverted. Re-applying them."
+            );
+            apply_launch_args(log, game_id, args, previous, consent.overwrite, false).await?;
+            Ok(true)
+        }
+    }
+}
+
+/// Removes the launch options that Manderrow applied to the game, leaving any others alone, so
+/// that Steam can launch it without Manderrow installed. Once they are removed, the watchdog stops
+/// re-applying them.
+///
+/// If `comms` is given, the user is shown how the launch options will change and asked to confirm,
+/// which closes Steam if it is running.
+///
+/// Returns `true` if they were removed.
+///
+/// Fails with [`SteamRunningError`] if they need to be removed, Steam is running, and `comms` is
+/// not given.
+pub async fn remove_launch_args(
+    log: &slog::Logger,
+    comms: Option<&mut InProcessIpc>,
+    game_id: &str,
+) -> Result<bool, crate::Error> {
+    let consent = watchdog::consent(game_id).await?;
+    let args = generate_launch_options(game_id, WrapperMode::Injection).await?;
+    let edit = LaunchArgsEdit::Remove {
+        args: &args,
+        previous: consent.as_ref().and_then(|c| c.args.as_deref()),
+    };
+    let mut changes = Vec::new();
+    if matches!(
+        edit_launch_args(log, game_id, edit, true, &mut changes).await?,
+        AppliedLaunchArgs::Unchanged
+    ) {
+        watchdog::forget_consent(game_id).await?;
+        return Ok(false);
+    }
+    if let Some(ipc) = comms {
+        #[derive(Clone, Copy, serde::Deserialize, serde::Serialize)]
+        #[serde(rename_all = "snake_case")]
+        enum Fix {
+            Remove,
+            Abort,
+        }
+        changes.dedup();
+        let join =
+            |f: fn(&LaunchArgsChange) -> &str| changes.iter().map(f).collect::<Vec<_>>().join("\n");
+        let message_args = [
+            ("before".to_owned(), join(|c| c.before.as_str())),
+            ("after".to_owned(), join(|c| c.after.as_str())),
+        ];
+        let choice = ipc
+            .prompt_patient(
+                "launch_options_remove",
+                None,
+                Some(message_args.into()),
+                [Fix::Remove, Fix::Abort].map(|id| DoctorFix {
+                    id,
+                    label: None,
+                    confirm_label: None,
+                    description: None,
+                }),
+                Fix::Abort,
+            )
+            .await?;
+        match choice {
+            Fix::Remove => kill_steam(log).await?,
+            Fix::Abort => return Err(crate::Error::Aborted),
+        }
+    }
+    edit_launch_args(log, game_id, edit, false, &mut Vec::new()).await?;
+    // not before, so that they are still re-applied if they couldn't be removed
+    watchdog::forget_consent(game_id).await?;
+    info!(log, "Removed launch options for {game_id}");
+    Ok(true)
+}
+
+/// An edit to Manderrow's launch options. Those that were applied before are recognized by
+/// Manderrow's wrapper in them, or exactly if they are `previous`, which is what was last applied.
+#[derive(Clone, Copy)]
+enum LaunchArgsEdit<'a> {
+    /// Merges `args` into the launch options, replacing the ones Manderrow applied before. Fails
+    /// if they can't be merged with the launch options that are set, unless `overwrite_ok`, in
+    /// which case they are replaced.
+    Apply {
+        args: &'a str,
+        previous: Option<&'a str>,
+        overwrite_ok: bool,
+    },
+    /// Removes `args`, or the ones Manderrow applied before, from the launch options, leaving the
+    /// rest of them.
+    Remove {
+        args: &'a str,
+        previous: Option<&'a str>,
+    },
+}
+
+/// A change to the launch options of a Steam user, for showing to the user before it is made.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct LaunchArgsChange {
+    before: String,
+    after: String,
+}
+
+impl LaunchArgsChange {
+    fn new(before: &[u8], after: &[u8]) -> Self {
+        Self {
+            before: String::from_utf8_lossy(before).into_owned(),
+            after: String::from_utf8_lossy(after).into_owned(),
+        }
+    }
+}
+
 #[derive(Clone, Copy)]
 enum AppliedLaunchArgs {
     Unchanged,
@@ -226,12 +478,71 @@ impl BitOrAssign for AppliedLaunchArgs {
 /// options have already been applied.
 ///
 /// Returns `true` if a change was made, or would be made if this is a dry run.
+///
+/// Fails with [`SteamRunningError`] if this is not a dry run and Steam is running.
 async fn apply_launch_args(
+    log: &slog::Logger,
     game_id: &str,
     args: &str,
+    previous: Option<&str>,
     overwrite_ok: bool,
     dry_run: bool,
 ) -> Result<AppliedLaunchArgs> {
+    edit_launch_args(
+        log,
+        game_id,
+        LaunchArgsEdit::Apply {
+            args,
+            previous,
+            overwrite_ok,
+        },
+        dry_run,
+        &mut Vec::new(),
+    )
+    .await
+}
+
+/// Returns how applying `args` would change the launch options of each Steam user, without
+/// changing them.
+async fn preview_launch_args(
+    log: &slog::Logger,
+    game_id: &str,
+    args: &str,
+    previous: Option<&str>,
+) -> Result<Vec<LaunchArgsChange>> {
+    let mut changes = Vec::new();
+    edit_launch_args(
+        log,
+        game_id,
+        LaunchArgsEdit::Apply {
+            args,
+            previous,
+            overwrite_ok: true,
+        },
+        true,
+        &mut changes,
+    )
+    .await?;
+    // Steam users often have the same launch options
+    changes.dedup();
+    Ok(changes)
+}
+
+/// Makes `edit` to the game's launch options for every Steam user, adding the changes to
+/// `changes`. If `dry_run` is `true`, this will simply check whether a change would be made.
+///
+/// Fails with [`SteamRunningError`] if this is not a dry run and Steam is running.
+async fn edit_launch_args(
+    log: &slog::Logger,
+    game_id: &str,
+    edit: LaunchArgsEdit<'_>,
+    dry_run: bool,
+    changes: &mut Vec<LaunchArgsChange>,
+) -> Result<AppliedLaunchArgs> {
+    if !dry_run && is_steam_running(log).await? {
+        return Err(SteamRunningError.into());
+    }
+
     let mut path = resolve_steam_directory().await?;
     path.push("userdata");
 
@@ -262,11 +573,11 @@ async fn apply_launch_args(
             let rdr = vdf::Reader::new(std::io::BufReader::new(std::fs::File::open(&path)?));
 
             let result = if let Some(ref mut wtr) = wtr {
-                let result = apply_launch_args_inner(game_id, overwrite_ok, args, rdr, &mut *wtr)?;
+                let result = edit_launch_args_inner(game_id, edit, rdr, &mut *wtr, changes)?;
                 wtr.flush()?;
                 result
             } else {
-                apply_launch_args_inner(game_id, overwrite_ok, args, rdr, std::io::empty())?
+                edit_launch_args_inner(game_id, edit, rdr, std::io::empty(), changes)?
             };
             drop(wtr);
 
@@ -276,7 +587,7 @@ async fn apply_launch_args(
 
             Ok::<_, anyhow::Error>(result)
         })
-        .with_context(|| format!("Failed to apply launch options to {path:?}"))?;
+        .with_context(|| format!("Failed to edit launch options in {path:?}"))?;
 
         path.pop();
         path.pop();
@@ -285,15 +596,177 @@ async fn apply_launch_args(
     Ok(result)
 }
 
-fn apply_launch_args_inner<R: std::io::BufRead, W: std::io::Write>(
+/// Where Steam substitutes the command that it would have run the game with.
+const COMMAND: &[u8] = b"%command%";
+
+/// How Manderrow's wrapper is invoked, followed by the mode, like `wrap-with-injection`.
+const WRAPPER_COMMAND: &[u8] = b"wrap-with-";
+
+/// Launch options split around the `%command%` in them, like `PROTON_LOG=1 gamemoderun %command%
+/// -windowed`. Steam appends launch options without a `%command%` to the command, so they are
+/// treated as if they started with one.
+struct ParsedLaunchOptions<'a> {
+    /// What runs the game, like wrappers and environment variables.
+    prefix: &'a [u8],
+    /// The arguments passed to the game, including the space before them.
+    suffix: &'a [u8],
+    /// Whether `%command%` is written out.
+    explicit: bool,
+}
+
+impl<'a> ParsedLaunchOptions<'a> {
+    /// Returns `None` if `%command%` appears more than once, in which case it is not clear where
+    /// Manderrow's wrapper would go.
+    fn parse(options: &'a [u8]) -> Option<Self> {
+        let mut matches = options
+            .windows(COMMAND.len())
+            .enumerate()
+            .filter(|(_, w)| *w == COMMAND)
+            .map(|(i, _)| i);
+        match (matches.next(), matches.next()) {
+            (None, _) => Some(Self {
+                prefix: b"",
+                suffix: options,
+                explicit: false,
+            }),
+            (Some(i), None) => Some(Self {
+                prefix: &options[..i],
+                suffix: &options[i + COMMAND.len()..],
+                explicit: true,
+            }),
+            (Some(_), Some(_)) => None,
+        }
+    }
+
+    /// Parses Manderrow's launch options, which must contain `%command%`.
+    fn parse_args(args: &'a str) -> Option<Self> {
+        Self::parse(args.as_bytes()).filter(|args| args.explicit)
+    }
+
+    /// Returns what comes before Manderrow's launch options in the prefix and after them in the
+    /// suffix, or `None` if they aren't there.
+    fn strip_wrapper(&self, args: &str) -> Option<(&'a [u8], &'a [u8])> {
+        let args = ParsedLaunchOptions::parse_args(args)?;
+        let before = self.prefix.strip_suffix(args.prefix)?;
+        let after = self.suffix.strip_prefix(args.suffix)?;
+        ((before.is_empty() || before.ends_with(b" "))
+            && (args.suffix.is_empty() || after.is_empty() || after.starts_with(b" ")))
+        .then_some((before, after))
+    }
+
+    /// Like [`Self::strip_wrapper`], but also recognizes Manderrow's launch options as they were
+    /// applied before, either exactly as `previous` or just by Manderrow's wrapper, whatever
+    /// executable and templates they were applied with. In the latter case, the options of the
+    /// templates can't be told apart from the user's, so they are kept.
+    fn strip_applied(&self, args: &str, previous: Option<&str>) -> Option<(&'a [u8], &'a [u8])> {
+        self.strip_wrapper(args)
+            .or_else(|| self.strip_wrapper(previous?))
+            .or_else(|| Some((strip_wrapper_command(self.prefix)?, self.suffix)))
+    }
+
+    fn write(&self, prefix: &[&[u8]], suffix: &[&[u8]], out: &mut Vec<u8>) {
+        for part in prefix {
+            out.extend_from_slice(part);
+        }
+        out.extend_from_slice(COMMAND);
+        let start = out.len();
+        for part in suffix {
+            out.extend_from_slice(part);
+        }
+        if !self.explicit && out.len() > start && out[start] != b' ' {
+            out.insert(start, b' ');
+        }
+    }
+}
+
+/// Returns what comes before Manderrow's wrapper in `prefix`, if it ends with the wrapper, like
+/// `gamemoderun ` for `gamemoderun "/opt/manderrow" wrap-with-injection `.
+fn strip_wrapper_command(prefix: &[u8]) -> Option<&[u8]> {
+    let rest = prefix.strip_suffix(b" ")?;
+    let i = rest.iter().rposition(|&b| b == b' ')?;
+    if !rest[i + 1..].starts_with(WRAPPER_COMMAND) {
+        return None;
+    }
+    let bin = &rest[..i];
+    let start = match bin.strip_suffix(b"\"") {
+        // the path is quoted, so it may contain spaces
+        Some(quoted) => {
+            let start = quoted.iter().rposition(|&b| b == b'"')?;
+            // Steam escapes quotes in localconfig.vdf
+            match start.checked_sub(1) {
+                Some(i) if quoted[i] == b'\\' => i,
+                _ => start,
+            }
+        }
+        None => bin.iter().rposition(|&b| b == b' ').map_or(0, |i| i + 1),
+    };
+    Some(&bin[..start])
+}
+
+/// Inserts Manderrow's launch options at the `%command%` in `options`, keeping the user's
+/// wrappers, environment variables, and game arguments. Returns `None` if `options` can't be
+/// merged with.
+fn merge_launch_args(options: &[u8], args: &str) -> Option<Vec<u8>> {
+    let parsed = ParsedLaunchOptions::parse(options)?;
+    // Manderrow's wrapper in a place it wouldn't have been applied to, which would be run twice
+    if parsed
+        .prefix
+        .windows(WRAPPER_COMMAND.len())
+        .any(|w| w == WRAPPER_COMMAND)
+    {
+        return None;
+    }
+    let args = ParsedLaunchOptions::parse_args(args)?;
+    let before: &[u8] = if !parsed.prefix.is_empty() && !parsed.prefix.ends_with(b" ") {
+        b" "
+    } else {
+        b""
+    };
+    // the arguments from the templates and the user's would run together
+    let joined = !args.suffix.is_empty() && !parsed.suffix.is_empty();
+    let after: &[u8] = if joined && !parsed.suffix.starts_with(b" ") {
+        b" "
+    } else {
+        b""
+    };
+    let mut merged = Vec::new();
+    parsed.write(
+        &[parsed.prefix, before, args.prefix],
+        &[args.suffix, after, parsed.suffix],
+        &mut merged,
+    );
+    Some(merged)
+}
+
+/// Removes just Manderrow's launch options from `options`, leaving everything that the user added
+/// around them. Returns `None` if they aren't in them. See [`ParsedLaunchOptions::strip_applied`].
+fn strip_launch_args(options: &[u8], args: &str, previous: Option<&str>) -> Option<Vec<u8>> {
+    let parsed = ParsedLaunchOptions::parse(options)?;
+    let (before, after) = parsed.strip_applied(args, previous)?;
+    let mut stripped = Vec::new();
+    parsed.write(&[before], &[after], &mut stripped);
+    // nothing is left but the command itself
+    if stripped == COMMAND {
+        stripped.clear();
+    }
+    Some(stripped)
+}
+
+fn edit_launch_args_inner<R: std::io::BufRead, W: std::io::Write>(
     game_id: &str,
-    overwrite_ok: bool,
-    args: &str,
+    edit: LaunchArgsEdit<'_>,
     mut rdr: vdf::Reader<R>,
     mut wtr: W,
+    changes: &mut Vec<LaunchArgsChange>,
 ) -> Result<AppliedLaunchArgs> {
     use vdf::Event;
 
+    // missing launch options are only added when applying
+    let insert = match edit {
+        LaunchArgsEdit::Apply { args, .. } => Some(args),
+        LaunchArgsEdit::Remove { .. } => None,
+    };
+
     const KEY_PATH: &[&str] = &["UserLocalConfigStore", "Software", "Valve", "Steam", "apps"];
     const LAUNCH_OPTIONS_KEY: &str = "LaunchOptions";
     enum MatcherState {
@@ -384,25 +857,66 @@ fn apply_launch_args_inner<R: std::io::BufRead, W: std::io::Write>(
                         bail!("Duplicate LaunchOptions entry")
                     }
                 }
+                let mut composed = Vec::new();
                 vdf::write_io(
                     Event::Item {
                         pre_whitespace,
                         key,
                         mid_whitespace,
-                        value: if value.s != args.as_bytes() {
-                            if !value.s.is_empty() && !overwrite_ok {
-                                bail!("Refusing to overwrite launch options.");
+                        value: match edit {
+                            LaunchArgsEdit::Apply { args, .. }
+                                if ParsedLaunchOptions::parse(value.s)
+                                    .is_some_and(|o| o.strip_wrapper(args).is_some()) =>
+                            {
+                                flag = Flag::MatchedLaunchOptions;
+                                value
                             }
-                            flag = Flag::ModifiedLaunchOptions {
-                                overwrote: !value.s.is_empty(),
-                            };
-                            vdf::Str {
-                                s: args.as_bytes(),
-                                quoted: true,
+                            LaunchArgsEdit::Apply {
+                                args,
+                                previous,
+                                overwrite_ok,
+                            } => {
+                                // replace what Manderrow applied before, with other templates
+                                let stripped = strip_launch_args(value.s, args, previous);
+                                match merge_launch_args(
+                                    stripped.as_deref().unwrap_or(value.s),
+                                    args,
+                                ) {
+                                    Some(merged) => {
+                                        composed = merged;
+                                        flag = Flag::ModifiedLaunchOptions { overwrote: false };
+                                    }
+                                    None => {
+                                        if !overwrite_ok {
+                                            bail!("Refusing to overwrite launch options.");
+                                        }
+                                        composed.extend_from_slice(args.as_bytes());
+                                        flag = Flag::ModifiedLaunchOptions { overwrote: true };
+                                    }
+                                }
+                                changes.push(LaunchArgsChange::new(value.s, &composed));
+                                vdf::Str {
+                                    s: &composed,
+                                    quoted: true,
+                                }
+                            }
+                            LaunchArgsEdit::Remove { args, previous } => {
+                                match strip_launch_args(value.s, args, previous) {
+                                    Some(stripped) => {
+                                        composed = stripped;
+                                        flag = Flag::ModifiedLaunchOptions { overwrote: false };
+                                        changes.push(LaunchArgsChange::new(value.s, &composed));
+                                        vdf::Str {
+                                            s: &composed,
+                                            quoted: true,
+                                        }
+                                    }
+                                    None => {
+                                        flag = Flag::MatchedLaunchOptions;
+                                        value
+                                    }
+                                }
                             }
-                        } else {
-                            flag = Flag::MatchedLaunchOptions;
-                            value
                         },
                     },
                     &mut wtr,
@@ -433,39 +947,42 @@ fn apply_launch_args_inner<R: std::io::BufRead, W: std::io::Write>(
                         match flag {
                             Flag::None => unreachable!(),
                             Flag::MatchedPath(_) => {
-                                flag = Flag::ModifiedLaunchOptions { overwrote: false };
-                                vdf::write_io(
-                                    Event::GroupStart {
-                                        pre_whitespace: b"\n\t\t\t\t\t",
-                                        key: vdf::Str {
-                                            s: game_id.as_bytes(),
-                                            quoted: true,
+                                if let Some(args) = insert {
+                                    flag = Flag::ModifiedLaunchOptions { overwrote: false };
+                                    changes.push(LaunchArgsChange::new(b"", args.as_bytes()));
+                                    vdf::write_io(
+                                        Event::GroupStart {
+                                            pre_whitespace: b"\n\t\t\t\t\t",
+                                            key: vdf::Str {
+                                                s: game_id.as_bytes(),
+                                                quoted: true,
+                                            },
+                                            mid_whitespace: b"\n\t\t\t\t\t",
                                         },
-                                        mid_whitespace: b"\n\t\t\t\t\t",
-                                    },
-                                    &mut wtr,
-                                )?;
-                                vdf::write_io(
-                                    Event::Item {
-                                        pre_whitespace,
-                                        key: vdf::Str {
-                                            s: LAUNCH_OPTIONS_KEY.as_bytes(),
-                                            quoted: true,
+                                        &mut wtr,
+                                    )?;
+                                    vdf::write_io(
+                                        Event::Item {
+                                            pre_whitespace,
+                                            key: vdf::Str {
+                                                s: LAUNCH_OPTIONS_KEY.as_bytes(),
+                                                quoted: true,
+                                            },
+                                            mid_whitespace: b"\t\t",
+                                            value: vdf::Str {
+                                                s: args.as_bytes(),
+                                                quoted: true,
+                                            },
                                         },
-                                        mid_whitespace: b"\t\t",
-                                        value: vdf::Str {
-                                            s: args.as_bytes(),
-                                            quoted: true,
+                                        &mut wtr,
+                                    )?;
+                                    vdf::write_io(
+                                        Event::GroupEnd {
+                                            pre_whitespace: b"\n\t\t\t\t\t",
                                         },
-                                    },
-                                    &mut wtr,
-                                )?;
-                                vdf::write_io(
-                                    Event::GroupEnd {
-                                        pre_whitespace: b"\n\t\t\t\t\t",
-                                    },
-                                    &mut wtr,
-                                )?;
+                                        &mut wtr,
+                                    )?;
+                                }
                             }
                             Flag::MatchedGame
                             | Flag::MatchedLaunchOptions
@@ -478,22 +995,25 @@ fn apply_launch_args_inner<R: std::io::BufRead, W: std::io::Write>(
                             Flag::None => unreachable!(),
                             Flag::MatchedPath(_) => unreachable!(),
                             Flag::MatchedGame => {
-                                flag = Flag::ModifiedLaunchOptions { overwrote: false };
-                                vdf::write_io(
-                                    Event::Item {
-                                        pre_whitespace,
-                                        key: vdf::Str {
-                                            s: LAUNCH_OPTIONS_KEY.as_bytes(),
-                                            quoted: true,
+                                if let Some(args) = insert {
+                                    flag = Flag::ModifiedLaunchOptions { overwrote: false };
+                                    changes.push(LaunchArgsChange::new(b"", args.as_bytes()));
+                                    vdf::write_io(
+                                        Event::Item {
+                                            pre_whitespace,
+                                            key: vdf::Str {
+                                                s: LAUNCH_OPTIONS_KEY.as_bytes(),
+                                                quoted: true,
+                                            },
+                                            mid_whitespace: b"\t\t",
+                                            value: vdf::Str {
+                                                s: args.as_bytes(),
+                                                quoted: true,
+                                            },
                                         },
-                                        mid_whitespace: b"\t\t",
-                                        value: vdf::Str {
-                                            s: args.as_bytes(),
-                                            quoted: true,
-                                        },
-                                    },
-                                    &mut wtr,
-                                )?;
+                                        &mut wtr,
+                                    )?;
+                                }
                             }
                             Flag::MatchedLaunchOptions | Flag::ModifiedLaunchOptions { .. } => {}
                         }
@@ -514,6 +1034,10 @@ fn apply_launch_args_inner<R: std::io::BufRead, W: std::io::Write>(
 
     Ok(match flag {
         Flag::None => bail!("Nothing matched"),
+        // there was nothing to remove
+        Flag::MatchedPath(_) | Flag::MatchedGame if insert.is_none() => {
+            AppliedLaunchArgs::Unchanged
+        }
         Flag::MatchedPath(i) => bail!(
             "Game options not found for game_id {game_id:?}, path matched was {:?}",
             &KEY_PATH[..=i]
@@ -526,3 +1050,301 @@ fn apply_launch_args_inner<R: std::io::BufRead, W: std::io::Write>(
         Flag::ModifiedLaunchOptions { overwrote: true } => AppliedLaunchArgs::Overwrote,
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ARGS: &str = "/opt/manderrow wrap-with-injection %command%";
+
+    fn local_config(options: &str) -> String {
+        format!(
+            r#""UserLocalConfigStore"
+{{
+	"Software"
+	{{
+		"Valve"
+		{{
+			"Steam"
+			{{
+				"apps"
+				{{
+					"1966720"
+					{{
+						"LaunchOptions"		"{options}"
+					}}
+				}}
+			}}
+		}}
+	}}
+}}
+"#
+        )
+    }
+
+    fn edit(options: &str, edit: LaunchArgsEdit<'_>) -> String {
+        let input = local_config(options);
+        let mut output = Vec::new();
+        edit_launch_args_inner(
+            "1966720",
+            edit,
+            vdf::Reader::new(std::io::Cursor::new(input.as_bytes())),
+            &mut output,
+            &mut Vec::new(),
+        )
+        .unwrap();
+        let output = String::from_utf8(output).unwrap();
+        let start = output.find("\"LaunchOptions\"\t\t\"").unwrap() + 18;
+        output[start..start + output[start..].find("\"\n").unwrap()].to_owned()
+    }
+
+    #[test]
+    fn test_edit_launch_args_with_wrappers() {
+        let apply = LaunchArgsEdit::Apply {
+            args: ARGS,
+            previous: None,
+            overwrite_ok: false,
+        };
+        let remove = LaunchArgsEdit::Remove {
+            args: ARGS,
+            previous: None,
+        };
+
+        let wrapped = format!("gamemoderun mangohud {ARGS}");
+        assert_eq!(edit("gamemoderun mangohud %command%", apply), wrapped);
+        assert_eq!(edit(&wrapped, apply), wrapped);
+        assert_eq!(edit(&wrapped, remove), "gamemoderun mangohud %command%");
+        assert_eq!(edit(ARGS, remove), "");
+        assert_eq!(edit("%command% -windowed", remove), "%command% -windowed");
+    }
+
+    #[test]
+    fn test_edit_launch_args_merge() {
+        let apply = LaunchArgsEdit::Apply {
+            args: ARGS,
+            previous: None,
+            overwrite_ok: false,
+        };
+        let remove = LaunchArgsEdit::Remove {
+            args: ARGS,
+            previous: None,
+        };
+
+        for (options, merged) in [
+            ("", ARGS.to_owned()),
+            ("-windowed -nolog", format!("{ARGS} -windowed -nolog")),
+            ("%command% -windowed", format!("{ARGS} -windowed")),
+            (
+                "PROTON_LOG=1 gamemoderun %command% -windowed",
+                format!("PROTON_LOG=1 gamemoderun {ARGS} -windowed"),
+            ),
+        ] {
+            assert_eq!(edit(options, apply), merged);
+            assert_eq!(edit(&merged, apply), merged);
+            let stripped = edit(&merged, remove);
+            // options without `%command%` come back with it written out
+            assert!(
+                stripped == options || stripped == format!("%command% {options}"),
+                "{stripped:?} != {options:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_edit_launch_args_with_templates() {
+        let args = compose_launch_args(
+            &[
+                "mangohud %command% -fps 60".to_owned(),
+                "gamemoderun".to_owned(),
+            ],
+            ARGS,
+        );
+        assert_eq!(
+            args,
+            "mangohud gamemoderun /opt/manderrow wrap-with-injection %command% -fps 60"
+        );
+        let apply = LaunchArgsEdit::Apply {
+            args: &args,
+            previous: None,
+            overwrite_ok: false,
+        };
+        let remove = LaunchArgsEdit::Remove {
+            args: &args,
+            previous: None,
+        };
+
+        for (options, merged) in [
+            ("", args.clone()),
+            ("-windowed", format!("{args} -windowed")),
+            (
+                "PROTON_LOG=1 %command% -windowed",
+                format!("PROTON_LOG=1 {args} -windowed"),
+            ),
+        ] {
+            assert_eq!(edit(options, apply), merged);
+            assert_eq!(edit(&merged, apply), merged);
+            let stripped = edit(&merged, remove);
+            assert!(
+                stripped == options || stripped == format!("%command% {options}"),
+                "{stripped:?} != {options:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_edit_launch_args_changed_templates() {
+        let old = compose_launch_args(
+            &["mangohud".to_owned()],
+            "/old/manderrow wrap-with-injection %command%",
+        );
+        let args = compose_launch_args(&["gamemoderun".to_owned()], ARGS);
+        let options = format!("PROTON_LOG=1 {old} -windowed");
+
+        // what was applied before is replaced entirely
+        let apply = LaunchArgsEdit::Apply {
+            args: &args,
+            previous: Some(&old),
+            overwrite_ok: false,
+        };
+        assert_eq!(
+            edit(&options, apply),
+            format!("PROTON_LOG=1 {args} -windowed")
+        );
+        let remove = LaunchArgsEdit::Remove {
+            args: &args,
+            previous: Some(&old),
+        };
+        assert_eq!(edit(&options, remove), "PROTON_LOG=1 %command% -windowed");
+
+        // otherwise, just Manderrow's wrapper is recognized
+        let apply = LaunchArgsEdit::Apply {
+            args: &args,
+            previous: None,
+            overwrite_ok: false,
+        };
+        assert_eq!(
+            edit(&options, apply),
+            format!("PROTON_LOG=1 mangohud {args} -windowed")
+        );
+        let remove = LaunchArgsEdit::Remove {
+            args: &args,
+            previous: None,
+        };
+        assert_eq!(
+            edit(&options, remove),
+            "PROTON_LOG=1 mangohud %command% -windowed"
+        );
+    }
+
+    #[test]
+    fn test_strip_wrapper_command() {
+        assert_eq!(
+            strip_wrapper_command(b"gamemoderun \"/opt/my apps/manderrow\" wrap-with-injection "),
+            Some(&b"gamemoderun "[..])
+        );
+        assert_eq!(
+            strip_wrapper_command(b"/opt/manderrow wrap-with-injection "),
+            Some(&b""[..])
+        );
+        assert_eq!(
+            strip_wrapper_command(br#"gamemoderun \"/opt/manderrow\" wrap-with-injection "#),
+            Some(&b"gamemoderun "[..])
+        );
+        assert_eq!(strip_wrapper_command(b"gamemoderun "), None);
+        assert_eq!(
+            strip_wrapper_command(b"/opt/manderrow wrap-with-injection mangohud "),
+            None
+        );
+    }
+
+    #[test]
+    fn test_edit_launch_args_remove() {
+        let remove = LaunchArgsEdit::Remove {
+            args: ARGS,
+            previous: None,
+        };
+        // launch options that Manderrow didn't apply are left alone
+        for options in ["", "-windowed", "gamemoderun %command% -windowed"] {
+            assert_eq!(edit(options, remove), options);
+        }
+        assert_eq!(
+            edit(&format!("gamemoderun {ARGS} -windowed"), remove),
+            "gamemoderun %command% -windowed"
+        );
+
+        let input = local_config(ARGS);
+        let mut changes = Vec::new();
+        let result = edit_launch_args_inner(
+            "1966720",
+            remove,
+            vdf::Reader::new(std::io::Cursor::new(input.as_bytes())),
+            std::io::empty(),
+            &mut changes,
+        )
+        .unwrap();
+        assert!(matches!(result, AppliedLaunchArgs::Applied));
+        assert_eq!(
+            changes,
+            [LaunchArgsChange {
+                before: ARGS.to_owned(),
+                after: String::new(),
+            }]
+        );
+
+        // nothing is added for a game without launch options
+        let mut output = Vec::new();
+        let result = edit_launch_args_inner(
+            "1966721",
+            remove,
+            vdf::Reader::new(std::io::Cursor::new(input.as_bytes())),
+            &mut output,
+            &mut Vec::new(),
+        )
+        .unwrap();
+        assert!(matches!(result, AppliedLaunchArgs::Unchanged));
+        assert!(!String::from_utf8(output).unwrap().contains("1966721"));
+    }
+
+    #[test]
+    fn test_edit_launch_args_changes() {
+        let input = local_config("gamemoderun %command% -windowed");
+        let mut changes = Vec::new();
+        let apply = LaunchArgsEdit::Apply {
+            args: ARGS,
+            previous: None,
+            overwrite_ok: false,
+        };
+        edit_launch_args_inner(
+            "1966720",
+            apply,
+            vdf::Reader::new(std::io::Cursor::new(input.as_bytes())),
+            std::io::empty(),
+            &mut changes,
+        )
+        .unwrap();
+        assert_eq!(
+            changes,
+            [LaunchArgsChange {
+                before: "gamemoderun %command% -windowed".to_owned(),
+                after: format!("gamemoderun {ARGS} -windowed"),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_edit_launch_args_unmergeable() {
+        // `%command%` twice, or Manderrow's wrapper somewhere that it would run twice
+        for options in [
+            "%command% && %command%",
+            "/old/manderrow wrap-with-injection mangohud %command%",
+        ] {
+            assert!(merge_launch_args(options.as_bytes(), ARGS).is_none());
+            let overwrite = LaunchArgsEdit::Apply {
+                args: ARGS,
+                previous: None,
+                overwrite_ok: true,
+            };
+            assert_eq!(edit(options, overwrite), ARGS);
+        }
+    }
+}
@@ -1,3 +1,8 @@
+pub mod flatpak;
 pub mod launching;
 pub mod paths;
 pub mod proton;
+pub mod runtime;
+pub mod shortcuts;
+pub mod templates;
+pub mod watchdog;
@@ -3,7 +3,16 @@ use std::path::{Path, PathBuf};
 
 use anyhow::{anyhow, bail, ensure, Result};
 use manderrow_paths::home_dir;
-use slog::warn;
+use slog::{debug, warn};
+use tokio::process::Command;
+
+use super::flatpak;
+
+/// The Flatpak app id of Steam.
+pub const FLATPAK_APP_ID: &str = "com.valvesoftware.Steam";
+
+/// Where the Flatpak sandbox mounts the host's `/usr`, if it has been given access to it.
+const FLATPAK_HOST_OS: &str = "/run/host";
 
 #[cfg(windows)]
 pub fn get_steam_install_path_from_registry() -> Result<PathBuf> {
@@ -46,7 +55,11 @@ pub async fn resolve_steam_directory() -> Result<PathBuf> {
             Err(anyhow::Error::msg(ERROR_MSG))
         }
     } else if cfg!(target_os = "linux") {
-        const PREFIXES: &[&[&str]] = &[&[], &[".var", "app", "com.valvesoftware.Steam"]];
+        const PREFIXES: &[&[&str]] = &[
+            &[],
+            &[".var", "app", FLATPAK_APP_ID],
+            &["snap", "steam", "common"],
+        ];
         const PATHS: &[&[&str]] = &[
             &[".local", "share", "Steam"],
             &[".steam", "steam"],
@@ -86,6 +99,126 @@ pub async fn resolve_steam_directory() -> Result<PathBuf> {
     }
 }
 
+/// How Steam was installed.
+///
+/// Steam from Flatpak runs in a sandbox whose home directory is stored in
+/// `~/.var/app/com.valvesoftware.Steam` on the host, so the paths that it writes to its
+/// configuration are not the paths on the host, and only the parts of the host that the user has
+/// given it access to are visible to it and the games that it runs. Steam from Snap only has its
+/// `$HOME` pointed at `~/snap/steam/common`, and sees the same paths as the host.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SteamPackaging {
+    Native,
+    Flatpak,
+    Snap,
+}
+
+fn flatpak_home(home: &Path) -> PathBuf {
+    home.join(".var").join("app").join(FLATPAK_APP_ID)
+}
+
+fn snap_home(home: &Path) -> PathBuf {
+    home.join("snap").join("steam").join("common")
+}
+
+impl SteamPackaging {
+    /// Returns the packaging of the Steam installation at `steam_dir`.
+    pub fn of(steam_dir: &Path) -> Self {
+        Self::of_in(home_dir(), steam_dir)
+    }
+
+    fn of_in(home: &Path, steam_dir: &Path) -> Self {
+        if steam_dir.starts_with(flatpak_home(home)) {
+            Self::Flatpak
+        } else if steam_dir.starts_with(snap_home(home)) {
+            Self::Snap
+        } else {
+            Self::Native
+        }
+    }
+
+    /// Returns a command that runs Steam, to which Steam's own arguments can be added.
+    pub fn command(self) -> Result<Command> {
+        match self {
+            Self::Native => Ok(Command::new(get_steam_exe()?.as_ref())),
+            Self::Flatpak => {
+                let mut command = Command::new("flatpak");
+                command.args(["run", FLATPAK_APP_ID]);
+                Ok(command)
+            }
+            Self::Snap => {
+                let mut command = Command::new("snap");
+                command.args(["run", "steam"]);
+                Ok(command)
+            }
+        }
+    }
+
+    /// Translates a path that Steam wrote to its configuration to the path on the host.
+    pub async fn sandbox_to_host(self, path: &Path) -> Result<Cow<'_, Path>> {
+        if self != Self::Flatpak {
+            return Ok(Cow::Borrowed(path));
+        }
+        let filesystems = flatpak::read_filesystem_overrides().await?;
+        Ok(tokio::task::block_in_place(|| {
+            self.sandbox_to_host_in(home_dir(), &filesystems, |path| path.exists(), path)
+        }))
+    }
+
+    /// `filesystems` are the permissions that the sandbox was given, as listed in a Flatpak
+    /// override file, and `exists` tells whether a path exists on the host.
+    fn sandbox_to_host_in<'a>(
+        self,
+        home: &Path,
+        filesystems: &str,
+        exists: impl Fn(&Path) -> bool,
+        path: &'a Path,
+    ) -> Cow<'a, Path> {
+        if self == Self::Flatpak {
+            let app_home = flatpak_home(home);
+            // the parts of the host that the sandbox was given access to are at the same paths
+            // inside of it
+            if !path.starts_with(&app_home) && !flatpak::is_exposed_by(filesystems, home, path) {
+                if let Ok(rel) = path.strip_prefix(home) {
+                    let translated = app_home.join(rel);
+                    // the permissions may have been granted some other way, e.g. with Flatseal
+                    if exists(&translated) || !exists(path) {
+                        return Cow::Owned(translated);
+                    }
+                }
+            }
+        }
+        Cow::Borrowed(path)
+    }
+
+    /// Translates a host path to the path at which Steam, and the games that it runs, can find
+    /// it. Unless the path is inside of the sandbox's home directory, the sandbox must also have
+    /// been given access to it.
+    pub fn host_to_sandbox(self, path: &Path) -> Cow<'_, Path> {
+        self.host_to_sandbox_in(home_dir(), path)
+    }
+
+    fn host_to_sandbox_in<'a>(self, home: &Path, path: &'a Path) -> Cow<'a, Path> {
+        if self == Self::Flatpak {
+            if let Ok(rel) = path.strip_prefix(flatpak_home(home)) {
+                return Cow::Owned(home.join(rel));
+            }
+            // the sandbox has its own /usr, and the host's is only mounted elsewhere
+            if path.starts_with("/usr") {
+                let mut buf = PathBuf::from(FLATPAK_HOST_OS);
+                buf.push(path.strip_prefix("/").unwrap_or(path));
+                return Cow::Owned(buf);
+            }
+        }
+        Cow::Borrowed(path)
+    }
+}
+
+/// Returns how the Steam installation that Manderrow uses was installed.
+pub async fn resolve_steam_packaging() -> Result<SteamPackaging> {
+    Ok(SteamPackaging::of(&resolve_steam_directory().await?))
+}
+
 pub async fn resolve_steamapps_directory() -> Result<PathBuf> {
     const ERROR_MSG: &str = "Could not locate steamapps directory";
     let mut buf = resolve_steam_directory().await?;
@@ -109,64 +242,118 @@ pub async fn resolve_steamapps_directory() -> Result<PathBuf> {
     Err(anyhow::Error::msg(ERROR_MSG))
 }
 
-pub async fn resolve_steam_library_folders() -> Result<Vec<PathBuf>> {
-    let steamapps_dir = resolve_steamapps_directory().await?;
-    let mut locations = vec![steamapps_dir.clone()];
-
-    let mut iter = tokio::fs::read_dir(&steamapps_dir).await?;
-    let mut path_buf = steamapps_dir;
-    while let Some(e) = iter.next_entry().await? {
-        let name = e.file_name();
-        if name.eq_ignore_ascii_case("libraryfolders.vdf") {
-            path_buf.push(name);
-            tokio::task::block_in_place(|| {
-                let mut rdr = vdf::Reader::new(std::fs::File::open(&path_buf)?);
-                let Some(vdf::Event::GroupStart { key, .. }) = rdr.next()? else {
-                    bail!("Invalid libraryfolders.vdf file: Invalid VDF file")
-                };
-                if !key.s.eq_ignore_ascii_case(b"libraryfolders") {
-                    bail!("Invalid libraryfolders.vdf file: Unexpected root key")
-                }
+/// Reads the paths of the libraries listed in a `libraryfolders.vdf` file. These are the
+/// directories that contain each library's `steamapps` directory.
+fn parse_library_folders<R: std::io::BufRead>(mut rdr: vdf::Reader<R>) -> Result<Vec<PathBuf>> {
+    let mut locations = Vec::new();
+    let Some(vdf::Event::GroupStart { key, .. }) = rdr.next()? else {
+        bail!("Invalid libraryfolders.vdf file: Invalid VDF file")
+    };
+    if !key.s.eq_ignore_ascii_case(b"libraryfolders") {
+        bail!("Invalid libraryfolders.vdf file: Unexpected root key")
+    }
+    while let Some(event) = rdr.next()? {
+        match event {
+            vdf::Event::GroupEnd { .. } => break,
+            vdf::Event::GroupStart { .. } => {
+                let mut depth = 0;
                 while let Some(event) = rdr.next()? {
                     match event {
-                        vdf::Event::GroupEnd { .. } => break,
-                        vdf::Event::GroupStart { .. } => {
-                            let mut depth = 0;
-                            while let Some(event) = rdr.next()? {
-                                match event {
-                                    vdf::Event::GroupStart { .. } => depth += 1,
-                                    vdf::Event::GroupEnd { .. } if depth == 0 => break,
-                                    vdf::Event::GroupEnd { .. } => depth -= 1,
-                                    vdf::Event::Item { key, value, .. } if key.s == b"path" => {
-                                        locations.push(value.validate_utf8()?.s.into());
-                                    }
-                                    vdf::Event::Item { .. } => {}
-                                    vdf::Event::Comment { .. } => {}
-                                    vdf::Event::FileEnd { .. } => bail!("Unexpected EOF"),
-                                }
-                            }
-                        }
-                        vdf::Event::Item { value, .. } => {
+                        vdf::Event::GroupStart { .. } => depth += 1,
+                        vdf::Event::GroupEnd { .. } if depth == 0 => break,
+                        vdf::Event::GroupEnd { .. } => depth -= 1,
+                        vdf::Event::Item { key, value, .. } if depth == 0 && key.s == b"path" => {
                             locations.push(value.validate_utf8()?.s.into());
                         }
+                        vdf::Event::Item { .. } => {}
                         vdf::Event::Comment { .. } => {}
-                        vdf::Event::FileEnd { .. } => {}
+                        vdf::Event::FileEnd { .. } => bail!("Unexpected EOF"),
                     }
                 }
-                Result::Ok(())
-            })?;
-            path_buf.pop();
+            }
+            // the old format lists libraries by number, alongside other items
+            vdf::Event::Item { key, value, .. } if key.s.iter().all(u8::is_ascii_digit) => {
+                locations.push(value.validate_utf8()?.s.into());
+            }
+            vdf::Event::Item { .. } => {}
+            vdf::Event::Comment { .. } => {}
+            vdf::Event::FileEnd { .. } => {}
         }
     }
     Ok(locations)
 }
 
+/// Returns the `steamapps` directories of every Steam library, starting with the one inside of
+/// the Steam installation. Libraries that can't be found, such as those on drives that are not
+/// mounted, are skipped.
+pub async fn resolve_all_libraries(log: &slog::Logger) -> Result<Vec<PathBuf>> {
+    let steamapps_dir = resolve_steamapps_directory().await?;
+    let packaging = SteamPackaging::of(&steamapps_dir);
+    let mut libraries = vec![steamapps_dir.clone()];
+
+    let mut listed = Vec::new();
+    let mut iter = tokio::fs::read_dir(&steamapps_dir).await?;
+    while let Some(e) = iter.next_entry().await? {
+        let name = e.file_name();
+        if name.eq_ignore_ascii_case("libraryfolders.vdf") {
+            let path = e.path();
+            let result = tokio::task::block_in_place(|| {
+                let file = std::fs::File::open(&path)?;
+                parse_library_folders(vdf::Reader::new(std::io::BufReader::new(file)))
+            });
+            match result {
+                Ok(t) => listed = t,
+                // the main library can still be searched
+                Err(e) => warn!(
+                    log,
+                    "Failed to read Steam library folders from {path:?}: {e:#}"
+                ),
+            }
+            break;
+        }
+    }
+
+    for path in listed {
+        // Flatpak's Steam lists its libraries as it sees them from inside of its sandbox
+        let mut path = packaging.sandbox_to_host(&path).await?.into_owned();
+        path.push("steamapps");
+        if libraries.contains(&path) {
+            continue;
+        }
+        if !tokio::fs::try_exists(&path).await.unwrap_or(false) {
+            debug!(
+                log,
+                "Skipping Steam library at {path:?}, which does not exist"
+            );
+            continue;
+        }
+        // the main library is usually listed too, possibly through a different path
+        if let (Ok(a), Ok(b)) = (
+            tokio::fs::canonicalize(&path).await,
+            tokio::fs::canonicalize(&steamapps_dir).await,
+        ) {
+            if a == b {
+                continue;
+            }
+        }
+        libraries.push(path);
+    }
+    Ok(libraries)
+}
+
+/// The game is not installed in any of the Steam libraries.
+#[derive(Debug, thiserror::Error)]
+#[error("Steam app {0} is not installed in any Steam library")]
+pub struct AppNotInstalledError(pub String);
+
 /// The `game_id` is Steam's numerical id for the game.
+///
+/// Fails with [`AppNotInstalledError`] if the game is not installed.
 pub async fn resolve_steam_app_manifest(log: &slog::Logger, game_id: &str) -> Result<PathBuf> {
     let target_name = format!("appmanifest_{game_id}.acf");
 
-    let library_folders = resolve_steam_library_folders().await?;
-    for (i, path) in library_folders.iter().enumerate() {
+    let libraries = resolve_all_libraries(log).await?;
+    for path in &libraries {
         let mut iter = match tokio::fs::read_dir(&path).await {
             Ok(t) => t,
             Err(e) => {
@@ -178,17 +365,16 @@ pub async fn resolve_steam_app_manifest(log: &slog::Logger, game_id: &str) -> Re
             }
         };
         while let Some(e) = iter.next_entry().await? {
-            let name = e.file_name();
-            if name.eq_ignore_ascii_case(&target_name) {
-                let mut path = library_folders.into_iter().nth(i).unwrap();
-                path.push(name);
-                return Ok(path);
+            if e.file_name().eq_ignore_ascii_case(&target_name) {
+                return Ok(e.path());
             }
         }
     }
-    Err(anyhow!(
-        "Unable to locate game app manifest for {game_id:?} in {library_folders:?}"
-    ))
+    debug!(
+        log,
+        "Unable to locate game app manifest for {game_id:?} in {libraries:?}"
+    );
+    Err(AppNotInstalledError(game_id.to_owned()).into())
 }
 
 /// The `game_id` is Steam's numerical id for the game.
@@ -247,3 +433,126 @@ pub async fn resolve_app_install_directory(log: &slog::Logger, game_id: &str) ->
         ))
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(s: &str) -> Vec<PathBuf> {
+        parse_library_folders(vdf::Reader::new(std::io::Cursor::new(s.as_bytes()))).unwrap()
+    }
+
+    #[test]
+    fn test_parse_library_folders() {
+        let current = r#"
+"libraryfolders"
+{
+	"0"
+	{
+		"path"		"/home/user/.local/share/Steam"
+		"label"		""
+		"apps"
+		{
+			"228980"		"456211153"
+		}
+	}
+	"1"
+	{
+		"path"		"/mnt/games/SteamLibrary"
+		"label"		"Games"
+	}
+}
+"#;
+        assert_eq!(
+            parse(current),
+            [
+                PathBuf::from("/home/user/.local/share/Steam"),
+                PathBuf::from("/mnt/games/SteamLibrary"),
+            ]
+        );
+
+        let old = r#"
+"LibraryFolders"
+{
+	"TimeNextStatsReport"		"1576866567"
+	"ContentStatsID"		"-4539345464745834218"
+	"1"		"/mnt/games/SteamLibrary"
+}
+"#;
+        assert_eq!(parse(old), [PathBuf::from("/mnt/games/SteamLibrary")]);
+    }
+
+    #[test]
+    fn test_packaging_paths() {
+        let home = Path::new("/home/user");
+        let flatpak = SteamPackaging::of_in(
+            home,
+            Path::new("/home/user/.var/app/com.valvesoftware.Steam/.local/share/Steam"),
+        );
+        assert_eq!(flatpak, SteamPackaging::Flatpak);
+        assert_eq!(
+            SteamPackaging::of_in(home, Path::new("/home/user/snap/steam/common/.steam/steam")),
+            SteamPackaging::Snap
+        );
+        assert_eq!(
+            SteamPackaging::of_in(home, Path::new("/home/user/.local/share/Steam")),
+            SteamPackaging::Native
+        );
+
+        let missing = |_: &Path| false;
+        assert_eq!(
+            flatpak.sandbox_to_host_in(
+                home,
+                "",
+                missing,
+                Path::new("/home/user/.local/share/Steam")
+            ),
+            Path::new("/home/user/.var/app/com.valvesoftware.Steam/.local/share/Steam")
+        );
+        assert_eq!(
+            flatpak.sandbox_to_host_in(home, "", missing, Path::new("/mnt/games/SteamLibrary")),
+            Path::new("/mnt/games/SteamLibrary")
+        );
+        assert_eq!(
+            flatpak.host_to_sandbox_in(
+                home,
+                Path::new("/home/user/.var/app/com.valvesoftware.Steam/data/agent.so")
+            ),
+            Path::new("/home/user/data/agent.so")
+        );
+        assert_eq!(
+            flatpak.host_to_sandbox_in(home, Path::new("/usr/bin/manderrow")),
+            Path::new("/run/host/usr/bin/manderrow")
+        );
+        assert_eq!(
+            SteamPackaging::Snap.host_to_sandbox_in(home, Path::new("/usr/bin/manderrow")),
+            Path::new("/usr/bin/manderrow")
+        );
+    }
+
+    #[test]
+    fn test_sandbox_to_host_exposed() {
+        let home = Path::new("/home/user");
+        let flatpak = SteamPackaging::Flatpak;
+        let library = Path::new("/home/user/Games/SteamLibrary");
+        let missing = |_: &Path| false;
+
+        assert_eq!(
+            flatpak.sandbox_to_host_in(home, "~/Games:rw;", missing, library),
+            library
+        );
+        assert_eq!(
+            flatpak.sandbox_to_host_in(home, "home;", missing, library),
+            library
+        );
+        assert_eq!(
+            flatpak.sandbox_to_host_in(home, "!home;", missing, library),
+            Path::new("/home/user/.var/app/com.valvesoftware.Steam/Games/SteamLibrary")
+        );
+        // exposed without an override that can be read
+        assert_eq!(
+            flatpak.sandbox_to_host_in(home, "", |path| path == library, library),
+            library
+        );
+    }
+}
@@ -0,0 +1,266 @@
+//! Support for the Steam Linux Runtime, which runs native Linux games inside a pressure-vessel
+//! container.
+//!
+//! Inside the container, `/usr` and friends belong to the runtime rather than the host, and only
+//! a few host directories (the home directory and Steam libraries, mainly) are shared. Anything
+//! injected into the game must therefore be somewhere the container can see.
+
+use std::borrow::Cow;
+use std::ffi::{OsStr, OsString};
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Result};
+use slog::debug;
+
+use super::paths::resolve_steam_directory;
+
+/// Environment variable listing extra host paths that pressure-vessel should share with the
+/// container, read-only.
+pub const FILESYSTEMS_RO_VAR: &str = "PRESSURE_VESSEL_FILESYSTEMS_RO";
+
+/// Where pressure-vessel mounts the host's root filesystem inside the container.
+const HOST_ROOT: &str = "/run/host";
+
+/// Host directories that the runtime replaces with its own.
+const RUNTIME_OWNED_DIRS: &[&str] = &["/usr", "/lib", "/lib32", "/lib64", "/bin", "/sbin", "/etc"];
+
+/// Whether the current process is running inside a pressure-vessel container.
+pub fn is_inside_container() -> bool {
+    std::fs::read_to_string(Path::new(HOST_ROOT).join("container-manager"))
+        .is_ok_and(|s| s.trim() == "pressure-vessel")
+}
+
+/// Whether running `command` with `args` will enter a Steam Linux Runtime container.
+pub fn command_enters_container<'a>(
+    command: &'a OsStr,
+    args: impl IntoIterator<Item = &'a OsString>,
+) -> bool {
+    std::iter::once(command)
+        .chain(args.into_iter().map(OsString::as_os_str))
+        .any(|arg| {
+            let path = Path::new(arg);
+            path.file_name()
+                .is_some_and(|name| name == "_v2-entry-point")
+                || path.components().any(|c| {
+                    c.as_os_str()
+                        .as_encoded_bytes()
+                        .starts_with(b"SteamLinuxRuntime")
+                })
+        })
+}
+
+/// Translates a host path to the path at which it can be found from inside the container.
+pub fn host_path_in_container(path: &Path) -> Cow<'_, Path> {
+    if RUNTIME_OWNED_DIRS.iter().any(|dir| path.starts_with(dir)) {
+        let mut buf = PathBuf::from(HOST_ROOT);
+        buf.push(path.strip_prefix("/").unwrap_or(path));
+        Cow::Owned(buf)
+    } else {
+        Cow::Borrowed(path)
+    }
+}
+
+/// Returns the new value of [`FILESYSTEMS_RO_VAR`] needed for `path` to be visible inside the
+/// container, given its current value.
+pub fn expose_to_container(current: Option<&OsStr>, path: &Path) -> Result<OsString> {
+    // the variable is colon separated, with no way to escape
+    if path.as_os_str().as_encoded_bytes().contains(&b':') {
+        bail!("Path {path:?} contains a colon and cannot be shared with the Steam Linux Runtime");
+    }
+    let mut buf = OsString::new();
+    if let Some(current) = current.filter(|s| !s.is_empty()) {
+        buf.push(current);
+        buf.push(":");
+    }
+    buf.push(path);
+    Ok(buf)
+}
+
+/// Returns the name of the compatibility tool that the user has selected for the game in Steam,
+/// if any. For native Linux games, this is how the Steam Linux Runtime is opted into.
+///
+/// The `game_id` is Steam's numerical id for the game.
+pub async fn compat_tool(log: &slog::Logger, game_id: &str) -> Result<Option<String>> {
+    let mut path = resolve_steam_directory().await?;
+    path.push("config");
+    path.push("config.vdf");
+
+    let tool = tokio::task::block_in_place(|| {
+        let file = match std::fs::File::open(&path) {
+            Ok(t) => t,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(anyhow::Error::from(e)),
+        };
+        find_compat_tool(vdf::Reader::new(std::io::BufReader::new(file)), game_id)
+    })?;
+    debug!(log, "Compatibility tool for {game_id:?}: {tool:?}");
+    Ok(tool)
+}
+
+fn find_compat_tool<R: std::io::BufRead>(
+    mut rdr: vdf::Reader<R>,
+    game_id: &str,
+) -> Result<Option<String>> {
+    use vdf::Event;
+
+    const KEY_PATH: &[&str] = &[
+        "InstallConfigStore",
+        "Software",
+        "Valve",
+        "Steam",
+        "CompatToolMapping",
+    ];
+
+    // the keys of the groups we are currently inside of
+    let mut stack = Vec::<Vec<u8>>::new();
+    while let Some(event) = rdr.next()? {
+        match event {
+            Event::GroupStart { key, .. } => stack.push(key.s.to_ascii_lowercase()),
+            Event::GroupEnd { .. } => {
+                stack.pop();
+            }
+            Event::Item { key, value, .. }
+                if key.s.eq_ignore_ascii_case(b"name")
+                    && stack.len() == KEY_PATH.len() + 1
+                    && stack[KEY_PATH.len()] == game_id.as_bytes()
+                    && stack
+                        .iter()
+                        .zip(KEY_PATH)
+                        .all(|(a, b)| a.eq_ignore_ascii_case(b.as_bytes())) =>
+            {
+                let name = value.validate_utf8()?.s;
+                return Ok((!name.is_empty()).then(|| name.to_string()));
+            }
+            Event::Item { .. } | Event::Comment { .. } | Event::FileEnd { .. } => {}
+        }
+    }
+    Ok(None)
+}
+
+/// Whether the compatibility tool is one of the Steam Linux Runtime containers.
+pub fn is_steam_linux_runtime(compat_tool: &str) -> bool {
+    compat_tool.starts_with("steamlinuxruntime")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = r#""InstallConfigStore"
+{
+	"Software"
+	{
+		"Valve"
+		{
+			"Steam"
+			{
+				"CompatToolMapping"
+				{
+					"0"
+					{
+						"name"		"proton_9"
+						"config"		""
+						"priority"		"75"
+					}
+					"1966720"
+					{
+						"name"		"steamlinuxruntime_sniper"
+						"config"		""
+						"priority"		"250"
+					}
+					"632360"
+					{
+						"name"		""
+						"config"		""
+						"priority"		"250"
+					}
+				}
+				"Other"
+				{
+					"427520"
+					{
+						"name"		"proton_8"
+					}
+				}
+			}
+		}
+	}
+}
+"#;
+
+    fn find(game_id: &str) -> Option<String> {
+        find_compat_tool(
+            vdf::Reader::new(std::io::Cursor::new(CONFIG.as_bytes())),
+            game_id,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_find_compat_tool() {
+        assert_eq!(find("1966720").as_deref(), Some("steamlinuxruntime_sniper"));
+        assert_eq!(find("0").as_deref(), Some("proton_9"));
+        // cleared in Steam
+        assert_eq!(find("632360"), None);
+        // not under CompatToolMapping
+        assert_eq!(find("427520"), None);
+        assert_eq!(find("1"), None);
+    }
+
+    #[test]
+    fn test_expose_to_container() {
+        assert_eq!(
+            expose_to_container(None, Path::new("/opt/manderrow")).unwrap(),
+            "/opt/manderrow"
+        );
+        assert_eq!(
+            expose_to_container(Some(OsStr::new("")), Path::new("/opt/manderrow")).unwrap(),
+            "/opt/manderrow"
+        );
+        assert_eq!(
+            expose_to_container(
+                Some(OsStr::new("/srv/a:/srv/b")),
+                Path::new("/opt/manderrow")
+            )
+            .unwrap(),
+            "/srv/a:/srv/b:/opt/manderrow"
+        );
+        assert!(expose_to_container(None, Path::new("/opt/a:b")).is_err());
+    }
+
+    #[test]
+    fn test_command_enters_container() {
+        let entry_point = OsString::from(
+            "/home/user/.steam/steam/steamapps/common/SteamLinuxRuntime_sniper/_v2-entry-point",
+        );
+        assert!(command_enters_container(
+            OsStr::new("/home/user/.steam/steam/ubuntu12_32/reaper"),
+            [&OsString::from("--"), &entry_point],
+        ));
+        assert!(command_enters_container(
+            OsStr::new("/srv/SteamLinuxRuntime_soldier/run"),
+            &[],
+        ));
+        assert!(!command_enters_container(
+            OsStr::new("/home/user/.steam/steam/ubuntu12_32/reaper"),
+            [&OsString::from("--"), &OsString::from("./game.x86_64")],
+        ));
+    }
+
+    #[test]
+    fn test_host_path_in_container() {
+        assert_eq!(
+            host_path_in_container(Path::new("/usr/lib/manderrow/agent.so")),
+            Path::new("/run/host/usr/lib/manderrow/agent.so")
+        );
+        assert_eq!(
+            host_path_in_container(Path::new("/home/user/.local/share/manderrow")),
+            Path::new("/home/user/.local/share/manderrow")
+        );
+        // only whole components count
+        assert_eq!(
+            host_path_in_container(Path::new("/usrlocal/agent.so")),
+            Path::new("/usrlocal/agent.so")
+        );
+    }
+}
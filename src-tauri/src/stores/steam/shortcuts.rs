@@ -0,0 +1,563 @@
+//! Non-Steam shortcuts to profiles in each Steam user's library, along with the grid artwork that
+//! Steam shows for them.
+//!
+//! Shortcuts are stored in `userdata/<user>/config/shortcuts.vdf`, which unlike `localconfig.vdf`
+//! is in Steam's binary VDF format. Artwork is looked up in `userdata/<user>/config/grid` by the
+//! shortcut's app id.
+
+use std::path::Path;
+
+use anyhow::{bail, ensure, Context as _, Result};
+use parking_lot::Mutex;
+use slog::{debug, error, info, warn};
+use tauri::{AppHandle, Emitter};
+use uuid::Uuid;
+
+use super::launching::{is_steam_running, SteamRunningError};
+use super::paths::resolve_steam_directory;
+use crate::installing::{fetch_resource_as_bytes, CacheOptions};
+use crate::profiles::Profile;
+use crate::util::IoErrorKindExt as _;
+
+/// The option that shortcuts pass to Manderrow to open their profile.
+pub const PROFILE_ARG: &str = "profile";
+
+/// Sent to the frontend when a shortcut is opened while Manderrow is already running.
+pub const REQUESTED_PROFILE_EVENT: &str = "requested_profile";
+
+static REQUESTED_PROFILE: Mutex<Option<Uuid>> = Mutex::new(None);
+
+pub fn set_requested_profile(id: Uuid) {
+    *REQUESTED_PROFILE.lock() = Some(id);
+}
+
+/// Returns the profile that Manderrow was last opened with through a shortcut, if it hasn't been
+/// taken yet.
+pub fn take_requested_profile() -> Option<Uuid> {
+    REQUESTED_PROFILE.lock().take()
+}
+
+/// Finds the profile passed to another instance of Manderrow by a shortcut in its `args`.
+pub fn find_requested_profile(args: &[String]) -> Option<Uuid> {
+    let flag = format!("--{PROFILE_ARG}");
+    let mut iter = args.iter().skip(1);
+    while let Some(arg) = iter.next() {
+        if *arg == flag {
+            return iter.next()?.parse().ok();
+        }
+        if let Some(value) = arg
+            .strip_prefix(&flag)
+            .and_then(|arg| arg.strip_prefix('='))
+        {
+            return value.parse().ok();
+        }
+    }
+    None
+}
+
+/// Requests that the frontend open the profile of a shortcut that was opened while Manderrow was
+/// already running.
+pub fn request_profile(app: &AppHandle, id: Uuid) {
+    set_requested_profile(id);
+    if let Err(e) = app.emit(REQUESTED_PROFILE_EVENT, id) {
+        error!(
+            slog_scope::logger(),
+            "Failed to emit {REQUESTED_PROFILE_EVENT} event: {e}"
+        );
+    }
+}
+
+const TYPE_MAP: u8 = 0x00;
+const TYPE_STRING: u8 = 0x01;
+const TYPE_INT32: u8 = 0x02;
+const TYPE_FLOAT32: u8 = 0x03;
+const TYPE_UINT64: u8 = 0x07;
+const TYPE_END: u8 = 0x08;
+
+/// A value in a binary VDF file. Keys and strings are kept as bytes so that entries written by
+/// other tools are preserved exactly.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Map(Vec<(Vec<u8>, Value)>),
+    String(Vec<u8>),
+    Int32(u32),
+    Float32(f32),
+    UInt64(u64),
+}
+
+impl Value {
+    fn get(&self, key: &str) -> Option<&Value> {
+        match self {
+            // Steam isn't consistent about the case of keys
+            Self::Map(entries) => entries
+                .iter()
+                .find(|(k, _)| k.eq_ignore_ascii_case(key.as_bytes()))
+                .map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    fn set(&mut self, key: &str, value: Value) {
+        let Self::Map(entries) = self else {
+            return;
+        };
+        match entries
+            .iter_mut()
+            .find(|(k, _)| k.eq_ignore_ascii_case(key.as_bytes()))
+        {
+            Some((_, v)) => *v = value,
+            None => entries.push((key.as_bytes().to_owned(), value)),
+        }
+    }
+}
+
+fn read_cstr<'a>(rdr: &mut &'a [u8]) -> Result<&'a [u8]> {
+    let len = rdr
+        .iter()
+        .position(|&b| b == 0)
+        .context("Unterminated string")?;
+    let s = &rdr[..len];
+    *rdr = &rdr[len + 1..];
+    Ok(s)
+}
+
+fn read_array<const N: usize>(rdr: &mut &[u8]) -> Result<[u8; N]> {
+    let (bytes, rest) = rdr
+        .split_first_chunk::<N>()
+        .context("Unexpected end of file")?;
+    *rdr = rest;
+    Ok(*bytes)
+}
+
+/// Reads the entries of a map up to and including its end marker.
+fn read_map(rdr: &mut &[u8]) -> Result<Vec<(Vec<u8>, Value)>> {
+    let mut entries = Vec::new();
+    loop {
+        let [ty] = read_array(rdr)?;
+        if ty == TYPE_END {
+            return Ok(entries);
+        }
+        let key = read_cstr(rdr)?.to_owned();
+        let value = match ty {
+            TYPE_MAP => Value::Map(read_map(rdr)?),
+            TYPE_STRING => Value::String(read_cstr(rdr)?.to_owned()),
+            TYPE_INT32 => Value::Int32(u32::from_le_bytes(read_array(rdr)?)),
+            TYPE_FLOAT32 => Value::Float32(f32::from_le_bytes(read_array(rdr)?)),
+            TYPE_UINT64 => Value::UInt64(u64::from_le_bytes(read_array(rdr)?)),
+            _ => bail!("Unsupported value type {ty:#04x}"),
+        };
+        entries.push((key, value));
+    }
+}
+
+/// Parses a binary VDF file, which is the contents of a map without a key.
+pub fn read(mut bytes: &[u8]) -> Result<Value> {
+    let map = read_map(&mut bytes)?;
+    ensure!(bytes.is_empty(), "Trailing data after end of file");
+    Ok(Value::Map(map))
+}
+
+fn write_map(buf: &mut Vec<u8>, entries: &[(Vec<u8>, Value)]) {
+    for (key, value) in entries {
+        let ty = match value {
+            Value::Map(_) => TYPE_MAP,
+            Value::String(_) => TYPE_STRING,
+            Value::Int32(_) => TYPE_INT32,
+            Value::Float32(_) => TYPE_FLOAT32,
+            Value::UInt64(_) => TYPE_UINT64,
+        };
+        buf.push(ty);
+        buf.extend_from_slice(key);
+        buf.push(0);
+        match value {
+            Value::Map(entries) => write_map(buf, entries),
+            Value::String(s) => {
+                buf.extend_from_slice(s);
+                buf.push(0);
+            }
+            Value::Int32(n) => buf.extend_from_slice(&n.to_le_bytes()),
+            Value::Float32(n) => buf.extend_from_slice(&n.to_le_bytes()),
+            Value::UInt64(n) => buf.extend_from_slice(&n.to_le_bytes()),
+        }
+    }
+    buf.push(TYPE_END);
+}
+
+/// Serializes a map as a binary VDF file. Values other than maps produce an empty file.
+pub fn write(value: &Value) -> Vec<u8> {
+    let mut buf = Vec::new();
+    if let Value::Map(entries) = value {
+        write_map(&mut buf, entries);
+    }
+    buf
+}
+
+/// Steam's app id for a non-Steam shortcut, which is used to name its artwork. This is the scheme
+/// that Steam has always used, and that tools like Steam ROM Manager rely on.
+pub fn shortcut_app_id(exe: &str, app_name: &str) -> u32 {
+    let mut hasher = crc32fast::Hasher::new();
+    hasher.update(exe.as_bytes());
+    hasher.update(app_name.as_bytes());
+    hasher.finalize() | 0x8000_0000
+}
+
+pub struct Shortcut {
+    pub app_name: String,
+    /// The quoted path of the executable.
+    pub exe: String,
+    pub start_dir: String,
+    pub launch_options: String,
+}
+
+impl Shortcut {
+    pub fn app_id(&self) -> u32 {
+        shortcut_app_id(&self.exe, &self.app_name)
+    }
+}
+
+/// Adds the shortcut to the parsed `shortcuts.vdf`, or updates the existing one with the same app
+/// id.
+pub fn upsert(shortcuts: &mut Value, shortcut: &Shortcut) -> Result<()> {
+    let app_id = shortcut.app_id();
+    let Value::Map(root) = shortcuts else {
+        bail!("Expected a map at the root");
+    };
+    let list = match root
+        .iter_mut()
+        .find(|(k, _)| k.eq_ignore_ascii_case(b"shortcuts"))
+    {
+        Some((_, list)) => list,
+        None => {
+            root.push((b"shortcuts".to_vec(), Value::Map(Vec::new())));
+            &mut root.last_mut().unwrap().1
+        }
+    };
+    let Value::Map(entries) = list else {
+        bail!("Expected a map of shortcuts");
+    };
+
+    let existing = entries.iter_mut().find(|(_, entry)| {
+        entry.get("appid") == Some(&Value::Int32(app_id))
+            || (entry.get("Exe") == Some(&Value::String(shortcut.exe.as_bytes().to_owned()))
+                && entry.get("AppName")
+                    == Some(&Value::String(shortcut.app_name.as_bytes().to_owned())))
+    });
+    let entry = match existing {
+        Some((_, entry)) => entry,
+        None => {
+            // entries are keyed by their index
+            let key = entries.len().to_string().into_bytes();
+            entries.push((key, Value::Map(Vec::new())));
+            let (_, entry) = entries.last_mut().unwrap();
+            entry.set("IsHidden", Value::Int32(0));
+            entry.set("AllowDesktopConfig", Value::Int32(1));
+            entry.set("AllowOverlay", Value::Int32(1));
+            entry.set("OpenVR", Value::Int32(0));
+            entry.set("LastPlayTime", Value::Int32(0));
+            entry.set("tags", Value::Map(Vec::new()));
+            entry
+        }
+    };
+    entry.set("appid", Value::Int32(app_id));
+    entry.set(
+        "AppName",
+        Value::String(shortcut.app_name.clone().into_bytes()),
+    );
+    entry.set("Exe", Value::String(shortcut.exe.clone().into_bytes()));
+    entry.set(
+        "StartDir",
+        Value::String(shortcut.start_dir.clone().into_bytes()),
+    );
+    entry.set(
+        "LaunchOptions",
+        Value::String(shortcut.launch_options.clone().into_bytes()),
+    );
+    Ok(())
+}
+
+/// A kind of artwork that Steam shows in the library.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Artwork {
+    /// The capsule shown in the grid view.
+    Portrait,
+    /// The capsule shown in recently played games.
+    Wide,
+    /// The banner at the top of the game's page.
+    Hero,
+    /// The logo drawn over the hero.
+    Logo,
+}
+
+impl Artwork {
+    pub const ALL: [Self; 4] = [Self::Portrait, Self::Wide, Self::Hero, Self::Logo];
+
+    /// The name of the artwork in the store's CDN, from which the extension is also taken.
+    fn cdn_file_name(self) -> &'static str {
+        match self {
+            Self::Portrait => "library_600x900.jpg",
+            Self::Wide => "header.jpg",
+            Self::Hero => "library_hero.jpg",
+            Self::Logo => "logo.png",
+        }
+    }
+
+    fn extension(self) -> &'static str {
+        let name = self.cdn_file_name();
+        &name[name.rfind('.').unwrap() + 1..]
+    }
+
+    /// The name of the file in the grid folder without an extension.
+    pub fn grid_stem(self, app_id: u32) -> String {
+        match self {
+            Self::Portrait => format!("{app_id}p"),
+            Self::Wide => format!("{app_id}"),
+            Self::Hero => format!("{app_id}_hero"),
+            Self::Logo => format!("{app_id}_logo"),
+        }
+    }
+
+    fn url(self, steam_app_id: &str) -> String {
+        format!(
+            "https://cdn.cloudflare.steamstatic.com/steam/apps/{steam_app_id}/{}",
+            self.cdn_file_name()
+        )
+    }
+}
+
+/// Writes the artwork into the grid folder, removing any artwork for the same shortcut in the
+/// other format, which Steam would otherwise choose between arbitrarily.
+async fn install_artwork(grid: &Path, app_id: u32, artwork: Artwork, bytes: &[u8]) -> Result<()> {
+    let stem = artwork.grid_stem(app_id);
+    for ext in ["png", "jpg"] {
+        if ext == artwork.extension() {
+            continue;
+        }
+        let path = grid.join(format!("{stem}.{ext}"));
+        match tokio::fs::remove_file(&path).await {
+            Ok(()) => {}
+            Err(e) if e.is_not_found() => {}
+            Err(e) => return Err(e).with_context(|| format!("Failed to delete {path:?}")),
+        }
+    }
+    let path = grid.join(format!("{stem}.{}", artwork.extension()));
+    tokio::fs::write(&path, bytes)
+        .await
+        .with_context(|| format!("Failed to write {path:?}"))
+}
+
+/// Adds a shortcut that opens the profile in Manderrow to the library of every Steam user, with
+/// the game's own artwork so that it looks like the game in the library.
+///
+/// Returns the app id of the shortcut. Fails with [`SteamRunningError`] if Steam is running,
+/// because Steam overwrites `shortcuts.vdf` when it exits.
+pub async fn create_profile_shortcut(
+    app: Option<&AppHandle>,
+    log: &slog::Logger,
+    id: Uuid,
+    profile: &Profile,
+) -> Result<u32> {
+    if is_steam_running(log).await? {
+        return Err(SteamRunningError.into());
+    }
+
+    let game = crate::games::games_by_id()?
+        .get(&*profile.game)
+        .with_context(|| format!("Unknown game {:?}", profile.game))?;
+
+    // an AppImage's executable is only valid while it is mounted
+    let exe = match std::env::var_os("APPIMAGE") {
+        Some(path) => path.into(),
+        None => std::env::current_exe().context("Failed to get current exe path")?,
+    };
+    let shortcut = Shortcut {
+        app_name: format!("{} ({})", game.name, profile.name),
+        exe: format!("\"{}\"", exe.display()),
+        start_dir: format!(
+            "\"{}\"",
+            exe.parent().context("exe must have a parent")?.display()
+        ),
+        launch_options: format!("--{PROFILE_ARG} {id}"),
+    };
+    let app_id = shortcut.app_id();
+
+    let mut artwork = Vec::new();
+    match game
+        .store_platform_metadata
+        .iter()
+        .find_map(|m| m.steam_or_direct())
+    {
+        Some(steam) => {
+            for kind in Artwork::ALL {
+                match fetch_resource_as_bytes(
+                    app,
+                    log,
+                    format!("Steam artwork of {}", game.name),
+                    &kind.url(steam.id),
+                    Some(CacheOptions::by_url()),
+                    None,
+                )
+                .await
+                {
+                    Ok(bytes) => artwork.push((kind, bytes)),
+                    // not every game has every kind of artwork
+                    Err(e) => warn!(
+                        log,
+                        "Failed to fetch {kind:?} artwork of {}: {e:#}", game.id
+                    ),
+                }
+            }
+        }
+        None => debug!(log, "{} is not on Steam, so it has no artwork", game.id),
+    }
+
+    let mut path = resolve_steam_directory().await?;
+    path.push("userdata");
+
+    let mut added = false;
+    let mut error = None;
+    let mut iter = tokio::fs::read_dir(&path).await?;
+    while let Some(e) = iter.next_entry().await? {
+        let config = e.path().join("config");
+        if tokio::fs::metadata(&config).await.is_err() {
+            continue;
+        }
+        // one user's broken shortcuts shouldn't keep the others from getting the shortcut
+        match add_shortcut(&config, &shortcut, app_id, &artwork).await {
+            Ok(()) => {
+                info!(
+                    log,
+                    "Added shortcut {app_id} for profile {id} to {config:?}"
+                );
+                added = true;
+            }
+            Err(e) => {
+                warn!(log, "Failed to add shortcut {app_id} to {config:?}: {e:#}");
+                error.get_or_insert(e);
+            }
+        }
+    }
+
+    match error {
+        Some(e) if !added => Err(e),
+        _ => Ok(app_id),
+    }
+}
+
+/// Adds `shortcut` and its artwork to the Steam user's `config` folder.
+async fn add_shortcut(
+    config: &Path,
+    shortcut: &Shortcut,
+    app_id: u32,
+    artwork: &[(Artwork, bytes::BytesMut)],
+) -> Result<()> {
+    let path = config.join("shortcuts.vdf");
+    let mut shortcuts = match tokio::fs::read(&path).await {
+        Ok(bytes) => read(&bytes).with_context(|| format!("Failed to parse {path:?}"))?,
+        Err(e) if e.is_not_found() => Value::Map(Vec::new()),
+        Err(e) => return Err(e).with_context(|| format!("Failed to read {path:?}")),
+    };
+    upsert(&mut shortcuts, shortcut)?;
+    let bytes = write(&shortcuts);
+    tokio::task::block_in_place(|| {
+        let mut dst = tempfile::NamedTempFile::new_in(config)?;
+        std::io::Write::write_all(&mut dst, &bytes)?;
+        dst.persist(&path)?;
+        Ok::<_, anyhow::Error>(())
+    })
+    .with_context(|| format!("Failed to write {path:?}"))?;
+
+    let grid = config.join("grid");
+    tokio::fs::create_dir_all(&grid)
+        .await
+        .with_context(|| format!("Failed to create {grid:?}"))?;
+    for (kind, bytes) in artwork {
+        install_artwork(&grid, app_id, *kind, bytes).await?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shortcut_app_id() {
+        // the standard CRC-32 check value, which already has the high bit set
+        assert_eq!(shortcut_app_id("12345", "6789"), 0xcbf43926);
+        assert_eq!(shortcut_app_id("", ""), 0x8000_0000);
+    }
+
+    #[test]
+    fn test_find_requested_profile() {
+        let id = "01234567-89ab-cdef-0123-456789abcdef";
+        let args = |args: &[&str]| args.iter().map(|&s| s.to_owned()).collect::<Vec<_>>();
+        assert_eq!(
+            find_requested_profile(&args(&["manderrow", "--profile", id])),
+            Some(id.parse().unwrap())
+        );
+        assert_eq!(
+            find_requested_profile(&args(&["manderrow", &format!("--profile={id}")])),
+            Some(id.parse().unwrap())
+        );
+        assert_eq!(find_requested_profile(&args(&["manderrow"])), None);
+        assert_eq!(
+            find_requested_profile(&args(&["manderrow", "--profile"])),
+            None
+        );
+        assert_eq!(
+            find_requested_profile(&args(&["manderrow", "--profile", "nonsense"])),
+            None
+        );
+        // the executable's own path isn't an argument
+        assert_eq!(find_requested_profile(&args(&["--profile", id])), None);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(b"\x00shortcuts\x00");
+        bytes.extend_from_slice(b"\x000\x00");
+        bytes.extend_from_slice(b"\x02appid\x00\x01\x02\x03\x84");
+        bytes.extend_from_slice(b"\x01AppName\x00Other\x00");
+        bytes.extend_from_slice(b"\x07LastPlayed\x00\x01\x00\x00\x00\x00\x00\x00\x00");
+        bytes.extend_from_slice(b"\x00tags\x00\x010\x00Favorite\x00\x08");
+        bytes.extend_from_slice(b"\x08");
+        bytes.extend_from_slice(b"\x08\x08");
+        let value = read(&bytes).unwrap();
+        assert_eq!(write(&value), bytes);
+        assert!(read(&bytes[..bytes.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn test_upsert() {
+        let shortcut = Shortcut {
+            app_name: "Game (Profile)".into(),
+            exe: "\"/usr/bin/manderrow\"".into(),
+            start_dir: "\"/usr/bin\"".into(),
+            launch_options: "--profile 00000000-0000-0000-0000-000000000000".into(),
+        };
+        let mut shortcuts = Value::Map(Vec::new());
+        upsert(&mut shortcuts, &shortcut).unwrap();
+        upsert(&mut shortcuts, &shortcut).unwrap();
+        let list = shortcuts.get("shortcuts").unwrap();
+        let Value::Map(entries) = list else {
+            panic!("expected a map");
+        };
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].0, b"0");
+        assert_eq!(
+            entries[0].1.get("appid"),
+            Some(&Value::Int32(shortcut.app_id()))
+        );
+        assert_eq!(read(&write(&shortcuts)).unwrap(), shortcuts);
+    }
+
+    #[test]
+    fn test_grid_stem() {
+        assert_eq!(Artwork::Portrait.grid_stem(1), "1p");
+        assert_eq!(Artwork::Wide.grid_stem(1), "1");
+        assert_eq!(Artwork::Hero.grid_stem(1), "1_hero");
+        assert_eq!(Artwork::Logo.grid_stem(1), "1_logo");
+        assert_eq!(Artwork::Logo.extension(), "png");
+    }
+}
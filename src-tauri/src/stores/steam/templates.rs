@@ -0,0 +1,108 @@
+//! Templates for the launch options that Steam is given, like `mangohud %command%`, which are
+//! added around Manderrow's own launch options for the games that they are selected for.
+//!
+//! Unlike [`crate::launching::wrappers`], they also apply when the game is launched from Steam
+//! without Manderrow, but changing them means changing the launch options in Steam again.
+
+use anyhow::{bail, Context as _, Result};
+use parking_lot::Mutex;
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub struct LaunchOptionsTemplate {
+    pub name: String,
+    /// Written like Steam's launch options, with `%command%` where the game's command goes.
+    /// Without one, the options are a command to run the game through, like `gamemoderun`.
+    pub options: String,
+    /// The ids of the games that the template is selected for.
+    pub games: Vec<String>,
+}
+
+static TEMPLATES: Mutex<Vec<LaunchOptionsTemplate>> = Mutex::new(Vec::new());
+
+pub fn set(templates: &[LaunchOptionsTemplate]) {
+    *TEMPLATES.lock() = templates.to_owned();
+}
+
+/// Parses templates that were exported as JSON, like they are stored in the settings. Fails if
+/// any of them has more than one `%command%`, since it wouldn't be clear where Manderrow's launch
+/// options go.
+pub fn parse_exported(json: &str) -> Result<Vec<LaunchOptionsTemplate>> {
+    let templates = serde_json::from_str::<Vec<LaunchOptionsTemplate>>(json)
+        .context("Not launch options templates")?;
+    for template in &templates {
+        if template.options.matches("%command%").count() > 1 {
+            bail!(
+                "Launch options template {:?} has more than one %command%",
+                template.name
+            );
+        }
+    }
+    Ok(templates)
+}
+
+/// Returns the options of the templates selected for the game with the Steam app id `steam_id`,
+/// in the order that they are configured in.
+pub fn for_steam_game(steam_id: &str) -> Vec<String> {
+    let templates = TEMPLATES.lock();
+    if templates.is_empty() {
+        return Vec::new();
+    }
+    let games = match crate::games::games_by_id() {
+        Ok(t) => t,
+        Err(e) => {
+            slog_scope::warn!("Failed to load games, ignoring launch options templates: {e}");
+            return Vec::new();
+        }
+    };
+    let is_selected = |game: &str| {
+        games.get(game).is_some_and(|game| {
+            game.store_platform_metadata
+                .iter()
+                .filter_map(|m| m.steam_or_direct())
+                .any(|m| m.id == steam_id)
+        })
+    };
+    templates
+        .iter()
+        .filter(|template| template.games.iter().any(|game| is_selected(game)))
+        .filter(|template| {
+            // it wouldn't be clear where Manderrow's launch options go
+            let valid = template.options.matches("%command%").count() <= 1;
+            if !valid {
+                slog_scope::warn!(
+                    "Ignoring launch options template {:?} with more than one %command%",
+                    template.name
+                );
+            }
+            valid
+        })
+        .map(|template| template.options.trim().to_owned())
+        .filter(|options| !options.is_empty())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_exported, LaunchOptionsTemplate};
+
+    #[test]
+    fn test_parse_exported() {
+        let templates = parse_exported(
+            r#"[{ "name": "HUD", "options": "mangohud %command%", "games": ["lethal-company"] }]"#,
+        )
+        .unwrap();
+        assert_eq!(
+            templates,
+            [LaunchOptionsTemplate {
+                name: "HUD".to_owned(),
+                options: "mangohud %command%".to_owned(),
+                games: vec!["lethal-company".to_owned()],
+            }]
+        );
+        assert!(parse_exported(r#"[{ "name": "HUD" }]"#).is_err());
+        assert!(parse_exported(
+            r#"[{ "name": "Twice", "options": "%command% && %command%", "games": [] }]"#
+        )
+        .is_err());
+    }
+}
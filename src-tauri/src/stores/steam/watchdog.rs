@@ -0,0 +1,161 @@
+//! Keeps the launch options that Manderrow needs applied to Steam games.
+//!
+//! Steam Cloud occasionally syncs an older `localconfig.vdf` back down, reverting the launch
+//! options. Once the user has agreed to apply them for a game, they are re-applied without asking
+//! again, both before launching and in the background whenever Steam is not running.
+
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use anyhow::{Context as _, Result};
+use slog::{debug, warn};
+
+use crate::profiles::configs::write_atomically;
+use crate::util::IoErrorKindExt as _;
+use crate::wrap::WrapperMode;
+
+use super::launching::{
+    generate_launch_options, is_steam_running, reapply_launch_args, SteamRunningError,
+};
+
+/// How often to check whether Steam has exited.
+const POLL_INTERVAL: Duration = Duration::from_secs(30);
+/// How often to check the launch options while Steam is not running.
+const VERIFY_INTERVAL: Duration = Duration::from_secs(10 * 60);
+
+/// Serializes changes to the consents, so that concurrent changes aren't lost and the watchdog
+/// doesn't re-apply launch options while their consent is being forgotten.
+static LOCK: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]
+pub struct Consent {
+    /// Whether the user agreed to replace launch options that were already set.
+    pub overwrite: bool,
+    /// The launch options that were last applied, so that they are still recognized once the
+    /// templates selected for the game change.
+    #[serde(default)]
+    pub args: Option<String>,
+}
+
+/// Consents, by Steam app id.
+type Consents = BTreeMap<String, Consent>;
+
+fn consents_path() -> PathBuf {
+    manderrow_paths::local_data_dir().join("steam_launch_options.json")
+}
+
+async fn read_consents() -> Result<Consents> {
+    let path = consents_path();
+    let mut bytes = match tokio::fs::read(&path).await {
+        Ok(t) => t,
+        Err(e) if e.is_not_found() => return Ok(Consents::new()),
+        Err(e) => return Err(e).with_context(|| format!("Failed to read {path:?}")),
+    };
+    simd_json::from_slice(&mut bytes).with_context(|| format!("Failed to parse {path:?}"))
+}
+
+/// Returns the user's consent to apply launch options to the game, if they have given it.
+pub async fn consent(game_id: &str) -> Result<Option<Consent>> {
+    Ok(read_consents().await?.remove(game_id))
+}
+
+async fn write_consents(consents: &Consents) -> Result<()> {
+    let path = consents_path();
+    let bytes = simd_json::to_vec(consents)?;
+    tokio::task::block_in_place(|| write_atomically(&path, &bytes))
+}
+
+/// Remembers that the user agreed to apply launch options to the game, and that `args` were
+/// applied.
+pub async fn remember_consent(game_id: &str, overwrite: bool, args: &str) -> Result<()> {
+    let _guard = LOCK.lock().await;
+    let mut consents = read_consents().await?;
+    let consent = consents.entry(game_id.to_owned()).or_insert(Consent {
+        overwrite,
+        args: None,
+    });
+    consent.overwrite |= overwrite;
+    consent.args = Some(args.to_owned());
+    write_consents(&consents).await
+}
+
+/// Forgets that the user agreed to apply launch options to the game, so that they are no longer
+/// re-applied.
+pub async fn forget_consent(game_id: &str) -> Result<()> {
+    let _guard = LOCK.lock().await;
+    let mut consents = read_consents().await?;
+    if consents.remove(game_id).is_some() {
+        write_consents(&consents).await?;
+    }
+    Ok(())
+}
+
+/// Periodically checks that the launch options the user agreed to are still applied, re-applying
+/// them if they were reverted.
+pub fn spawn() {
+    if cfg!(windows) {
+        // launch options are only used on other platforms
+        return;
+    }
+    tauri::async_runtime::spawn(async move {
+        let log = slog_scope::logger();
+        let mut was_running = false;
+        let mut last_verified = None::<Instant>;
+        loop {
+            tokio::time::sleep(POLL_INTERVAL).await;
+            if let Err(e) = check(&log, &mut was_running, &mut last_verified).await {
+                warn!(log, "Failed to check Steam launch options: {e:#}");
+            }
+        }
+    });
+}
+
+async fn check(
+    log: &slog::Logger,
+    was_running: &mut bool,
+    last_verified: &mut Option<Instant>,
+) -> Result<()> {
+    let _guard = LOCK.lock().await;
+    let mut consents = read_consents().await?;
+    if consents.is_empty() {
+        return Ok(());
+    }
+
+    let running = is_steam_running(log).await?;
+    let exited = std::mem::replace(was_running, running) && !running;
+    // Steam rewrites localconfig.vdf when it exits, so changes made while it runs would be lost
+    if running {
+        return Ok(());
+    }
+    if !exited && last_verified.is_some_and(|t| t.elapsed() < VERIFY_INTERVAL) {
+        return Ok(());
+    }
+    *last_verified = Some(Instant::now());
+
+    let mut changed = false;
+    for (game_id, consent) in &mut consents {
+        let args = generate_launch_options(game_id, WrapperMode::Injection).await?;
+        match reapply_launch_args(log, game_id, &args, consent).await {
+            Ok(true) => {
+                consent.args = Some(args);
+                changed = true;
+            }
+            Ok(false) => {}
+            Err(e) if e.is::<SteamRunningError>() => {
+                // Steam was opened in the meantime, so try again once it exits
+                debug!(log, "Steam is running, not re-applying launch options yet");
+                *was_running = true;
+                break;
+            }
+            Err(e) => warn!(
+                log,
+                "Failed to re-apply launch options for {game_id}: {e:#}"
+            ),
+        }
+    }
+    if changed {
+        write_consents(&consents).await?;
+    }
+    Ok(())
+}
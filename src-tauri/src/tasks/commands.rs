@@ -2,6 +2,7 @@ use anyhow::Context;
 
 use crate::CommandError;
 
+use super::history::HistoryEntry;
 use super::Id;
 
 #[tauri::command]
@@ -23,3 +24,8 @@ pub async fn cancel_task(id: Id) -> Result<(), CommandError> {
     }
     Ok(())
 }
+
+#[tauri::command]
+pub async fn get_task_history() -> Result<Vec<HistoryEntry>, CommandError> {
+    Ok(super::history::get())
+}
@@ -0,0 +1,181 @@
+//! Rolls the progress of dependencies up into their [aggregate](Kind::Aggregate) parent tasks.
+//!
+//! When every dependency reports progress in the parent's [unit](ProgressUnit), their progress is
+//! summed, so larger dependencies (e.g. bigger downloads) weigh more. Otherwise, the units can't
+//! be compared and each dependency contributes an equal share based on its completed fraction.
+
+use std::collections::HashMap;
+use std::sync::LazyLock;
+
+use parking_lot::Mutex;
+use tauri::AppHandle;
+
+use super::{Id, Kind, Progress, ProgressUnit, TaskProgress};
+
+/// The share of an aggregate's total progress given to each dependency when the units of the
+/// dependencies don't all match the aggregate's.
+const SHARE: u64 = 1000;
+
+#[derive(Default)]
+struct Child {
+    /// `None` until the dependency has been created.
+    unit: Option<ProgressUnit>,
+    completed: u64,
+    total: u64,
+    finished: bool,
+}
+
+struct Aggregate {
+    unit: ProgressUnit,
+    children: HashMap<Id, Child>,
+}
+
+#[derive(Default)]
+struct Hierarchy {
+    aggregates: HashMap<Id, Aggregate>,
+    /// Maps each dependency to its aggregate parent.
+    parents: HashMap<Id, Id>,
+}
+
+static HIERARCHY: LazyLock<Mutex<Hierarchy>> = LazyLock::new(Default::default);
+
+impl Aggregate {
+    /// Dependencies that were allocated but never created are left out, as they may never run.
+    fn progress(&self) -> Progress {
+        let started = || self.children.values().filter(|c| c.unit.is_some());
+        let same_unit = started().all(|c| c.unit == Some(self.unit));
+        let (mut completed, mut total) = (0, 0);
+        for child in started() {
+            if same_unit {
+                completed += if child.finished {
+                    child.total
+                } else {
+                    child.completed
+                };
+                total += child.total;
+            } else {
+                completed += if child.finished {
+                    SHARE
+                } else if child.total == 0 {
+                    0
+                } else {
+                    (child.completed.min(child.total) as u128 * SHARE as u128 / child.total as u128)
+                        as u64
+                };
+                total += SHARE;
+            }
+        }
+        Progress { completed, total }
+    }
+}
+
+/// Updates the aggregate parent of `child`, if it has one, and reports its new progress.
+fn update(app: &AppHandle, child: Id, f: impl FnOnce(&mut Child)) -> tauri::Result<()> {
+    let (parent, progress) = {
+        let mut hierarchy = HIERARCHY.lock();
+        let Some(&parent) = hierarchy.parents.get(&child) else {
+            return Ok(());
+        };
+        let Some(aggregate) = hierarchy.aggregates.get_mut(&parent) else {
+            return Ok(());
+        };
+        f(aggregate.children.entry(child).or_default());
+        (parent, aggregate.progress())
+    };
+    parent.emit(app, TaskProgress { progress })
+}
+
+pub(super) fn created(id: Id, kind: &Kind, unit: ProgressUnit) {
+    let mut hierarchy = HIERARCHY.lock();
+    if let Kind::Aggregate = kind {
+        hierarchy.aggregates.insert(
+            id,
+            Aggregate {
+                unit,
+                children: HashMap::new(),
+            },
+        );
+    }
+    if let Some(&parent) = hierarchy.parents.get(&id) {
+        if let Some(aggregate) = hierarchy.aggregates.get_mut(&parent) {
+            aggregate.children.entry(id).or_default().unit = Some(unit);
+        }
+    }
+}
+
+pub(super) fn add_dependency(parent: Id, dependency: Id) {
+    let mut hierarchy = HIERARCHY.lock();
+    if let Some(aggregate) = hierarchy.aggregates.get_mut(&parent) {
+        aggregate.children.entry(dependency).or_default();
+        hierarchy.parents.insert(dependency, parent);
+    }
+}
+
+pub(super) fn progress(app: &AppHandle, id: Id, completed: u64, total: u64) -> tauri::Result<()> {
+    update(app, id, |child| {
+        child.completed = completed;
+        child.total = total;
+    })
+}
+
+pub(super) fn dropped(app: &AppHandle, id: Id) -> tauri::Result<()> {
+    let r = update(app, id, |child| child.finished = true);
+    let mut hierarchy = HIERARCHY.lock();
+    hierarchy.parents.remove(&id);
+    if let Some(aggregate) = hierarchy.aggregates.remove(&id) {
+        for child in aggregate.children.keys() {
+            hierarchy.parents.remove(child);
+        }
+    }
+    r
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Aggregate, Child, Id, ProgressUnit, SHARE};
+
+    fn aggregate(children: impl IntoIterator<Item = Child>) -> Aggregate {
+        Aggregate {
+            unit: ProgressUnit::Bytes,
+            children: children
+                .into_iter()
+                .map(|c| (Id(uuid::Uuid::new_v4()), c))
+                .collect(),
+        }
+    }
+
+    fn child(unit: ProgressUnit, completed: u64, total: u64, finished: bool) -> Child {
+        Child {
+            unit: Some(unit),
+            completed,
+            total,
+            finished,
+        }
+    }
+
+    #[test]
+    fn test_never_started_children_are_ignored() {
+        let p = aggregate([child(ProgressUnit::Bytes, 0, 100, true), Child::default()]).progress();
+        assert_eq!((p.completed, p.total), (100, 100));
+    }
+
+    #[test]
+    fn test_matching_units_are_summed() {
+        let p = aggregate([
+            child(ProgressUnit::Bytes, 10, 100, false),
+            child(ProgressUnit::Bytes, 0, 300, true),
+        ])
+        .progress();
+        assert_eq!((p.completed, p.total), (310, 400));
+    }
+
+    #[test]
+    fn test_mismatched_units_get_equal_shares() {
+        let p = aggregate([
+            child(ProgressUnit::Bytes, 50, 100, false),
+            child(ProgressUnit::Other, 1, 4, false),
+        ])
+        .progress();
+        assert_eq!((p.completed, p.total), (SHARE / 2 + SHARE / 4, 2 * SHARE));
+    }
+}
@@ -0,0 +1,150 @@
+//! A bounded, persistent record of finished tasks.
+
+use std::collections::VecDeque;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::LazyLock;
+use std::time::{Duration, SystemTime};
+
+use anyhow::Result;
+use manderrow_paths::local_data_dir;
+use parking_lot::Mutex;
+
+use crate::profiles::configs::write_atomically;
+use crate::util::{unix_millis, IoErrorKindExt as _};
+
+use super::{DropStatus, Id, Metadata};
+
+/// The number of entries kept. Older entries are discarded.
+const MAX_ENTRIES: usize = 500;
+
+/// Tasks tend to finish in bursts, so writes are delayed by this long to batch them together.
+const WRITE_DELAY: Duration = Duration::from_secs(2);
+
+static PATH: LazyLock<PathBuf> = LazyLock::new(|| local_data_dir().join("task_history.json"));
+
+/// Entries, oldest first.
+static HISTORY: LazyLock<Mutex<VecDeque<HistoryEntry>>> = LazyLock::new(|| {
+    Mutex::new(match read() {
+        Ok(t) => t,
+        Err(e) => {
+            slog_scope::error!("Failed to read task history from {:?}: {e}", *PATH);
+            VecDeque::new()
+        }
+    })
+});
+
+static WRITE_SCHEDULED: AtomicBool = AtomicBool::new(false);
+
+#[derive(Clone, serde::Deserialize, serde::Serialize)]
+pub struct HistoryEntry {
+    pub id: Id,
+    pub metadata: Metadata,
+    /// Milliseconds since the Unix epoch.
+    pub started_at: u64,
+    pub duration_ms: u64,
+    pub status: DropStatus,
+}
+
+fn read() -> Result<VecDeque<HistoryEntry>> {
+    let mut bytes = match std::fs::read(&*PATH) {
+        Ok(t) => t,
+        Err(e) if e.is_not_found() => return Ok(VecDeque::new()),
+        Err(e) => return Err(e.into()),
+    };
+    Ok(simd_json::from_slice(&mut bytes)?)
+}
+
+fn write() -> Result<()> {
+    let history = HISTORY.lock().clone();
+    write_atomically(&PATH, &simd_json::to_vec(&history)?)
+}
+
+/// Appends `entry` to `history`, discarding the oldest entries beyond [`MAX_ENTRIES`].
+fn push(history: &mut VecDeque<HistoryEntry>, entry: HistoryEntry) {
+    while history.len() >= MAX_ENTRIES {
+        history.pop_front();
+    }
+    history.push_back(entry);
+}
+
+fn newest_first(history: &VecDeque<HistoryEntry>) -> Vec<HistoryEntry> {
+    history.iter().rev().cloned().collect()
+}
+
+pub(super) fn record(id: Id, metadata: Metadata, started_at: SystemTime, status: DropStatus) {
+    let entry = HistoryEntry {
+        id,
+        metadata,
+        started_at: unix_millis(started_at),
+        duration_ms: started_at
+            .elapsed()
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or(0),
+        status,
+    };
+    push(&mut HISTORY.lock(), entry);
+
+    if !WRITE_SCHEDULED.swap(true, Ordering::AcqRel) {
+        tauri::async_runtime::spawn(async {
+            tokio::time::sleep(WRITE_DELAY).await;
+            WRITE_SCHEDULED.store(false, Ordering::Release);
+            match tauri::async_runtime::spawn_blocking(write).await {
+                Ok(Ok(())) => {}
+                Ok(Err(e)) => slog_scope::error!("Failed to write task history: {e}"),
+                Err(e) => slog_scope::error!("Failed to write task history: {e}"),
+            }
+        });
+    }
+}
+
+/// Returns the recorded entries, newest first.
+pub fn get() -> Vec<HistoryEntry> {
+    newest_first(&HISTORY.lock())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{Kind, ProgressUnit};
+    use super::*;
+
+    fn entry(started_at: u64) -> HistoryEntry {
+        HistoryEntry {
+            id: Id(uuid::Uuid::new_v4()),
+            metadata: Metadata {
+                title: "Test".into(),
+                kind: Kind::Other,
+                progress_unit: ProgressUnit::Other,
+            },
+            started_at,
+            duration_ms: 0,
+            status: DropStatus::Success { success: None },
+        }
+    }
+
+    #[test]
+    fn test_push_trims_to_max_entries() {
+        let mut history = VecDeque::new();
+        for i in 0..MAX_ENTRIES as u64 + 10 {
+            push(&mut history, entry(i));
+        }
+        assert_eq!(history.len(), MAX_ENTRIES);
+        assert_eq!(history.front().unwrap().started_at, 10);
+        assert_eq!(history.back().unwrap().started_at, MAX_ENTRIES as u64 + 9);
+    }
+
+    #[test]
+    fn test_newest_first() {
+        let mut history = VecDeque::new();
+        for i in 0..3 {
+            push(&mut history, entry(i));
+        }
+        assert_eq!(
+            newest_first(&history)
+                .iter()
+                .map(|e| e.started_at)
+                .collect::<Vec<_>>(),
+            [2, 1, 0]
+        );
+    }
+}
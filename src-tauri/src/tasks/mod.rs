@@ -1,15 +1,13 @@
 //! Task management and monitoring.
 
 pub mod commands;
+mod hierarchy;
+pub mod history;
 pub mod types;
 
 use std::{
-    borrow::Cow,
-    collections::HashMap,
-    future::Future,
-    mem::ManuallyDrop,
-    ops::Deref,
-    sync::{atomic::AtomicU64, LazyLock},
+    borrow::Cow, collections::HashMap, future::Future, mem::ManuallyDrop, ops::Deref,
+    sync::LazyLock, time::SystemTime,
 };
 
 use anyhow::{anyhow, bail, Result};
@@ -35,8 +33,6 @@ struct TaskData {
 
 static TASKS: LazyLock<RwLock<HashMap<Id, TaskData>>> = LazyLock::new(Default::default);
 
-static NEXT_TASK_ID: AtomicU64 = AtomicU64::new(0);
-
 pub enum TaskError<E> {
     Cancelled,
     Failed(E),
@@ -61,6 +57,8 @@ struct OwnedTaskHandleInner<'a> {
     app: &'a AppHandle,
     id: Id,
     cancelled: oneshot::Receiver<()>,
+    metadata: Metadata,
+    started_at: SystemTime,
 }
 
 impl Id {
@@ -90,6 +88,7 @@ impl TaskHandle {
                     progress: Progress { completed, total },
                 },
             )?;
+            hierarchy::progress(app, handle, completed, total)?;
         }
         Ok(())
     }
@@ -103,12 +102,14 @@ impl TaskHandle {
                     progress: Progress { completed, total },
                 },
             )?;
+            hierarchy::progress(app, handle, completed, total)?;
         }
         Ok(())
     }
 
     pub fn send_dependency(&self, app: &AppHandle, dependency: Id) -> Result<()> {
         if let Some(handle) = self.0 {
+            hierarchy::add_dependency(handle, dependency);
             handle.emit(app, TaskDependency { dependency })?;
         }
         Ok(())
@@ -139,7 +140,14 @@ impl<'a> Deref for OwnedTaskHandleInner<'a> {
 
 impl OwnedTaskHandleInner<'_> {
     fn drop(self, status: DropStatus) -> Result<()> {
+        history::record(
+            self.id,
+            self.metadata.clone(),
+            self.started_at,
+            status.clone(),
+        );
         self.emit(self.app, TaskDropped { status })?;
+        hierarchy::dropped(self.app, self.id)?;
         Ok(())
     }
 }
@@ -179,7 +187,7 @@ impl Drop for OwnedTaskHandle<'_> {
 }
 
 pub fn allocate_task() -> Id {
-    Id(NEXT_TASK_ID.fetch_add(1, std::sync::atomic::Ordering::Relaxed))
+    Id(uuid::Uuid::new_v4())
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -223,18 +231,19 @@ impl TaskBuilder {
         let (cancel, cancelled) = oneshot::channel();
         match TASKS.write().await.entry(self.id) {
             std::collections::hash_map::Entry::Occupied(_) => {
-                // the NEXT_TASK_ID counter not only wrapped around, but also collided with a task that has not been removed yet.
+                // two random ids collided, or a caller reused an id with TaskBuilder::with_id.
                 return Err(CreateTaskError::IdCollision);
             }
             std::collections::hash_map::Entry::Vacant(entry) => {
                 entry.insert(TaskData {
                     cancel: Some(cancel),
                 });
+                hierarchy::created(self.id, &self.metadata.kind, self.metadata.progress_unit);
                 self.id
                     .emit(
                         app,
                         TaskCreated {
-                            metadata: self.metadata,
+                            metadata: self.metadata.clone(),
                         },
                     )
                     .map_err(CreateTaskError::EmitEventFailed)?;
@@ -243,6 +252,8 @@ impl TaskBuilder {
                         app,
                         id: self.id,
                         cancelled,
+                        metadata: self.metadata,
+                        started_at: SystemTime::now(),
                     }),
                 })
             }
@@ -1,10 +1,12 @@
 use std::borrow::Cow;
 
+/// Task ids are random so that they can be allocated without coordination, and never repeat
+/// across restarts of the app or between processes.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Deserialize, serde::Serialize)]
 #[repr(transparent)]
-pub struct Id(pub(super) u64);
+pub struct Id(pub(super) uuid::Uuid);
 
-#[derive(Clone, serde::Serialize)]
+#[derive(Clone, serde::Deserialize, serde::Serialize)]
 pub struct Metadata {
     pub title: Cow<'static, str>,
     #[serde(flatten)]
@@ -12,7 +14,7 @@ pub struct Metadata {
     pub progress_unit: ProgressUnit,
 }
 
-#[derive(Debug, Clone, serde::Serialize)]
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]
 #[serde(tag = "kind")]
 pub enum Kind {
     Aggregate,
@@ -20,7 +22,7 @@ pub enum Kind {
     Other,
 }
 
-#[derive(Debug, Clone, Copy, serde::Serialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
 pub enum ProgressUnit {
     Bytes,
     Other,
@@ -79,7 +81,7 @@ impl TaskEventBody for TaskDropped {
     const NAME: &str = "task_dropped";
 }
 
-#[derive(Clone, serde::Serialize)]
+#[derive(Clone, serde::Deserialize, serde::Serialize)]
 #[serde(tag = "status")]
 pub enum DropStatus {
     Success {
@@ -94,7 +96,7 @@ pub enum DropStatus {
     },
 }
 
-#[derive(Clone, Copy, serde::Serialize)]
+#[derive(Clone, Copy, serde::Deserialize, serde::Serialize)]
 pub enum SuccessInfo {
     Cached,
 }
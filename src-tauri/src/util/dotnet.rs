@@ -0,0 +1,509 @@
+//! A minimal reader for the metadata of .NET assemblies, as specified by ECMA-335 partition II.
+//!
+//! Only as much is parsed as is needed to find custom attributes and their arguments, which is how
+//! mod loaders like BepInEx identify plugins.
+
+use anyhow::{bail, ensure, Context as _, Result};
+
+/// Reads a little-endian integer at `offset`.
+fn read<const N: usize>(bytes: &[u8], offset: usize) -> Result<[u8; N]> {
+    bytes
+        .get(offset..offset + N)
+        .and_then(|b| b.try_into().ok())
+        .with_context(|| format!("Unexpected end of data at {offset:#x}"))
+}
+
+fn read_u16(bytes: &[u8], offset: usize) -> Result<u16> {
+    read(bytes, offset).map(u16::from_le_bytes)
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> Result<u32> {
+    read(bytes, offset).map(u32::from_le_bytes)
+}
+
+fn read_u64(bytes: &[u8], offset: usize) -> Result<u64> {
+    read(bytes, offset).map(u64::from_le_bytes)
+}
+
+/// Reads a compressed unsigned integer (II.23.2), returning it and the number of bytes it took.
+fn read_compressed(bytes: &[u8], offset: usize) -> Result<(u32, usize)> {
+    let b = |i: usize| {
+        bytes
+            .get(offset + i)
+            .map(|&b| u32::from(b))
+            .context("Unexpected end of compressed integer")
+    };
+    let b0 = b(0)?;
+    Ok(if b0 & 0x80 == 0 {
+        (b0, 1)
+    } else if b0 & 0xC0 == 0x80 {
+        (((b0 & 0x3F) << 8) | b(1)?, 2)
+    } else if b0 & 0xE0 == 0xC0 {
+        (
+            ((b0 & 0x1F) << 24) | (b(1)? << 16) | (b(2)? << 8) | b(3)?,
+            4,
+        )
+    } else {
+        bail!("Invalid compressed integer {b0:#x}")
+    })
+}
+
+// table numbers (II.22)
+const MODULE: usize = 0x00;
+const TYPE_REF: usize = 0x01;
+const TYPE_DEF: usize = 0x02;
+const FIELD: usize = 0x04;
+const METHOD_DEF: usize = 0x06;
+const PARAM: usize = 0x08;
+const INTERFACE_IMPL: usize = 0x09;
+const MEMBER_REF: usize = 0x0A;
+const CUSTOM_ATTRIBUTE: usize = 0x0C;
+const PERMISSION: usize = 0x0E;
+const STAND_ALONE_SIG: usize = 0x11;
+const EVENT: usize = 0x14;
+const PROPERTY: usize = 0x17;
+const MODULE_REF: usize = 0x1A;
+const TYPE_SPEC: usize = 0x1B;
+const ASSEMBLY: usize = 0x20;
+const ASSEMBLY_REF: usize = 0x23;
+const FILE: usize = 0x26;
+const EXPORTED_TYPE: usize = 0x27;
+const MANIFEST_RESOURCE: usize = 0x28;
+const GENERIC_PARAM: usize = 0x2A;
+const METHOD_SPEC: usize = 0x2B;
+const GENERIC_PARAM_CONSTRAINT: usize = 0x2C;
+
+/// The tables that a coded index can refer to, in the order of their tags (II.24.2.6).
+struct CodedIndex {
+    tag_bits: u32,
+    tables: &'static [usize],
+}
+
+const TYPE_DEF_OR_REF: CodedIndex = CodedIndex {
+    tag_bits: 2,
+    tables: &[TYPE_DEF, TYPE_REF, TYPE_SPEC],
+};
+const RESOLUTION_SCOPE: CodedIndex = CodedIndex {
+    tag_bits: 2,
+    tables: &[MODULE, MODULE_REF, ASSEMBLY_REF, TYPE_REF],
+};
+const MEMBER_REF_PARENT: CodedIndex = CodedIndex {
+    tag_bits: 3,
+    tables: &[TYPE_DEF, TYPE_REF, MODULE_REF, METHOD_DEF, TYPE_SPEC],
+};
+const HAS_CONSTANT: CodedIndex = CodedIndex {
+    tag_bits: 2,
+    tables: &[FIELD, PARAM, PROPERTY],
+};
+const HAS_CUSTOM_ATTRIBUTE: CodedIndex = CodedIndex {
+    tag_bits: 5,
+    tables: &[
+        METHOD_DEF,
+        FIELD,
+        TYPE_REF,
+        TYPE_DEF,
+        PARAM,
+        INTERFACE_IMPL,
+        MEMBER_REF,
+        MODULE,
+        PERMISSION,
+        PROPERTY,
+        EVENT,
+        STAND_ALONE_SIG,
+        MODULE_REF,
+        TYPE_SPEC,
+        ASSEMBLY,
+        ASSEMBLY_REF,
+        FILE,
+        EXPORTED_TYPE,
+        MANIFEST_RESOURCE,
+        GENERIC_PARAM,
+        GENERIC_PARAM_CONSTRAINT,
+        METHOD_SPEC,
+    ],
+};
+const CUSTOM_ATTRIBUTE_TYPE: CodedIndex = CodedIndex {
+    tag_bits: 3,
+    tables: &[METHOD_DEF, MEMBER_REF],
+};
+
+/// A column of a metadata table.
+#[derive(Clone, Copy)]
+enum Column {
+    Fixed(usize),
+    String,
+    Guid,
+    Blob,
+    Table(usize),
+    Coded(&'static CodedIndex),
+}
+
+/// The columns of every table up to and including `CustomAttribute`, which are all that need to be
+/// skipped over to get to it.
+const COLUMNS: [&[Column]; CUSTOM_ATTRIBUTE + 1] = {
+    use Column::*;
+    [
+        // Module
+        &[Fixed(2), String, Guid, Guid, Guid],
+        // TypeRef
+        &[Coded(&RESOLUTION_SCOPE), String, String],
+        // TypeDef
+        &[
+            Fixed(4),
+            String,
+            String,
+            Coded(&TYPE_DEF_OR_REF),
+            Table(FIELD),
+            Table(METHOD_DEF),
+        ],
+        // FieldPtr
+        &[Table(FIELD)],
+        // Field
+        &[Fixed(2), String, Blob],
+        // MethodPtr
+        &[Table(METHOD_DEF)],
+        // MethodDef
+        &[Fixed(4), Fixed(2), Fixed(2), String, Blob, Table(PARAM)],
+        // ParamPtr
+        &[Table(PARAM)],
+        // Param
+        &[Fixed(2), Fixed(2), String],
+        // InterfaceImpl
+        &[Table(TYPE_DEF), Coded(&TYPE_DEF_OR_REF)],
+        // MemberRef
+        &[Coded(&MEMBER_REF_PARENT), String, Blob],
+        // Constant
+        &[Fixed(2), Coded(&HAS_CONSTANT), Blob],
+        // CustomAttribute
+        &[
+            Coded(&HAS_CUSTOM_ATTRIBUTE),
+            Coded(&CUSTOM_ATTRIBUTE_TYPE),
+            Blob,
+        ],
+    ]
+};
+
+/// A location in the file, by section.
+struct Section {
+    virtual_address: u32,
+    virtual_size: u32,
+    raw_offset: u32,
+}
+
+/// Finds the metadata of the assembly in the PE image `image`.
+fn find_metadata(image: &[u8]) -> Result<&[u8]> {
+    ensure!(image.starts_with(b"MZ"), "Not a PE image");
+    let pe = read_u32(image, 0x3C)? as usize;
+    ensure!(read::<4>(image, pe)? == *b"PE\0\0", "Not a PE image");
+    let coff = pe + 4;
+    let section_count = read_u16(image, coff + 2)?;
+    let optional_header_size = read_u16(image, coff + 16)? as usize;
+    let optional = coff + 20;
+    let data_directories = match read_u16(image, optional)? {
+        0x10B => optional + 96,
+        0x20B => optional + 112,
+        magic => bail!("Unknown optional header magic {magic:#x}"),
+    };
+
+    let sections = (0..usize::from(section_count))
+        .map(|i| {
+            let header = optional + optional_header_size + i * 40;
+            Ok(Section {
+                virtual_size: read_u32(image, header + 8)?,
+                virtual_address: read_u32(image, header + 12)?,
+                raw_offset: read_u32(image, header + 20)?,
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let resolve = |rva: u32| {
+        sections
+            .iter()
+            .find(|s| {
+                (s.virtual_address..s.virtual_address.saturating_add(s.virtual_size)).contains(&rva)
+            })
+            .map(|s| (rva - s.virtual_address) as usize + s.raw_offset as usize)
+            .with_context(|| format!("RVA {rva:#x} is not in any section"))
+    };
+
+    // the CLI header is the 15th data directory
+    let cli_header_rva = read_u32(image, data_directories + 14 * 8)?;
+    ensure!(cli_header_rva != 0, "Not a .NET assembly");
+    let cli_header = resolve(cli_header_rva)?;
+    let metadata = resolve(read_u32(image, cli_header + 8)?)?;
+    let metadata_size = read_u32(image, cli_header + 12)? as usize;
+    image
+        .get(metadata..metadata + metadata_size)
+        .context("Metadata is out of bounds")
+}
+
+/// The metadata of a .NET assembly.
+pub struct Metadata<'a> {
+    strings: &'a [u8],
+    blobs: &'a [u8],
+    tables: &'a [u8],
+    /// The number of rows in each table.
+    rows: [u32; 64],
+    /// The offset of each of the tables up to `CustomAttribute` in `tables`.
+    offsets: [usize; CUSTOM_ATTRIBUTE + 1],
+    /// The size of the rows of each of the tables up to `CustomAttribute`.
+    row_sizes: [usize; CUSTOM_ATTRIBUTE + 1],
+    string_size: usize,
+    guid_size: usize,
+    blob_size: usize,
+}
+
+impl<'a> Metadata<'a> {
+    /// Parses the metadata of the assembly in the PE image `image`.
+    pub fn parse(image: &'a [u8]) -> Result<Self> {
+        let metadata = find_metadata(image)?;
+        ensure!(
+            read_u32(metadata, 0)? == 0x424A5342,
+            "Invalid metadata signature"
+        );
+        let version_len = read_u32(metadata, 12)? as usize;
+        let mut offset = 16 + version_len;
+        let stream_count = read_u16(metadata, offset + 2)?;
+        offset += 4;
+
+        let mut strings = None;
+        let mut blobs = None;
+        let mut tables = None;
+        for _ in 0..stream_count {
+            let stream_offset = read_u32(metadata, offset)? as usize;
+            let stream_size = read_u32(metadata, offset + 4)? as usize;
+            let name_len = metadata
+                .get(offset + 8..)
+                .and_then(|b| b.iter().position(|&b| b == 0))
+                .context("Unterminated stream name")?;
+            let name = &metadata[offset + 8..offset + 8 + name_len];
+            // the name is padded to a multiple of 4 bytes, including the terminator
+            offset += 8 + (name_len + 4) / 4 * 4;
+            let stream = metadata
+                .get(stream_offset..stream_offset + stream_size)
+                .context("Stream is out of bounds")?;
+            match name {
+                b"#Strings" => strings = Some(stream),
+                b"#Blob" => blobs = Some(stream),
+                b"#~" => tables = Some(stream),
+                _ => {}
+            }
+        }
+        let tables = tables.context("Missing #~ stream")?;
+
+        let heap_sizes = *tables.get(6).context("Truncated #~ stream")?;
+        let valid = read_u64(tables, 8)?;
+        let mut rows = [0; 64];
+        let mut offset = 24;
+        for (i, rows) in rows.iter_mut().enumerate() {
+            if valid & (1 << i) != 0 {
+                *rows = read_u32(tables, offset)?;
+                offset += 4;
+            }
+        }
+        // an undocumented flag that is set by some obfuscators
+        if heap_sizes & 0x40 != 0 {
+            offset += 4;
+        }
+
+        let index_size = |large: bool| if large { 4 } else { 2 };
+        let mut this = Self {
+            strings: strings.unwrap_or_default(),
+            blobs: blobs.unwrap_or_default(),
+            tables,
+            rows,
+            offsets: [0; CUSTOM_ATTRIBUTE + 1],
+            row_sizes: [0; CUSTOM_ATTRIBUTE + 1],
+            string_size: index_size(heap_sizes & 0x01 != 0),
+            guid_size: index_size(heap_sizes & 0x02 != 0),
+            blob_size: index_size(heap_sizes & 0x04 != 0),
+        };
+        for (table, columns) in COLUMNS.iter().enumerate() {
+            this.offsets[table] = offset;
+            this.row_sizes[table] = columns.iter().map(|&c| this.column_size(c)).sum();
+            offset += this.row_sizes[table] * this.rows[table] as usize;
+        }
+        ensure!(offset <= tables.len(), "Tables are out of bounds");
+        Ok(this)
+    }
+
+    fn column_size(&self, column: Column) -> usize {
+        match column {
+            Column::Fixed(size) => size,
+            Column::String => self.string_size,
+            Column::Guid => self.guid_size,
+            Column::Blob => self.blob_size,
+            Column::Table(table) => {
+                if self.rows[table] < 1 << 16 {
+                    2
+                } else {
+                    4
+                }
+            }
+            Column::Coded(coded) => {
+                let max_rows = coded.tables.iter().map(|&t| self.rows[t]).max();
+                if max_rows.unwrap_or(0) < 1 << (16 - coded.tag_bits) {
+                    2
+                } else {
+                    4
+                }
+            }
+        }
+    }
+
+    /// Reads column `column` of row `row` (1-based) of table `table`.
+    fn get(&self, table: usize, row: u32, column: usize) -> Result<u32> {
+        ensure!(
+            (1..=self.rows[table]).contains(&row),
+            "Row {row} of table {table:#x} does not exist"
+        );
+        let columns = COLUMNS[table];
+        let mut offset = self.offsets[table] + (row as usize - 1) * self.row_sizes[table];
+        offset += columns[..column]
+            .iter()
+            .map(|&c| self.column_size(c))
+            .sum::<usize>();
+        Ok(match self.column_size(columns[column]) {
+            2 => read_u16(self.tables, offset)?.into(),
+            4 => read_u32(self.tables, offset)?,
+            _ => bail!("Column {column} of table {table:#x} is not an index"),
+        })
+    }
+
+    fn string(&self, index: u32) -> Result<&'a str> {
+        let bytes = self
+            .strings
+            .get(index as usize..)
+            .context("String is out of bounds")?;
+        let len = bytes
+            .iter()
+            .position(|&b| b == 0)
+            .context("Unterminated string")?;
+        Ok(std::str::from_utf8(&bytes[..len])?)
+    }
+
+    fn blob(&self, index: u32) -> Result<&'a [u8]> {
+        let (len, len_size) = read_compressed(self.blobs, index as usize)?;
+        let start = index as usize + len_size;
+        self.blobs
+            .get(start..start + len as usize)
+            .context("Blob is out of bounds")
+    }
+
+    /// Returns the namespace and name of the type whose constructor is `ctor`, a
+    /// `CustomAttributeType` coded index.
+    fn attribute_type(&self, ctor: u32) -> Result<Option<(&'a str, &'a str)>> {
+        // only attributes defined in other assemblies are referenced through a MemberRef
+        if ctor & 0b111 != 3 {
+            return Ok(None);
+        }
+        let parent = self.get(MEMBER_REF, ctor >> 3, 0)?;
+        // the MemberRefParent tag of TypeRef
+        if parent & 0b111 != 1 {
+            return Ok(None);
+        }
+        let type_ref = parent >> 3;
+        Ok(Some((
+            self.string(self.get(TYPE_REF, type_ref, 2)?)?,
+            self.string(self.get(TYPE_REF, type_ref, 1)?)?,
+        )))
+    }
+
+    /// Finds the custom attributes of type `namespace.name` that are applied to types in the
+    /// assembly, returning the full name of each type alongside the attribute's value blob.
+    pub fn type_attributes(&self, namespace: &str, name: &str) -> Result<Vec<(String, &'a [u8])>> {
+        let mut attributes = Vec::new();
+        for row in 1..=self.rows[CUSTOM_ATTRIBUTE] {
+            let parent = self.get(CUSTOM_ATTRIBUTE, row, 0)?;
+            // the HasCustomAttribute tag of TypeDef
+            if parent & 0b11111 != 3 {
+                continue;
+            }
+            let ctor = self.get(CUSTOM_ATTRIBUTE, row, 1)?;
+            if self.attribute_type(ctor)? != Some((namespace, name)) {
+                continue;
+            }
+            let type_def = parent >> 5;
+            let type_name = self.string(self.get(TYPE_DEF, type_def, 1)?)?;
+            let type_namespace = self.string(self.get(TYPE_DEF, type_def, 2)?)?;
+            let full_name = if type_namespace.is_empty() {
+                type_name.to_owned()
+            } else {
+                format!("{type_namespace}.{type_name}")
+            };
+            attributes.push((full_name, self.blob(self.get(CUSTOM_ATTRIBUTE, row, 2)?)?));
+        }
+        Ok(attributes)
+    }
+}
+
+/// Reads the fixed arguments of a custom attribute value blob (II.23.3) whose constructor takes
+/// only strings. `None` is returned for null strings.
+pub fn attribute_string_args(blob: &[u8], count: usize) -> Result<Vec<Option<String>>> {
+    ensure!(read_u16(blob, 0)? == 1, "Invalid custom attribute prolog");
+    let mut offset = 2;
+    (0..count)
+        .map(|_| {
+            if blob.get(offset) == Some(&0xFF) {
+                offset += 1;
+                return Ok(None);
+            }
+            let (len, len_size) = read_compressed(blob, offset)?;
+            offset += len_size;
+            let bytes = blob
+                .get(offset..offset + len as usize)
+                .context("String argument is out of bounds")?;
+            offset += len as usize;
+            Ok(Some(std::str::from_utf8(bytes)?.to_owned()))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_compressed() {
+        assert_eq!(read_compressed(&[0x03], 0).unwrap(), (0x03, 1));
+        assert_eq!(read_compressed(&[0x7F], 0).unwrap(), (0x7F, 1));
+        assert_eq!(read_compressed(&[0x80, 0x80], 0).unwrap(), (0x80, 2));
+        assert_eq!(read_compressed(&[0xBF, 0xFF], 0).unwrap(), (0x3FFF, 2));
+        assert_eq!(
+            read_compressed(&[0xC0, 0x00, 0x40, 0x00], 0).unwrap(),
+            (0x4000, 4)
+        );
+        assert!(read_compressed(&[0xFF], 0).is_err());
+        assert!(read_compressed(&[0x80], 0).is_err());
+    }
+
+    #[test]
+    fn test_attribute_string_args() {
+        let mut blob = vec![0x01, 0x00];
+        for s in ["com.example.plugin", "Example Plugin", "1.2.3"] {
+            blob.push(s.len() as u8);
+            blob.extend_from_slice(s.as_bytes());
+        }
+        blob.extend_from_slice(&[0x00, 0x00]);
+        assert_eq!(
+            attribute_string_args(&blob, 3).unwrap(),
+            [
+                Some("com.example.plugin".to_owned()),
+                Some("Example Plugin".to_owned()),
+                Some("1.2.3".to_owned())
+            ]
+        );
+        assert_eq!(
+            attribute_string_args(&[0x01, 0x00, 0xFF, 0x00], 2).unwrap(),
+            [None, Some(String::new())]
+        );
+        assert!(attribute_string_args(&[0x00, 0x00], 0).is_err());
+        assert!(attribute_string_args(&[0x01, 0x00, 0x05, b'a'], 1).is_err());
+    }
+
+    #[test]
+    fn test_not_a_pe_image() {
+        assert!(Metadata::parse(b"").is_err());
+        assert!(Metadata::parse(b"MZ").is_err());
+        assert!(Metadata::parse(&[0; 256]).is_err());
+    }
+}
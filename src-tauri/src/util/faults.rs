@@ -0,0 +1,117 @@
+//! Artificial latency, bandwidth caps, and failures, injected into HTTP requests and the file
+//! operations of installs so that cancellation, retries, and rollback can be exercised by hand and
+//! in integration tests.
+//!
+//! Configured through the developer settings, which are hidden outside of development builds, and
+//! off by default.
+
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+use std::time::Duration;
+
+/// Milliseconds to wait before each request or operation.
+static LATENCY: AtomicU64 = AtomicU64::new(0);
+/// Bytes per second that responses are read at, or 0 to not limit them.
+static BANDWIDTH: AtomicU64 = AtomicU64::new(0);
+/// Chance, out of 100, of each request or operation failing.
+static FAILURE_RATE: AtomicU32 = AtomicU32::new(0);
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FaultOptions {
+    pub latency: Duration,
+    /// Bytes per second, or 0 to not limit the bandwidth.
+    pub bandwidth: u64,
+    /// Percentage of requests and operations that fail, from 0 to 100.
+    pub failure_rate: u32,
+}
+
+pub fn configure(options: FaultOptions) {
+    let options = allowed(options, cfg!(debug_assertions));
+    if options != FaultOptions::default() {
+        slog_scope::warn!("Injecting faults: {options:?}");
+    }
+    LATENCY.store(
+        options.latency.as_millis().try_into().unwrap_or(u64::MAX),
+        Ordering::Relaxed,
+    );
+    BANDWIDTH.store(options.bandwidth, Ordering::Relaxed);
+    FAILURE_RATE.store(options.failure_rate.min(100), Ordering::Relaxed);
+}
+
+/// Faults are only injected in development builds, even if a settings file from one is used with a
+/// release build.
+fn allowed(options: FaultOptions, development: bool) -> FaultOptions {
+    if development || options == FaultOptions::default() {
+        options
+    } else {
+        slog_scope::warn!("Not injecting faults outside of development builds: {options:?}");
+        FaultOptions::default()
+    }
+}
+
+/// A failure that was injected rather than caused by anything going wrong.
+#[derive(Debug, thiserror::Error)]
+#[error("Injected failure of {0}")]
+pub struct InjectedFault(&'static str);
+
+/// Waits out the configured latency, then fails at the configured rate. Called before each
+/// `operation` that faults are injected into.
+pub async fn inject(operation: &'static str) -> std::io::Result<()> {
+    let latency = LATENCY.load(Ordering::Relaxed);
+    if latency != 0 {
+        tokio::time::sleep(Duration::from_millis(latency)).await;
+    }
+    let rate = FAILURE_RATE.load(Ordering::Relaxed);
+    if rate != 0 && fastrand::u32(0..100) < rate {
+        slog_scope::debug!("Injecting failure of {operation}");
+        return Err(std::io::Error::other(InjectedFault(operation)));
+    }
+    Ok(())
+}
+
+/// Waits for as long as receiving `len` bytes would take at the configured bandwidth.
+pub async fn throttle(len: usize) {
+    let bandwidth = BANDWIDTH.load(Ordering::Relaxed);
+    if bandwidth != 0 {
+        tokio::time::sleep(Duration::from_secs_f64(len as f64 / bandwidth as f64)).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{allowed, configure, inject, FaultOptions};
+
+    #[test]
+    fn test_allowed() {
+        let options = FaultOptions {
+            latency: Duration::from_millis(10),
+            bandwidth: 1024,
+            failure_rate: 50,
+        };
+        assert_eq!(allowed(options, true), options);
+        assert_eq!(allowed(options, false), FaultOptions::default());
+    }
+
+    #[test]
+    fn test_inject() {
+        tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()
+            .unwrap()
+            .block_on(async {
+                // faults are off unless configured
+                for _ in 0..100 {
+                    inject("testing").await.unwrap();
+                }
+
+                configure(FaultOptions {
+                    failure_rate: 100,
+                    ..Default::default()
+                });
+                let result = inject("testing").await;
+                configure(FaultOptions::default());
+                assert_eq!(result.is_err(), cfg!(debug_assertions));
+            });
+    }
+}
@@ -1,6 +1,11 @@
+use std::sync::LazyLock;
+use std::time::Duration;
+
 use bytes::Bytes;
+use manderrow_paths::PRODUCT_NAME;
+use parking_lot::RwLock;
 use pin_project_lite::pin_project;
-use reqwest::Response;
+use reqwest::{Response, StatusCode};
 use tokio::io::{AsyncBufRead, AsyncRead};
 use tokio_util::io::StreamReader;
 
@@ -10,14 +15,26 @@ mod private {
     use std::io::Result;
 
     use bytes::Bytes;
-    use futures_util::{Stream, TryStreamExt};
+    use futures_util::{Stream, StreamExt, TryStreamExt};
     use reqwest::Response;
 
+    use crate::util::faults;
+
     pub type ReqwestBytesStream = impl Stream<Item = Result<Bytes>>;
 
     #[define_opaque(ReqwestBytesStream)]
     pub fn bytes_stream(response: Response) -> ReqwestBytesStream {
-        response.bytes_stream().map_err(std::io::Error::other)
+        futures_util::stream::once(faults::inject("HTTP response"))
+            .try_filter_map(|()| async { Ok(None::<Bytes>) })
+            .chain(
+                response
+                    .bytes_stream()
+                    .map_err(std::io::Error::other)
+                    .and_then(|chunk| async move {
+                        faults::throttle(chunk.len()).await;
+                        Ok(chunk)
+                    }),
+            )
     }
 }
 
@@ -25,9 +42,81 @@ pub use private::ReqwestBytesStream;
 
 use super::{Progress, UsizeExt};
 
+/// The options that the shared HTTP client is built with.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClientOptions {
+    pub connect_timeout: Option<Duration>,
+    /// The longest to wait for more of a response to arrive.
+    pub read_timeout: Option<Duration>,
+    /// Replaces the default user agent, unless empty.
+    pub user_agent: String,
+    pub http2: bool,
+}
+
+impl Default for ClientOptions {
+    fn default() -> Self {
+        Self {
+            connect_timeout: None,
+            read_timeout: None,
+            user_agent: String::new(),
+            http2: true,
+        }
+    }
+}
+
+impl ClientOptions {
+    fn build(&self) -> reqwest::Result<reqwest::Client> {
+        let mut builder = reqwest::Client::builder().user_agent(if self.user_agent.is_empty() {
+            default_user_agent()
+        } else {
+            self.user_agent.clone()
+        });
+        if let Some(timeout) = self.connect_timeout {
+            builder = builder.connect_timeout(timeout);
+        }
+        if let Some(timeout) = self.read_timeout {
+            builder = builder.read_timeout(timeout);
+        }
+        if !self.http2 {
+            builder = builder.http1_only();
+        }
+        builder.build()
+    }
+}
+
+pub fn default_user_agent() -> String {
+    format!("{}/{}", PRODUCT_NAME, env!("CARGO_PKG_VERSION"))
+}
+
+static CLIENT: LazyLock<RwLock<(ClientOptions, reqwest::Client)>> = LazyLock::new(|| {
+    let options = ClientOptions::default();
+    let client = options.build().expect("Failed to build HTTP client");
+    RwLock::new((options, client))
+});
+
+/// Returns the shared HTTP client. It is replaced when its options change, so it should not be
+/// held on to for longer than a request.
+pub fn client() -> reqwest::Client {
+    CLIENT.read().1.clone()
+}
+
+/// Rebuilds the shared HTTP client if `options` differ from the ones it was built with. Requests
+/// that are already in progress continue with the old client.
+pub fn configure(options: ClientOptions) -> reqwest::Result<()> {
+    if CLIENT.read().0 == options {
+        return Ok(());
+    }
+    let client = options.build()?;
+    *CLIENT.write() = (options, client);
+    Ok(())
+}
+
 pub trait ResponseExt {
     fn reader(self) -> ResponseReader;
 
+    /// Like [`Response::chunk`], but slowed down by any injected bandwidth cap.
+    fn next_chunk(&mut self) -> impl Future<Output = reqwest::Result<Option<Bytes>>> + Send;
+
     fn reader_with_progress(self, progress: &Progress) -> ProgressReader<'_, ResponseReader>;
 }
 
@@ -36,6 +125,14 @@ impl ResponseExt for Response {
         ResponseReader::new(private::bytes_stream(self))
     }
 
+    async fn next_chunk(&mut self) -> reqwest::Result<Option<Bytes>> {
+        let chunk = self.chunk().await?;
+        if let Some(chunk) = &chunk {
+            super::faults::throttle(chunk.len()).await;
+        }
+        Ok(chunk)
+    }
+
     fn reader_with_progress(self, progress: &Progress) -> ProgressReader<'_, ResponseReader> {
         let expected = self.content_length();
         if expected.is_none() {
@@ -51,6 +148,14 @@ impl ResponseExt for Response {
     }
 }
 
+/// Returns `true` if `error` was caused by an HTTP response with the given `status`.
+pub fn has_status(error: &anyhow::Error, status: StatusCode) -> bool {
+    error.chain().any(|e| {
+        e.downcast_ref::<reqwest::Error>()
+            .is_some_and(|e| e.status() == Some(status))
+    })
+}
+
 pin_project! {
     pub struct ProgressReader<'a, R> {
         #[pin]
@@ -93,3 +198,23 @@ impl<'a, R: AsyncBufRead> AsyncBufRead for ProgressReader<'a, R> {
         }
     }
 }
+
+/// Returns `true` if `url` is an absolute `http` or `https` URL. Links that come from mods are only
+/// kept if they are, since opening anything else, like a `file` URL, could run a program.
+pub fn is_web_url(url: &str) -> bool {
+    reqwest::Url::parse(url).is_ok_and(|url| matches!(url.scheme(), "http" | "https"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_web_url() {
+        assert!(is_web_url("https://ko-fi.com/someone"));
+        assert!(is_web_url("http://example.com"));
+        assert!(!is_web_url("file:///C:/Windows/System32/calc.exe"));
+        assert!(!is_web_url("javascript:alert(1)"));
+        assert!(!is_web_url("ko-fi.com/someone"));
+    }
+}
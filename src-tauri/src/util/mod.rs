@@ -1,10 +1,14 @@
+pub mod dotnet;
+pub mod faults;
 pub mod http;
 mod progress;
 pub mod search;
+pub mod watch;
 
 pub use progress::Progress;
 
 use std::io;
+use std::time::{SystemTime, UNIX_EPOCH};
 
 pub trait IoErrorKindExt {
     fn is_not_found(&self) -> bool;
@@ -22,6 +26,12 @@ impl IoErrorKindExt for io::Error {
     }
 }
 
+/// Returns the number of milliseconds from the Unix epoch to `time`, or 0 if it is before it.
+pub fn unix_millis(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_millis() as u64)
+}
+
 macro_rules! hyphenated_uuid {
     ($id:expr) => {
         $id.hyphenated().encode_lower(&mut Uuid::encode_buffer())
@@ -0,0 +1,72 @@
+//! Watching folders for changes, which tend to come in bursts, e.g. when a mod writes several
+//! config files at once or a build writes its output.
+
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use anyhow::{Context as _, Result};
+use notify::{EventKind, RecursiveMode, Watcher as _};
+use slog::warn;
+use tokio::sync::mpsc;
+
+/// Watches a folder and everything in it, reporting changes in batches of those that are made
+/// within a debounce period of each other.
+pub struct DebouncedWatcher {
+    path: PathBuf,
+    debounce: Duration,
+    rx: mpsc::UnboundedReceiver<notify::Result<notify::Event>>,
+    /// The changes of the batch that is still being collected.
+    pending: Vec<PathBuf>,
+    // stops watching when dropped
+    _watcher: notify::RecommendedWatcher,
+}
+
+impl DebouncedWatcher {
+    pub fn new(path: &Path, debounce: Duration) -> Result<Self> {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let mut watcher =
+            notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+                _ = tx.send(event);
+            })?;
+        watcher
+            .watch(path, RecursiveMode::Recursive)
+            .with_context(|| format!("Failed to watch {path:?}"))?;
+        Ok(Self {
+            path: path.to_owned(),
+            debounce,
+            rx,
+            pending: Vec::new(),
+            _watcher: watcher,
+        })
+    }
+
+    /// Waits for the next batch of changes, which ends once nothing has changed for the debounce
+    /// period, and returns the paths of the files and folders that were created, modified, or
+    /// removed. Returns `None` once the watcher stops.
+    ///
+    /// Cancelling the returned future loses no changes, so it can be used with `tokio::select!`.
+    pub async fn next(&mut self, log: &slog::Logger) -> Option<Vec<PathBuf>> {
+        loop {
+            let event = if self.pending.is_empty() {
+                self.rx.recv().await?
+            } else {
+                match tokio::time::timeout(self.debounce, self.rx.recv()).await {
+                    Ok(Some(event)) => event,
+                    Ok(None) | Err(_) => return Some(std::mem::take(&mut self.pending)),
+                }
+            };
+            match event {
+                Ok(event)
+                    if matches!(
+                        event.kind,
+                        EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_)
+                    ) =>
+                {
+                    self.pending.extend(event.paths);
+                }
+                Ok(_) => {}
+                Err(e) => warn!(log, "Error while watching {:?}: {e}", self.path),
+            }
+        }
+    }
+}
@@ -1,8 +1,14 @@
+use std::ffi::OsString;
 use std::io::Write;
 use std::path::PathBuf;
 
-use anyhow::{Context as _, Result};
+use anyhow::{ensure, Context as _, Result};
 use lexopt::ValueExt;
+use manderrow_ipc::ipc_channel::ipc::{IpcOneShotServer, IpcSender};
+use manderrow_ipc::S2CMessage;
+
+use crate::ipc::C2SMessage;
+use crate::launching::failures;
 
 struct DisplayArgList;
 impl std::fmt::Display for DisplayArgList {
@@ -38,6 +44,185 @@ pub enum WrapperMode {
     Injection,
 }
 
+/// The arguments Manderrow passes to the wrapper when launching a profile.
+struct WrapperConfig {
+    agent_path: PathBuf,
+    /// Libraries to preload alongside the agent, for those that must be loaded before the game
+    /// starts.
+    preload: Vec<PathBuf>,
+    /// The command line to run the game through, if any.
+    wrapper: Vec<OsString>,
+    c2s_tx: String,
+}
+
+impl WrapperConfig {
+    /// Fails if the arguments are missing or point at things that no longer exist, as happens
+    /// when the game is launched from Steam after Manderrow was removed or its data was lost.
+    fn parse(args: &[OsString]) -> Result<Self> {
+        // TODO: avoid cloning so much. Not just here. All over dealing with arguments.
+        let (manderrow_args, _) = manderrow_args::extract(args.iter().cloned())?;
+        anyhow::ensure!(
+            !manderrow_args.is_empty(),
+            "No Manderrow arguments were passed"
+        );
+
+        let mut manderrow_args = lexopt::Parser::from_args(manderrow_args);
+
+        let mut agent_path = None::<PathBuf>;
+        let mut preload = Vec::<PathBuf>::new();
+        let mut wrapper = Vec::<OsString>::new();
+        let mut c2s_tx = None::<String>;
+
+        while let Some(arg) = manderrow_args.next()? {
+            // NOTE: this can break if an unhandled option's value happens to be `--agent-path` or `--c2s-tx`
+            match arg {
+                lexopt::Arg::Long("agent-path") => {
+                    agent_path = Some(manderrow_args.value()?.into());
+                }
+                lexopt::Arg::Long("preload-library") => {
+                    preload.push(manderrow_args.value()?.into());
+                }
+                lexopt::Arg::Long("wrapper-arg") => {
+                    wrapper.push(manderrow_args.value()?);
+                }
+                lexopt::Arg::Long("c2s-tx") => {
+                    c2s_tx = Some(manderrow_args.value()?.parse()?);
+                }
+                _ => {}
+            }
+        }
+
+        let agent_path = agent_path.context("Missing --agent-path")?;
+        anyhow::ensure!(agent_path.is_file(), "Agent is missing from {agent_path:?}");
+        for path in &preload {
+            anyhow::ensure!(path.is_file(), "Library is missing from {path:?}");
+        }
+        let c2s_tx = c2s_tx.context("Missing --c2s-tx")?;
+
+        Ok(Self {
+            agent_path,
+            preload,
+            wrapper,
+            c2s_tx,
+        })
+    }
+}
+
+/// Options that only the wrapper understands. They are removed before the game is started, as the
+/// agent rejects options that it doesn't know.
+const WRAPPER_OPTIONS: &[&str] = &["--preload-library", "--wrapper-arg"];
+
+/// Removes `options` and their values from the Manderrow arguments in `args`.
+fn strip_options(args: Vec<OsString>, options: &[&str]) -> Vec<OsString> {
+    let mut stripped = Vec::with_capacity(args.len());
+    let mut capturing = false;
+    let mut iter = args.into_iter();
+    while let Some(arg) = iter.next() {
+        if arg == manderrow_args::ARG_START_DELIMITER {
+            capturing = true;
+        } else if arg == manderrow_args::ARG_END_DELIMITER {
+            capturing = false;
+        } else if capturing && options.iter().any(|option| arg == *option) {
+            iter.next();
+            continue;
+        }
+        stripped.push(arg);
+    }
+    stripped
+}
+
+/// Runs this wrapper again through `wrapper`, like `gamemoderun %command%`, but without `wrapper`
+/// this time. The processes of `wrapper` itself are then not injected into, only the game.
+fn run_through_wrapper(
+    mut log_file: impl Write,
+    mode: WrapperMode,
+    wrapper: &[OsString],
+    command_name: OsString,
+    args: Vec<OsString>,
+) -> Result<()> {
+    _ = writeln!(log_file, "Running through {wrapper:?}");
+
+    let current_exe = std::env::current_exe().context("Failed to get current exe path")?;
+    let (assignments, wrapper) = crate::launching::wrappers::split_assignments(wrapper);
+    let mut command = match wrapper.split_first() {
+        Some((program, wrapper_args)) => {
+            let mut command = std::process::Command::new(program);
+            command.args(wrapper_args);
+            command.arg(current_exe);
+            command
+        }
+        None => std::process::Command::new(current_exe),
+    };
+    command.envs(assignments);
+    command.arg(match mode {
+        WrapperMode::Injection => "wrap-with-injection",
+    });
+    command.arg(command_name);
+    command.args(strip_options(args, &["--wrapper-arg"]));
+
+    let status = match command.status() {
+        Ok(t) => t,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            return Err(anyhow::Error::new(e).context(format!(
+                "Could not locate command {:?}",
+                command.get_program()
+            )))
+        }
+        Err(e) => return Err(e.into()),
+    };
+    status.exit_ok()?;
+    Ok(())
+}
+
+/// Runs the game exactly as it would have been run without the wrapper.
+fn pass_through(command_name: OsString, args: Vec<OsString>) -> Result<()> {
+    let mut command = std::process::Command::new(&command_name);
+    command.args(args);
+
+    #[cfg(unix)]
+    {
+        let e = std::os::unix::process::CommandExt::exec(&mut command);
+        Err(anyhow::Error::new(e).context(format!("Failed to exec {command_name:?}")))
+    }
+    #[cfg(not(unix))]
+    {
+        let status = command
+            .status()
+            .with_context(|| format!("Failed to run {command_name:?}"))?;
+        std::process::exit(status.code().unwrap_or(1))
+    }
+}
+
+/// Tells the app that launched the game why it failed to start, through the connection that
+/// would otherwise have been used by the agent.
+fn send_crash(c2s_tx: String, error: String) -> Result<()> {
+    let c2s_tx = IpcSender::<C2SMessage>::connect(c2s_tx)?;
+    let (s2c_rx, s2c_tx) = IpcOneShotServer::<S2CMessage>::new()?;
+    c2s_tx.send(&C2SMessage::Connect { s2c_tx })?;
+    let (_s2c_rx, msg) = s2c_rx.accept()?;
+    ensure!(
+        matches!(msg, S2CMessage::Connect),
+        "Unexpected initial message"
+    );
+    c2s_tx.send(&C2SMessage::Crash { error })?;
+    Ok(())
+}
+
+/// Makes a failure that happened before the game started visible to the app, which would not
+/// otherwise know of it when the game was launched from Steam.
+fn report_failure(error: &anyhow::Error, c2s_tx: Option<String>) {
+    if let Err(e) = failures::record(error.chain().map(ToString::to_string).collect()) {
+        _ = std::fs::write(
+            "manderrow-wrap-report-crash.txt",
+            format!("Failed to record failure: {e:#}"),
+        );
+    }
+    if let Some(c2s_tx) = c2s_tx {
+        // the app may have been closed since it launched the game
+        _ = send_crash(c2s_tx, format!("{error:#}"));
+    }
+}
+
 pub fn run(args: lexopt::Parser, mode: WrapperMode) -> Result<()> {
     std::panic::set_backtrace_style(std::panic::BacktraceStyle::Full);
     std::panic::set_hook(Box::new(|info| {
@@ -55,11 +240,16 @@ pub fn run(args: lexopt::Parser, mode: WrapperMode) -> Result<()> {
                 DisplayArgList
             ),
         );
+        _ = failures::record(vec![info.to_string()]);
     }));
 
-    std::fs::write("manderrow-wrap-args.txt", DisplayArgList.to_string()).unwrap();
+    _ = std::fs::write("manderrow-wrap-args.txt", DisplayArgList.to_string());
 
-    fn inner1(mut args: lexopt::Parser, mode: WrapperMode) -> Result<()> {
+    fn inner1(
+        mut args: lexopt::Parser,
+        mode: WrapperMode,
+        c2s_tx: &mut Option<String>,
+    ) -> Result<()> {
         use lexopt::Arg::*;
 
         let command_name = match args.next()?.context("Missing required argument BINARY")? {
@@ -69,50 +259,120 @@ pub fn run(args: lexopt::Parser, mode: WrapperMode) -> Result<()> {
 
         let args = args.raw_args()?.collect::<Vec<_>>();
 
-        // TODO: avoid cloning so much. Not just here. All over dealing with arguments.
-        let (manderrow_args, _) = manderrow_args::extract(args.iter().cloned())?;
+        // a log file that can't be created must not stop the game from launching
+        let mut log_file: Box<dyn Write> = match std::fs::File::create("manderrow-wrap.log") {
+            Ok(f) => Box::new(f),
+            Err(_) => Box::new(std::io::sink()),
+        };
 
-        let mut log_file = std::fs::File::create("manderrow-wrap.log").unwrap();
+        _ = writeln!(log_file, "Args: {}", DisplayArgList);
+        _ = writeln!(log_file, "Env: {}", DisplayEnv);
 
-        let mut manderrow_args = lexopt::Parser::from_args(manderrow_args);
+        let config = match WrapperConfig::parse(&args) {
+            Ok(t) => t,
+            Err(e) => {
+                _ = writeln!(
+                    log_file,
+                    "Manderrow is not configured correctly, launching without it: {e:#}"
+                );
+                // strip our arguments if they can be found so that the game doesn't see them
+                let args = match manderrow_args::extract(args.iter().cloned()) {
+                    Ok((_, remaining)) => remaining,
+                    Err(_) => args,
+                };
+                return pass_through(command_name, args);
+            }
+        };
 
-        let mut agent_path = None::<PathBuf>;
-        let mut c2s_tx = None::<String>;
+        _ = writeln!(log_file, "--agent-path: {:?}", config.agent_path);
+        for path in &config.preload {
+            _ = writeln!(log_file, "--preload-library: {path:?}");
+        }
+        _ = writeln!(log_file, "--c2s-tx: {:?}", config.c2s_tx);
+        *c2s_tx = Some(config.c2s_tx);
 
-        while let Some(arg) = manderrow_args.next()? {
-            // NOTE: this can break if an unhandled option's value happens to be `--agent-path` or `--c2s-tx`
-            match arg {
-                lexopt::Arg::Long("agent-path") => {
-                    agent_path = Some(manderrow_args.value()?.into());
-                }
-                lexopt::Arg::Long("c2s-tx") => {
-                    c2s_tx = Some(manderrow_args.value()?.parse()?);
-                }
-                _ => {}
-            }
+        if !config.wrapper.is_empty() {
+            return run_through_wrapper(log_file, mode, &config.wrapper, command_name, args);
         }
 
-        writeln!(log_file, "--agent-path: {:?}", agent_path).unwrap();
-        writeln!(log_file, "--c2s-tx: {:?}", c2s_tx).unwrap();
-        writeln!(log_file, "Args: {}", DisplayArgList).unwrap();
-        writeln!(log_file, "Env: {}", DisplayEnv).unwrap();
+        let args = strip_options(args, WRAPPER_OPTIONS);
 
         match mode {
-            WrapperMode::Injection => {
-                super::wrap_with_injection::inner1(log_file, command_name, args, agent_path)
-            }
+            WrapperMode::Injection => super::wrap_with_injection::inner1(
+                log_file,
+                command_name,
+                args,
+                Some(config.agent_path),
+                config.preload,
+            ),
         }
     }
 
-    match inner1(args, mode) {
+    let mut c2s_tx = None;
+    match inner1(args, mode, &mut c2s_tx) {
         Ok(()) => Ok(()),
         Err(e) => {
-            std::fs::write(
+            _ = std::fs::write(
                 "manderrow-wrap-crash.txt",
                 format!("{e}\nargs: {}", DisplayArgList),
-            )
-            .unwrap();
+            );
+            // the game exiting unsuccessfully is not a failure of the wrapper
+            if e.downcast_ref::<std::process::ExitStatusError>().is_none() {
+                report_failure(&e, c2s_tx);
+            }
             Err(e)
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::OsString;
+
+    use super::{strip_options, WRAPPER_OPTIONS};
+
+    fn args(args: &[&str]) -> Vec<OsString> {
+        args.iter().map(OsString::from).collect()
+    }
+
+    #[test]
+    fn test_strip_options() {
+        assert_eq!(
+            strip_options(
+                args(&[
+                    "-batchmode",
+                    "--preload-library",
+                    "{manderrow",
+                    "--agent-path",
+                    "/agent.so",
+                    "--preload-library",
+                    "/preload.so",
+                    "--insn-load-library",
+                    "/doorstop.so",
+                    "manderrow}",
+                    "{manderrow",
+                    "--preload-library",
+                    "/other.so",
+                    "--wrapper-arg",
+                    "gamemoderun",
+                    "--enable",
+                    "manderrow}",
+                ]),
+                WRAPPER_OPTIONS,
+            ),
+            args(&[
+                "-batchmode",
+                "--preload-library",
+                "{manderrow",
+                "--agent-path",
+                "/agent.so",
+                "--insn-load-library",
+                "/doorstop.so",
+                "manderrow}",
+                "{manderrow",
+                "--enable",
+                "manderrow}",
+            ]),
+        );
+    }
+}
@@ -1,45 +1,115 @@
 use std::ffi::OsString;
 use std::io::Write;
-use std::path::PathBuf;
+use std::io::{BufRead as _, BufReader};
+use std::path::{Path, PathBuf};
 use std::process::Command;
 
 use anyhow::Result;
 
+/// Directories that System Integrity Protection protects on macOS. `dyld` drops `DYLD_*`
+/// variables when starting anything in them, so they never reach a game that is started by a
+/// script that one of them interprets.
+const PROTECTED_DIRS: &[&str] = &["/System", "/usr", "/bin", "/sbin"];
+
+/// Returns the interpreter of the script at `path` if it is in a directory protected by SIP.
+fn protected_interpreter(path: &Path) -> Option<PathBuf> {
+    let file = std::fs::File::open(path).ok()?;
+    let mut line = String::new();
+    BufReader::new(file).read_line(&mut line).ok()?;
+    let interpreter = line.strip_prefix("#!")?.split_whitespace().next()?;
+    PROTECTED_DIRS
+        .iter()
+        .any(|dir| interpreter.starts_with(dir))
+        .then(|| PathBuf::from(interpreter))
+}
+
+/// Runs `command_name` with the agent, followed by the libraries in `preload`, injected into it.
 pub fn inner1(
-    mut log_file: std::fs::File,
+    mut log_file: impl Write,
     command_name: OsString,
     args: Vec<OsString>,
     agent_path: Option<PathBuf>,
+    preload: Vec<PathBuf>,
 ) -> Result<()> {
     let mut command = Command::new(&command_name);
+
+    let mut libraries = agent_path.into_iter().chain(preload).collect::<Vec<_>>();
+
+    if cfg!(target_os = "linux") && !libraries.is_empty() {
+        use crate::stores::steam::runtime;
+
+        if runtime::is_inside_container() {
+            for path in &mut libraries {
+                let translated = runtime::host_path_in_container(path).into_owned();
+                _ = writeln!(
+                    log_file,
+                    "Running inside the Steam Linux Runtime, using {translated:?}"
+                );
+                *path = translated;
+            }
+        } else if runtime::command_enters_container(&command_name, &args) {
+            let mut current = std::env::var_os(runtime::FILESYSTEMS_RO_VAR);
+            for path in &libraries {
+                let dir = path.parent().unwrap_or(path);
+                match runtime::expose_to_container(current.as_deref(), dir) {
+                    Ok(value) => current = Some(value),
+                    Err(e) => _ = writeln!(log_file, "{e}"),
+                }
+            }
+            if let Some(value) = current {
+                _ = writeln!(
+                    log_file,
+                    "Launching into the Steam Linux Runtime, setting {}={value:?}",
+                    runtime::FILESYSTEMS_RO_VAR
+                );
+                command.env(runtime::FILESYSTEMS_RO_VAR, value);
+            }
+        }
+    }
+
     command.args(args);
 
-    if let Some(agent_path) = agent_path {
-        if cfg!(unix) {
-            const VAR: &str = if cfg!(target_os = "macos") {
-                "DYLD_INSERT_LIBRARIES"
-            } else {
-                "LD_PRELOAD"
-            };
-            let base = std::env::var_os(VAR).unwrap_or_else(OsString::new);
-            let mut buf = agent_path.into_os_string();
-            if !base.is_empty() {
+    if cfg!(unix) && !libraries.is_empty() {
+        const VAR: &str = if cfg!(target_os = "macos") {
+            "DYLD_INSERT_LIBRARIES"
+        } else {
+            "LD_PRELOAD"
+        };
+        let mut buf = OsString::new();
+        for path in libraries {
+            if !buf.is_empty() {
                 buf.push(":");
-                buf.push(base);
             }
+            buf.push(path);
+        }
+        let base = std::env::var_os(VAR).unwrap_or_else(OsString::new);
+        if !base.is_empty() {
+            buf.push(":");
+            buf.push(base);
+        }
 
-            writeln!(log_file, "Injecting {VAR} {buf:?}").unwrap();
+        _ = writeln!(log_file, "Injecting {VAR} {buf:?}");
 
-            command.env(VAR, buf);
+        if cfg!(target_os = "macos") {
+            if let Some(interpreter) = protected_interpreter(Path::new(&command_name)) {
+                _ = writeln!(
+                    log_file,
+                    "{command_name:?} is run by {interpreter:?}, which is protected by System \
+                     Integrity Protection. {VAR} will be dropped before the game is started."
+                );
+            }
         }
+
+        command.env(VAR, buf);
     }
 
     let mut child = match command.spawn() {
         Ok(t) => t,
         Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
-            return Err(
-                anyhow::Error::new(e).context(format!("Could not locate command {command_name:?}"))
-            )
+            return Err(anyhow::Error::new(e).context(format!(
+                "Could not locate command {:?}",
+                command.get_program()
+            )))
         }
         Err(e) => return Err(e.into()),
     };
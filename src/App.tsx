@@ -9,6 +9,7 @@ import { Show, createResource, lazy, onCleanup, onMount } from "solid-js";
 
 import { relaunch } from "./api/app";
 import { coreResources } from "./globals";
+import { isWebUrl } from "./utils/utils";
 
 import ErrorDialog from "./components/ErrorDialog";
 import TitleBar from "./components/TitleBar.tsx";
@@ -28,11 +29,13 @@ export default function App() {
 
   function onLinkClick(event: MouseEvent) {
     const link = getLink(event);
-    if (link == null || link.target === "_blank") return;
+    if (link == null) return;
 
     if (link.href.startsWith(`${location.protocol}//${location.host}`)) return;
 
+    // including links with target="_blank", which the webview would otherwise open itself
     event.preventDefault();
+    if (!isWebUrl(link.href)) return;
     openUrl(link.href).catch(() => alert(`Failed to open link: ${link.href}`));
   }
 
@@ -41,7 +44,7 @@ export default function App() {
     if (link == null) return;
 
     event.preventDefault();
-    if (event.button !== 2) {
+    if (event.button !== 2 && isWebUrl(link.href)) {
       // Link was not right clicked, likely middle click
       openUrl(link.href).catch(() => alert(`Failed to open link: ${link.href}`));
     }
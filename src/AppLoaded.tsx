@@ -4,6 +4,7 @@ import ErrorPage from "./views/error/Error";
 import Profile from "./views/profile/Profile";
 import Settings from "./views/settings/Settings";
 import ErrorBoundary from "./components/ErrorBoundary";
+import WrapperFailuresDialog from "./components/WrapperFailuresDialog";
 import { onMount } from "solid-js";
 import { invoke } from "@tauri-apps/api/core";
 
@@ -18,6 +19,7 @@ export default function AppLoaded() {
         <Route path="/settings" component={Settings} />
         <Route path="*path" component={ErrorPage} />
       </Router>
+      <WrapperFailuresDialog />
     </ErrorBoundary>
   );
 }
@@ -1,4 +1,5 @@
 import { Channel, invoke } from "@tauri-apps/api/core";
+import { listen, UnlistenFn } from "@tauri-apps/api/event";
 import { Game, ModListing, ModMetadata, ModPackage, ModVersion } from "../types";
 import { invokeWithListener, Listener, TaskEvent, Id as TaskId } from "./tasks";
 import { promiseWithErrorStack } from "../utils/utils";
@@ -31,6 +32,15 @@ export class AbortedError extends Error {
   }
 }
 
+/**
+ * Thrown by commands that would change a profile when Manderrow was started with `--read-only`.
+ */
+export class ReadOnlyError extends Error {
+  constructor() {
+    super("Profiles are read-only");
+  }
+}
+
 export function wrapInvoke<T>(f: () => Promise<T>): Promise<T> {
   return promiseWithErrorStack(
     (async () => {
@@ -40,6 +50,8 @@ export function wrapInvoke<T>(f: () => Promise<T>): Promise<T> {
         console.error("Error in invoke", e);
         if (e === "Aborted") {
           throw new AbortedError();
+        } else if (e === "ReadOnly") {
+          throw new ReadOnlyError();
         } else if (e instanceof Object && "Error" in e) {
           throw new NativeError(e.Error.messages, e.Error.backtrace);
         } else {
@@ -73,10 +85,75 @@ export async function getGameModDownloads(): Promise<{ [key: string]: number }>
   return await wrapInvoke(() => invoke("get_game_mods_downloads", {}));
 }
 
+export interface GameStatsSnapshot {
+  taken_at: number;
+  mod_count: number;
+  mod_downloads: number;
+}
+
+export interface GameTrend {
+  latest: GameStatsSnapshot;
+  downloads_delta_week: number | null;
+  mod_count_delta_week: number | null;
+}
+
+export async function getGameStatsTrends(): Promise<{ [thunderstoreId: string]: GameTrend }> {
+  return await wrapInvoke(() => invoke("get_game_stats_trends", {}));
+}
+
 export async function fetchModIndex(game: string, options: { refresh: boolean }, listener: (event: TaskEvent) => void) {
   await invokeWithListener(listener, (taskId) => invoke("fetch_mod_index", { game, ...options, taskId }));
 }
 
+export interface ModIndexStatus {
+  /** Milliseconds since the Unix epoch, or `null` if the index has not been fetched since Manderrow started. */
+  fetchedAt: number | null;
+  chunkCount: number;
+  entryCount: number;
+  /** The number of chunks that failed to fetch, whose mods are missing. They are retried on the next fetch. */
+  failedChunkCount: number;
+  /** The error of the last attempt to fetch the index or one of its chunks. */
+  lastError: string | null;
+  refreshing: boolean;
+}
+
+export async function getModIndexStatus(game: string): Promise<ModIndexStatus> {
+  return await wrapInvoke(() => invoke("get_mod_index_status", { game }));
+}
+
+export interface ChunkDiagnostics {
+  url: string;
+  jsonBytes: number;
+  memoryBytes: number;
+  internedStrings: number;
+  internedBytes: number;
+  averageUses: number;
+  singleUseStrings: number;
+  /** Milliseconds between starting the fetch and the chunk's task starting. */
+  spawnMs: number;
+  fetchMs: number;
+  decodeMs: number;
+  encodeMs: number;
+}
+
+export interface IndexDiagnostics {
+  /** Shared between games, so only accurate if no other index was fetched at the same time. */
+  inlineVersionCount: number;
+  outOfLineVersionCount: number;
+  chunks: ChunkDiagnostics[];
+}
+
+export async function getIndexDiagnostics(game: string): Promise<IndexDiagnostics> {
+  return await wrapInvoke(() => invoke("get_index_diagnostics", { game }));
+}
+
+/**
+ * @returns the mod's icon if it has been downloaded in the background, or an empty buffer otherwise
+ */
+export async function getCachedModIcon(owner: string, name: string, version: string): Promise<ArrayBuffer> {
+  return await wrapInvoke(() => invoke("get_cached_mod_icon", { owner, name, version }));
+}
+
 export enum ModSortColumn {
   Relevance = "relevance",
   Downloads = "downloads",
@@ -90,15 +167,70 @@ export interface SortOption<C> {
   descending: boolean;
 }
 
-export async function countModIndex(game: string, query: string): Promise<number> {
-  return await wrapInvoke(() => invoke("count_mod_index", { game, query }));
+/** Narrows down the mods that a query matches. */
+export interface ModFilter {
+  /** Only mods whose latest version depends on the package with this full name, like `BepInEx-BepInExPack`. */
+  dependsOn?: string;
+  /** Only mods in at least one of these categories, unless empty. */
+  categories?: string[];
+  /** No mods in any of these categories. */
+  excludeCategories?: string[];
+  hideDeprecated?: boolean;
+  hideNsfw?: boolean;
+  /** Only mods with at least this many downloads across all of their versions. */
+  minDownloads?: number;
+  /** Only mods whose latest version was published within this many days. */
+  updatedWithinDays?: number;
+}
+
+export interface ChangedMod {
+  owner: string;
+  name: string;
+}
+
+export interface UpdatedMod extends ChangedMod {
+  from: string;
+  to: string;
+}
+
+/** What changed in a game's mod index with a fetch. Each list is sorted by owner and name. */
+export interface ModIndexChanges {
+  game: string;
+  /** Milliseconds since the Unix epoch. */
+  fetched_at: number;
+  new_mods: ChangedMod[];
+  /** Mods whose latest version changed. */
+  updated_mods: UpdatedMod[];
+  deprecated_mods: ChangedMod[];
+}
+
+/**
+ * @returns what changed in the game's mod index with its last fetch, or `null` if it hasn't been
+ * compared against an earlier one
+ */
+export async function getModIndexChanges(game: string): Promise<ModIndexChanges | null> {
+  return await wrapInvoke(() => invoke("get_mod_index_changes", { game }));
+}
+
+/** Calls `listener` with the changes found by each fetch of any game's mod index. */
+export async function listenModIndexChanges(listener: (changes: ModIndexChanges) => void): Promise<UnlistenFn> {
+  return await listen<ModIndexChanges>("mod_index_changes", (event) => listener(event.payload));
+}
+
+/** The categories that the game's mods are in, sorted by name. */
+export async function getModIndexCategories(game: string): Promise<string[]> {
+  return await wrapInvoke(() => invoke("get_mod_index_categories", { game }));
+}
+
+export async function countModIndex(game: string, query: string, filter?: ModFilter): Promise<number> {
+  return await wrapInvoke(() => invoke("count_mod_index", { game, query, filter }));
 }
 
 export async function queryModIndex(
   game: string,
   query: string,
   sort: readonly SortOption<ModSortColumn>[],
-  options: { skip?: number; limit?: Exclude<number, 0> },
+  options: { filter?: ModFilter; skip?: number; limit?: Exclude<number, 0> },
 ): Promise<{
   mods: ModListing[];
   count: number;
@@ -127,16 +259,83 @@ export async function getFromModIndex<const ModIds extends readonly ModId[]>(
   return await wrapInvoke(() => invoke("get_from_mod_index", { game, modIds }));
 }
 
+export interface ModSizes {
+  latest: number;
+  total: number;
+  /** A unit that suits {@link latest} for display. */
+  unit: "B" | "KB" | "MB" | "GB" | "TB";
+  versions: { version_number: string; file_size: number }[];
+}
+
+export async function getModSizes(game: string, modId: ModId): Promise<ModSizes | null> {
+  return await wrapInvoke(() => invoke("get_mod_sizes", { game, modId }));
+}
+
+export interface VersionListing {
+  version_number: string;
+  date_created: string;
+  /** The size of the version's archive in bytes. */
+  file_size: number;
+  downloads: number;
+  is_active: boolean;
+}
+
+/**
+ * @returns the versions of the mod with their release dates and sizes, latest first, or `null` if the mod is not in
+ *          the index
+ */
+export async function getModVersionList(game: string, modId: ModId): Promise<VersionListing[] | null> {
+  return await wrapInvoke(() => invoke("get_mod_version_list", { game, modId }));
+}
+
 export async function getPreferredLocales(): Promise<string[]> {
   return await wrapInvoke(() => invoke("get_preferred_locales"));
 }
 
 export interface Profile {
+  schema_version: number;
   name: string;
   game: string;
   pinned: boolean;
+  launch: LaunchOptions;
+  /** Replacements of mods' files that were interrupted by a failed installation. See {@link repairProfileMod}. */
+  incomplete_replacements?: IncompleteReplacement[];
+}
+
+export interface IncompleteReplacement {
+  /** The mod, as `Owner-Name`. */
+  mod: string;
+  /** Relative to the profile. */
+  target: string;
+  /** Where the original was moved aside to, relative to the profile. */
+  deletion_path: string;
+}
+
+/**
+ * `finished` if the new files were in place and the original was deleted, `rolled_back` if the
+ * original was moved back, or `nothing` if the original was already gone.
+ */
+export type Repair = "finished" | "rolled_back" | "nothing";
+
+export type LoaderLogLevel = "error" | "warning" | "info" | "debug" | "all";
+
+export interface LaunchOptions {
+  show_console: boolean;
+  log_level: LoaderLogLevel;
+  /** If set, the game is launched by running this executable directly instead of through a store. */
+  game_executable?: string;
+  /** Missing for profiles that were saved before it could be set. */
+  loader_version?: LoaderVersion;
+  /** Commands to run the game through, like `gamemoderun mangohud`. If unset, the ones from the settings are used. */
+  wrapper?: string;
 }
 
+/**
+ * The build of the mod loader to launch the profile with. Releases are versions like `5.4.23.2+build.20`, as returned
+ * by {@link getLoaderVersions}.
+ */
+export type LoaderVersion = "stable" | "ci" | { release: string };
+
 export interface ProfileWithId extends Profile {
   id: string;
 }
@@ -149,6 +348,33 @@ export async function createProfile(game: string, name: string): Promise<string>
   return await wrapInvoke(() => invoke("create_profile", { game, name }));
 }
 
+/**
+ * Adds a shortcut that opens the profile to every Steam user's library, with the game's artwork.
+ * Fails if Steam is running, since it would overwrite the shortcut when it exits.
+ *
+ * @returns the app id of the shortcut
+ */
+export async function createProfileSteamShortcut(id: string): Promise<number> {
+  return await wrapInvoke(() => invoke("create_profile_steam_shortcut", { id }));
+}
+
+/**
+ * Takes the profile that Manderrow was last opened with through a Steam shortcut, so that it is only opened once.
+ *
+ * @returns the id of the profile, if any
+ */
+export async function takeRequestedProfile(): Promise<string | null> {
+  return await wrapInvoke(() => invoke("take_requested_profile", {}));
+}
+
+/**
+ * Calls `listener` whenever a Steam shortcut is opened while Manderrow is already running. The profile can then be
+ * taken with {@link takeRequestedProfile}.
+ */
+export async function listenRequestedProfile(listener: () => void): Promise<UnlistenFn> {
+  return await listen<string>("requested_profile", () => listener());
+}
+
 export async function overwriteProfileMetadata(id: string, metadata: Profile): Promise<void> {
   return await wrapInvoke(() => invoke("overwrite_profile_metadata", { id, metadata }));
 }
@@ -157,6 +383,28 @@ export async function deleteProfile(id: string): Promise<void> {
   return await wrapInvoke(() => invoke("delete_profile", { id }));
 }
 
+/**
+ * @returns the BepInEx releases that a profile's loader can be pinned to, newest first
+ */
+export async function getLoaderVersions(): Promise<string[]> {
+  return await wrapInvoke(() => invoke("get_loader_versions", {}));
+}
+
+/**
+ * Pins the profile's mod loader to `version`. Fails if the version doesn't exist or the game's loader can't be pinned.
+ */
+export async function setProfileLoaderVersion(id: string, version: LoaderVersion): Promise<void> {
+  return await wrapInvoke(() => invoke("set_profile_loader_version", { id, version }));
+}
+
+/**
+ * @returns whether Manderrow was started with `--read-only`, in which case changing profiles fails with
+ *          {@link ReadOnlyError}
+ */
+export async function isReadOnly(): Promise<boolean> {
+  return await wrapInvoke(() => invoke("is_read_only", {}));
+}
+
 export async function getProfileMods(id: string): Promise<ModPackage[]> {
   return await wrapInvoke(() => invoke("get_profile_mods", { id }));
 }
@@ -166,14 +414,331 @@ export async function installProfileMod(
   mod: ModMetadata,
   version: ModVersion,
   listener: Listener,
-): Promise<void> {
-  await invokeWithListener(listener, (taskId) => invoke("install_profile_mod", { id, mod, version, taskId }));
+  allowOverlaps: boolean = false,
+): Promise<CompatWarning[]> {
+  return await invokeWithListener(listener, (taskId) =>
+    invoke("install_profile_mod", { id, mod, version, allowOverlaps, taskId }),
+  );
+}
+
+/**
+ * Installs the installed version of a mod again. With `forceDownload`, the mod is downloaded even if it is cached.
+ */
+export async function reinstallProfileMod(
+  id: string,
+  owner: string,
+  name: string,
+  forceDownload: boolean,
+  listener: Listener,
+): Promise<CompatWarning[]> {
+  return await invokeWithListener(listener, (taskId) =>
+    invoke("reinstall_profile_mod", { id, owner, name, forceDownload, taskId }),
+  );
+}
+
+export type ModOp =
+  | { op: "install"; owner: string; name: string; version: string }
+  /** Installs the latest version of an installed mod. */
+  | { op: "update"; owner: string; name: string }
+  | { op: "uninstall"; owner: string; name: string }
+  | { op: "enable"; owner: string; name: string }
+  | { op: "disable"; owner: string; name: string };
+
+/**
+ * Applies the operations to the profile's mods as one task. If any install or update fails, nothing is changed.
+ */
+export async function batchProfileOps(
+  id: string,
+  ops: ModOp[],
+  listener: Listener,
+  allowOverlaps: boolean = false,
+): Promise<CompatWarning[]> {
+  return await invokeWithListener(listener, (taskId) =>
+    invoke("batch_profile_ops", { id, ops, allowOverlaps, taskId }),
+  );
+}
+
+export interface FileOverlap {
+  path: string;
+  /** The other mod that ships the file, as `Owner-Name`. */
+  other: string;
+  identical: boolean;
+}
+
+/** Files shipped by more than one of the profile's mods, by mod as `Owner-Name`. */
+export async function getProfileFileOverlaps(id: string): Promise<Record<string, FileOverlap[]>> {
+  return await wrapInvoke(() => invoke("get_profile_file_overlaps", { id }));
+}
+
+export interface PluginInfo {
+  guid: string;
+  name: string;
+  version: string;
+  /** The full name of the plugin's class. */
+  type_name: string;
+  /** The assembly that contains the plugin, relative to the mod's folder. */
+  path: string;
+}
+
+export interface ProfilePlugins {
+  /** The plugins in each mod, by mod as `Owner-Name`. */
+  plugins: Record<string, PluginInfo[]>;
+  /** Plugin GUIDs that are shipped by more than one mod, of which BepInEx only loads one. */
+  duplicates: { guid: string; mods: string[] }[];
+}
+
+/**
+ * Reads the BepInEx plugins that each of the profile's mods contains from their assemblies.
+ */
+export async function getProfilePlugins(id: string): Promise<ProfilePlugins> {
+  return await wrapInvoke(() => invoke("get_profile_plugins", { id }));
+}
+
+/**
+ * @returns the README or CHANGELOG stored in the folder of a mod installed in the profile, or `null` if there is none.
+ */
+export async function getProfileModDocs(
+  id: string,
+  owner: string,
+  name: string,
+  endpoint: "readme" | "changelog",
+): Promise<string | null> {
+  return await wrapInvoke(() => invoke("get_profile_mod_docs", { id, owner, name, endpoint }));
+}
+
+/**
+ * @returns the folder of a mod installed in the profile
+ */
+export async function getProfileModPath(id: string, owner: string, name: string): Promise<string> {
+  return await wrapInvoke(() => invoke("get_profile_mod_path", { id, owner, name }));
+}
+
+export interface ProfilePaths {
+  profile: string;
+  mods: string;
+  config: string;
+  /** The directory that the profile's game is installed in, or `null` if it isn't installed. */
+  game_install: string | null;
+}
+
+export async function getProfilePaths(id: string): Promise<ProfilePaths> {
+  return await wrapInvoke(() => invoke("get_profile_paths", { id }));
+}
+
+/**
+ * Runs one of the executables of a tool that is installed in the profile.
+ */
+export async function runProfileTool(id: string, owner: string, name: string, executable: string): Promise<void> {
+  return await wrapInvoke(() => invoke("run_profile_tool", { id, owner, name, executable }));
+}
+
+/**
+ * Links a folder, like the output folder of a mod's build, into the profile as a mod that is copied again whenever
+ * the folder changes. Linked mods are not updated from Thunderstore or included in exports.
+ *
+ * @returns the name of the mod, whose owner is `dev`
+ */
+export async function linkDevMod(id: string, source: string): Promise<string> {
+  return await wrapInvoke(() => invoke("link_dev_mod", { id, source }));
+}
+
+export interface DevModSynced {
+  profile: string;
+  /** The mod, as `Owner-Name`. */
+  mod: string;
+}
+
+/**
+ * Calls `listener` whenever a linked mod is copied into its profile again after its folder changed.
+ */
+export async function listenDevModSynced(listener: (event: DevModSynced) => void): Promise<UnlistenFn> {
+  return await listen<DevModSynced>("dev_mod_synced", (event) => listener(event.payload));
+}
+
+/**
+ * Finishes or rolls back the replacements of the mod's files that a failed installation left
+ * incomplete. This also happens automatically when the profile is launched.
+ */
+export async function repairProfileMod(
+  id: string,
+  owner: string,
+  name: string,
+): Promise<[IncompleteReplacement, Repair][]> {
+  return await wrapInvoke(() => invoke("repair_profile_mod", { id, owner, name }));
 }
 
 export async function uninstallProfileMod(id: string, owner: string, name: string): Promise<void> {
   return await wrapInvoke(() => invoke("uninstall_profile_mod", { id, owner, name }));
 }
 
+/** Values, by key, by section. */
+export type ConfigValues = { [section: string]: { [key: string]: string } };
+
+/**
+ * @returns the user's overrides for the profile's config files, by file name
+ */
+export async function getProfileConfigOverrides(id: string): Promise<{ [file: string]: ConfigValues }> {
+  return await wrapInvoke(() => invoke("get_profile_config_overrides", { id }));
+}
+
+/**
+ * Sets an override for a value in one of the profile's config files, or removes it if `value` is `null`.
+ */
+export async function setProfileConfigOverride(
+  id: string,
+  file: string,
+  section: string,
+  key: string,
+  value: string | null,
+): Promise<void> {
+  return await wrapInvoke(() => invoke("set_profile_config_override", { id, file, section, key, value }));
+}
+
+export type ConfigPathSegment = string | number;
+
+export type ConfigChange =
+  | { type: "set"; value: unknown }
+  | { type: "append"; value: unknown }
+  | { type: "remove" };
+
+export interface ConfigPatch {
+  path: ConfigPathSegment[];
+  change: ConfigChange;
+}
+
+/**
+ * Applies patches to one of the profile's JSON config files, leaving the rest of the file, including its formatting,
+ * as it was.
+ */
+export async function updateProfileConfig(id: string, file: string, patches: ConfigPatch[]): Promise<void> {
+  return await wrapInvoke(() => invoke("update_profile_config", { id, file, patches }));
+}
+
+export interface ConfigMatch {
+  /** The file, relative to the profile's config folder. */
+  file: string;
+  section: string;
+  key: string;
+  /** The value, shortened if it is long. */
+  value: string;
+}
+
+/**
+ * Finds the values in the profile's config files whose section, key, or value contains `query`, ignoring case.
+ */
+export async function searchProfileConfigs(id: string, query: string): Promise<ConfigMatch[]> {
+  return await wrapInvoke(() => invoke("search_profile_configs", { id, query }));
+}
+
+export interface ProfileConfigChanged {
+  profile: string;
+  /** The files that changed, relative to the profile's config folder. */
+  files: string[];
+}
+
+/**
+ * Calls `listener` whenever a mod changes the config files of a profile whose game is running.
+ */
+export async function listenProfileConfigChanged(
+  listener: (event: ProfileConfigChanged) => void,
+): Promise<UnlistenFn> {
+  return await listen<ProfileConfigChanged>("profile_config_changed", (event) => listener(event.payload));
+}
+
+export type ConfigSnapshotReason = "mod_install" | "config_edit" | "restore";
+
+export interface ConfigSnapshot {
+  id: string;
+  /** When the snapshot was taken, in milliseconds since the Unix epoch. */
+  created_at: number;
+  reason: ConfigSnapshotReason;
+}
+
+export interface ConfigValueChange {
+  section: string;
+  key: string;
+  /** The value in the snapshot, or `null` if it wasn't set. */
+  snapshot: string | null;
+  /** The current value, or `null` if it isn't set. */
+  current: string | null;
+}
+
+export interface ConfigFileDiff {
+  /** The path of the file, relative to the profile's config folder. */
+  file: string;
+  status: "Deleted" | "Created" | "Modified";
+  /** The values that changed, if both versions of the file are INI-style configs. */
+  values: ConfigValueChange[];
+}
+
+/**
+ * @returns the snapshots of the profile's config folder, newest first
+ */
+export async function getProfileConfigSnapshots(id: string): Promise<ConfigSnapshot[]> {
+  return await wrapInvoke(() => invoke("get_profile_config_snapshots", { id }));
+}
+
+/**
+ * Compares a snapshot of the profile's config folder with its current state.
+ */
+export async function diffProfileConfigSnapshot(id: string, snapshot: string): Promise<ConfigFileDiff[]> {
+  return await wrapInvoke(() => invoke("diff_profile_config_snapshot", { id, snapshot }));
+}
+
+/**
+ * Replaces the profile's config folder with a snapshot of it. The current state is snapshotted first.
+ */
+export async function restoreProfileConfigSnapshot(id: string, snapshot: string): Promise<void> {
+  return await wrapInvoke(() => invoke("restore_profile_config_snapshot", { id, snapshot }));
+}
+
+export interface ExportedProfile {
+  /** Where the archive was written, if a destination was given. */
+  path?: string;
+  /** The Thunderstore profile code, if the profile was uploaded. */
+  code?: string;
+}
+
+/**
+ * Exports a profile in the r2modman format.
+ *
+ * @param destination if provided, the archive is written to this path
+ * @param upload whether to upload the profile to Thunderstore to get a shareable code
+ */
+export async function exportProfile(
+  id: string,
+  options: { destination?: string; upload: boolean },
+): Promise<ExportedProfile> {
+  return await wrapInvoke(() => invoke("export_profile", { id, ...options }));
+}
+
+/**
+ * Writes the profile to `destination` as a portable archive, which contains its mod list and config files, but not
+ * the mods themselves.
+ */
+export async function exportProfileToFile(id: string, destination: string): Promise<void> {
+  return await wrapInvoke(() => invoke("export_profile_to_file", { id, destination }));
+}
+
+export async function importProfileFromFile(
+  path: string,
+  modProgressChannel: Channel<ModProgressRegistration>,
+  listener: Listener,
+): Promise<ImportReport> {
+  return await invokeWithListener(listener, (taskId) =>
+    invoke("import_profile_from_file", { path, modProgressChannel, taskId }),
+  );
+}
+
+export type ModListFormat = "markdown" | "csv" | "text";
+
+/**
+ * @returns a list of the mods installed in the profile, with their versions and links to them, for sharing
+ */
+export async function exportModList(id: string, format: ModListFormat): Promise<string> {
+  return await wrapInvoke(() => invoke("export_mod_list", { id, format }));
+}
+
 export interface ModSpec {
   type: "Online";
   url: string;
@@ -212,14 +777,140 @@ export interface ModProgressRegistration {
   task: TaskId;
 }
 
+export type ModImportResult = { name: string; version: string } & (
+  | { status: "Installed" }
+  | { status: "Failed"; error: string }
+);
+
+export interface ImportReport {
+  profile_id: string;
+  mods: ModImportResult[];
+  compat_warnings: CompatWarning[];
+}
+
 export async function importModpackFromThunderstoreCode(
   thunderstoreId: string,
   game: string,
   profileId: string | undefined,
   modProgressChannel: Channel<ModProgressRegistration>,
   listener: Listener,
-): Promise<string> {
+): Promise<ImportReport> {
   return await invokeWithListener(listener, (taskId) =>
     invoke("import_modpack_from_thunderstore_code", { thunderstoreId, game, profileId, modProgressChannel, taskId }),
   );
 }
+
+export interface LocalR2modmanProfile {
+  manager: "r2modman" | "thunderstore_mod_manager";
+  /** The id of the game, or `null` if the game is not known to Manderrow. */
+  game: string | null;
+  name: string;
+  path: string;
+  mod_count: number;
+}
+
+/**
+ * Lists the profiles of r2modman and Thunderstore Mod Manager that are stored on this computer.
+ */
+export async function scanLocalR2modmanProfiles(): Promise<LocalR2modmanProfile[]> {
+  return await wrapInvoke(() => invoke("scan_local_r2modman_profiles", {}));
+}
+
+/**
+ * Creates a new profile from a local r2modman or Thunderstore Mod Manager profile, installing its mods and copying
+ * its config files.
+ */
+export async function importLocalR2modmanProfile(
+  path: string,
+  game: string,
+  modProgressChannel: Channel<ModProgressRegistration>,
+  listener: Listener,
+): Promise<ImportReport> {
+  return await invokeWithListener(listener, (taskId) =>
+    invoke("import_local_r2modman_profile", { path, game, modProgressChannel, taskId }),
+  );
+}
+
+export interface DetectedMod {
+  full_name: string;
+  /** From the mod's manifest.json, or the latest version if it has none. */
+  version: string;
+  /** Relative to the game directory. */
+  path: string;
+  detection: "folder_name" | "manifest" | "name";
+}
+
+export interface GameDirScan {
+  path: string;
+  mods: DetectedMod[];
+  /** The files and folders that no mod was recognized in. */
+  unrecognized: string[];
+}
+
+/**
+ * Recognizes the mods that were installed by hand into the game's directory.
+ *
+ * @param path the game directory, if not where the game is installed
+ */
+export async function scanGameDir(game: string, path: string | undefined, listener: Listener): Promise<GameDirScan> {
+  return await invokeWithListener(listener, (taskId) => invoke("scan_game_dir", { game, path, taskId }));
+}
+
+/**
+ * Creates a new profile with the mods that the user picked from the result of {@link scanGameDir}, copying the config
+ * files from the game's directory.
+ */
+export async function importGameDir(
+  game: string,
+  path: string | undefined,
+  name: string,
+  mods: DetectedMod[],
+  modProgressChannel: Channel<ModProgressRegistration>,
+  listener: Listener,
+): Promise<ImportReport> {
+  return await invokeWithListener(listener, (taskId) =>
+    invoke("import_game_dir", { game, path, name, mods, modProgressChannel, taskId }),
+  );
+}
+
+export type CompatNoteKind =
+  | { kind: "conflicts_with"; other: string }
+  | { kind: "needs_config"; file: string; section: string; key: string; value: string }
+  | { kind: "note" };
+
+export type CompatNote = {
+  id: string;
+  /** The mod that the note is about, as `Owner-Name`. */
+  mod: string;
+  message?: string;
+} & CompatNoteKind;
+
+export type CompatWarning = CompatNote & { source: "community" | "user" };
+
+/**
+ * Checks the mods installed in the profile against the compatibility notes for its game.
+ */
+export async function getProfileCompatReport(id: string): Promise<CompatWarning[]> {
+  return await wrapInvoke(() => invoke("get_profile_compat_report", { id }));
+}
+
+export async function getCompatNotes(game: string): Promise<CompatNote[]> {
+  return await wrapInvoke(() => invoke("get_compat_notes", { game }));
+}
+
+export async function getUserCompatNotes(game: string): Promise<CompatNote[]> {
+  return await wrapInvoke(() => invoke("get_user_compat_notes", { game }));
+}
+
+export async function addUserCompatNote(
+  game: string,
+  mod: string,
+  kind: CompatNoteKind,
+  message?: string,
+): Promise<CompatNote> {
+  return await wrapInvoke(() => invoke("add_user_compat_note", { game, mod, kind, message }));
+}
+
+export async function removeUserCompatNote(game: string, id: string): Promise<boolean> {
+  return await wrapInvoke(() => invoke("remove_user_compat_note", { game, id }));
+}
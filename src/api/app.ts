@@ -14,6 +14,14 @@ export function minimize(): Promise<void> {
   return wrapInvoke(() => invoke("minimize"));
 }
 
+/**
+ * Shows the path in the platform's file manager. Folders are opened, while files are selected in the folder that
+ * contains them.
+ */
+export function openInFileManager(path: string): Promise<void> {
+  return wrapInvoke(() => invoke("open_in_file_manager", { path }));
+}
+
 export function relaunch(): Promise<never> {
   return wrapInvoke(() => invoke("relaunch"));
 }
@@ -25,3 +33,20 @@ export function setMaximized(desiredState?: boolean): Promise<never> {
 export function startDragging(): Promise<void> {
   return wrapInvoke(() => invoke("start_dragging"));
 }
+
+/** A section of app startup, with times in milliseconds. */
+export interface StartupSection {
+  name: string;
+  start_ms: number;
+  /** Zero for sections that only mark a point in time. */
+  duration_ms: number;
+  /** Whether the section ran in parallel with the others. */
+  deferred: boolean;
+}
+
+/**
+ * @returns how long each section of startup took, in the order that they finished
+ */
+export function getStartupTimings(): Promise<StartupSection[]> {
+  return wrapInvoke(() => invoke("get_startup_timings"));
+}
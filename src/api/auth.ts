@@ -0,0 +1,27 @@
+import { invoke } from "@tauri-apps/api/core";
+import { wrapInvoke } from "./api";
+
+export type Provider = "github" | "discord";
+
+export interface Account {
+  username: string;
+}
+
+export function getAccount(): Promise<Account | null> {
+  return wrapInvoke(() => invoke("thunderstore_get_account"));
+}
+
+/**
+ * Opens the provider's login page in the user's browser and waits for them to log in.
+ */
+export function login(provider: Provider): Promise<Account> {
+  return wrapInvoke(() => invoke("thunderstore_login", { provider }));
+}
+
+export function loginWithToken(sessionToken: string): Promise<Account> {
+  return wrapInvoke(() => invoke("thunderstore_login_with_token", { sessionToken }));
+}
+
+export function logout(): Promise<void> {
+  return wrapInvoke(() => invoke("thunderstore_logout"));
+}
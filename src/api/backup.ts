@@ -0,0 +1,12 @@
+import { invoke } from "@tauri-apps/api/core";
+import { invokeWithListener, TaskEvent } from "./tasks";
+
+/** Backs up profiles, settings, and other data to a zip archive at `targetPath`. */
+export async function backupAllData(targetPath: string, listener: (event: TaskEvent) => void) {
+  await invokeWithListener(listener, (taskId) => invoke("backup_all_data", { targetPath, taskId }));
+}
+
+/** Restores a backup made by {@link backupAllData}. Manderrow should be relaunched afterwards. */
+export async function restoreAllData(archive: string, listener: (event: TaskEvent) => void) {
+  await invokeWithListener(listener, (taskId) => invoke("restore_all_data", { archive, taskId }));
+}
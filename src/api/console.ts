@@ -1,6 +1,16 @@
 import { Accessor, Setter, createSignal } from "solid-js";
 
-import { C2SMessage, DoctorReport, allocateIpcConnection, getIpcConnections } from "./ipc";
+import {
+  C2SMessage,
+  CrashReported,
+  DoctorReport,
+  ResourceSample,
+  Responsiveness,
+  allocateIpcConnection,
+  getConnectionHistory,
+  getIpcConnections,
+  getPendingDoctorPrompts,
+} from "./ipc";
 import { listen } from "@tauri-apps/api/event";
 
 export type ConnectionStatus = "connecting" | "connected" | "disconnected";
@@ -21,16 +31,48 @@ function getOrInitConnection(connId: number): ConsoleConnection {
   return conn;
 }
 
+const HISTORY_PAGE_SIZE = 1000;
+
 (async () => {
-  for (const conn of await getIpcConnections()) {
-    getOrInitConnection(conn);
+  for (const { id: connId } of await getIpcConnections()) {
+    const conn = getOrInitConnection(connId);
+    // fill the console again with the messages sent before the window was reloaded
+    for (let offset = 0; ; ) {
+      const page = await getConnectionHistory(connId, offset, HISTORY_PAGE_SIZE);
+      for (const entry of page) {
+        conn.handleEvent({ ...entry, connId });
+      }
+      if (page.length < HISTORY_PAGE_SIZE) break;
+      offset = page[page.length - 1].seq + 1;
+    }
   }
+  const pending = await getPendingDoctorPrompts();
+  setDoctorReports((reports) => [
+    ...reports,
+    ...pending.filter((prompt) => !reports.some((report) => report.id === prompt.id)),
+  ]);
 })();
 
 listen<IdentifiedC2SMessage>("ipc_message", (event) => {
   getOrInitConnection(event.payload.connId).handleEvent(event.payload);
 });
 
+listen<CrashReported>("crash_report", (event) => {
+  getOrInitConnection(event.payload.connId).handleEvent({ type: "CrashReported", path: event.payload.path });
+});
+
+listen<Responsiveness>("ipc_responsiveness", (event) => {
+  connections.get(event.payload.connId)?.setUnresponsive(event.payload.unresponsive);
+});
+
+listen<ResourceSample>("ipc_resource_usage", (event) => {
+  connections.get(event.payload.connId)?.setResourceUsage(event.payload);
+});
+
+listen<string>("doctor_prompt_resolved", (event) => {
+  setDoctorReports((reports) => reports.filter((report) => report.id !== event.payload));
+});
+
 listen<number>("ipc_closed", (event) => {
   let conn = connections.get(event.payload);
   if (conn !== undefined) {
@@ -40,9 +82,9 @@ listen<number>("ipc_closed", (event) => {
 
 export type Event = C2SMessage | FrontendEvent;
 
-type FrontendEvent = { type: "Error"; error: unknown };
+type FrontendEvent = { type: "Error"; error: unknown } | { type: "CrashReported"; path: string };
 
-type IdentifiedC2SMessage = C2SMessage & { connId: number };
+type IdentifiedC2SMessage = C2SMessage & { connId: number; seq?: number };
 export type IdentifiedDoctorReport = DoctorReport & { connId: number };
 
 export const [doctorReports, setDoctorReports] = createSignal<IdentifiedDoctorReport[]>([]);
@@ -52,10 +94,18 @@ export class ConsoleConnection {
   readonly profileId?: string;
   readonly status: Accessor<ConnectionStatus>;
   readonly setStatus: (value: ConnectionStatus) => void;
+  /** Whether the game has stopped sending heartbeats, i.e. it seems to hang. */
+  readonly unresponsive: Accessor<boolean>;
+  readonly setUnresponsive: (value: boolean) => void;
+  /** The latest sample of the game's CPU and memory use, if it is being monitored. */
+  readonly resourceUsage: Accessor<ResourceSample | undefined>;
+  readonly setResourceUsage: (value: ResourceSample) => void;
   // TODO: don't use a signal for these
   readonly events: Accessor<Event[]>;
   readonly setEvents: Setter<Event[]>;
   readonly createdTime: Date;
+  /** The number of the last message from the backend's history, to skip ones received twice. */
+  private lastSeq = -1;
 
   constructor(id: number, profileId?: string) {
     this.id = id;
@@ -63,6 +113,12 @@ export class ConsoleConnection {
     const [status, setStatus] = createSignal<ConnectionStatus>("connecting");
     this.status = status;
     this.setStatus = setStatus;
+    const [unresponsive, setUnresponsive] = createSignal(false);
+    this.unresponsive = unresponsive;
+    this.setUnresponsive = setUnresponsive;
+    const [resourceUsage, setResourceUsage] = createSignal<ResourceSample>();
+    this.resourceUsage = resourceUsage;
+    this.setResourceUsage = setResourceUsage;
     const [events, setEvents] = createSignal<Event[]>([]);
     this.events = events;
     this.setEvents = setEvents;
@@ -84,10 +140,18 @@ export class ConsoleConnection {
 
   handleEvent(event: IdentifiedC2SMessage | FrontendEvent) {
     if (event.type === "DoctorReport") {
-      setDoctorReports((reports) => [...reports, event]);
+      // pending prompts are sent again when the page is reloaded
+      setDoctorReports((reports) =>
+        reports.some((report) => report.id === event.id) ? reports : [...reports, event],
+      );
       return;
     }
 
+    if ("seq" in event && event.seq !== undefined) {
+      if (event.seq <= this.lastSeq) return;
+      this.lastSeq = event.seq;
+    }
+
     if (event.type === "Connect") {
       this.setStatus("connected");
     } else if (event.type === "Disconnect") {
@@ -63,13 +63,67 @@ export type C2SMessage =
       type: "Crash";
       error: string;
     }
+  | CrashReport
   | DoctorReport;
 
-export type S2CMessage = {
-  type: "PatientResponse";
-  id: string;
-  choice: string;
-};
+export interface CrashReport {
+  type: "CrashReport";
+  error: string;
+  /** The native backtrace of the thread that crashed, innermost frame first. */
+  frames: { symbol?: string; location?: string }[];
+  /** The code of the exception that crashed the game, on Windows. */
+  exception_code?: number;
+  /** The paths of the modules loaded into the game, on Windows. */
+  modules: string[];
+}
+
+/** Sent once a {@link CrashReport} has been written to the logs directory. */
+export interface CrashReported {
+  connId: number;
+  error: string;
+  exceptionCode?: number;
+  /** The innermost frame whose function is known. */
+  frame?: string;
+  path: string;
+}
+
+/** Sent when a game stops or resumes sending heartbeats while it is still running. */
+export interface Responsiveness {
+  connId: number;
+  unresponsive: boolean;
+}
+
+/** A sample of the game process' CPU and memory use, sent every few seconds while monitoring is enabled. */
+export interface ResourceSample {
+  connId: number;
+  /** Milliseconds since the Unix epoch. */
+  time: number;
+  /** The percentage of one CPU core used since the previous sample, unknown for the first. */
+  cpu_percent?: number;
+  /** Bytes of the game's memory that are resident in RAM. */
+  resident_memory: number;
+  /** Whether the game's memory has been growing steadily for the last few minutes. */
+  runaway_memory: boolean;
+}
+
+export type S2CMessage =
+  | {
+      type: "PatientResponse";
+      id: string;
+      choice: string;
+    }
+  | {
+      type: "ReloadMod";
+      mod: string;
+      files: string[];
+    }
+  | {
+      type: "RequestExit";
+    }
+  | {
+      type: "WriteStdin";
+      data: number[];
+    };
 
 export async function allocateIpcConnection(): Promise<number> {
   return await wrapInvoke(() => invoke("allocate_ipc_connection", {}));
@@ -79,10 +133,101 @@ export async function sendS2CMessage(connId: number, msg: S2CMessage): Promise<v
   return await wrapInvoke(() => invoke("send_s2c_message", { connId, msg }));
 }
 
-export async function killIpcClient(connId: number): Promise<void> {
-  return await wrapInvoke(() => invoke("kill_ipc_client", { connId }));
+/**
+ * Asks the game to exit and kills it if it has not after the grace period from the settings.
+ *
+ * @param force kill the game immediately instead, for games that cannot respond
+ */
+export async function killIpcClient(connId: number, force?: boolean): Promise<void> {
+  return await wrapInvoke(() => invoke("kill_ipc_client", { connId, force }));
+}
+
+/**
+ * Asks the game to exit on its own, giving it the chance to save and clean up, unlike {@link killIpcClient}.
+ */
+export async function requestExit(connId: number): Promise<void> {
+  return await wrapInvoke(() => invoke("request_exit", { connId }));
 }
 
-export async function getIpcConnections(): Promise<number[]> {
+/**
+ * Writes to the game's standard input, for games like dedicated servers that read commands from it.
+ */
+export async function sendStdin(connId: number, data: string): Promise<void> {
+  return await wrapInvoke(() => invoke("send_stdin", { connId, data }));
+}
+
+/**
+ * Starts or stops sampling the game's CPU and memory use, sent as `ipc_resource_usage` events.
+ * Sampling stops by itself once the game disconnects.
+ */
+export async function setResourceMonitoring(connId: number, enabled: boolean): Promise<void> {
+  return await wrapInvoke(() => invoke("set_resource_monitoring", { connId, enabled }));
+}
+
+/** A sample of how the game performed, sent by the agent every few seconds. */
+export interface MetricsSample {
+  /** When the sample was received, in milliseconds since the Unix epoch. */
+  time: number;
+  /** Only known if the game's frames are counted, which they are on Windows. */
+  fps?: number;
+  memory_mb?: number;
+  /** The longest time between two frames. Only known if the game's frames are counted. */
+  frame_time_ms?: number;
+}
+
+/**
+ * @returns the samples of the last few minutes, oldest first
+ */
+export async function getConnectionMetrics(connId: number): Promise<MetricsSample[]> {
+  return await wrapInvoke(() => invoke("get_connection_metrics", { connId }));
+}
+
+/** A message kept by the backend, numbered in the order that the connection sent them. */
+export type HistoryEntry = C2SMessage & { seq: number };
+
+/**
+ * @param offset the number of the first message to return
+ * @returns up to `limit` of the messages kept for the connection, oldest first
+ */
+export async function getConnectionHistory(connId: number, offset: number, limit: number): Promise<HistoryEntry[]> {
+  return await wrapInvoke(() => invoke("get_connection_history", { connId, offset, limit }));
+}
+
+/** An open connection, with what is known of its game's process. */
+export interface ConnectionInfo {
+  id: number;
+  pid?: number;
+  /** Seconds since the game started. */
+  uptime?: number;
+  /** The percentage of one CPU core used since the previous call to {@link getIpcConnections}. */
+  cpu_percent?: number;
+  /** Bytes of the game's memory that are resident in RAM. */
+  resident_memory?: number;
+  /** The game that was launched with the connection, if it was launched by Manderrow. */
+  game?: string;
+  /** The profile that was launched with the connection, unless it was a vanilla launch. */
+  profile?: string;
+}
+
+export async function getIpcConnections(): Promise<ConnectionInfo[]> {
   return await wrapInvoke(() => invoke("get_ipc_connections"));
 }
+
+/**
+ * @param destination if provided, the log is copied to this path
+ * @returns the path of the recorded log file for the connection
+ */
+export async function exportConnectionLog(connId: number, destination?: string): Promise<string> {
+  return await wrapInvoke(() => invoke("export_connection_log", { connId, destination }));
+}
+
+/**
+ * @returns the doctor prompts that are still waiting on an answer
+ */
+export async function getPendingDoctorPrompts(): Promise<(DoctorReport & { connId: number })[]> {
+  return await wrapInvoke(() => invoke("get_pending_doctor_prompts"));
+}
+
+export async function respondToPrompt(connId: number, id: string, choice: string): Promise<void> {
+  return await wrapInvoke(() => invoke("respond_to_prompt", { connId, id, choice }));
+}
@@ -1,4 +1,4 @@
-import { invoke } from "@tauri-apps/api/core";
+import { Channel, invoke } from "@tauri-apps/api/core";
 
 import { wrapInvoke } from "./api.ts";
 
@@ -9,3 +9,107 @@ export async function launchProfile(
 ): Promise<void> {
   return await wrapInvoke(() => invoke("launch_profile", { connId, target, ...options }));
 }
+
+/**
+ * Launches the game without any mods, after putting back the files that Manderrow has replaced in the game's
+ * directory.
+ */
+export async function launchVanilla(connId: number, game: string): Promise<void> {
+  return await wrapInvoke(() => invoke("launch_vanilla", { connId, game }));
+}
+
+export interface Restored {
+  /** The files that were restored or removed. */
+  restored: string[];
+  /** The files that were left alone, as something else changed them after Manderrow wrote them. */
+  drifted: string[];
+}
+
+/**
+ * Removes everything that Manderrow has written into the game's directory, putting back any files that it replaced.
+ * Files that were changed by something else since Manderrow wrote them are left alone.
+ */
+export async function restoreVanilla(game: string): Promise<Restored> {
+  return await wrapInvoke(() => invoke("restore_vanilla", { game }));
+}
+
+/**
+ * Removes the launch options that Manderrow applied to the game in Steam, so that Steam can still launch it once
+ * Manderrow is uninstalled.
+ *
+ * @param connId the connection to ask the user to confirm through, which closes Steam if it is running. Without one,
+ * this fails if Steam is running.
+ * @returns whether they were removed
+ */
+export async function removeSteamLaunchOptions(game: string, connId?: number): Promise<boolean> {
+  return await wrapInvoke(() => invoke("remove_steam_launch_options", { game, connId }));
+}
+
+export type SupportReason = "loader" | "store" | "platform";
+
+export type Support = { status: "supported" } | { status: "unsupported"; reason: SupportReason };
+
+export type InjectionMethod = "proxy_dll" | "preload" | "remote";
+
+export interface GameSupport {
+  modded_launch: Support;
+  vanilla_launch: Support;
+  config_editing: Support;
+  /** `null` if the game can't be launched. */
+  injection: InjectionMethod | null;
+}
+
+/**
+ * Returns what Manderrow can do for the game on this platform, and why not where it can't.
+ */
+export async function getGameSupport(game: string): Promise<GameSupport> {
+  return await wrapInvoke(() => invoke("get_game_support", { game }));
+}
+
+export type AgentTestStep = "resolve_agent" | "spawn" | "connect" | "started" | "handshake" | "heartbeat" | "exit";
+
+export interface AgentTestStepReport {
+  step: AgentTestStep;
+  /** Milliseconds since the test began. */
+  elapsed_ms: number;
+  /** Why the step failed. The test stops at the first step that fails. */
+  error: string | null;
+}
+
+export interface AgentTestReport {
+  steps: AgentTestStepReport[];
+  /** Whether the game runs through Proton, in which case the Windows agent that it would load is not covered. */
+  uses_proton: boolean;
+}
+
+/**
+ * Launches a stand-in for the game with the agent and checks each step of the handshake with it, so that the setup can
+ * be verified without waiting for the game to boot.
+ *
+ * @param onStep called with the report of each step as it completes
+ */
+export async function testAgentIpc(
+  game: string,
+  onStep: (report: AgentTestStepReport) => void,
+): Promise<AgentTestReport> {
+  const channel = new Channel<AgentTestStepReport>();
+  channel.onmessage = onStep;
+  return await wrapInvoke(() => invoke("test_agent_ipc", { game, onStep: channel }));
+}
+
+export interface WrapperFailure {
+  /** Milliseconds since the Unix epoch. */
+  time: number;
+  /** The command line that the wrapper was run with. */
+  args: string[];
+  /** The error followed by its causes. */
+  messages: string[];
+}
+
+/**
+ * Returns the failures of the wrapper that happened before the game could start since this was last called, so that
+ * launches from Steam that failed silently can be shown.
+ */
+export async function takeWrapperFailures(): Promise<WrapperFailure[]> {
+  return await wrapInvoke(() => invoke("take_wrapper_failures"));
+}
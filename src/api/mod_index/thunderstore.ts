@@ -1,4 +1,5 @@
 import { invoke } from "@tauri-apps/api/core";
+import { wrapInvoke } from "../api";
 import { Listener, invokeWithListener } from "../tasks";
 
 export type Endpoint = "readme" | "changelog";
@@ -14,3 +15,55 @@ export function fetchModMarkdown(
     return invoke("thunderstore_fetch_mod_markdown", { owner, name, version, endpoint, taskId });
   });
 }
+
+export interface VersionChangelog {
+  version: string;
+  /** `null` if the version has no changelog, or it could not be fetched. */
+  markdown: string | null;
+  /** Why the changelog could not be fetched. */
+  error: string | null;
+}
+
+/**
+ * Fetches the CHANGELOG of each of the mod's versions at once, so that what changed before upgrading can be reviewed.
+ */
+export async function fetchModChangelogs(owner: string, name: string, versions: string[]): Promise<VersionChangelog[]> {
+  return await wrapInvoke(() => invoke("thunderstore_fetch_mod_changelogs", { owner, name, versions }));
+}
+
+export type RatingState = "rated" | "unrated";
+
+export type ReportReason =
+  | "Spam"
+  | "Malware"
+  | "Reupload"
+  | "CopyrightOrLicense"
+  | "WrongCommunity"
+  | "WrongCategories"
+  | "Other";
+
+/**
+ * @returns the full names (`owner-name`) of the mods the user has rated through Manderrow.
+ */
+export async function getRatedMods(): Promise<string[]> {
+  return await invoke("thunderstore_get_rated_mods");
+}
+
+export async function rateMod(
+  owner: string,
+  name: string,
+  targetState: RatingState,
+): Promise<{ state: RatingState; score: number }> {
+  return await invoke("thunderstore_rate_mod", { owner, name, targetState });
+}
+
+export async function reportMod(
+  community: string,
+  owner: string,
+  name: string,
+  version: string | null,
+  reason: ReportReason,
+  description: string | null,
+): Promise<void> {
+  await invoke("thunderstore_report_mod", { community, owner, name, version, reason, description });
+}
@@ -14,6 +14,33 @@ export interface Setting<T> {
 export interface Settings {
   defaultGame: Setting<string | null>;
   openConsoleOnLaunch: Setting<boolean>;
+  pauseBackgroundActivity: Setting<boolean>;
+  packageMirrors: Setting<string>;
+  concurrentDownloads: Setting<number>;
+  storeModDocs: Setting<boolean>;
+  cacheMaxSize: Setting<number>;
+  cacheMaxAge: Setting<number>;
+  requestConnectTimeout: Setting<number>;
+  requestReadTimeout: Setting<number>;
+  userAgent: Setting<string>;
+  useHttp2: Setting<boolean>;
+  prefetchTopMods: Setting<number>;
+  simulatedLatency: Setting<number>;
+  simulatedBandwidth: Setting<number>;
+  simulatedFailureRate: Setting<number>;
+  launchWrapper: Setting<string>;
+  launchOptionsTemplates: Setting<LaunchOptionsTemplate[]>;
+  consoleHistorySize: Setting<number>;
+  killGracePeriod: Setting<number>;
+  allowConcurrentLaunches: Setting<boolean>;
+}
+
+export interface LaunchOptionsTemplate {
+  name: string;
+  /** Written like Steam's launch options, e.g. `mangohud %command%`, or just a command like `gamemoderun`. */
+  options: string;
+  /** The ids of the games that the template is selected for. */
+  games: string[];
 }
 
 export type SettingsT<T> = keyof {
@@ -42,3 +69,8 @@ export const settingsUI = () => settingsUIResource.latest!;
 export function updateSettings(patch: SettingsPatch): Promise<void> {
   return wrapInvoke(() => invoke("update_settings", { patch }));
 }
+
+/** Parses launch options templates that were exported as JSON. */
+export function parseLaunchOptionsTemplates(json: string): Promise<LaunchOptionsTemplate[]> {
+  return wrapInvoke(() => invoke("parse_launch_options_templates", { json }));
+}
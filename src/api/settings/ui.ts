@@ -1,5 +1,5 @@
 import { RawDictionary } from "../../i18n/i18n.ts";
-import { SettingsT } from "../settings.ts";
+import { LaunchOptionsTemplate, SettingsT } from "../settings.ts";
 
 export interface Settings {
   sections: Section[];
@@ -20,9 +20,24 @@ export interface TextSetting {
   input: "text";
 }
 
+export interface NumberSetting {
+  key: SettingsT<number>;
+  input: "number";
+}
+
 export interface GameSelectSetting {
   key: SettingsT<string>;
   input: "game_select";
 }
 
-export type Setting = ToggleSetting | TextSetting | GameSelectSetting;
+export interface LaunchOptionsTemplatesSetting {
+  key: SettingsT<LaunchOptionsTemplate[]>;
+  input: "launch_options_templates";
+}
+
+export type Setting =
+  | ToggleSetting
+  | TextSetting
+  | NumberSetting
+  | GameSelectSetting
+  | LaunchOptionsTemplatesSetting;
@@ -6,7 +6,8 @@ import { createStore, SetStoreFunction, Store } from "solid-js/store";
 import { wrapInvoke } from "./api";
 import { callWithErrorStack } from "../utils/utils";
 
-export type Id = number;
+/** A UUID, unique across app restarts. */
+export type Id = string;
 
 export type Listener = (event: TaskEvent) => void;
 
@@ -211,6 +212,22 @@ export function invokeWithListener<R>(listener: Listener, f: (taskId: Id) => Pro
   });
 }
 
+export interface HistoryEntry {
+  id: Id;
+  metadata: Metadata;
+  /** Milliseconds since the Unix epoch. */
+  started_at: number;
+  duration_ms: number;
+  status: DropStatus;
+}
+
+/**
+ * @returns the recently finished tasks, newest first
+ */
+export async function getTaskHistory(): Promise<HistoryEntry[]> {
+  return await wrapInvoke(() => invoke("get_task_history"));
+}
+
 /**
  * Cancels the task `id`, returning without waiting for the cancellation to complete.
  */
@@ -258,34 +275,7 @@ listen<TaskDependencyEvent>("task_dependency", (event) => {
   const task = _tasks.get(event.payload.id);
   if (task !== undefined) {
     task._setDependencies(task.dependencies.length, event.payload.dependency);
-    if (task.metadata.kind === "Aggregate") {
-      const dependency = event.payload.dependency;
-      registerTaskListener(dependency, function handler(event) {
-        if (event.event === "created") {
-          if (event.metadata.progress_unit !== task.metadata.progress_unit) {
-            // Units don't match. We don't want to handle this.
-            unregisterTaskListener(dependency, handler);
-          }
-        } else if (event.event === "progress") {
-          // TODO: don't redo it all
-          let completed = event.progress.completed;
-          let total = event.progress.total;
-          for (const id of task.dependencies) {
-            if (id !== dependency) {
-              const subTask = _tasks.get(id);
-              if (subTask === undefined) continue;
-              completed += subTask.progress.completed;
-              total += subTask.progress.total;
-            }
-          }
-
-          task._setProgress({
-            completed,
-            total,
-          });
-        }
-      });
-    }
+    // the progress of aggregate tasks is rolled up from their dependencies by the backend
     notifyTaskListeners(task, { ...event.payload, event: "dependency" });
   }
 });
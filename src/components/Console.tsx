@@ -14,7 +14,19 @@ import {
 } from "solid-js";
 import { createStore } from "solid-js/store";
 
-import { LOG_LEVELS, SafeOsString, sendS2CMessage } from "../api/ipc";
+import {
+  LOG_LEVELS,
+  MetricsSample,
+  ResourceSample,
+  SafeOsString,
+  getConnectionMetrics,
+  killIpcClient,
+  requestExit,
+  respondToPrompt,
+  sendStdin,
+  setResourceMonitoring,
+} from "../api/ipc";
+import { openInFileManager } from "../api/app";
 // @ts-ignore: typescript is unaware of solid's use: syntax
 import { bindValue } from "./Directives";
 import styles from "./Console.module.css";
@@ -22,6 +34,7 @@ import Dialog, { dialogStyles } from "../widgets/Dialog";
 import { t } from "../i18n/i18n";
 import { ErrorContext } from "./ErrorBoundary";
 import SelectDropdown from "../widgets/SelectDropdown";
+import { humanizeFileSize } from "../utils/utils";
 import {
   connections,
   connectionsUpdate,
@@ -55,6 +68,28 @@ export function DoctorReports() {
 const VISIBLE_LEVELS_OPTIONS = [...LOG_LEVELS, "STDOUT", "STDERR"] as const;
 type VisibleLevels = { [k in (typeof VISIBLE_LEVELS_OPTIONS)[number]]: boolean };
 
+/** The agent samples every 5 seconds. */
+const METRICS_POLL_INTERVAL = 5000;
+
+function formatMetrics(metrics: MetricsSample) {
+  const parts = [];
+  if (metrics.fps !== undefined) parts.push(t("console.metrics_fps", { fps: metrics.fps.toFixed(0) }));
+  if (metrics.frame_time_ms !== undefined) {
+    parts.push(t("console.metrics_frame_time", { ms: metrics.frame_time_ms.toFixed(1) }));
+  }
+  if (metrics.memory_mb !== undefined) parts.push(t("console.metrics_memory", { mb: metrics.memory_mb.toFixed(0) }));
+  return parts.join(" · ");
+}
+
+function formatResourceUsage(usage: ResourceSample) {
+  const parts = [];
+  if (usage.cpu_percent !== undefined) {
+    parts.push(t("console.resource_usage_cpu", { percent: usage.cpu_percent.toFixed(0) }));
+  }
+  parts.push(t("console.resource_usage_memory", { size: humanizeFileSize(usage.resident_memory, true) }));
+  return parts.join(" · ");
+}
+
 export default function Console() {
   const isFocusedConnection = createSelector<number | undefined, number>(() => focusedConnection()?.id);
 
@@ -109,6 +144,68 @@ export default function Console() {
   });
 
   const [searchInput, setSearchInput] = createSignal("");
+  const [stdinInput, setStdinInput] = createSignal("");
+  const reportErr = useContext(ErrorContext)!;
+
+  async function submitStdin(e: SubmitEvent) {
+    e.preventDefault();
+    const conn = focusedConnection();
+    if (conn === undefined) return;
+    try {
+      await sendStdin(conn.id, `${stdinInput()}\n`);
+      setStdinInput("");
+    } catch (e) {
+      reportErr(e);
+    }
+  }
+
+  const [metrics, setMetrics] = createSignal<MetricsSample>();
+  createEffect(() => {
+    const conn = focusedConnection();
+    setMetrics(undefined);
+    if (conn === undefined || conn.status() !== "connected") return;
+    async function update() {
+      try {
+        setMetrics((await getConnectionMetrics(conn!.id)).at(-1));
+      } catch {
+        // the game may have exited in the meantime
+      }
+    }
+    update();
+    const interval = setInterval(update, METRICS_POLL_INTERVAL);
+    onCleanup(() => clearInterval(interval));
+  });
+
+  createEffect(() => {
+    const conn = focusedConnection();
+    if (conn === undefined || conn.status() !== "connected") return;
+    setResourceMonitoring(conn.id, true).catch(reportErr);
+    onCleanup(() => {
+      // the game may have exited in the meantime, in which case monitoring has already stopped
+      setResourceMonitoring(conn.id, false).catch(() => {});
+    });
+  });
+
+  async function stopGame() {
+    const conn = focusedConnection();
+    if (conn === undefined) return;
+    try {
+      await requestExit(conn.id);
+    } catch (e) {
+      reportErr(e);
+    }
+  }
+
+  async function killGame() {
+    const conn = focusedConnection();
+    if (conn === undefined) return;
+    try {
+      // a game that hangs won't get to handle the request to exit either
+      await killIpcClient(conn.id, true);
+    } catch (e) {
+      reportErr(e);
+    }
+  }
 
   return (
     <>
@@ -175,15 +272,47 @@ export default function Console() {
               {focusedConnection()?.status() !== "disconnected" ? t("console.live_log") : t("console.log_created_at")}
             </p>
             {focusedConnection()?.status() !== "disconnected" ? (
-              <span class={styles.statusIndicator} data-connected={focusedConnection()?.status() === "connected"}>
-                {focusedConnection()?.status() === "connected"
-                  ? t("console.live_log_connected")
-                  : t("console.live_log_disconnected")}
+              <span
+                class={styles.statusIndicator}
+                data-connected={focusedConnection()?.status() === "connected"}
+                data-unresponsive={focusedConnection()?.unresponsive()}
+              >
+                {focusedConnection()?.status() !== "connected"
+                  ? t("console.live_log_disconnected")
+                  : focusedConnection()?.unresponsive()
+                    ? t("console.live_log_unresponsive")
+                    : t("console.live_log_connected")}
               </span>
             ) : (
               focusedConnection()!.createdTime.toLocaleString()
             )}
+            <Show when={metrics()}>{(metrics) => <span class={styles.metrics}>{formatMetrics(metrics())}</span>}</Show>
+            <Show when={focusedConnection()?.status() === "connected" && focusedConnection()?.resourceUsage()}>
+              {(usage) => (
+                <span class={styles.metrics} data-runaway={usage().runaway_memory}>
+                  {formatResourceUsage(usage())}
+                  <Show when={usage().runaway_memory}> · {t("console.resource_usage_runaway")}</Show>
+                </span>
+              )}
+            </Show>
           </div>
+          <Show when={focusedConnection()?.status() === "connected"}>
+            {
+              // a game that hangs won't get to handle the request to exit
+            }
+            <Show
+              when={focusedConnection()?.unresponsive()}
+              fallback={
+                <button class={styles.stopBtn} on:click={stopGame}>
+                  {t("console.stop_game_btn")}
+                </button>
+              }
+            >
+              <button class={styles.stopBtn} on:click={killGame} data-kill>
+                {t("console.kill_unresponsive_game_btn")}
+              </button>
+            </Show>
+          </Show>
         </div>
       </header>
       <div class={styles.console} ref={consoleContainer}>
@@ -194,6 +323,17 @@ export default function Console() {
           {(event) => ConsoleEvent(event, visibleLevels, searchInput)}
         </For>
       </div>
+      <Show when={focusedConnection()?.status() === "connected"}>
+        <form class={styles.stdin} on:submit={submitStdin}>
+          <input
+            type="text"
+            name="stdin"
+            placeholder={t("console.stdin_placeholder")}
+            use:bindValue={[stdinInput, setStdinInput]}
+          />
+          <button type="submit">{t("console.send_stdin_btn")}</button>
+        </form>
+      </Show>
     </>
   );
 }
@@ -229,6 +369,8 @@ function ConsoleEvent(event: Event, visibleLevels: VisibleLevels, searchInput: (
     case "Started":
     case "Exit":
     case "Crash":
+    case "CrashReport":
+    case "CrashReported":
     case "DoctorReport":
     case "Error": {
       visibleTmp = () => true;
@@ -362,6 +504,48 @@ function ConsoleEvent(event: Event, visibleLevels: VisibleLevels, searchInput: (
           </span>
         </>
       );
+    case "CrashReport":
+      return (
+        <>
+          <span class={styles.event__type} style={displayStyle()} data-type="CRASH">
+            CRASH
+          </span>
+          <span class={styles.event__scope} style={displayStyle()}></span>
+          <span class={styles.event__message} style={displayStyle()}>
+            {event.error}
+            <Show when={event.exception_code !== undefined}>
+              {" "}
+              ({t("console.exception_code", { code: `0x${event.exception_code!.toString(16).padStart(8, "0")}` })})
+            </Show>
+            <Show when={event.frames.length !== 0}>
+              <details>
+                <summary>{t("console.backtrace")}</summary>
+                <For each={event.frames}>
+                  {(frame, i) => (
+                    <div>
+                      {i()}: {frame.symbol ?? "<unknown>"}
+                      <Show when={frame.location}>{(location) => <> at {location()}</>}</Show>
+                    </div>
+                  )}
+                </For>
+              </details>
+            </Show>
+          </span>
+        </>
+      );
+    case "CrashReported":
+      return (
+        <>
+          <span class={styles.event__type} style={displayStyle()} data-type="CRASH">
+            CRASH
+          </span>
+          <span class={styles.event__scope} style={displayStyle()}></span>
+          <span class={styles.event__message} style={displayStyle()}>
+            {t("console.crash_report_written", { path: event.path })}{" "}
+            <button on:click={() => openInFileManager(event.path)}>{t("console.show_crash_report_btn")}</button>
+          </span>
+        </>
+      );
     case "Error":
       return (
         <>
@@ -433,11 +617,7 @@ function DoctorDialog(props: { report: IdentifiedDoctorReport; onDismiss: () =>
                   type="button"
                   on:click={async () => {
                     try {
-                      await sendS2CMessage(props.report.connId, {
-                        type: "PatientResponse",
-                        id: props.report.id,
-                        choice: fix.id,
-                      });
+                      await respondToPrompt(props.report.connId, props.report.id, fix.id);
                     } catch (e) {
                       reportErr(e);
                     } finally {
@@ -0,0 +1,61 @@
+import { getCurrentWindow } from "@tauri-apps/api/window";
+import { createSignal, For, onCleanup, onMount, Show } from "solid-js";
+
+import { takeWrapperFailures, WrapperFailure } from "../api/launching";
+import { t } from "../i18n/i18n";
+
+import { DefaultDialog, DialogClose, dialogStyles } from "../widgets/Dialog";
+
+import styles from "./ErrorDialog.module.css";
+
+/**
+ * Shows the failures of launches that never reached the game, like those started from Steam, whenever the app is
+ * focused.
+ */
+export default function WrapperFailuresDialog() {
+  const [failures, setFailures] = createSignal<WrapperFailure[]>([]);
+
+  async function check() {
+    const taken = await takeWrapperFailures();
+    if (taken.length !== 0) {
+      setFailures((failures) => [...failures, ...taken]);
+    }
+  }
+
+  onMount(() => {
+    const unlisten = getCurrentWindow().onFocusChanged((event) => {
+      if (event.payload) check();
+    });
+    onCleanup(() => unlisten.then((unlisten) => unlisten()));
+    check();
+  });
+
+  return (
+    <Show when={failures().length !== 0}>
+      <DefaultDialog initialOpen onDismiss={() => setFailures([])}>
+        <h2 class={dialogStyles.dialog__title}>{t("wrapper_failures.title")}</h2>
+        <p class={dialogStyles.dialog__message}>{t("wrapper_failures.message", { count: failures().length })}</p>
+        <div class={styles.report}>
+          <For each={failures()}>
+            {(failure) => (
+              <details class={styles.spoiler}>
+                <summary>
+                  <h3>{new Date(failure.time).toLocaleString()}</h3>
+                </summary>
+                <For each={failure.messages}>{(msg) => <p>{msg}</p>}</For>
+                <div class={styles.pre}>
+                  <pre>{failure.args.join(" ")}</pre>
+                </div>
+              </details>
+            )}
+          </For>
+        </div>
+        <div class={dialogStyles.dialog__btns}>
+          <DialogClose class={dialogStyles.dialog__btnsBtn} onClick={() => setFailures([])}>
+            {t("global.phrases.close")}
+          </DialogClose>
+        </div>
+      </DefaultDialog>
+    </Show>
+  );
+}
@@ -46,6 +46,8 @@ export interface ModMetadata {
   name: string;
   owner: string;
   donation_link?: string;
+  /** The mod's page on Thunderstore. Only present for installed mods. */
+  package_url?: string;
   date_created: string;
   is_deprecated: boolean;
   has_nsfw_content: boolean;
@@ -64,6 +66,14 @@ export interface ModListing extends ModMetadata {
  */
 export interface ModPackage extends ModMetadata {
   version: ModVersion;
+  /** Whether the package was extracted from a previously downloaded copy. Missing for older installs. */
+  installed_from_cache?: boolean;
+  /** Present if the package is a tool, which is run on its own instead of being loaded by the mod loader. */
+  tool?: { executables: string[] };
+  /** Present if the mod is linked from a folder outside of the profile, which it is copied from whenever it changes. */
+  dev_source?: string;
+  /** Whether the mod's plugins have been renamed so that the mod loader doesn't load them. */
+  disabled?: boolean;
 }
 
 export interface ModVersion {
@@ -10,6 +10,33 @@ export const dateFormatterMed = new Intl.DateTimeFormat(undefined, {
   month: "short",
   day: "numeric",
 });
+const relativeTimeFormatter = new Intl.RelativeTimeFormat(undefined, { numeric: "auto" });
+const RELATIVE_TIME_UNITS: readonly [Intl.RelativeTimeFormatUnit, number][] = Object.freeze([
+  ["day", 24 * 60 * 60 * 1000],
+  ["hour", 60 * 60 * 1000],
+  ["minute", 60 * 1000],
+]);
+/**
+ * Whether the URL is an absolute http or https URL. Links from outside the app, like those of mods, are only opened if
+ * they are, since opening anything else, like a file URL, could run a program.
+ */
+export function isWebUrl(url: string): boolean {
+  try {
+    const protocol = new URL(url).protocol;
+    return protocol === "http:" || protocol === "https:";
+  } catch {
+    return false;
+  }
+}
+
+/** Formats a timestamp, in milliseconds since the Unix epoch, relative to now, e.g. "3 days ago". */
+export function formatRelativeTime(timestamp: number): string {
+  const elapsed = Date.now() - timestamp;
+  for (const [unit, ms] of RELATIVE_TIME_UNITS) {
+    if (elapsed >= ms) return relativeTimeFormatter.format(-Math.floor(elapsed / ms), unit);
+  }
+  return relativeTimeFormatter.format(0, "minute");
+}
 const BYTE_UNITS = Object.freeze(["B", "KB", "MB", "GB", "TB"]);
 export function humanizeFileSize(sizeBytes: number, space = false): string {
   const i = sizeBytes === 0 ? 0 : Math.floor(Math.log(sizeBytes) / Math.log(1000));
@@ -8,6 +8,7 @@ import { createStore } from "solid-js/store";
 import {
   importModpackFromThunderstoreCode,
   Modpack,
+  ModImportResult,
   ModProgressRegistration,
   ModSpec,
   previewImportModpackFromThunderstoreCode,
@@ -102,22 +103,28 @@ function PreviewPage(props: PreviewPageProps) {
 
 function PreviewPageInner(props: PreviewPageProps) {
   let [modProgress, setModProgress] = createStore<Record<string, TaskId>>({});
+  const [failures, setFailures] = createSignal<(ModImportResult & { status: "Failed" })[]>();
 
   async function onImport(listener: Listener) {
     const modProgressChannel = new Channel<ModProgressRegistration>();
     modProgressChannel.onmessage = (info) => {
       setModProgress(info.url, info.task);
     };
-    const id = await importModpackFromThunderstoreCode(
+    const report = await importModpackFromThunderstoreCode(
       props.thunderstoreCode,
       props.gameId,
       props.profile,
       modProgressChannel,
       listener,
     );
-    console.log(`Imported to profile ${id}`);
-    props.actions.dismiss?.();
+    console.log(`Imported to profile ${report.profile_id}`);
     await refetchProfiles();
+    const failed = report.mods.filter((mod) => mod.status === "Failed");
+    if (failed.length === 0) {
+      props.actions.dismiss?.();
+    } else {
+      setFailures(failed);
+    }
   }
 
   return (
@@ -136,6 +143,26 @@ function PreviewPageInner(props: PreviewPageProps) {
               <For each={props.modpack.mods}>{(mod) => <ModEntry mod={mod} modProgress={modProgress} />}</For>
             </ul>
 
+            <Show when={failures()}>
+              {(failures) => (
+                <>
+                  <h3>{t("profile.import_model.failed_mods_title")}</h3>
+                  <ul>
+                    <For each={failures()}>
+                      {(mod) => (
+                        <li>
+                          <strong>
+                            {mod.name} {mod.version}
+                          </strong>
+                          : {mod.error}
+                        </li>
+                      )}
+                    </For>
+                  </ul>
+                </>
+              )}
+            </Show>
+
             <h3>Files</h3>
             <ul>
               <For each={props.modpack.diff}>
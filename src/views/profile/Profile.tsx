@@ -6,6 +6,8 @@ import {
   createSignal,
   For,
   Match,
+  onCleanup,
+  onMount,
   Show,
   Switch,
 } from "solid-js";
@@ -34,7 +36,14 @@ import SelectDropdown from "../../widgets/SelectDropdown.tsx";
 import TabRenderer from "../../widgets/TabRenderer";
 import { InstalledModList, ModInstallContext, OnlineModList } from "./modlist/ModList.tsx";
 
-import { createProfile, getProfileMods, ProfileWithId } from "../../api/api";
+import {
+  createProfile,
+  getProfileMods,
+  getProfiles,
+  listenRequestedProfile,
+  ProfileWithId,
+  takeRequestedProfile,
+} from "../../api/api";
 import * as globals from "../../globals.ts";
 import { initialGame, refetchProfiles } from "../../globals.ts";
 // @ts-ignore: TS is unaware of `use:` directives despite using them for type definitions
@@ -47,7 +56,7 @@ import { settings } from "../../api/settings.ts";
 import { useSearchParamsInPlace } from "../../utils/router.ts";
 import { killIpcClient } from "../../api/ipc.ts";
 import { t } from "../../i18n/i18n.ts";
-import { launchProfile } from "../../api/launching.ts";
+import { getGameSupport, launchProfile, launchVanilla } from "../../api/launching.ts";
 import { ConsoleConnection, focusedConnection, setFocusedConnection } from "../../api/console";
 import { setCurrentProfileName } from "../../components/TitleBar.tsx";
 import Tooltip, { TooltipTrigger } from "../../widgets/Tooltip.tsx";
@@ -87,13 +96,33 @@ export default function Profile() {
     showing: params.gameId == null,
   });
 
+  let openedRequestedProfile = false;
+
   createEffect(() => {
     const game = initialGame.latestOrThrow;
-    if (game) {
+    if (game && !openedRequestedProfile) {
       navigate(`/profile/${game}`, { replace: true });
     }
   });
 
+  // opens the profile of a Steam shortcut that Manderrow was opened with
+  async function openRequestedProfile() {
+    const id = await takeRequestedProfile();
+    if (id == null) return;
+    const profile = (await getProfiles()).find((profile) => profile.id === id);
+    // the profile may have been deleted since the shortcut was created
+    if (profile === undefined) return;
+    openedRequestedProfile = true;
+    setGameSelect("shouldShow", false);
+    navigate(`/profile/${profile.game}/${id}`);
+  }
+
+  onMount(() => {
+    const unlisten = listenRequestedProfile(openRequestedProfile);
+    onCleanup(() => unlisten.then((unlisten) => unlisten()));
+    openRequestedProfile();
+  });
+
   return (
     <>
       <Show when={gameSelect.showing}>
@@ -124,6 +153,12 @@ function ProfileWithGame(
   // TODO, handle undefined case
   const gameInfo = createMemo(() => globals.gamesById().get(params.gameId)!);
 
+  const [gameSupport] = createResource(() => params.gameId, getGameSupport);
+  const moddedLaunchUnsupported = () => {
+    const support = gameSupport()?.modded_launch;
+    return support?.status === "unsupported" ? support.reason : undefined;
+  };
+
   const [profiles] = createResource(
     () => {
       // TODO: catch error and handle correctly
@@ -170,11 +205,11 @@ function ProfileWithGame(
         if (settings().openConsoleOnLaunch.value && searchParams["profile-tab"] !== "logs") {
           setSearchParams({ "profile-tab": "logs" });
         }
-        await launchProfile(
-          conn.id,
-          params.profileId !== undefined ? { profile: params.profileId } : { vanilla: params.gameId },
-          { modded },
-        );
+        if (params.profileId !== undefined) {
+          await launchProfile(conn.id, { profile: params.profileId }, { modded });
+        } else {
+          await launchVanilla(conn.id, params.gameId);
+        }
       } catch (error) {
         conn.handleEvent({
           type: "Error",
@@ -274,7 +309,17 @@ function ProfileWithGame(
             {
               // TODO: based on hasLiveConnection change the UI of these a bit
             }
-            <button disabled={params.profileId === undefined} on:click={() => launch(true)} data-launch>
+            <button
+              // a game executable set on the profile skips the store, so only the loader is certain to block it
+              disabled={params.profileId === undefined || moddedLaunchUnsupported() === "loader"}
+              title={
+                moddedLaunchUnsupported() !== undefined
+                  ? t(`profile.sidebar.unsupported.${moddedLaunchUnsupported()}`)
+                  : undefined
+              }
+              on:click={() => launch(true)}
+              data-launch
+            >
               <Fa icon={faCirclePlay} data-icon />
               <span>{t("profile.sidebar.launch_modded_btn")}</span>
               <span data-arrow>
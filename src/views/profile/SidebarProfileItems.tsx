@@ -13,12 +13,14 @@ import {
 } from "@fortawesome/free-solid-svg-icons";
 import { A, useNavigate } from "@solidjs/router";
 import Fa from "solid-fa";
-import { createSignal, createUniqueId, Show } from "solid-js";
+import { createSignal, createUniqueId, Show, useContext } from "solid-js";
 
-import { deleteProfile, overwriteProfileMetadata, ProfileWithId } from "../../api/api";
+import { deleteProfile, getProfilePaths, overwriteProfileMetadata, ProfileWithId } from "../../api/api";
+import { openInFileManager } from "../../api/app";
 import { connections, connectionsUpdate } from "../../api/console";
 import { ctrling, shifting } from "../../globals";
 import { autofocus } from "../../components/Directives";
+import { ErrorContext } from "../../components/ErrorBoundary";
 
 import { t } from "../../i18n/i18n";
 import { ActionContext } from "../../widgets/AsyncButton";
@@ -55,6 +57,17 @@ export function SidebarProfileComponent(props: {
   const [deleting, setDeleting] = createSignal(false);
 
   const navigate = useNavigate();
+  const reportErr = useContext(ErrorContext)!;
+
+  async function openFolder(folder: "profile" | "game_install") {
+    try {
+      const path = (await getProfilePaths(props.profile.id))[folder];
+      if (path == null) throw new Error(t("profile.sidebar.game_not_installed_msg"));
+      await openInFileManager(path);
+    } catch (e) {
+      reportErr(e);
+    }
+  }
 
   const [renaming, setRenaming] = createSignal(false);
 
@@ -239,7 +252,19 @@ export function SidebarProfileComponent(props: {
                         />
                       ),
                       action() {
-                        // TODO: implement open folder
+                        openFolder("profile");
+                      },
+                    },
+                    {
+                      label: (
+                        <SidebarContextMenuItem
+                          icon={faFolderOpen}
+                          label={t("profile.sidebar.open_game_folder_profile_btn")}
+                          iconClass={sidebarStyles.openFolderIcon}
+                        />
+                      ),
+                      action() {
+                        openFolder("game_install");
                       },
                     },
                   ]}
@@ -0,0 +1,59 @@
+import CorvuDialog from "corvu/dialog";
+import { createSignal, createUniqueId, FlowProps } from "solid-js";
+
+import { linkDevMod } from "../../../api/api";
+import { t } from "../../../i18n/i18n";
+
+import { bindValue } from "../../../components/Directives";
+import { SimpleAsyncButton } from "../../../widgets/AsyncButton";
+import { DefaultDialog, DialogClose, dialogStyles } from "../../../widgets/Dialog";
+
+import styles from "./DevModDialog.module.css";
+
+interface DevModDialogProps {
+  profileId: string;
+  onLinked: () => Promise<void> | void;
+}
+
+/**
+ * Links a folder, like the output folder of a mod's build, into the profile so that the mod can be tested without
+ * packaging it.
+ */
+export default function DevModDialog(props: FlowProps & DevModDialogProps) {
+  return (
+    <DefaultDialog trigger={props.children}>
+      <h2 class={dialogStyles.dialog__title}>{t("modlist.installed.link_dev_mod_title")}</h2>
+      <p class={dialogStyles.dialog__message}>{t("modlist.installed.link_dev_mod_msg")}</p>
+      <LinkForm profileId={props.profileId} onLinked={props.onLinked} />
+    </DefaultDialog>
+  );
+}
+
+function LinkForm(props: DevModDialogProps) {
+  const dialog = CorvuDialog.useContext();
+  const sourceFieldId = createUniqueId();
+
+  const [source, setSource] = createSignal("");
+
+  async function onLink() {
+    await linkDevMod(props.profileId, source());
+    dialog.setOpen(false);
+    await props.onLinked();
+  }
+
+  return (
+    <form on:submit={(e) => e.preventDefault()}>
+      <div class={styles.inputGroup}>
+        <label for={sourceFieldId}>{t("modlist.installed.link_dev_mod_source_label")}</label>
+        <input id={sourceFieldId} use:bindValue={[source, setSource]} required></input>
+      </div>
+
+      <div class={styles.buttonRow}>
+        <DialogClose>{t("global.phrases.cancel")}</DialogClose>
+        <SimpleAsyncButton type="submit" onClick={onLink}>
+          {t("modlist.installed.link_dev_mod_btn")}
+        </SimpleAsyncButton>
+      </div>
+    </form>
+  );
+}
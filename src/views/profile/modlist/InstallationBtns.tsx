@@ -1,6 +1,6 @@
 import { ComponentProps, JSX, splitProps } from "solid-js";
 
-import { installProfileMod, uninstallProfileMod } from "../../../api/api";
+import { installProfileMod, runProfileTool, uninstallProfileMod } from "../../../api/api";
 import { registerTaskListener, tasks } from "../../../api/tasks";
 import { Mod, ModPackage } from "../../../types";
 import { removeProperty } from "../../../utils/utils";
@@ -92,3 +92,24 @@ export function UninstallButton(
     </SimpleAsyncButton>
   );
 }
+
+export function RunToolButton(
+  props: ComponentProps<"button"> & {
+    mod: ModPackage;
+    executable: string;
+    installContext: NonNullable<typeof ModInstallContext.defaultValue>;
+  },
+) {
+  const [local, rest] = splitProps(props, ["mod", "executable", "installContext", "onClick"]);
+
+  return (
+    <SimpleAsyncButton
+      onClick={async () => {
+        await runProfileTool(local.installContext.profileId(), local.mod.owner, local.mod.name, local.executable);
+      }}
+      {...rest}
+    >
+      {props.children}
+    </SimpleAsyncButton>
+  );
+}
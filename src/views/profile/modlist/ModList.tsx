@@ -1,4 +1,4 @@
-import { faCircleUp, faRefresh } from "@fortawesome/free-solid-svg-icons";
+import { faCircleUp, faFolderPlus, faRefresh } from "@fortawesome/free-solid-svg-icons";
 import { createInfiniteScroll } from "@solid-primitives/pagination";
 import { Fa } from "solid-fa";
 import {
@@ -17,6 +17,8 @@ import {
   createResource,
   createSelector,
   createSignal,
+  onCleanup,
+  onMount,
   untrack,
   useContext,
 } from "solid-js";
@@ -28,12 +30,14 @@ import {
   countModIndex,
   fetchModIndex,
   getFromModIndex,
+  getModIndexStatus,
+  listenDevModSynced,
   modIdEquals,
   queryModIndex,
 } from "../../../api/api";
 import { Progress, createProgressProxyStore } from "../../../api/tasks";
 import { Mod, ModPackage } from "../../../types";
-import { createMultiselectableList, numberFormatter } from "../../../utils/utils";
+import { createMultiselectableList, formatRelativeTime, numberFormatter } from "../../../utils/utils";
 
 import { ActionContext, SimpleAsyncButton } from "../../../widgets/AsyncButton";
 import ModSearch from "./ModSearch.tsx";
@@ -49,6 +53,7 @@ import ModListItem from "./ModListItem.tsx";
 import ModUpdateDialogue, { ModUpdate } from "./Updater.tsx";
 import ModView from "./ModView.tsx";
 import BulkActions from "./BulkActions.tsx";
+import DevModDialog from "./DevModDialog.tsx";
 
 type PageFetcher = (page: number) => Promise<readonly Mod[]>;
 type ModFetcherResult = {
@@ -77,6 +82,9 @@ export const enum ModSelectorTutorialState {
 
 const MODS_PER_PAGE = 50;
 
+/** How old the mod index may get before the user is offered to refresh it. */
+const MOD_INDEX_STALE_AFTER_MS = 24 * 60 * 60 * 1000;
+
 export function OnlineModList(props: { game: string }) {
   const [progress, setProgress] = createProgressProxyStore();
 
@@ -92,6 +100,19 @@ export function OnlineModList(props: { game: string }) {
     },
   );
 
+  // re-read whenever a fetch finishes, whether or not it succeeded
+  const [indexStatus] = createResource(
+    () => (loadStatus.loading ? undefined : props.game),
+    (game) => getModIndexStatus(game),
+  );
+  const isIndexStale = () => {
+    const status = indexStatus.latest;
+    if (status == null) return false;
+    return (
+      status.lastError != null || (status.fetchedAt != null && Date.now() - status.fetchedAt > MOD_INDEX_STALE_AFTER_MS)
+    );
+  };
+
   const getFetcher: () => Fetcher = () => {
     // track load status
     loadStatus.latest;
@@ -123,6 +144,29 @@ export function OnlineModList(props: { game: string }) {
       }}
       mods={getFetcher()}
       multiselect={false}
+      trailingControls={
+        <Show when={isIndexStale() ? indexStatus.latest : undefined}>
+          {(status) => (
+            <SimpleAsyncButton
+              btnStyle="ghost"
+              busy={loadStatus.loading}
+              progress={progress}
+              onClick={async () => {
+                // without a refresh, only the chunks that failed are fetched again
+                await refetchModIndex(status().failedChunkCount === 0);
+              }}
+              title={status().lastError ?? undefined}
+            >
+              <Fa icon={faRefresh} />{" "}
+              {status().failedChunkCount !== 0
+                ? t("modlist.online.partial_btn")
+                : status().lastError != null
+                ? t("modlist.online.refresh_failed_btn")
+                : t("modlist.online.stale_btn", { age: formatRelativeTime(status().fetchedAt!) })}
+            </SimpleAsyncButton>
+          )}
+        </Show>
+      }
     />
   );
 }
@@ -151,7 +195,8 @@ export function InstalledModList(props: { game: string }) {
         }
       });
 
-      const installedMods = untrack(() => context.installed.latest);
+      // linked mods are not on Thunderstore
+      const installedMods = untrack(() => context.installed.latest).filter((mod) => mod.dev_source === undefined);
 
       const latestMods = await getFromModIndex(
         props.game,
@@ -185,6 +230,21 @@ export function InstalledModList(props: { game: string }) {
     await refreshUpdates(CHECK_UPDATES_REFETCH);
   }
 
+  onMount(() => {
+    const unlisten = listenDevModSynced((event) => {
+      if (event.profile === context.profileId()) context.refetchInstalled();
+    });
+    onCleanup(() => unlisten.then((unlisten) => unlisten()));
+  });
+
+  const linkDevModBtn = () => (
+    <DevModDialog profileId={context.profileId()} onLinked={context.refetchInstalled}>
+      <DialogTrigger data-btn="ghost">
+        <Fa icon={faFolderPlus} /> {t("modlist.installed.link_dev_mod_btn")}
+      </DialogTrigger>
+    </DevModDialog>
+  );
+
   return (
     <Show
       when={context.installed.latest.length !== 0}
@@ -197,6 +257,7 @@ export function InstalledModList(props: { game: string }) {
             <button data-btn="primary" onClick={() => setSearchParams({ "profile-tab": "mod-search" })}>
               {t("modlist.installed.browse_btn")}
             </button>
+            {linkDevModBtn()}
           </div>
         </div>
       }
@@ -209,25 +270,28 @@ export function InstalledModList(props: { game: string }) {
         mods={getFetcher()}
         multiselect={true}
         trailingControls={
-          <Show
-            when={updates().length > 0}
-            fallback={
-              <SimpleAsyncButton
-                btnStyle="ghost"
-                busy={updates.loading}
-                progress={checkUpdatesProgress}
-                onClick={checkUpdates}
-              >
-                <Fa icon={faRefresh} /> {t("modlist.installed.check_updates_btn")}
-              </SimpleAsyncButton>
-            }
-          >
-            <ModUpdateDialogue updates={updates()}>
-              <DialogTrigger data-btn="primary">
-                <Fa icon={faCircleUp} /> {t("modlist.installed.updates_available_btn")}
-              </DialogTrigger>
-            </ModUpdateDialogue>
-          </Show>
+          <>
+            {linkDevModBtn()}
+            <Show
+              when={updates().length > 0}
+              fallback={
+                <SimpleAsyncButton
+                  btnStyle="ghost"
+                  busy={updates.loading}
+                  progress={checkUpdatesProgress}
+                  onClick={checkUpdates}
+                >
+                  <Fa icon={faRefresh} /> {t("modlist.installed.check_updates_btn")}
+                </SimpleAsyncButton>
+              }
+            >
+              <ModUpdateDialogue updates={updates()}>
+                <DialogTrigger data-btn="primary">
+                  <Fa icon={faCircleUp} /> {t("modlist.installed.updates_available_btn")}
+                </DialogTrigger>
+              </ModUpdateDialogue>
+            </Show>
+          </>
         }
       />
     </Show>
@@ -8,7 +8,7 @@ import { t } from "../../../i18n/i18n";
 import { Mod, ModListing, ModPackage } from "../../../types";
 import { humanizeFileSize, roundedNumberFormatter } from "../../../utils/utils";
 import { ModInstallContext, SelectableModListProps } from "./ModList";
-import { getIconUrl, getQualifiedModName, useInstalled } from "./common";
+import { createModIcon, useInstalled } from "./common";
 
 import ErrorBoundary from "../../../components/ErrorBoundary";
 import Checkbox from "../../../widgets/Checkbox";
@@ -37,6 +37,12 @@ export default function ModListItem(
     return props.mod.versions[0];
   });
 
+  const icon = createModIcon(() => ({
+    owner: props.mod.owner,
+    name: props.mod.name,
+    version: displayVersion().version_number,
+  }));
+
   const installContext = useContext(ModInstallContext);
   const installed = useInstalled(installContext, () => props.mod);
 
@@ -95,7 +101,7 @@ export default function ModListItem(
             class={styles.modIcon}
             width={64}
             alt="mod icon"
-            src={getIconUrl(getQualifiedModName(props.mod.owner, props.mod.name, displayVersion().version_number))}
+            src={icon()}
           />
           <div class={styles.mod__content}>
             <div class={styles.left}>
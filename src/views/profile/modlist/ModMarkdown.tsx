@@ -1,10 +1,12 @@
-import { Show, createResource } from "solid-js";
+import { Show, createResource, useContext } from "solid-js";
+import { getProfileModDocs } from "../../../api/api";
 import { Endpoint, fetchModMarkdown } from "../../../api/mod_index/thunderstore";
 import { Mod } from "../../../types";
 import Markdown from "../../../widgets/Markdown";
 import { createProgressProxyStore } from "../../../api/tasks";
 import { SimpleProgressIndicator } from "../../../widgets/Progress";
 import { t } from "../../../i18n/i18n";
+import { ModInstallContext } from "./ModList";
 
 export default function ModMarkdown(props: {
   mod: Mod | undefined;
@@ -23,11 +25,22 @@ export default function ModMarkdown(props: {
 
   const [progress, setProgress] = createProgressProxyStore();
 
+  const installContext = useContext(ModInstallContext);
+
   const [resource] = createResource(
     modData,
     async ({ mod, version }: { mod?: never; version?: never } | { mod: Mod; version: string }) => {
       if (mod == null) return undefined;
 
+      // the installed version may have its documentation stored for offline use
+      const installed = installContext?.installed.latest.find(
+        (pkg) => pkg.owner === mod.owner && pkg.name === mod.name,
+      );
+      if (installContext !== undefined && installed?.version.version_number === version) {
+        const markdown = await getProfileModDocs(installContext.profileId(), mod.owner, mod.name, props.endpoint);
+        if (markdown !== null) return { markdown };
+      }
+
       return await fetchModMarkdown(mod.owner, mod.name, version, props.endpoint, (event) => {
         if (event.event === "created") {
           setProgress!(event.progress);
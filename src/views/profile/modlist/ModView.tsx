@@ -7,14 +7,20 @@ import { fetchModIndex, getFromModIndex } from "../../../api/api";
 import { createProgressProxyStore, initProgress } from "../../../api/tasks";
 import { t } from "../../../i18n/i18n";
 import { Mod, ModListing, ModPackage, ModVersion } from "../../../types";
-import { dateFormatterMed, humanizeFileSize, removeProperty, roundedNumberFormatter } from "../../../utils/utils";
+import {
+  dateFormatterMed,
+  humanizeFileSize,
+  isWebUrl,
+  removeProperty,
+  roundedNumberFormatter,
+} from "../../../utils/utils";
 import { getIconUrl, getModAuthorUrl, getModUrl, getModVersionUrl, useInstalled } from "./common";
 import { bindValue } from "../../../components/Directives";
 
 import SelectDropdown, { SelectDropdownList } from "../../../widgets/SelectDropdown";
 import TabRenderer, { Tab, TabContent } from "../../../widgets/TabRenderer";
 import TogglableDropdown from "../../../widgets/TogglableDropdown";
-import { InstallButton, UninstallButton } from "./InstallationBtns";
+import { InstallButton, RunToolButton, UninstallButton } from "./InstallationBtns";
 import { ModInstallContext } from "./ModList";
 import ModMarkdown from "./ModMarkdown";
 
@@ -94,21 +100,26 @@ export default function ModView(props: { mod: Mod; gameId: string; closeModView:
 
   const installed = useInstalled(installContext, () => props.mod);
 
+  // mods linked from a folder aren't on Thunderstore
+  const packageUrl = () =>
+    "version" in props.mod
+      ? (props.mod.package_url ??
+        (props.mod.dev_source == null ? getModUrl(props.gameId, props.mod.owner, props.mod.name) : undefined))
+      : getModUrl(props.gameId, props.mod.owner, props.mod.name);
+
   return (
     <div class={styles.modView}>
       <div class={styles.modSticky}>
         <div class={styles.modMeta}>
-          {/* TODO: For local mod with no package URL, remove link */}
           <div style={{ "grid-area": "name" }}>
-            <a
-              href={getModUrl(props.gameId, props.mod.owner, props.mod.name)}
-              target="_blank"
-              rel="noopener noreferrer"
-              class={styles.modMetaLink}
-            >
-              <h2 class={styles.name}>{props.mod.name}</h2>
-              <Fa icon={faExternalLink} />
-            </a>
+            <Show when={packageUrl()} fallback={<h2 class={styles.name}>{props.mod.name}</h2>}>
+              {(url) => (
+                <a href={url()} target="_blank" rel="noopener noreferrer" class={styles.modMetaLink}>
+                  <h2 class={styles.name}>{props.mod.name}</h2>
+                  <Fa icon={faExternalLink} />
+                </a>
+              )}
+            </Show>
           </div>
           <div style={{ "grid-area": "owner" }}>
             <a
@@ -132,7 +143,7 @@ export default function ModView(props: { mod: Mod; gameId: string; closeModView:
             </li>
           </ul>
 
-          <Show when={props.mod.donation_link != null}>
+          <Show when={props.mod.donation_link != null && isWebUrl(props.mod.donation_link)}>
             <a class={styles.modMeta__donate} href={props.mod.donation_link} target="_blank" rel="noopener noreferrer">
               <Fa icon={faHeart} class={styles.donate__icon} />
               <br /> {t("modlist.modview.donate_btn")}
@@ -256,6 +267,13 @@ export default function ModView(props: { mod: Mod; gameId: string; closeModView:
                 )}
               </Show>
             </TogglableDropdown>
+            <For each={installed()?.tool?.executables}>
+              {(executable) => (
+                <RunToolButton mod={installed()!} executable={executable} installContext={installContext!}>
+                  {t("modlist.installed.run_tool_btn", { executable })}
+                </RunToolButton>
+              )}
+            </For>
             <UninstallButton mod={installed()!} installContext={installContext!} class={styles.modView__uninstallBtn}>
               {t("modlist.installed.uninstall_btn")}
             </UninstallButton>
@@ -1,11 +1,43 @@
-import { Accessor, createMemo } from "solid-js";
+import { Accessor, createMemo, createResource, onCleanup } from "solid-js";
 
+import { getCachedModIcon } from "../../../api/api";
 import { Mod, ModPackage } from "../../../types";
 import { ModInstallContext } from "./ModList";
 
 export function getIconUrl(qualifiedModName: string) {
   return `https://gcdn.thunderstore.io/live/repository/icons/${qualifiedModName}.png`;
 }
+/**
+ * @returns the source of the mod's icon, which is the downloaded copy if there is one
+ */
+export function createModIcon(
+  mod: Accessor<{ owner: string; name: string; version: string }>,
+): Accessor<string | undefined> {
+  let objectUrl: string | undefined;
+  const revoke = () => {
+    if (objectUrl !== undefined) URL.revokeObjectURL(objectUrl);
+    objectUrl = undefined;
+  };
+  onCleanup(revoke);
+
+  const [cached] = createResource(mod, async ({ owner, name, version }) => {
+    revoke();
+    try {
+      const bytes = await getCachedModIcon(owner, name, version);
+      if (bytes.byteLength === 0) return null;
+      objectUrl = URL.createObjectURL(new Blob([bytes], { type: "image/png" }));
+      return objectUrl;
+    } catch {
+      return null;
+    }
+  });
+  return () => {
+    if (cached.loading) return undefined;
+    const { owner, name, version } = mod();
+    return cached() ?? getIconUrl(getQualifiedModName(owner, name, version));
+  };
+}
+
 export function getQualifiedModName(owner: string, name: string, version: string) {
   return `${owner}-${name}-${version}`;
 }
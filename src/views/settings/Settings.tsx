@@ -1,13 +1,28 @@
 import { useNavigate } from "@solidjs/router";
 import styles from "./Settings.module.css";
 import TabRenderer, { Tab, TabContent } from "../../widgets/TabRenderer.tsx";
-import { createUniqueId, For, Match, Switch, useContext, createSignal, createSelector } from "solid-js";
+import { createUniqueId, For, Index, Match, Switch, useContext, createSignal, createSelector } from "solid-js";
 
-import { Settings, SettingsPatch, updateSettings, settings, settingsUI } from "../../api/settings.ts";
+import {
+  LaunchOptionsTemplate,
+  parseLaunchOptionsTemplates,
+  Settings,
+  SettingsPatch,
+  updateSettings,
+  settings,
+  settingsUI,
+} from "../../api/settings.ts";
 import { Fa } from "solid-fa";
-import { faChevronLeft, faClockRotateLeft } from "@fortawesome/free-solid-svg-icons";
+import { faChevronLeft, faClockRotateLeft, faPlus, faTrash } from "@fortawesome/free-solid-svg-icons";
 import { t } from "../../i18n/i18n.ts";
-import { GameSelectSetting, Setting, TextSetting, ToggleSetting } from "../../api/settings/ui.ts";
+import {
+  GameSelectSetting,
+  LaunchOptionsTemplatesSetting,
+  NumberSetting,
+  Setting,
+  TextSetting,
+  ToggleSetting,
+} from "../../api/settings/ui.ts";
 import SelectDropdown from "../../widgets/SelectDropdown.tsx";
 import { games } from "../../globals.ts";
 import { ErrorContext, ReportErrFn } from "../../components/ErrorBoundary.tsx";
@@ -16,7 +31,10 @@ export default function SettingsPage() {
   const idPrefix = createUniqueId();
   const navigate = useNavigate();
 
-  const tabs: Tab<string>[] = settingsUI().sections.map((section) => ({
+  // the developer settings inject faults, so they are kept out of reach in release builds
+  const sections = settingsUI().sections.filter((section) => section.id !== "developer" || import.meta.env.DEV);
+
+  const tabs: Tab<string>[] = sections.map((section) => ({
     id: section.id,
     name: t(`settings.section.${section.id}`),
     component: () => (
@@ -34,9 +52,15 @@ export default function SettingsPage() {
                 <Match when={setting.input === "text"}>
                   <TextInput idPrefix={idPrefix} setting={setting as TextSetting} />
                 </Match>
+                <Match when={setting.input === "number"}>
+                  <NumberInput idPrefix={idPrefix} setting={setting as NumberSetting} />
+                </Match>
                 <Match when={setting.input === "game_select"}>
                   <GameSelectInput idPrefix={idPrefix} setting={setting as GameSelectSetting} />
                 </Match>
+                <Match when={setting.input === "launch_options_templates"}>
+                  <LaunchOptionsTemplatesInput idPrefix={idPrefix} setting={setting as LaunchOptionsTemplatesSetting} />
+                </Match>
               </Switch>
               <button
                 type="button"
@@ -150,6 +174,20 @@ function TextInput(props: { idPrefix: string; setting: TextSetting }) {
   );
 }
 
+function NumberInput(props: { idPrefix: string; setting: NumberSetting }) {
+  const reportErr = useContext(ErrorContext);
+  return (
+    <input
+      type="number"
+      id={`${props.idPrefix}_${props.setting.key}`}
+      min="1"
+      value={get(props.setting)}
+      // @ts-ignore: typescript chokes on the type of `e.valueAsNumber`
+      on:change={onChange(reportErr, props.setting, (e) => e.valueAsNumber)}
+    />
+  );
+}
+
 function GameSelectInput(props: { idPrefix: string; setting: GameSelectSetting }) {
   const reportErr = useContext(ErrorContext);
   function onChanged(value: string, selected: boolean) {
@@ -173,3 +211,98 @@ function GameSelectInput(props: { idPrefix: string; setting: GameSelectSetting }
     />
   );
 }
+
+function LaunchOptionsTemplatesInput(props: { idPrefix: string; setting: LaunchOptionsTemplatesSetting }) {
+  const reportErr = useContext(ErrorContext)!;
+
+  async function update(templates: LaunchOptionsTemplate[]) {
+    try {
+      await overrideSetting(props.setting, templates);
+    } catch (e) {
+      reportErr(e);
+    }
+  }
+
+  function updateTemplate(i: number, change: Partial<LaunchOptionsTemplate>) {
+    update(get(props.setting).map((template, j) => (i === j ? { ...template, ...change } : template)));
+  }
+
+  async function exportTemplates() {
+    try {
+      await navigator.clipboard.writeText(JSON.stringify(get(props.setting), null, 2));
+    } catch (e) {
+      reportErr(e);
+    }
+  }
+
+  async function importTemplates() {
+    try {
+      const imported = await parseLaunchOptionsTemplates(await navigator.clipboard.readText());
+      // templates with the same name as an existing one replace it
+      const names = new Set(imported.map((template) => template.name));
+      await update([...get(props.setting).filter((template) => !names.has(template.name)), ...imported]);
+    } catch (e) {
+      reportErr(e);
+    }
+  }
+
+  return (
+    <div class={styles.templates} id={`${props.idPrefix}_${props.setting.key}`}>
+      <Index each={get(props.setting)}>
+        {(template, i) => (
+          <div class={styles.templates__template}>
+            <input
+              type="text"
+              value={template().name}
+              placeholder={t("settings.launch_options_templates.name_placeholder")}
+              on:change={(e) => updateTemplate(i, { name: e.target.value })}
+            />
+            <input
+              type="text"
+              value={template().options}
+              placeholder={t("settings.launch_options_templates.options_placeholder")}
+              on:change={(e) => updateTemplate(i, { options: e.target.value })}
+            />
+            <SelectDropdown
+              label={{ labelText: "preset", preset: t("settings.launch_options_templates.games_label") }}
+              multiselect
+              options={games()
+                .map((game) => ({
+                  label: game.name,
+                  value: game.id,
+                  selected: () => template().games.includes(game.id),
+                }))
+                .sort((a, b) => a.label.localeCompare(b.label))}
+              onChanged={(game, selected) =>
+                updateTemplate(i, {
+                  games: selected ? [...template().games, game] : template().games.filter((id) => id !== game),
+                })
+              }
+            />
+            <button
+              type="button"
+              title={t("settings.launch_options_templates.remove_btn")}
+              on:click={() => update(get(props.setting).filter((_, j) => i !== j))}
+            >
+              <Fa icon={faTrash} />
+            </button>
+          </div>
+        )}
+      </Index>
+      <div class={styles.templates__actions}>
+        <button
+          type="button"
+          on:click={() => update([...get(props.setting), { name: "", options: "%command%", games: [] }])}
+        >
+          <Fa icon={faPlus} /> {t("settings.launch_options_templates.add_btn")}
+        </button>
+        <button type="button" on:click={importTemplates}>
+          {t("settings.launch_options_templates.import_btn")}
+        </button>
+        <button type="button" on:click={exportTemplates}>
+          {t("settings.launch_options_templates.export_btn")}
+        </button>
+      </div>
+    </div>
+  );
+}